@@ -0,0 +1,120 @@
+//! Benchmarks for the apply pipeline
+//!
+//! These benchmarks measure the per-entry cost of apply when the destination is
+//! already in sync, which is dominated by destination `stat`/read syscalls:
+//! - Up-to-date apply (compare every entry, write nothing)
+//! - Up-to-date apply with explicit modes (adds the permission check)
+
+use criterion::{BenchmarkId, Criterion, black_box, criterion_group, criterion_main};
+use guisu::cmd::apply::ApplyCommand;
+use guisu::command::Command;
+use guisu::common::RuntimeContext;
+use guisu_config::Config;
+use std::path::Path;
+use tempfile::TempDir;
+
+/// A source repository with N files, already applied to its destination
+struct AppliedRepo {
+    _temp: TempDir,
+    context: RuntimeContext,
+}
+
+/// Create a repository with N files spread over nested directories and apply it once
+fn create_applied_repo(num_files: usize, with_modes: bool) -> AppliedRepo {
+    let temp = TempDir::new().expect("Failed to create temp directory");
+    let root = std::fs::canonicalize(temp.path()).expect("Failed to canonicalize temp directory");
+    let source_dir = root.join("source");
+    let dest_dir = root.join("dest");
+    let home = source_dir.join("home");
+    std::fs::create_dir_all(&dest_dir).expect("Failed to create destination directory");
+
+    for i in 0..num_files {
+        let dir = home.join(format!(".config/app_{}", i % 10));
+        std::fs::create_dir_all(&dir).expect("Failed to create source directory");
+
+        let path = dir.join(format!("file_{i}.conf"));
+        std::fs::write(&path, format!("Content for file {i}\n"))
+            .unwrap_or_else(|_| panic!("Failed to write file: {}", path.display()));
+        if with_modes {
+            set_mode(&path, if i % 2 == 0 { 0o644 } else { 0o600 });
+        }
+    }
+
+    let context = RuntimeContext::new_with_db_path(
+        Config::default(),
+        &source_dir,
+        &dest_dir,
+        &root.join("state.db"),
+    )
+    .expect("Failed to create runtime context");
+    apply(&context);
+
+    AppliedRepo {
+        _temp: temp,
+        context,
+    }
+}
+
+#[cfg(unix)]
+fn set_mode(path: &Path, mode: u32) {
+    use std::os::unix::fs::PermissionsExt;
+    std::fs::set_permissions(path, std::fs::Permissions::from_mode(mode))
+        .expect("Failed to set permissions");
+}
+
+#[cfg(not(unix))]
+fn set_mode(_path: &Path, _mode: u32) {}
+
+fn apply(context: &RuntimeContext) -> usize {
+    let command = ApplyCommand {
+        files: Vec::new(),
+        dry_run: false,
+        force: false,
+        merge: false,
+        prune: false,
+        interactive: false,
+        include: Vec::new(),
+        exclude: Vec::new(),
+        jobs: None,
+        backup: false,
+        refresh_externals: false,
+        one_shot: None,
+        source_ref: None,
+        timings: false,
+    };
+    let stats = command.execute(context).expect("Apply failed");
+    stats.files()
+}
+
+/// Benchmark apply over a destination that is already up to date
+fn bench_apply_up_to_date(c: &mut Criterion) {
+    let mut group = c.benchmark_group("apply_up_to_date");
+    group.sample_size(20);
+
+    for size in &[10, 100, 500] {
+        for (label, with_modes) in [("plain", false), ("modes", true)] {
+            let repo = create_applied_repo(*size, with_modes);
+
+            group.bench_with_input(BenchmarkId::new(label, size), &repo, |b, repo| {
+                b.iter(|| {
+                    let written = apply(black_box(&repo.context));
+                    assert_eq!(written, 0, "Destination should already be up to date");
+                    black_box(written)
+                });
+            });
+        }
+    }
+
+    group.finish();
+}
+
+// Allow missing docs for criterion-generated code
+#[allow(missing_docs)]
+#[allow(clippy::wildcard_imports)]
+mod bench_groups {
+    use super::*;
+
+    criterion_group!(benches, bench_apply_up_to_date);
+}
+
+criterion_main!(bench_groups::benches);
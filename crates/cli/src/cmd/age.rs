@@ -1,28 +1,40 @@
 //! Age encryption identity management
 //!
-//! Commands for generating and showing age identities.
+//! Commands for generating, showing, migrating, and rotating age identities.
 
 use anyhow::{Context, Result};
 use guisu_crypto::{
-    Identity, IdentityFile, Recipient, decrypt_inline, encrypt_file_content, encrypt_inline,
-    load_identities,
+    Identity, IdentityFile, PassphraseCache, Recipient, SecretString, decrypt_inline,
+    encrypt_file_content, encrypt_inline, load_identities,
 };
+use guisu_vault::keyring::{Keyring, SERVICE};
 use owo_colors::OwoColorize;
-use std::io::{self, Write};
-use std::path::PathBuf;
+use secrecy::ExposeSecret;
+use std::io::{self, IsTerminal, Write};
+use std::path::{Path, PathBuf};
+use tracing::{debug, warn};
 use walkdir::WalkDir;
 
 use guisu_config::Config;
 
 /// Generate a new age identity
 ///
+/// With `passphrase`, the identity file is encrypted with a passphrase read
+/// from the terminal.
+///
 /// # Errors
 ///
 /// Returns an error if:
 /// - The configuration directory cannot be determined (when no output path specified)
+/// - A passphrase is requested without a terminal
 /// - Parent directory creation fails
 /// - Identity file cannot be saved
-pub fn generate(output: Option<PathBuf>) -> Result<()> {
+pub fn generate(output: Option<PathBuf>, passphrase: bool) -> Result<()> {
+    let passphrase = if passphrase {
+        Some(read_new_passphrase()?)
+    } else {
+        None
+    };
     let identity = Identity::generate();
     let public_key = identity.to_public();
 
@@ -42,10 +54,19 @@ pub fn generate(output: Option<PathBuf>) -> Result<()> {
     }
 
     // Save the identity
-    IdentityFile::save(&output_path, &[identity]).context("Failed to save identity file")?;
+    match &passphrase {
+        Some(passphrase) => {
+            IdentityFile::save_with_passphrase(&output_path, &[identity], passphrase)
+        }
+        None => IdentityFile::save(&output_path, &[identity]),
+    }
+    .context("Failed to save identity file")?;
 
     println!("Generated new age identity");
     println!("Private key saved to: {}", output_path.display());
+    if passphrase.is_some() {
+        println!("The private key is protected by your passphrase.");
+    }
     println!("\nPublic key: {public_key}");
     println!("\nKeep your private key secure!");
     println!("Share your public key with others to allow them to encrypt files for you.");
@@ -53,6 +74,96 @@ pub fn generate(output: Option<PathBuf>) -> Result<()> {
     Ok(())
 }
 
+/// Ask for a new passphrase, with confirmation
+fn read_new_passphrase() -> Result<SecretString> {
+    use dialoguer::{Password, theme::ColorfulTheme};
+
+    if !io::stdin().is_terminal() {
+        anyhow::bail!("--passphrase requires an interactive terminal");
+    }
+    let passphrase = Password::with_theme(&ColorfulTheme::default())
+        .with_prompt("Passphrase for the new identity")
+        .with_confirmation("Confirm passphrase", "Passphrases do not match")
+        .interact()
+        .context("Failed to read passphrase")?;
+    Ok(SecretString::from(passphrase))
+}
+
+/// Ask for the passphrase of an encrypted identity file
+///
+/// Registered with [`guisu_crypto::set_passphrase_prompt`]. Returns `None`
+/// without a terminal, so unattended runs fail instead of blocking.
+#[must_use]
+pub fn prompt_passphrase(path: &str) -> Option<SecretString> {
+    use dialoguer::{Password, theme::ColorfulTheme};
+
+    if !io::stdin().is_terminal() {
+        return None;
+    }
+    Password::with_theme(&ColorfulTheme::default())
+        .with_prompt(format!("Passphrase for {path}"))
+        .interact()
+        .ok()
+        .map(SecretString::from)
+}
+
+/// Identity passphrases remembered in the system keyring
+///
+/// Registered with [`guisu_crypto::set_passphrase_cache`] when
+/// `[age] keyring = true`. Keyring failures are logged and otherwise ignored,
+/// falling back to the passphrase prompt.
+pub struct KeyringPassphraseCache {
+    keyring: Keyring,
+}
+
+impl KeyringPassphraseCache {
+    /// Create a cache backed by the platform keyring
+    #[must_use]
+    pub fn new() -> Self {
+        Self {
+            keyring: Keyring::new(),
+        }
+    }
+
+    /// Keyring user under which the passphrase for `path` is stored
+    fn user(path: &str) -> String {
+        format!("age-identity:{path}")
+    }
+}
+
+impl Default for KeyringPassphraseCache {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl PassphraseCache for KeyringPassphraseCache {
+    fn load(&self, path: &str) -> Option<SecretString> {
+        match self.keyring.get(SERVICE, &Self::user(path)) {
+            Ok(passphrase) => passphrase.map(SecretString::from),
+            Err(e) => {
+                debug!("Keyring lookup for {path} failed: {e}");
+                None
+            }
+        }
+    }
+
+    fn store(&self, path: &str, passphrase: &SecretString) {
+        if let Err(e) = self
+            .keyring
+            .set(SERVICE, &Self::user(path), passphrase.expose_secret())
+        {
+            warn!("Failed to store passphrase for {path} in keyring: {e}");
+        }
+    }
+
+    fn forget(&self, path: &str) {
+        if let Err(e) = self.keyring.delete(SERVICE, &Self::user(path)) {
+            debug!("Failed to remove passphrase for {path} from keyring: {e}");
+        }
+    }
+}
+
 /// Show the public key for the current identity
 ///
 /// # Errors
@@ -295,6 +406,44 @@ fn scan_encrypted_files(source_dir: &std::path::Path) -> (Vec<PathBuf>, Vec<Path
     (encrypted_files, inline_files)
 }
 
+/// Map a source file to its target path relative to the destination directory
+///
+/// Strips the `root_entry` prefix and the `.age`/`.j2` source extensions so the
+/// result can be matched against `[age.scopes]` patterns. Returns `None` for files
+/// outside the root entry (e.g. under `.guisu/`).
+pub(crate) fn target_path_for_source(
+    source_dir: &Path,
+    source_file: &Path,
+    config: &Config,
+) -> Option<PathBuf> {
+    let rel_path = source_file
+        .strip_prefix(source_dir.join(&config.general.root_entry))
+        .ok()?;
+    let file_name = rel_path.file_name()?.to_str()?;
+    let file_name = file_name.strip_suffix(".age").unwrap_or(file_name);
+    let file_name = file_name.strip_suffix(".j2").unwrap_or(file_name);
+    Some(rel_path.with_file_name(file_name))
+}
+
+/// Recipients a migrated file should be re-encrypted to
+///
+/// Returns the scope's recipients (and `true`) for files matching an `[age.scopes]`
+/// pattern, otherwise the recipients derived from the new identities.
+fn migration_recipients(
+    file: &Path,
+    source_dir: &Path,
+    config: &Config,
+    new_recipients: &[Recipient],
+) -> Result<(Vec<Recipient>, bool)> {
+    if let Some(target_path) = target_path_for_source(source_dir, file, config)
+        && config.age_scope_for(&target_path)?.is_some()
+    {
+        return Ok((config.age_recipients_for(&target_path)?, true));
+    }
+
+    Ok((new_recipients.to_vec(), false))
+}
+
 /// Display list of files to be migrated
 fn display_migration_file_list(
     encrypted_files: &[PathBuf],
@@ -321,58 +470,76 @@ fn display_migration_file_list(
     println!();
 }
 
+/// Outcome counts of a migration run
+#[derive(Debug, Default)]
+struct MigrationCounts {
+    migrated: usize,
+    unavailable: usize,
+    errors: usize,
+}
+
 /// Perform the actual migration of files
+///
+/// Files matching an `[age.scopes]` pattern are re-encrypted to the scope's
+/// recipients. Scoped files the old identities cannot decrypt belong to another
+/// machine and are skipped rather than counted as errors.
 fn perform_file_migrations(
     encrypted_files: &[PathBuf],
     inline_files: &[PathBuf],
     source_dir: &std::path::Path,
+    config: &Config,
     old_identities: &[guisu_crypto::Identity],
     new_recipients: &[guisu_crypto::Recipient],
-) -> Result<(usize, usize)> {
+) -> Result<MigrationCounts> {
     println!("{}", "Migrating files...".bold().cyan());
 
-    let mut migrated_count = 0;
-    let mut error_count = 0;
+    let mut counts = MigrationCounts::default();
+
+    let files = encrypted_files
+        .iter()
+        .map(|file| (file, true))
+        .chain(inline_files.iter().map(|file| (file, false)));
 
-    // Migrate .age files
-    for file in encrypted_files {
+    for (file, is_age_file) in files {
         let relative = file.strip_prefix(source_dir).unwrap_or(file);
         print!("  Migrating {} ... ", relative.display());
         io::stdout().flush()?;
 
-        match migrate_encrypted_file(file, old_identities, new_recipients) {
-            Ok(()) => {
-                println!("{}", "✓".green());
-                migrated_count += 1;
-            }
-            Err(e) => {
-                println!("{}", "✗".red());
-                eprintln!("    Error: {e}");
-                error_count += 1;
+        let result = match migration_recipients(file, source_dir, config, new_recipients) {
+            Ok((recipients, scoped)) => if is_age_file {
+                migrate_encrypted_file(file, old_identities, &recipients, config.age.armor)
+            } else {
+                migrate_inline_file(file, old_identities, &recipients)
             }
-        }
-    }
-
-    // Migrate files with inline encryption
-    for file in inline_files {
-        let relative = file.strip_prefix(source_dir).unwrap_or(file);
-        print!("  Migrating {} ... ", relative.display());
-        io::stdout().flush()?;
+            .map_err(|e| (e, scoped)),
+            Err(e) => Err((e, false)),
+        };
 
-        match migrate_inline_file(file, old_identities, new_recipients) {
+        match result {
             Ok(()) => {
                 println!("{}", "✓".green());
-                migrated_count += 1;
+                counts.migrated += 1;
             }
-            Err(e) => {
+            Err((e, true)) if is_decryption_error(&e) => {
+                println!("{}", "not available on this machine".dimmed());
+                counts.unavailable += 1;
+            }
+            Err((e, _)) => {
                 println!("{}", "✗".red());
                 eprintln!("    Error: {e}");
-                error_count += 1;
+                counts.errors += 1;
             }
         }
     }
 
-    Ok((migrated_count, error_count))
+    Ok(counts)
+}
+
+/// Check whether a migration error was caused by the old identities failing to decrypt
+fn is_decryption_error(error: &anyhow::Error) -> bool {
+    error
+        .chain()
+        .any(|cause| cause.to_string().contains("Failed to decrypt"))
 }
 
 /// Migrate encrypted files from old keys to new keys
@@ -397,6 +564,7 @@ pub fn migrate(
     new_identity_paths: &[PathBuf],
     dry_run: bool,
     yes: bool,
+    config: &Config,
 ) -> Result<()> {
     println!("{}", "Age Key Migration".bold().cyan());
     println!();
@@ -438,18 +606,7 @@ pub fn migrate(
 
     // Confirmation prompt
     if !yes {
-        print!(
-            "{} {}",
-            "Proceed with migration?".yellow().bold(),
-            "(yes/no):".dimmed()
-        );
-        io::stdout().flush()?;
-
-        let mut input = String::new();
-        io::stdin().read_line(&mut input)?;
-        let answer = input.trim().to_lowercase();
-
-        if answer != "yes" && answer != "y" {
+        if !confirm("Proceed with migration?")? {
             println!("{}", "Migration cancelled.".yellow());
             return Ok(());
         }
@@ -457,39 +614,62 @@ pub fn migrate(
     }
 
     // Perform migration
-    let (migrated_count, error_count) = perform_file_migrations(
+    let counts = perform_file_migrations(
         &encrypted_files,
         &inline_files,
         source_dir,
+        config,
         &old_identities,
         &new_recipients,
     )?;
 
     // Summary
     println!();
-    if error_count == 0 {
+    if counts.errors == 0 {
         println!(
             "{} Successfully migrated {} files.",
             "✓".green().bold(),
-            migrated_count
+            counts.migrated
         );
     } else {
         println!(
             "{} Migrated {} files with {} errors.",
             "!".yellow().bold(),
-            migrated_count,
-            error_count
+            counts.migrated,
+            counts.errors
+        );
+    }
+    if counts.unavailable > 0 {
+        println!(
+            "{} Skipped {} scoped files not available on this machine.",
+            "ℹ".bright_blue(),
+            counts.unavailable
         );
     }
 
     Ok(())
 }
 
+/// Ask a yes/no question on stdin
+fn confirm(question: &str) -> Result<bool> {
+    print!("{} {}", question.yellow().bold(), "(yes/no):".dimmed());
+    io::stdout().flush()?;
+
+    let mut input = String::new();
+    io::stdin().read_line(&mut input)?;
+    let answer = input.trim().to_lowercase();
+    Ok(answer == "yes" || answer == "y")
+}
+
 /// Migrate a single .age encrypted file
+///
+/// The file is rewritten in ASCII armor format if `armor` is set, binary
+/// otherwise.
 fn migrate_encrypted_file(
     file_path: &std::path::Path,
     old_identities: &[guisu_crypto::Identity],
     new_recipients: &[guisu_crypto::Recipient],
+    armor: bool,
 ) -> Result<()> {
     // Read and decrypt with old key
     let encrypted_content = std::fs::read(file_path)?;
@@ -497,7 +677,7 @@ fn migrate_encrypted_file(
         .context("Failed to decrypt with old identities")?;
 
     // Re-encrypt with new key
-    let re_encrypted = guisu_crypto::encrypt(&decrypted, new_recipients)
+    let re_encrypted = guisu_crypto::encrypt_with_armor(&decrypted, new_recipients, armor)
         .context("Failed to encrypt with new recipients")?;
 
     // Write back
@@ -528,6 +708,830 @@ fn migrate_inline_file(
     Ok(())
 }
 
+/// Convert `.age` files to the format set by `age.armor`
+///
+/// Armor is only an encoding of the encrypted data, so files are converted
+/// without decrypting them. Files already in the configured format are left
+/// alone.
+///
+/// # Errors
+///
+/// Returns an error if a file cannot be read, is not age encrypted, or cannot
+/// be written
+pub fn convert(source_dir: &Path, dry_run: bool, config: &Config) -> Result<()> {
+    let armor = config.age.armor;
+    let format = if armor { "ASCII armor" } else { "binary" };
+
+    let mut pending = Vec::new();
+    for entry in WalkDir::new(source_dir)
+        .into_iter()
+        .filter_entry(|entry| entry.file_name() != ".git")
+        .filter_map(std::result::Result::ok)
+    {
+        let path = entry.path();
+        if !entry.file_type().is_file() || path.extension().and_then(|s| s.to_str()) != Some("age")
+        {
+            continue;
+        }
+        let data = std::fs::read(path)
+            .with_context(|| format!("Failed to read file: {}", path.display()))?;
+        if guisu_crypto::is_armored(&data) != armor {
+            pending.push((path.to_path_buf(), data));
+        }
+    }
+
+    if pending.is_empty() {
+        println!(
+            "{} All encrypted files are already in {format} format.",
+            "✓".green().bold()
+        );
+        return Ok(());
+    }
+
+    for (path, data) in &pending {
+        let relative = path.strip_prefix(source_dir).unwrap_or(path);
+        if dry_run {
+            println!("  {}", relative.display());
+            continue;
+        }
+        let converted = guisu_crypto::convert_armor(data, armor)
+            .with_context(|| format!("Failed to convert {}", relative.display()))?;
+        std::fs::write(path, converted)
+            .with_context(|| format!("Failed to write file: {}", path.display()))?;
+        debug!(file = %relative.display(), format, "Converted encrypted file");
+    }
+
+    if dry_run {
+        println!(
+            "{}",
+            format!(
+                "Dry run - {} files would be converted to {format}.",
+                pending.len()
+            )
+            .yellow()
+            .bold()
+        );
+    } else {
+        println!(
+            "{} Converted {} files to {format}.",
+            "✓".green().bold(),
+            pending.len()
+        );
+    }
+    Ok(())
+}
+
+/// Options for [`rotate`]
+#[derive(Debug, Clone, Default)]
+#[allow(clippy::struct_excessive_bools)]
+pub struct RotateOptions {
+    /// Identity file to rotate, required when several are configured
+    pub identity: Option<PathBuf>,
+    /// Protect the new identity file with a passphrase
+    pub passphrase: bool,
+    /// Commit the re-encrypted files and config files afterwards
+    pub commit: bool,
+    /// Only show what would change
+    pub dry_run: bool,
+    /// Skip the confirmation prompt
+    pub yes: bool,
+}
+
+/// Replace an age identity with a newly generated one
+///
+/// Every encrypted file and inline value is re-encrypted from the configured
+/// identities to the new one, the old public key is replaced in the config
+/// files (`.guisu.toml`, its `.j2` template, and the other formats), and the
+/// new key is saved at the identity's path. The old key file is kept next to
+/// it as `<name>.guisu-backup`. With `commit`, the changed source files are
+/// committed to the source repository.
+///
+/// # Errors
+///
+/// Returns an error if:
+/// - No identity, or an SSH identity, is selected for rotation
+/// - The old public key is a configured recipient but none of the config
+///   files contain it (so they cannot be updated)
+/// - `commit` is set and the source directory is not a git repository
+/// - Loading identities, saving the new key, or writing files fails
+pub fn rotate(source_dir: &Path, options: &RotateOptions, config: &Config) -> Result<()> {
+    println!("{}", "Age Identity Rotation".bold().cyan());
+    println!();
+
+    let identity_path = rotated_identity_path(config, options.identity.as_deref())?;
+    let working_tree = if options.commit {
+        Some(
+            guisu_engine::git::find_working_tree(source_dir).ok_or_else(|| {
+                anyhow::anyhow!("--commit needs the source directory to be a git repository")
+            })?,
+        )
+    } else {
+        None
+    };
+
+    let (old_identities, old_public_keys, kept_recipients) =
+        load_rotation_identities(config, &identity_path)?;
+
+    let new_identity = Identity::generate();
+    let new_public_key = new_identity.to_public();
+    let new_public = new_public_key.to_string();
+
+    let rotated_config = replace_config_recipients(config, &old_public_keys, &new_public);
+    let config_updates = config_file_updates(source_dir, &old_public_keys, &new_public)?;
+    let recipients_changed = rotated_config.age.recipient != config.age.recipient
+        || rotated_config.age.recipients != config.age.recipients
+        || rotated_config.age.scopes != config.age.scopes;
+    if recipients_changed && config_updates.is_empty() {
+        anyhow::bail!(
+            "The public key of {} is a configured recipient, but no config file contains it.\n\
+             Generate a new key with `guisu age generate`, update the recipients, and run \
+             `guisu age migrate` instead.",
+            identity_path.display()
+        );
+    }
+
+    // Entries outside any scope go to the configured recipients, or with
+    // `derive` to the identities' own public keys
+    let mut new_recipients = rotated_config.age_recipients()?;
+    if new_recipients.is_empty() {
+        new_recipients = kept_recipients;
+        new_recipients.push(new_public_key);
+    }
+
+    let (encrypted_files, inline_files) = scan_encrypted_files(source_dir);
+
+    println!();
+    println!("{}", "Rotation Summary:".bold());
+    println!("  Identity:                {}", identity_path.display());
+    println!("  New public key:          {new_public}");
+    println!("  Encrypted files (.age):  {}", encrypted_files.len());
+    println!("  Files with inline encryption: {}", inline_files.len());
+    for (path, _) in &config_updates {
+        let relative = path.strip_prefix(source_dir).unwrap_or(path);
+        println!("  Config file updated:     {}", relative.display());
+    }
+    println!();
+
+    if options.dry_run || !options.yes {
+        display_migration_file_list(&encrypted_files, &inline_files, source_dir);
+    }
+
+    if options.dry_run {
+        println!("{}", "Dry run - no files were modified.".yellow().bold());
+        return Ok(());
+    }
+
+    if !options.yes {
+        if !confirm("Proceed with rotation?")? {
+            println!("{}", "Rotation cancelled.".yellow());
+            return Ok(());
+        }
+        println!();
+    }
+
+    let passphrase = if options.passphrase {
+        Some(read_new_passphrase()?)
+    } else {
+        None
+    };
+    let backup = replace_identity_file(&identity_path, &new_identity, passphrase.as_ref())?;
+
+    let counts = perform_file_migrations(
+        &encrypted_files,
+        &inline_files,
+        source_dir,
+        &rotated_config,
+        &old_identities,
+        &new_recipients,
+    )?;
+
+    for (path, content) in &config_updates {
+        std::fs::write(path, content)
+            .with_context(|| format!("Failed to write config file: {}", path.display()))?;
+    }
+
+    println!();
+    println!(
+        "{} New identity saved to {}",
+        "✓".green().bold(),
+        identity_path.display()
+    );
+    println!("  Old identity kept at {}", backup.display());
+    if !report_rotation(&counts, options.commit) {
+        return Ok(());
+    }
+
+    if let Some(working_tree) = working_tree {
+        let changed: Vec<PathBuf> = encrypted_files
+            .iter()
+            .chain(&inline_files)
+            .chain(config_updates.iter().map(|(path, _)| path))
+            .cloned()
+            .collect();
+        let commit_id = commit_rotation(&working_tree, &changed, &new_public)?;
+        println!("{} Committed {commit_id}", "✓".green().bold());
+    }
+
+    Ok(())
+}
+
+/// Load every configured identity for decryption
+///
+/// Returns all identities, the public keys of those in `identity_path` (which
+/// are replaced), and the public keys of the others (which are kept).
+fn load_rotation_identities(
+    config: &Config,
+    identity_path: &Path,
+) -> Result<(Vec<Identity>, Vec<String>, Vec<Recipient>)> {
+    let mut old_identities = Vec::new();
+    let mut old_public_keys = Vec::new();
+    let mut kept_recipients = Vec::new();
+    for path in configured_identity_paths(config) {
+        let ids = load_identities(&path, Config::is_ssh_identity(&path))
+            .with_context(|| format!("Failed to load identity: {}", path.display()))?;
+        for identity in &ids {
+            if path == identity_path {
+                old_public_keys.push(identity.to_public().to_string());
+            } else {
+                kept_recipients.push(identity.to_public());
+            }
+        }
+        old_identities.extend(ids);
+    }
+    Ok((old_identities, old_public_keys, kept_recipients))
+}
+
+/// Print the outcome of re-encrypting files, returning whether all succeeded
+fn report_rotation(counts: &MigrationCounts, commit: bool) -> bool {
+    if counts.errors > 0 {
+        println!(
+            "{} Re-encrypted {} files with {} errors; keep the old identity until they are fixed.",
+            "!".yellow().bold(),
+            counts.migrated,
+            counts.errors
+        );
+        if commit {
+            println!("{}", "Nothing was committed.".yellow());
+        }
+        return false;
+    }
+    println!(
+        "{} Re-encrypted {} files.",
+        "✓".green().bold(),
+        counts.migrated
+    );
+    if counts.unavailable > 0 {
+        println!(
+            "{} Skipped {} scoped files not available on this machine.",
+            "ℹ".bright_blue(),
+            counts.unavailable
+        );
+    }
+    true
+}
+
+/// Identity file paths from `[age] identity` and `identities`, in that order
+fn configured_identity_paths(config: &Config) -> Vec<PathBuf> {
+    config
+        .age
+        .identity
+        .iter()
+        .chain(config.age.identities.iter().flatten())
+        .cloned()
+        .collect()
+}
+
+/// The identity file to rotate: `requested`, or the only configured one
+fn rotated_identity_path(config: &Config, requested: Option<&Path>) -> Result<PathBuf> {
+    let paths = configured_identity_paths(config);
+    let path = match requested {
+        Some(requested) => paths
+            .into_iter()
+            .find(|path| path == requested)
+            .ok_or_else(|| {
+                anyhow::anyhow!("{} is not a configured identity", requested.display())
+            })?,
+        None => match paths.as_slice() {
+            [] => {
+                anyhow::bail!("No identity file configured. Generate one with: guisu age generate")
+            }
+            [path] => path.clone(),
+            _ => anyhow::bail!(
+                "Several identities are configured. Choose one with --identity <PATH>"
+            ),
+        },
+    };
+
+    if Config::is_ssh_identity(&path) {
+        anyhow::bail!(
+            "SSH identities cannot be rotated. Generate an age identity with \
+             `guisu age generate` and re-encrypt with `guisu age migrate`."
+        );
+    }
+    Ok(path)
+}
+
+/// `config` with `old_public_keys` replaced by `new_public` in the recipients and scopes
+fn replace_config_recipients(
+    config: &Config,
+    old_public_keys: &[String],
+    new_public: &str,
+) -> Config {
+    let replace = |recipient: &mut String| {
+        if old_public_keys.contains(recipient) {
+            *recipient = new_public.to_string();
+        }
+    };
+
+    let mut config = config.clone();
+    if let Some(recipient) = &mut config.age.recipient {
+        replace(recipient);
+    }
+    config.age.recipients.iter_mut().for_each(replace);
+    config.age.scopes.values_mut().flatten().for_each(replace);
+    config
+}
+
+/// Config files in `source_dir` that mention an old public key, with their new content
+///
+/// Plain config files and their `.j2` templates are both updated, so a templated
+/// config keeps its template expressions.
+fn config_file_updates(
+    source_dir: &Path,
+    old_public_keys: &[String],
+    new_public: &str,
+) -> Result<Vec<(PathBuf, String)>> {
+    let mut updates = Vec::new();
+    for (name, _) in guisu_config::ConfigFormat::FILE_NAMES {
+        for path in [source_dir.join(name), source_dir.join(format!("{name}.j2"))] {
+            if !path.is_file() {
+                continue;
+            }
+            let content = std::fs::read_to_string(&path)
+                .with_context(|| format!("Failed to read config file: {}", path.display()))?;
+            let rewritten = old_public_keys
+                .iter()
+                .fold(content.clone(), |text, old| text.replace(old, new_public));
+            if rewritten != content {
+                updates.push((path, rewritten));
+            }
+        }
+    }
+    Ok(updates)
+}
+
+/// Move the old identity file aside and save `identity` in its place
+///
+/// Returns the path the old file was moved to. If saving fails, the old file
+/// is moved back.
+fn replace_identity_file(
+    path: &Path,
+    identity: &Identity,
+    passphrase: Option<&SecretString>,
+) -> Result<PathBuf> {
+    let file_name = path
+        .file_name()
+        .with_context(|| format!("Invalid identity path: {}", path.display()))?
+        .to_string_lossy();
+    let mut backup = path.with_file_name(format!("{file_name}.guisu-backup"));
+    let mut n = 0;
+    while std::fs::symlink_metadata(&backup).is_ok() {
+        n += 1;
+        backup = path.with_file_name(format!("{file_name}.guisu-backup.{n}"));
+    }
+
+    std::fs::rename(path, &backup).with_context(|| {
+        format!(
+            "Failed to back up {} to {}",
+            path.display(),
+            backup.display()
+        )
+    })?;
+
+    let identities = std::slice::from_ref(identity);
+    let saved = match passphrase {
+        Some(passphrase) => IdentityFile::save_with_passphrase(path, identities, passphrase),
+        None => IdentityFile::save(path, identities),
+    };
+    if let Err(e) = saved {
+        let _ = std::fs::rename(&backup, path);
+        return Err(e).context("Failed to save the new identity file");
+    }
+
+    Ok(backup)
+}
+
+/// Stage `paths` and commit them, returning the commit ID
+fn commit_rotation(working_tree: &Path, paths: &[PathBuf], new_public: &str) -> Result<String> {
+    use guisu_engine::git::{Git2Provider, GitProvider};
+
+    let pathspecs: Vec<PathBuf> = paths
+        .iter()
+        .filter_map(|path| crate::utils::autocommit::relative_to(path, working_tree))
+        .collect();
+
+    let provider = Git2Provider::new();
+    provider
+        .stage(working_tree, &pathspecs)
+        .context("Failed to stage re-encrypted files")?;
+    provider
+        .commit(
+            working_tree,
+            &format!("Rotate age identity to {new_public}"),
+        )
+        .context("Failed to commit re-encrypted files")
+}
+
+/// Whether `age recipients` adds or removes recipients
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RecipientChange {
+    /// Add recipients
+    Add,
+    /// Remove recipients
+    Remove,
+}
+
+/// List the configured recipients, global and per scope
+///
+/// Public keys of this machine's identities are marked.
+///
+/// # Errors
+///
+/// Returns an error if a scope pattern is invalid
+pub fn list_recipients(config: &Config) -> Result<()> {
+    let own_keys: Vec<String> = config
+        .age_identities()
+        .unwrap_or_default()
+        .iter()
+        .map(|identity| identity.to_public().to_string())
+        .collect();
+    let print_keys = |label: &str, keys: &[String]| {
+        for (i, key) in keys.iter().enumerate() {
+            let label = if i == 0 { label } else { "" };
+            // SSH keys may carry a comment that the parsed form drops
+            let is_own = key
+                .parse::<Recipient>()
+                .is_ok_and(|recipient| own_keys.contains(&recipient.to_string()));
+            if is_own {
+                println!(
+                    "  {} {label:14} {} {}",
+                    "✓".bright_green(),
+                    key.bright_white(),
+                    "(this machine)".dimmed()
+                );
+            } else {
+                print_item(label, key, true);
+            }
+        }
+    };
+
+    println!("{}", "Age Recipients".bright_white().bold());
+    println!();
+
+    let global: Vec<String> = config
+        .age
+        .recipient
+        .iter()
+        .chain(&config.age.recipients)
+        .cloned()
+        .collect();
+    if !global.is_empty() {
+        print_keys("Recipients", &global);
+    } else if config.age.derive {
+        print_keys("Derived", &own_keys);
+    } else {
+        print_item("Recipients", "None configured", false);
+    }
+
+    for (pattern, keys) in &config.age.scopes {
+        println!();
+        print_keys(pattern, keys);
+    }
+
+    Ok(())
+}
+
+/// Add or remove recipients and re-encrypt the files they apply to
+///
+/// With `scope`, the recipients of that `[age.scopes]` pattern change and only
+/// files under it are re-encrypted; otherwise the `[age] recipients` change and
+/// all unscoped files are re-encrypted. The config file is edited in place
+/// (TOML keeps its formatting and comments; a `.j2` template is edited as long
+/// as its `[age]` section is plain TOML).
+///
+/// # Errors
+///
+/// Returns an error if:
+/// - A recipient cannot be parsed, or a removed recipient is not configured
+/// - The change would leave no recipients
+/// - The config file cannot be found, parsed, or written
+/// - Loading identities or re-encrypting files fails
+pub fn edit_recipients(
+    source_dir: &Path,
+    change: RecipientChange,
+    recipients: &[String],
+    scope: Option<&str>,
+    dry_run: bool,
+    yes: bool,
+    config: &Config,
+) -> Result<()> {
+    for recipient in recipients {
+        recipient
+            .parse::<Recipient>()
+            .map_err(|e| anyhow::anyhow!("Invalid recipient '{recipient}': {e}"))?;
+    }
+
+    let mut updated = config.clone();
+    change_recipients(&mut updated.age, change, recipients, scope)?;
+
+    let (config_path, format, _) = guisu_config::ConfigFormat::find(source_dir)
+        .ok_or_else(|| anyhow::anyhow!("No config file found in {}", source_dir.display()))?;
+    let content = std::fs::read_to_string(&config_path)
+        .with_context(|| format!("Failed to read config file: {}", config_path.display()))?;
+    let edited =
+        edit_config_file(&content, format, change, recipients, scope).with_context(|| {
+            format!(
+                "Cannot edit {}; change the recipients by hand",
+                config_path.display()
+            )
+        })?;
+
+    let identities = config.age_identities()?;
+    let mut new_recipients = updated.age_recipients()?;
+    if new_recipients.is_empty() {
+        new_recipients = identities.iter().map(Identity::to_public).collect();
+    }
+
+    // Only files whose scope changed need new recipients
+    let (encrypted_files, inline_files) = scan_encrypted_files(source_dir);
+    let affected = |file: &PathBuf| {
+        let file_scope = target_path_for_source(source_dir, file, config)
+            .and_then(|target| config.age_scope_for(&target).ok().flatten());
+        file_scope == scope
+    };
+    let encrypted_files: Vec<PathBuf> = encrypted_files.into_iter().filter(affected).collect();
+    let inline_files: Vec<PathBuf> = inline_files.into_iter().filter(affected).collect();
+
+    let verb = match change {
+        RecipientChange::Add => "Adding",
+        RecipientChange::Remove => "Removing",
+    };
+    println!();
+    match scope {
+        Some(scope) => println!(
+            "{} {verb} recipients of scope {scope}:",
+            "Recipients".bold()
+        ),
+        None => println!("{} {verb} recipients:", "Recipients".bold()),
+    }
+    for recipient in recipients {
+        println!("  • {recipient}");
+    }
+    let relative = config_path.strip_prefix(source_dir).unwrap_or(&config_path);
+    println!("  Config file:             {}", relative.display());
+    println!(
+        "  Files to re-encrypt:     {}",
+        encrypted_files.len() + inline_files.len()
+    );
+    println!();
+
+    if dry_run || !yes {
+        display_migration_file_list(&encrypted_files, &inline_files, source_dir);
+    }
+
+    if dry_run {
+        println!("{}", "Dry run - no files were modified.".yellow().bold());
+        return Ok(());
+    }
+
+    if !yes {
+        if !confirm("Proceed?")? {
+            println!("{}", "Cancelled.".yellow());
+            return Ok(());
+        }
+        println!();
+    }
+
+    std::fs::write(&config_path, edited)
+        .with_context(|| format!("Failed to write config file: {}", config_path.display()))?;
+
+    let counts = perform_file_migrations(
+        &encrypted_files,
+        &inline_files,
+        source_dir,
+        &updated,
+        &identities,
+        &new_recipients,
+    )?;
+
+    println!();
+    println!("{} Updated {}", "✓".green().bold(), relative.display());
+    if report_rotation(&counts, false) && change == RecipientChange::Remove {
+        println!(
+            "{}",
+            "Removed recipients can still decrypt older versions of these files from the repository history."
+                .dimmed()
+        );
+    }
+
+    Ok(())
+}
+
+/// Apply a recipient change to the `[age]` settings
+fn change_recipients(
+    age: &mut guisu_config::AgeConfig,
+    change: RecipientChange,
+    recipients: &[String],
+    scope: Option<&str>,
+) -> Result<()> {
+    if let Some(scope) = scope {
+        let keys = age.scopes.entry(scope.to_string()).or_default();
+        match change {
+            RecipientChange::Add => {
+                for recipient in recipients {
+                    if !keys.contains(recipient) {
+                        keys.push(recipient.clone());
+                    }
+                }
+            }
+            RecipientChange::Remove => {
+                for recipient in recipients {
+                    let Some(index) = keys.iter().position(|key| key == recipient) else {
+                        anyhow::bail!("{recipient} is not a recipient of scope {scope}");
+                    };
+                    keys.remove(index);
+                }
+                if keys.is_empty() {
+                    anyhow::bail!(
+                        "Scope {scope} would have no recipients left; remove the scope from the config instead"
+                    );
+                }
+            }
+        }
+        return Ok(());
+    }
+
+    match change {
+        RecipientChange::Add => {
+            for recipient in recipients {
+                if age.recipient.as_ref() != Some(recipient) && !age.recipients.contains(recipient)
+                {
+                    age.recipients.push(recipient.clone());
+                }
+            }
+        }
+        RecipientChange::Remove => {
+            for recipient in recipients {
+                if age.recipient.as_ref() == Some(recipient) {
+                    age.recipient = None;
+                } else if let Some(index) = age.recipients.iter().position(|key| key == recipient) {
+                    age.recipients.remove(index);
+                } else {
+                    anyhow::bail!("{recipient} is not a configured recipient");
+                }
+            }
+            if age.recipient.is_none() && age.recipients.is_empty() && !age.derive {
+                anyhow::bail!(
+                    "No recipients would be left. Add another recipient first, or set `derive = true` under [age]"
+                );
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Config file content with the recipient change applied
+fn edit_config_file(
+    content: &str,
+    format: guisu_config::ConfigFormat,
+    change: RecipientChange,
+    recipients: &[String],
+    scope: Option<&str>,
+) -> Result<String> {
+    use guisu_config::ConfigFormat;
+
+    match format {
+        ConfigFormat::Toml => {
+            let mut doc: toml_edit::DocumentMut = content.parse().context("Invalid TOML")?;
+            edit_toml_recipients(&mut doc, change, recipients, scope)?;
+            Ok(doc.to_string())
+        }
+        ConfigFormat::Json => {
+            let mut value: serde_json::Value =
+                serde_json::from_str(content).context("Invalid JSON")?;
+            edit_json_recipients(&mut value, change, recipients, scope)?;
+            let mut json = serde_json::to_string_pretty(&value)?;
+            json.push('\n');
+            Ok(json)
+        }
+        ConfigFormat::Yaml => anyhow::bail!("YAML config files are not supported yet"),
+    }
+}
+
+/// Apply a recipient change to a TOML config document
+fn edit_toml_recipients(
+    doc: &mut toml_edit::DocumentMut,
+    change: RecipientChange,
+    recipients: &[String],
+    scope: Option<&str>,
+) -> Result<()> {
+    use toml_edit::{Array, Item, Table, Value};
+
+    let age = doc
+        .entry("age")
+        .or_insert_with(|| Item::Table(Table::new()))
+        .as_table_like_mut()
+        .context("[age] is not a table")?;
+
+    let array = if let Some(scope) = scope {
+        let scopes = age
+            .entry("scopes")
+            .or_insert_with(|| Item::Table(Table::new()))
+            .as_table_like_mut()
+            .context("[age.scopes] is not a table")?;
+        scopes
+            .entry(scope)
+            .or_insert_with(|| Item::Value(Value::Array(Array::new())))
+    } else {
+        // A single `recipient` is folded into `recipients`
+        if let Some(single) = age.remove("recipient") {
+            let single = single
+                .as_str()
+                .context("[age] recipient is not a string")?
+                .to_string();
+            let mut array = Array::new();
+            array.push(single);
+            age.insert("recipients", Item::Value(Value::Array(array)));
+        }
+        age.entry("recipients")
+            .or_insert_with(|| Item::Value(Value::Array(Array::new())))
+    }
+    .as_array_mut()
+    .context("recipients are not an array")?;
+
+    for recipient in recipients {
+        let position = array
+            .iter()
+            .position(|value| value.as_str() == Some(recipient.as_str()));
+        match (change, position) {
+            (RecipientChange::Add, None) => array.push(recipient.as_str()),
+            (RecipientChange::Remove, Some(index)) => {
+                array.remove(index);
+            }
+            _ => {}
+        }
+    }
+    array.fmt();
+    Ok(())
+}
+
+/// Apply a recipient change to a JSON config value
+fn edit_json_recipients(
+    value: &mut serde_json::Value,
+    change: RecipientChange,
+    recipients: &[String],
+    scope: Option<&str>,
+) -> Result<()> {
+    use serde_json::{Value, json};
+
+    let root = value.as_object_mut().context("Config is not an object")?;
+    let age = root
+        .entry("age")
+        .or_insert_with(|| json!({}))
+        .as_object_mut()
+        .context("age is not an object")?;
+
+    let array = if let Some(scope) = scope {
+        age.entry("scopes")
+            .or_insert_with(|| json!({}))
+            .as_object_mut()
+            .context("age.scopes is not an object")?
+            .entry(scope)
+            .or_insert_with(|| json!([]))
+    } else {
+        if let Some(single) = age.remove("recipient") {
+            age.insert("recipients".to_string(), Value::Array(vec![single]));
+        }
+        age.entry("recipients").or_insert_with(|| json!([]))
+    }
+    .as_array_mut()
+    .context("recipients are not an array")?;
+
+    for recipient in recipients {
+        let position = array
+            .iter()
+            .position(|value| value.as_str() == Some(recipient.as_str()));
+        match (change, position) {
+            (RecipientChange::Add, None) => array.push(json!(recipient)),
+            (RecipientChange::Remove, Some(index)) => {
+                array.remove(index);
+            }
+            _ => {}
+        }
+    }
+    Ok(())
+}
+
 #[cfg(test)]
 mod tests {
     #![allow(clippy::unwrap_used, clippy::panic)]
@@ -562,7 +1566,7 @@ mod tests {
         let temp = TempDir::new().expect("Failed to create temp dir");
         let output_path = temp.path().join("test-identity.txt");
 
-        let result = generate(Some(output_path.clone()));
+        let result = generate(Some(output_path.clone()), false);
         assert!(result.is_ok());
 
         // Verify file was created
@@ -578,7 +1582,7 @@ mod tests {
         let temp = TempDir::new().expect("Failed to create temp dir");
         let output_path = temp.path().join("subdir").join("identity.txt");
 
-        let result = generate(Some(output_path.clone()));
+        let result = generate(Some(output_path.clone()), false);
         assert!(result.is_ok());
 
         // Verify parent directory was created
@@ -606,7 +1610,7 @@ mod tests {
         std::fs::write(&test_file, encrypted).expect("Failed to write file");
 
         // Migrate the file
-        let result = migrate_encrypted_file(&test_file, &[old_identity], &[new_recipient]);
+        let result = migrate_encrypted_file(&test_file, &[old_identity], &[new_recipient], true);
         assert!(result.is_ok());
 
         // Verify the file can be decrypted with new identity
@@ -638,10 +1642,47 @@ mod tests {
         std::fs::write(&test_file, encrypted).expect("Failed to write file");
 
         // Try to migrate with wrong old identity (identity2)
-        let result = migrate_encrypted_file(&test_file, &[identity2], &[recipient3]);
+        let result = migrate_encrypted_file(&test_file, &[identity2], &[recipient3], true);
         assert!(result.is_err());
     }
 
+    #[test]
+    fn test_convert_switches_format_without_keys() {
+        let temp = TempDir::new().unwrap();
+        let identity = Identity::generate();
+        let recipients = [identity.to_public()];
+        let armored = temp.path().join("home/secret.txt.age");
+        let binary = temp.path().join("home/token.age");
+        std::fs::create_dir_all(armored.parent().unwrap()).unwrap();
+        std::fs::write(
+            &armored,
+            guisu_crypto::encrypt(b"secret", &recipients).unwrap(),
+        )
+        .unwrap();
+        std::fs::write(
+            &binary,
+            guisu_crypto::encrypt_with_armor(b"token", &recipients, false).unwrap(),
+        )
+        .unwrap();
+
+        let mut config = Config::default();
+        config.age.armor = false;
+        convert(temp.path(), true, &config).unwrap();
+        assert!(guisu_crypto::is_armored(&std::fs::read(&armored).unwrap()));
+
+        convert(temp.path(), false, &config).unwrap();
+        let converted = std::fs::read(&armored).unwrap();
+        assert!(!guisu_crypto::is_armored(&converted));
+        assert_eq!(
+            guisu_crypto::decrypt(&converted, std::slice::from_ref(&identity)).unwrap(),
+            b"secret"
+        );
+
+        config.age.armor = true;
+        convert(temp.path(), false, &config).unwrap();
+        assert!(guisu_crypto::is_armored(&std::fs::read(&binary).unwrap()));
+    }
+
     #[test]
     fn test_migrate_inline_file_roundtrip() {
         let temp = TempDir::new().expect("Failed to create temp dir");
@@ -843,7 +1884,7 @@ mod tests {
         let output_path = temp.path().join("identity.txt");
 
         // Generate identity
-        generate(Some(output_path.clone())).expect("Generate failed");
+        generate(Some(output_path.clone()), false).expect("Generate failed");
 
         // Load the generated identity
         let identities =
@@ -889,7 +1930,7 @@ mod tests {
             std::fs::set_permissions(&test_file, perms).expect("Failed to set permissions");
 
             // Migrate
-            migrate_encrypted_file(&test_file, &[old_identity], &[new_recipient])
+            migrate_encrypted_file(&test_file, &[old_identity], &[new_recipient], true)
                 .expect("Migration failed");
 
             // Verify permissions are preserved
@@ -940,4 +1981,291 @@ mod tests {
         let migrated = std::fs::read_to_string(&test_file).expect("Failed to read file");
         assert_eq!(migrated, content);
     }
+
+    #[test]
+    fn test_target_path_for_source() {
+        let config = Config::default();
+        let source_dir = Path::new("/dotfiles");
+        let root = source_dir.join(&config.general.root_entry);
+
+        assert_eq!(
+            target_path_for_source(source_dir, &root.join("work/.env.j2.age"), &config),
+            Some(PathBuf::from("work/.env"))
+        );
+        assert_eq!(
+            target_path_for_source(source_dir, &root.join(".bashrc"), &config),
+            Some(PathBuf::from(".bashrc"))
+        );
+        assert_eq!(
+            target_path_for_source(source_dir, Path::new("/dotfiles/.guisu/x.age"), &config),
+            None
+        );
+    }
+
+    #[test]
+    fn test_perform_file_migrations_skips_unavailable_scoped_files() {
+        let temp = TempDir::new().expect("Failed to create temp dir");
+        let source_dir = temp.path();
+        let mut config = Config::default();
+        let root = source_dir.join(&config.general.root_entry);
+        std::fs::create_dir_all(root.join("work")).expect("Failed to create dirs");
+
+        let old_identity = Identity::generate();
+        let other_machine = Identity::generate();
+        let work = Identity::generate();
+        let new_identity = Identity::generate();
+        config
+            .age
+            .scopes
+            .insert("work/**".to_string(), vec![work.to_public().to_string()]);
+
+        // Scoped file this machine can decrypt, re-encrypted to the scope
+        let scoped = root.join("work/token.age");
+        let encrypted = guisu_crypto::encrypt(b"token", &[old_identity.to_public()]).unwrap();
+        std::fs::write(&scoped, encrypted).unwrap();
+
+        // Scoped file only another machine can decrypt
+        let foreign = root.join("work/other.age");
+        let encrypted = guisu_crypto::encrypt(b"other", &[other_machine.to_public()]).unwrap();
+        std::fs::write(&foreign, encrypted).unwrap();
+
+        let counts = perform_file_migrations(
+            &[scoped.clone(), foreign],
+            &[],
+            source_dir,
+            &config,
+            &[old_identity],
+            &[new_identity.to_public()],
+        )
+        .unwrap();
+
+        assert_eq!(counts.migrated, 1);
+        assert_eq!(counts.unavailable, 1);
+        assert_eq!(counts.errors, 0);
+
+        let migrated = std::fs::read(&scoped).unwrap();
+        assert_eq!(guisu_crypto::decrypt(&migrated, &[work]).unwrap(), b"token");
+        assert!(guisu_crypto::decrypt(&migrated, &[new_identity]).is_err());
+    }
+
+    #[test]
+    fn test_rotate_reencrypts_and_updates_config() {
+        let temp = TempDir::new().expect("Failed to create temp dir");
+        let key_path = temp.path().join("keys/key.txt");
+        let source_dir = temp.path().join("source");
+        let root = source_dir.join("home");
+        std::fs::create_dir_all(key_path.parent().unwrap()).unwrap();
+        std::fs::create_dir_all(&root).unwrap();
+
+        let old_identity = Identity::generate();
+        let old_public = old_identity.to_public();
+        IdentityFile::save(&key_path, std::slice::from_ref(&old_identity)).unwrap();
+
+        let mut config = Config::default();
+        config.age.identity = Some(key_path.clone());
+        config.age.recipient = Some(old_public.to_string());
+        let template =
+            format!("[age]\nidentity = \"{{{{ keys }}}}/key.txt\"\nrecipient = \"{old_public}\"\n");
+        std::fs::write(source_dir.join(".guisu.toml.j2"), &template).unwrap();
+
+        let encrypted =
+            guisu_crypto::encrypt(b"secret", std::slice::from_ref(&old_public)).unwrap();
+        std::fs::write(root.join("secret.age"), encrypted).unwrap();
+        let inline = encrypt_inline("token", std::slice::from_ref(&old_public)).unwrap();
+        std::fs::write(root.join(".env"), format!("TOKEN={inline}\n")).unwrap();
+
+        let options = RotateOptions {
+            yes: true,
+            ..RotateOptions::default()
+        };
+        rotate(&source_dir, &options, &config).unwrap();
+
+        let backup = load_identities(temp.path().join("keys/key.txt.guisu-backup"), false).unwrap();
+        assert_eq!(backup[0].to_public().to_string(), old_public.to_string());
+        let new_identities = load_identities(&key_path, false).unwrap();
+        let new_public = new_identities[0].to_public().to_string();
+        assert_ne!(new_public, old_public.to_string());
+
+        let secret = std::fs::read(root.join("secret.age")).unwrap();
+        assert_eq!(
+            guisu_crypto::decrypt(&secret, &new_identities).unwrap(),
+            b"secret"
+        );
+        let env = std::fs::read_to_string(root.join(".env")).unwrap();
+        assert_eq!(
+            guisu_crypto::decrypt_file_content(&env, &new_identities).unwrap(),
+            "TOKEN=token\n"
+        );
+
+        let template = std::fs::read_to_string(source_dir.join(".guisu.toml.j2")).unwrap();
+        assert!(template.contains(&new_public));
+        assert!(template.contains("{{ keys }}"));
+        assert!(!template.contains(&old_public.to_string()));
+    }
+
+    #[test]
+    fn test_rotated_identity_path() {
+        let mut config = Config::default();
+        assert!(rotated_identity_path(&config, None).is_err());
+
+        config.age.identity = Some(PathBuf::from("/keys/a.txt"));
+        assert_eq!(
+            rotated_identity_path(&config, None).unwrap(),
+            PathBuf::from("/keys/a.txt")
+        );
+
+        config.age.identities = Some(vec![PathBuf::from("/keys/b.txt")]);
+        assert!(rotated_identity_path(&config, None).is_err());
+        assert_eq!(
+            rotated_identity_path(&config, Some(Path::new("/keys/b.txt"))).unwrap(),
+            PathBuf::from("/keys/b.txt")
+        );
+        assert!(rotated_identity_path(&config, Some(Path::new("/keys/c.txt"))).is_err());
+
+        config.age.identities = None;
+        config.age.identity = Some(PathBuf::from("/home/me/.ssh/id_ed25519"));
+        assert!(rotated_identity_path(&config, None).is_err());
+    }
+
+    #[test]
+    fn test_recipients_add_reencrypts_and_edits_config() {
+        let temp = TempDir::new().expect("Failed to create temp dir");
+        let key_path = temp.path().join("key.txt");
+        let source_dir = temp.path().join("source");
+        let root = source_dir.join("home");
+        std::fs::create_dir_all(&root).unwrap();
+
+        let identity = Identity::generate();
+        let public = identity.to_public();
+        IdentityFile::save(&key_path, std::slice::from_ref(&identity)).unwrap();
+        let other = Identity::generate();
+        let other_public = other.to_public().to_string();
+
+        let mut config = Config::default();
+        config.age.identity = Some(key_path.clone());
+        config.age.recipient = Some(public.to_string());
+        std::fs::write(
+            source_dir.join(".guisu.toml"),
+            format!(
+                "# keys\n[age]\nidentity = \"{}\"\nrecipient = \"{public}\"\n",
+                key_path.display()
+            ),
+        )
+        .unwrap();
+
+        let encrypted = guisu_crypto::encrypt(b"secret", std::slice::from_ref(&public)).unwrap();
+        std::fs::write(root.join("secret.age"), encrypted).unwrap();
+
+        edit_recipients(
+            &source_dir,
+            RecipientChange::Add,
+            std::slice::from_ref(&other_public),
+            None,
+            false,
+            true,
+            &config,
+        )
+        .unwrap();
+
+        let secret = std::fs::read(root.join("secret.age")).unwrap();
+        assert_eq!(guisu_crypto::decrypt(&secret, &[other]).unwrap(), b"secret");
+        assert_eq!(
+            guisu_crypto::decrypt(&secret, &[identity]).unwrap(),
+            b"secret"
+        );
+
+        let edited = std::fs::read_to_string(source_dir.join(".guisu.toml")).unwrap();
+        assert!(edited.starts_with("# keys\n"));
+        let edited: Config = toml::from_str(&edited).unwrap();
+        assert_eq!(edited.age.recipient, None);
+        assert_eq!(
+            edited.age.recipients,
+            vec![public.to_string(), other_public]
+        );
+    }
+
+    #[test]
+    fn test_change_recipients() {
+        let first = Identity::generate().to_public().to_string();
+        let second = Identity::generate().to_public().to_string();
+        let mut age = guisu_config::AgeConfig {
+            recipient: Some(first.clone()),
+            ..Default::default()
+        };
+
+        // Removing the last recipient is refused without derive
+        assert!(
+            change_recipients(
+                &mut age.clone(),
+                RecipientChange::Remove,
+                std::slice::from_ref(&first),
+                None
+            )
+            .is_err()
+        );
+        assert!(
+            change_recipients(
+                &mut age.clone(),
+                RecipientChange::Remove,
+                std::slice::from_ref(&second),
+                None
+            )
+            .is_err()
+        );
+
+        change_recipients(
+            &mut age,
+            RecipientChange::Add,
+            std::slice::from_ref(&first),
+            None,
+        )
+        .unwrap();
+        assert_eq!(age.recipients, Vec::<String>::new());
+
+        change_recipients(
+            &mut age,
+            RecipientChange::Add,
+            std::slice::from_ref(&second),
+            Some("work/**"),
+        )
+        .unwrap();
+        assert_eq!(age.scopes["work/**"], vec![second.clone()]);
+        assert!(
+            change_recipients(
+                &mut age,
+                RecipientChange::Remove,
+                &[second],
+                Some("work/**")
+            )
+            .is_err()
+        );
+    }
+
+    #[test]
+    fn test_edit_config_file_scopes() {
+        let key = Identity::generate().to_public().to_string();
+        let toml = "[age]\nderive = true\n";
+        let edited = edit_config_file(
+            toml,
+            guisu_config::ConfigFormat::Toml,
+            RecipientChange::Add,
+            std::slice::from_ref(&key),
+            Some("work/**"),
+        )
+        .unwrap();
+        let config: Config = toml::from_str(&edited).unwrap();
+        assert_eq!(config.age.scopes["work/**"], vec![key.clone()]);
+
+        let json = format!("{{\"age\": {{\"recipients\": [\"{key}\"]}}}}");
+        let edited = edit_config_file(
+            &json,
+            guisu_config::ConfigFormat::Json,
+            RecipientChange::Remove,
+            std::slice::from_ref(&key),
+            None,
+        )
+        .unwrap();
+        let config: Config = serde_json::from_str(&edited).unwrap();
+        assert_eq!(config.age.recipients, Vec::<String>::new());
+    }
 }
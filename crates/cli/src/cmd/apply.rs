@@ -4,36 +4,352 @@
 
 use anyhow::{Context, Result};
 use clap::Args;
-use guisu_core::path::AbsPath;
-use guisu_engine::entry::TargetEntry;
+use guisu_config::{FsyncPolicy, SecretAction, TargetRoot};
+use guisu_core::path::{AbsPath, RelPath};
+use guisu_engine::FileAttributes;
+use guisu_engine::adapters::crypto::{CryptoDecryptorAdapter, IdentityHints};
+use guisu_engine::adapters::template::{TemplateRendererAdapter, template_context};
+use guisu_engine::clock::RunStamp;
+use guisu_engine::database::MergeBase;
+use guisu_engine::entry::{EntryKind, TargetEntry};
+use guisu_engine::external::{CurlFetcher, Externals, FetchMode, resolve_externals};
+use guisu_engine::parallel::{WorkerPool, batch_by_parent};
+use guisu_engine::pool::{ContentMemo, SharedContent};
 use guisu_engine::processor::ContentProcessor;
-use guisu_engine::state::{SourceState, TargetState};
+use guisu_engine::state::{
+    ApplySnapshot, ConflictSnapshot, ManagedKind, RedbPersistentState, RenderCache, SnapshotFile,
+    SourceState, TargetState,
+};
+use indicatif::ProgressBar;
 use owo_colors::OwoColorize;
 use rayon::prelude::*;
+use std::collections::{BTreeSet, HashSet};
 use std::fs;
-use std::io::IsTerminal;
+use std::io::{IsTerminal, Write};
+use std::num::NonZeroUsize;
 use std::path::{Path, PathBuf};
-use std::sync::Arc;
+use std::sync::{Arc, OnceLock};
 use subtle::ConstantTimeEq;
-use tracing::{debug, info, warn};
+use tracing::{debug, info, trace_span, warn};
 
 use crate::command::Command;
-use crate::common::RuntimeContext;
-use crate::conflict::{ChangeType, ConflictHandler};
-use crate::stats::ApplyStats;
+use crate::common::{EntryFilter, ResolvedPaths, RuntimeContext};
+use crate::conflict::{ChangeType, ConflictHandler, compare_three_way, describe_kind};
+use crate::stats::{ApplyStats, Phase, Timed, Timings};
 use crate::ui::ConflictAction;
 use crate::ui::progress;
+use crate::utils::atomic;
+use crate::utils::dest::DestProbe;
 use crate::utils::path::SourceDirExt;
+use crate::utils::secrets::{SecretScanner, is_world_readable};
+use crate::utils::sudo;
 
 // File permission constants
 const PERM_MASK: u32 = 0o777; // Permission bits mask (rwxrwxrwx)
 const DEFAULT_SECURE_MODE: u32 = 0o600; // Default secure file mode (rw-------)
 
 /// Type alias for batch entry state data (path, content, mode)
-type BatchEntryData = (String, Vec<u8>, Option<u32>);
+type BatchEntryData = (String, Vec<u8>, Option<u32>, MergeBase);
+
+/// Content processor whose decryption and rendering are timed
+type Processor = ContentProcessor<Timed<CryptoDecryptorAdapter>, Timed<TemplateRendererAdapter>>;
+
+/// Decrypts inline age values once per distinct target content
+///
+/// Entries rendered to identical bytes share one pooled content and therefore
+/// one content hash, so the decrypted bytes and their hash are computed for the
+/// first such entry and reused for the rest.
+///
+/// Large files are not held in memory; their content is streamed through the
+/// content processor when they are written.
+struct InlineDecryptor<'a> {
+    identities: &'a [guisu_crypto::Identity],
+    fail_on_decrypt_error: bool,
+    decrypted: ContentMemo<(SharedContent, [u8; 32])>,
+    /// Where the entries' decryption, hashing and I/O are timed
+    timings: Option<&'a Timings>,
+    /// Processor large files are streamed through
+    processor: Option<&'a Processor>,
+}
+
+impl<'a> InlineDecryptor<'a> {
+    fn new(identities: &'a [guisu_crypto::Identity], fail_on_decrypt_error: bool) -> Self {
+        Self {
+            identities,
+            fail_on_decrypt_error,
+            decrypted: ContentMemo::new(),
+            timings: None,
+            processor: None,
+        }
+    }
+
+    /// Stream large files through `processor`
+    fn with_processor(mut self, processor: &'a Processor) -> Self {
+        self.processor = Some(processor);
+        self
+    }
+
+    /// Write the processed content of a large file's `source` to `output`
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if there is no processor, or if processing or
+    /// writing fails
+    fn stream(
+        &self,
+        source: &Path,
+        attributes: FileAttributes,
+        output: &mut dyn Write,
+    ) -> Result<()> {
+        let processor = self
+            .processor
+            .context("Large files cannot be written without a content processor")?;
+        let source = AbsPath::new(source.to_path_buf())?;
+        let mut output = std::io::BufWriter::new(output);
+        processor.stream_file(&source, &attributes, &mut output)?;
+        output.flush()?;
+        Ok(())
+    }
+
+    /// Record the time spent on the entries in `timings`
+    fn with_timings(mut self, timings: &'a Timings) -> Self {
+        self.timings = Some(timings);
+        self
+    }
+
+    /// Run `f`, adding the time it took to `phase` if timings are recorded
+    fn timed<T>(&self, phase: Phase, f: impl FnOnce() -> T) -> T {
+        match self.timings {
+            Some(timings) => timings.time(phase, f),
+            None => f(),
+        }
+    }
+
+    /// Content to write for a target file, with its hash
+    ///
+    /// Content without inline age values is returned as is, with the hash
+    /// computed while building the target state.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if decryption fails and `fail_on_decrypt_error` is set
+    fn decrypt(
+        &self,
+        content: &SharedContent,
+        content_hash: &[u8; 32],
+    ) -> Result<(SharedContent, [u8; 32])> {
+        if self.identities.is_empty() || !content.windows(4).any(|window| window == b"age:") {
+            return Ok((Arc::clone(content), *content_hash));
+        }
+
+        self.decrypted.get_or_try_insert_with(content_hash, || {
+            let decrypted = self.timed(Phase::Decrypt, || {
+                decrypt_inline_age_values(content, self.identities, self.fail_on_decrypt_error)
+            })?;
+            let hash = self.timed(Phase::Hash, || guisu_engine::hash::hash_content(&decrypted));
+            Ok((SharedContent::from(decrypted), hash))
+        })
+    }
+}
+
+/// A target entry together with a single probe of its destination
+///
+/// Built once per entry, so the compare, confirmation and write phases share one
+/// `stat` of the destination, one read of its content and one change detection.
+#[allow(clippy::struct_excessive_bools)]
+struct EntryContext<'a> {
+    entry: &'a TargetEntry,
+    dest: DestProbe,
+    decryptor: &'a InlineDecryptor<'a>,
+    change_type: OnceLock<Option<ChangeType>>,
+    /// Keep extended attributes and file flags of a rewritten file
+    preserve_xattrs: bool,
+    /// What to sync to disk when writing a file
+    fsync: FsyncPolicy,
+    /// Where to copy local changes before overwriting them
+    backup: Option<&'a LocalBackup>,
+    /// Destination is under a root of .guisu/targets.toml
+    mapped: bool,
+    /// Write the destination through `sudo`
+    sudo: bool,
+    /// Entry is a secret env file, whose values are never displayed
+    env_file: bool,
+    /// Entry is decrypted from an encrypted source
+    encrypted: bool,
+}
+
+impl<'a> EntryContext<'a> {
+    fn new(entry: &'a TargetEntry, dest_path: AbsPath, decryptor: &'a InlineDecryptor<'a>) -> Self {
+        Self {
+            entry,
+            dest: DestProbe::new(dest_path),
+            decryptor,
+            change_type: OnceLock::new(),
+            preserve_xattrs: false,
+            fsync: FsyncPolicy::default(),
+            backup: None,
+            mapped: false,
+            sudo: false,
+            env_file: false,
+            encrypted: false,
+        }
+    }
+
+    /// Keep the extended attributes and file flags of the destination when rewriting it
+    fn with_preserve_xattrs(mut self, preserve_xattrs: bool) -> Self {
+        self.preserve_xattrs = preserve_xattrs;
+        self
+    }
+
+    /// Sync written files to disk according to `fsync`
+    fn with_fsync(mut self, fsync: FsyncPolicy) -> Self {
+        self.fsync = fsync;
+        self
+    }
+
+    /// Copy local changes aside before overwriting them
+    fn with_backup(mut self, backup: Option<&'a LocalBackup>) -> Self {
+        self.backup = backup;
+        self
+    }
+
+    /// Mark the entry as a secret env file
+    fn with_env_file(mut self, env_file: bool) -> Self {
+        self.env_file = env_file;
+        self
+    }
+
+    /// Mark the entry as decrypted from an encrypted source
+    fn with_encrypted(mut self, encrypted: bool) -> Self {
+        self.encrypted = encrypted;
+        self
+    }
+
+    /// Record the root of .guisu/targets.toml the entry is applied to
+    ///
+    /// Entries under one of `sudo_roots` are written through `sudo`.
+    fn with_target_root(mut self, root: Option<&TargetRoot>, sudo_roots: &[&TargetRoot]) -> Self {
+        self.mapped = root.is_some();
+        self.sudo = root.is_some_and(|root| sudo_roots.contains(&root));
+        self
+    }
+
+    /// Path shown to the user: `~/`-relative, or absolute outside the destination directory
+    fn display_path(&self) -> String {
+        if self.mapped {
+            self.dest.path().to_string()
+        } else {
+            format!("~/{}", self.entry.path())
+        }
+    }
+
+    /// Decrypted content and hash of a file entry, `None` for other entries
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if decryption fails and `fail_on_decrypt_error` is set
+    fn target_content(&self) -> Result<Option<(SharedContent, [u8; 32])>> {
+        match self.entry {
+            TargetEntry::File {
+                content,
+                content_hash,
+                ..
+            } => self.decryptor.decrypt(content, content_hash).map(Some),
+            _ => Ok(None),
+        }
+    }
+
+    /// The destination's kind, if applying would have to replace it with another kind
+    ///
+    /// Files are written through symlinks, and symlinks replace files, so only
+    /// these need replacing: a directory where the target is a file or symlink,
+    /// and anything but a directory where the target is a directory.
+    fn type_mismatch(&self) -> Option<ChangeType> {
+        let dest = &self.dest;
+        let (expected, found) = match self.entry {
+            TargetEntry::File { .. } | TargetEntry::LargeFile { .. } if dest.is_dir() => {
+                (EntryKind::File, EntryKind::Directory)
+            }
+            TargetEntry::Symlink { .. } if dest.kind() == EntryKind::Directory => {
+                (EntryKind::Symlink, EntryKind::Directory)
+            }
+            TargetEntry::Directory { .. } if dest.is_present() && !dest.is_dir() => {
+                (EntryKind::Directory, dest.kind())
+            }
+            _ => return None,
+        };
+        Some(ChangeType::TypeMismatch { expected, found })
+    }
+
+    /// Three-way change detection against the last written state, computed once
+    fn change_type(
+        &self,
+        db: &guisu_engine::state::RedbPersistentState,
+    ) -> Result<Option<ChangeType>> {
+        if let Some(change_type) = self.change_type.get() {
+            return Ok(*change_type);
+        }
+
+        let change_type = match self.entry {
+            // A destination of the wrong kind conflicts whatever its content
+            _ if self.type_mismatch().is_some() => self.type_mismatch(),
+            // Only files can conflict, and a missing destination is simply created
+            TargetEntry::File {
+                content_hash: rendered_hash,
+                ..
+            } if self.dest.exists() => {
+                // Content that fails to decrypt is compared as rendered; applying
+                // it reports the decryption error
+                let target_hash = self
+                    .target_content()
+                    .ok()
+                    .flatten()
+                    .map_or(*rendered_hash, |(_, hash)| hash);
+                let actual_content = self.decryptor.timed(Phase::Io, || self.dest.content())?;
+                let actual_hash = self.decryptor.timed(Phase::Hash, || {
+                    guisu_engine::hash::hash_content(actual_content)
+                });
+                let last_written_hash = get_last_written_hash(db, self.entry);
+                compare_three_way(
+                    &target_hash,
+                    &actual_hash,
+                    last_written_hash.as_ref().map(|arr| &arr[..]),
+                )
+                .into()
+            }
+            TargetEntry::LargeFile { content_hash, .. } if self.dest.exists() => {
+                let actual_hash = self
+                    .decryptor
+                    .timed(Phase::Hash, || self.dest.content_hash())?;
+                let last_written_hash = get_last_written_hash(db, self.entry);
+                compare_three_way(
+                    content_hash,
+                    &actual_hash,
+                    last_written_hash.as_ref().map(|arr| &arr[..]),
+                )
+                .into()
+            }
+            _ => None,
+        };
+
+        Ok(*self.change_type.get_or_init(|| change_type))
+    }
+
+    /// Whether applying would discard changes made to the destination
+    fn overwrites_local_changes(
+        &self,
+        db: &guisu_engine::state::RedbPersistentState,
+    ) -> Result<bool> {
+        Ok(matches!(
+            self.change_type(db)?,
+            Some(ChangeType::LocalModification | ChangeType::TrueConflict)
+        ))
+    }
+}
 
 /// Apply the source state to the destination
 #[derive(Debug, Clone, Args)]
+#[allow(clippy::struct_excessive_bools)]
 pub struct ApplyCommand {
     /// Specific files to apply (all if not specified)
     #[arg(value_name = "FILES")]
@@ -47,17 +363,59 @@ pub struct ApplyCommand {
     #[arg(short, long)]
     pub force: bool,
 
+    /// Three-way merge local changes with source changes instead of
+    /// overwriting them
+    #[arg(long, conflicts_with = "force")]
+    pub merge: bool,
+
+    /// Remove entries deleted from the source from the destination without
+    /// asking (locally modified files are kept unless --force is given)
+    #[arg(long)]
+    pub prune: bool,
+
     /// Interactive mode - prompt on conflicts
     #[arg(short, long)]
     pub interactive: bool,
 
-    /// Include only these entry types (comma-separated)
+    /// Include only these entry types or target path globs (comma-separated)
     #[arg(long, value_delimiter = ',')]
     pub include: Vec<String>,
 
-    /// Exclude these entry types (comma-separated)
+    /// Exclude these entry types or target path globs (comma-separated)
     #[arg(long, value_delimiter = ',')]
     pub exclude: Vec<String>,
+
+    /// Number of worker threads (defaults to one per CPU)
+    #[arg(short, long, value_name = "N")]
+    pub jobs: Option<NonZeroUsize>,
+
+    /// Copy locally modified files aside before overwriting them
+    /// (see `[apply] backup`)
+    #[arg(long)]
+    pub backup: bool,
+
+    /// Download externals again even if their cached copy is still fresh
+    #[arg(long)]
+    pub refresh_externals: bool,
+
+    /// Clone REPO (GitHub owner/repo, URL, or path) to a temporary directory,
+    /// apply it, and remove it afterwards without keeping any state
+    #[arg(long, value_name = "REPO")]
+    pub one_shot: Option<String>,
+
+    /// Apply the source directory as of a git commit, tag, or branch, read
+    /// from the repository without touching the working tree
+    #[arg(
+        long = "ref",
+        value_name = "REV",
+        visible_alias = "source-ref",
+        conflicts_with = "one_shot"
+    )]
+    pub source_ref: Option<String>,
+
+    /// Print how long each phase took (scan, render, decrypt, hash, io)
+    #[arg(long)]
+    pub timings: bool,
 }
 
 /// Get the last written content hash for an entry from the database
@@ -69,7 +427,7 @@ fn get_last_written_hash(
     entry: &TargetEntry,
 ) -> Option<[u8; 32]> {
     match entry {
-        TargetEntry::File { .. } => {
+        TargetEntry::File { .. } | TargetEntry::LargeFile { .. } => {
             let path_str = entry.path().to_string();
             guisu_engine::database::get_entry_state(db, &path_str)
                 .ok()
@@ -81,7 +439,7 @@ fn get_last_written_hash(
 }
 
 /// Load and prepare all variables for template rendering
-fn load_all_variables(
+pub(crate) fn load_all_variables(
     source_dir: &std::path::Path,
     config: &guisu_config::Config,
 ) -> Result<indexmap::IndexMap<String, serde_json::Value>> {
@@ -105,34 +463,151 @@ fn load_all_variables(
 }
 
 /// Setup content processor with decryptor and template renderer
+///
+/// Templates may generate missing SSH keys only when `dry_run` is off.
+///
+/// # Errors
+///
+/// Returns an error if the template engine cannot be created
 fn setup_content_processor(
     source_dir: &std::path::Path,
     identities: &Arc<Vec<guisu_crypto::Identity>>,
+    identity_hints: &Arc<IdentityHints>,
     config: &guisu_config::Config,
-) -> ContentProcessor<
-    guisu_engine::adapters::crypto::CryptoDecryptorAdapter,
-    guisu_engine::adapters::template::TemplateRendererAdapter,
-> {
-    use guisu_engine::adapters::crypto::CryptoDecryptorAdapter;
-    use guisu_engine::adapters::template::TemplateRendererAdapter;
-
-    let template_engine = crate::create_template_engine(source_dir, identities, config);
-
-    // Use Arc to share identity without cloning
-    let identity_arc = identities.first().map_or_else(
-        || Arc::new(guisu_crypto::Identity::generate()),
-        |id| Arc::new(id.clone()),
-    );
+    timings: &Arc<Timings>,
+    dry_run: bool,
+) -> Result<Processor> {
+    let mut template_engine = crate::create_template_engine(source_dir, identities, config)?;
+    if config.template.ssh_keygen && !dry_run {
+        template_engine = template_engine.with_ssh_keygen(guisu_template::SshKeygen::Write);
+    }
 
-    let decryptor = CryptoDecryptorAdapter::from_arc(identity_arc);
+    let decryptor = CryptoDecryptorAdapter::from_identities(Arc::clone(identities))
+        .with_hints(Arc::clone(identity_hints));
     let renderer = TemplateRendererAdapter::new(template_engine);
-    ContentProcessor::new(decryptor, renderer)
+    Ok(ContentProcessor::new(
+        Timed::new(decryptor, Arc::clone(timings)),
+        Timed::new(renderer, Arc::clone(timings)),
+    )
+    .with_eol(config.general.eol)
+    .with_stream_threshold(config.general.stream_threshold))
+}
+
+/// Load identity hints, starting empty if the database cannot be read
+fn load_identity_hints(db: &guisu_engine::state::RedbPersistentState) -> Arc<IdentityHints> {
+    Arc::new(IdentityHints::load(db).unwrap_or_else(|e| {
+        warn!(error = %e, "Failed to load identity hints");
+        IdentityHints::default()
+    }))
+}
+
+/// Persist identity hints learned while building the target state
+///
+/// Hints only speed up decryption, so a failure is logged rather than returned.
+fn save_identity_hints(db: &guisu_engine::state::RedbPersistentState, hints: &IdentityHints) {
+    if let Err(e) = hints.save(db) {
+        warn!(error = %e, "Failed to save identity hints");
+    }
+}
+
+/// Inputs for loading the render cache of an incremental apply
+pub(crate) struct RenderCacheInputs<'a> {
+    /// Database holding the render records of earlier applies
+    pub(crate) database: &'a guisu_engine::state::RedbPersistentState,
+    /// Source directory, for `.guisu/templates` and `.guisu/functions`
+    pub(crate) source_dir: &'a Path,
+    /// Identities encrypted files are decrypted with
+    pub(crate) identities: &'a [guisu_crypto::Identity],
+}
+
+/// Load the render cache, `None` if `[apply] incremental` is off
+///
+/// The cache fingerprint covers what every processed file depends on besides
+/// its own inputs: the guisu version, the identities, the line ending policy,
+/// the XDG base directories templates can ask for, the templates that can be
+/// included, and the script functions that can be called.
+pub(crate) fn load_render_cache(
+    inputs: &RenderCacheInputs<'_>,
+    paths: &ResolvedPaths,
+    template_context: &serde_json::Value,
+    config: &guisu_config::Config,
+) -> Option<RenderCache> {
+    if !config.apply.incremental {
+        return None;
+    }
+
+    let records = match guisu_engine::database::get_render_records(inputs.database) {
+        Ok(records) => records,
+        Err(e) => {
+            warn!(error = %e, "Failed to read render records, processing all files");
+            return None;
+        }
+    };
+
+    let mut fingerprint = env!("CARGO_PKG_VERSION").as_bytes().to_vec();
+    for identity in inputs.identities {
+        fingerprint.push(0);
+        fingerprint.extend_from_slice(identity.to_public().to_string().as_bytes());
+    }
+    if let Some(eol) = config.general.eol {
+        fingerprint.push(0);
+        fingerprint.extend_from_slice(format!("{eol:?}").as_bytes());
+    }
+    for dir in [
+        guisu_config::dirs::config_home(),
+        guisu_config::dirs::data_home(),
+        guisu_config::dirs::cache_home(),
+        guisu_config::dirs::state_home(),
+    ] {
+        fingerprint.push(0);
+        fingerprint.extend_from_slice(dir.unwrap_or_default().as_os_str().as_encoded_bytes());
+    }
+
+    let mut shared: Vec<_> = [
+        inputs.source_dir.templates_dir(),
+        inputs.source_dir.functions_dir(),
+    ]
+    .iter()
+    .flat_map(walkdir::WalkDir::new)
+    .filter_map(std::result::Result::ok)
+    .filter(|entry| entry.file_type().is_file())
+    .map(walkdir::DirEntry::into_path)
+    .collect();
+    shared.sort();
+    for path in shared {
+        fingerprint.push(0);
+        fingerprint.extend_from_slice(path.to_string_lossy().as_bytes());
+        fingerprint.push(0);
+        fingerprint.extend_from_slice(&fs::read(&path).unwrap_or_default());
+    }
+
+    Some(
+        RenderCache::new(
+            records,
+            paths.dest_dir.clone(),
+            template_context,
+            fingerprint,
+        )
+        .with_targets(paths.targets.clone()),
+    )
 }
 
-/// Read source state with optional ignore filtering
+/// Save render records of the files processed while building the target state
+fn save_render_records(db: &guisu_engine::state::RedbPersistentState, target_state: &TargetState) {
+    let records: Vec<_> = target_state
+        .render_records()
+        .iter()
+        .map(|(path, record)| (path.to_string(), record.clone()))
+        .collect();
+    if let Err(e) = guisu_engine::database::save_render_records(db, &records) {
+        warn!(error = %e, "Failed to save render records");
+    }
+}
+
+/// Read source state, skipping ignored entries and excluded subtrees
 fn read_source_state(
     source_abs: AbsPath,
-    source_dir: &std::path::Path,
+    matcher: &guisu_config::IgnoreMatcher,
     is_single_file: bool,
 ) -> Result<SourceState> {
     let spinner = if is_single_file {
@@ -141,14 +616,8 @@ fn read_source_state(
         Some(progress::create_spinner("Reading source state..."))
     };
 
-    let matcher = guisu_config::IgnoreMatcher::from_ignores_toml(source_dir).ok();
-
-    let source_state = if let Some(ref matcher) = matcher {
-        SourceState::read_with_matcher(source_abs, Some(matcher))
-            .context("Failed to read source state with ignore matcher")?
-    } else {
-        SourceState::read(source_abs).context("Failed to read source state")?
-    };
+    let source_state = SourceState::read_with_matcher(source_abs, Some(matcher))
+        .context("Failed to read source state with ignore matcher")?;
 
     if let Some(spinner) = spinner {
         spinner.finish_and_clear();
@@ -158,19 +627,13 @@ fn read_source_state(
 }
 
 /// Build target state from source state (process templates, decrypt files)
-#[allow(clippy::too_many_arguments)]
 fn build_target_state(
     filtered_source_state: &SourceState,
-    processor: &ContentProcessor<
-        guisu_engine::adapters::crypto::CryptoDecryptorAdapter,
-        guisu_engine::adapters::template::TemplateRendererAdapter,
-    >,
-    source_abs: &AbsPath,
-    dest_abs: &AbsPath,
-    working_tree: &Path,
+    processor: &Processor,
+    paths: &ResolvedPaths,
     config: &guisu_config::Config,
-    all_variables: indexmap::IndexMap<String, serde_json::Value>,
     is_single_file: bool,
+    render_cache: Option<&RenderCacheInputs<'_>>,
 ) -> Result<TargetState> {
     let spinner = if is_single_file {
         None
@@ -180,19 +643,36 @@ fn build_target_state(
         ))
     };
 
-    let template_context = guisu_template::TemplateContext::with_guisu_context(
-        source_abs.to_string(),
-        working_tree.display().to_string(),
-        dest_abs.to_string(),
-        config.general.root_entry.display().to_string(),
-        all_variables,
-    );
+    let template_context = template_context(
+        &paths.source_dir,
+        paths.dotfiles_dir.as_path(),
+        paths.dest_dir.as_path(),
+        config,
+    )
+    .context("Failed to load variables")?;
 
     let template_context_value =
         serde_json::to_value(&template_context).context("Failed to serialize template context")?;
 
-    let target_state =
-        TargetState::from_source(filtered_source_state, processor, &template_context_value)?;
+    let render_cache = render_cache
+        .and_then(|inputs| load_render_cache(inputs, paths, &template_context_value, config));
+    let mut target_state = if let Some(cache) = &render_cache {
+        TargetState::from_source_incremental(
+            filtered_source_state,
+            processor,
+            &template_context_value,
+            config.apply.mode,
+            cache,
+        )?
+    } else {
+        TargetState::from_source_with_mode(
+            filtered_source_state,
+            processor,
+            &template_context_value,
+            config.apply.mode,
+        )?
+    };
+    target_state.normalize_link_targets(config.apply.symlink_targets, |path| paths.dest_path(path));
 
     if let Some(spinner) = spinner {
         spinner.finish_and_clear();
@@ -201,13 +681,79 @@ fn build_target_state(
     Ok(target_state)
 }
 
-/// Filter entries to apply based on file paths, ignore patterns, and create-once status
+/// Add removals for unmanaged entries of `exact_` directories
+pub(crate) fn add_exact_removals(
+    target_state: &mut TargetState,
+    source_state: &SourceState,
+    paths: &ResolvedPaths,
+    ignore_matcher: &guisu_config::IgnoreMatcher,
+) -> Result<()> {
+    target_state
+        .add_exact_removals(
+            source_state,
+            |path| paths.dest_path(path),
+            |path, is_dir| ignore_matcher.is_ignored(path.as_path(), Some(is_dir)),
+        )
+        .context("Failed to read exact directories")
+}
+
+/// Add the files and archives of `.guisu/externals.toml` to the target state
+///
+/// Entries from the source directory take precedence over external entries at
+/// the same path. Offline, only cached downloads are used.
+pub(crate) fn add_externals(
+    target_state: &mut TargetState,
+    externals: &Externals,
+    context: &RuntimeContext,
+    refresh: bool,
+    is_single_file: bool,
+) -> Result<()> {
+    if externals.is_empty() {
+        return Ok(());
+    }
+
+    let mode = if crate::is_offline(&context.config) {
+        FetchMode::Offline
+    } else if refresh {
+        FetchMode::Refresh
+    } else {
+        FetchMode::Cached
+    };
+
+    let spinner = (!is_single_file).then(|| progress::create_spinner("Fetching externals..."));
+    let entries = resolve_externals(
+        externals,
+        context.database(),
+        &CurlFetcher,
+        &context.clock.begin_run(),
+        mode,
+    )
+    .context("Failed to fetch externals")?;
+    if let Some(spinner) = spinner {
+        spinner.finish_and_clear();
+    }
+
+    for entry in entries {
+        if target_state.get(entry.path()).is_none() {
+            target_state.add(entry);
+        }
+    }
+    Ok(())
+}
+
+/// Filter entries to apply based on file paths, entry filters, ignore patterns,
+/// and create-once status
+///
+/// Entries are matched against `entry_filter` through their source entry;
+/// externals and removals, which have none, are matched by kind.
 fn filter_entries_to_apply<'a>(
     target_state: &'a TargetState,
+    source_state: &SourceState,
     filter_paths: Option<&Vec<guisu_core::path::RelPath>>,
+    entry_filter: &EntryFilter,
     ignore_matcher: &guisu_config::IgnoreMatcher,
     metadata: &guisu_engine::state::Metadata,
-    dest_abs: &AbsPath,
+    paths: &ResolvedPaths,
 ) -> Vec<&'a TargetEntry> {
     let mut entries: Vec<&TargetEntry> = target_state
         .entries()
@@ -215,25 +761,19 @@ fn filter_entries_to_apply<'a>(
             let target_path = entry.path();
 
             // Filter by files or directories
-            if let Some(filter) = filter_paths {
-                let matches = filter.iter().any(|filter_path| {
-                    // Exact match (file or directory itself)
-                    if filter_path == target_path {
-                        return true;
-                    }
-
-                    // Check if target is under the filter directory
-                    // Ensure we don't match ".config/zsh-backup" when filter is ".config/zsh"
-                    let filter_str = filter_path.as_path().to_str().unwrap_or("");
-                    let target_str = target_path.as_path().to_str().unwrap_or("");
-
-                    target_str.starts_with(filter_str)
-                        && target_str.as_bytes().get(filter_str.len()) == Some(&b'/')
-                });
+            if let Some(filter) = filter_paths
+                && !matches_filter(filter, target_path)
+            {
+                return false;
+            }
 
-                if !matches {
-                    return false;
-                }
+            // Filter by entry type and glob
+            let selected = match source_state.get(target_path) {
+                Some(source_entry) => entry_filter.matches_source(source_entry),
+                None => entry_filter.matches_target(entry),
+            };
+            if !selected {
+                return false;
             }
 
             // Skip if file is ignored
@@ -248,7 +788,7 @@ fn filter_entries_to_apply<'a>(
             if let Some(path_str) = target_path.as_path().to_str()
                 && metadata.is_create_once(path_str)
             {
-                let dest_path = dest_abs.join(entry.path());
+                let dest_path = paths.dest_path(entry.path());
                 if dest_path.as_path().exists() {
                     debug!(
                         path = %target_path,
@@ -267,6 +807,315 @@ fn filter_entries_to_apply<'a>(
     entries
 }
 
+/// Whether a target is one of the filter paths or under one of them
+fn matches_filter(filter: &[RelPath], target_path: &RelPath) -> bool {
+    filter.iter().any(|filter_path| {
+        // Exact match (file or directory itself)
+        if filter_path == target_path {
+            return true;
+        }
+
+        // Check if target is under the filter directory
+        // Ensure we don't match ".config/zsh-backup" when filter is ".config/zsh"
+        let filter_str = filter_path.as_path().to_str().unwrap_or("");
+        let target_str = target_path.as_path().to_str().unwrap_or("");
+
+        target_str.starts_with(filter_str)
+            && target_str.as_bytes().get(filter_str.len()) == Some(&b'/')
+    })
+}
+
+/// A target applied by an earlier run that is no longer in the source
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct DeletedTarget {
+    pub(crate) path: RelPath,
+    pub(crate) kind: ManagedKind,
+}
+
+/// Managed targets that the source no longer has, sorted by path
+///
+/// A target is deleted when it was recorded as managed but neither the
+/// source state nor an external provides it. Ignored targets are not
+/// deleted, they are only left alone on this machine. With `filter_paths`,
+/// only targets matching the filter are returned.
+///
+/// # Errors
+///
+/// Returns an error if the managed paths cannot be read from the database
+pub(crate) fn deleted_from_source(
+    db: &RedbPersistentState,
+    source_state: &SourceState,
+    externals: &Externals,
+    ignore_matcher: &guisu_config::IgnoreMatcher,
+    filter_paths: Option<&Vec<RelPath>>,
+) -> Result<Vec<DeletedTarget>> {
+    let current: HashSet<&Path> = source_state
+        .entries()
+        .map(|entry| entry.target_path().as_path())
+        .collect();
+
+    let mut deleted = Vec::new();
+    for (path, kind) in guisu_engine::database::get_managed_paths(db)? {
+        let path = RelPath::new(PathBuf::from(path))?;
+        if current.contains(path.as_path())
+            || externals
+                .iter()
+                .any(|(root, _)| path.as_path().starts_with(root.as_path()))
+            || ignore_matcher.is_ignored(path.as_path(), None)
+            || filter_paths.is_some_and(|filter| !matches_filter(filter, &path))
+        {
+            continue;
+        }
+        deleted.push(DeletedTarget { path, kind });
+    }
+    Ok(deleted)
+}
+
+/// Unmanaged entries of `exact_` directories, which apply would remove
+///
+/// Entries provided by an external are kept. With `filter_paths`, only
+/// entries matching the filter are returned.
+///
+/// # Errors
+///
+/// Returns an error if an exact directory cannot be read
+pub(crate) fn exact_removals(
+    source_state: &SourceState,
+    externals: &Externals,
+    paths: &ResolvedPaths,
+    ignore_matcher: &guisu_config::IgnoreMatcher,
+    filter_paths: Option<&Vec<RelPath>>,
+) -> Result<Vec<DeletedTarget>> {
+    let mut target_state = TargetState::new();
+    target_state
+        .add_exact_removals(
+            source_state,
+            |path| paths.dest_path(path),
+            |path, is_dir| {
+                ignore_matcher.is_ignored(path.as_path(), Some(is_dir))
+                    || externals.iter().any(|(root, _)| {
+                        path.as_path().starts_with(root.as_path())
+                            || root.as_path().starts_with(path.as_path())
+                    })
+            },
+        )
+        .context("Failed to read exact directories")?;
+
+    let mut removals: Vec<DeletedTarget> = target_state
+        .entries()
+        .filter(|entry| filter_paths.is_none_or(|filter| matches_filter(filter, entry.path())))
+        .filter_map(|entry| {
+            let file_type = fs::symlink_metadata(paths.dest_path(entry.path()).as_path())
+                .ok()?
+                .file_type();
+            let kind = if file_type.is_symlink() {
+                ManagedKind::Symlink
+            } else if file_type.is_dir() {
+                ManagedKind::Directory
+            } else {
+                ManagedKind::File
+            };
+            Some(DeletedTarget {
+                path: entry.path().clone(),
+                kind,
+            })
+        })
+        .collect();
+    removals.sort_by(|a, b| a.path.as_path().cmp(b.path.as_path()));
+    Ok(removals)
+}
+
+/// Record the applied entries as managed, so that they are offered for
+/// removal once deleted from the source
+fn record_managed_paths(db: &RedbPersistentState, contexts: &[EntryContext<'_>]) -> Result<()> {
+    let paths: Vec<(String, ManagedKind)> = contexts
+        .iter()
+        .filter_map(|ctx| Some((ctx.entry.path().to_string(), ManagedKind::of(ctx.entry)?)))
+        .collect();
+    guisu_engine::database::save_managed_paths(db, &paths).context("Failed to record managed paths")
+}
+
+/// How deleted targets are handled
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum PruneMode {
+    /// List what would be removed
+    DryRun,
+    /// Remove without asking
+    Prune,
+    /// Ask before removing each file or symlink
+    Ask,
+    /// Only point at `--prune`
+    Report,
+}
+
+/// What happened to a deleted target
+enum PruneOutcome {
+    Removed,
+    /// Left in place and still managed, so it is offered again next time
+    Kept(&'static str),
+    /// Left in place and no longer managed
+    Released(&'static str),
+}
+
+/// Remove targets deleted from the source from the destination
+///
+/// Children are handled before their parents, so a directory is removed once
+/// the deleted entries in it are gone. Directories are only removed when
+/// empty, and locally modified files only with `force`.
+fn prune_deleted_targets(
+    db: &RedbPersistentState,
+    deleted: &[DeletedTarget],
+    dest_path: impl Fn(&RelPath) -> AbsPath,
+    mode: PruneMode,
+    force: bool,
+    stats: &ApplyStats,
+) -> Result<()> {
+    // Targets already gone from the destination need nothing but forgetting
+    let mut present = Vec::with_capacity(deleted.len());
+    for target in deleted {
+        if fs::symlink_metadata(dest_path(&target.path).as_path()).is_ok() {
+            present.push(target);
+        } else if mode != PruneMode::DryRun {
+            guisu_engine::database::delete_entry_state(db, &target.path.to_string())?;
+        }
+    }
+    if present.is_empty() {
+        return Ok(());
+    }
+
+    if mode == PruneMode::Report {
+        println!(
+            "\n{} {} deleted from the source but still in the destination:",
+            "●".yellow(),
+            present.len().to_string().yellow().bold()
+        );
+        for target in &present {
+            println!("  {} ~/{}", "-".red(), target.path);
+        }
+        println!("  {}", "Run `guisu apply --prune` to remove them".dimmed());
+        return Ok(());
+    }
+
+    println!("\n{}", "Deleted from the source:".bold());
+    let mut parents = BTreeSet::new();
+    for target in present.into_iter().rev() {
+        let outcome = if mode == PruneMode::DryRun {
+            PruneOutcome::Removed
+        } else {
+            prune_target(db, target, &dest_path(&target.path), mode, force)?
+        };
+
+        let path = format!("~/{}", target.path);
+        match outcome {
+            PruneOutcome::Removed => {
+                stats.inc_removed();
+                stats.record_change(target.path.to_string());
+                parents.extend(
+                    target
+                        .path
+                        .as_path()
+                        .ancestors()
+                        .skip(1)
+                        .map(Path::to_path_buf),
+                );
+                println!("  {} {}", "-".red(), path);
+            }
+            PruneOutcome::Kept(reason) => {
+                println!(
+                    "  {} {} {}",
+                    "•".yellow(),
+                    path,
+                    format!("({reason})").dimmed()
+                );
+            }
+            PruneOutcome::Released(reason) => {
+                debug!(path = %target.path, reason, "No longer managed");
+                guisu_engine::database::delete_entry_state(db, &target.path.to_string())?;
+                println!(
+                    "  {} {} {}",
+                    "•".dimmed(),
+                    path,
+                    format!("({reason})").dimmed()
+                );
+            }
+        }
+    }
+
+    if mode != PruneMode::DryRun {
+        crate::cmd::forget::remove_empty_parents(parents, dest_path);
+    }
+    Ok(())
+}
+
+/// Remove a single deleted target, asking first in [`PruneMode::Ask`]
+fn prune_target(
+    db: &RedbPersistentState,
+    target: &DeletedTarget,
+    dest_path: &AbsPath,
+    mode: PruneMode,
+    force: bool,
+) -> Result<PruneOutcome> {
+    let path = target.path.to_string();
+    let meta = fs::symlink_metadata(dest_path.as_path())
+        .with_context(|| format!("Failed to read metadata: {dest_path}"))?;
+
+    let kind_matches = match target.kind {
+        ManagedKind::File => meta.is_file(),
+        ManagedKind::Directory => meta.is_dir(),
+        ManagedKind::Symlink => meta.is_symlink(),
+    };
+    if !kind_matches {
+        return Ok(PruneOutcome::Released("replaced, left in place"));
+    }
+
+    if target.kind == ManagedKind::Directory {
+        if fs::read_dir(dest_path.as_path())?.next().is_some() {
+            return Ok(PruneOutcome::Released("not empty, left in place"));
+        }
+        fs::remove_dir(dest_path.as_path())
+            .with_context(|| format!("Failed to remove directory: {dest_path}"))?;
+        guisu_engine::database::delete_entry_state(db, &path)?;
+        return Ok(PruneOutcome::Removed);
+    }
+
+    let modified = target.kind == ManagedKind::File
+        && guisu_engine::database::get_entry_state(db, &path)?.is_some_and(|state| {
+            fs::read(dest_path.as_path()).map_or(true, |content| {
+                guisu_engine::hash::hash_content(&content) != state.content_hash
+            })
+        });
+
+    let remove = match mode {
+        PruneMode::Ask => {
+            use dialoguer::{Confirm, theme::ColorfulTheme};
+            let prompt = if modified {
+                format!("Remove ~/{path} (deleted from the source, modified locally)?")
+            } else {
+                format!("Remove ~/{path} (deleted from the source)?")
+            };
+            Confirm::with_theme(&ColorfulTheme::default())
+                .with_prompt(prompt)
+                .default(false)
+                .interact()
+                .context("Failed to read user input")?
+        }
+        _ => !modified || force,
+    };
+    if !remove {
+        // Declining keeps the file for good; --prune leaves it to be forced
+        return Ok(if mode == PruneMode::Ask {
+            PruneOutcome::Released("kept, no longer managed")
+        } else {
+            PruneOutcome::Kept("modified locally, use --force to remove")
+        });
+    }
+
+    fs::remove_file(dest_path.as_path())
+        .with_context(|| format!("Failed to remove: {dest_path}"))?;
+    guisu_engine::database::delete_entry_state(db, &path)?;
+    Ok(PruneOutcome::Removed)
+}
+
 /// Display drift warnings for files modified both locally and in source
 fn display_drift_warnings(drift_warnings: &[String]) {
     if !drift_warnings.is_empty() {
@@ -285,29 +1134,185 @@ fn display_drift_warnings(drift_warnings: &[String]) {
         );
         println!(
             "{}",
-            "Consider backing up modified files or using interactive mode (-i) for control."
+            "Overwritten content is saved; recover it with `guisu conflicts list`, or use interactive mode (-i) for control."
                 .dimmed()
         );
         println!();
     }
 }
 
+/// Files apply would write world-readable that contain potential secrets
+///
+/// Returns `(path, findings)` pairs sorted by path; files that are already up
+/// to date are skipped.
+fn find_exposed_secrets(
+    contexts: &[EntryContext<'_>],
+    scanner: &SecretScanner,
+) -> Vec<(String, String)> {
+    let mut exposed: Vec<_> = contexts
+        .par_iter()
+        .filter_map(|ctx| {
+            let TargetEntry::File { mode, .. } = ctx.entry else {
+                return None;
+            };
+            if !is_world_readable(*mode, ctx.dest.mode()) || !needs_update(ctx).unwrap_or(true) {
+                return None;
+            }
+            let (content, _) = ctx.target_content().ok()??;
+            let path = ctx.entry.path();
+            scanner
+                .scan(path.as_path(), &content)
+                .map(|findings| (path.to_string(), findings))
+        })
+        .collect();
+    exposed.sort();
+    exposed
+}
+
+/// Warn about or refuse secrets that would be written world-readable
+///
+/// Governed by `[security] apply`; a dry run only warns.
+///
+/// # Errors
+///
+/// Returns an error if the allowlist is invalid, or if secrets are found and
+/// the action is `error`
+fn check_exposed_secrets(
+    contexts: &[EntryContext<'_>],
+    config: &guisu_config::Config,
+    dry_run: bool,
+) -> Result<()> {
+    let action = config.security.apply;
+    if action == SecretAction::Ignore {
+        return Ok(());
+    }
+
+    let scanner = SecretScanner::new(&config.security)?;
+    let exposed = find_exposed_secrets(contexts, &scanner);
+    if exposed.is_empty() {
+        return Ok(());
+    }
+
+    if action == SecretAction::Error && !dry_run {
+        let details: Vec<String> = exposed
+            .iter()
+            .map(|(path, findings)| format!("{path}:\n{findings}"))
+            .collect();
+        anyhow::bail!(
+            "Potential secrets would be written to world-readable files:\n{}\n\n\
+             Use the private_ prefix or set a restrictive mode in the source,\n\
+             or allow them with [security] allowlist or apply = \"warning\"",
+            details.join("\n")
+        );
+    }
+
+    for (path, findings) in &exposed {
+        warn!("Potential secrets would be written to world-readable {path}:\n{findings}");
+    }
+    warn!("  Tip: Use the private_ prefix to write these files with mode 0600");
+    Ok(())
+}
+
+/// Log secrets an unattended apply would write world-readable
+///
+/// Returns the paths to refuse, which are only non-empty when `[security]
+/// apply` is `error` and this is not a dry run.
+///
+/// # Errors
+///
+/// Returns an error if the allowlist is invalid
+fn unattended_secret_guard(
+    contexts: &[EntryContext<'_>],
+    config: &guisu_config::Config,
+    dry_run: bool,
+) -> Result<HashSet<String>> {
+    let action = config.security.apply;
+    if action == SecretAction::Ignore {
+        return Ok(HashSet::new());
+    }
+
+    let exposed = find_exposed_secrets(contexts, &SecretScanner::new(&config.security)?);
+    for (path, findings) in &exposed {
+        warn!(path = %path, findings = %findings, "Potential secrets would be written world-readable");
+    }
+    if action == SecretAction::Error && !dry_run {
+        Ok(exposed.into_iter().map(|(path, _)| path).collect())
+    } else {
+        Ok(HashSet::new())
+    }
+}
+
+/// Report destinations whose kind differs from the target, grouped by kind
+///
+/// These are found while planning, so they show up before anything is written
+/// instead of as IO errors partway through apply.
+fn display_type_conflicts(contexts: &[EntryContext<'_>], force: bool) {
+    let mut groups: Vec<((EntryKind, EntryKind), Vec<String>)> = Vec::new();
+    for ctx in contexts {
+        let Some(ChangeType::TypeMismatch { expected, found }) = ctx.type_mismatch() else {
+            continue;
+        };
+        let path = ctx.entry.path().to_string();
+        match groups
+            .iter_mut()
+            .find(|(kinds, _)| *kinds == (expected, found))
+        {
+            Some((_, paths)) => paths.push(path),
+            None => groups.push(((expected, found), vec![path])),
+        }
+    }
+
+    if groups.is_empty() {
+        return;
+    }
+
+    println!("\n{}", "Type Conflicts Detected".yellow().bold());
+    for ((expected, found), paths) in &groups {
+        println!(
+            "{}",
+            format!(
+                "The source has {}, but the destination is {}:",
+                describe_kind(*expected),
+                describe_kind(*found)
+            )
+            .yellow()
+        );
+        for path in paths {
+            println!("  {} {}", "•".yellow(), path.bright_white());
+        }
+    }
+    println!();
+    if force {
+        println!(
+            "{}",
+            "These will be replaced; the existing entries are renamed to <name>.guisu-backup first."
+                .yellow()
+        );
+    } else {
+        println!("{}", "These entries will be skipped.".yellow());
+        println!(
+            "{}",
+            "Re-run with --force to replace them (the existing entries are renamed to <name>.guisu-backup first), use interactive mode (-i) to decide per entry, or move them aside manually."
+                .dimmed()
+        );
+    }
+    println!();
+}
+
 /// Handle dry run mode for a single entry
 fn handle_dry_run_entry(
-    entry: &TargetEntry,
-    dest_path: &AbsPath,
-    identities: &[guisu_crypto::Identity],
+    ctx: &EntryContext<'_>,
     stats: &ApplyStats,
     show_icons: bool,
-    fail_on_decrypt_error: bool,
 ) -> Result<bool> {
-    if !needs_update(entry, dest_path, identities, fail_on_decrypt_error)? {
+    let entry = ctx.entry;
+    if !needs_update(ctx)? {
         debug!(path = %entry.path(), "File is already up to date, skipping");
         return Ok(false);
     }
 
     debug!(path = %entry.path(), "Would apply entry");
-    print_dry_run_entry(entry, show_icons);
+    print_dry_run_entry(ctx, show_icons);
     stats.record_dry_run(entry);
     Ok(true)
 }
@@ -315,23 +1320,12 @@ fn handle_dry_run_entry(
 /// Handle interactive conflict resolution
 fn handle_interactive_conflict(
     db: &guisu_engine::state::RedbPersistentState,
-    entry: &TargetEntry,
-    dest_abs: &AbsPath,
-    dest_path: &AbsPath,
-    identities: &[guisu_crypto::Identity],
+    ctx: &EntryContext<'_>,
     handler: &mut ConflictHandler,
-    fail_on_decrypt_error: bool,
 ) -> Result<bool> {
-    let last_written_hash = get_last_written_hash(db, entry);
-    let change_type = ConflictHandler::detect_change_type(
-        entry,
-        dest_abs,
-        last_written_hash.as_ref().map(|arr| &arr[..]),
-        identities,
-    )?;
-
-    if let Some(change_type) = change_type {
-        match handler.prompt_action(entry, dest_abs, None, change_type)? {
+    let entry = ctx.entry;
+    if let Some(change_type) = ctx.change_type(db)? {
+        match handler.prompt_action(entry, ctx.dest.path(), None, change_type, ctx.env_file)? {
             ConflictAction::Override => Ok(true),
             ConflictAction::Skip => {
                 debug!(path = %entry.path(), "Skipping due to user choice");
@@ -345,45 +1339,33 @@ fn handle_interactive_conflict(
             _ => unreachable!("Unexpected action returned from prompt_action"),
         }
     } else {
-        needs_update(entry, dest_path, identities, fail_on_decrypt_error)
+        needs_update(ctx)
     }
 }
 
 /// Handle non-interactive conflict resolution with user confirmation
 fn handle_non_interactive_conflict(
     db: &guisu_engine::state::RedbPersistentState,
-    entry: &TargetEntry,
-    dest_abs: &AbsPath,
-    dest_path: &AbsPath,
-    identities: &[guisu_crypto::Identity],
-    fail_on_decrypt_error: bool,
+    ctx: &EntryContext<'_>,
 ) -> Result<bool> {
-    if !needs_update(entry, dest_path, identities, fail_on_decrypt_error)? {
+    if !needs_update(ctx)? {
         return Ok(false);
     }
 
-    let last_written_hash = get_last_written_hash(db, entry);
-    let change_type = ConflictHandler::detect_change_type(
-        entry,
-        dest_abs,
-        last_written_hash.as_ref().map(|arr| &arr[..]),
-        identities,
-    )?;
-
-    if let Some(change_type) = change_type {
+    if let Some(change_type) = ctx.change_type(db)? {
         match change_type {
             ChangeType::LocalModification | ChangeType::TrueConflict => {
                 use dialoguer::{Confirm, theme::ColorfulTheme};
                 let change_label = match change_type {
                     ChangeType::LocalModification => "Local modification",
                     ChangeType::TrueConflict => "Conflict (both local and source modified)",
-                    ChangeType::SourceUpdate => {
-                        unreachable!("SourceUpdate filtered by outer match")
+                    ChangeType::SourceUpdate | ChangeType::TypeMismatch { .. } => {
+                        unreachable!("SourceUpdate and TypeMismatch filtered by outer match")
                     }
                 };
 
                 println!("\n{} {}", "⚠".yellow(), change_label.yellow().bold());
-                println!("  File: {}", entry.path().bright_white());
+                println!("  File: {}", ctx.entry.path().bright_white());
                 println!("  {}", "This file has been modified locally.".yellow());
                 println!(
                     "  {}",
@@ -398,52 +1380,407 @@ fn handle_non_interactive_conflict(
                     .context("Failed to read user input")
             }
             ChangeType::SourceUpdate => Ok(true),
+            // Already reported while planning; replacing needs --force
+            ChangeType::TypeMismatch { .. } => {
+                debug!(path = %ctx.entry.path(), "Skipping destination of the wrong type");
+                Ok(false)
+            }
         }
     } else {
         Ok(true)
     }
 }
 
+/// Save the destination content if applying the entry would discard local changes
+///
+/// Returns the snapshot ID when one was taken. The snapshot is saved before the
+/// destination is touched, so if saving fails the local changes are still on disk.
+fn snapshot_local_changes(
+    db: &guisu_engine::state::RedbPersistentState,
+    ctx: &EntryContext<'_>,
+    stamp: &RunStamp,
+) -> Result<Option<String>> {
+    if !ctx.overwrites_local_changes(db)? {
+        return Ok(None);
+    }
+
+    let entry = ctx.entry;
+    if matches!(entry, TargetEntry::LargeFile { .. }) {
+        // Too large to keep in the database; a backup copy still can be made
+        debug!(path = %entry.path(), "Not snapshotting large file");
+        return Ok(None);
+    }
+    let content = ctx.dest.content()?.to_vec();
+    let mode = ctx.dest.mode().map(|mode| mode & PERM_MASK);
+
+    let snapshot = ConflictSnapshot::new(entry.path().to_string(), content, mode, stamp);
+    let id = guisu_engine::database::save_conflict_snapshot(db, &snapshot)
+        .with_context(|| format!("Failed to save local changes of {}", entry.path()))?;
+    debug!(path = %entry.path(), id = %id, "Saved conflict snapshot");
+    Ok(Some(id))
+}
+
+/// Where locally modified files are copied before apply overwrites them
+#[derive(Debug)]
+enum LocalBackup {
+    /// Next to the file, as `<name><suffix>`
+    Suffix(String),
+    /// Under this directory, at the file's target path
+    Dir(PathBuf),
+}
+
+impl LocalBackup {
+    /// Backup location for a run, `None` unless backups are enabled
+    ///
+    /// With `backupDir` set, each run gets its own directory named after the
+    /// time it started.
+    fn for_run(
+        enabled: bool,
+        config: &guisu_config::ApplyConfig,
+        stamp: &RunStamp,
+    ) -> Option<Self> {
+        if !enabled {
+            return None;
+        }
+        Some(match &config.backup_dir {
+            Some(dir) => {
+                let started = chrono::DateTime::<chrono::Local>::from(stamp.time());
+                Self::Dir(dir.join(started.format("%Y%m%d-%H%M%S").to_string()))
+            }
+            None => Self::Suffix(config.backup_suffix.clone()),
+        })
+    }
+
+    /// Backup path for an entry's destination
+    fn path_for(&self, ctx: &EntryContext<'_>) -> Result<PathBuf> {
+        match self {
+            Self::Suffix(suffix) => {
+                let dest_path = ctx.dest.path();
+                let file_name = dest_path
+                    .as_path()
+                    .file_name()
+                    .with_context(|| format!("Cannot back up destination: {dest_path}"))?
+                    .to_string_lossy();
+                Ok(dest_path
+                    .as_path()
+                    .with_file_name(format!("{file_name}{suffix}")))
+            }
+            Self::Dir(dir) => Ok(dir.join(ctx.entry.path().as_path())),
+        }
+    }
+}
+
+/// Copy the destination aside if applying the entry would discard local changes
+///
+/// Does nothing unless backups are enabled. An older backup at the same path is
+/// replaced. Returns the backup path when one was written.
+fn backup_local_changes(
+    db: &guisu_engine::state::RedbPersistentState,
+    ctx: &EntryContext<'_>,
+) -> Result<Option<PathBuf>> {
+    let Some(backup) = ctx.backup else {
+        return Ok(None);
+    };
+    if !ctx.overwrites_local_changes(db)? {
+        return Ok(None);
+    }
+
+    let path = backup.path_for(ctx)?;
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)
+            .with_context(|| format!("Failed to create backup directory: {}", parent.display()))?;
+    }
+    let copied = if matches!(ctx.entry, TargetEntry::LargeFile { .. }) {
+        fs::copy(ctx.dest.path().as_path(), &path).map(|_| ())
+    } else {
+        fs::write(&path, ctx.dest.content()?)
+    };
+    copied.with_context(|| {
+        format!(
+            "Failed to back up {} to {}",
+            ctx.dest.path(),
+            path.display()
+        )
+    })?;
+
+    #[cfg(unix)]
+    if let Some(mode) = ctx.dest.mode() {
+        use std::os::unix::fs::PermissionsExt;
+        fs::set_permissions(&path, fs::Permissions::from_mode(mode & PERM_MASK))
+            .with_context(|| format!("Failed to set permissions: {}", path.display()))?;
+    }
+
+    debug!(path = %ctx.entry.path(), backup = %path.display(), "Backed up local changes");
+    Ok(Some(path))
+}
+
+/// Save local changes about to be overwritten and record where they went
+///
+/// Takes the conflict snapshot and, with backups enabled, the backup copy.
+fn save_local_changes(
+    db: &guisu_engine::state::RedbPersistentState,
+    ctx: &EntryContext<'_>,
+    stats: &ApplyStats,
+    stamp: &RunStamp,
+) -> Result<()> {
+    let path = ctx.entry.path();
+    if let Some(id) = snapshot_local_changes(db, ctx, stamp)? {
+        stats.record_conflict_snapshot(id, path.to_string());
+    }
+    if let Some(backup) = backup_local_changes(db, ctx)? {
+        stats.record_file_backup(path.to_string(), backup.display().to_string());
+    }
+    Ok(())
+}
+
+/// Save the destination files this run is about to change as an apply snapshot
+///
+/// Taken once, before any entry is written, so `guisu rollback` can put the
+/// destination back as it was. Files that will be skipped because they were
+/// modified locally are left out unless `overwrite_local` is set. Only the
+/// `keep` most recent snapshots are kept.
+fn snapshot_destinations(
+    db: &guisu_engine::state::RedbPersistentState,
+    contexts: &[EntryContext<'_>],
+    overwrite_local: bool,
+    stamp: &RunStamp,
+    keep: usize,
+) -> Result<()> {
+    let mut snapshot = ApplySnapshot::new(stamp);
+    for ctx in contexts {
+        if !matches!(ctx.entry, TargetEntry::File { .. })
+            || ctx.type_mismatch().is_some()
+            || !needs_update(ctx).unwrap_or(true)
+            || (!overwrite_local && ctx.overwrites_local_changes(db)?)
+        {
+            continue;
+        }
+
+        let content_hash = if ctx.dest.exists() {
+            Some(guisu_engine::database::save_snapshot_content(
+                db,
+                ctx.dest.content()?,
+            )?)
+        } else {
+            None
+        };
+        snapshot.files.push(SnapshotFile {
+            path: ctx.entry.path().to_string(),
+            content_hash,
+            mode: ctx.dest.mode().map(|mode| mode & PERM_MASK),
+        });
+    }
+
+    if snapshot.files.is_empty() {
+        return Ok(());
+    }
+    let id = guisu_engine::database::save_apply_snapshot(db, &snapshot, keep)
+        .context("Failed to save apply snapshot")?;
+    debug!(id = %id, files = snapshot.files.len(), "Saved apply snapshot");
+    Ok(())
+}
+
+/// Entry data to record in the database after the entry was written
+///
+/// Only files have state. Inline age values are decrypted so the saved content
+/// matches what was written to disk. Large files have no content in memory,
+/// so their hash is saved right away instead.
+fn entry_state_data(
+    db: &guisu_engine::state::RedbPersistentState,
+    ctx: &EntryContext<'_>,
+    stamp: &RunStamp,
+) -> Option<BatchEntryData> {
+    let entry = ctx.entry;
+    if let TargetEntry::LargeFile {
+        content_hash, mode, ..
+    } = entry
+    {
+        let path = entry.path().to_string();
+        if let Err(e) =
+            guisu_engine::database::save_entry_hash(db, &path, *content_hash, *mode, stamp)
+        {
+            warn!(path = %path, error = %e, "Failed to save entry state");
+        }
+        return None;
+    }
+    let TargetEntry::File {
+        content,
+        content_hash,
+        mode,
+        ..
+    } = entry
+    else {
+        return None;
+    };
+
+    let (final_content, decrypted_inline) = match ctx.target_content() {
+        Ok(Some((decrypted, hash))) => (decrypted.to_vec(), hash != *content_hash),
+        Ok(None) => (content.to_vec(), false),
+        Err(e) => {
+            warn!(path = %entry.path(), error = %e, "Failed to decrypt inline age values for state saving");
+            // Fall back to original content to avoid data loss
+            (content.to_vec(), false)
+        }
+    };
+    let secret = ctx.encrypted || ctx.env_file || decrypted_inline;
+    let base = MergeBase::for_file(secret, written_mode(*mode, &ctx.dest));
+    Some((entry.path().to_string(), final_content, *mode, base))
+}
+
+/// Outcome of merging a locally modified destination
+enum MergeOutcome {
+    /// Destination unchanged since the source did not change
+    KeptLocal,
+    /// Local and source changes combined cleanly
+    Merged,
+    /// Written with conflict markers for the user to resolve
+    Conflicts,
+}
+
+/// Merge local changes into a destination whose source also changed
+///
+/// The base is the content last applied, kept in the database. Returns `None`
+/// if the entry has no local changes, or if it cannot be merged (no base was
+/// kept, or any side is not text) and must go through the usual prompt.
+///
+/// The merged content is written over the destination and the new target is
+/// recorded as applied, so the merged file shows up as a local modification
+/// until the source changes again.
+fn merge_local_changes(
+    db: &guisu_engine::state::RedbPersistentState,
+    ctx: &EntryContext<'_>,
+    stats: &ApplyStats,
+    stamp: &RunStamp,
+) -> Result<Option<MergeOutcome>> {
+    let entry = ctx.entry;
+    match ctx.change_type(db)? {
+        Some(ChangeType::LocalModification) => return Ok(Some(MergeOutcome::KeptLocal)),
+        Some(ChangeType::TrueConflict) => {}
+        _ => return Ok(None),
+    }
+
+    let path = entry.path().to_string();
+    let Some(base) = guisu_engine::database::get_base_content(db, &path)? else {
+        debug!(path = %path, "No merge base kept, falling back to overwrite");
+        return Ok(None);
+    };
+    let Some((target, _)) = ctx.target_content()? else {
+        return Ok(None);
+    };
+    let (Ok(base), Ok(local), Ok(remote)) = (
+        std::str::from_utf8(&base),
+        std::str::from_utf8(ctx.dest.content()?),
+        std::str::from_utf8(&target),
+    ) else {
+        debug!(path = %path, "Not a text file, falling back to overwrite");
+        return Ok(None);
+    };
+
+    let merged = crate::ui::merge::three_way_merge(base, local, remote)?;
+
+    save_local_changes(db, ctx, stats, stamp)?;
+    let dest_path = ctx.dest.path();
+    write_file(ctx, &ctx.dest, entry.mode(), |file| {
+        file.write_all(merged.content().as_bytes())
+            .with_context(|| format!("Failed to write merged file: {dest_path:?}"))
+    })?;
+
+    if let Some((path, content, mode, base)) = entry_state_data(db, ctx, stamp) {
+        guisu_engine::database::save_entry_state(db, &path, &content, mode, base, stamp)?;
+    }
+
+    Ok(Some(if merged.has_conflicts() {
+        MergeOutcome::Conflicts
+    } else {
+        MergeOutcome::Merged
+    }))
+}
+
+/// Merge every locally modified entry, returning the entries still to apply
+///
+/// Entries that were merged, or whose local changes were kept, are left out of
+/// the result. A failed merge counts as a failed entry.
+fn merge_entries<'a>(
+    db: &guisu_engine::state::RedbPersistentState,
+    contexts: Vec<EntryContext<'a>>,
+    stats: &ApplyStats,
+    stamp: &RunStamp,
+) -> Vec<EntryContext<'a>> {
+    let mut conflicts = 0;
+    let remaining = contexts
+        .into_iter()
+        .filter(|ctx| {
+            let path = ctx.entry.path();
+            match merge_local_changes(db, ctx, stats, stamp) {
+                Ok(None) => return true,
+                Ok(Some(MergeOutcome::KeptLocal)) => {
+                    debug!(path = %path, "Keeping local changes");
+                    println!("  {} ~/{} (modified locally)", "⏭".yellow(), path);
+                }
+                Ok(Some(MergeOutcome::Merged)) => {
+                    println!("  {} ~/{} (merged)", "✓".bright_green(), path);
+                    stats.inc_files();
+                    stats.record_change(path.to_string());
+                }
+                Ok(Some(MergeOutcome::Conflicts)) => {
+                    conflicts += 1;
+                    println!("  {} ~/{} (merged with conflicts)", "⚠".yellow(), path);
+                    stats.inc_files();
+                    stats.record_change(path.to_string());
+                }
+                Err(e) => {
+                    warn!(path = %path, error = %e, "Failed to merge entry");
+                    print_error_entry(ctx, &e, false);
+                    stats.record_failure();
+                }
+            }
+            false
+        })
+        .collect();
+
+    if conflicts > 0 {
+        println!(
+            "\n{} {} with conflict markers; edit them to resolve (local versions are listed by {})",
+            "⚠".yellow(),
+            if conflicts == 1 {
+                "1 file was merged".to_string()
+            } else {
+                format!("{conflicts} files were merged")
+            },
+            "guisu conflicts list".bright_white()
+        );
+    }
+
+    remaining
+}
+
 /// Apply entry and handle errors, returning entry data for batch save
 ///
+/// Local changes about to be overwritten are snapshotted first.
 /// Returns `Some((path, content, mode))` if the entry was successfully applied and needs state saved
 fn apply_entry_with_error_handling(
-    entry: &TargetEntry,
-    dest_path: &AbsPath,
-    identities: &[guisu_crypto::Identity],
+    db: &guisu_engine::state::RedbPersistentState,
+    ctx: &EntryContext<'_>,
     stats: &ApplyStats,
     show_icons: bool,
-    fail_on_decrypt_error: bool,
+    stamp: &RunStamp,
 ) -> Option<BatchEntryData> {
-    match apply_target_entry(entry, dest_path, identities, fail_on_decrypt_error) {
-        Ok(()) => {
+    let entry = ctx.entry;
+    let _span = trace_span!("apply_entry", path = %entry.path()).entered();
+    let result = save_local_changes(db, ctx, stats, stamp)
+        .and_then(|()| ctx.decryptor.timed(Phase::Io, || apply_target_entry(ctx)));
+
+    match result {
+        Ok(backup) => {
             debug!(path = %entry.path(), "Applied entry successfully");
-            print_success_entry(entry, show_icons);
+            print_success_entry(ctx, show_icons);
             stats.record_success(entry);
-
-            // Return entry data for batch save (only for files)
-            if let TargetEntry::File { content, mode, .. } = entry {
-                // Save decrypted content to match what was written to disk
-                let final_content = match decrypt_inline_age_values(
-                    content,
-                    identities,
-                    fail_on_decrypt_error,
-                ) {
-                    Ok(decrypted) => decrypted,
-                    Err(e) => {
-                        warn!(path = %entry.path(), error = %e, "Failed to decrypt inline age values for state saving");
-                        // Fall back to original content to avoid data loss
-                        content.clone()
-                    }
-                };
-                Some((entry.path().to_string(), final_content, *mode))
-            } else {
-                None
-            }
+            stats.record_backup(entry, backup);
+            entry_state_data(db, ctx, stamp)
         }
         Err(e) => {
             warn!(path = %entry.path(), error = %e, "Failed to apply entry");
-            print_error_entry(entry, &e, show_icons);
+            print_error_entry(ctx, &e, show_icons);
             stats.record_failure();
             None
         }
@@ -451,64 +1788,31 @@ fn apply_entry_with_error_handling(
 }
 
 /// Process entries sequentially (for interactive mode or dry run)
-#[allow(clippy::too_many_arguments)]
 fn process_entries_sequential(
     db: &guisu_engine::state::RedbPersistentState,
-    entries: Vec<&TargetEntry>,
-    dest_abs: &AbsPath,
-    identities: &[guisu_crypto::Identity],
+    contexts: &[EntryContext<'_>],
     conflict_handler: &mut Option<ConflictHandler>,
     stats: &ApplyStats,
     show_icons: bool,
     dry_run: bool,
-    fail_on_decrypt_error: bool,
+    stamp: &RunStamp,
 ) -> Result<()> {
     // Pre-allocate capacity for worst case (all entries applied successfully)
-    let mut batch_entries = Vec::with_capacity(entries.len());
-
-    for entry in entries {
-        let dest_path = dest_abs.join(entry.path());
+    let mut batch_entries = Vec::with_capacity(contexts.len());
 
+    for ctx in contexts {
         if dry_run {
-            handle_dry_run_entry(
-                entry,
-                &dest_path,
-                identities,
-                stats,
-                show_icons,
-                fail_on_decrypt_error,
-            )?;
+            handle_dry_run_entry(ctx, stats, show_icons)?;
         } else {
             let should_apply = if let Some(handler) = conflict_handler {
-                handle_interactive_conflict(
-                    db,
-                    entry,
-                    dest_abs,
-                    &dest_path,
-                    identities,
-                    handler,
-                    fail_on_decrypt_error,
-                )?
+                handle_interactive_conflict(db, ctx, handler)?
             } else {
-                handle_non_interactive_conflict(
-                    db,
-                    entry,
-                    dest_abs,
-                    &dest_path,
-                    identities,
-                    fail_on_decrypt_error,
-                )?
+                handle_non_interactive_conflict(db, ctx)?
             };
 
             if should_apply
-                && let Some(state_data) = apply_entry_with_error_handling(
-                    entry,
-                    &dest_path,
-                    identities,
-                    stats,
-                    show_icons,
-                    fail_on_decrypt_error,
-                )
+                && let Some(state_data) =
+                    apply_entry_with_error_handling(db, ctx, stats, show_icons, stamp)
             {
                 batch_entries.push(state_data);
             }
@@ -517,55 +1821,55 @@ fn process_entries_sequential(
 
     // Batch save all successful entries to database
     if !batch_entries.is_empty() {
-        guisu_engine::database::save_entry_states_batch(db, &batch_entries).map_err(|e| {
-            warn!(error = %e, "Failed to save batch state to database");
-            e
-        })?;
+        guisu_engine::database::save_entry_states_batch(db, &batch_entries, stamp).map_err(
+            |e| {
+                warn!(error = %e, "Failed to save batch state to database");
+                e
+            },
+        )?;
     }
 
     Ok(())
 }
 
 /// Get user confirmations for entries with local modifications
-fn get_user_confirmations(
+///
+/// Returns the entries that need updating and were confirmed, in order.
+/// With `force`, local modifications are overwritten without prompting.
+fn get_user_confirmations<'c, 'a>(
     db: &guisu_engine::state::RedbPersistentState,
-    entries: &[&TargetEntry],
-    dest_abs: &AbsPath,
-    identities: &[guisu_crypto::Identity],
-    fail_on_decrypt_error: bool,
-) -> Result<std::collections::HashSet<String>> {
+    contexts: &'c [EntryContext<'a>],
+    force: bool,
+) -> Result<Vec<&'c EntryContext<'a>>> {
     use dialoguer::{Confirm, theme::ColorfulTheme};
-    use std::collections::HashSet;
 
-    let mut confirmed_paths = HashSet::new();
+    let mut confirmed = Vec::with_capacity(contexts.len());
     let mut has_warnings = false;
 
-    for entry in entries {
-        let dest_path = dest_abs.join(entry.path());
-        if !needs_update(entry, &dest_path, identities, fail_on_decrypt_error)? {
+    for ctx in contexts {
+        if !needs_update(ctx)? {
+            debug!(path = %ctx.entry.path(), "File is already up to date, skipping");
             continue;
         }
 
-        let last_written_hash = get_last_written_hash(db, entry);
-        if let Ok(Some(change_type)) = ConflictHandler::detect_change_type(
-            entry,
-            dest_abs,
-            last_written_hash.as_ref().map(|arr| &arr[..]),
-            identities,
-        ) {
+        if let Ok(Some(change_type)) = ctx.change_type(db) {
             match change_type {
+                ChangeType::LocalModification | ChangeType::TrueConflict if force => {
+                    debug!(path = %ctx.entry.path(), "Overwriting local changes (forced)");
+                    confirmed.push(ctx);
+                }
                 ChangeType::LocalModification | ChangeType::TrueConflict => {
                     has_warnings = true;
                     let change_label = match change_type {
                         ChangeType::LocalModification => "Local modification",
                         ChangeType::TrueConflict => "Conflict (both local and source modified)",
-                        ChangeType::SourceUpdate => {
-                            unreachable!("SourceUpdate filtered by outer match")
+                        ChangeType::SourceUpdate | ChangeType::TypeMismatch { .. } => {
+                            unreachable!("SourceUpdate and TypeMismatch filtered by outer match")
                         }
                     };
 
                     println!("\n{} {}", "⚠".yellow(), change_label.yellow().bold());
-                    println!("  File: {}", entry.path().bright_white());
+                    println!("  File: {}", ctx.entry.path().bright_white());
                     println!("  {}", "This file has been modified locally.".yellow());
                     println!(
                         "  {}",
@@ -573,22 +1877,30 @@ fn get_user_confirmations(
                     );
 
                     let theme = ColorfulTheme::default();
-                    let confirmed = Confirm::with_theme(&theme)
+                    let overwrite = Confirm::with_theme(&theme)
                         .with_prompt("Continue and overwrite local changes?")
                         .default(false)
                         .interact()
                         .context("Failed to read user input")?;
 
-                    if confirmed {
-                        confirmed_paths.insert(entry.path().to_string());
+                    if overwrite {
+                        confirmed.push(ctx);
                     }
                 }
                 ChangeType::SourceUpdate => {
-                    confirmed_paths.insert(entry.path().to_string());
+                    confirmed.push(ctx);
+                }
+                ChangeType::TypeMismatch { .. } if force => {
+                    debug!(path = %ctx.entry.path(), "Replacing destination of the wrong type (forced)");
+                    confirmed.push(ctx);
+                }
+                // Already reported while planning
+                ChangeType::TypeMismatch { .. } => {
+                    debug!(path = %ctx.entry.path(), "Skipping destination of the wrong type");
                 }
             }
         } else {
-            confirmed_paths.insert(entry.path().to_string());
+            confirmed.push(ctx);
         }
     }
 
@@ -596,80 +1908,75 @@ fn get_user_confirmations(
         println!();
     }
 
-    Ok(confirmed_paths)
+    Ok(confirmed)
 }
 
-/// Process a single entry and return batch data if successful
+/// Apply a single confirmed entry and return batch data if successful
+///
+/// The entry has already been checked with `needs_update`, so the destination is
+/// not compared again here.
 fn process_single_entry(
-    entry: &TargetEntry,
-    dest_abs: &AbsPath,
-    identities: &[guisu_crypto::Identity],
+    db: &guisu_engine::state::RedbPersistentState,
+    ctx: &EntryContext<'_>,
     stats: &ApplyStats,
     show_icons: bool,
-    fail_on_decrypt_error: bool,
+    stamp: &RunStamp,
+    progress: &ProgressBar,
 ) -> Result<Option<BatchEntryData>> {
-    let dest_path = dest_abs.join(entry.path());
+    let entry = ctx.entry;
+    let _span = trace_span!("apply_entry", path = %entry.path()).entered();
 
-    if !needs_update(entry, &dest_path, identities, fail_on_decrypt_error)? {
-        debug!(path = %entry.path(), "File is already up to date, skipping");
-        return Ok(None);
-    }
+    save_local_changes(db, ctx, stats, stamp)?;
 
-    apply_target_entry(entry, &dest_path, identities, fail_on_decrypt_error)?;
+    let backup = ctx.decryptor.timed(Phase::Io, || apply_target_entry(ctx))?;
     debug!(path = %entry.path(), "Applied entry successfully");
-    print_success_entry(entry, show_icons);
+    progress.suspend(|| print_success_entry(ctx, show_icons));
     stats.record_success(entry);
+    stats.record_backup(entry, backup);
 
-    // Prepare entry data for batch save (only for files)
-    let state_data = if let TargetEntry::File { content, mode, .. } = entry {
-        let final_content = decrypt_inline_age_values(content, identities, fail_on_decrypt_error)
-            .unwrap_or_else(|e| {
-                warn!(path = %entry.path(), error = %e, "Failed to decrypt inline age values for state saving");
-                content.clone()
-            });
-        Some((entry.path().to_string(), final_content, *mode))
-    } else {
-        None
-    };
-
-    Ok(state_data)
+    Ok(entry_state_data(db, ctx, stamp))
 }
 
 /// Process entries in parallel (for non-interactive mode)
+///
+/// With `show_progress`, a progress bar counts the written entries.
+#[allow(clippy::too_many_arguments)]
 fn process_entries_parallel(
     db: &guisu_engine::state::RedbPersistentState,
-    entries: &[&TargetEntry],
-    dest_abs: &AbsPath,
-    identities: &[guisu_crypto::Identity],
+    contexts: &[EntryContext<'_>],
     stats: &ApplyStats,
     show_icons: bool,
-    fail_on_decrypt_error: bool,
+    force: bool,
+    stamp: &RunStamp,
+    show_progress: bool,
 ) -> Result<()> {
     // Get user confirmations for conflicting files
-    let confirmed_paths =
-        get_user_confirmations(db, entries, dest_abs, identities, fail_on_decrypt_error)?;
+    let confirmed = get_user_confirmations(db, contexts, force)?;
+
+    let progress = if show_progress {
+        progress::create_progress_bar(confirmed.len() as u64, "Writing")
+    } else {
+        ProgressBar::hidden()
+    };
 
-    // Process confirmed files in parallel
-    let results: Vec<Result<Option<BatchEntryData>>> = entries
+    // Process confirmed files in parallel, one worker per destination directory
+    let batches = batch_by_parent(confirmed, |ctx| ctx.entry.path().as_path());
+    let results: Vec<Result<Option<BatchEntryData>>> = batches
         .par_iter()
-        .filter(|entry| confirmed_paths.contains(&entry.path().to_string()))
-        .map(|entry| {
-            process_single_entry(
-                entry,
-                dest_abs,
-                identities,
-                stats,
-                show_icons,
-                fail_on_decrypt_error,
-            )
-            .map_err(|e| {
-                warn!(path = %entry.path(), error = %e, "Failed to apply entry");
-                print_error_entry(entry, &e, show_icons);
-                stats.record_failure();
-                e
-            })
+        .flat_map_iter(|batch| batch.iter())
+        .map(|ctx| {
+            let result = process_single_entry(db, ctx, stats, show_icons, stamp, &progress)
+                .map_err(|e| {
+                    warn!(path = %ctx.entry.path(), error = %e, "Failed to apply entry");
+                    progress.suspend(|| print_error_entry(ctx, &e, show_icons));
+                    stats.record_failure();
+                    e
+                });
+            progress.inc(1);
+            result
         })
         .collect();
+    progress.finish_and_clear();
 
     // Collect successful entries and check for errors
     let mut batch_entries = Vec::with_capacity(results.len());
@@ -681,10 +1988,12 @@ fn process_entries_parallel(
 
     // Batch save all successful entries to database
     if !batch_entries.is_empty() {
-        guisu_engine::database::save_entry_states_batch(db, &batch_entries).map_err(|e| {
-            warn!(error = %e, "Failed to save batch state to database");
-            e
-        })?;
+        guisu_engine::database::save_entry_states_batch(db, &batch_entries, stamp).map_err(
+            |e| {
+                warn!(error = %e, "Failed to save batch state to database");
+                e
+            },
+        )?;
     }
 
     Ok(())
@@ -692,20 +2001,34 @@ fn process_entries_parallel(
 
 impl Command for ApplyCommand {
     type Output = ApplyStats;
-    #[allow(clippy::too_many_lines)]
     fn execute(&self, context: &RuntimeContext) -> crate::error::Result<ApplyStats> {
-        // Parse entry type filters
-        let include_types: Result<Vec<EntryType>> =
-            self.include.iter().map(|s| s.parse()).collect();
-        let _include_types = include_types?;
+        let workers = WorkerPool::new(self.jobs).context("Failed to start apply workers")?;
+        debug!(threads = workers.threads(), "Applying with worker pool");
+        let timings = Arc::new(Timings::new());
+        workers.install(|| {
+            let result = self.run(context, &timings);
+            if self.timings {
+                eprint!("{}", timings.report());
+            }
+            result
+        })
+    }
+}
 
-        let exclude_types: Result<Vec<EntryType>> =
-            self.exclude.iter().map(|s| s.parse()).collect();
-        let _exclude_types = exclude_types?;
+impl ApplyCommand {
+    /// Apply on the current rayon pool
+    #[allow(clippy::too_many_lines)]
+    fn run(
+        &self,
+        context: &RuntimeContext,
+        timings: &Arc<Timings>,
+    ) -> crate::error::Result<ApplyStats> {
+        let entry_filter = EntryFilter::new(&self.include, &self.exclude)?;
 
         // Extract paths, config, and database from context
         let source_abs = context.dotfiles_dir();
         let dest_abs = context.dest_dir();
+        let paths = &context.paths;
         let source_dir = context.source_dir();
         let config = &context.config;
         let database = context.database();
@@ -716,7 +2039,9 @@ impl Command for ApplyCommand {
 
         // Load age identities for decryption
         let spinner = progress::create_spinner("Loading identities...");
-        let identities = std::sync::Arc::new(config.age_identities().unwrap_or_default());
+        let identities = timings.time(Phase::Decrypt, || {
+            Arc::new(config.age_identities().unwrap_or_default())
+        });
         spinner.finish_and_clear();
 
         // Detect if output is to a terminal for icon auto mode
@@ -727,16 +2052,22 @@ impl Command for ApplyCommand {
         let fail_on_decrypt_error = config.age.fail_on_decrypt_error;
 
         // Load variables and create processor
-        let all_variables = load_all_variables(source_dir, config)?;
-        let processor = setup_content_processor(source_dir, &identities, config);
+        let identity_hints = load_identity_hints(database);
+        let processor = setup_content_processor(
+            source_dir,
+            &identities,
+            &identity_hints,
+            config,
+            timings,
+            self.dry_run,
+        )?;
 
         // Load metadata for create-once tracking
         let metadata =
             guisu_engine::state::Metadata::load(source_dir).context("Failed to load metadata")?;
 
-        // Create ignore matcher from .guisu/ignores.toml
-        let ignore_matcher = guisu_config::IgnoreMatcher::from_ignores_toml(source_dir)
-            .context("Failed to load ignore patterns from .guisu/ignores.toml")?;
+        // Create ignore matcher from .guisu/ignores.toml and .guisuignore files
+        let ignore_matcher = crate::load_ignore_matcher(source_dir, source_abs.as_path(), config)?;
 
         // Check if we're applying a single file (affects output verbosity)
         let is_single_file = !self.files.is_empty() && self.files.len() == 1;
@@ -745,52 +2076,147 @@ impl Command for ApplyCommand {
         let filter_paths = if self.files.is_empty() {
             None
         } else {
-            Some(crate::build_filter_paths(&self.files, dest_abs)?)
+            Some(crate::build_filter_paths(
+                &self.files,
+                dest_abs,
+                &paths.targets,
+            )?)
         };
 
         // Read source state
-        let source_state = read_source_state(source_abs.to_owned(), source_dir, is_single_file)?;
+        let source_state = timings.time(Phase::Scan, || {
+            read_source_state(source_abs.to_owned(), &ignore_matcher, is_single_file)
+        })?;
+        let externals = timings
+            .time(Phase::Scan, || Externals::load(source_dir))
+            .context("Failed to load externals")?;
 
-        if source_state.is_empty() {
-            if !is_single_file {
+        let stats = Arc::new(ApplyStats::new());
+        let mut deleted = timings.time(Phase::Scan, || {
+            deleted_from_source(
+                database,
+                &source_state,
+                &externals,
+                &ignore_matcher,
+                filter_paths.as_ref(),
+            )
+        })?;
+        deleted.retain(|target| entry_filter.matches_managed(&target.path, target.kind));
+
+        if source_state.is_empty() && externals.is_empty() {
+            if !is_single_file && deleted.is_empty() {
                 info!("No files to apply");
             }
-            return Ok(ApplyStats::new());
+            self.prune(database, &deleted, paths, &stats)?;
+            return Ok(stats.snapshot());
         }
 
         // Build target state
-        let working_tree = context.working_tree();
-        let target_state = build_target_state(
+        let mut target_state = build_target_state(
             &source_state,
             &processor,
-            source_abs,
-            dest_abs,
-            &working_tree,
+            paths,
             config,
-            all_variables,
             is_single_file,
+            Some(&RenderCacheInputs {
+                database,
+                source_dir,
+                identities: &identities,
+            }),
         )?;
+        timings.time(Phase::Scan, || {
+            add_externals(
+                &mut target_state,
+                &externals,
+                context,
+                self.refresh_externals,
+                is_single_file,
+            )
+        })?;
+        add_exact_removals(&mut target_state, &source_state, paths, &ignore_matcher)?;
+
+        if !self.dry_run {
+            save_identity_hints(database, &identity_hints);
+            save_render_records(database, &target_state);
+        }
 
         // Filter entries to apply
         let entries_to_apply = filter_entries_to_apply(
             &target_state,
+            &source_state,
             filter_paths.as_ref(),
+            &entry_filter,
             &ignore_matcher,
             &metadata,
-            dest_abs,
+            paths,
         );
 
         if entries_to_apply.is_empty() {
-            info!("No matching files to apply");
-            return Ok(ApplyStats::new());
+            if deleted.is_empty() {
+                info!("No matching files to apply");
+            }
+            self.prune(database, &deleted, paths, &stats)?;
+            return Ok(stats.snapshot());
+        }
+
+        let stamp = context.clock.begin_run();
+        let backup =
+            LocalBackup::for_run(self.backup || config.apply.backup, &config.apply, &stamp);
+
+        // Stat every destination once, up front; all later phases reuse the result
+        let sudo_roots = sudo::roots_needing_sudo(&paths.targets);
+        let decryptor = InlineDecryptor::new(&identities, fail_on_decrypt_error)
+            .with_timings(timings)
+            .with_processor(&processor);
+        let contexts: Vec<EntryContext> = entries_to_apply
+            .par_iter()
+            .map(|entry| {
+                EntryContext::new(entry, paths.dest_path(entry.path()), &decryptor)
+                    .with_preserve_xattrs(config.apply.preserve_xattrs)
+                    .with_fsync(config.apply.fsync)
+                    .with_backup(backup.as_ref())
+                    .with_target_root(paths.targets.root_for(entry.path()), &sudo_roots)
+                    .with_env_file(target_state.is_env_file(entry.path()))
+                    .with_encrypted(target_state.is_encrypted_file(entry.path()))
+            })
+            .collect();
+
+        check_exposed_secrets(&contexts, config, self.dry_run)?;
+
+        if !self.dry_run && contexts.iter().any(|ctx| ctx.sudo) {
+            sudo::authenticate(&sudo_roots)?;
         }
 
         // Check for configuration drift (files modified by user AND source updated)
         if !self.dry_run && !is_single_file {
-            let drift_warnings = detect_config_drift(database, &entries_to_apply, dest_abs);
+            let drift_warnings = detect_config_drift(database, &contexts);
             display_drift_warnings(&drift_warnings);
         }
 
+        // Interactive mode asks per entry instead
+        if !self.interactive || self.dry_run {
+            display_type_conflicts(&contexts, self.force);
+        }
+
+        if !self.dry_run && config.apply.snapshots > 0 {
+            timings.time(Phase::Io, || {
+                snapshot_destinations(
+                    database,
+                    &contexts,
+                    self.force || self.merge || self.interactive,
+                    &stamp,
+                    config.apply.snapshots,
+                )
+            })?;
+        }
+
+        // Merge local changes first; the rest are applied as usual
+        let contexts = if self.merge && !self.dry_run {
+            merge_entries(database, contexts, &stats, &stamp)
+        } else {
+            contexts
+        };
+
         // Create conflict handler for interactive mode
         let mut conflict_handler = if self.interactive && !self.dry_run {
             Some(ConflictHandler::new(
@@ -801,37 +2227,38 @@ impl Command for ApplyCommand {
             None
         };
 
-        // Apply entries
-        let stats = Arc::new(ApplyStats::new());
-
         // Use parallel processing only when NOT in interactive mode
         if self.interactive || self.dry_run {
             process_entries_sequential(
                 database,
-                entries_to_apply,
-                dest_abs,
-                &identities,
+                &contexts,
                 &mut conflict_handler,
                 &stats,
                 show_icons,
                 self.dry_run,
-                fail_on_decrypt_error,
+                &stamp,
             )?;
         } else {
             process_entries_parallel(
                 database,
-                &entries_to_apply,
-                dest_abs,
-                &identities,
+                &contexts,
                 &stats,
                 show_icons,
-                fail_on_decrypt_error,
+                self.force,
+                &stamp,
+                !is_single_file,
             )?;
         }
 
         // Return stats instead of printing here
         // The caller (lib.rs) will print the summary after hooks complete
 
+        if !self.dry_run {
+            apply_ownership(&target_state, &contexts);
+            record_managed_paths(database, &contexts)?;
+        }
+        timings.time(Phase::Io, || self.prune(database, &deleted, paths, &stats))?;
+
         let failed_count = stats.failed();
         if failed_count > 0 {
             return Err(anyhow::anyhow!("Failed to apply {failed_count} entries").into());
@@ -841,32 +2268,300 @@ impl Command for ApplyCommand {
     }
 }
 
-/// Entry type filter for apply command
-#[derive(Debug, Clone, Copy, PartialEq)]
-enum EntryType {
-    Files,
-    Dirs,
-    Symlinks,
-    Templates,
-    Encrypted,
+impl ApplyCommand {
+    /// Handle targets deleted from the source according to the flags and terminal
+    fn prune(
+        &self,
+        db: &RedbPersistentState,
+        deleted: &[DeletedTarget],
+        paths: &ResolvedPaths,
+        stats: &ApplyStats,
+    ) -> Result<()> {
+        let mode = if self.dry_run {
+            PruneMode::DryRun
+        } else if self.prune {
+            PruneMode::Prune
+        } else if std::io::stdin().is_terminal() && std::io::stdout().is_terminal() {
+            PruneMode::Ask
+        } else {
+            PruneMode::Report
+        };
+        prune_deleted_targets(
+            db,
+            deleted,
+            |path| paths.dest_path(path),
+            mode,
+            self.force,
+            stats,
+        )
+    }
+}
+
+/// Per-entry outcome of an unattended apply
+#[derive(Debug, Default, serde::Serialize)]
+pub(crate) struct ApplyReport {
+    /// Entries written (or that would be written in dry-run mode)
+    pub(crate) applied: Vec<String>,
+    /// Entries left untouched because the destination was modified locally
+    pub(crate) skipped: Vec<String>,
+    /// Entries that could not be applied
+    pub(crate) failed: Vec<ApplyFailure>,
+    /// Local changes saved before being overwritten
+    pub(crate) snapshots: Vec<SnapshotRef>,
+    /// Destinations of the wrong type moved aside, and locally modified files
+    /// copied aside, before being replaced
+    pub(crate) backups: Vec<BackupRef>,
 }
 
-impl std::str::FromStr for EntryType {
-    type Err = anyhow::Error;
+/// Reference to a conflict snapshot taken during an unattended apply
+#[derive(Debug, serde::Serialize)]
+pub(crate) struct SnapshotRef {
+    /// Snapshot ID for `guisu conflicts show`
+    pub(crate) id: String,
+    /// Target path relative to the destination
+    pub(crate) path: String,
+}
 
-    fn from_str(s: &str) -> Result<Self> {
-        match s.to_lowercase().as_str() {
-            "files" | "file" => Ok(EntryType::Files),
-            "dirs" | "dir" | "directories" => Ok(EntryType::Dirs),
-            "symlinks" | "symlink" => Ok(EntryType::Symlinks),
-            "templates" | "template" => Ok(EntryType::Templates),
-            "encrypted" | "encrypt" => Ok(EntryType::Encrypted),
-            _ => anyhow::bail!(
-                "Invalid entry type: {s}. Valid types: files, dirs, symlinks, templates, encrypted"
-            ),
+/// Where a destination was backed up during an unattended apply
+#[derive(Debug, serde::Serialize)]
+pub(crate) struct BackupRef {
+    /// Target path relative to the destination
+    pub(crate) path: String,
+    /// Absolute path the existing entry was moved or copied to
+    pub(crate) backup: String,
+}
+
+/// A single entry that failed to apply
+#[derive(Debug, serde::Serialize)]
+pub(crate) struct ApplyFailure {
+    /// Target path relative to the destination
+    pub(crate) path: String,
+    /// Error message
+    pub(crate) error: String,
+}
+
+impl ApplyCommand {
+    /// Apply without printing or prompting
+    ///
+    /// Used by `guisu serve`, where stdout carries the protocol and there is no
+    /// terminal to prompt on. Destination files with local modifications are
+    /// reported as skipped unless `force` is set. Hooks are not run.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the source or target state cannot be built, or if
+    /// saving entry state to the database fails. Per-entry failures are recorded
+    /// in the report instead.
+    #[allow(clippy::too_many_lines)]
+    pub(crate) fn execute_unattended(&self, context: &RuntimeContext) -> Result<ApplyReport> {
+        let source_abs = context.dotfiles_dir();
+        let dest_abs = context.dest_dir();
+        let paths = &context.paths;
+        let source_dir = context.source_dir();
+        let config = &context.config;
+        let database = context.database();
+
+        let identities = Arc::new(config.age_identities().unwrap_or_default());
+        let fail_on_decrypt_error = config.age.fail_on_decrypt_error;
+
+        let identity_hints = load_identity_hints(database);
+        let processor = setup_content_processor(
+            source_dir,
+            &identities,
+            &identity_hints,
+            config,
+            &Arc::new(Timings::new()),
+            self.dry_run,
+        )?;
+        let metadata =
+            guisu_engine::state::Metadata::load(source_dir).context("Failed to load metadata")?;
+        let ignore_matcher = crate::load_ignore_matcher(source_dir, source_abs.as_path(), config)?;
+
+        let filter_paths = if self.files.is_empty() {
+            None
+        } else {
+            Some(crate::build_filter_paths(
+                &self.files,
+                dest_abs,
+                &paths.targets,
+            )?)
+        };
+        let entry_filter = EntryFilter::new(&self.include, &self.exclude)?;
+
+        let mut report = ApplyReport::default();
+
+        let source_state = read_source_state(source_abs.to_owned(), &ignore_matcher, true)?;
+        if source_state.is_empty() {
+            return Ok(report);
+        }
+
+        let mut target_state = build_target_state(
+            &source_state,
+            &processor,
+            paths,
+            config,
+            true,
+            Some(&RenderCacheInputs {
+                database,
+                source_dir,
+                identities: &identities,
+            }),
+        )?;
+        add_exact_removals(&mut target_state, &source_state, paths, &ignore_matcher)?;
+
+        if !self.dry_run {
+            save_identity_hints(database, &identity_hints);
+            save_render_records(database, &target_state);
+        }
+
+        let entries_to_apply = filter_entries_to_apply(
+            &target_state,
+            &source_state,
+            filter_paths.as_ref(),
+            &entry_filter,
+            &ignore_matcher,
+            &metadata,
+            paths,
+        );
+
+        let mut batch_entries = Vec::with_capacity(entries_to_apply.len());
+        let stamp = context.clock.begin_run();
+        let backup =
+            LocalBackup::for_run(self.backup || config.apply.backup, &config.apply, &stamp);
+        // Unattended runs cannot prompt, so sudo writes rely on cached credentials
+        let sudo_roots = sudo::roots_needing_sudo(&paths.targets);
+        let decryptor =
+            InlineDecryptor::new(&identities, fail_on_decrypt_error).with_processor(&processor);
+        let contexts: Vec<EntryContext> = entries_to_apply
+            .into_iter()
+            .map(|entry| {
+                EntryContext::new(entry, paths.dest_path(entry.path()), &decryptor)
+                    .with_preserve_xattrs(config.apply.preserve_xattrs)
+                    .with_fsync(config.apply.fsync)
+                    .with_backup(backup.as_ref())
+                    .with_target_root(paths.targets.root_for(entry.path()), &sudo_roots)
+                    .with_env_file(target_state.is_env_file(entry.path()))
+                    .with_encrypted(target_state.is_encrypted_file(entry.path()))
+            })
+            .collect();
+
+        let blocked = unattended_secret_guard(&contexts, config, self.dry_run)?;
+        if !self.dry_run && config.apply.snapshots > 0 {
+            snapshot_destinations(
+                database,
+                &contexts,
+                self.force,
+                &stamp,
+                config.apply.snapshots,
+            )?;
+        }
+
+        for ctx in &contexts {
+            let path = ctx.entry.path().to_string();
+            if blocked.contains(&path) {
+                report.failed.push(ApplyFailure {
+                    path,
+                    error: "Potential secrets would be written to a world-readable file"
+                        .to_string(),
+                });
+                continue;
+            }
+            match self.apply_entry_unattended(database, ctx, &stamp) {
+                Ok(UnattendedOutcome::UpToDate) => {}
+                Ok(UnattendedOutcome::Skipped) => report.skipped.push(path),
+                Ok(UnattendedOutcome::Applied {
+                    state,
+                    snapshot,
+                    backup,
+                }) => {
+                    if let Some(id) = snapshot {
+                        report.snapshots.push(SnapshotRef {
+                            id,
+                            path: path.clone(),
+                        });
+                    }
+                    if let Some(backup) = backup {
+                        report.backups.push(BackupRef {
+                            path: path.clone(),
+                            backup: backup.display().to_string(),
+                        });
+                    }
+                    report.applied.push(path);
+                    batch_entries.extend(state);
+                }
+                Err(e) => {
+                    warn!(path = %path, error = %e, "Failed to apply entry");
+                    report.failed.push(ApplyFailure {
+                        path,
+                        error: format!("{e:#}"),
+                    });
+                }
+            }
+        }
+
+        if !batch_entries.is_empty() {
+            guisu_engine::database::save_entry_states_batch(database, &batch_entries, &stamp)?;
+        }
+        if !self.dry_run {
+            record_managed_paths(database, &contexts)?;
         }
+
+        Ok(report)
+    }
+
+    /// Decide what to do with a single entry and apply it unless this is a dry run
+    fn apply_entry_unattended(
+        &self,
+        db: &guisu_engine::state::RedbPersistentState,
+        ctx: &EntryContext<'_>,
+        stamp: &RunStamp,
+    ) -> Result<UnattendedOutcome> {
+        let _span = trace_span!("apply_entry", path = %ctx.entry.path()).entered();
+        if !needs_update(ctx)? {
+            return Ok(UnattendedOutcome::UpToDate);
+        }
+
+        if !self.force && (ctx.overwrites_local_changes(db)? || ctx.type_mismatch().is_some()) {
+            return Ok(UnattendedOutcome::Skipped);
+        }
+
+        if self.dry_run {
+            return Ok(UnattendedOutcome::Applied {
+                state: None,
+                snapshot: None,
+                backup: None,
+            });
+        }
+
+        let snapshot = snapshot_local_changes(db, ctx, stamp)?;
+        let local_backup = backup_local_changes(db, ctx)?;
+        let backup = apply_target_entry(ctx)?.or(local_backup);
+
+        Ok(UnattendedOutcome::Applied {
+            state: entry_state_data(db, ctx, stamp),
+            snapshot,
+            backup,
+        })
     }
 }
+
+/// What happened to a single entry during an unattended apply
+enum UnattendedOutcome {
+    /// Destination already matches the target
+    UpToDate,
+    /// Destination was modified locally or has the wrong type, and `force`
+    /// was not set
+    Skipped,
+    /// Entry was applied, with state to record for files, the ID of the
+    /// snapshot taken if local changes were overwritten, and where the
+    /// destination was backed up
+    Applied {
+        state: Option<BatchEntryData>,
+        snapshot: Option<String>,
+        backup: Option<PathBuf>,
+    },
+}
+
 /// Check if a target entry needs to be updated at the destination
 ///
 /// Returns true if:
@@ -874,30 +2569,32 @@ impl std::str::FromStr for EntryType {
 /// - The content differs from the target
 /// - The permissions differ from the target
 ///
+/// Answered from the entry's destination probe, so no further `stat` is made.
+///
 /// NOTE: This function should NOT be used alone to determine if a file needs updating.
 /// Use `detect_change_type` instead for proper three-way comparison.
 /// This function is only called after `detect_change_type` returns None.
-fn needs_update(
-    entry: &TargetEntry,
-    dest_path: &AbsPath,
-    identities: &[guisu_crypto::Identity],
-    fail_on_decrypt_error: bool,
-) -> Result<bool> {
-    match entry {
-        TargetEntry::File { content, mode, .. } => {
+fn needs_update(ctx: &EntryContext<'_>) -> Result<bool> {
+    let dest = &ctx.dest;
+    match ctx.entry {
+        TargetEntry::File {
+            content,
+            content_hash,
+            mode,
+            ..
+        } => {
             // If file doesn't exist, it needs to be created
-            if !dest_path.as_path().exists() {
+            if !dest.exists() {
                 return Ok(true);
             }
 
             // Decrypt inline age values in target content before comparing
             // This matches the behavior in detect_change_type and apply_target_entry
-            let target_content_decrypted =
-                decrypt_inline_age_values(content, identities, fail_on_decrypt_error)?;
+            let (target_content_decrypted, _) = ctx.decryptor.decrypt(content, content_hash)?;
 
             // Check if content differs
-            if let Ok(existing_content) = fs::read(dest_path.as_path()) {
-                if existing_content != target_content_decrypted {
+            if let Ok(existing_content) = dest.content() {
+                if existing_content != &*target_content_decrypted {
                     return Ok(true);
                 }
             } else {
@@ -905,161 +2602,282 @@ fn needs_update(
                 return Ok(true);
             }
 
-            // Check if permissions differ (Unix only)
-            #[cfg(unix)]
-            {
-                use std::os::unix::fs::PermissionsExt;
-                if let Some(target_mode) = mode
-                    && let Ok(metadata) = fs::metadata(dest_path.as_path())
-                {
-                    let current_mode = metadata.permissions().mode() & PERM_MASK;
-                    if current_mode != *target_mode {
-                        return Ok(true);
-                    }
-                }
+            // Content matches; check if permissions differ (Unix only)
+            Ok(mode_differs(*mode, dest))
+        }
+        TargetEntry::LargeFile {
+            content_hash, mode, ..
+        } => {
+            if !dest.exists() {
+                return Ok(true);
             }
 
-            // Content and permissions match, no update needed
-            Ok(false)
+            // Compared by hash, without reading the file into memory
+            match dest.content_hash() {
+                Ok(existing_hash) if existing_hash == *content_hash => {
+                    Ok(mode_differs(*mode, dest))
+                }
+                _ => Ok(true),
+            }
         }
         TargetEntry::Directory { mode, .. } => {
-            // If directory doesn't exist, it needs to be created
-            if !dest_path.as_path().exists() {
+            // If directory doesn't exist or isn't a directory, it needs to be created
+            if !dest.is_dir() {
+                return Ok(true);
+            }
+
+            // Directory exists; check if permissions differ (Unix only)
+            Ok(mode_differs(*mode, dest))
+        }
+        TargetEntry::Symlink { target, .. } => {
+            // If symlink doesn't exist, it needs to be created
+            if !dest.exists() {
+                return Ok(true);
+            }
+
+            // Check if it's actually a symlink
+            if !dest.is_symlink() {
                 return Ok(true);
             }
 
-            // Check if it's actually a directory
-            if !dest_path.as_path().is_dir() {
+            // Check if symlink target differs
+            if let Ok(existing_target) = guisu_engine::system::read_symlink(dest.path().as_path()) {
+                if existing_target != target.as_path() {
+                    return Ok(true);
+                }
+            } else {
+                // Can't read symlink, assume it needs update
                 return Ok(true);
             }
 
-            // Check if permissions differ (Unix only)
-            #[cfg(unix)]
-            {
-                use std::os::unix::fs::PermissionsExt;
-                if let Some(target_mode) = mode
-                    && let Ok(metadata) = fs::metadata(dest_path.as_path())
-                {
-                    let current_mode = metadata.permissions().mode() & PERM_MASK;
-                    if current_mode != *target_mode {
-                        return Ok(true);
-                    }
-                }
-            }
+            // Symlink exists with correct target
+            Ok(false)
+        }
+        TargetEntry::Remove { .. } => {
+            // Always needs update if file exists
+            Ok(dest.exists())
+        }
+    }
+}
+
+/// Whether the destination's permission bits differ from the target mode
+///
+/// A target without a mode never differs. Always false on non-Unix platforms.
+pub(crate) fn mode_differs(target_mode: Option<u32>, dest: &DestProbe) -> bool {
+    match (target_mode, dest.mode()) {
+        (Some(target_mode), Some(current_mode)) => current_mode & PERM_MASK != target_mode,
+        _ => false,
+    }
+}
+
+/// Give destinations the owner and group from their `owner_`/`group_` prefixes
+///
+/// Only root can change ownership, so other users get a single warning.
+/// Failures are reported per entry and do not fail the apply.
+fn apply_ownership(target_state: &TargetState, contexts: &[EntryContext<'_>]) {
+    let owned: Vec<_> = contexts
+        .iter()
+        .filter_map(|ctx| {
+            target_state
+                .ownership(ctx.entry.path())
+                .map(|ownership| (ctx, ownership))
+        })
+        .collect();
+    if owned.is_empty() {
+        return;
+    }
+
+    #[cfg(unix)]
+    {
+        if !rustix::process::geteuid().is_root() {
+            warn!(
+                count = owned.len(),
+                "Skipping owner/group prefixes: changing ownership requires root"
+            );
+            return;
+        }
+
+        for (ctx, ownership) in owned {
+            if let Err(e) = set_ownership(ctx.dest.path(), ownership) {
+                warn!(path = %ctx.entry.path(), error = %e, "Failed to set ownership");
+            }
+        }
+    }
+
+    #[cfg(not(unix))]
+    warn!(
+        count = owned.len(),
+        "Skipping owner/group prefixes: not supported on this platform"
+    );
+}
+
+/// Change the owner and group of a destination if they differ
+///
+/// Symlinks are changed themselves rather than their targets.
+#[cfg(unix)]
+fn set_ownership(dest_path: &AbsPath, ownership: &guisu_engine::attr::Ownership) -> Result<()> {
+    use std::os::unix::fs::MetadataExt;
+
+    let uid = ownership
+        .owner
+        .as_deref()
+        .map(|owner| {
+            owner
+                .parse()
+                .ok()
+                .or_else(|| uzers::get_user_by_name(owner).map(|user| user.uid()))
+                .with_context(|| format!("Unknown user: {owner}"))
+        })
+        .transpose()?;
+    let gid = ownership
+        .group
+        .as_deref()
+        .map(|group| {
+            group
+                .parse()
+                .ok()
+                .or_else(|| uzers::get_group_by_name(group).map(|group| group.gid()))
+                .with_context(|| format!("Unknown group: {group}"))
+        })
+        .transpose()?;
+
+    let metadata = fs::symlink_metadata(dest_path.as_path())
+        .with_context(|| format!("Failed to read metadata: {dest_path:?}"))?;
+    if uid.is_none_or(|uid| uid == metadata.uid()) && gid.is_none_or(|gid| gid == metadata.gid()) {
+        return Ok(());
+    }
 
-            // Directory exists with correct permissions
-            Ok(false)
+    std::os::unix::fs::lchown(dest_path.as_path(), uid, gid)
+        .with_context(|| format!("Failed to change ownership: {dest_path:?}"))
+}
+
+/// Apply a single target entry to the destination
+///
+/// A destination of the wrong kind (see [`EntryContext::type_mismatch`]) is
+/// renamed to a backup first, and the backup path is returned.
+fn apply_target_entry(ctx: &EntryContext<'_>) -> Result<Option<PathBuf>> {
+    if ctx.type_mismatch().is_none() {
+        if ctx.sudo {
+            sudo_write_target_entry(ctx, &ctx.dest)?;
+        } else {
+            write_target_entry(ctx, &ctx.dest)?;
         }
-        TargetEntry::Symlink { target, .. } => {
-            // If symlink doesn't exist, it needs to be created
-            if !dest_path.as_path().exists() {
-                return Ok(true);
-            }
+        return Ok(None);
+    }
 
-            // Check if it's actually a symlink
-            if !dest_path.as_path().is_symlink() {
-                return Ok(true);
-            }
+    let backup = backup_destination(ctx.dest.path(), ctx.sudo)?;
+    // The entry's probe still describes what was moved away
+    let dest = DestProbe::new(ctx.dest.path().clone());
+    if ctx.sudo {
+        sudo_write_target_entry(ctx, &dest)?;
+    } else {
+        write_target_entry(ctx, &dest)?;
+    }
+    Ok(Some(backup))
+}
 
-            // Check if symlink target differs
-            if let Ok(existing_target) = fs::read_link(dest_path.as_path()) {
-                if existing_target != target.as_path() {
-                    return Ok(true);
-                }
-            } else {
-                // Can't read symlink, assume it needs update
-                return Ok(true);
-            }
+/// Move a destination aside to `<name>.guisu-backup`, as root with `sudo`
+///
+/// If that name is taken, `<name>.guisu-backup.1`, `.2`, ... are tried.
+fn backup_destination(dest_path: &AbsPath, sudo: bool) -> Result<PathBuf> {
+    let path = dest_path.as_path();
+    let file_name = path
+        .file_name()
+        .with_context(|| format!("Cannot back up destination: {dest_path}"))?
+        .to_string_lossy();
+
+    let mut backup = path.with_file_name(format!("{file_name}.guisu-backup"));
+    let mut n = 0;
+    while fs::symlink_metadata(&backup).is_ok() {
+        n += 1;
+        backup = path.with_file_name(format!("{file_name}.guisu-backup.{n}"));
+    }
 
-            // Symlink exists with correct target
-            Ok(false)
-        }
-        TargetEntry::Remove { .. } => {
-            // Always needs update if file exists
-            Ok(dest_path.as_path().exists())
-        }
+    if sudo {
+        sudo::rename(path, &backup)?;
+    } else {
+        fs::rename(path, &backup).with_context(|| {
+            format!(
+                "Failed to back up {dest_path} to {}",
+                backup.as_path().display()
+            )
+        })?;
     }
+    debug!(path = %dest_path, backup = %backup.display(), "Backed up destination of the wrong type");
+    Ok(backup)
 }
 
-/// Apply a single target entry to the destination
-fn apply_target_entry(
-    entry: &TargetEntry,
-    dest_path: &AbsPath,
-    identities: &[guisu_crypto::Identity],
-    fail_on_decrypt_error: bool,
-) -> Result<()> {
-    match entry {
-        TargetEntry::File { content, mode, .. } => {
-            // Ensure parent directory exists
-            if let Some(parent) = dest_path.as_path().parent() {
-                fs::create_dir_all(parent).with_context(|| {
-                    format!("Failed to create parent directory: {}", parent.display())
-                })?;
-            }
+/// Capture the attributes of a file destination and unlock it for writing
+///
+/// Returns `None` unless `[apply] preserveXattrs` is set and a regular file
+/// occupies the destination.
+fn unlock_preserved_attrs(
+    ctx: &EntryContext<'_>,
+    dest: &DestProbe,
+) -> Result<Option<guisu_engine::system::PreservedAttrs>> {
+    if !ctx.preserve_xattrs || dest.kind() != EntryKind::File {
+        return Ok(None);
+    }
 
-            // Check if file exists and save its permissions
-            #[cfg(unix)]
-            let existing_mode = if dest_path.as_path().exists() {
-                use std::os::unix::fs::PermissionsExt;
-                fs::metadata(dest_path.as_path())
-                    .ok()
-                    .map(|m| m.permissions().mode())
-            } else {
-                None
-            };
+    let dest_path = dest.path();
+    let attrs = guisu_engine::system::PreservedAttrs::capture(dest_path.as_path())
+        .with_context(|| format!("Failed to read extended attributes: {dest_path:?}"))?;
+    attrs
+        .unlock(dest_path.as_path())
+        .with_context(|| format!("Failed to unlock file: {dest_path:?}"))?;
+    Ok(Some(attrs))
+}
 
+/// Write a single target entry over the destination described by `dest`
+///
+/// Uses the destination probe to skip work that is already done: parent
+/// directories of existing destinations are not recreated, and directory modes
+/// are only set when they differ.
+fn write_target_entry(ctx: &EntryContext<'_>, dest: &DestProbe) -> Result<()> {
+    let dest_path = dest.path();
+    match ctx.entry {
+        TargetEntry::File {
+            content,
+            content_hash,
+            mode,
+            ..
+        } => {
             // Decrypt inline age values before writing to destination
             // This allows source files to contain age:... encrypted values
             // but destination files get plaintext (for applications to use)
-            let final_content =
-                decrypt_inline_age_values(content, identities, fail_on_decrypt_error)?;
-
-            // Write file with atomic permission setting to avoid TOCTOU race condition
-            #[cfg(unix)]
-            {
-                use std::io::Write;
-                use std::os::unix::fs::OpenOptionsExt;
-
-                // Determine permissions to use
-                // - If source has mode, use it (source is authoritative)
-                // - Otherwise, preserve existing permissions if file existed
-                // - Default to 0o600 (owner read/write only) for security
-                let mode_to_use = mode.or(existing_mode).unwrap_or(DEFAULT_SECURE_MODE);
-
-                // Create file with permissions atomically (no TOCTOU window)
-                let mut file = fs::OpenOptions::new()
-                    .write(true)
-                    .create(true)
-                    .truncate(true)
-                    .mode(mode_to_use)
-                    .open(dest_path.as_path())
-                    .with_context(|| format!("Failed to create file: {dest_path:?}"))?;
+            let (final_content, _) = ctx.decryptor.decrypt(content, content_hash)?;
 
+            write_file(ctx, dest, *mode, |file| {
                 file.write_all(&final_content)
-                    .with_context(|| format!("Failed to write file content: {dest_path:?}"))?;
-            }
-
-            #[cfg(not(unix))]
-            {
-                // On non-Unix systems, use standard write (no mode support)
-                fs::write(dest_path.as_path(), &final_content)
-                    .with_context(|| format!("Failed to write file: {:?}", dest_path))?;
-            }
-
-            Ok(())
+                    .with_context(|| format!("Failed to write file content: {dest_path:?}"))
+            })
         }
 
+        // The content was fully processed while building the target state,
+        // so streaming it again does not fail halfway through a bad source
+        TargetEntry::LargeFile {
+            source,
+            attributes,
+            mode,
+            ..
+        } => write_file(ctx, dest, *mode, |file| {
+            ctx.decryptor
+                .stream(source, *attributes, file)
+                .with_context(|| format!("Failed to write file content: {dest_path:?}"))
+        }),
+
         TargetEntry::Directory { mode, .. } => {
             // Create directory
-            fs::create_dir_all(dest_path.as_path())
-                .with_context(|| format!("Failed to create directory: {dest_path:?}"))?;
-
-            // Set permissions
-            #[cfg(unix)]
-            if let Some(mode) = mode {
-                use std::os::unix::fs::PermissionsExt;
-                let permissions = fs::Permissions::from_mode(*mode);
-                fs::set_permissions(dest_path.as_path(), permissions)
+            if !dest.is_dir() {
+                fs::create_dir_all(dest_path.as_path())
+                    .with_context(|| format!("Failed to create directory: {dest_path:?}"))?;
+            }
+
+            // Set permissions, unless the existing directory already has them
+            if let Some(mode) = mode
+                && (!dest.is_dir() || mode_differs(Some(*mode), dest))
+            {
+                guisu_engine::system::set_permissions(dest_path.as_path(), *mode)
                     .with_context(|| format!("Failed to set permissions: {dest_path:?}"))?;
             }
 
@@ -1067,48 +2885,37 @@ fn apply_target_entry(
         }
 
         TargetEntry::Symlink { target, .. } => {
-            // Ensure parent directory exists
-            if let Some(parent) = dest_path.as_path().parent() {
-                fs::create_dir_all(parent).with_context(|| {
-                    format!("Failed to create parent directory: {}", parent.display())
-                })?;
-            }
-
-            // Remove existing symlink/file if it exists
-            if dest_path.as_path().exists() || dest_path.as_path().is_symlink() {
-                if dest_path.as_path().is_dir() && !dest_path.as_path().is_symlink() {
+            if dest.is_present() {
+                // Remove existing symlink/file
+                if dest.is_dir() && !dest.is_symlink() {
                     fs::remove_dir_all(dest_path.as_path()).with_context(|| {
                         format!("Failed to remove existing directory: {dest_path:?}")
                     })?;
+                } else if dest.is_symlink() {
+                    guisu_engine::system::remove_symlink(dest_path.as_path()).with_context(
+                        || format!("Failed to remove existing symlink: {dest_path:?}"),
+                    )?;
                 } else {
                     fs::remove_file(dest_path.as_path()).with_context(|| {
                         format!("Failed to remove existing file/symlink: {dest_path:?}")
                     })?;
                 }
+            } else {
+                // Ensure parent directory exists
+                create_parent_dir(dest_path)?;
             }
 
-            // Create symlink
-            #[cfg(unix)]
-            {
-                use std::os::unix::fs::symlink;
-                symlink(target, dest_path.as_path())
-                    .with_context(|| format!("Failed to create symlink: {dest_path:?}"))?;
-            }
-
-            #[cfg(windows)]
-            {
-                use std::os::windows::fs::symlink_file;
-                symlink_file(target, dest_path.as_path())
-                    .with_context(|| format!("Failed to create symlink: {:?}", dest_path))?;
-            }
+            // Directory links fall back to junctions on Windows without developer mode
+            guisu_engine::system::create_symlink(target, dest_path.as_path())
+                .with_context(|| format!("Failed to create symlink: {dest_path:?}"))?;
 
             Ok(())
         }
 
         TargetEntry::Remove { .. } => {
-            // Handle removal entries (not used in apply, but included for completeness)
-            if dest_path.as_path().exists() {
-                if dest_path.as_path().is_dir() {
+            // Unmanaged entries of exact directories
+            if dest.exists() {
+                if dest.is_dir() {
                     fs::remove_dir_all(dest_path.as_path())
                         .with_context(|| format!("Failed to remove directory: {dest_path:?}"))?;
                 } else {
@@ -1120,13 +2927,129 @@ fn apply_target_entry(
         }
     }
 }
+
+/// Write a file destination with the content `write` produces
+///
+/// Creates missing parent directories, sets the permissions, and keeps
+/// extended attributes if asked to. The file is replaced atomically through a
+/// temporary file (see [`atomic`]); destinations with other hard links are
+/// overwritten in place so the links keep sharing the content, and symlinked
+/// destinations have the file they point to replaced.
+fn write_file(
+    ctx: &EntryContext<'_>,
+    dest: &DestProbe,
+    mode: Option<u32>,
+    write: impl FnOnce(&mut fs::File) -> Result<()>,
+) -> Result<()> {
+    let dest_path = dest.path();
+
+    // Ensure parent directory exists
+    if !dest.is_present() {
+        create_parent_dir(dest_path)?;
+    }
+
+    // Capture attributes before writing, as locked files must be unlocked first
+    let preserved = unlock_preserved_attrs(ctx, dest)?;
+
+    #[cfg(unix)]
+    let mode = Some(written_mode(mode, dest));
+
+    let path = if dest.is_symlink() && dest.exists() {
+        fs::canonicalize(dest_path.as_path())
+            .with_context(|| format!("Failed to resolve symlink: {dest_path:?}"))?
+    } else {
+        dest_path.as_path().to_path_buf()
+    };
+    if dest.hard_links() > 1 {
+        atomic::overwrite(&path, mode, ctx.fsync, write)?;
+    } else {
+        atomic::replace(&path, mode, ctx.fsync, write)?;
+    }
+
+    if let Some(attrs) = preserved {
+        attrs
+            .restore(dest_path.as_path())
+            .with_context(|| format!("Failed to restore extended attributes: {dest_path:?}"))?;
+    }
+
+    Ok(())
+}
+
+/// Permissions a file with source `mode` is written over `dest` with
+///
+/// - If source has mode, use it (source is authoritative)
+/// - Otherwise, preserve existing permissions if file existed
+/// - Default to 0o600 (owner read/write only) for security
+fn written_mode(mode: Option<u32>, dest: &DestProbe) -> u32 {
+    mode.or(dest.mode().map(|mode| mode & PERM_MASK))
+        .unwrap_or(DEFAULT_SECURE_MODE)
+}
+
+/// Write a single target entry over `dest` through `sudo`
+///
+/// Like [`write_target_entry`], destinations of the wrong kind must have been
+/// backed up already.
+fn sudo_write_target_entry(ctx: &EntryContext<'_>, dest: &DestProbe) -> Result<()> {
+    let dest_path = dest.path().as_path();
+    match ctx.entry {
+        TargetEntry::File {
+            content,
+            content_hash,
+            mode,
+            ..
+        } => {
+            let (final_content, _) = ctx.decryptor.decrypt(content, content_hash)?;
+            let mode = mode.or_else(|| dest.mode()).unwrap_or(DEFAULT_SECURE_MODE);
+            sudo::write_file(dest_path, &final_content, mode)
+        }
+        TargetEntry::LargeFile {
+            source,
+            attributes,
+            mode,
+            ..
+        } => {
+            let mode = mode.or_else(|| dest.mode()).unwrap_or(DEFAULT_SECURE_MODE);
+            sudo::write_file_with(dest_path, mode, |staged| {
+                ctx.decryptor.stream(source, *attributes, staged)
+            })
+        }
+        TargetEntry::Directory { mode, .. } => {
+            if !dest.is_dir() {
+                sudo::create_dir(dest_path)?;
+            }
+            match mode {
+                Some(mode) if mode_differs(Some(*mode), dest) || !dest.is_dir() => {
+                    sudo::set_mode(dest_path, *mode)
+                }
+                _ => Ok(()),
+            }
+        }
+        TargetEntry::Symlink { target, .. } => sudo::symlink(target, dest_path),
+        TargetEntry::Remove { .. } => {
+            if dest.exists() {
+                sudo::remove(dest_path)?;
+            }
+            Ok(())
+        }
+    }
+}
+
+/// Create the parent directory of a destination that does not exist yet
+fn create_parent_dir(dest_path: &AbsPath) -> Result<()> {
+    if let Some(parent) = dest_path.as_path().parent() {
+        fs::create_dir_all(parent)
+            .with_context(|| format!("Failed to create parent directory: {}", parent.display()))?;
+    }
+    Ok(())
+}
 impl ApplyStats {
     fn record_success(&self, entry: &TargetEntry) {
+        self.record_change(entry.path().to_string());
         match entry {
-            TargetEntry::File { .. } => self.inc_files(),
+            TargetEntry::File { .. } | TargetEntry::LargeFile { .. } => self.inc_files(),
             TargetEntry::Directory { .. } => self.inc_directories(),
             TargetEntry::Symlink { .. } => self.inc_symlinks(),
-            TargetEntry::Remove { .. } => {}
+            TargetEntry::Remove { .. } => self.inc_removed(),
         }
     }
 
@@ -1134,6 +3057,12 @@ impl ApplyStats {
         self.inc_failed();
     }
 
+    fn record_backup(&self, entry: &TargetEntry, backup: Option<PathBuf>) {
+        if let Some(backup) = backup {
+            self.record_type_backup(entry.path().to_string(), backup.display().to_string());
+        }
+    }
+
     fn record_dry_run(&self, entry: &TargetEntry) {
         // Same as success for counting purposes
         self.record_success(entry);
@@ -1141,7 +3070,7 @@ impl ApplyStats {
 }
 
 /// Print a dry-run entry
-fn print_dry_run_entry(entry: &TargetEntry, use_nerd_fonts: bool) {
+fn print_dry_run_entry(ctx: &EntryContext<'_>, use_nerd_fonts: bool) {
     use lscolors::{LsColors, Style};
     use std::sync::atomic::{AtomicBool, Ordering};
 
@@ -1152,12 +3081,14 @@ fn print_dry_run_entry(entry: &TargetEntry, use_nerd_fonts: bool) {
     }
 
     let lscolors = LsColors::from_env().unwrap_or_default();
-    let path = entry.path();
-    let display_path = format!("~/{path}");
+    let entry = ctx.entry;
+    let display_path = ctx.display_path();
 
     // Get file icon
     let (is_directory, is_symlink) = match entry {
-        TargetEntry::File { .. } | TargetEntry::Remove { .. } => (false, false),
+        TargetEntry::File { .. } | TargetEntry::LargeFile { .. } | TargetEntry::Remove { .. } => {
+            (false, false)
+        }
         TargetEntry::Directory { .. } => (true, false),
         TargetEntry::Symlink { .. } => (false, true),
     };
@@ -1178,20 +3109,26 @@ fn print_dry_run_entry(entry: &TargetEntry, use_nerd_fonts: bool) {
     let styled_icon = file_style.paint(icon);
     let styled_path = file_style.paint(&display_path);
 
-    println!("  {styled_icon} {styled_path}");
+    if entry.is_removal() {
+        println!("  {} {styled_icon} {styled_path}", "-".bright_red());
+    } else {
+        println!("  {styled_icon} {styled_path}");
+    }
 }
 
 /// Print a successful entry
-fn print_success_entry(entry: &TargetEntry, use_nerd_fonts: bool) {
+fn print_success_entry(ctx: &EntryContext<'_>, use_nerd_fonts: bool) {
     use lscolors::{LsColors, Style};
 
     let lscolors = LsColors::from_env().unwrap_or_default();
-    let path = entry.path();
-    let display_path = format!("~/{path}");
+    let entry = ctx.entry;
+    let display_path = ctx.display_path();
 
     // Get file icon
     let (is_directory, is_symlink) = match entry {
-        TargetEntry::File { .. } | TargetEntry::Remove { .. } => (false, false),
+        TargetEntry::File { .. } | TargetEntry::LargeFile { .. } | TargetEntry::Remove { .. } => {
+            (false, false)
+        }
         TargetEntry::Directory { .. } => (true, false),
         TargetEntry::Symlink { .. } => (false, true),
     };
@@ -1212,20 +3149,26 @@ fn print_success_entry(entry: &TargetEntry, use_nerd_fonts: bool) {
     let styled_icon = file_style.paint(icon);
     let styled_path = file_style.paint(&display_path);
 
-    println!("  {} {} {}", "✓".bright_green(), styled_icon, styled_path);
+    if entry.is_removal() {
+        println!("  {} {styled_icon} {styled_path}", "-".bright_red());
+    } else {
+        println!("  {} {styled_icon} {styled_path}", "✓".bright_green());
+    }
 }
 
 /// Print an error entry
-fn print_error_entry(entry: &TargetEntry, error: &anyhow::Error, use_nerd_fonts: bool) {
+fn print_error_entry(ctx: &EntryContext<'_>, error: &anyhow::Error, use_nerd_fonts: bool) {
     use lscolors::{LsColors, Style};
 
     let lscolors = LsColors::from_env().unwrap_or_default();
-    let path = entry.path();
-    let display_path = format!("~/{path}");
+    let entry = ctx.entry;
+    let display_path = ctx.display_path();
 
     // Get file icon
     let (is_directory, is_symlink) = match entry {
-        TargetEntry::File { .. } | TargetEntry::Remove { .. } => (false, false),
+        TargetEntry::File { .. } | TargetEntry::LargeFile { .. } | TargetEntry::Remove { .. } => {
+            (false, false)
+        }
         TargetEntry::Directory { .. } => (true, false),
         TargetEntry::Symlink { .. } => (false, true),
     };
@@ -1264,27 +3207,27 @@ fn print_error_entry(entry: &TargetEntry, error: &anyhow::Error, use_nerd_fonts:
 /// This indicates potential conflict where both local and source changes exist.
 fn detect_config_drift(
     db: &guisu_engine::state::RedbPersistentState,
-    entries: &[&TargetEntry],
-    dest_abs: &AbsPath,
+    contexts: &[EntryContext<'_>],
 ) -> Vec<String> {
     // Parallel processing of drift detection (3x blake3 hash per file = CPU-intensive)
-    entries
+    contexts
         .par_iter()
-        .filter_map(|entry| {
-            // Only check files
-            let TargetEntry::File {
-                content: target_content,
-                content_hash: target_hash,
-                ..
-            } = entry
-            else {
-                return None;
-            };
+        .filter_map(|ctx| {
+            let entry = ctx.entry;
 
-            let dest_path = dest_abs.join(entry.path());
+            // Only check files; large files are compared by hash alone
+            let (target_hash, target_content) = match entry {
+                TargetEntry::File {
+                    content,
+                    content_hash,
+                    ..
+                } => (content_hash, Some(content)),
+                TargetEntry::LargeFile { content_hash, .. } => (content_hash, None),
+                _ => return None,
+            };
 
             // Skip if destination doesn't exist
-            if !dest_path.as_path().exists() {
+            if !ctx.dest.exists() {
                 return None;
             }
 
@@ -1298,16 +3241,29 @@ fn detect_config_drift(
                 }
             };
 
-            let actual_content = match fs::read(dest_path.as_path()) {
-                Ok(content) => content,
+            let actual = match target_content {
+                Some(_) => ctx
+                    .decryptor
+                    .timed(Phase::Io, || ctx.dest.content())
+                    .map(|content| {
+                        let hash = ctx
+                            .decryptor
+                            .timed(Phase::Hash, || guisu_engine::hash::hash_content(content));
+                        (hash, Some(content))
+                    }),
+                None => ctx
+                    .decryptor
+                    .timed(Phase::Hash, || ctx.dest.content_hash())
+                    .map(|hash| (hash, None)),
+            };
+            let (actual_hash, actual_content) = match actual {
+                Ok(actual) => actual,
                 Err(e) => {
                     warn!(path = %path_str, error = %e, "Failed to read destination file");
                     return None;
                 }
             };
 
-            let actual_hash = guisu_engine::hash::hash_content(&actual_content);
-
             // Check for drift:
             // 1. actual != last_written (user modified)
             // 2. target != last_written (source updated)
@@ -1316,7 +3272,10 @@ fn detect_config_drift(
             // Use constant-time comparison for hashes to prevent timing side-channel attacks
             let user_modified = !bool::from(actual_hash.ct_eq(&last_written_state.content_hash));
             let source_updated = !bool::from(target_hash.ct_eq(&last_written_state.content_hash));
-            let contents_differ = target_content != &actual_content;
+            let contents_differ = match (target_content, actual_content) {
+                (Some(target), Some(actual)) => &**target != actual,
+                _ => *target_hash != actual_hash,
+            };
 
             if user_modified && source_updated && contents_differ {
                 Some(path_str.to_string())
@@ -1343,7 +3302,7 @@ fn detect_config_drift(
 /// - If no identities are available, returns the original content (not an error)
 /// - If content is binary (non-UTF-8), returns the original content (not an error)
 /// - If no age: patterns are found, returns the original content (not an error)
-fn decrypt_inline_age_values(
+pub(crate) fn decrypt_inline_age_values(
     content: &[u8],
     identities: &[guisu_crypto::Identity],
     fail_on_decrypt_error: bool,
@@ -1397,6 +3356,7 @@ fn decrypt_inline_age_values(
 mod tests {
     #![allow(clippy::unwrap_used, clippy::panic)]
     use super::*;
+    use crate::common::EntryType;
 
     // Tests for EntryType
 
@@ -1554,9 +3514,17 @@ mod tests {
             files: vec![],
             dry_run: false,
             force: false,
+            merge: false,
+            prune: false,
             interactive: false,
             include: vec![],
             exclude: vec![],
+            jobs: None,
+            backup: false,
+            refresh_externals: false,
+            one_shot: None,
+            source_ref: None,
+            timings: false,
         };
 
         assert!(cmd.files.is_empty());
@@ -1573,9 +3541,17 @@ mod tests {
             files: vec![PathBuf::from("file1.txt"), PathBuf::from("file2.txt")],
             dry_run: false,
             force: false,
+            merge: false,
+            prune: false,
             interactive: false,
             include: vec![],
             exclude: vec![],
+            jobs: None,
+            backup: false,
+            refresh_externals: false,
+            one_shot: None,
+            source_ref: None,
+            timings: false,
         };
 
         assert_eq!(cmd.files.len(), 2);
@@ -1588,9 +3564,17 @@ mod tests {
             files: vec![],
             dry_run: true,
             force: false,
+            merge: false,
+            prune: false,
             interactive: false,
             include: vec![],
             exclude: vec![],
+            jobs: None,
+            backup: false,
+            refresh_externals: false,
+            one_shot: None,
+            source_ref: None,
+            timings: false,
         };
 
         assert!(cmd.dry_run);
@@ -1602,9 +3586,17 @@ mod tests {
             files: vec![],
             dry_run: false,
             force: true,
+            merge: false,
+            prune: false,
             interactive: false,
             include: vec![],
             exclude: vec![],
+            jobs: None,
+            backup: false,
+            refresh_externals: false,
+            one_shot: None,
+            source_ref: None,
+            timings: false,
         };
 
         assert!(cmd.force);
@@ -1616,9 +3608,17 @@ mod tests {
             files: vec![],
             dry_run: false,
             force: false,
+            merge: false,
+            prune: false,
             interactive: true,
             include: vec![],
             exclude: vec![],
+            jobs: None,
+            backup: false,
+            refresh_externals: false,
+            one_shot: None,
+            source_ref: None,
+            timings: false,
         };
 
         assert!(cmd.interactive);
@@ -1630,9 +3630,17 @@ mod tests {
             files: vec![],
             dry_run: false,
             force: false,
+            merge: false,
+            prune: false,
             interactive: false,
             include: vec!["files".to_string(), "dirs".to_string()],
             exclude: vec!["encrypted".to_string()],
+            jobs: None,
+            backup: false,
+            refresh_externals: false,
+            one_shot: None,
+            source_ref: None,
+            timings: false,
         };
 
         assert_eq!(cmd.include.len(), 2);
@@ -1647,9 +3655,17 @@ mod tests {
             files: vec![PathBuf::from("test.txt")],
             dry_run: true,
             force: false,
+            merge: false,
+            prune: false,
             interactive: false,
             include: vec!["files".to_string()],
             exclude: vec![],
+            jobs: None,
+            backup: false,
+            refresh_externals: false,
+            one_shot: None,
+            source_ref: None,
+            timings: false,
         };
 
         let cloned = cmd.clone();
@@ -1660,4 +3676,593 @@ mod tests {
         assert_eq!(cloned.include, cmd.include);
         assert_eq!(cloned.exclude, cmd.exclude);
     }
+
+    // Tests for the per-entry destination probe
+
+    #[cfg(unix)]
+    #[test]
+    fn test_needs_update_and_apply_reuse_probe() {
+        use guisu_core::path::RelPath;
+        use std::os::unix::fs::PermissionsExt;
+
+        let temp = tempfile::TempDir::new().unwrap();
+        let dest_abs = AbsPath::new(fs::canonicalize(temp.path()).unwrap()).unwrap();
+        let content = b"set x\n".to_vec();
+        let entry = TargetEntry::File {
+            path: RelPath::new(PathBuf::from(".config/app/rc")).unwrap(),
+            content_hash: guisu_engine::hash::hash_content(&content),
+            content: content.into(),
+            mode: Some(0o640),
+        };
+        let decryptor = InlineDecryptor::new(&[], true);
+
+        // Missing destination: apply creates the parent directory
+        let ctx = EntryContext::new(&entry, dest_abs.join(entry.path()), &decryptor);
+        assert!(needs_update(&ctx).unwrap());
+        apply_target_entry(&ctx).unwrap();
+
+        let written = temp.path().join(".config/app/rc");
+        assert_eq!(fs::read(&written).unwrap(), b"set x\n");
+        assert!(
+            !needs_update(&EntryContext::new(
+                &entry,
+                dest_abs.join(entry.path()),
+                &decryptor
+            ))
+            .unwrap()
+        );
+
+        // Same content, different mode
+        fs::set_permissions(&written, fs::Permissions::from_mode(0o600)).unwrap();
+        let ctx = EntryContext::new(&entry, dest_abs.join(entry.path()), &decryptor);
+        assert!(needs_update(&ctx).unwrap());
+
+        // Content matches, so this is not a content change
+        let db =
+            guisu_engine::state::RedbPersistentState::new(temp.path().join("state.db")).unwrap();
+        assert_eq!(ctx.change_type(&db).unwrap(), None);
+    }
+
+    #[test]
+    fn test_snapshot_destinations_saves_changed_files() {
+        use guisu_core::path::RelPath;
+
+        let temp = tempfile::TempDir::new().unwrap();
+        let dest_abs = AbsPath::new(fs::canonicalize(temp.path()).unwrap()).unwrap();
+        let db =
+            guisu_engine::state::RedbPersistentState::new(temp.path().join("state.db")).unwrap();
+        let file = |path: &str, content: &[u8]| TargetEntry::File {
+            path: RelPath::new(PathBuf::from(path)).unwrap(),
+            content_hash: guisu_engine::hash::hash_content(content),
+            content: content.to_vec().into(),
+            mode: None,
+        };
+        fs::write(temp.path().join(".changed"), "old").unwrap();
+        fs::write(temp.path().join(".same"), "same").unwrap();
+        let entries = [
+            file(".changed", b"new"),
+            file(".same", b"same"),
+            file(".created", b"new"),
+        ];
+        let decryptor = InlineDecryptor::new(&[], true);
+        let contexts: Vec<EntryContext> = entries
+            .iter()
+            .map(|entry| EntryContext::new(entry, dest_abs.join(entry.path()), &decryptor))
+            .collect();
+
+        let stamp = guisu_engine::clock::StateClock::fixed(1_700_000_000).begin_run();
+        snapshot_destinations(&db, &contexts, false, &stamp, 5).unwrap();
+
+        let snapshots = guisu_engine::database::get_apply_snapshots(&db).unwrap();
+        let files = &snapshots[0].1.files;
+        let paths: Vec<&str> = files.iter().map(|f| f.path.as_str()).collect();
+        assert_eq!(paths, vec![".changed", ".created"]);
+        let saved =
+            guisu_engine::database::get_snapshot_content(&db, &files[0].content_hash.unwrap())
+                .unwrap()
+                .unwrap();
+        assert_eq!(saved, b"old");
+        assert_eq!(files[1].content_hash, None);
+    }
+
+    #[test]
+    fn test_backup_local_changes() {
+        use guisu_core::path::RelPath;
+
+        let temp = tempfile::TempDir::new().unwrap();
+        let dest_abs = AbsPath::new(fs::canonicalize(temp.path()).unwrap()).unwrap();
+        let db =
+            guisu_engine::state::RedbPersistentState::new(temp.path().join("state.db")).unwrap();
+        let stamp = guisu_engine::clock::StateClock::fixed(1_700_000_000).begin_run();
+        let file = |path: &str| TargetEntry::File {
+            path: RelPath::new(PathBuf::from(path)).unwrap(),
+            content_hash: guisu_engine::hash::hash_content(b"new"),
+            content: b"new".to_vec().into(),
+            mode: None,
+        };
+        // .vimrc was changed locally since it was applied, .zshrc was not
+        for path in [".vimrc", ".zshrc"] {
+            guisu_engine::database::save_entry_state(
+                &db,
+                path,
+                b"applied",
+                None,
+                MergeBase::Keep,
+                &stamp,
+            )
+            .unwrap();
+        }
+        fs::write(temp.path().join(".vimrc"), "local").unwrap();
+        fs::write(temp.path().join(".zshrc"), "applied").unwrap();
+        let (vimrc, zshrc) = (file(".vimrc"), file(".zshrc"));
+        let decryptor = InlineDecryptor::new(&[], true);
+
+        let mut config = guisu_config::ApplyConfig::default();
+        let suffix = LocalBackup::for_run(true, &config, &stamp).unwrap();
+        let ctx = EntryContext::new(&vimrc, dest_abs.join(vimrc.path()), &decryptor)
+            .with_backup(Some(&suffix));
+        let backup = backup_local_changes(&db, &ctx).unwrap().unwrap();
+        assert_eq!(backup, dest_abs.as_path().join(".vimrc.bak"));
+        assert_eq!(fs::read(&backup).unwrap(), b"local");
+        let ctx = EntryContext::new(&zshrc, dest_abs.join(zshrc.path()), &decryptor)
+            .with_backup(Some(&suffix));
+        assert_eq!(backup_local_changes(&db, &ctx).unwrap(), None);
+
+        config.backup_dir = Some(temp.path().join("backups"));
+        let dir = LocalBackup::for_run(true, &config, &stamp).unwrap();
+        let ctx = EntryContext::new(&vimrc, dest_abs.join(vimrc.path()), &decryptor)
+            .with_backup(Some(&dir));
+        let backup = backup_local_changes(&db, &ctx).unwrap().unwrap();
+        assert!(backup.starts_with(temp.path().join("backups")));
+        assert!(backup.ends_with(".vimrc"));
+        assert_eq!(fs::read(&backup).unwrap(), b"local");
+
+        // Backups are off unless enabled
+        assert!(LocalBackup::for_run(false, &config, &stamp).is_none());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_secrets_never_kept_as_merge_base() {
+        use crate::common::testing::{TestWorkspace, write};
+        use guisu_engine::state::{BASE_CONTENT_BUCKET, PersistentState};
+        use std::os::unix::fs::PermissionsExt;
+
+        let identity = guisu_crypto::Identity::generate();
+        let keys = tempfile::TempDir::new().unwrap();
+        let key = keys.path().join("key.txt");
+        guisu_crypto::IdentityFile::save(&key, std::slice::from_ref(&identity)).unwrap();
+        let mut config = guisu_config::Config::default();
+        config.age.identity = Some(key);
+        let workspace = TestWorkspace::new(config);
+
+        let recipients = [identity.to_public()];
+        let inline = guisu_crypto::encrypt_inline("hunter2", &recipients).unwrap();
+        let sources = [
+            (
+                "secret.age",
+                guisu_crypto::encrypt(b"hunter2\n", &recipients).unwrap(),
+            ),
+            (".netrc", format!("password {inline}\n").into_bytes()),
+            (".zshrc", b"export A=1\n".to_vec()),
+            (".profile", b"export B=1\n".to_vec()),
+        ];
+        for (path, content) in &sources {
+            write(&workspace.source(path), content);
+            fs::set_permissions(workspace.source(path), fs::Permissions::from_mode(0o644)).unwrap();
+        }
+        // Existing group-readable destinations keep their mode; .profile is new
+        for path in ["secret", ".netrc", ".zshrc"] {
+            write(&workspace.dest(path), "old\n");
+            fs::set_permissions(workspace.dest(path), fs::Permissions::from_mode(0o644)).unwrap();
+        }
+
+        let cmd = ApplyCommand {
+            files: vec![],
+            dry_run: false,
+            force: true,
+            merge: false,
+            prune: false,
+            interactive: false,
+            include: vec![],
+            exclude: vec![],
+            jobs: None,
+            backup: false,
+            refresh_externals: false,
+            one_shot: None,
+            source_ref: None,
+            timings: false,
+        };
+        let report = cmd.execute_unattended(&workspace.context).unwrap();
+        assert_eq!(report.applied.len(), 4);
+        assert_eq!(fs::read(workspace.dest("secret")).unwrap(), b"hunter2\n");
+
+        let base = |path: &str| {
+            workspace
+                .context
+                .database()
+                .get(BASE_CONTENT_BUCKET, path.as_bytes())
+                .unwrap()
+        };
+        assert_eq!(base("secret"), None);
+        assert_eq!(base(".netrc"), None);
+        // Written 0600, as it had no destination to take a mode from
+        assert_eq!(base(".profile"), None);
+        assert_eq!(base(".zshrc"), Some(b"export A=1\n".to_vec()));
+    }
+
+    #[test]
+    fn test_render_cache_invalidated_by_script_functions() {
+        use crate::common::testing::{TestWorkspace, write};
+
+        let workspace = TestWorkspace::new(guisu_config::Config::default());
+        let function = workspace.root.join("source/.guisu/functions/greet.rhai");
+        write(&function, r#"fn greet() { "hello" }"#);
+        write(&workspace.source(".greeting.j2"), "{{ greet() }}\n");
+
+        let cmd = ApplyCommand {
+            files: vec![],
+            dry_run: false,
+            force: true,
+            merge: false,
+            prune: false,
+            interactive: false,
+            include: vec![],
+            exclude: vec![],
+            jobs: None,
+            backup: false,
+            refresh_externals: false,
+            one_shot: None,
+            source_ref: None,
+            timings: false,
+        };
+        cmd.execute_unattended(&workspace.context).unwrap();
+        assert_eq!(
+            fs::read_to_string(workspace.dest(".greeting")).unwrap(),
+            "hello\n"
+        );
+
+        write(&function, r#"fn greet() { "goodbye" }"#);
+        cmd.execute_unattended(&workspace.context).unwrap();
+        assert_eq!(
+            fs::read_to_string(workspace.dest(".greeting")).unwrap(),
+            "goodbye\n"
+        );
+    }
+
+    // Tests for the shared inline decryption
+
+    #[test]
+    fn test_inline_decryptor_shares_identical_contents() {
+        use guisu_engine::pool::ContentPool;
+
+        let identity = guisu_crypto::Identity::generate();
+        let secret = guisu_crypto::encrypt_inline("hunter2", &[identity.to_public()]).unwrap();
+        let rendered = format!("password: {secret}\n");
+
+        let pool = ContentPool::new();
+        let (first, first_hash) = pool.intern(rendered.clone().into_bytes());
+        let (second, second_hash) = pool.intern(rendered.into_bytes());
+
+        let identities = [identity];
+        let decryptor = InlineDecryptor::new(&identities, true);
+        let (decrypted, hash) = decryptor.decrypt(&first, &first_hash).unwrap();
+        assert_eq!(&*decrypted, b"password: hunter2\n");
+        assert_eq!(
+            hash,
+            guisu_engine::hash::hash_content(b"password: hunter2\n")
+        );
+
+        // The second entry reuses the first decryption
+        let (again, again_hash) = decryptor.decrypt(&second, &second_hash).unwrap();
+        assert!(Arc::ptr_eq(&decrypted, &again));
+        assert_eq!(hash, again_hash);
+
+        // Content without inline values is passed through with its rendered hash
+        let (plain, plain_hash) = pool.intern(b"password: plain\n".to_vec());
+        let (passed, passed_hash) = decryptor.decrypt(&plain, &plain_hash).unwrap();
+        assert!(Arc::ptr_eq(&plain, &passed));
+        assert_eq!(plain_hash, passed_hash);
+    }
+
+    #[test]
+    fn test_parallel_apply_on_bounded_pool() {
+        use guisu_core::path::RelPath;
+
+        let temp = tempfile::TempDir::new().unwrap();
+        let dest_abs = AbsPath::new(fs::canonicalize(temp.path()).unwrap()).unwrap();
+        let entries: Vec<TargetEntry> = (0..40)
+            .map(|i| {
+                let content = format!("file {i}\n").into_bytes();
+                TargetEntry::File {
+                    path: RelPath::new(PathBuf::from(format!("dir{}/file{i}", i % 4))).unwrap(),
+                    content_hash: guisu_engine::hash::hash_content(&content),
+                    content: content.into(),
+                    mode: None,
+                }
+            })
+            .collect();
+        let decryptor = InlineDecryptor::new(&[], true);
+        let db =
+            guisu_engine::state::RedbPersistentState::new(temp.path().join("state.db")).unwrap();
+        let stats = ApplyStats::new();
+        let stamp = guisu_engine::clock::StateClock::fixed(1_700_000_000).begin_run();
+
+        let workers = WorkerPool::new(NonZeroUsize::new(2)).unwrap();
+        workers
+            .install(|| {
+                let contexts: Vec<EntryContext> = entries
+                    .par_iter()
+                    .map(|entry| EntryContext::new(entry, dest_abs.join(entry.path()), &decryptor))
+                    .collect();
+                process_entries_parallel(&db, &contexts, &stats, false, false, &stamp, false)
+            })
+            .unwrap();
+
+        assert_eq!(stats.failed(), 0);
+        for i in 0..40 {
+            let path = format!("dir{}/file{i}", i % 4);
+            assert_eq!(
+                fs::read_to_string(temp.path().join(&path)).unwrap(),
+                format!("file {i}\n")
+            );
+            assert!(
+                guisu_engine::database::get_entry_state(&db, &path)
+                    .unwrap()
+                    .is_some()
+            );
+        }
+    }
+
+    // Tests for destination type conflicts
+
+    #[test]
+    fn test_file_over_directory_is_backed_up() {
+        use guisu_core::path::RelPath;
+
+        let temp = tempfile::TempDir::new().unwrap();
+        let dest_abs = AbsPath::new(fs::canonicalize(temp.path()).unwrap()).unwrap();
+        fs::create_dir_all(temp.path().join(".vimrc/plugins")).unwrap();
+        fs::create_dir_all(temp.path().join(".vimrc.guisu-backup")).unwrap();
+
+        let content = b"set nu\n".to_vec();
+        let entry = TargetEntry::File {
+            path: RelPath::new(PathBuf::from(".vimrc")).unwrap(),
+            content_hash: guisu_engine::hash::hash_content(&content),
+            content: content.into(),
+            mode: None,
+        };
+        let decryptor = InlineDecryptor::new(&[], true);
+        let db =
+            guisu_engine::state::RedbPersistentState::new(temp.path().join("state.db")).unwrap();
+
+        let ctx = EntryContext::new(&entry, dest_abs.join(entry.path()), &decryptor);
+        assert_eq!(
+            ctx.change_type(&db).unwrap(),
+            Some(ChangeType::TypeMismatch {
+                expected: EntryKind::File,
+                found: EntryKind::Directory,
+            })
+        );
+
+        // The taken backup name is skipped
+        let backup = apply_target_entry(&ctx).unwrap().unwrap();
+        assert_eq!(backup, temp.path().join(".vimrc.guisu-backup.1"));
+        assert!(backup.join("plugins").is_dir());
+        assert_eq!(fs::read(temp.path().join(".vimrc")).unwrap(), b"set nu\n");
+        assert!(
+            EntryContext::new(&entry, dest_abs.join(entry.path()), &decryptor)
+                .type_mismatch()
+                .is_none()
+        );
+    }
+
+    #[test]
+    fn test_directory_over_file_is_type_mismatch() {
+        use guisu_core::path::RelPath;
+
+        let temp = tempfile::TempDir::new().unwrap();
+        let dest_abs = AbsPath::new(fs::canonicalize(temp.path()).unwrap()).unwrap();
+        fs::write(temp.path().join(".config"), "not a directory").unwrap();
+
+        let entry = TargetEntry::Directory {
+            path: RelPath::new(PathBuf::from(".config")).unwrap(),
+            mode: None,
+        };
+        let decryptor = InlineDecryptor::new(&[], true);
+        let ctx = EntryContext::new(&entry, dest_abs.join(entry.path()), &decryptor);
+        assert_eq!(
+            ctx.type_mismatch(),
+            Some(ChangeType::TypeMismatch {
+                expected: EntryKind::Directory,
+                found: EntryKind::File,
+            })
+        );
+
+        let backup = apply_target_entry(&ctx).unwrap().unwrap();
+        assert_eq!(fs::read(backup).unwrap(), b"not a directory");
+        assert!(temp.path().join(".config").is_dir());
+    }
+
+    #[test]
+    fn test_symlink_mode_links_plain_files() {
+        use guisu_engine::content::{NoOpDecryptor, NoOpRenderer};
+        use guisu_engine::processor::ContentProcessor;
+        use guisu_engine::state::SourceState;
+
+        let temp = tempfile::TempDir::new().unwrap();
+        let root = fs::canonicalize(temp.path()).unwrap();
+        fs::write(root.join(".bashrc"), "export A=1\n").unwrap();
+        fs::write(root.join(".profile.j2"), "export B=1\n").unwrap();
+        fs::write(root.join(".netrc"), "password: age:abc\n").unwrap();
+
+        let source = SourceState::read(AbsPath::new(root.clone()).unwrap()).unwrap();
+        let processor = ContentProcessor::new(NoOpDecryptor, NoOpRenderer);
+        let target_state = TargetState::from_source_with_mode(
+            &source,
+            &processor,
+            &serde_json::json!({}),
+            guisu_config::ApplyMode::Symlink,
+        )
+        .unwrap();
+
+        let mut kinds: Vec<_> = target_state
+            .entries()
+            .map(|entry| match entry {
+                TargetEntry::Symlink { path, target } => (path.to_string(), Some(target.clone())),
+                other => (other.path().to_string(), None),
+            })
+            .collect();
+        kinds.sort();
+        assert_eq!(
+            kinds,
+            vec![
+                (".bashrc".to_string(), Some(root.join(".bashrc"))),
+                (".netrc".to_string(), None),
+                (".profile".to_string(), None),
+            ]
+        );
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_mode_and_ownership_prefixes() {
+        use guisu_core::path::RelPath;
+        use guisu_engine::content::{NoOpDecryptor, NoOpRenderer};
+        use guisu_engine::processor::ContentProcessor;
+        use std::os::unix::fs::MetadataExt;
+
+        let temp = tempfile::TempDir::new().unwrap();
+        let root = fs::canonicalize(temp.path()).unwrap();
+        fs::write(root.join("owner_0_group_0_mode_0640_app.conf"), "x=1\n").unwrap();
+
+        let source = SourceState::read(AbsPath::new(root.clone()).unwrap()).unwrap();
+        let processor = ContentProcessor::new(NoOpDecryptor, NoOpRenderer);
+        let target_state =
+            TargetState::from_source(&source, &processor, &serde_json::json!({})).unwrap();
+
+        let path = RelPath::new(PathBuf::from("app.conf")).unwrap();
+        assert!(matches!(
+            target_state.get(&path),
+            Some(TargetEntry::File {
+                mode: Some(0o640),
+                ..
+            })
+        ));
+        let ownership = target_state.ownership(&path).unwrap();
+        assert_eq!(ownership.owner.as_deref(), Some("0"));
+
+        // Ownership that already matches is left alone, even without root
+        let dest = AbsPath::new(root.join("owner_0_group_0_mode_0640_app.conf")).unwrap();
+        let metadata = fs::metadata(dest.as_path()).unwrap();
+        let current = guisu_engine::attr::Ownership {
+            owner: Some(metadata.uid().to_string()),
+            group: Some(metadata.gid().to_string()),
+        };
+        set_ownership(&dest, &current).unwrap();
+
+        let unknown = guisu_engine::attr::Ownership {
+            owner: Some("no-such-user-guisu".to_string()),
+            group: None,
+        };
+        assert!(set_ownership(&dest, &unknown).is_err());
+    }
+
+    #[test]
+    fn test_prune_deleted_targets() {
+        let temp = tempfile::TempDir::new().unwrap();
+        let dest = temp.path().join("dest");
+        fs::create_dir_all(dest.join("empty")).unwrap();
+        fs::create_dir_all(dest.join("full")).unwrap();
+        fs::write(dest.join("full/keep"), "mine").unwrap();
+        fs::write(dest.join("clean"), "applied").unwrap();
+        fs::write(dest.join("edited"), "changed").unwrap();
+        let dest_abs = AbsPath::new(dest.clone()).unwrap();
+
+        let db = RedbPersistentState::new(temp.path().join("state.db")).unwrap();
+        let stamp = guisu_engine::clock::StateClock::fixed(1_700_000_000).begin_run();
+        guisu_engine::database::save_entry_states_batch(
+            &db,
+            &[
+                (
+                    "clean".to_string(),
+                    b"applied".to_vec(),
+                    None,
+                    MergeBase::Keep,
+                ),
+                (
+                    "edited".to_string(),
+                    b"applied".to_vec(),
+                    None,
+                    MergeBase::Keep,
+                ),
+            ],
+            &stamp,
+        )
+        .unwrap();
+        let deleted: Vec<DeletedTarget> = [
+            ("clean", ManagedKind::File),
+            ("edited", ManagedKind::File),
+            ("empty", ManagedKind::Directory),
+            ("full", ManagedKind::Directory),
+            ("gone", ManagedKind::Symlink),
+        ]
+        .into_iter()
+        .map(|(path, kind)| DeletedTarget {
+            path: RelPath::new(PathBuf::from(path)).unwrap(),
+            kind,
+        })
+        .collect();
+        let paths: Vec<(String, ManagedKind)> = deleted
+            .iter()
+            .map(|target| (target.path.to_string(), target.kind))
+            .collect();
+        guisu_engine::database::save_managed_paths(&db, &paths).unwrap();
+
+        let stats = ApplyStats::new();
+        prune_deleted_targets(
+            &db,
+            &deleted,
+            |path| dest_abs.join(path),
+            PruneMode::DryRun,
+            false,
+            &stats,
+        )
+        .unwrap();
+        assert_eq!(stats.removed(), 4);
+        assert!(dest.join("clean").exists());
+
+        let stats = ApplyStats::new();
+        prune_deleted_targets(
+            &db,
+            &deleted,
+            |path| dest_abs.join(path),
+            PruneMode::Prune,
+            false,
+            &stats,
+        )
+        .unwrap();
+        assert_eq!(stats.removed(), 2);
+        assert!(!dest.join("clean").exists());
+        assert!(!dest.join("empty").exists());
+        assert!(dest.join("edited").exists());
+        assert!(dest.join("full/keep").exists());
+
+        // Only the locally modified file is still offered for removal
+        let managed = guisu_engine::database::get_managed_paths(&db).unwrap();
+        assert_eq!(managed.keys().collect::<Vec<_>>(), ["edited"]);
+
+        let stats = ApplyStats::new();
+        prune_deleted_targets(
+            &db,
+            &deleted[1..2],
+            |path| dest_abs.join(path),
+            PruneMode::Prune,
+            true,
+            &stats,
+        )
+        .unwrap();
+        assert_eq!(stats.removed(), 1);
+        assert!(!dest.join("edited").exists());
+    }
 }
@@ -0,0 +1,519 @@
+//! Archive command implementation
+//!
+//! Render the target state into a tar.gz or zip archive, so the configuration
+//! can be copied to machines without guisu or inspected offline.
+
+use anyhow::{Context, Result};
+use clap::Args;
+use flate2::Compression;
+use flate2::write::GzEncoder;
+use guisu_engine::adapters::crypto::{CryptoDecryptorAdapter, IdentityHints};
+use guisu_engine::adapters::template::{TemplateRendererAdapter, template_context};
+use guisu_engine::entry::TargetEntry;
+use guisu_engine::processor::ContentProcessor;
+use guisu_engine::state::{SourceState, TargetState};
+use std::io::{self, IsTerminal, Seek, Write};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use tracing::info;
+use zip::write::{SimpleFileOptions, StreamWriter};
+
+use crate::cmd::apply::{add_externals, decrypt_inline_age_values};
+use crate::command::Command;
+use crate::common::{EntryFilter, RuntimeContext};
+
+/// Mode of archived files that have none of their own
+const DEFAULT_FILE_MODE: u32 = 0o644;
+
+/// Mode of archived directories that have none of their own
+const DEFAULT_DIR_MODE: u32 = 0o755;
+
+type Processor = ContentProcessor<CryptoDecryptorAdapter, TemplateRendererAdapter>;
+
+/// Format of the archive
+#[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+pub enum ArchiveFormat {
+    /// Gzip-compressed tarball
+    #[value(name = "tar.gz", alias = "tgz")]
+    TarGz,
+    /// Zip archive
+    Zip,
+}
+
+/// Archive command
+#[derive(Debug, Args)]
+pub struct ArchiveCommand {
+    /// Archive format
+    #[arg(long, value_enum, default_value_t = ArchiveFormat::TarGz)]
+    pub format: ArchiveFormat,
+
+    /// Write the archive to FILE instead of stdout
+    #[arg(short, long, value_name = "FILE")]
+    pub output: Option<PathBuf>,
+
+    /// Include encrypted files and secret env files (`env_` prefix), and
+    /// decrypt inline age values; all are left out otherwise
+    #[arg(long)]
+    pub include_secrets: bool,
+
+    /// Only archive these entry types or target path globs (comma-separated)
+    #[arg(long, value_delimiter = ',')]
+    pub include: Vec<String>,
+
+    /// Skip these entry types or target path globs (comma-separated)
+    #[arg(long, value_delimiter = ',')]
+    pub exclude: Vec<String>,
+}
+
+impl Command for ArchiveCommand {
+    type Output = ();
+    fn execute(&self, context: &RuntimeContext) -> crate::error::Result<()> {
+        let entry_filter = EntryFilter::new(&self.include, &self.exclude)?;
+        if self.output.is_none() && io::stdout().is_terminal() {
+            return Err(anyhow::anyhow!(
+                "Refusing to write an archive to a terminal. Pass -o FILE or redirect stdout."
+            )
+            .into());
+        }
+
+        let rendered = render_target_state(context, &entry_filter, self.include_secrets)?;
+        let mtime = context.clock.begin_run().timestamp;
+
+        let Some(output) = &self.output else {
+            write_archive(self.format, &rendered, mtime, io::stdout().lock())?;
+            return Ok(());
+        };
+
+        // Moved into place only once complete, so a failure leaves no partial archive
+        let dir = output
+            .parent()
+            .filter(|dir| !dir.as_os_str().is_empty())
+            .unwrap_or(Path::new("."));
+        let mut temp = tempfile::NamedTempFile::new_in(dir)
+            .with_context(|| format!("Failed to create temporary file in {}", dir.display()))?;
+        let count = write_archive(self.format, &rendered, mtime, temp.as_file_mut())?;
+        temp.persist(output)
+            .with_context(|| format!("Failed to write {}", output.display()))?;
+        info!("Archived {count} entries to {}", output.display());
+        Ok(())
+    }
+}
+
+/// The target state to archive, with what its files still need for writing
+struct Rendered {
+    /// Entries to archive, sorted by path
+    entries: Vec<TargetEntry>,
+    /// Processor large files are streamed through
+    processor: Processor,
+    /// Identities for inline age values; empty unless secrets are included
+    identities: Arc<Vec<guisu_crypto::Identity>>,
+    fail_on_decrypt_error: bool,
+}
+
+/// Build the target state of the entries selected by `entry_filter`
+///
+/// Files are always copied, whatever the apply mode, since links into the
+/// source directory would be of no use elsewhere. Without `include_secrets`
+/// encrypted and secret env files are left out and no identities are loaded,
+/// so nothing is decrypted.
+fn render_target_state(
+    context: &RuntimeContext,
+    entry_filter: &EntryFilter,
+    include_secrets: bool,
+) -> Result<Rendered> {
+    let source_dir = context.source_dir();
+    let source_abs = context.dotfiles_dir();
+    let paths = &context.paths;
+    let config = &context.config;
+
+    let ignore_matcher = crate::load_ignore_matcher(source_dir, source_abs.as_path(), config)?;
+    let mut source_state =
+        SourceState::read_with_matcher(source_abs.to_owned(), Some(&ignore_matcher))
+            .context("Failed to read source state")?;
+    entry_filter.retain(&mut source_state);
+    if !include_secrets {
+        let before = source_state.len();
+        source_state.retain(|entry| !entry.is_encrypted() && !entry.is_env());
+        let skipped = before - source_state.len();
+        if skipped > 0 {
+            info!(
+                "Skipped {skipped} encrypted or secret files; pass --include-secrets to include them"
+            );
+        }
+    }
+
+    let identities = if include_secrets {
+        Arc::new(config.age_identities().unwrap_or_default())
+    } else {
+        Arc::new(Vec::new())
+    };
+    let template_engine = crate::create_template_engine(source_dir, &identities, config)?;
+    let decryptor = CryptoDecryptorAdapter::from_identities(Arc::clone(&identities))
+        .with_hints(Arc::new(IdentityHints::default()));
+    let processor = ContentProcessor::new(decryptor, TemplateRendererAdapter::new(template_engine))
+        .with_eol(config.general.eol)
+        .with_stream_threshold(config.general.stream_threshold);
+
+    let template_context = template_context(
+        source_dir,
+        source_abs.as_path(),
+        paths.dest_dir.as_path(),
+        config,
+    )
+    .context("Failed to load variables")?;
+    let template_context_value =
+        serde_json::to_value(&template_context).context("Failed to serialize template context")?;
+
+    let mut target_state =
+        TargetState::from_source(&source_state, &processor, &template_context_value)
+            .context("Failed to render the target state")?;
+    target_state.normalize_link_targets(config.apply.symlink_targets, |path| paths.dest_path(path));
+
+    let externals =
+        guisu_engine::external::Externals::load(source_dir).context("Failed to load externals")?;
+    add_externals(&mut target_state, &externals, context, false, true)?;
+
+    let mut entries: Vec<TargetEntry> = target_state
+        .entries()
+        .filter(|entry| {
+            !matches!(entry, TargetEntry::Remove { .. })
+                && entry_filter.matches_target(entry)
+                && !ignore_matcher.is_ignored(
+                    entry.path().as_path(),
+                    Some(matches!(entry, TargetEntry::Directory { .. })),
+                )
+        })
+        .cloned()
+        .collect();
+    // Parents before their children
+    entries.sort_by(|a, b| a.path().as_path().cmp(b.path().as_path()));
+
+    Ok(Rendered {
+        entries,
+        processor,
+        identities,
+        fail_on_decrypt_error: config.age.fail_on_decrypt_error,
+    })
+}
+
+/// Write the entries of `rendered` to `out` as an archive
+///
+/// Returns the number of entries written.
+fn write_archive(
+    format: ArchiveFormat,
+    rendered: &Rendered,
+    mtime: u64,
+    out: impl Write,
+) -> Result<usize> {
+    match format {
+        ArchiveFormat::TarGz => {
+            let mut archive = TarArchive::new(out, mtime);
+            let count = write_entries(&mut archive, rendered)?;
+            archive.finish()?;
+            Ok(count)
+        }
+        ArchiveFormat::Zip => {
+            let mut archive = ZipArchive::new(out, mtime);
+            let count = write_entries(&mut archive, rendered)?;
+            archive.finish()?;
+            Ok(count)
+        }
+    }
+}
+
+/// Add the entries of `rendered` to `archive`
+fn write_entries(archive: &mut dyn ArchiveWriter, rendered: &Rendered) -> Result<usize> {
+    for entry in &rendered.entries {
+        let path = entry.path().as_path();
+        let added = match entry {
+            TargetEntry::File { content, mode, .. } => {
+                let content = decrypt_inline_age_values(
+                    content,
+                    &rendered.identities,
+                    rendered.fail_on_decrypt_error,
+                )?;
+                archive.add_file(path, mode.unwrap_or(DEFAULT_FILE_MODE), &content)
+            }
+            TargetEntry::LargeFile {
+                source,
+                attributes,
+                size,
+                mode,
+                ..
+            } => {
+                let source = guisu_core::path::AbsPath::new(source.clone())?;
+                archive.add_streamed_file(
+                    path,
+                    mode.unwrap_or(DEFAULT_FILE_MODE),
+                    *size,
+                    &mut |out| {
+                        rendered.processor.stream_file(&source, attributes, out)?;
+                        Ok(())
+                    },
+                )
+            }
+            TargetEntry::Directory { mode, .. } => {
+                archive.add_dir(path, mode.unwrap_or(DEFAULT_DIR_MODE))
+            }
+            TargetEntry::Symlink { target, .. } => archive.add_symlink(path, target),
+            TargetEntry::Remove { .. } => continue,
+        };
+        added.with_context(|| format!("Failed to archive {}", path.display()))?;
+    }
+
+    Ok(rendered.entries.len())
+}
+
+/// Writer of one archive format
+trait ArchiveWriter {
+    /// Add a directory
+    fn add_dir(&mut self, path: &Path, mode: u32) -> Result<()>;
+
+    /// Add a file with the given content
+    fn add_file(&mut self, path: &Path, mode: u32, content: &[u8]) -> Result<()>;
+
+    /// Add a file of `size` bytes whose content `write` produces
+    fn add_streamed_file(
+        &mut self,
+        path: &Path,
+        mode: u32,
+        size: u64,
+        write: &mut dyn FnMut(&mut dyn Write) -> Result<()>,
+    ) -> Result<()>;
+
+    /// Add a symlink pointing to `target`
+    fn add_symlink(&mut self, path: &Path, target: &Path) -> Result<()>;
+}
+
+/// Gzip-compressed tarball
+struct TarArchive<W: Write> {
+    builder: tar::Builder<GzEncoder<W>>,
+    mtime: u64,
+}
+
+impl<W: Write> TarArchive<W> {
+    fn new(out: W, mtime: u64) -> Self {
+        let mut builder = tar::Builder::new(GzEncoder::new(out, Compression::default()));
+        builder.mode(tar::HeaderMode::Deterministic);
+        Self { builder, mtime }
+    }
+
+    fn header(&self, entry_type: tar::EntryType, mode: u32, size: u64) -> tar::Header {
+        let mut header = tar::Header::new_gnu();
+        header.set_entry_type(entry_type);
+        header.set_mode(mode);
+        header.set_size(size);
+        header.set_mtime(self.mtime);
+        header
+    }
+
+    fn finish(self) -> Result<()> {
+        self.builder.into_inner()?.finish()?.flush()?;
+        Ok(())
+    }
+}
+
+impl<W: Write> ArchiveWriter for TarArchive<W> {
+    fn add_dir(&mut self, path: &Path, mode: u32) -> Result<()> {
+        let mut header = self.header(tar::EntryType::Directory, mode, 0);
+        self.builder.append_data(&mut header, path, io::empty())?;
+        Ok(())
+    }
+
+    fn add_file(&mut self, path: &Path, mode: u32, content: &[u8]) -> Result<()> {
+        let mut header = self.header(tar::EntryType::Regular, mode, content.len() as u64);
+        self.builder.append_data(&mut header, path, content)?;
+        Ok(())
+    }
+
+    fn add_streamed_file(
+        &mut self,
+        path: &Path,
+        mode: u32,
+        _size: u64,
+        write: &mut dyn FnMut(&mut dyn Write) -> Result<()>,
+    ) -> Result<()> {
+        // Tar headers come before the content, and the builder cannot seek
+        // back through the gzip stream, so the content is spooled first
+        let mut spool = io::BufWriter::new(tempfile::tempfile()?);
+        write(&mut spool)?;
+        let mut spool = spool.into_inner().map_err(io::IntoInnerError::into_error)?;
+        let size = spool.stream_position()?;
+        spool.rewind()?;
+        let mut header = self.header(tar::EntryType::Regular, mode, size);
+        self.builder.append_data(&mut header, path, spool)?;
+        Ok(())
+    }
+
+    fn add_symlink(&mut self, path: &Path, target: &Path) -> Result<()> {
+        let mut header = self.header(tar::EntryType::Symlink, 0o777, 0);
+        self.builder.append_link(&mut header, path, target)?;
+        Ok(())
+    }
+}
+
+/// Zip archive, written as a stream so stdout works as well as files
+struct ZipArchive<W: Write> {
+    writer: zip::ZipWriter<StreamWriter<W>>,
+    options: SimpleFileOptions,
+}
+
+impl<W: Write> ZipArchive<W> {
+    fn new(out: W, mtime: u64) -> Self {
+        let mut options =
+            SimpleFileOptions::default().compression_method(zip::CompressionMethod::Deflated);
+        // Zip stores local time, from 1980 on
+        if let Some(time) = i64::try_from(mtime)
+            .ok()
+            .and_then(|secs| chrono::DateTime::from_timestamp(secs, 0))
+            .and_then(|time| {
+                zip::DateTime::try_from(time.with_timezone(&chrono::Local).naive_local()).ok()
+            })
+        {
+            options = options.last_modified_time(time);
+        }
+        Self {
+            writer: zip::ZipWriter::new_stream(out),
+            options,
+        }
+    }
+
+    fn finish(self) -> Result<()> {
+        self.writer.finish()?.into_inner().flush()?;
+        Ok(())
+    }
+}
+
+impl<W: Write> ArchiveWriter for ZipArchive<W> {
+    fn add_dir(&mut self, path: &Path, mode: u32) -> Result<()> {
+        self.writer
+            .add_directory_from_path(path, self.options.unix_permissions(mode))?;
+        Ok(())
+    }
+
+    fn add_file(&mut self, path: &Path, mode: u32, content: &[u8]) -> Result<()> {
+        self.writer
+            .start_file_from_path(path, self.options.unix_permissions(mode))?;
+        self.writer.write_all(content)?;
+        Ok(())
+    }
+
+    fn add_streamed_file(
+        &mut self,
+        path: &Path,
+        mode: u32,
+        size: u64,
+        write: &mut dyn FnMut(&mut dyn Write) -> Result<()>,
+    ) -> Result<()> {
+        let options = self
+            .options
+            .unix_permissions(mode)
+            .large_file(size >= u64::from(u32::MAX));
+        self.writer.start_file_from_path(path, options)?;
+        let mut out = io::BufWriter::new(&mut self.writer);
+        write(&mut out)?;
+        out.flush()?;
+        Ok(())
+    }
+
+    fn add_symlink(&mut self, path: &Path, target: &Path) -> Result<()> {
+        self.writer
+            .add_symlink_from_path(path, target, self.options)?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    #![allow(clippy::unwrap_used)]
+    use super::*;
+    use std::io::Read;
+
+    fn sample_entries(archive: &mut dyn ArchiveWriter) {
+        archive.add_dir(Path::new(".config"), 0o700).unwrap();
+        archive
+            .add_file(Path::new(".config/app.toml"), 0o600, b"key = 1\n")
+            .unwrap();
+        archive
+            .add_streamed_file(Path::new("large.bin"), 0o755, 5, &mut |out| {
+                Ok(out.write_all(b"large")?)
+            })
+            .unwrap();
+        archive
+            .add_symlink(Path::new(".vimrc"), Path::new(".config/vimrc"))
+            .unwrap();
+    }
+
+    #[test]
+    fn test_tar_archive_keeps_modes_and_links() {
+        let mut bytes = Vec::new();
+        let mut archive = TarArchive::new(&mut bytes, 1_700_000_000);
+        sample_entries(&mut archive);
+        archive.finish().unwrap();
+
+        let mut tar = tar::Archive::new(flate2::read::GzDecoder::new(bytes.as_slice()));
+        let mut seen = Vec::new();
+        for entry in tar.entries().unwrap() {
+            let mut entry = entry.unwrap();
+            let path = entry.path().unwrap().display().to_string();
+            let mode = entry.header().mode().unwrap();
+            let link = entry
+                .link_name()
+                .unwrap()
+                .map(|link| link.display().to_string());
+            let mut content = String::new();
+            entry.read_to_string(&mut content).unwrap();
+            assert_eq!(entry.header().mtime().unwrap(), 1_700_000_000);
+            seen.push((path, mode, content, link));
+        }
+        assert_eq!(
+            seen,
+            vec![
+                (".config".to_string(), 0o700, String::new(), None),
+                (
+                    ".config/app.toml".to_string(),
+                    0o600,
+                    "key = 1\n".to_string(),
+                    None
+                ),
+                ("large.bin".to_string(), 0o755, "large".to_string(), None),
+                (
+                    ".vimrc".to_string(),
+                    0o777,
+                    String::new(),
+                    Some(".config/vimrc".to_string())
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_zip_archive_keeps_modes_and_links() {
+        let mut bytes = Vec::new();
+        let mut archive = ZipArchive::new(&mut bytes, 1_700_000_000);
+        sample_entries(&mut archive);
+        archive.finish().unwrap();
+
+        let mut zip = zip::ZipArchive::new(io::Cursor::new(bytes)).unwrap();
+        let mut file = zip.by_name(".config/app.toml").unwrap();
+        assert_eq!(file.unix_mode().unwrap() & 0o777, 0o600);
+        let mut content = String::new();
+        file.read_to_string(&mut content).unwrap();
+        assert_eq!(content, "key = 1\n");
+        drop(file);
+
+        let mut large = zip.by_name("large.bin").unwrap();
+        assert_eq!(large.unix_mode().unwrap() & 0o777, 0o755);
+        content.clear();
+        large.read_to_string(&mut content).unwrap();
+        assert_eq!(content, "large");
+        drop(large);
+
+        assert!(zip.by_name(".config/").unwrap().is_dir());
+        let mut link = zip.by_name(".vimrc").unwrap();
+        assert!(link.is_symlink());
+        content.clear();
+        link.read_to_string(&mut content).unwrap();
+        assert_eq!(content, ".config/vimrc");
+    }
+}
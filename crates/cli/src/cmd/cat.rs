@@ -4,39 +4,118 @@
 
 use anyhow::{Context, Result};
 use clap::Args;
-use guisu_core::path::AbsPath;
+use guisu_core::path::{AbsPath, RelPath};
+use guisu_engine::adapters::template::template_context;
+use guisu_engine::attr::FileAttributes;
+use guisu_engine::entry::SourceEntry;
 use guisu_engine::state::SourceState;
-use guisu_template::TemplateContext;
 use std::fs;
+use std::io::Write;
 use std::path::{Path, PathBuf};
 
 use crate::command::Command;
-use crate::common::RuntimeContext;
+use crate::common::{EntryFilter, RuntimeContext};
 use guisu_config::Config;
 
 /// Cat command
 #[derive(Args)]
 pub struct CatCommand {
-    /// Files to display
+    /// Files or directories to display
     #[arg(required = true)]
     pub files: Vec<PathBuf>,
+
+    /// Print source files as stored, without decrypting or rendering
+    #[arg(long, conflicts_with = "target_only")]
+    pub raw: bool,
+
+    /// Print only the processed content, without headers for files in directories
+    #[arg(long)]
+    pub target_only: bool,
+
+    /// Write the content to FILE instead of stdout
+    #[arg(short, long, value_name = "FILE")]
+    pub output: Option<PathBuf>,
+
+    /// Print only these entry types or target path globs from directories
+    /// (comma-separated)
+    #[arg(long, value_delimiter = ',')]
+    pub include: Vec<String>,
+
+    /// Skip these entry types or target path globs in directories
+    /// (comma-separated)
+    #[arg(long, value_delimiter = ',')]
+    pub exclude: Vec<String>,
+
+    /// Print secret env files (`env_` prefix) too; they are skipped otherwise
+    #[arg(long)]
+    pub show_secrets: bool,
 }
 
 impl Command for CatCommand {
     type Output = ();
     fn execute(&self, context: &RuntimeContext) -> crate::error::Result<()> {
+        let options = CatOptions {
+            raw: self.raw,
+            headers: !self.target_only,
+            terminal_newline: self.output.is_none(),
+            show_secrets: self.show_secrets,
+        };
+        let entry_filter = EntryFilter::new(&self.include, &self.exclude)?;
+
+        let Some(output) = &self.output else {
+            return run_impl(
+                context.source_dir(),
+                context.dest_dir().as_path(),
+                &self.files,
+                &entry_filter,
+                &context.config,
+                options,
+                &mut std::io::stdout().lock(),
+            )
+            .map_err(Into::into);
+        };
+
+        // Written only once everything rendered, so a failure leaves no partial file
+        let mut rendered = Vec::new();
         run_impl(
             context.source_dir(),
             context.dest_dir().as_path(),
             &self.files,
+            &entry_filter,
             &context.config,
-        )
-        .map_err(Into::into)
+            options,
+            &mut rendered,
+        )?;
+        fs::write(output, rendered)
+            .with_context(|| format!("Failed to write {}", output.display()))?;
+        Ok(())
     }
 }
 
+/// How the content of each file is printed
+#[derive(Debug, Clone, Copy)]
+#[allow(clippy::struct_excessive_bools)]
+struct CatOptions {
+    /// Print source files as stored
+    raw: bool,
+    /// Print a header before each file of a directory
+    headers: bool,
+    /// End each file with a newline (for terminals; files are written as is)
+    terminal_newline: bool,
+    /// Print secret env files instead of skipping them
+    show_secrets: bool,
+}
+
 /// Run the cat command implementation
-fn run_impl(source_dir: &Path, dest_dir: &Path, files: &[PathBuf], config: &Config) -> Result<()> {
+fn run_impl(
+    source_dir: &Path,
+    dest_dir: &Path,
+    files: &[PathBuf],
+    entry_filter: &EntryFilter,
+    config: &Config,
+    options: CatOptions,
+    out: &mut dyn Write,
+) -> Result<()> {
     if files.is_empty() {
         anyhow::bail!("No files specified. Usage: guisu cat <file>");
     }
@@ -46,10 +125,118 @@ fn run_impl(source_dir: &Path, dest_dir: &Path, files: &[PathBuf], config: &Conf
     let source_abs = &paths.dotfiles_dir;
     let dest_abs = &paths.dest_dir;
 
-    // Create ignore matcher from .guisu/ignores.toml
+    // Only built when a file needs decryption or rendering
+    let mut source_state = None;
+
+    // Process each file
+    for file_path in files {
+        // Resolve file path and get relative path
+        let rel_path = resolve_file_path(file_path, dest_abs, &paths.targets)?;
+
+        // Fast path: plain files are written as-is, without reading the whole
+        // source state or loading identities
+        if let Some(source_file) = plain_source_file(source_abs, &rel_path)
+            && let Some(content) = read_plain_file(&source_file, options.raw)?
+        {
+            write_content(out, &content, options.terminal_newline)?;
+            continue;
+        }
+
+        let source_state = match source_state {
+            Some(ref state) => state,
+            None => source_state.insert(read_source_state(source_dir, source_abs)?),
+        };
+
+        let dir_files =
+            directory_files(source_state, &rel_path, entry_filter, options.show_secrets);
+        if dir_files.is_empty() {
+            if !options.show_secrets && source_state.get(&rel_path).is_some_and(SourceEntry::is_env)
+            {
+                anyhow::bail!(
+                    "{} is a secret env file. Pass --show-secrets to print it.",
+                    file_path.display()
+                );
+            }
+            let content = file_content(
+                source_state,
+                &rel_path,
+                file_path,
+                config,
+                source_dir,
+                dest_dir,
+                options.raw,
+            )?;
+            write_content(out, &content, options.terminal_newline)?;
+            continue;
+        }
+
+        for (index, target_path) in dir_files.iter().enumerate() {
+            let display_path = match target_path.as_path().strip_prefix(rel_path.as_path()) {
+                Ok(rest) => file_path.join(rest),
+                Err(_) => target_path.as_path().to_path_buf(),
+            };
+            let content = file_content(
+                source_state,
+                target_path,
+                &display_path,
+                config,
+                source_dir,
+                dest_dir,
+                options.raw,
+            )?;
+
+            if options.headers {
+                if index > 0 {
+                    writeln!(out)?;
+                }
+                writeln!(out, "==> {} <==", display_path.display())?;
+            }
+            write_content(out, &content, options.terminal_newline || options.headers)?;
+        }
+    }
+
+    Ok(())
+}
+
+/// Target paths of the managed files under a directory selected by
+/// `entry_filter`, sorted
+///
+/// Secret env files are left out unless `show_secrets` is set. Empty if
+/// `rel_path` is not a directory in the source state.
+fn directory_files<'a>(
+    source_state: &'a SourceState,
+    rel_path: &RelPath,
+    entry_filter: &EntryFilter,
+    show_secrets: bool,
+) -> Vec<&'a RelPath> {
+    if matches!(source_state.get(rel_path), Some(entry) if !matches!(entry, SourceEntry::Directory { .. }))
+    {
+        return Vec::new();
+    }
+
+    let mut files: Vec<&RelPath> = source_state
+        .entries()
+        .filter(|entry| {
+            matches!(entry, SourceEntry::File { .. })
+                && entry_filter.matches_source(entry)
+                && (show_secrets || !entry.is_env())
+        })
+        .map(SourceEntry::target_path)
+        .filter(|target| {
+            target.as_path() != rel_path.as_path()
+                && target.as_path().starts_with(rel_path.as_path())
+        })
+        .collect();
+    files.sort_by(|a, b| a.as_path().cmp(b.as_path()));
+    files
+}
+
+/// Read the full source state for files that need processing
+fn read_source_state(source_dir: &Path, source_abs: &AbsPath) -> Result<SourceState> {
+    // Create ignore matcher from .guisu/ignores.toml and .guisuignore files
     // Use dotfiles_dir as the match root so patterns match relative to the dotfiles directory
-    let _ignore_matcher = guisu_config::IgnoreMatcher::from_ignores_toml(source_dir)
-        .context("Failed to load ignore patterns from .guisu/ignores.toml")?;
+    let _ignore_matcher = guisu_config::IgnoreMatcher::load(source_dir, source_abs.as_path())
+        .context("Failed to load ignore patterns")?;
 
     // Read source state
     let source_state =
@@ -59,23 +246,60 @@ fn run_impl(source_dir: &Path, dest_dir: &Path, files: &[PathBuf], config: &Conf
         anyhow::bail!("No files managed. Add files with: guisu add <file>");
     }
 
-    // Process each file
-    for file_path in files {
-        cat_file(
-            &source_state,
-            dest_abs,
-            file_path,
-            config,
-            source_dir,
-            dest_dir,
-        )?;
+    Ok(source_state)
+}
+
+/// Find the source file of a target that needs no processing
+///
+/// Returns the source file if it exists as a regular file under its target
+/// name and no template or encrypted variant exists next to it. Anything else
+/// (including unmanaged paths, for the error message) goes through the full
+/// source state.
+fn plain_source_file(source_abs: &AbsPath, rel_path: &RelPath) -> Option<PathBuf> {
+    let source_file = source_abs.join(rel_path).as_path().to_path_buf();
+    if !fs::symlink_metadata(&source_file).is_ok_and(|metadata| metadata.is_file()) {
+        return None;
     }
 
-    Ok(())
+    let file_name = source_file.file_name()?.to_string_lossy().to_string();
+    let has_variant = [".j2", ".age", ".j2.age"].iter().any(|suffix| {
+        source_file
+            .with_file_name(format!("{file_name}{suffix}"))
+            .exists()
+    });
+
+    (!has_variant).then_some(source_file)
+}
+
+/// Read a plain source file
+///
+/// Returns `None` if the file contains inline encrypted values, which need
+/// identities to decrypt, unless the raw content was asked for.
+fn read_plain_file(source_file: &Path, raw: bool) -> Result<Option<Vec<u8>>> {
+    let content = fs::read(source_file)
+        .with_context(|| format!("Failed to read source file: {}", source_file.display()))?;
+
+    if !raw && contains_inline_values(&content) {
+        return Ok(None);
+    }
+
+    Ok(Some(content))
+}
+
+/// Check for something shaped like an inline encrypted value (`age:` + base64)
+fn contains_inline_values(content: &[u8]) -> bool {
+    content.windows(5).any(|window| {
+        window.starts_with(b"age:")
+            && (window[4].is_ascii_alphanumeric() || matches!(window[4], b'+' | b'/'))
+    })
 }
 
 /// Resolve file path by expanding tilde and converting to absolute path
-fn resolve_file_path(file_path: &Path, dest_abs: &AbsPath) -> Result<guisu_core::path::RelPath> {
+fn resolve_file_path(
+    file_path: &Path,
+    dest_abs: &AbsPath,
+    targets: &guisu_config::Targets,
+) -> Result<RelPath> {
     // Expand tilde in path
     let expanded_path = if file_path.starts_with("~") {
         if let Some(home) = dirs::home_dir() {
@@ -108,8 +332,8 @@ fn resolve_file_path(file_path: &Path, dest_abs: &AbsPath) -> Result<guisu_core:
         AbsPath::new(abs_path)?
     };
 
-    // Get relative path from destination
-    file_abs.strip_prefix(dest_abs).with_context(|| {
+    // Get relative path from destination or one of the roots of .guisu/targets.toml
+    targets.target_path(dest_abs, &file_abs).with_context(|| {
         format!(
             "File {} is not under destination directory {}",
             file_abs.as_path().display(),
@@ -121,9 +345,9 @@ fn resolve_file_path(file_path: &Path, dest_abs: &AbsPath) -> Result<guisu_core:
 /// Get source entry info and validate it's a file (not directory or symlink)
 fn get_source_entry_info<'a>(
     source_state: &'a SourceState,
-    rel_path: &guisu_core::path::RelPath,
+    rel_path: &RelPath,
     file_path: &Path,
-) -> Result<(&'a guisu_core::path::SourceRelPath, bool, bool)> {
+) -> Result<(&'a guisu_core::path::SourceRelPath, FileAttributes)> {
     // Find the entry in source state
     let entry = source_state
         .get(rel_path)
@@ -135,17 +359,16 @@ fn get_source_entry_info<'a>(
             source_path,
             attributes,
             ..
-        } => Ok((
-            source_path,
-            attributes.is_template(),
-            attributes.is_encrypted(),
-        )),
+        } => Ok((source_path, *attributes)),
         guisu_engine::entry::SourceEntry::Directory { .. } => {
             anyhow::bail!("{} is a directory", file_path.display());
         }
         guisu_engine::entry::SourceEntry::Symlink { .. } => {
             anyhow::bail!("{} is a symlink", file_path.display());
         }
+        guisu_engine::entry::SourceEntry::Script { .. } => {
+            anyhow::bail!("{} is a script", file_path.display());
+        }
     }
 }
 
@@ -162,21 +385,16 @@ fn render_template_content(
     let identities_arc = std::sync::Arc::new(identities);
 
     // Create template engine with template directory support and bitwarden provider
-    let engine = crate::create_template_engine(source_dir, &identities_arc, config);
-
-    let dotfiles_dir_str = crate::path_to_string(&config.dotfiles_dir(source_dir));
+    let engine = crate::create_template_engine(source_dir, &identities_arc, config)?;
+
+    let template_ctx = template_context(
+        source_dir,
+        &config.dotfiles_dir(source_dir),
+        dest_dir,
+        config,
+    )
+    .context("Failed to load variables")?;
     let root_entry_str = crate::path_to_string(&config.general.root_entry);
-    let working_tree = guisu_engine::git::find_working_tree(source_dir)
-        .unwrap_or_else(|| source_dir.to_path_buf());
-    let mut template_ctx = TemplateContext::new().with_guisu_info(
-        dotfiles_dir_str,
-        crate::path_to_string(&working_tree),
-        crate::path_to_string(dest_dir),
-        root_entry_str.clone(),
-    );
-
-    // Add user variables from config
-    template_ctx = template_ctx.with_variables_ref(&config.variables);
 
     // Build template name with root_entry prefix
     let template_name = format!("{}/{}", root_entry_str, source_path.as_path().display());
@@ -208,58 +426,63 @@ fn decrypt_inline_values(content: Vec<u8>, config: &Config) -> Result<Vec<u8>> {
     }
 }
 
-/// Output content to stdout with POSIX-compliant newline
-fn output_content_with_newline(content: &[u8]) -> Result<()> {
-    std::io::Write::write_all(&mut std::io::stdout(), content)?;
+/// Write content, optionally with a POSIX-compliant trailing newline
+fn write_content(out: &mut dyn Write, content: &[u8], ensure_newline: bool) -> Result<()> {
+    out.write_all(content)?;
 
     // Ensure output ends with newline (POSIX standard for text files)
     // This prevents the shell '%' symbol from appearing
-    if !content.is_empty() && !content.ends_with(b"\n") {
-        println!();
+    if ensure_newline && !content.is_empty() && !content.ends_with(b"\n") {
+        writeln!(out)?;
     }
 
     Ok(())
 }
 
-fn cat_file(
+/// Content of a managed file, processed unless `raw` is set
+fn file_content(
     source_state: &SourceState,
-    dest_abs: &AbsPath,
+    rel_path: &RelPath,
     file_path: &Path,
     config: &Config,
     source_dir: &Path,
     dest_dir: &Path,
-) -> Result<()> {
-    // Resolve file path and get relative path
-    let rel_path = resolve_file_path(file_path, dest_abs)?;
-
+    raw: bool,
+) -> Result<Vec<u8>> {
     // Get source entry info and validate it's a file
-    let (source_path, is_template, is_encrypted) =
-        get_source_entry_info(source_state, &rel_path, file_path)?;
-
-    let source_file_path = source_state.source_file_path(source_path);
+    let (source_path, attributes) = get_source_entry_info(source_state, rel_path, file_path)?;
 
     // Read the file content
-    let mut content = fs::read(source_file_path.as_path())
-        .with_context(|| format!("Failed to read source file: {source_file_path:?}"))?;
+    let mut content = source_state.read_file(source_path).with_context(|| {
+        format!(
+            "Failed to read source file: {:?}",
+            source_state.source_file_path(source_path)
+        )
+    })?;
+
+    if raw {
+        return Ok(content);
+    }
 
     // Decrypt if encrypted
-    if is_encrypted {
+    if attributes.is_encrypted() {
         content = decrypt_content(&content, config)?;
     }
 
+    // Decompress after decrypting, as compression is applied first
+    if attributes.is_compressed() {
+        content =
+            guisu_engine::compress::decompress(&content).context("Failed to decompress content")?;
+    }
+
     // Render template if needed
-    if is_template {
+    if attributes.is_template() {
         let content_str = String::from_utf8(content).context("File content is not valid UTF-8")?;
         content = render_template_content(&content_str, source_path, source_dir, dest_dir, config)?;
     }
 
     // Decrypt inline age values (sops-like behavior)
-    content = decrypt_inline_values(content, config)?;
-
-    // Output the content
-    output_content_with_newline(&content)?;
-
-    Ok(())
+    decrypt_inline_values(content, config)
 }
 
 /// Decrypt content using age
@@ -509,7 +732,21 @@ mod tests {
         let dest_dir = temp.path();
         let config = test_config();
 
-        let result = run_impl(source_dir, dest_dir, &[], &config);
+        let options = CatOptions {
+            raw: false,
+            headers: true,
+            terminal_newline: true,
+            show_secrets: false,
+        };
+        let result = run_impl(
+            source_dir,
+            dest_dir,
+            &[],
+            &EntryFilter::default(),
+            &config,
+            options,
+            &mut Vec::new(),
+        );
 
         assert!(result.is_err());
         assert!(
@@ -520,6 +757,94 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_plain_source_file() {
+        let temp = TempDir::new().expect("Failed to create temp dir");
+        let root = fs::canonicalize(temp.path()).expect("Failed to canonicalize");
+        fs::write(root.join(".bashrc"), "alias ll='ls -l'\n").expect("Failed to write");
+        fs::write(root.join(".gitconfig.j2"), "[user]\n").expect("Failed to write");
+        fs::write(root.join(".vimrc"), "set nu\n").expect("Failed to write");
+        fs::write(root.join(".vimrc.j2"), "set nu\n").expect("Failed to write");
+        let source_abs = AbsPath::new(root.clone()).expect("Failed to create AbsPath");
+
+        let rel = |p: &str| guisu_core::path::RelPath::new(PathBuf::from(p)).expect("RelPath");
+
+        assert_eq!(
+            plain_source_file(&source_abs, &rel(".bashrc")),
+            Some(root.join(".bashrc"))
+        );
+        // Templates and ambiguous entries need the full source state
+        assert!(plain_source_file(&source_abs, &rel(".gitconfig")).is_none());
+        assert!(plain_source_file(&source_abs, &rel(".vimrc")).is_none());
+        assert!(plain_source_file(&source_abs, &rel(".missing")).is_none());
+    }
+
+    #[test]
+    fn test_cat_plain_file_defers_inline_values() {
+        let temp = TempDir::new().expect("Failed to create temp dir");
+        let file = temp.path().join("config");
+        fs::write(&file, "token = age:YWdlLWVuY3J5cHRpb24\n").expect("Failed to write");
+
+        assert!(
+            read_plain_file(&file, false)
+                .expect("read failed")
+                .is_none()
+        );
+        assert!(read_plain_file(&file, true).expect("read failed").is_some());
+        assert!(contains_inline_values(b"key: age:abc"));
+        assert!(!contains_inline_values(b"page: 1"));
+        assert!(!contains_inline_values(b"age:"));
+    }
+
+    #[test]
+    fn test_directory_files() {
+        let temp = TempDir::new().expect("Failed to create temp dir");
+        let root = fs::canonicalize(temp.path()).expect("Failed to canonicalize");
+        fs::create_dir_all(root.join(".config/nvim/lua")).expect("Failed to create dir");
+        fs::write(root.join(".config/nvim/init.lua"), "").expect("Failed to write");
+        fs::write(root.join(".config/nvim/lua/plugins.lua.j2"), "").expect("Failed to write");
+        fs::write(root.join(".bashrc"), "").expect("Failed to write");
+        fs::write(root.join(".config/nvim/env_.env"), "").expect("Failed to write");
+        let source_state =
+            SourceState::read(AbsPath::new(root).expect("AbsPath")).expect("Failed to read");
+
+        let rel = |p: &str| RelPath::new(PathBuf::from(p)).expect("RelPath");
+        let paths = |dir: &str| -> Vec<PathBuf> {
+            directory_files(&source_state, &rel(dir), &EntryFilter::default(), false)
+                .iter()
+                .map(|path| path.as_path().to_path_buf())
+                .collect()
+        };
+
+        assert_eq!(
+            paths(".config"),
+            vec![
+                PathBuf::from(".config/nvim/init.lua"),
+                PathBuf::from(".config/nvim/lua/plugins.lua")
+            ]
+        );
+        // Secret env files only with --show-secrets
+        assert_eq!(
+            directory_files(
+                &source_state,
+                &rel(".config"),
+                &EntryFilter::default(),
+                true
+            )[0],
+            &rel(".config/nvim/.env")
+        );
+        // Files are not directories
+        assert_eq!(paths(".bashrc"), Vec::<PathBuf>::new());
+        assert_eq!(paths(".config/nvim/init.lua"), Vec::<PathBuf>::new());
+
+        let templates =
+            EntryFilter::new(&[], &["templates".to_string()]).expect("Failed to parse filter");
+        assert_eq!(
+            directory_files(&source_state, &rel(".config"), &templates, false),
+            vec![&rel(".config/nvim/init.lua")]
+        );
+    }
+
     #[test]
     fn test_decrypt_content_wrong_identity() {
         let temp = TempDir::new().expect("Failed to create temp dir");
@@ -0,0 +1,60 @@
+//! Cd command implementation
+//!
+//! Start an interactive shell in the source directory, returning to the
+//! caller's shell and directory when it exits.
+
+use anyhow::{Context, Result};
+use clap::Args;
+use std::path::Path;
+use std::process::Command as ProcessCommand;
+
+/// Environment variable set in the shell started by `guisu cd`
+pub const SUBSHELL_ENV: &str = "GUISU_SUBSHELL";
+
+/// Start a shell in the source directory
+#[derive(Debug, Clone, Args)]
+pub struct CdCommand {
+    /// Shell to start (default: $SHELL)
+    #[arg(long)]
+    pub shell: Option<String>,
+}
+
+impl CdCommand {
+    /// Run a shell in `source_dir` until it exits
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the source directory does not exist or the shell
+    /// cannot be started
+    pub fn run(&self, source_dir: &Path) -> Result<()> {
+        if !source_dir.is_dir() {
+            anyhow::bail!(
+                "Source directory does not exist: {}\nRun 'guisu init' first",
+                source_dir.display()
+            );
+        }
+
+        let shell = self.shell.clone().unwrap_or_else(default_shell);
+        // The shell's exit status is that of the last command typed into it
+        ProcessCommand::new(&shell)
+            .current_dir(source_dir)
+            .env(SUBSHELL_ENV, "1")
+            .status()
+            .with_context(|| format!("Failed to start shell: {shell}"))?;
+        Ok(())
+    }
+}
+
+/// Shell of the current user
+fn default_shell() -> String {
+    if let Ok(shell) = std::env::var("SHELL")
+        && !shell.is_empty()
+    {
+        return shell;
+    }
+    if cfg!(windows) {
+        std::env::var("COMSPEC").unwrap_or_else(|_| "cmd.exe".to_string())
+    } else {
+        "/bin/sh".to_string()
+    }
+}
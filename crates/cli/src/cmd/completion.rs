@@ -0,0 +1,196 @@
+//! Completion command implementation
+//!
+//! Generate shell completion scripts from the `Cli` definition, so every
+//! subcommand and flag is covered without maintaining the scripts by hand.
+//! `--integration` prints shell functions for prompts instead.
+
+use anyhow::{Context, Result};
+use clap::{Args, CommandFactory, ValueEnum};
+use std::io::Write;
+
+use crate::Cli;
+
+/// Shells completion scripts can be generated for
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum CompletionShell {
+    /// Bourne Again `SHell`
+    Bash,
+    /// Z `SHell`
+    Zsh,
+    /// Friendly Interactive `SHell`
+    Fish,
+    /// `PowerShell`
+    Powershell,
+    /// Nushell
+    Nushell,
+}
+
+/// Shells prompt integration is provided for
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum IntegrationShell {
+    /// Bourne Again `SHell`
+    Bash,
+    /// Z `SHell`
+    Zsh,
+    /// Friendly Interactive `SHell`
+    Fish,
+}
+
+const BASH_INTEGRATION: &str = r#"# guisu shell integration for bash
+#
+# Load it from ~/.bashrc:
+#   eval "$(guisu completion --integration bash)"
+#
+# guisu_prompt_info prints "guisu:N" when N managed files differ from the
+# source state, and nothing otherwise. It runs `guisu status --porcelain
+# --quick`, which compares the hashes recorded by the last apply instead of
+# rendering templates. Add it to the prompt:
+#   PS1='$(guisu_prompt_info) '"$PS1"
+#
+# Shells started by `guisu cd` have GUISU_SUBSHELL=1 set.
+
+guisu_prompt_info() {
+    local count
+    count=$(command guisu status --porcelain --quick 2>/dev/null | wc -l)
+    count=${count//[[:space:]]/}
+    if [ "${count:-0}" -gt 0 ]; then
+        printf 'guisu:%s' "$count"
+    fi
+}
+"#;
+
+const ZSH_INTEGRATION: &str = r#"# guisu shell integration for zsh
+#
+# Load it from ~/.zshrc:
+#   eval "$(guisu completion --integration zsh)"
+#
+# guisu_prompt_info prints "guisu:N" when N managed files differ from the
+# source state, and nothing otherwise. It runs `guisu status --porcelain
+# --quick`, which compares the hashes recorded by the last apply instead of
+# rendering templates. Add it to the prompt:
+#   setopt prompt_subst
+#   RPROMPT='$(guisu_prompt_info)'
+#
+# Shells started by `guisu cd` have GUISU_SUBSHELL=1 set.
+
+guisu_prompt_info() {
+    local count
+    count=$(command guisu status --porcelain --quick 2>/dev/null | wc -l)
+    count=${count//[[:space:]]/}
+    if (( ${count:-0} > 0 )); then
+        print -n "guisu:${count}"
+    fi
+}
+"#;
+
+const FISH_INTEGRATION: &str = r"# guisu shell integration for fish
+#
+# Load it from ~/.config/fish/config.fish:
+#   guisu completion --integration fish | source
+#
+# guisu_prompt_info prints 'guisu:N' when N managed files differ from the
+# source state, and nothing otherwise. It runs `guisu status --porcelain
+# --quick`, which compares the hashes recorded by the last apply instead of
+# rendering templates. Call it from fish_prompt or fish_right_prompt.
+#
+# Shells started by `guisu cd` have GUISU_SUBSHELL=1 set.
+
+function guisu_prompt_info --description 'Print the number of guisu entries out of sync'
+    set -l count (command guisu status --porcelain --quick 2>/dev/null | count)
+    if test $count -gt 0
+        printf 'guisu:%s' $count
+    end
+end
+";
+
+/// Print a shell completion script to stdout
+#[derive(Debug, Clone, Args)]
+pub struct CompletionCommand {
+    /// Shell to generate completions for
+    #[arg(value_enum, required_unless_present = "integration")]
+    pub shell: Option<CompletionShell>,
+
+    /// Print prompt integration functions for this shell instead
+    #[arg(long, value_enum, value_name = "SHELL", conflicts_with = "shell")]
+    pub integration: Option<IntegrationShell>,
+}
+
+impl CompletionCommand {
+    /// Write the completion or integration script to stdout
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if writing to stdout fails
+    pub fn run(&self) -> Result<()> {
+        let mut stdout = std::io::stdout().lock();
+        if let Some(shell) = self.integration {
+            stdout
+                .write_all(integration_script(shell).as_bytes())
+                .context("Failed to write shell integration")?;
+        } else if let Some(shell) = self.shell {
+            write_completions(shell, &mut stdout);
+        }
+        stdout.flush().context("Failed to write completions")
+    }
+}
+
+/// Prompt integration functions for `shell`
+fn integration_script(shell: IntegrationShell) -> &'static str {
+    match shell {
+        IntegrationShell::Bash => BASH_INTEGRATION,
+        IntegrationShell::Zsh => ZSH_INTEGRATION,
+        IntegrationShell::Fish => FISH_INTEGRATION,
+    }
+}
+
+fn write_completions(shell: CompletionShell, out: &mut dyn Write) {
+    use clap_complete::Shell;
+
+    let mut cmd = Cli::command();
+    let name = cmd.get_name().to_string();
+    match shell {
+        CompletionShell::Bash => clap_complete::generate(Shell::Bash, &mut cmd, name, out),
+        CompletionShell::Zsh => clap_complete::generate(Shell::Zsh, &mut cmd, name, out),
+        CompletionShell::Fish => clap_complete::generate(Shell::Fish, &mut cmd, name, out),
+        CompletionShell::Powershell => {
+            clap_complete::generate(Shell::PowerShell, &mut cmd, name, out);
+        }
+        CompletionShell::Nushell => {
+            clap_complete::generate(clap_complete_nushell::Nushell, &mut cmd, name, out);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    #![allow(clippy::unwrap_used, clippy::panic)]
+    use super::*;
+
+    fn generate(shell: CompletionShell) -> String {
+        let mut out = Vec::new();
+        write_completions(shell, &mut out);
+        String::from_utf8(out).unwrap()
+    }
+
+    #[test]
+    fn test_completions_cover_subcommands() {
+        for shell in CompletionShell::value_variants() {
+            let script = generate(*shell);
+            assert!(script.contains("guisu"), "{shell:?}");
+            assert!(script.contains("apply"), "{shell:?}");
+            assert!(script.contains("rollback"), "{shell:?}");
+        }
+    }
+
+    #[test]
+    fn test_integration_defines_prompt_function() {
+        for shell in IntegrationShell::value_variants() {
+            let script = integration_script(*shell);
+            assert!(script.contains("guisu_prompt_info"), "{shell:?}");
+            assert!(
+                script.contains("guisu status --porcelain --quick"),
+                "{shell:?}"
+            );
+        }
+    }
+}
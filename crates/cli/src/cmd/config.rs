@@ -0,0 +1,705 @@
+//! Config command implementation
+//!
+//! Show the effective configuration: the config file after rendering, merged
+//! with `.guisu/variables/`, `.guisu/ignores.toml` and the active profile,
+//! with every section annotated with the files it came from. Values that look
+//! like secrets are redacted.
+//!
+//! Edit the config file in an editor or set single keys from scripts. Changes
+//! are only written once the file still loads, and the rendered config cached
+//! in the state database is dropped so the next command reads the new file.
+
+use anyhow::{Context, Result, bail};
+use guisu_config::{Config, ConfigFormat};
+use guisu_engine::state::RedbPersistentState;
+use indexmap::IndexMap;
+use owo_colors::OwoColorize;
+use serde_json::{Map, Value};
+use std::fmt::Write as _;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+use crate::utils::path::SourceDirExt;
+use crate::utils::secrets::SecretScanner;
+
+/// Placeholder for redacted values
+const REDACTED: &str = "<redacted>";
+
+/// Origin of sections that are not set anywhere
+const DEFAULT_ORIGIN: &str = "default";
+
+/// Output format for `config show`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+pub enum ConfigFormatArg {
+    /// TOML with a comment naming the origin of each section
+    Toml,
+    /// JSON object with the config and its origins
+    Json,
+}
+
+/// Effective configuration and where each part of it came from
+#[derive(Debug, serde::Serialize)]
+struct ConfigDump {
+    /// Merged configuration, with secrets redacted
+    config: Value,
+    /// Files each top-level section came from, `default` if none
+    origins: IndexMap<String, Vec<String>>,
+    /// File that set each variable, keyed by dotted name
+    #[serde(rename = "variableOrigins")]
+    variable_origins: IndexMap<String, String>,
+}
+
+/// Run config show command
+///
+/// # Errors
+///
+/// Returns an error if the config file, variables, ignores or profiles cannot
+/// be read, or if the output cannot be serialized
+pub fn run_show(
+    source_dir: &Path,
+    config: &Config,
+    db: &Arc<RedbPersistentState>,
+    format: ConfigFormatArg,
+) -> Result<()> {
+    let dump = collect(source_dir, config, Some(db))?;
+    match format {
+        ConfigFormatArg::Json => println!(
+            "{}",
+            serde_json::to_string_pretty(&dump).context("Failed to serialize config")?
+        ),
+        ConfigFormatArg::Toml => print!("{}", format_toml(&dump)?),
+    }
+    Ok(())
+}
+
+/// Run config edit command
+///
+/// Opens a copy of the config file in the editor. Once the editor exits, the
+/// copy is written back if it still loads; otherwise the user can edit it
+/// again or discard the changes. `editor_config` picks the editor, so a
+/// config that fails to load can still be fixed.
+///
+/// # Errors
+///
+/// Returns an error if there is no config file, the editor fails, or the
+/// config file cannot be written
+pub fn run_edit(
+    source_dir: &Path,
+    editor_config: &Config,
+    db: &Arc<RedbPersistentState>,
+) -> Result<()> {
+    use dialoguer::{Confirm, theme::ColorfulTheme};
+
+    let (path, format, templated) = find_config(source_dir)?;
+    let label = config_label(&path);
+    let original =
+        fs::read_to_string(&path).with_context(|| format!("Failed to read {}", path.display()))?;
+
+    // Edit a copy with the same file name, so the editor picks the right syntax
+    let temp = tempfile::tempdir().context("Failed to create temporary directory")?;
+    let draft = temp.path().join(&label);
+    fs::write(&draft, &original).with_context(|| format!("Failed to write {}", draft.display()))?;
+
+    let (editor, args) = crate::cmd::edit::get_editor(editor_config);
+    loop {
+        crate::cmd::edit::run_editor(&editor, &args, &draft)?;
+        let content = fs::read_to_string(&draft)
+            .with_context(|| format!("Failed to read {}", draft.display()))?;
+        if content == original {
+            println!("No changes to {label}");
+            return Ok(());
+        }
+
+        match load(source_dir, &content, format, templated, Some(db)) {
+            Ok(_) => {
+                write_config(&path, &content, db)?;
+                println!("{} {label}", "Updated".green());
+                return Ok(());
+            }
+            Err(e) => {
+                eprintln!("{} {e:#}", "Invalid config:".red());
+                let again = Confirm::with_theme(&ColorfulTheme::default())
+                    .with_prompt("Edit again?")
+                    .default(true)
+                    .interact()?;
+                if !again {
+                    println!("Discarded changes, {label} is unchanged");
+                    return Ok(());
+                }
+            }
+        }
+    }
+}
+
+/// Run config set command
+///
+/// Sets `key`, a dotted path such as `age.identity`, to `value` in the config
+/// file, keeping its comments and layout. `value` is read as a TOML or JSON
+/// value when it is one (`true`, `3`, `["a", "b"]`) and as a string otherwise.
+///
+/// # Errors
+///
+/// Returns an error if there is no config file, the file cannot be edited
+/// (a template whose syntax is not valid TOML or JSON), `key` is not a config
+/// key, or the edited config no longer loads
+pub fn run_set(
+    source_dir: &Path,
+    db: &Arc<RedbPersistentState>,
+    key: &str,
+    value: &str,
+) -> Result<()> {
+    let (path, format, templated) = find_config(source_dir)?;
+    let label = config_label(&path);
+    let content =
+        fs::read_to_string(&path).with_context(|| format!("Failed to read {}", path.display()))?;
+
+    let edited = set_value(&content, format, key, value)
+        .with_context(|| format!("Cannot edit {label}; use `guisu config edit` instead"))?;
+    let config = load(source_dir, &edited, format, templated, Some(db))
+        .with_context(|| format!("Setting {key} would make {label} invalid"))?;
+    if !has_key(&config, key)? {
+        bail!("Unknown config key: {key}");
+    }
+
+    write_config(&path, &edited, db)?;
+    println!("{} {key} in {label}", "Set".green());
+    Ok(())
+}
+
+/// Config file in `source_dir`, with its format and whether it is a template
+fn find_config(source_dir: &Path) -> Result<(PathBuf, ConfigFormat, bool)> {
+    ConfigFormat::find(source_dir).ok_or_else(|| {
+        anyhow::anyhow!(
+            "No config file found in {}; create .guisu.toml first",
+            source_dir.display()
+        )
+    })
+}
+
+/// File name of the config file, used to refer to it in messages
+fn config_label(path: &Path) -> String {
+    path.file_name().map_or_else(
+        || crate::path_to_string(path),
+        |name| name.to_string_lossy().into_owned(),
+    )
+}
+
+/// Load config file content the way commands do, rendering a template first
+fn load(
+    source_dir: &Path,
+    content: &str,
+    format: ConfigFormat,
+    templated: bool,
+    db: Option<&Arc<RedbPersistentState>>,
+) -> Result<Config> {
+    let rendered;
+    let text = if templated {
+        rendered = crate::render_config_template(source_dir, content, db)?;
+        &rendered
+    } else {
+        content
+    };
+    let config = Config::from_str_with_format(text, format, source_dir)
+        .map_err(|e| anyhow::anyhow!("{e}"))?;
+    config.age.validate().map_err(|e| anyhow::anyhow!("{e}"))?;
+    Ok(config)
+}
+
+/// Write the config file and drop the rendered config cached from the old one
+fn write_config(path: &Path, content: &str, db: &RedbPersistentState) -> Result<()> {
+    fs::write(path, content).with_context(|| format!("Failed to write {}", path.display()))?;
+    guisu_engine::database::delete_config_metadata(db)
+        .context("Failed to clear the cached config")?;
+    Ok(())
+}
+
+/// Config file content with `key` set to `value`
+fn set_value(content: &str, format: ConfigFormat, key: &str, value: &str) -> Result<String> {
+    use toml_edit::{Item, Table};
+
+    let segments: Vec<&str> = key.split('.').collect();
+    let Some((last, parents)) = segments
+        .split_last()
+        .filter(|_| segments.iter().all(|segment| !segment.is_empty()))
+    else {
+        bail!("Invalid config key: {key}");
+    };
+    // Settings accept camelCase and snake_case names; variables are as written
+    let renamed = segments[0] != "variables";
+
+    match format {
+        ConfigFormat::Toml => {
+            let mut doc: toml_edit::DocumentMut = content.parse().context("Invalid TOML")?;
+            let mut new_value = value
+                .parse::<toml_edit::Value>()
+                .unwrap_or_else(|_| value.into());
+
+            let mut table: &mut dyn toml_edit::TableLike = doc.as_table_mut();
+            for (depth, segment) in parents.iter().enumerate() {
+                let segment = spelling(table.iter().map(|(k, _)| k), segment, renamed);
+                table = table
+                    .entry(&segment)
+                    .or_insert_with(|| {
+                        let mut child = Table::new();
+                        child.set_implicit(true);
+                        Item::Table(child)
+                    })
+                    .as_table_like_mut()
+                    .with_context(|| format!("{} is not a table", segments[..=depth].join(".")))?;
+            }
+
+            // Keep the comment after a value that is replaced
+            let last = spelling(table.iter().map(|(k, _)| k), last, renamed);
+            if let Some(old) = table.get(&last).and_then(Item::as_value) {
+                *new_value.decor_mut() = old.decor().clone();
+            }
+            table.insert(&last, Item::Value(new_value));
+            Ok(doc.to_string())
+        }
+        ConfigFormat::Json => {
+            let mut doc: Value = serde_json::from_str(content).context("Invalid JSON")?;
+            let new_value =
+                serde_json::from_str(value).unwrap_or_else(|_| Value::String(value.to_string()));
+
+            let mut object = &mut doc;
+            for (depth, segment) in parents.iter().enumerate() {
+                let map = object
+                    .as_object_mut()
+                    .with_context(|| format!("{} is not an object", segments[..depth].join(".")))?;
+                let segment = spelling(map.keys().map(String::as_str), segment, renamed);
+                object = map
+                    .entry(segment)
+                    .or_insert_with(|| Value::Object(Map::new()));
+            }
+            let map = object
+                .as_object_mut()
+                .with_context(|| format!("{} is not an object", parents.join(".")))?;
+            let last = spelling(map.keys().map(String::as_str), last, renamed);
+            map.insert(last, new_value);
+
+            let mut json = serde_json::to_string_pretty(&doc)?;
+            json.push('\n');
+            Ok(json)
+        }
+        ConfigFormat::Yaml => bail!("YAML config files are not supported yet"),
+    }
+}
+
+/// Whether `key` names a setting of `config`
+///
+/// Every key under `variables` is valid. Other keys must appear in the
+/// config once loaded, so misspelled keys, which serde ignores, are caught.
+/// `snake_case` keys match their camelCase names.
+fn has_key(config: &Config, key: &str) -> Result<bool> {
+    if key.split('.').next() == Some("variables") {
+        return Ok(true);
+    }
+    let mut value = &serde_json::to_value(config).context("Failed to serialize config")?;
+    for segment in key.split('.') {
+        let Some(child) = value
+            .get(segment)
+            .or_else(|| value.get(camel_case(segment)))
+        else {
+            return Ok(false);
+        };
+        value = child;
+    }
+    Ok(true)
+}
+
+/// Key of `keys` that is another spelling of `segment`, or `segment` itself
+///
+/// With `renamed`, `context_lines` and `contextLines` name the same setting,
+/// so setting one replaces the other instead of adding a duplicate.
+fn spelling<'a>(keys: impl IntoIterator<Item = &'a str>, segment: &str, renamed: bool) -> String {
+    let camel = camel_case(segment);
+    keys.into_iter()
+        .filter(|_| renamed)
+        .find(|key| *key == segment || camel_case(key) == camel)
+        .unwrap_or(segment)
+        .to_string()
+}
+
+/// camelCase form of a `snake_case` key
+fn camel_case(key: &str) -> String {
+    let mut words = key.split('_');
+    let mut camel = words.next().unwrap_or_default().to_string();
+    for word in words {
+        let mut chars = word.chars();
+        if let Some(first) = chars.next() {
+            camel.extend(first.to_uppercase());
+            camel.push_str(chars.as_str());
+        }
+    }
+    camel
+}
+
+/// Gather the effective configuration and its origins
+fn collect(
+    source_dir: &Path,
+    config: &Config,
+    db: Option<&Arc<RedbPersistentState>>,
+) -> Result<ConfigDump> {
+    let mut value = serde_json::to_value(config).context("Failed to serialize config")?;
+    let scanner = SecretScanner::new(&config.security)?;
+    redact(&mut value, "", &scanner);
+
+    let document = config_document(source_dir, db)?;
+    let variable_origins = variable_origins(source_dir, config, document.as_ref())?;
+
+    let ignores = guisu_config::IgnoresConfig::load(source_dir)
+        .map_err(|e| anyhow::anyhow!("Failed to load .guisu/ignores.toml: {e}"))?;
+    let has_ignores = !(ignores.global.is_empty()
+        && ignores.darwin.is_empty()
+        && ignores.linux.is_empty()
+        && ignores.windows.is_empty());
+
+    let mut origins = IndexMap::new();
+    if let Value::Object(sections) = &value {
+        for section in sections.keys() {
+            let mut sources = Vec::new();
+            if let Some((label, Value::Object(doc))) = &document
+                && doc.contains_key(section)
+            {
+                sources.push(label.clone());
+            }
+            match section.as_str() {
+                "ignore" if has_ignores => sources.push(".guisu/ignores.toml".to_string()),
+                "variables" => {
+                    for origin in variable_origins.values() {
+                        if !sources.contains(origin) {
+                            sources.push(origin.clone());
+                        }
+                    }
+                }
+                _ => {}
+            }
+            if sources.is_empty() {
+                sources.push(DEFAULT_ORIGIN.to_string());
+            }
+            origins.insert(section.clone(), sources);
+        }
+    }
+
+    Ok(ConfigDump {
+        config: value,
+        origins,
+        variable_origins,
+    })
+}
+
+/// The config file as written, after rendering, labeled with its file name
+///
+/// A rendered template is taken from the state database when it is still
+/// current, and rendered again otherwise.
+fn config_document(
+    source_dir: &Path,
+    db: Option<&Arc<RedbPersistentState>>,
+) -> Result<Option<(String, Value)>> {
+    let Some((path, format, templated)) = ConfigFormat::find(source_dir) else {
+        return Ok(None);
+    };
+    let label = config_label(&path);
+    let content =
+        fs::read_to_string(&path).with_context(|| format!("Failed to read {}", path.display()))?;
+
+    let text = if templated {
+        let cached = db
+            .and_then(|db| {
+                guisu_engine::database::get_config_metadata(db)
+                    .ok()
+                    .flatten()
+            })
+            .filter(|metadata| metadata.template_matches(&content));
+        match cached {
+            Some(metadata) => metadata.rendered_config,
+            None => crate::render_config_template(source_dir, &content, db)?,
+        }
+    } else {
+        content
+    };
+
+    let document = match format {
+        ConfigFormat::Toml => {
+            toml::from_str(&text).with_context(|| format!("Failed to parse {}", path.display()))?
+        }
+        ConfigFormat::Json => serde_json::from_str(&text)
+            .with_context(|| format!("Failed to parse {}", path.display()))?,
+        ConfigFormat::Yaml => Value::Object(Map::new()),
+    };
+    Ok(Some((label, document)))
+}
+
+/// File that set each variable, keyed by dotted name
+///
+/// Replays the order variables are loaded in: the config file's `[variables]`,
+/// then `.guisu/variables/` and data files, each replacing a whole top-level
+/// variable, then the active profile, merged key by key.
+fn variable_origins(
+    source_dir: &Path,
+    config: &Config,
+    document: Option<&(String, Value)>,
+) -> Result<IndexMap<String, String>> {
+    let mut origins = IndexMap::new();
+    if let Some((label, Value::Object(doc))) = document
+        && let Some(Value::Object(variables)) = doc.get("variables")
+    {
+        for (key, value) in variables {
+            guisu_config::variables::record_origins(&mut origins, key, value, label);
+        }
+    }
+
+    let platform = guisu_core::platform::CURRENT_PLATFORM.os;
+    let loaded = guisu_config::variables::variable_origins(&source_dir.guisu_dir(), platform)
+        .context("Failed to load variables from .guisu/variables/")?;
+    for key in loaded.keys() {
+        let top = key.split('.').next().unwrap_or(key);
+        let nested = format!("{top}.");
+        origins.retain(|k: &String, _| k != top && !k.starts_with(&nested));
+    }
+    for (key, path) in loaded {
+        let path = path.strip_prefix(source_dir).unwrap_or(&path);
+        origins.insert(key, crate::path_to_string(path));
+    }
+
+    if let Some(name) = &config.general.profile {
+        let profiles = guisu_config::Profiles::load(source_dir)
+            .map_err(|e| anyhow::anyhow!("Failed to load .guisu/profiles.toml: {e}"))?;
+        if let Ok(profile) = profiles.get(name) {
+            let origin = format!(".guisu/profiles.toml [{name}]");
+            for (key, value) in &profile.variables {
+                guisu_config::variables::record_origins(&mut origins, key, value, &origin);
+            }
+        }
+    }
+
+    Ok(origins)
+}
+
+/// Replace string values that look like secrets with a placeholder
+///
+/// A value is redacted when `key = value` matches a secret pattern, such as
+/// a password or token assignment, a private key or a high-entropy string.
+fn redact(value: &mut Value, key: &str, scanner: &SecretScanner) {
+    match value {
+        Value::String(text) => {
+            let name = key.rsplit('.').next().unwrap_or(key);
+            let line = format!("{name} = {text}");
+            if scanner.scan(Path::new(""), line.as_bytes()).is_some() {
+                *text = REDACTED.to_string();
+            }
+        }
+        Value::Object(map) => {
+            for (child, value) in map.iter_mut() {
+                let path = if key.is_empty() {
+                    child.clone()
+                } else {
+                    format!("{key}.{child}")
+                };
+                redact(value, &path, scanner);
+            }
+        }
+        Value::Array(items) => {
+            for item in items {
+                redact(item, key, scanner);
+            }
+        }
+        _ => {}
+    }
+}
+
+/// Format the config as TOML, one table per section under a comment naming
+/// its origins
+fn format_toml(dump: &ConfigDump) -> Result<String> {
+    let mut output = String::new();
+    let Value::Object(sections) = &dump.config else {
+        return Ok(output);
+    };
+    for (section, value) in sections {
+        let origins = dump
+            .origins
+            .get(section)
+            .map(|origins| origins.join(", "))
+            .unwrap_or_default();
+        let mut table = Map::new();
+        table.insert(section.clone(), without_nulls(value.clone()));
+        let text = toml::to_string_pretty(&table)
+            .with_context(|| format!("Failed to format [{section}] as TOML"))?;
+
+        let _ = writeln!(output, "# [{section}] from {origins}\n");
+        output.push_str(&text);
+        if !text.ends_with("\n\n") {
+            output.push('\n');
+        }
+    }
+    Ok(output)
+}
+
+/// Drop null values, which TOML cannot represent
+fn without_nulls(value: Value) -> Value {
+    match value {
+        Value::Object(map) => Value::Object(
+            map.into_iter()
+                .filter(|(_, value)| !value.is_null())
+                .map(|(key, value)| (key, without_nulls(value)))
+                .collect(),
+        ),
+        Value::Array(items) => Value::Array(items.into_iter().map(without_nulls).collect()),
+        other => other,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    #![allow(clippy::unwrap_used, clippy::panic)]
+    use super::*;
+    use std::fs;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_collect_origins_and_redaction() {
+        let temp = TempDir::new().unwrap();
+        let source = temp.path();
+        fs::write(
+            source.join(".guisu.toml"),
+            "[general]\nrootEntry = \"home\"\n\n[variables]\nname = \"me\"\n\
+             api_token = \"s3cr3t-v4lue-123\"\n\n[variables.git]\nemail = \"me@home\"\n",
+        )
+        .unwrap();
+        fs::create_dir_all(source.join(".guisu/variables")).unwrap();
+        fs::write(
+            source.join(".guisu/variables/git.toml"),
+            "email = \"me@example.com\"\n",
+        )
+        .unwrap();
+        fs::write(
+            source.join(".guisu/ignores.toml"),
+            "global = [\"README.md\"]\n",
+        )
+        .unwrap();
+
+        let config = Config::load_with_variables(None, source).unwrap();
+        let dump = collect(source, &config, None).unwrap();
+
+        assert_eq!(dump.origins["general"], vec![".guisu.toml"]);
+        assert_eq!(dump.origins["age"], vec![DEFAULT_ORIGIN]);
+        assert_eq!(
+            dump.origins["ignore"],
+            vec![".guisu/ignores.toml".to_string()]
+        );
+        assert_eq!(
+            dump.origins["variables"],
+            vec![".guisu.toml", ".guisu/variables/git.toml"]
+        );
+        assert_eq!(dump.variable_origins["name"], ".guisu.toml");
+        assert_eq!(
+            dump.variable_origins["git.email"],
+            ".guisu/variables/git.toml"
+        );
+
+        assert_eq!(dump.config["variables"]["name"], "me");
+        assert_eq!(dump.config["variables"]["api_token"], REDACTED);
+        assert_eq!(dump.config["variables"]["git"]["email"], "me@example.com");
+
+        let text = format_toml(&dump).unwrap();
+        assert!(text.contains("[general] from .guisu.toml"), "{text}");
+        assert!(!text.contains("s3cr3t"), "{text}");
+    }
+
+    #[test]
+    fn test_collect_rendered_template() {
+        let temp = TempDir::new().unwrap();
+        let source = temp.path();
+        fs::write(
+            source.join(".guisu.toml.j2"),
+            "[ui]\ncontextLines = {{ 2 + 3 }}\n",
+        )
+        .unwrap();
+
+        let config = crate::load_config_with_template_support(None, source, None).unwrap();
+        let dump = collect(source, &config, None).unwrap();
+
+        assert_eq!(dump.origins["ui"], vec![".guisu.toml.j2"]);
+        assert_eq!(dump.config["ui"]["contextLines"], 5);
+    }
+
+    #[test]
+    fn test_set_value_toml_keeps_comments() {
+        let content = "# My config\n[ui]\ncontextLines = 3 # lines around changes\n";
+
+        let edited = set_value(content, ConfigFormat::Toml, "ui.contextLines", "5").unwrap();
+        assert_eq!(
+            edited,
+            "# My config\n[ui]\ncontextLines = 5 # lines around changes\n"
+        );
+
+        let edited = set_value(
+            content,
+            ConfigFormat::Toml,
+            "age.identity",
+            "~/.config/guisu/key.txt",
+        )
+        .unwrap();
+        assert!(edited.starts_with(content), "{edited}");
+        assert!(
+            edited.contains("[age]\nidentity = \"~/.config/guisu/key.txt\"\n"),
+            "{edited}"
+        );
+
+        let edited = set_value(content, ConfigFormat::Toml, "variables.git.email", "me@x").unwrap();
+        assert!(
+            edited.contains("[variables.git]\nemail = \"me@x\"\n"),
+            "{edited}"
+        );
+        assert!(!edited.contains("[variables]\n"), "{edited}");
+
+        assert!(set_value(content, ConfigFormat::Toml, "ui.contextLines.x", "1").is_err());
+        assert!(set_value(content, ConfigFormat::Toml, "ui..x", "1").is_err());
+    }
+
+    #[test]
+    fn test_set_value_json() {
+        let content = "{\n  \"ui\": {\n    \"contextLines\": 3\n  }\n}\n";
+
+        let edited = set_value(content, ConfigFormat::Json, "ui.contextLines", "5").unwrap();
+        let doc: Value = serde_json::from_str(&edited).unwrap();
+        assert_eq!(doc["ui"]["contextLines"], 5);
+
+        let edited = set_value(content, ConfigFormat::Json, "general.editor", "nvim").unwrap();
+        let doc: Value = serde_json::from_str(&edited).unwrap();
+        assert_eq!(doc["general"]["editor"], "nvim");
+    }
+
+    #[test]
+    fn test_run_set_validates_and_clears_cache() {
+        let temp = TempDir::new().unwrap();
+        let source = temp.path().join("source");
+        fs::create_dir_all(&source).unwrap();
+        let config_path = source.join(".guisu.toml");
+        fs::write(&config_path, "[ui]\ncontextLines = 3\n").unwrap();
+        let db = Arc::new(RedbPersistentState::new(temp.path().join("state.db")).unwrap());
+        guisu_engine::database::save_config_metadata(&db, "template", "rendered".to_string())
+            .unwrap();
+
+        run_set(&source, &db, "ui.context_lines", "7").unwrap();
+        assert_eq!(
+            fs::read_to_string(&config_path).unwrap(),
+            "[ui]\ncontextLines = 7\n"
+        );
+        assert!(
+            guisu_engine::database::get_config_metadata(&db)
+                .unwrap()
+                .is_none()
+        );
+
+        let err = run_set(&source, &db, "ui.contextLine", "7").unwrap_err();
+        assert!(err.to_string().contains("Unknown config key"), "{err}");
+        let err = run_set(&source, &db, "ui.contextLines", "many").unwrap_err();
+        assert!(err.to_string().contains("would make"), "{err}");
+        assert_eq!(
+            fs::read_to_string(&config_path).unwrap(),
+            "[ui]\ncontextLines = 7\n"
+        );
+    }
+}
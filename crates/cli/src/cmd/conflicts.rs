@@ -0,0 +1,100 @@
+//! Conflicts command operations
+//!
+//! When apply overwrites a destination file with local modifications, the
+//! overwritten content is saved as a conflict snapshot first. This module
+//! provides commands for inspecting those snapshots:
+//! - list: List saved snapshots, oldest first
+//! - show: Print the saved content of a snapshot
+
+use anyhow::{Context, Result, bail};
+use guisu_engine::state::{ConflictSnapshot, RedbPersistentState};
+use owo_colors::OwoColorize;
+use std::io::Write;
+
+/// Run conflicts list command
+///
+/// # Errors
+///
+/// Returns an error if the snapshots cannot be read from the database
+pub fn run_list(db: &RedbPersistentState) -> Result<()> {
+    let snapshots = guisu_engine::database::get_all_conflict_snapshots(db)
+        .context("Failed to read conflict snapshots")?;
+
+    if snapshots.is_empty() {
+        println!("No overwritten local changes saved.");
+        return Ok(());
+    }
+
+    for (id, snapshot) in &snapshots {
+        println!(
+            "{}  {}  {}",
+            id.yellow(),
+            format_saved_at(snapshot).dimmed(),
+            snapshot.path.bright_white()
+        );
+    }
+
+    Ok(())
+}
+
+/// Run conflicts show command
+///
+/// Writes the saved content to stdout unchanged, so it can be redirected back
+/// over the destination file.
+///
+/// # Errors
+///
+/// Returns an error if no snapshot has the given ID or writing to stdout fails
+pub fn run_show(db: &RedbPersistentState, id: &str) -> Result<()> {
+    let Some(snapshot) = guisu_engine::database::get_conflict_snapshot(db, id)
+        .context("Failed to read conflict snapshot")?
+    else {
+        bail!(
+            "No conflict snapshot with ID '{id}'. Run `guisu conflicts list` to see saved snapshots."
+        );
+    };
+
+    let mut stdout = std::io::stdout().lock();
+    stdout
+        .write_all(&snapshot.content)
+        .and_then(|()| stdout.flush())
+        .context("Failed to write snapshot content")?;
+
+    Ok(())
+}
+
+/// Format when the snapshot was taken, in local time
+fn format_saved_at(snapshot: &ConflictSnapshot) -> String {
+    chrono::DateTime::<chrono::Local>::from(snapshot.stamp.time())
+        .format("%Y-%m-%d %H:%M:%S")
+        .to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    #![allow(clippy::unwrap_used, clippy::panic)]
+    use super::*;
+    use guisu_engine::clock::StateClock;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_run_show_unknown_id() {
+        let temp = TempDir::new().unwrap();
+        let db = RedbPersistentState::new(temp.path().join("state.db")).unwrap();
+
+        let err = run_show(&db, "deadbeef").unwrap_err();
+        assert!(err.to_string().contains("deadbeef"));
+    }
+
+    #[test]
+    fn test_run_show_and_list_saved_snapshot() {
+        let temp = TempDir::new().unwrap();
+        let db = RedbPersistentState::new(temp.path().join("state.db")).unwrap();
+        let stamp = StateClock::fixed(1_700_000_000).begin_run();
+        let snapshot = ConflictSnapshot::new(".zshrc", b"alias k=kubectl\n".to_vec(), None, &stamp);
+        let id = guisu_engine::database::save_conflict_snapshot(&db, &snapshot).unwrap();
+
+        run_list(&db).unwrap();
+        run_show(&db, &id).unwrap();
+    }
+}
@@ -1,20 +1,24 @@
 //! Diff command implementation
 //!
 //! Show differences between source and destination states.
+//!
+//! Encrypted files are decrypted in memory only. With `--secrets`, their
+//! decrypted content can be masked or left out of the output entirely.
 
 use anyhow::{Context, Result};
 use clap::Args;
-use guisu_core::path::AbsPath;
 use guisu_engine::adapters::crypto::CryptoDecryptorAdapter;
-use guisu_engine::adapters::template::TemplateRendererAdapter;
+use guisu_engine::adapters::template::{TemplateRendererAdapter, template_context};
 use guisu_engine::entry::{SourceEntry, TargetEntry};
 use guisu_engine::hooks::config::HookMode;
+use guisu_engine::pool::ContentPool;
 use guisu_engine::processor::ContentProcessor;
-use guisu_engine::state::{RedbPersistentState, SourceState, TargetState};
-use guisu_template::TemplateContext;
+use guisu_engine::state::{ManagedKind, RedbPersistentState, SourceState, TargetState};
+use indicatif::ProgressBar;
 use owo_colors::OwoColorize;
 use rayon::prelude::*;
-use similar::{ChangeTag, TextDiff};
+use similar::{ChangeTag, DiffTag, TextDiff};
+use std::borrow::Cow;
 use std::collections::HashSet;
 use std::env;
 use std::fmt::Write as FmtWrite;
@@ -24,25 +28,59 @@ use std::path::Path;
 use std::path::PathBuf;
 use std::process::{Command as ProcessCommand, Stdio};
 use std::sync::Arc;
-use tracing::{debug, warn};
+use tracing::{debug, trace_span, warn};
 
+use crate::cmd::apply::{DeletedTarget, deleted_from_source, exact_removals};
 use crate::command::Command;
-use crate::common::RuntimeContext;
-use crate::stats::DiffStats;
+use crate::common::{EntryFilter, ResolvedPaths, RuntimeContext};
+use crate::stats::{DiffStats, Phase, Timed, Timings};
+use crate::ui::progress;
 use crate::ui::{FileDiff, FileStatus, InteractiveDiffViewer};
-use crate::utils::path::SourceDirExt;
-use guisu_config::Config;
+use crate::utils::dest::DestProbe;
+use crate::utils::secrets::{KeyChanges, SecretScanner, is_world_readable};
+use guisu_config::{Config, SecretAction};
 
 // File permission constants
 const PERM_MASK: u32 = 0o7777; // Permission bits mask (rwxrwxrwx)
 const S_IFREG: u32 = 0o100_000; // Regular file type bit
 const DEFAULT_FILE_MODE: u32 = 0o644; // Default file permissions (rw-r--r--)
+const DEFAULT_CONTEXT_LINES: usize = 3; // Context lines around each hunk
 
 // Binary detection constants
 const BINARY_CHECK_BYTES: usize = 8000; // Check first 8KB for null bytes
 
+/// Replacement for values of encrypted files with `--secrets masked`
+const MASK: &str = "***";
+
+/// Output format for the diff command
+#[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+pub enum DiffFormat {
+    /// Colored unified diff
+    Text,
+    /// JSON object with one record per changed file
+    Json,
+}
+
+/// How the decrypted content of encrypted files is shown
+///
+/// Secret env files (`env_` prefix) are always shown as `keys`, unless they
+/// are encrypted and `hidden` is asked for.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, clap::ValueEnum)]
+pub enum SecretDisplay {
+    /// Only say that the encrypted contents differ
+    Hidden,
+    /// Only list the names of added, changed, and removed `KEY=value` keys
+    Keys,
+    /// Show the changed lines with their values replaced by ***
+    Masked,
+    /// Show the decrypted content
+    #[default]
+    Plain,
+}
+
 /// Diff command
 #[derive(Args)]
+#[allow(clippy::struct_excessive_bools)]
 pub struct DiffCommand {
     /// Specific files to diff (all if not specified)
     pub files: Vec<PathBuf>,
@@ -54,34 +92,123 @@ pub struct DiffCommand {
     /// Interactive diff viewer
     #[arg(short, long)]
     pub interactive: bool,
+
+    /// Lines of context around each change (default: ui.contextLines)
+    #[arg(short = 'U', long, value_name = "N")]
+    pub context: Option<usize>,
+
+    /// Ignore whitespace when comparing lines
+    #[arg(short = 'w', long)]
+    pub ignore_all_space: bool,
+
+    /// Ignore changes whose lines are all blank
+    #[arg(long)]
+    pub ignore_blank_lines: bool,
+
+    /// Output format
+    #[arg(long, value_enum, default_value = "text", conflicts_with_all = ["pager", "interactive"])]
+    pub format: DiffFormat,
+
+    /// Include only these entry types or target path globs (comma-separated)
+    #[arg(long, value_delimiter = ',')]
+    pub include: Vec<String>,
+
+    /// Exclude these entry types or target path globs (comma-separated)
+    #[arg(long, value_delimiter = ',')]
+    pub exclude: Vec<String>,
+
+    /// Print how long each phase took (scan, render, decrypt, hash, io)
+    #[arg(long)]
+    pub timings: bool,
+
+    /// How to show decrypted content of encrypted files (the interactive
+    /// viewer shows masked files as hidden)
+    #[arg(long, value_enum, default_value = "plain")]
+    pub secrets: SecretDisplay,
 }
 
 impl Command for DiffCommand {
     type Output = ();
     fn execute(&self, context: &RuntimeContext) -> crate::error::Result<()> {
+        let timings = Arc::new(Timings::new());
+        let result = self.run(context, &timings);
+        if self.timings {
+            eprint!("{}", timings.report());
+        }
+        result
+    }
+}
+
+impl DiffCommand {
+    fn run(&self, context: &RuntimeContext, timings: &Arc<Timings>) -> crate::error::Result<()> {
+        let options = DiffOptions {
+            context: self.context.unwrap_or(context.config.ui.context_lines),
+            ignore_all_space: self.ignore_all_space,
+            ignore_blank_lines: self.ignore_blank_lines,
+            secrets: self.secrets,
+        };
+        let entry_filter = EntryFilter::new(&self.include, &self.exclude)?;
+
+        if self.format == DiffFormat::Json {
+            let report = collect_diff_report(
+                context.source_dir(),
+                context.dest_dir().as_path(),
+                &self.files,
+                &entry_filter,
+                &options,
+                &context.config,
+                context.database(),
+                timings,
+            )?;
+            println!(
+                "{}",
+                serde_json::to_string_pretty(&report).context("Failed to serialize diff report")?
+            );
+            return Ok(());
+        }
+
         run_impl(
             context.source_dir(),
             context.dest_dir().as_path(),
             &self.files,
+            &entry_filter,
             self.pager,
             self.interactive,
+            &options,
             &context.config,
             &context.database,
+            timings,
         )
         .map_err(Into::into)
     }
 }
 
 /// Handle file processing errors, showing detailed messages for first error only
+///
+/// Returns `false` for entries that are expected to be unavailable on this
+/// machine, `true` for real failures.
 fn handle_file_processing_error<E: std::fmt::Display>(
     error: &E,
     target_path: &guisu_core::path::RelPath,
     identities: &[guisu_crypto::Identity],
     shown_decryption_error: &std::sync::Arc<std::sync::atomic::AtomicBool>,
     config: &Config,
-) {
+) -> bool {
     let error_msg = error.to_string();
 
+    // Scoped entries are only encrypted to their scope's recipients, so other
+    // machines are expected to be unable to decrypt them
+    if error_msg.contains("Decryption failed")
+        && matches!(config.age_scope_for(target_path.as_path()), Ok(Some(_)))
+    {
+        eprintln!(
+            "{} {}: not available on this machine",
+            "ℹ".bright_blue(),
+            target_path.as_path().display()
+        );
+        return false;
+    }
+
     if error_msg.contains("Decryption failed") {
         if !shown_decryption_error.swap(true, std::sync::atomic::Ordering::Relaxed) {
             // First decryption error - check if it's a missing identity file
@@ -121,24 +248,40 @@ fn handle_file_processing_error<E: std::fmt::Display>(
             error
         );
     }
+    true
 }
 
 /// Build target state by processing source entries
-#[allow(clippy::too_many_arguments)]
+///
+/// Entries that fail to render are reported and left out of the target state;
+/// their paths and error messages are returned alongside it. Progress is shown
+/// on `progress`.
+#[allow(clippy::too_many_arguments, clippy::too_many_lines)]
 fn build_diff_target_state(
     source_state: &SourceState,
     filter_paths: Option<&Vec<guisu_core::path::RelPath>>,
     ignore_matcher: &guisu_config::IgnoreMatcher,
-    processor: &ContentProcessor<CryptoDecryptorAdapter, TemplateRendererAdapter>,
+    processor: &ContentProcessor<Timed<CryptoDecryptorAdapter>, Timed<TemplateRendererAdapter>>,
     template_ctx_value: &serde_json::Value,
     identities: &[guisu_crypto::Identity],
     shown_decryption_error: &std::sync::Arc<std::sync::atomic::AtomicBool>,
     config: &Config,
-) -> TargetState {
+    timings: &Timings,
+    progress: &ProgressBar,
+) -> (
+    TargetState,
+    Vec<(guisu_core::path::RelPath, String)>,
+    HashSet<guisu_core::path::RelPath>,
+) {
     let mut target_state = TargetState::new();
+    let mut failed = Vec::new();
+    let mut encrypted = HashSet::new();
+    let pool = ContentPool::new();
 
     for source_entry in source_state.entries() {
+        progress.inc(1);
         let target_path = source_entry.target_path();
+        let _span = trace_span!("process_entry", path = %target_path).entered();
 
         // Skip if file is ignored
         if ignore_matcher.is_ignored(target_path.as_path(), None) {
@@ -152,6 +295,14 @@ fn build_diff_target_state(
             continue;
         }
 
+        if let Some(link_target) = source_state.link_target(source_entry, config.apply.mode) {
+            target_state.add(TargetEntry::Symlink {
+                path: target_path.clone(),
+                target: link_target.as_path().to_path_buf(),
+            });
+            continue;
+        }
+
         // Process this entry manually to handle errors gracefully
         match source_entry {
             SourceEntry::File {
@@ -163,17 +314,27 @@ fn build_diff_target_state(
                 match processor.process_file(&abs_source_path, attributes, template_ctx_value) {
                     Ok(mut content) => {
                         // Decrypt inline age: values (sops-like behavior)
+                        let mut decrypted_inline = false;
                         if !identities.is_empty()
                             && let Ok(content_str) = String::from_utf8(content.clone())
                             && content_str.contains("age:")
-                            && let Ok(decrypted) =
+                            && let Ok(decrypted) = timings.time(Phase::Decrypt, || {
                                 guisu_crypto::decrypt_file_content(&content_str, identities)
+                            })
                         {
+                            decrypted_inline = decrypted != content_str;
                             content = decrypted.into_bytes();
                         }
+                        if attributes.is_encrypted() || decrypted_inline {
+                            encrypted.insert(target_path.clone());
+                        }
+                        if attributes.is_env() {
+                            target_state.set_env_file(target_path.clone());
+                        }
 
                         let mode = attributes.mode();
-                        let content_hash = guisu_engine::hash::hash_content(&content);
+                        let (content, content_hash) =
+                            timings.time(Phase::Hash, || pool.intern(content));
                         target_state.add(TargetEntry::File {
                             path: target_path.clone(),
                             content,
@@ -182,13 +343,17 @@ fn build_diff_target_state(
                         });
                     }
                     Err(e) => {
-                        handle_file_processing_error(
-                            &e,
-                            target_path,
-                            identities,
-                            shown_decryption_error,
-                            config,
-                        );
+                        if progress.suspend(|| {
+                            handle_file_processing_error(
+                                &e,
+                                target_path,
+                                identities,
+                                shown_decryption_error,
+                                config,
+                            )
+                        }) {
+                            failed.push((target_path.clone(), e.to_string()));
+                        }
                     }
                 }
             }
@@ -204,29 +369,53 @@ fn build_diff_target_state(
                 });
             }
             SourceEntry::Symlink {
-                source_path: _,
+                source_path,
                 target_path,
-                link_target,
-            } => {
-                target_state.add(TargetEntry::Symlink {
+                attributes,
+            } => match source_state.symlink_target(
+                source_path,
+                attributes,
+                processor,
+                template_ctx_value,
+            ) {
+                Ok(target) => target_state.add(TargetEntry::Symlink {
                     path: target_path.clone(),
-                    target: link_target.clone(),
-                });
-            }
+                    target,
+                }),
+                Err(e) => {
+                    if progress.suspend(|| {
+                        handle_file_processing_error(
+                            &e,
+                            target_path,
+                            identities,
+                            shown_decryption_error,
+                            config,
+                        )
+                    }) {
+                        failed.push((target_path.clone(), e.to_string()));
+                    }
+                }
+            },
+            // Scripts are run during apply, not written to the destination
+            SourceEntry::Script { .. } => {}
         }
     }
 
-    target_state
+    (target_state, failed, encrypted)
 }
 
 /// Generate diff outputs in parallel
+#[allow(clippy::too_many_arguments)]
 fn generate_diff_outputs(
     target_state: &TargetState,
     filter_paths: Option<&Vec<guisu_core::path::RelPath>>,
+    encrypted: &HashSet<guisu_core::path::RelPath>,
     metadata: &guisu_engine::state::Metadata,
-    dest_abs: &AbsPath,
+    paths: &ResolvedPaths,
     stats: &DiffStats,
+    options: &DiffOptions,
     config: &Config,
+    timings: &Timings,
 ) -> Vec<String> {
     target_state
         .entries()
@@ -249,7 +438,7 @@ fn generate_diff_outputs(
 
             // Skip create-once files that already exist at destination (silently)
             if metadata.is_create_once(&path_str) {
-                let dest_path = dest_abs.join(target_path);
+                let dest_path = paths.dest_path(target_path);
                 if dest_path.as_path().exists() {
                     debug!(
                         path = %path_str,
@@ -259,7 +448,11 @@ fn generate_diff_outputs(
                 }
             }
 
-            match diff_target_entry(entry, dest_abs, stats) {
+            let options = options.for_entry(
+                encrypted.contains(target_path),
+                target_state.is_env_file(target_path),
+            );
+            match diff_target_entry(entry, paths, stats, &options, timings) {
                 Ok(entry_diff) => {
                     if entry_diff.is_empty() {
                         None
@@ -289,8 +482,10 @@ fn generate_diff_outputs(
 fn build_interactive_file_diffs(
     target_state: &TargetState,
     filter_paths: Option<&Vec<guisu_core::path::RelPath>>,
+    encrypted: &HashSet<guisu_core::path::RelPath>,
     metadata: &guisu_engine::state::Metadata,
-    dest_abs: &AbsPath,
+    paths: &ResolvedPaths,
+    options: &DiffOptions,
 ) -> Vec<crate::ui::FileDiff> {
     target_state
         .entries()
@@ -311,7 +506,7 @@ fn build_interactive_file_diffs(
 
             // Skip create-once files that already exist at destination
             if metadata.is_create_once(&path_str) {
-                let dest_path = dest_abs.join(target_path);
+                let dest_path = paths.dest_path(target_path);
                 if dest_path.as_path().exists() {
                     return None;
                 }
@@ -322,7 +517,7 @@ fn build_interactive_file_diffs(
                 ..
             } = entry
             {
-                let dest_path = dest_abs.join(target_path);
+                let dest_path = paths.dest_path(target_path);
 
                 // Determine file status and content
                 let (file_status, old_content, new_content) = if !dest_path.as_path().exists() {
@@ -346,10 +541,30 @@ fn build_interactive_file_diffs(
                 };
 
                 // Only include files that have actual changes
-                if file_status == FileStatus::Modified && old_content == new_content {
+                if file_status == FileStatus::Modified
+                    && options.contents_equal(&old_content, &new_content)
+                {
                     return None;
                 }
 
+                // The viewer shows whole files, so concealed content is left out
+                let options = options.for_entry(
+                    encrypted.contains(target_path),
+                    target_state.is_env_file(target_path),
+                );
+                let (old_content, new_content) = match options.secrets {
+                    SecretDisplay::Plain => (old_content, new_content),
+                    SecretDisplay::Keys => {
+                        let changes = KeyChanges::between(&old_content, &new_content);
+                        let mut lines = changes.lines().join("\n");
+                        lines.push('\n');
+                        (String::new(), lines)
+                    }
+                    SecretDisplay::Hidden | SecretDisplay::Masked => {
+                        (String::new(), "Encrypted contents differ\n".to_string())
+                    }
+                };
+
                 Some(FileDiff::new(
                     path_str,
                     old_content,
@@ -365,7 +580,7 @@ fn build_interactive_file_diffs(
 
 /// Display diff output for non-interactive mode
 fn display_diff_output(
-    source_dir: &Path,
+    paths: &ResolvedPaths,
     diff_outputs: &[String],
     stats: &DiffStats,
     pager: bool,
@@ -373,7 +588,7 @@ fn display_diff_output(
     db: &RedbPersistentState,
 ) -> Result<()> {
     // Check and display hooks status first
-    let hooks_displayed = print_hooks_status(source_dir, config, db);
+    let hooks_displayed = print_hooks_status(paths, config, db);
 
     // Join all diff outputs
     let diff_output = diff_outputs.join("\n");
@@ -389,7 +604,8 @@ fn display_diff_output(
     }
 
     // Print statistics at the end (only add blank line if there was content above)
-    let has_stats = stats.added() > 0 || stats.modified() > 0 || stats.errors() > 0;
+    let has_stats =
+        stats.added() > 0 || stats.modified() > 0 || stats.removed() > 0 || stats.errors() > 0;
     if has_stats && (hooks_displayed || !diff_output.is_empty()) {
         println!();
     }
@@ -398,92 +614,636 @@ fn display_diff_output(
     Ok(())
 }
 
+/// Warn about changed files that apply would write world-readable with secrets
+///
+/// # Errors
+///
+/// Returns an error if the `[security]` allowlist is invalid
+fn warn_exposed_secrets(plan: &DiffPlan, config: &Config) -> Result<()> {
+    if config.security.apply == SecretAction::Ignore {
+        return Ok(());
+    }
+
+    let scanner = SecretScanner::new(&config.security)?;
+    let mut exposed: Vec<_> = plan
+        .target_state
+        .entries()
+        .par_bridge()
+        .filter_map(|entry| {
+            let TargetEntry::File {
+                path,
+                content,
+                mode,
+                ..
+            } = entry
+            else {
+                return None;
+            };
+            if plan
+                .filter_paths
+                .as_ref()
+                .is_some_and(|filter| !filter.contains(path))
+            {
+                return None;
+            }
+            let dest = DestProbe::new(plan.paths.dest_path(path));
+            if !is_world_readable(*mode, dest.mode())
+                || dest.content().is_ok_and(|current| current == &**content)
+            {
+                return None;
+            }
+            scanner
+                .scan(path.as_path(), content)
+                .map(|findings| (path.to_string(), findings))
+        })
+        .collect();
+    exposed.sort();
+
+    for (path, findings) in &exposed {
+        warn!("Potential secrets would be written to world-readable {path}:\n{findings}");
+    }
+    Ok(())
+}
+
+/// Target state and lookup data shared by the diff renderers
+pub(crate) struct DiffPlan {
+    pub(crate) paths: ResolvedPaths,
+    pub(crate) metadata: guisu_engine::state::Metadata,
+    pub(crate) filter_paths: Option<Vec<guisu_core::path::RelPath>>,
+    pub(crate) target_state: TargetState,
+    /// Entries that could not be rendered, with the error message
+    pub(crate) failed: Vec<(guisu_core::path::RelPath, String)>,
+    /// Targets decrypted from whole-file or inline age encryption
+    pub(crate) encrypted: HashSet<guisu_core::path::RelPath>,
+    /// Managed targets deleted from the source, which apply would remove
+    pub(crate) deleted: Vec<DeletedTarget>,
+}
+
 /// Run the diff command implementation
+#[allow(clippy::too_many_arguments)]
 fn run_impl(
     source_dir: &Path,
     dest_dir: &Path,
     files: &[PathBuf],
+    entry_filter: &EntryFilter,
     pager: bool,
     interactive: bool,
+    options: &DiffOptions,
     config: &Config,
     db: &RedbPersistentState,
+    timings: &Arc<Timings>,
 ) -> Result<()> {
+    let Some(plan) = build_diff_plan(
+        source_dir,
+        dest_dir,
+        files,
+        entry_filter,
+        config,
+        Some(db),
+        timings,
+        true,
+    )?
+    else {
+        return Ok(());
+    };
+
+    // Use thread-safe stats for parallel processing
+    let stats = Arc::new(DiffStats::new());
+
+    // If interactive mode is enabled, use the interactive diff viewer
+    if interactive {
+        let mut file_diffs = build_interactive_file_diffs(
+            &plan.target_state,
+            plan.filter_paths.as_ref(),
+            &plan.encrypted,
+            &plan.metadata,
+            &plan.paths,
+            options,
+        );
+        file_diffs.extend(plan.deleted.iter().filter_map(|target| {
+            let (old_content, _) = deleted_content(target, &plan.paths)?;
+            (!is_binary(&old_content)).then(|| {
+                FileDiff::new(
+                    target.path.to_string(),
+                    String::from_utf8_lossy(&old_content).into_owned(),
+                    String::new(),
+                    FileStatus::Deleted,
+                )
+            })
+        }));
+
+        if !file_diffs.is_empty() {
+            let mut viewer = InteractiveDiffViewer::new(file_diffs);
+            viewer.run()?;
+        }
+
+        return Ok(());
+    }
+
+    warn_exposed_secrets(&plan, config)?;
+
+    // Generate diff outputs in parallel
+    let mut diff_outputs = generate_diff_outputs(
+        &plan.target_state,
+        plan.filter_paths.as_ref(),
+        &plan.encrypted,
+        &plan.metadata,
+        &plan.paths,
+        &stats,
+        options,
+        config,
+        timings,
+    );
+    diff_outputs.extend(
+        plan.deleted
+            .iter()
+            .filter_map(|target| format_deleted_target(target, &plan.paths, &stats)),
+    );
+
+    display_diff_output(&plan.paths, &diff_outputs, &stats, pager, config, db)
+}
+
+/// Compute per-file diffs without printing anything
+///
+/// Binary files and files without changes are omitted. Returns an empty list
+/// when the source state is empty.
+///
+/// # Errors
+///
+/// Returns an error if paths cannot be resolved or the source state, metadata,
+/// ignore patterns, or variables cannot be loaded.
+pub(crate) fn collect_file_diffs(
+    source_dir: &Path,
+    dest_dir: &Path,
+    files: &[PathBuf],
+    config: &Config,
+) -> Result<Vec<FileDiff>> {
+    let Some(plan) = build_diff_plan(
+        source_dir,
+        dest_dir,
+        files,
+        &EntryFilter::default(),
+        config,
+        None,
+        &Arc::new(Timings::new()),
+        false,
+    )?
+    else {
+        return Ok(Vec::new());
+    };
+
+    Ok(build_interactive_file_diffs(
+        &plan.target_state,
+        plan.filter_paths.as_ref(),
+        &plan.encrypted,
+        &plan.metadata,
+        &plan.paths,
+        &DiffOptions::default(),
+    ))
+}
+
+/// Permission change between the destination and the target, as octal strings
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize)]
+pub(crate) struct ModeChange {
+    /// Current permissions, or `None` if the destination is missing
+    pub(crate) from: Option<String>,
+    /// Permissions the target state asks for
+    pub(crate) to: String,
+}
+
+impl ModeChange {
+    /// Change from `current` to `target`, or `None` if the permission bits match
+    /// or the target does not specify a mode
+    pub(crate) fn between(current: Option<u32>, target: Option<u32>) -> Option<Self> {
+        let target = target? & PERM_MASK;
+        let current = current.map(|mode| mode & PERM_MASK);
+        if current == Some(target) {
+            return None;
+        }
+        Some(Self {
+            from: current.map(|mode| format!("{mode:04o}")),
+            to: format!("{target:04o}"),
+        })
+    }
+}
+
+/// How a file in the diff report differs from the destination
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Serialize)]
+#[serde(rename_all = "lowercase")]
+pub(crate) enum DiffState {
+    /// Destination does not exist yet
+    Added,
+    /// Destination content or permissions differ
+    Modified,
+    /// Target was deleted from the source and would be removed
+    Removed,
+    /// Entry could not be rendered or read
+    Error,
+}
+
+/// One changed file in `diff --format json` output
+#[derive(Debug, serde::Serialize)]
+pub(crate) struct DiffRecord {
+    pub(crate) path: String,
+    pub(crate) state: DiffState,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub(crate) mode: Option<ModeChange>,
+    pub(crate) binary: bool,
+    /// Decrypted from an encrypted source
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    pub(crate) encrypted: bool,
+    /// Uncolored unified diff, omitted for binary files, secret env files, and
+    /// with `--secrets hidden` or `keys`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub(crate) diff: Option<String>,
+    /// Names of the changed keys, instead of a diff, with `--secrets keys`
+    /// and for secret env files
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub(crate) keys: Option<KeyChanges>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub(crate) error: Option<String>,
+}
+
+impl DiffRecord {
+    fn error(path: String, error: String) -> Self {
+        Self {
+            path,
+            state: DiffState::Error,
+            mode: None,
+            binary: false,
+            encrypted: false,
+            diff: None,
+            keys: None,
+            error: Some(error),
+        }
+    }
+}
+
+/// Report printed by `diff --format json`
+#[derive(Debug, serde::Serialize)]
+pub(crate) struct DiffReport {
+    pub(crate) files: Vec<DiffRecord>,
+}
+
+/// Compute a record for every changed file without printing anything
+///
+/// Entries that fail to render are reported with an `error` state. Records are
+/// sorted by path.
+///
+/// # Errors
+///
+/// Returns an error if paths cannot be resolved or the source state, metadata,
+/// ignore patterns, or variables cannot be loaded.
+#[allow(clippy::too_many_arguments)]
+fn collect_diff_report(
+    source_dir: &Path,
+    dest_dir: &Path,
+    files: &[PathBuf],
+    entry_filter: &EntryFilter,
+    options: &DiffOptions,
+    config: &Config,
+    db: &RedbPersistentState,
+    timings: &Arc<Timings>,
+) -> Result<DiffReport> {
+    let Some(plan) = build_diff_plan(
+        source_dir,
+        dest_dir,
+        files,
+        entry_filter,
+        config,
+        Some(db),
+        timings,
+        false,
+    )?
+    else {
+        return Ok(DiffReport { files: Vec::new() });
+    };
+
+    let mut records: Vec<DiffRecord> = plan
+        .target_state
+        .entries()
+        .par_bridge()
+        .filter(|entry| {
+            let path = entry.path();
+            plan.filter_paths
+                .as_ref()
+                .is_none_or(|filter| filter.iter().any(|p| p == path))
+                && !(plan.metadata.is_create_once(&path.to_string())
+                    && plan.paths.dest_path(path).as_path().exists())
+        })
+        .filter_map(|entry| {
+            let encrypted = plan.encrypted.contains(entry.path());
+            let options = options.for_entry(encrypted, plan.target_state.is_env_file(entry.path()));
+            diff_record(entry, &plan.paths, encrypted, &options, timings)
+        })
+        .collect();
+    records.extend(
+        plan.failed
+            .into_iter()
+            .map(|(path, error)| DiffRecord::error(path.to_string(), error)),
+    );
+    records.extend(plan.deleted.iter().filter_map(|target| {
+        if target.kind == ManagedKind::Directory {
+            return plan
+                .paths
+                .dest_path(&target.path)
+                .as_path()
+                .is_dir()
+                .then(|| DiffRecord {
+                    path: target.path.to_string(),
+                    state: DiffState::Removed,
+                    mode: None,
+                    binary: false,
+                    encrypted: false,
+                    diff: None,
+                    keys: None,
+                    error: None,
+                });
+        }
+        let (old_content, _) = deleted_content(target, &plan.paths)?;
+        let binary = is_binary(&old_content);
+        Some(DiffRecord {
+            path: target.path.to_string(),
+            state: DiffState::Removed,
+            mode: None,
+            binary,
+            encrypted: false,
+            diff: (!binary).then(|| {
+                plain_unified_diff(
+                    &String::from_utf8_lossy(&old_content),
+                    "",
+                    &format!("a/{}", target.path),
+                    "/dev/null",
+                    options,
+                )
+            }),
+            keys: None,
+            error: None,
+        })
+    }));
+    records.sort_by(|a, b| a.path.cmp(&b.path));
+
+    Ok(DiffReport { files: records })
+}
+
+/// Build the report record for a single target file, or `None` if it is unchanged
+///
+/// `options` are those for this entry, see [`DiffOptions::for_entry`].
+fn diff_record(
+    entry: &TargetEntry,
+    paths: &ResolvedPaths,
+    encrypted: bool,
+    options: &DiffOptions,
+    timings: &Timings,
+) -> Option<DiffRecord> {
+    let concealed = matches!(options.secrets, SecretDisplay::Hidden | SecretDisplay::Keys);
+    let keys = options.secrets == SecretDisplay::Keys;
+    let TargetEntry::File {
+        path,
+        content,
+        mode,
+        ..
+    } = entry
+    else {
+        return None;
+    };
+
+    let dest = DestProbe::new(paths.dest_path(path));
+    let new_path = format!("b/{path}");
+
+    if !dest.exists() {
+        let binary = is_binary(content);
+        return Some(DiffRecord {
+            path: path.to_string(),
+            state: DiffState::Added,
+            mode: ModeChange::between(None, *mode),
+            binary,
+            encrypted,
+            diff: (!binary && !concealed).then(|| {
+                plain_unified_diff(
+                    "",
+                    &String::from_utf8_lossy(content),
+                    "/dev/null",
+                    &new_path,
+                    options,
+                )
+            }),
+            keys: (!binary && keys)
+                .then(|| KeyChanges::between("", &String::from_utf8_lossy(content))),
+            error: None,
+        });
+    }
+
+    let dest_content = match timings.time(Phase::Io, || dest.content()) {
+        Ok(dest_content) => dest_content,
+        Err(e) => return Some(DiffRecord::error(path.to_string(), e.to_string())),
+    };
+    let mode_change = ModeChange::between(dest.mode(), *mode);
+    let binary = is_binary(content) || is_binary(dest_content);
+
+    let mut key_changes = None;
+    let diff = if binary {
+        if **content == *dest_content && mode_change.is_none() {
+            return None;
+        }
+        None
+    } else {
+        let old = String::from_utf8_lossy(dest_content);
+        let new = String::from_utf8_lossy(content);
+        if options.contents_equal(&old, &new) {
+            // Unchanged content only shows up when the permissions differ
+            mode_change.as_ref()?;
+            None
+        } else if concealed {
+            key_changes = keys.then(|| KeyChanges::between(&old, &new));
+            None
+        } else {
+            Some(plain_unified_diff(
+                &old,
+                &new,
+                &format!("a/{path}"),
+                &new_path,
+                options,
+            ))
+        }
+    };
+
+    Some(DiffRecord {
+        path: path.to_string(),
+        state: DiffState::Modified,
+        mode: mode_change,
+        binary,
+        encrypted,
+        diff,
+        keys: key_changes,
+        error: None,
+    })
+}
+
+/// Uncolored unified diff for machine-readable output
+fn plain_unified_diff(
+    old: &str,
+    new: &str,
+    old_path: &str,
+    new_path: &str,
+    options: &DiffOptions,
+) -> String {
+    let diff = TextDiff::from_lines(old, new);
+    let mut unified = diff.unified_diff();
+    unified.context_radius(options.context);
+    if options.secrets != SecretDisplay::Masked {
+        return unified.header(old_path, new_path).to_string();
+    }
+
+    let mut output = format!("--- {old_path}\n+++ {new_path}\n");
+    for hunk in unified.iter_hunks() {
+        let _ = writeln!(output, "{}", hunk.header());
+        for change in hunk.iter_changes() {
+            let sign = match change.tag() {
+                ChangeTag::Delete => '-',
+                ChangeTag::Insert => '+',
+                ChangeTag::Equal => ' ',
+            };
+            output.push(sign);
+            output.push_str(&mask_line(change.value()));
+            if !change.value().ends_with('\n') {
+                output.push('\n');
+            }
+        }
+    }
+    output
+}
+
+/// Read the source state and render it into a target state for diffing
+///
+/// Unmanaged entries of `exact_` directories are listed as deleted. With a
+/// database, managed targets deleted from the source are listed as well.
+/// Only entries selected by `entry_filter` are included. Returns `None` when
+/// the source state is empty and nothing was deleted.
+///
+/// # Errors
+///
+/// Returns an error if paths cannot be resolved or the source state, metadata,
+/// ignore patterns, or variables cannot be loaded.
+#[allow(clippy::too_many_arguments, clippy::too_many_lines)]
+pub(crate) fn build_diff_plan(
+    source_dir: &Path,
+    dest_dir: &Path,
+    files: &[PathBuf],
+    entry_filter: &EntryFilter,
+    config: &Config,
+    db: Option<&RedbPersistentState>,
+    timings: &Arc<Timings>,
+    show_progress: bool,
+) -> Result<Option<DiffPlan>> {
     // Resolve all paths (handles root_entry and canonicalization)
     let paths = crate::common::ResolvedPaths::resolve(source_dir, dest_dir, config)?;
     let source_abs = &paths.dotfiles_dir;
     let dest_abs = &paths.dest_dir;
 
-    // Get .guisu directory and platform name for loading variables and ignore patterns
-    let guisu_dir = source_dir.guisu_dir();
-    let platform_name = guisu_core::platform::CURRENT_PLATFORM.os;
-
     // Load metadata for create-once tracking
     let metadata =
         guisu_engine::state::Metadata::load(source_dir).context("Failed to load metadata")?;
 
-    // Create ignore matcher from .guisu/ignores.toml
-    let ignore_matcher = guisu_config::IgnoreMatcher::from_ignores_toml(source_dir)
-        .context("Failed to load ignore patterns from .guisu/ignores.toml")?;
+    // Create ignore matcher from .guisu/ignores.toml and .guisuignore files
+    let ignore_matcher = crate::load_ignore_matcher(source_dir, source_abs.as_path(), config)?;
 
     // Read source state
-    let source_state =
-        SourceState::read(source_abs.to_owned()).context("Failed to read source state")?;
+    let spinner = show_progress.then(|| progress::create_spinner("Reading source state..."));
+    let mut source_state = timings
+        .time(Phase::Scan, || {
+            SourceState::read_with_matcher(source_abs.to_owned(), Some(&ignore_matcher))
+        })
+        .context("Failed to read source state")?;
 
-    if source_state.is_empty() {
-        return Ok(());
+    // Build filter paths if specific files requested
+    let filter_paths = if files.is_empty() {
+        None
+    } else {
+        Some(crate::build_filter_paths(files, dest_abs, &paths.targets)?)
+    };
+
+    let externals = timings
+        .time(Phase::Scan, || {
+            guisu_engine::external::Externals::load(source_dir)
+        })
+        .context("Failed to load externals")?;
+    let mut deleted = match db {
+        Some(db) => timings.time(Phase::Scan, || {
+            deleted_from_source(
+                db,
+                &source_state,
+                &externals,
+                &ignore_matcher,
+                filter_paths.as_ref(),
+            )
+        })?,
+        None => Vec::new(),
+    };
+    let removals = timings.time(Phase::Scan, || {
+        exact_removals(
+            &source_state,
+            &externals,
+            &paths,
+            &ignore_matcher,
+            filter_paths.as_ref(),
+        )
+    })?;
+    if let Some(spinner) = spinner {
+        spinner.finish_and_clear();
+    }
+    for removal in removals {
+        if !deleted.iter().any(|target| target.path == removal.path) {
+            deleted.push(removal);
+        }
+    }
+    deleted.retain(|target| entry_filter.matches_managed(&target.path, target.kind));
+    deleted.sort_by(|a, b| a.path.as_path().cmp(b.path.as_path()));
+
+    // Filtered only now: exact directories need every source entry to tell
+    // what is unmanaged
+    entry_filter.retain(&mut source_state);
+
+    if source_state.is_empty() && deleted.is_empty() {
+        return Ok(None);
     }
 
     // Load age identities for decryption
-    let identities = std::sync::Arc::new(config.age_identities().unwrap_or_default());
+    let identities = timings.time(Phase::Decrypt, || {
+        Arc::new(config.age_identities().unwrap_or_default())
+    });
 
     // Track if we've already shown a decryption error message
     let shown_decryption_error = std::sync::Arc::new(std::sync::atomic::AtomicBool::new(false));
 
-    // Load variables from .guisu/variables/ directory
-    let guisu_variables = if guisu_dir.exists() {
-        guisu_config::variables::load_variables(&guisu_dir, platform_name)
-            .context("Failed to load variables from .guisu/variables/")?
-    } else {
-        indexmap::IndexMap::new()
-    };
-
-    // Merge variables: guisu variables + config variables (config overrides)
-    let mut all_variables = guisu_variables;
-    all_variables.extend(config.variables.clone());
-
     // Create template engine with identities, template directory, and bitwarden provider
-    let template_engine = crate::create_template_engine(source_dir, &identities, config);
+    let template_engine = crate::create_template_engine(source_dir, &identities, config)?;
 
     // Create content processor with real decryptor and renderer
-    let identity_arc = identities.first().map_or_else(
-        || Arc::new(guisu_crypto::Identity::generate()),
-        |id| Arc::new(id.clone()),
-    );
-    let decryptor = CryptoDecryptorAdapter::from_arc(identity_arc);
+    let decryptor = CryptoDecryptorAdapter::from_identities(Arc::clone(&identities));
     let renderer = TemplateRendererAdapter::new(template_engine);
-    let processor = ContentProcessor::new(decryptor, renderer);
-
-    // Build filter paths if specific files requested
-    let filter_paths = if files.is_empty() {
-        None
-    } else {
-        Some(crate::build_filter_paths(files, dest_abs)?)
-    };
+    let processor = ContentProcessor::new(
+        Timed::new(decryptor, Arc::clone(timings)),
+        Timed::new(renderer, Arc::clone(timings)),
+    )
+    .with_eol(config.general.eol);
 
     // Build target state (processes templates and decrypts files)
-    let working_tree = guisu_engine::git::find_working_tree(source_dir)
-        .unwrap_or_else(|| source_dir.to_path_buf());
-    let template_context = guisu_template::TemplateContext::with_guisu_context(
-        source_abs.to_string(),
-        working_tree.display().to_string(),
-        dest_abs.to_string(),
-        config.general.root_entry.display().to_string(),
-        all_variables,
-    );
+    let template_context =
+        template_context(source_dir, source_abs.as_path(), dest_abs.as_path(), config)
+            .context("Failed to load variables")?;
     let template_ctx_value =
         serde_json::to_value(&template_context).context("Failed to serialize template context")?;
 
-    let target_state = build_diff_target_state(
+    let progress = if show_progress {
+        progress::create_progress_bar(
+            source_state.len() as u64,
+            "Processing templates and encrypted files",
+        )
+    } else {
+        ProgressBar::hidden()
+    };
+    let (mut target_state, failed, encrypted) = build_diff_target_state(
         &source_state,
         filter_paths.as_ref(),
         &ignore_matcher,
@@ -492,71 +1252,69 @@ fn run_impl(
         &identities,
         &shown_decryption_error,
         config,
+        timings,
+        &progress,
     );
-
-    // Use thread-safe stats for parallel processing
-    let stats = Arc::new(DiffStats::new());
-
-    // If interactive mode is enabled, use the interactive diff viewer
-    if interactive {
-        let file_diffs =
-            build_interactive_file_diffs(&target_state, filter_paths.as_ref(), &metadata, dest_abs);
-
-        if !file_diffs.is_empty() {
-            let mut viewer = InteractiveDiffViewer::new(file_diffs);
-            viewer.run()?;
-        }
-
-        return Ok(());
-    }
-
-    // Generate diff outputs in parallel
-    let diff_outputs = generate_diff_outputs(
-        &target_state,
-        filter_paths.as_ref(),
-        &metadata,
-        dest_abs,
-        &stats,
-        config,
-    );
-
-    display_diff_output(source_dir, &diff_outputs, &stats, pager, config, db)
+    progress.finish_and_clear();
+    target_state.normalize_link_targets(config.apply.symlink_targets, |path| paths.dest_path(path));
+
+    Ok(Some(DiffPlan {
+        paths,
+        metadata,
+        filter_paths,
+        target_state,
+        failed,
+        encrypted,
+        deleted,
+    }))
 }
 
 /// Diff a single target entry against destination
-fn diff_target_entry(entry: &TargetEntry, dest_abs: &AbsPath, stats: &DiffStats) -> Result<String> {
+fn diff_target_entry(
+    entry: &TargetEntry,
+    paths: &ResolvedPaths,
+    stats: &DiffStats,
+    options: &DiffOptions,
+    timings: &Timings,
+) -> Result<String> {
     let target_path = entry.path();
-    let dest_path = dest_abs.join(target_path);
 
     // Only process File entries
     let (source_content, source_mode) = match entry {
-        TargetEntry::File { content, mode, .. } => (content.clone(), *mode),
+        TargetEntry::File { content, mode, .. } => (&**content, *mode),
         _ => return Ok(String::new()),
     };
 
+    // One stat for existence and mode, one read for content
+    let dest = DestProbe::new(paths.dest_path(target_path));
+
     // Check if destination exists
-    if !dest_path.as_path().exists() {
+    if !dest.exists() {
         stats.inc_added();
-        return Ok(format_new_file(
-            target_path.as_path(),
-            &source_content,
-            source_mode,
-        ));
+        return Ok(match options.secrets {
+            SecretDisplay::Plain => {
+                format_new_file(target_path.as_path(), source_content, source_mode)
+            }
+            SecretDisplay::Masked => format_new_file(
+                target_path.as_path(),
+                mask_text(&String::from_utf8_lossy(source_content)).as_bytes(),
+                source_mode,
+            ),
+            SecretDisplay::Keys => {
+                KeyChanges::between("", &String::from_utf8_lossy(source_content))
+                    .format(&target_path.to_string())
+            }
+            SecretDisplay::Hidden => format!(
+                "{} {} added\n",
+                "Encrypted file".bold(),
+                target_path.to_string().cyan()
+            ),
+        });
     }
 
     // Get destination content and mode
-    let dest_content = fs::read(dest_path.as_path())
-        .with_context(|| format!("Failed to read destination file: {dest_path}"))?;
-
-    #[cfg(unix)]
-    let dest_mode = {
-        use std::os::unix::fs::PermissionsExt;
-        fs::metadata(dest_path.as_path())
-            .ok()
-            .map(|m| m.permissions().mode())
-    };
-    #[cfg(not(unix))]
-    let dest_mode: Option<u32> = None;
+    let dest_content = timings.time(Phase::Io, || dest.content())?;
+    let dest_mode = dest.mode();
 
     // Check if mode differs (compare only permission bits, not file type)
     let mode_differs = if let Some(src_mode) = source_mode {
@@ -571,7 +1329,7 @@ fn diff_target_entry(entry: &TargetEntry, dest_abs: &AbsPath, stats: &DiffStats)
     };
 
     // Check if binary
-    if is_binary(&source_content) || is_binary(&dest_content) {
+    if is_binary(source_content) || is_binary(dest_content) {
         if source_content != dest_content || mode_differs {
             stats.inc_modified();
             let mut output = String::new();
@@ -591,9 +1349,9 @@ fn diff_target_entry(entry: &TargetEntry, dest_abs: &AbsPath, stats: &DiffStats)
     }
 
     // Generate text diff
-    let source_str = String::from_utf8_lossy(&source_content);
-    let dest_str = String::from_utf8_lossy(&dest_content);
-    let content_differs = source_str != dest_str;
+    let source_str = String::from_utf8_lossy(source_content);
+    let dest_str = String::from_utf8_lossy(dest_content);
+    let content_differs = !options.contents_equal(&dest_str, &source_str);
 
     if !content_differs && !mode_differs {
         stats.inc_unchanged();
@@ -601,6 +1359,28 @@ fn diff_target_entry(entry: &TargetEntry, dest_abs: &AbsPath, stats: &DiffStats)
     }
 
     stats.inc_modified();
+    if matches!(options.secrets, SecretDisplay::Hidden | SecretDisplay::Keys) {
+        let mut output = String::new();
+        if mode_differs {
+            output.push_str(&format_mode_diff(dest_mode, source_mode));
+        }
+        if !content_differs {
+            return Ok(output);
+        }
+        if options.secrets == SecretDisplay::Keys {
+            output.push_str(
+                &KeyChanges::between(&dest_str, &source_str).format(&target_path.to_string()),
+            );
+        } else {
+            let _ = writeln!(
+                output,
+                "{} {} differ",
+                "Encrypted contents of".bold(),
+                target_path.to_string().cyan()
+            );
+        }
+        return Ok(output);
+    }
     Ok(generate_unified_diff(
         &dest_str,
         &source_str,
@@ -608,6 +1388,7 @@ fn diff_target_entry(entry: &TargetEntry, dest_abs: &AbsPath, stats: &DiffStats)
         &format!("b/{target_path}"),
         dest_mode,
         source_mode,
+        options,
     ))
 }
 
@@ -627,16 +1408,113 @@ fn format_mode_diff(old_mode: Option<u32>, new_mode: Option<u32>) -> String {
 ///
 /// Uses a simple heuristic: checks for null bytes in the first 8KB of content.
 /// This is a fast approximation that works well for most text vs binary detection.
-fn is_binary(content: &[u8]) -> bool {
+pub(crate) fn is_binary(content: &[u8]) -> bool {
     content.iter().take(BINARY_CHECK_BYTES).any(|&b| b == 0)
 }
 
+/// How file contents are compared and how much context is shown
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct DiffOptions {
+    /// Lines of context around each hunk
+    context: usize,
+    /// Compare lines with all whitespace removed (`-w`)
+    ignore_all_space: bool,
+    /// Leave blank lines out of the comparison
+    ignore_blank_lines: bool,
+    /// How encrypted files and secret env files are shown, see
+    /// [`DiffOptions::for_entry`]
+    secrets: SecretDisplay,
+}
+
+impl Default for DiffOptions {
+    fn default() -> Self {
+        Self {
+            context: DEFAULT_CONTEXT_LINES,
+            ignore_all_space: false,
+            ignore_blank_lines: false,
+            secrets: SecretDisplay::Plain,
+        }
+    }
+}
+
+impl DiffOptions {
+    /// Options for one entry; only encrypted entries and secret env files
+    /// have their content concealed
+    ///
+    /// Env files only ever show key names, or nothing at all when they are
+    /// encrypted and `hidden` was asked for.
+    fn for_entry(&self, encrypted: bool, env_file: bool) -> Self {
+        let secrets = match (encrypted, env_file) {
+            (true, true) if self.secrets == SecretDisplay::Hidden => SecretDisplay::Hidden,
+            (_, true) => SecretDisplay::Keys,
+            (true, false) => self.secrets,
+            (false, false) => SecretDisplay::Plain,
+        };
+        Self { secrets, ..*self }
+    }
+
+    /// Split `text` into lines and compute the key each line is compared by
+    fn normalize<'a>(&self, text: &'a str) -> NormalizedLines<'a> {
+        let lines: Vec<&str> = text.split_inclusive('\n').collect();
+        let mut keys = Vec::with_capacity(lines.len());
+        let mut index = Vec::with_capacity(lines.len());
+
+        for (i, line) in lines.iter().enumerate() {
+            if self.ignore_blank_lines && line.trim().is_empty() {
+                continue;
+            }
+            let key = if self.ignore_all_space {
+                Cow::Owned(line.split_whitespace().collect::<String>())
+            } else {
+                Cow::Borrowed(*line)
+            };
+            keys.push(key);
+            index.push(i);
+        }
+
+        NormalizedLines { lines, keys, index }
+    }
+
+    /// Whether the two texts are the same once ignored differences are dropped
+    fn contents_equal(&self, old: &str, new: &str) -> bool {
+        if !self.ignore_all_space && !self.ignore_blank_lines {
+            return old == new;
+        }
+        self.normalize(old).keys == self.normalize(new).keys
+    }
+}
+
+/// One side of a diff after normalization
+///
+/// Lines are compared by their keys but printed as they appear in the file.
+struct NormalizedLines<'a> {
+    /// Original lines, including line endings
+    lines: Vec<&'a str>,
+    /// Comparison key of every line that is not ignored
+    keys: Vec<Cow<'a, str>>,
+    /// Position in `lines` of each key
+    index: Vec<usize>,
+}
+
+impl<'a> NormalizedLines<'a> {
+    /// Original lines for a range of keys
+    fn original(&self, range: std::ops::Range<usize>) -> impl Iterator<Item = &'a str> + '_ {
+        self.index[range].iter().map(|&i| self.lines[i])
+    }
+
+    /// Line number in the file where the key at `key_idx` starts
+    fn line_number(&self, key_idx: usize) -> usize {
+        self.index.get(key_idx).copied().unwrap_or(self.lines.len())
+    }
+}
+
 /// Generate colored unified diff string using similar's native API
 ///
-/// This function uses similar's `iter_all_changes()` to iterate through changes
-/// and apply colors based on `ChangeTag` (Delete/Insert/Equal) instead of
-/// parsing the diff string output. This avoids ambiguity when lines
-/// naturally start with diff markers like "---".
+/// Lines are normalized according to `options` before diffing, and hunks are
+/// rendered from the original lines, so ignored whitespace still shows up in
+/// context and changed lines. Colors are applied per `DiffTag` instead of
+/// parsing the diff string output. This avoids ambiguity when lines naturally
+/// start with diff markers like "---".
 fn generate_unified_diff(
     old: &str,
     new: &str,
@@ -644,6 +1522,7 @@ fn generate_unified_diff(
     new_path: &str,
     old_mode: Option<u32>,
     new_mode: Option<u32>,
+    options: &DiffOptions,
 ) -> String {
     let mut output = String::new();
 
@@ -658,27 +1537,27 @@ fn generate_unified_diff(
         output.push_str(&format_mode_diff(old_mode, new_mode));
     }
 
-    let diff = TextDiff::from_lines(old, new);
+    let old_lines = options.normalize(old);
+    let new_lines = options.normalize(new);
+    let old_keys: Vec<&str> = old_lines.keys.iter().map(AsRef::as_ref).collect();
+    let new_keys: Vec<&str> = new_lines.keys.iter().map(AsRef::as_ref).collect();
+    let diff = TextDiff::configure().diff_slices(&old_keys, &new_keys);
 
     // Add file headers
     let _ = writeln!(output, "{}", format!("--- {old_path}").bold());
     let _ = writeln!(output, "{}", format!("+++ {new_path}").bold());
 
-    // Use similar's UnifiedDiff to generate hunks, but manually color each line
-    // based on ChangeTag instead of parsing string output
-    for (idx, group) in diff.grouped_ops(3).iter().enumerate() {
+    for (idx, group) in diff.grouped_ops(options.context).iter().enumerate() {
         if idx > 0 {
             output.push('\n'); // Add blank line between hunks
         }
 
         // Compute hunk header ranges from operations
         let first_op = &group[0];
-        let last_op = &group[group.len() - 1];
-
-        let old_start = first_op.old_range().start;
-        let new_start = first_op.new_range().start;
-        let old_end = last_op.old_range().end;
-        let new_end = last_op.new_range().end;
+        let old_start = old_lines.line_number(first_op.old_range().start);
+        let new_start = new_lines.line_number(first_op.new_range().start);
+        let old_len: usize = group.iter().map(|op| op.old_range().len()).sum();
+        let new_len: usize = group.iter().map(|op| op.new_range().len()).sum();
 
         let _ = writeln!(
             output,
@@ -686,30 +1565,43 @@ fn generate_unified_diff(
             format!(
                 "@@ -{},{} +{},{} @@",
                 old_start + 1,
-                old_end - old_start,
+                old_len,
                 new_start + 1,
-                new_end - new_start
+                new_len
             )
             .cyan()
         );
 
-        // Add changes using iter_changes to get proper change tags
         for op in group {
-            for change in diff.iter_changes(op) {
-                let sign = match change.tag() {
-                    ChangeTag::Delete => "-",
-                    ChangeTag::Insert => "+",
-                    ChangeTag::Equal => " ",
+            let (tag, old_range, new_range) = op.as_tag_tuple();
+            let changes: Vec<(&str, &str)> = match tag {
+                DiffTag::Equal => old_lines.original(old_range).map(|l| (" ", l)).collect(),
+                DiffTag::Delete => old_lines.original(old_range).map(|l| ("-", l)).collect(),
+                DiffTag::Insert => new_lines.original(new_range).map(|l| ("+", l)).collect(),
+                DiffTag::Replace => old_lines
+                    .original(old_range)
+                    .map(|l| ("-", l))
+                    .chain(new_lines.original(new_range).map(|l| ("+", l)))
+                    .collect(),
+            };
+
+            for (sign, value) in changes {
+                let masked;
+                let value = if options.secrets == SecretDisplay::Masked {
+                    masked = mask_line(value);
+                    masked.as_str()
+                } else {
+                    value
                 };
-                let line = format!("{}{}", sign, change.value());
-                let colored_line = match change.tag() {
-                    ChangeTag::Delete => line.red().to_string(),
-                    ChangeTag::Insert => line.green().to_string(),
-                    ChangeTag::Equal => line,
+                let line = format!("{sign}{value}");
+                let colored_line = match sign {
+                    "-" => line.red().to_string(),
+                    "+" => line.green().to_string(),
+                    _ => line,
                 };
                 let _ = write!(output, "{colored_line}");
 
-                if !change.value().ends_with('\n') {
+                if !value.ends_with('\n') {
                     output.push('\n');
                 }
             }
@@ -719,6 +1611,31 @@ fn generate_unified_diff(
     output
 }
 
+/// Line with its value replaced by [`MASK`]
+///
+/// The key of `key = value` and `key: value` lines is kept, so the changed
+/// settings can still be told apart. Blank lines and line endings are kept.
+fn mask_line(line: &str) -> String {
+    let text = line.trim_end_matches(['\r', '\n']);
+    let ending = &line[text.len()..];
+    if text.trim().is_empty() {
+        return line.to_string();
+    }
+    let keep = match text.find(['=', ':']) {
+        Some(separator) => {
+            let value = &text[separator + 1..];
+            separator + 1 + (value.len() - value.trim_start().len())
+        }
+        None => text.len() - text.trim_start().len(),
+    };
+    format!("{}{MASK}{ending}", &text[..keep])
+}
+
+/// Text with every line masked by [`mask_line`]
+fn mask_text(text: &str) -> String {
+    text.split_inclusive('\n').map(mask_line).collect()
+}
+
 /// Format a new file for diff output
 fn format_new_file(path: &Path, content: &[u8], mode: Option<u32>) -> String {
     let content_str = String::from_utf8_lossy(content);
@@ -745,6 +1662,75 @@ fn format_new_file(path: &Path, content: &[u8], mode: Option<u32>) -> String {
     output
 }
 
+/// Destination content of a deleted file or symlink, with the mode to report
+///
+/// The content of a symlink is its target, as in git. Returns `None` for
+/// directories and destinations that no longer exist.
+fn deleted_content(target: &DeletedTarget, paths: &ResolvedPaths) -> Option<(Vec<u8>, u32)> {
+    let dest_path = paths.dest_path(&target.path);
+    match target.kind {
+        ManagedKind::File => {
+            let dest = DestProbe::new(dest_path);
+            let mode = dest.mode().map_or(S_IFREG | DEFAULT_FILE_MODE, |mode| {
+                S_IFREG | (mode & PERM_MASK)
+            });
+            Some((dest.content().ok()?.to_vec(), mode))
+        }
+        ManagedKind::Symlink => {
+            let link = fs::read_link(dest_path.as_path()).ok()?;
+            Some((link.to_string_lossy().into_owned().into_bytes(), 0o120_000))
+        }
+        ManagedKind::Directory => None,
+    }
+}
+
+/// Format a target deleted from the source as a removal
+fn format_deleted_target(
+    target: &DeletedTarget,
+    paths: &ResolvedPaths,
+    stats: &DiffStats,
+) -> Option<String> {
+    let mut output = String::new();
+    if target.kind == ManagedKind::Directory {
+        if !paths.dest_path(&target.path).as_path().is_dir() {
+            return None;
+        }
+        stats.inc_removed();
+        let _ = writeln!(
+            output,
+            "{} {} deleted",
+            "Directory".bold(),
+            target.path.to_string().cyan()
+        );
+        return Some(output);
+    }
+
+    let (content, mode) = deleted_content(target, paths)?;
+    stats.inc_removed();
+
+    let _ = writeln!(output, "deleted file mode {mode:06o}");
+    if is_binary(&content) {
+        let _ = writeln!(
+            output,
+            "{} {} deleted",
+            "Binary file".bold(),
+            target.path.to_string().cyan()
+        );
+        return Some(output);
+    }
+
+    let content_str = String::from_utf8_lossy(&content);
+    let _ = writeln!(output, "{}", format!("--- a/{}", target.path).bold());
+    let _ = writeln!(output, "{}", "+++ /dev/null".bold());
+    let line_count = content_str.lines().count();
+    let _ = writeln!(output, "{}", format!("@@ -1,{line_count} +0,0 @@").cyan());
+    for line in content_str.lines() {
+        let _ = writeln!(output, "{}", format!("-{line}").red());
+    }
+
+    Some(output)
+}
+
 /// Use pager for output if available
 fn maybe_use_pager(output: &str, _config: &Config) -> Result<()> {
     // Try to use pager from environment
@@ -789,9 +1775,10 @@ fn print_stats(stats: &DiffStats) {
     let added = stats.added();
     let modified = stats.modified();
     let unchanged = stats.unchanged();
+    let removed = stats.removed();
     let errors = stats.errors();
 
-    if added == 0 && modified == 0 && errors == 0 {
+    if added == 0 && modified == 0 && removed == 0 && errors == 0 {
         return;
     }
 
@@ -810,6 +1797,13 @@ fn print_stats(stats: &DiffStats) {
             if modified == 1 { "file" } else { "files" }
         );
     }
+    if removed > 0 {
+        println!(
+            "  {} {} to be removed",
+            removed.to_string().red(),
+            if removed == 1 { "file" } else { "files" }
+        );
+    }
     if unchanged > 0 {
         println!(
             "  {} {} unchanged",
@@ -832,45 +1826,9 @@ fn print_stats(stats: &DiffStats) {
     }
 }
 
-/// Render hook template content
-fn render_hook_template(source_dir: &Path, content: &str, config: &Config) -> Result<String> {
-    // Load age identities for encryption support in templates
-    let identities = Arc::new(config.age_identities().unwrap_or_else(|_| Vec::new()));
-
-    // Create template engine with bitwarden provider support
-    let engine = crate::create_template_engine(source_dir, &identities, config);
-
-    // Get destination directory
-    let dst_dir = config
-        .general
-        .dst_dir
-        .clone()
-        .or_else(dirs::home_dir)
-        .unwrap_or_else(|| std::path::PathBuf::from("~"));
-
-    // Create template context with guisu info and all variables
-    let working_tree = guisu_engine::git::find_working_tree(source_dir)
-        .unwrap_or_else(|| source_dir.to_path_buf());
-    let dotfiles_dir = config.dotfiles_dir(source_dir);
-    let template_ctx = TemplateContext::new()
-        .with_guisu_info(
-            crate::path_to_string(&dotfiles_dir),
-            crate::path_to_string(&working_tree),
-            crate::path_to_string(&dst_dir),
-            crate::path_to_string(&config.general.root_entry),
-        )
-        .with_loaded_variables(source_dir, config)
-        .map_err(|e| anyhow::anyhow!("Failed to load variables: {e}"))?;
-
-    // Render template
-    engine
-        .render_str(content, &template_ctx)
-        .map_err(|e| anyhow::anyhow!("Template rendering error: {e}"))
-}
-
 /// Render script content, handling templates if needed
 fn render_script_content(
-    source_dir: &Path,
+    paths: &ResolvedPaths,
     script: &str,
     content: &str,
     config: &Config,
@@ -880,7 +1838,7 @@ fn render_script_content(
         .is_some_and(|ext| ext.eq_ignore_ascii_case("j2"));
 
     if is_template {
-        render_hook_template(source_dir, content, config).unwrap_or_else(|e| {
+        crate::cmd::hooks::render_hook_template(paths, content, config).unwrap_or_else(|e| {
             tracing::warn!("Failed to render hook template: {}", e);
             content.to_string()
         })
@@ -889,13 +1847,72 @@ fn render_script_content(
     }
 }
 
+/// Content a mode=onchange hook is compared by, rendered as the executor does
+fn onchange_content(
+    paths: &ResolvedPaths,
+    hook: &guisu_engine::hooks::Hook,
+    config: &Config,
+) -> String {
+    let content = hook.get_content();
+    match &hook.script {
+        Some(script) => render_script_content(paths, script, &content, config),
+        None => content,
+    }
+}
+
+/// Print why a mode=onchange hook will re-run
+fn print_onchange_diff(
+    hook: &guisu_engine::hooks::Hook,
+    stage: &str,
+    previous: &str,
+    current: &str,
+) {
+    println!();
+    println!(
+        "  {} hook: {} {}",
+        stage,
+        hook.name.yellow(),
+        "(onchange, will re-run)".dimmed()
+    );
+    print!("{}", onchange_diff(hook, previous, current));
+}
+
+/// Unified diff of a mode=onchange hook's content
+///
+/// Compares the content stored when the hook last ran against its current
+/// (rendered) content.
+pub(crate) fn onchange_diff(
+    hook: &guisu_engine::hooks::Hook,
+    previous: &str,
+    current: &str,
+) -> String {
+    let label = hook
+        .script
+        .as_deref()
+        .map_or(hook.name.as_str(), display_script_name);
+
+    generate_unified_diff(
+        previous,
+        current,
+        &format!("a/{label}"),
+        &format!("b/{label}"),
+        None,
+        None,
+        &DiffOptions::default(),
+    )
+}
+
 /// Display script name without .j2 suffix
 fn display_script_name(script: &str) -> &str {
     script.strip_suffix(".j2").unwrap_or(script)
 }
 
 /// Print a new (added) hook with its full content
-fn print_new_hook_content(source_dir: &Path, current: &guisu_engine::hooks::Hook, config: &Config) {
+fn print_new_hook_content(
+    paths: &ResolvedPaths,
+    current: &guisu_engine::hooks::Hook,
+    config: &Config,
+) {
     if let Some(ref cmd) = current.cmd {
         println!("    {} cmd:", "+".bold());
         for line in cmd.lines() {
@@ -909,12 +1926,12 @@ fn print_new_hook_content(source_dir: &Path, current: &guisu_engine::hooks::Hook
         let raw_content = if let Some(ref stored_content) = current.script_content {
             Some(stored_content.clone())
         } else {
-            let script_path = source_dir.join(script);
+            let script_path = paths.source_dir.join(script);
             std::fs::read_to_string(&script_path).ok()
         };
 
         if let Some(raw_content) = raw_content {
-            let content = render_script_content(source_dir, script, &raw_content, config);
+            let content = render_script_content(paths, script, &raw_content, config);
             for line in content.lines() {
                 println!("      + {}", line.green());
             }
@@ -955,7 +1972,7 @@ fn print_cmd_changes(old_cmd: Option<&str>, new_cmd: Option<&str>) {
 /// Print script changes between hooks
 #[allow(clippy::too_many_arguments)]
 fn print_script_changes(
-    source_dir: &Path,
+    paths: &ResolvedPaths,
     prev: &guisu_engine::hooks::Hook,
     current: &guisu_engine::hooks::Hook,
     config: &Config,
@@ -991,9 +2008,9 @@ fn print_script_changes(
                     (&prev.script_content, &current.script_content)
                 {
                     let old_rendered =
-                        render_script_content(source_dir, old_script, old_content, config);
+                        render_script_content(paths, old_script, old_content, config);
                     let new_rendered =
-                        render_script_content(source_dir, new_script, new_content, config);
+                        render_script_content(paths, new_script, new_content, config);
                     let diff_output = generate_text_diff(&old_rendered, &new_rendered);
                     for line in diff_output.lines() {
                         println!("      {line}");
@@ -1008,8 +2025,7 @@ fn print_script_changes(
                     display_old.red()
                 );
                 if let Some(old_content) = &prev.script_content {
-                    let rendered =
-                        render_script_content(source_dir, old_script, old_content, config);
+                    let rendered = render_script_content(paths, old_script, old_content, config);
                     for line in rendered.lines() {
                         println!("      - {}", line.red());
                     }
@@ -1023,8 +2039,7 @@ fn print_script_changes(
                     display_new.green()
                 );
                 if let Some(new_content) = &current.script_content {
-                    let rendered =
-                        render_script_content(source_dir, new_script, new_content, config);
+                    let rendered = render_script_content(paths, new_script, new_content, config);
                     for line in rendered.lines() {
                         println!("      + {}", line.green());
                     }
@@ -1038,8 +2053,8 @@ fn print_script_changes(
             (&prev.script_content, &current.script_content)
         {
             // Render both versions to detect changes in template dependencies
-            let old_rendered = render_script_content(source_dir, script, old_content, config);
-            let new_rendered = render_script_content(source_dir, script, new_content, config);
+            let old_rendered = render_script_content(paths, script, old_content, config);
+            let new_rendered = render_script_content(paths, script, new_content, config);
 
             // Compare rendered content instead of raw template content
             if old_rendered != new_rendered {
@@ -1081,7 +2096,7 @@ fn print_other_changes(prev: &guisu_engine::hooks::Hook, current: &guisu_engine:
 
 /// Print hook diff with content changes
 fn print_hook_diff(
-    source_dir: &Path,
+    paths: &ResolvedPaths,
     current: &guisu_engine::hooks::Hook,
     previous: Option<&guisu_engine::hooks::Hook>,
     stage: &str,
@@ -1095,7 +2110,7 @@ fn print_hook_diff(
             // New hook (added) - show full content
             println!();
             println!("  {} hook: {}", stage, current.name.green());
-            print_new_hook_content(source_dir, current, config);
+            print_new_hook_content(paths, current, config);
         }
         Some(prev) => {
             // Modified hook - show unified diff
@@ -1103,7 +2118,7 @@ fn print_hook_diff(
             println!("  {} hook: {}", stage, current.name.yellow());
 
             print_cmd_changes(prev.cmd.as_deref(), current.cmd.as_deref());
-            print_script_changes(source_dir, prev, current, config);
+            print_script_changes(paths, prev, current, config);
             print_other_changes(prev, current);
         }
     }
@@ -1142,7 +2157,7 @@ fn generate_text_diff(old: &str, new: &str) -> String {
 
 /// Print removed hook with content
 fn print_removed_hook(
-    source_dir: &Path,
+    paths: &ResolvedPaths,
     hook: &guisu_engine::hooks::Hook,
     stage: &str,
     platform: &str,
@@ -1169,7 +2184,7 @@ fn print_removed_hook(
                 .extension()
                 .is_some_and(|ext| ext.eq_ignore_ascii_case("j2"))
             {
-                render_hook_template(source_dir, content, config)
+                crate::cmd::hooks::render_hook_template(paths, content, config)
                     .unwrap_or_else(|_| content.clone())
             } else {
                 content.clone()
@@ -1195,7 +2210,7 @@ fn print_removed_hook(
     clippy::missing_panics_doc
 )]
 pub fn compare_and_print_hooks(
-    source_dir: &Path,
+    paths: &ResolvedPaths,
     current_hooks: &[guisu_engine::hooks::Hook],
     last_hooks: &[guisu_engine::hooks::Hook],
     stage: &str,
@@ -1211,7 +2226,7 @@ pub fn compare_and_print_hooks(
     // New hooks
     for hook in current_hooks {
         if !last_names.contains(hook.name.as_str()) {
-            print_hook_diff(source_dir, hook, None, stage, platform, config);
+            print_hook_diff(paths, hook, None, stage, platform, config);
             any_printed = true;
         }
     }
@@ -1219,7 +2234,7 @@ pub fn compare_and_print_hooks(
     // Removed hooks
     for hook in last_hooks {
         if !current_names.contains(hook.name.as_str()) {
-            print_removed_hook(source_dir, hook, stage, platform, config);
+            print_removed_hook(paths, hook, stage, platform, config);
             any_printed = true;
         }
     }
@@ -1227,6 +2242,23 @@ pub fn compare_and_print_hooks(
     // Modified hooks
     for hook in current_hooks {
         if let Some(last_hook) = last_hooks.iter().find(|h| h.name == hook.name) {
+            // mode=onchange hooks re-run when their content differs from the
+            // last run, so show that diff rather than the definition diff
+            if hook.mode == HookMode::OnChange
+                && let Some(previous) = onchange_rendered.get(&hook.name)
+            {
+                let current = onchange_content(paths, hook, config);
+                let current_hash = guisu_engine::hash::hash_content(current.as_bytes());
+                if onchange_hashes
+                    .get(&hook.name)
+                    .is_none_or(|saved_hash| *saved_hash != current_hash)
+                {
+                    print_onchange_diff(hook, stage, previous, &current);
+                    any_printed = true;
+                    continue;
+                }
+            }
+
             // For template scripts (.j2), check rendered content hash for mode=onchange
             let is_template = hook
                 .script
@@ -1246,7 +2278,7 @@ pub fn compare_and_print_hooks(
             {
                 // Render current content and compute hash
                 let rendered = render_script_content(
-                    source_dir,
+                    paths,
                     hook.script
                         .as_ref()
                         .expect("script must exist for template hook"),
@@ -1268,44 +2300,8 @@ pub fn compare_and_print_hooks(
 
             // Only show hooks that have actual changes
             if has_changes {
-                // Check if this is specifically an onchange dependency change
-                let is_onchange_dep_change = is_template
-                    && hook.mode == HookMode::OnChange
-                    && hook.order == last_hook.order
-                    && hook.mode == last_hook.mode
-                    && hook.cmd == last_hook.cmd
-                    && hook.script == last_hook.script
-                    && hook.script_content == last_hook.script_content;
-
-                if is_onchange_dep_change {
-                    // Special handling for onchange dependency changes - show unified diff
-                    if let (Some(script), Some(content)) = (&hook.script, &hook.script_content) {
-                        // Render current content
-                        let new_rendered =
-                            render_script_content(source_dir, script, content, config);
-
-                        // Get old rendered content from state
-                        if let Some(old_rendered) = onchange_rendered.get(&hook.name) {
-                            // Generate unified diff
-                            let display_script = display_script_name(script);
-                            let diff = generate_unified_diff(
-                                old_rendered,
-                                &new_rendered,
-                                &format!("a/{display_script}"),
-                                &format!("b/{display_script}"),
-                                None,
-                                None,
-                            );
-
-                            // Print the diff
-                            print!("{diff}");
-                            any_printed = true;
-                        }
-                    }
-                } else {
-                    print_hook_diff(source_dir, hook, Some(last_hook), stage, platform, config);
-                    any_printed = true;
-                }
+                print_hook_diff(paths, hook, Some(last_hook), stage, platform, config);
+                any_printed = true;
             }
         }
     }
@@ -1315,9 +2311,10 @@ pub fn compare_and_print_hooks(
 
 /// Check and print hooks status
 /// Returns true if any hooks were displayed
-fn print_hooks_status(source_dir: &Path, config: &Config, db: &RedbPersistentState) -> bool {
+fn print_hooks_status(paths: &ResolvedPaths, config: &Config, db: &RedbPersistentState) -> bool {
     // Load hooks and state using shared helper
-    let Some((collections, state)) = crate::utils::hooks::load_hooks_and_state(source_dir, db)
+    let Some((collections, state)) =
+        crate::utils::hooks::load_hooks_and_state(&paths.source_dir, db)
     else {
         return false;
     };
@@ -1335,7 +2332,7 @@ fn print_hooks_status(source_dir: &Path, config: &Config, db: &RedbPersistentSta
             // Pre hooks comparison
             if !collections.pre.is_empty() || !last.pre.is_empty() {
                 let printed = compare_and_print_hooks(
-                    source_dir,
+                    paths,
                     &collections.pre,
                     &last.pre,
                     "pre",
@@ -1350,7 +2347,7 @@ fn print_hooks_status(source_dir: &Path, config: &Config, db: &RedbPersistentSta
             // Post hooks comparison
             if !collections.post.is_empty() || !last.post.is_empty() {
                 let printed = compare_and_print_hooks(
-                    source_dir,
+                    paths,
                     &collections.post,
                     &last.post,
                     "post",
@@ -1391,6 +2388,14 @@ mod tests {
             files: vec![],
             pager: false,
             interactive: false,
+            context: None,
+            ignore_all_space: false,
+            ignore_blank_lines: false,
+            format: DiffFormat::Text,
+            include: vec![],
+            exclude: vec![],
+            timings: false,
+            secrets: SecretDisplay::Plain,
         };
 
         assert!(cmd.files.is_empty());
@@ -1404,6 +2409,14 @@ mod tests {
             files: vec![PathBuf::from("file1.txt"), PathBuf::from("file2.txt")],
             pager: false,
             interactive: false,
+            context: None,
+            ignore_all_space: false,
+            ignore_blank_lines: false,
+            format: DiffFormat::Text,
+            include: vec![],
+            exclude: vec![],
+            timings: false,
+            secrets: SecretDisplay::Plain,
         };
 
         assert_eq!(cmd.files.len(), 2);
@@ -1417,6 +2430,14 @@ mod tests {
             files: vec![],
             pager: true,
             interactive: false,
+            context: None,
+            ignore_all_space: false,
+            ignore_blank_lines: false,
+            format: DiffFormat::Text,
+            include: vec![],
+            exclude: vec![],
+            timings: false,
+            secrets: SecretDisplay::Plain,
         };
 
         assert!(cmd.pager);
@@ -1429,12 +2450,133 @@ mod tests {
             files: vec![],
             pager: false,
             interactive: true,
+            context: None,
+            ignore_all_space: false,
+            ignore_blank_lines: false,
+            format: DiffFormat::Text,
+            include: vec![],
+            exclude: vec![],
+            timings: false,
+            secrets: SecretDisplay::Plain,
         };
 
         assert!(!cmd.pager);
         assert!(cmd.interactive);
     }
 
+    #[test]
+    fn test_mode_change_between() {
+        assert_eq!(ModeChange::between(Some(0o100_644), Some(0o644)), None);
+        assert_eq!(ModeChange::between(Some(0o644), None), None);
+        assert_eq!(
+            ModeChange::between(Some(0o100_644), Some(0o755)),
+            Some(ModeChange {
+                from: Some("0644".into()),
+                to: "0755".into(),
+            })
+        );
+        assert_eq!(
+            ModeChange::between(None, Some(0o600)),
+            Some(ModeChange {
+                from: None,
+                to: "0600".into(),
+            })
+        );
+    }
+
+    #[test]
+    fn test_collect_diff_report() {
+        use tempfile::TempDir;
+
+        let temp = TempDir::new().unwrap();
+        let root = fs::canonicalize(temp.path()).unwrap();
+        let source = root.join("source");
+        let home = source.join("home");
+        let dest = root.join("dest");
+        fs::create_dir_all(&home).unwrap();
+        fs::create_dir_all(&dest).unwrap();
+
+        fs::write(home.join(".bashrc"), "export A=2\n").unwrap();
+        fs::write(dest.join(".bashrc"), "export A=1\n").unwrap();
+        fs::write(home.join(".profile"), "same\n").unwrap();
+        fs::write(dest.join(".profile"), "same\n").unwrap();
+        fs::write(home.join("data.bin"), b"\0new").unwrap();
+        fs::write(dest.join("data.bin"), b"\0old").unwrap();
+        fs::write(home.join(".vimrc"), "set nu\n").unwrap();
+        fs::write(home.join(".broken.j2"), "{{ missing(").unwrap();
+        fs::write(dest.join(".oldrc"), "old\n").unwrap();
+
+        let db = RedbPersistentState::new(root.join("state.db")).unwrap();
+        guisu_engine::database::save_managed_paths(
+            &db,
+            &[
+                (".bashrc".to_string(), ManagedKind::File),
+                (".oldrc".to_string(), ManagedKind::File),
+            ],
+        )
+        .unwrap();
+
+        let timings = Arc::new(Timings::new());
+        let report = collect_diff_report(
+            &source,
+            &dest,
+            &[],
+            &EntryFilter::default(),
+            &DiffOptions::default(),
+            &Config::default(),
+            &db,
+            &timings,
+        )
+        .unwrap();
+        // .broken.j2 is rendered, and the existing destinations are read
+        assert!(!timings.get(Phase::Scan).is_zero());
+        assert!(!timings.get(Phase::Render).is_zero());
+        assert!(!timings.get(Phase::Io).is_zero());
+        let json = serde_json::to_value(&report).unwrap();
+        let files = json["files"].as_array().unwrap();
+        let paths: Vec<&str> = files.iter().map(|f| f["path"].as_str().unwrap()).collect();
+        assert_eq!(
+            paths,
+            [".bashrc", ".broken", ".oldrc", ".vimrc", "data.bin"]
+        );
+
+        assert_eq!(files[0]["state"], "modified");
+        assert_eq!(files[0]["binary"], false);
+        let diff = files[0]["diff"].as_str().unwrap();
+        assert!(diff.contains("-export A=1"));
+        assert!(diff.contains("+export A=2"));
+        assert!(!diff.contains('\x1b'));
+
+        assert_eq!(files[1]["state"], "error");
+        assert!(files[1]["error"].is_string());
+
+        assert_eq!(files[2]["state"], "removed");
+        assert!(files[2]["diff"].as_str().unwrap().contains("-old"));
+
+        assert_eq!(files[3]["state"], "added");
+
+        assert_eq!(files[4]["state"], "modified");
+        assert_eq!(files[4]["binary"], true);
+        assert!(files[4].get("diff").is_none());
+
+        // Deleted targets are filtered by kind and path like source entries
+        let entry_filter =
+            EntryFilter::new(&["*rc".to_string()], &["templates".to_string()]).unwrap();
+        let report = collect_diff_report(
+            &source,
+            &dest,
+            &[],
+            &entry_filter,
+            &DiffOptions::default(),
+            &Config::default(),
+            &db,
+            &Arc::new(Timings::new()),
+        )
+        .unwrap();
+        let paths: Vec<&str> = report.files.iter().map(|f| f.path.as_str()).collect();
+        assert_eq!(paths, [".bashrc", ".oldrc", ".vimrc"]);
+    }
+
     // Tests for format_mode_diff
 
     #[test]
@@ -1542,7 +2684,15 @@ mod tests {
         let old = "line1\nline2\nline3\n";
         let new = "line1\nline2\nline3\n";
 
-        let result = generate_unified_diff(old, new, "a/file", "b/file", None, None);
+        let result = generate_unified_diff(
+            old,
+            new,
+            "a/file",
+            "b/file",
+            None,
+            None,
+            &DiffOptions::default(),
+        );
 
         // No hunks when files are identical, but headers are still present
         assert!(result.contains("--- a/file"));
@@ -1555,7 +2705,15 @@ mod tests {
         let old = "line1\nline2\n";
         let new = "line1\nline2\nline3\n";
 
-        let result = generate_unified_diff(old, new, "a/file", "b/file", None, None);
+        let result = generate_unified_diff(
+            old,
+            new,
+            "a/file",
+            "b/file",
+            None,
+            None,
+            &DiffOptions::default(),
+        );
 
         // Check for headers and content (output contains ANSI color codes)
         assert!(result.contains("--- a/file"));
@@ -1568,7 +2726,15 @@ mod tests {
         let old = "line1\nline2\nline3\n";
         let new = "line1\nline3\n";
 
-        let result = generate_unified_diff(old, new, "a/file", "b/file", None, None);
+        let result = generate_unified_diff(
+            old,
+            new,
+            "a/file",
+            "b/file",
+            None,
+            None,
+            &DiffOptions::default(),
+        );
 
         // Check for headers and content (output contains ANSI color codes)
         assert!(result.contains("--- a/file"));
@@ -1581,7 +2747,15 @@ mod tests {
         let old = "line1\nline2\nline3\n";
         let new = "line1\nmodified\nline3\n";
 
-        let result = generate_unified_diff(old, new, "a/file", "b/file", None, None);
+        let result = generate_unified_diff(
+            old,
+            new,
+            "a/file",
+            "b/file",
+            None,
+            None,
+            &DiffOptions::default(),
+        );
 
         // Check for headers and content (output contains ANSI color codes)
         assert!(result.contains("--- a/file"));
@@ -1595,7 +2769,15 @@ mod tests {
         let old = "content\n";
         let new = "content\n";
 
-        let result = generate_unified_diff(old, new, "a/file", "b/file", Some(0o644), Some(0o755));
+        let result = generate_unified_diff(
+            old,
+            new,
+            "a/file",
+            "b/file",
+            Some(0o644),
+            Some(0o755),
+            &DiffOptions::default(),
+        );
 
         // Should include mode diff
         assert!(result.contains("old mode"));
@@ -1607,7 +2789,15 @@ mod tests {
         let old = "content\n";
         let new = "content\n";
 
-        let result = generate_unified_diff(old, new, "a/file", "b/file", Some(0o755), Some(0o755));
+        let result = generate_unified_diff(
+            old,
+            new,
+            "a/file",
+            "b/file",
+            Some(0o755),
+            Some(0o755),
+            &DiffOptions::default(),
+        );
 
         // Should not include mode diff when same
         assert!(!result.contains("old mode"));
@@ -1618,7 +2808,15 @@ mod tests {
         let old = "";
         let new = "new content\n";
 
-        let result = generate_unified_diff(old, new, "a/file", "b/file", None, None);
+        let result = generate_unified_diff(
+            old,
+            new,
+            "a/file",
+            "b/file",
+            None,
+            None,
+            &DiffOptions::default(),
+        );
 
         // Check for headers and content (output contains ANSI color codes)
         assert!(result.contains("--- a/file"));
@@ -1631,7 +2829,15 @@ mod tests {
         let old = "old content\n";
         let new = "";
 
-        let result = generate_unified_diff(old, new, "a/file", "b/file", None, None);
+        let result = generate_unified_diff(
+            old,
+            new,
+            "a/file",
+            "b/file",
+            None,
+            None,
+            &DiffOptions::default(),
+        );
 
         // Check for headers and content (output contains ANSI color codes)
         assert!(result.contains("--- a/file"));
@@ -1639,6 +2845,138 @@ mod tests {
         assert!(result.contains("old content")); // Content is there, regardless of color codes
     }
 
+    #[test]
+    fn test_generate_unified_diff_context_size() {
+        let old = "a\nb\nc\nd\ne\nf\ng\n";
+        let new = "a\nb\nc\nX\ne\nf\ng\n";
+
+        let options = DiffOptions {
+            context: 1,
+            ..DiffOptions::default()
+        };
+        let result = generate_unified_diff(old, new, "a/file", "b/file", None, None, &options);
+        assert!(result.contains("@@ -3,3 +3,3 @@"));
+        assert!(!result.contains(" b\n"));
+
+        let options = DiffOptions {
+            context: 0,
+            ..DiffOptions::default()
+        };
+        let result = generate_unified_diff(old, new, "a/file", "b/file", None, None, &options);
+        assert!(result.contains("@@ -4,1 +4,1 @@"));
+        assert!(!result.contains(" c\n"));
+    }
+
+    #[test]
+    fn test_generate_unified_diff_ignore_all_space() {
+        let old = "fn main() {\n    run();\n}\nlet x = 1;\n";
+        let new = "fn main() {\n\trun();  \n}\nlet x = 2;\n";
+
+        let options = DiffOptions {
+            ignore_all_space: true,
+            ..DiffOptions::default()
+        };
+        let result = generate_unified_diff(old, new, "a/file", "b/file", None, None, &options);
+
+        // The reindented line is context, shown as it was on disk
+        assert!(result.contains("     run();\n"));
+        assert!(!result.contains("\trun();"));
+        assert!(result.contains("let x = 1;"));
+        assert!(result.contains("let x = 2;"));
+    }
+
+    #[test]
+    fn test_generate_unified_diff_ignore_blank_lines() {
+        let old = "a\nb\nc\n";
+        let new = "a\n\nb\n\nc\nd\n";
+
+        let options = DiffOptions {
+            context: 0,
+            ignore_blank_lines: true,
+            ..DiffOptions::default()
+        };
+        let result = generate_unified_diff(old, new, "a/file", "b/file", None, None, &options);
+
+        // Only the real change produces a hunk, at its position in the new file
+        assert_eq!(result.matches("@@").count(), 2);
+        assert!(result.contains("@@ -4,0 +6,1 @@"));
+    }
+
+    #[test]
+    fn test_mask_line() {
+        assert_eq!(mask_line("export TOKEN=abc123\n"), "export TOKEN=***\n");
+        assert_eq!(mask_line("  password: hunter2\r\n"), "  password: ***\r\n");
+        assert_eq!(mask_line("    c2VjcmV0"), "    ***");
+        assert_eq!(mask_line("  \n"), "  \n");
+        assert_eq!(mask_text("a = 1\n\nb\n"), "a = ***\n\n***\n");
+    }
+
+    #[test]
+    fn test_diff_options_for_entry() {
+        let options = DiffOptions {
+            secrets: SecretDisplay::Hidden,
+            ..DiffOptions::default()
+        };
+        assert_eq!(
+            options.for_entry(true, false).secrets,
+            SecretDisplay::Hidden
+        );
+        assert_eq!(
+            options.for_entry(false, false).secrets,
+            SecretDisplay::Plain
+        );
+        assert_eq!(options.for_entry(true, true).secrets, SecretDisplay::Hidden);
+        assert_eq!(options.for_entry(false, true).secrets, SecretDisplay::Keys);
+        assert_eq!(
+            DiffOptions::default().for_entry(true, true).secrets,
+            SecretDisplay::Keys
+        );
+    }
+
+    #[test]
+    fn test_masked_diffs_hide_values() {
+        let old = "user = me\ntoken = old-secret\n";
+        let new = "user = me\ntoken = new-secret\n";
+        let options = DiffOptions {
+            secrets: SecretDisplay::Masked,
+            ..DiffOptions::default()
+        };
+
+        let colored = generate_unified_diff(old, new, "a/f", "b/f", None, None, &options);
+        let plain = plain_unified_diff(old, new, "a/f", "b/f", &options);
+        for output in [&colored, &plain] {
+            assert!(!output.contains("secret"), "{output}");
+            assert!(!output.contains("user = me"), "{output}");
+            assert!(output.contains("token = ***"), "{output}");
+        }
+        assert!(
+            plain.starts_with("--- a/f\n+++ b/f\n@@ -1,2 +1,2 @@\n"),
+            "{plain}"
+        );
+        assert!(plain.contains("-token = ***\n+token = ***\n"), "{plain}");
+    }
+
+    #[test]
+    fn test_diff_options_contents_equal() {
+        let default = DiffOptions::default();
+        assert!(default.contents_equal("a\n", "a\n"));
+        assert!(!default.contents_equal("a b\n", "a  b\n"));
+
+        let ignore_space = DiffOptions {
+            ignore_all_space: true,
+            ..DiffOptions::default()
+        };
+        assert!(ignore_space.contents_equal("a b\r\n", "a  b\n"));
+        assert!(!ignore_space.contents_equal("a\n", "a\n\n"));
+
+        let ignore_blank = DiffOptions {
+            ignore_blank_lines: true,
+            ..DiffOptions::default()
+        };
+        assert!(ignore_blank.contents_equal("a\n", "a\n\n  \n"));
+        assert!(!ignore_blank.contents_equal("a\n", "a \n"));
+    }
+
     // Tests for format_new_file
 
     #[test]
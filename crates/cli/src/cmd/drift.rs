@@ -0,0 +1,311 @@
+//! Drift command operations
+//!
+//! Track changes made to managed destination files by something other than
+//! guisu, such as tools that keep rewriting their own config:
+//! - watch: Poll managed destinations and record out-of-band changes
+//! - report: Print the recorded changes with their diffs
+
+use anyhow::{Context, Result};
+use guisu_core::path::AbsPath;
+use guisu_engine::clock::StateClock;
+use guisu_engine::hash::hash_content;
+use guisu_engine::state::{DriftEvent, RedbPersistentState};
+use owo_colors::OwoColorize;
+use similar::TextDiff;
+use std::collections::HashMap;
+use std::fs;
+use std::path::Path;
+use std::time::Duration;
+use tracing::{debug, warn};
+
+use crate::cmd::diff::is_binary;
+
+/// Last seen content of each managed destination file
+///
+/// Guisu's own writes are recognized by the state it records after applying:
+/// a destination whose new content matches its entry state was applied by
+/// guisu and only moves the baseline.
+#[derive(Debug)]
+pub struct DriftWatcher {
+    dest_dir: AbsPath,
+    /// Content per target path, `None` if the destination did not exist
+    baselines: HashMap<String, Option<Vec<u8>>>,
+}
+
+impl DriftWatcher {
+    /// Create a watcher for managed files under `dest_dir`
+    #[must_use]
+    pub fn new(dest_dir: AbsPath) -> Self {
+        Self {
+            dest_dir,
+            baselines: HashMap::new(),
+        }
+    }
+
+    /// Compare managed destinations with their last seen content
+    ///
+    /// Files seen for the first time only set their baseline. Changes that
+    /// guisu did not apply are saved as drift events and returned.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the state database cannot be read or written
+    pub fn poll(
+        &mut self,
+        db: &RedbPersistentState,
+        clock: &StateClock,
+    ) -> Result<Vec<DriftEvent>> {
+        let states = guisu_engine::database::get_all_entry_states(db)
+            .context("Failed to read entry states")?;
+        self.baselines.retain(|path, _| states.contains_key(path));
+
+        let stamp = clock.begin_run();
+        let mut paths: Vec<&String> = states.keys().collect();
+        paths.sort();
+
+        let mut events = Vec::new();
+        for path in paths {
+            let current = fs::read(self.dest_dir.as_path().join(path)).ok();
+            let Some(baseline) = self.baselines.insert(path.clone(), current.clone()) else {
+                continue;
+            };
+            if baseline == current {
+                continue;
+            }
+
+            let applied_by_guisu = current
+                .as_deref()
+                .is_some_and(|content| hash_content(content) == states[path].content_hash);
+            if applied_by_guisu {
+                debug!(path = %path, "Destination updated by guisu");
+                continue;
+            }
+
+            let diff = describe_change(path, baseline.as_deref(), current.as_deref());
+            let event = DriftEvent::new(path.clone(), diff, &stamp);
+            guisu_engine::database::save_drift_event(db, &event)
+                .context("Failed to save drift event")?;
+            events.push(event);
+        }
+
+        Ok(events)
+    }
+}
+
+/// Unified diff between two versions of a destination file
+fn describe_change(path: &str, old: Option<&[u8]>, new: Option<&[u8]>) -> String {
+    let (old, new) = match (old, new) {
+        (_, None) => return "File was deleted\n".to_string(),
+        (None, Some(_)) => return "File was created\n".to_string(),
+        (Some(old), Some(new)) => (old, new),
+    };
+    if is_binary(old) || is_binary(new) {
+        return "Binary content changed\n".to_string();
+    }
+
+    TextDiff::from_lines(
+        &*String::from_utf8_lossy(old),
+        &*String::from_utf8_lossy(new),
+    )
+    .unified_diff()
+    .header(&format!("a/{path}"), &format!("b/{path}"))
+    .to_string()
+}
+
+/// Run drift watch command
+///
+/// Polls until interrupted. The state database is opened only while polling,
+/// so other guisu commands can run in between; a poll is skipped while another
+/// guisu process holds the database, which also keeps apply's writes from
+/// being mistaken for drift.
+///
+/// # Errors
+///
+/// Returns an error if the destination directory cannot be resolved
+pub fn run_watch(db_path: &Path, dest_dir: &Path, interval: u64) -> Result<()> {
+    let dest_dir = fs::canonicalize(dest_dir).with_context(|| {
+        format!(
+            "Failed to resolve destination directory: {}",
+            dest_dir.display()
+        )
+    })?;
+    let mut watcher = DriftWatcher::new(AbsPath::new(dest_dir)?);
+    let clock = StateClock::system();
+
+    println!(
+        "Watching managed files for changes made outside guisu (every {interval}s, Ctrl-C to stop)"
+    );
+
+    loop {
+        match guisu_engine::database::open(db_path) {
+            Ok(db) => match watcher.poll(&db, &clock) {
+                Ok(events) => {
+                    for event in &events {
+                        warn!(path = %event.path, id = %event.id(), "Destination changed outside guisu");
+                        println!(
+                            "{}  {}  {}",
+                            event.id().yellow(),
+                            format_seen_at(event).dimmed(),
+                            event.path.bright_white()
+                        );
+                    }
+                }
+                Err(e) => warn!("Drift poll failed: {e:#}"),
+            },
+            Err(e) => debug!("State database busy, skipping poll: {e}"),
+        }
+
+        std::thread::sleep(Duration::from_secs(interval));
+    }
+}
+
+/// Run drift report command
+///
+/// # Errors
+///
+/// Returns an error if the events cannot be read from the database
+pub fn run_report(db: &RedbPersistentState, paths: &[String]) -> Result<()> {
+    let events =
+        guisu_engine::database::get_all_drift_events(db).context("Failed to read drift events")?;
+    let events: Vec<_> = events
+        .into_iter()
+        .filter(|(_, event)| paths.is_empty() || paths.contains(&event.path))
+        .collect();
+
+    if events.is_empty() {
+        println!("No changes made outside guisu recorded.");
+        return Ok(());
+    }
+
+    for (id, event) in &events {
+        println!(
+            "{}  {}  {}",
+            id.yellow(),
+            format_seen_at(event).dimmed(),
+            event.path.bright_white()
+        );
+        for line in event.diff.lines() {
+            if line.starts_with('+') && !line.starts_with("+++") {
+                println!("    {}", line.green());
+            } else if line.starts_with('-') && !line.starts_with("---") {
+                println!("    {}", line.red());
+            } else {
+                println!("    {}", line.dimmed());
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// Format when the change was noticed, in local time
+fn format_seen_at(event: &DriftEvent) -> String {
+    chrono::DateTime::<chrono::Local>::from(event.stamp.time())
+        .format("%Y-%m-%d %H:%M:%S")
+        .to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    #![allow(clippy::unwrap_used, clippy::panic)]
+    use super::*;
+    use crate::common::testing::TestWorkspace;
+    use guisu_config::Config;
+
+    /// Destination watched for drift, with a fixed clock
+    struct Fixture {
+        workspace: TestWorkspace,
+        watcher: DriftWatcher,
+    }
+
+    impl Fixture {
+        fn new() -> Self {
+            let mut workspace = TestWorkspace::new(Config::default());
+            workspace.context = workspace
+                .context
+                .with_clock(StateClock::fixed(1_700_000_000));
+            Self {
+                watcher: DriftWatcher::new(workspace.context.dest_dir().clone()),
+                workspace,
+            }
+        }
+
+        /// Write a destination file as guisu's apply would, recording its state
+        fn apply(&self, path: &str, content: &str) {
+            self.write(path, content);
+            let ctx = &self.workspace.context;
+            guisu_engine::database::save_entry_state(
+                ctx.database(),
+                path,
+                content.as_bytes(),
+                None,
+                guisu_engine::database::MergeBase::Keep,
+                &ctx.clock.begin_run(),
+            )
+            .unwrap();
+        }
+
+        fn write(&self, path: &str, content: &str) {
+            fs::write(self.workspace.dest(path), content).unwrap();
+        }
+
+        fn poll(&mut self) -> Vec<DriftEvent> {
+            let context = &self.workspace.context;
+            self.watcher
+                .poll(context.database(), &context.clock)
+                .unwrap()
+        }
+    }
+
+    #[test]
+    fn test_poll_records_changes_made_outside_guisu() {
+        let mut fixture = Fixture::new();
+        fixture.apply(".zshrc", "export EDITOR=nvim\n");
+        assert_eq!(fixture.poll(), Vec::<DriftEvent>::new());
+
+        fixture.write(".zshrc", "export EDITOR=nvim\neval \"$(tool init)\"\n");
+        let events = fixture.poll();
+        assert_eq!(events.len(), 1);
+        assert_eq!(events[0].path, ".zshrc");
+        assert!(events[0].diff.contains("+eval \"$(tool init)\""));
+
+        // Recorded once, then the new content is the baseline
+        assert_eq!(fixture.poll(), Vec::<DriftEvent>::new());
+        assert_eq!(
+            guisu_engine::database::get_all_drift_events(fixture.workspace.context.database())
+                .unwrap()
+                .len(),
+            1
+        );
+    }
+
+    #[test]
+    fn test_poll_ignores_guisu_writes() {
+        let mut fixture = Fixture::new();
+        fixture.apply(".gitconfig", "[user]\n");
+        assert_eq!(fixture.poll(), Vec::<DriftEvent>::new());
+
+        fixture.apply(".gitconfig", "[user]\nname = me\n");
+        assert_eq!(fixture.poll(), Vec::<DriftEvent>::new());
+    }
+
+    #[test]
+    fn test_poll_records_deletion() {
+        let mut fixture = Fixture::new();
+        fixture.apply(".vimrc", "set nu\n");
+        assert_eq!(fixture.poll(), Vec::<DriftEvent>::new());
+
+        fs::remove_file(fixture.workspace.dest(".vimrc")).unwrap();
+        let events = fixture.poll();
+        assert_eq!(events.len(), 1);
+        assert_eq!(events[0].diff, "File was deleted\n");
+    }
+
+    #[test]
+    fn test_describe_change_binary() {
+        assert_eq!(
+            describe_change("a", Some(b"\0one"), Some(b"\0two")),
+            "Binary content changed\n"
+        );
+    }
+}
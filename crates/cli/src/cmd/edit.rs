@@ -4,12 +4,13 @@
 
 use anyhow::{Context, Result};
 use clap::Args;
-use guisu_crypto::{decrypt, decrypt_file_content, encrypt, encrypt_inline};
+use guisu_crypto::{decrypt, decrypt_file_content, encrypt_with_armor};
 use owo_colors::OwoColorize;
 use std::env;
 use std::fs;
 use std::path::{Path, PathBuf};
 use std::process::Command as ProcessCommand;
+use std::time::Duration;
 use tempfile::TempDir;
 
 use crate::command::Command;
@@ -32,68 +33,396 @@ pub struct EditCommand {
     /// Apply changes after editing
     #[arg(short, long)]
     pub apply: bool,
+
+    /// Apply changes each time the file is saved, while the editor is open
+    #[arg(short, long)]
+    pub watch: bool,
 }
 
 impl Command for EditCommand {
     type Output = ();
     fn execute(&self, context: &RuntimeContext) -> crate::error::Result<()> {
-        run_impl(
-            context.source_dir(),
-            context.dest_dir().as_path(),
-            &self.target,
-            self.apply,
-            &context.config,
-        )
-        .map_err(Into::into)
+        run_impl(context, &self.target, self.apply, self.watch).map_err(Into::into)
     }
 }
 
+/// How often the edited file is checked for saves with `--watch`
+const WATCH_INTERVAL: Duration = Duration::from_millis(300);
+
 /// Run the edit command implementation
-fn run_impl(
-    source_dir: &Path,
-    dest_dir: &Path,
-    target: &Path,
-    apply: bool,
-    config: &Config,
-) -> Result<()> {
+fn run_impl(context: &RuntimeContext, target: &Path, apply: bool, watch: bool) -> Result<()> {
+    let source_dir = context.source_dir();
+    let config = &context.config;
+
     // Find the source file corresponding to the target
-    let source_file = find_source_file(source_dir, dest_dir, target, config)?;
+    let source_file = find_source_file(source_dir, context.dest_dir().as_path(), target, config)?;
+
+    // Target path used to look up `[age.scopes]` recipients on re-encryption
+    let target_path = crate::cmd::age::target_path_for_source(source_dir, &source_file, config)
+        .unwrap_or_default();
 
-    // Check if the file is encrypted
-    let is_encrypted = source_file
-        .extension()
-        .and_then(|e| e.to_str())
-        .is_some_and(|e| e == "age");
+    let mut session = EditSession::open(&source_file, &target_path, config)?;
+    let edit_path = session.edit_path.clone();
+    let (editor, args) = get_editor(config);
 
-    if is_encrypted {
-        edit_encrypted_file(&source_file, config)?;
+    let saved_on_exit = if watch {
+        let mut stats = WatchStats::default();
+        run_editor_watching(&editor, &args, &edit_path, || match session.save() {
+            Ok(true) => stats.record(apply_unattended(context, target)),
+            Ok(false) => {}
+            Err(e) => stats.failures.push(format!("{e:#}")),
+        })?;
+        let saved = session.save()?;
+        stats.print();
+        saved
     } else {
-        edit_regular_file(&source_file, config)?;
-    }
+        run_editor(&editor, &args, &edit_path)?;
+        session.save()?
+    };
+
+    session.print_outcome();
 
-    // Apply if requested
-    if apply {
+    // Apply if requested, or catch up on changes made after the last watched save
+    if apply || (watch && saved_on_exit) {
         println!("\n  {} Applying changes...", "→".bright_blue());
 
-        // Create ApplyCommand with target file
         let apply_cmd = crate::cmd::apply::ApplyCommand {
             files: vec![target.to_path_buf()],
             dry_run: false,
             force: false,
+            merge: false,
+            prune: false,
             interactive: false,
             include: vec![],
             exclude: vec![],
+            jobs: None,
+            backup: false,
+            refresh_externals: false,
+            one_shot: None,
+            source_ref: None,
+            timings: false,
         };
-
-        // Create RuntimeContext and execute
-        let context = crate::common::RuntimeContext::new(config.clone(), source_dir, dest_dir)?;
-        apply_cmd.execute(&context)?;
+        apply_cmd.execute(context)?;
     }
 
     println!();
     Ok(())
 }
 
+/// Apply the edited target without printing or prompting
+///
+/// Output would garble the editor, so results are collected and shown once it
+/// closes. A destination modified locally is skipped rather than prompted for.
+fn apply_unattended(
+    context: &RuntimeContext,
+    target: &Path,
+) -> Result<crate::cmd::apply::ApplyReport> {
+    let apply_cmd = crate::cmd::apply::ApplyCommand {
+        files: vec![target.to_path_buf()],
+        dry_run: false,
+        force: false,
+        merge: false,
+        prune: false,
+        interactive: false,
+        include: vec![],
+        exclude: vec![],
+        jobs: None,
+        backup: false,
+        refresh_externals: false,
+        one_shot: None,
+        source_ref: None,
+        timings: false,
+    };
+    apply_cmd.execute_unattended(context)
+}
+
+/// Applies made while the editor was open with `--watch`
+#[derive(Debug, Default)]
+struct WatchStats {
+    applied: usize,
+    skipped: bool,
+    failures: Vec<String>,
+}
+
+impl WatchStats {
+    fn record(&mut self, result: Result<crate::cmd::apply::ApplyReport>) {
+        match result {
+            Ok(report) => {
+                self.applied += 1;
+                self.skipped |= !report.skipped.is_empty();
+                self.failures
+                    .extend(report.failed.into_iter().map(|failure| failure.error));
+            }
+            Err(e) => self.failures.push(format!("{e:#}")),
+        }
+    }
+
+    fn print(&self) {
+        if self.applied > 0 {
+            println!(
+                "  {} Applied {} time(s) while editing",
+                "✓".bright_green(),
+                self.applied
+            );
+        }
+        if self.skipped {
+            println!(
+                "  {} Destination was modified locally and left untouched",
+                "⚠".yellow()
+            );
+        }
+        if let Some(last) = self.failures.last() {
+            println!(
+                "  {} {} apply(s) failed while editing, last error: {}",
+                "⚠".yellow(),
+                self.failures.len(),
+                last
+            );
+        }
+    }
+}
+
+/// How edits are written back to the source file
+enum EditKind {
+    /// The source file is edited in place
+    Plain,
+    /// Whole-file encryption; the decrypted copy is re-encrypted to these recipients
+    Encrypted {
+        recipients: Vec<guisu_crypto::Recipient>,
+        /// Write the source in ASCII armor format
+        armor: bool,
+    },
+    /// Inline `age:` values, as `(ciphertext, plaintext)` pairs
+    Inline { values: Vec<(String, String)> },
+}
+
+/// A source file open for editing
+///
+/// Encrypted content is edited as a decrypted copy in a temporary directory.
+/// Saving writes it back only if the plaintext changed since the last save, so
+/// an unchanged file is never re-encrypted and its ciphertext does not churn.
+struct EditSession<'a> {
+    source_file: &'a Path,
+    kind: EditKind,
+    /// File the editor opens
+    edit_path: PathBuf,
+    /// Keeps the decrypted copy alive while editing
+    _temp_dir: Option<TempDir>,
+    /// Plaintext as last written back to the source
+    saved: Vec<u8>,
+    /// Whether any edit was written back
+    changed: bool,
+}
+
+impl<'a> EditSession<'a> {
+    /// Prepare a source file for editing, decrypting it if needed
+    fn open(source_file: &'a Path, target_path: &Path, config: &Config) -> Result<Self> {
+        // Check if the file is encrypted
+        let is_encrypted = source_file
+            .extension()
+            .and_then(|e| e.to_str())
+            .is_some_and(|e| e == "age");
+
+        if is_encrypted {
+            return Self::open_encrypted(source_file, target_path, config);
+        }
+
+        let content = fs::read(source_file)
+            .with_context(|| format!("Failed to read file: {}", source_file.display()))?;
+
+        // Files with inline age: values are edited decrypted (sops-like behavior)
+        if let Ok(identities) = config.age_identities()
+            && let Ok(text) = std::str::from_utf8(&content)
+            && text.contains("age:")
+        {
+            return Self::open_inline(source_file, text, &identities);
+        }
+
+        Ok(Self {
+            source_file,
+            kind: EditKind::Plain,
+            edit_path: source_file.to_path_buf(),
+            _temp_dir: None,
+            saved: content,
+            changed: false,
+        })
+    }
+
+    /// Decrypt a whole-file encrypted source into a temporary file
+    fn open_encrypted(source_file: &'a Path, target_path: &Path, config: &Config) -> Result<Self> {
+        // Load all configured identities
+        let identities = config
+            .age_identities()
+            .context("Age identity not configured. Cannot edit encrypted files.")?;
+
+        // Read and decrypt the file
+        let encrypted_content = fs::read(source_file)
+            .with_context(|| format!("Failed to read encrypted file: {}", source_file.display()))?;
+        let decrypted_content =
+            decrypt(&encrypted_content, &identities).context("Failed to decrypt file")?;
+
+        // Build temporary file name (remove .age extension)
+        let temp_file_name = source_file
+            .file_stem()
+            .and_then(|s| s.to_str())
+            .context("Invalid file name")?;
+        let (temp_dir, edit_path) = write_temp_file(temp_file_name, &decrypted_content)?;
+
+        Ok(Self {
+            source_file,
+            kind: EditKind::Encrypted {
+                recipients: reencryption_recipients(config, target_path, &identities)?,
+                armor: config.age.armor,
+            },
+            edit_path,
+            _temp_dir: Some(temp_dir),
+            saved: decrypted_content,
+            changed: false,
+        })
+    }
+
+    /// Decrypt the inline values of a source into a temporary file
+    fn open_inline(
+        source_file: &'a Path,
+        content: &str,
+        identities: &[guisu_crypto::Identity],
+    ) -> Result<Self> {
+        // Track all encrypted values for re-encryption using cached regex
+        let values = AGE_VALUE_REGEX
+            .find_iter(content)
+            .filter_map(|m| {
+                let plaintext = guisu_crypto::decrypt_inline(m.as_str(), identities).ok()?;
+                Some((m.as_str().to_string(), plaintext))
+            })
+            .collect();
+
+        // Decrypt all inline values for editing
+        let decrypted_content = decrypt_file_content(content, identities)
+            .context("Failed to decrypt inline age values")?;
+
+        let file_name = source_file
+            .file_name()
+            .context("Invalid file name")?
+            .to_string_lossy();
+        let (temp_dir, edit_path) = write_temp_file(&file_name, decrypted_content.as_bytes())?;
+
+        Ok(Self {
+            source_file,
+            kind: EditKind::Inline { values },
+            edit_path,
+            _temp_dir: Some(temp_dir),
+            saved: decrypted_content.into_bytes(),
+            changed: false,
+        })
+    }
+
+    /// Write the edited content back to the source if it changed since the last save
+    ///
+    /// Returns whether the source was updated.
+    fn save(&mut self) -> Result<bool> {
+        let edited = fs::read(&self.edit_path).context("Failed to read edited content")?;
+
+        // Compare plaintext before encrypting anything
+        if edited == self.saved {
+            return Ok(false);
+        }
+
+        match &self.kind {
+            // The editor already wrote the source
+            EditKind::Plain => {}
+            EditKind::Encrypted { recipients, armor } => {
+                let reencrypted_content = encrypt_with_armor(&edited, recipients, *armor)
+                    .context("Failed to re-encrypt file")?;
+                fs::write(self.source_file, &reencrypted_content).with_context(|| {
+                    format!(
+                        "Failed to write encrypted file: {}",
+                        self.source_file.display()
+                    )
+                })?;
+            }
+            EditKind::Inline { values } => {
+                let edited_text =
+                    std::str::from_utf8(&edited).context("Edited content is not valid UTF-8")?;
+                fs::write(self.source_file, restore_inline_values(edited_text, values))
+                    .with_context(|| {
+                        format!("Failed to write file: {}", self.source_file.display())
+                    })?;
+            }
+        }
+
+        self.saved = edited;
+        self.changed = true;
+        Ok(true)
+    }
+
+    /// Tell the user whether a decrypted copy was written back
+    fn print_outcome(&self) {
+        match (&self.kind, self.changed) {
+            (EditKind::Plain, _) => {}
+            (_, false) => println!("  {} No changes made", "ℹ".bright_blue()),
+            (EditKind::Encrypted { .. }, true) => {
+                println!("  {} Encrypted file updated", "✓".bright_green());
+            }
+            (EditKind::Inline { .. }, true) => println!(
+                "  {} File updated with re-encrypted values",
+                "✓".bright_green()
+            ),
+        }
+    }
+}
+
+/// Write decrypted content to a file named `file_name` in a new temporary directory
+///
+/// The directory is created in the user's runtime directory when there is one
+/// (`$XDG_RUNTIME_DIR`, a memory-backed tmpfs on Linux), so the plaintext
+/// never reaches the disk, and in the system temporary directory otherwise.
+/// Only the user can read the directory and the file.
+fn write_temp_file(file_name: &str, content: &[u8]) -> Result<(TempDir, PathBuf)> {
+    let mut builder = tempfile::Builder::new();
+    builder.prefix("guisu-edit-");
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        builder.permissions(fs::Permissions::from_mode(0o700));
+    }
+    let temp_dir = match ::dirs::runtime_dir().filter(|dir| dir.is_dir()) {
+        Some(runtime_dir) => builder.tempdir_in(runtime_dir),
+        None => builder.tempdir(),
+    }
+    .context("Failed to create temporary directory")?;
+
+    let temp_file = temp_dir.path().join(file_name);
+    let mut options = fs::OpenOptions::new();
+    options.write(true).create_new(true);
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::OpenOptionsExt;
+        options.mode(0o600);
+    }
+    options
+        .open(&temp_file)
+        .and_then(|mut file| {
+            use std::io::Write;
+            file.write_all(content)
+        })
+        .context("Failed to write decrypted content to temporary file")?;
+    Ok((temp_dir, temp_file))
+}
+
+/// Put the original ciphertext back for every inline value whose plaintext is unchanged
+///
+/// Keeping the ciphertext instead of encrypting again avoids a new nonce, and
+/// so a diff, for every value the edit did not touch.
+fn restore_inline_values(content: &str, values: &[(String, String)]) -> String {
+    let mut restored = content.to_string();
+    for (ciphertext, plaintext) in values {
+        if restored.contains(plaintext.as_str()) {
+            restored = restored.replacen(plaintext.as_str(), ciphertext, 1);
+        }
+    }
+    restored
+}
+
 /// Find the source file corresponding to a target file
 fn find_source_file(
     source_dir: &Path,
@@ -152,11 +481,26 @@ fn find_source_file(
         }
     }
 
+    // Compressed sources are binary and not edited in place
+    if let Some(file_name) = base_path.file_name() {
+        let compressed = format!("compress_{}", file_name.to_string_lossy());
+        if [compressed.clone(), format!("{compressed}.age")]
+            .iter()
+            .any(|name| base_path.with_file_name(name).exists())
+        {
+            anyhow::bail!(
+                "{} is stored compressed and cannot be edited. Change the destination file and run: guisu re-add {}",
+                target.display(),
+                target.display()
+            );
+        }
+    }
+
     anyhow::bail!("File not managed by guisu: {}", target.display())
 }
 
 /// Get the editor command to use
-fn get_editor(config: &Config) -> (String, Vec<String>) {
+pub(crate) fn get_editor(config: &Config) -> (String, Vec<String>) {
     // 4. System default editor constants
     #[cfg(unix)]
     const DEFAULT_EDITOR: &str = "vi";
@@ -184,7 +528,7 @@ fn get_editor(config: &Config) -> (String, Vec<String>) {
 }
 
 /// Run the editor with the given file
-fn run_editor(editor: &str, args: &[String], file: &Path) -> Result<()> {
+pub(crate) fn run_editor(editor: &str, args: &[String], file: &Path) -> Result<()> {
     let status = ProcessCommand::new(editor)
         .args(args)
         .arg(file)
@@ -198,152 +542,54 @@ fn run_editor(editor: &str, args: &[String], file: &Path) -> Result<()> {
     Ok(())
 }
 
-/// Edit a regular (non-encrypted) file
-/// This also handles files with inline age: encrypted values (sops-like behavior)
-fn edit_regular_file(source_file: &Path, config: &Config) -> Result<()> {
-    // Try to load all configured identities for inline decryption
-    let identities = config.age_identities().ok();
-
-    // If we have identities, check if the file contains inline encrypted values
-    if let Some(ref ids) = identities
-        && let Ok(content) = fs::read_to_string(source_file)
-    {
-        // Check if content contains age: prefix
-        if content.contains("age:") {
-            // Edit with inline decryption/encryption
-            return edit_file_with_inline_encryption(source_file, config, ids);
-        }
-    }
-
-    // No inline encryption or no identities - edit normally
-    let (editor, args) = get_editor(config);
-    run_editor(&editor, &args, source_file)
-}
-
-/// Edit a file that contains inline age: encrypted values
-/// Decrypts them before editing and re-encrypts after
-fn edit_file_with_inline_encryption(
-    source_file: &Path,
-    config: &Config,
-    identities: &[guisu_crypto::Identity],
+/// Run the editor, calling `on_save` each time the file's modification time changes
+fn run_editor_watching(
+    editor: &str,
+    args: &[String],
+    file: &Path,
+    mut on_save: impl FnMut(),
 ) -> Result<()> {
-    // Read the original file content
-    let original_content = fs::read_to_string(source_file)
-        .with_context(|| format!("Failed to read file: {}", source_file.display()))?;
-
-    // Track all encrypted values for re-encryption using cached regex
-    let encrypted_positions: Vec<_> = AGE_VALUE_REGEX
-        .find_iter(&original_content)
-        .map(|m| (m.start(), m.end(), m.as_str().to_string()))
-        .collect();
-
-    // Decrypt all inline values for editing
-    let decrypted_content = decrypt_file_content(&original_content, identities)
-        .context("Failed to decrypt inline age values")?;
-
-    // Create temporary file
-    let temp_dir = tempfile::TempDir::new().context("Failed to create temporary directory")?;
-    let temp_file = temp_dir
-        .path()
-        .join(source_file.file_name().context("Invalid file name")?);
-
-    // Write decrypted content to temp file
-    fs::write(&temp_file, &decrypted_content)
-        .context("Failed to write decrypted content to temporary file")?;
+    let modified = |file: &Path| fs::metadata(file).and_then(|m| m.modified()).ok();
 
-    // Open editor
-    let (editor, args) = get_editor(config);
-    run_editor(&editor, &args, &temp_file)?;
-
-    // Read edited content
-    let edited_content = fs::read_to_string(&temp_file).context("Failed to read edited content")?;
-
-    // Check if content changed
-    if edited_content == decrypted_content {
-        println!("  {} No changes made", "ℹ".bright_blue());
-        return Ok(());
-    }
+    let mut child = ProcessCommand::new(editor)
+        .args(args)
+        .arg(file)
+        .spawn()
+        .with_context(|| format!("Failed to run editor: {editor}"))?;
+    let mut last_modified = modified(file);
 
-    // Re-encrypt the edited plaintext values
-    let mut final_content = edited_content;
+    loop {
+        if let Some(status) = child.try_wait().context("Failed to wait for editor")? {
+            if !status.success() {
+                anyhow::bail!("Editor exited with error: {status}");
+            }
+            return Ok(());
+        }
 
-    // Convert all identities to recipients for re-encryption
-    let recipients = guisu_crypto::identities_to_recipients(identities);
+        std::thread::sleep(WATCH_INTERVAL);
 
-    for (_, _, encrypted_value) in encrypted_positions {
-        if let Ok(decrypted_value) = guisu_crypto::decrypt_inline(&encrypted_value, identities)
-            && final_content.contains(&decrypted_value)
-        {
-            let new_encrypted = encrypt_inline(&decrypted_value, &recipients)
-                .context("Failed to re-encrypt value")?;
-            final_content = final_content.replacen(&decrypted_value, &new_encrypted, 1);
+        let current = modified(file);
+        if current != last_modified {
+            last_modified = current;
+            on_save();
         }
     }
-
-    // Write the final content back to source file
-    fs::write(source_file, &final_content)
-        .with_context(|| format!("Failed to write file: {}", source_file.display()))?;
-
-    println!(
-        "  {} File updated with re-encrypted values",
-        "✓".bright_green()
-    );
-
-    Ok(())
 }
 
-/// Edit an encrypted file with transparent decryption/encryption
-fn edit_encrypted_file(source_file: &Path, config: &Config) -> Result<()> {
-    // Load all configured identities
-    let identities = config
-        .age_identities()
-        .context("Age identity not configured. Cannot edit encrypted files.")?;
-
-    // Read and decrypt the file
-    let encrypted_content = fs::read(source_file)
-        .with_context(|| format!("Failed to read encrypted file: {}", source_file.display()))?;
-
-    let decrypted_content =
-        decrypt(&encrypted_content, &identities).context("Failed to decrypt file")?;
-
-    // Create temporary directory and file
-    let temp_dir = TempDir::new().context("Failed to create temporary directory")?;
-
-    // Build temporary file name (remove .age extension)
-    let temp_file_name = source_file
-        .file_stem()
-        .and_then(|s| s.to_str())
-        .context("Invalid file name")?;
-
-    let temp_file = temp_dir.path().join(temp_file_name);
-
-    // Write decrypted content to temporary file
-    fs::write(&temp_file, &decrypted_content)
-        .context("Failed to write decrypted content to temporary file")?;
-
-    // Get editor and run it
-    let (editor, args) = get_editor(config);
-    run_editor(&editor, &args, &temp_file)?;
-
-    // Read the edited content
-    let edited_content =
-        fs::read(&temp_file).context("Failed to read edited content from temporary file")?;
-
-    // Check if content changed
-    if edited_content == decrypted_content {
-        return Ok(());
+/// Get the recipients to re-encrypt an edited entry to
+///
+/// Entries matching an `[age.scopes]` pattern go only to the scope's recipients;
+/// everything else is re-encrypted to all configured identities.
+fn reencryption_recipients(
+    config: &Config,
+    target_path: &Path,
+    identities: &[guisu_crypto::Identity],
+) -> Result<Vec<guisu_crypto::Recipient>> {
+    if config.age_scope_for(target_path)?.is_some() {
+        return Ok(config.age_recipients_for(target_path)?);
     }
 
-    // Re-encrypt the content with all recipients
-    let recipients = guisu_crypto::identities_to_recipients(&identities);
-    let reencrypted_content =
-        encrypt(&edited_content, &recipients).context("Failed to re-encrypt file")?;
-
-    // Write back to source file
-    fs::write(source_file, &reencrypted_content)
-        .with_context(|| format!("Failed to write encrypted file: {}", source_file.display()))?;
-
-    Ok(())
+    Ok(guisu_crypto::identities_to_recipients(identities))
 }
 
 #[cfg(test)]
@@ -560,6 +806,54 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_restore_inline_values_keeps_unchanged_ciphertext() {
+        let values = vec![
+            ("age:T0xE".to_string(), "old-token".to_string()),
+            ("age:UEFTUw==".to_string(), "hunter2".to_string()),
+        ];
+
+        let restored = restore_inline_values("token = old-token\npassword = hunter2\n", &values);
+        assert_eq!(restored, "token = age:T0xE\npassword = age:UEFTUw==\n");
+    }
+
+    #[test]
+    fn test_edit_session_reencrypts_only_changed_plaintext() {
+        use guisu_crypto::{Identity, IdentityFile};
+
+        let temp = TempDir::new().expect("Failed to create temp dir");
+        let identity_file = temp.path().join("identity.txt");
+        let identity = Identity::generate();
+        IdentityFile::save(&identity_file, std::slice::from_ref(&identity))
+            .expect("Failed to save identity");
+        let mut config = test_config();
+        config.age.identity = Some(identity_file);
+
+        let source_file = temp.path().join("secret.txt.age");
+        let ciphertext =
+            guisu_crypto::encrypt(b"token = 1\n", &[identity.to_public()]).expect("encrypt");
+        fs::write(&source_file, &ciphertext).expect("Failed to write source");
+
+        let mut session = EditSession::open(&source_file, Path::new("secret.txt"), &config)
+            .expect("Failed to open session");
+        assert_ne!(session.edit_path, source_file);
+
+        // Saving without changes leaves the ciphertext alone
+        assert!(!session.save().expect("save failed"));
+        assert_eq!(fs::read(&source_file).expect("read"), ciphertext);
+
+        fs::write(&session.edit_path, "token = 2\n").expect("Failed to edit");
+        assert!(session.save().expect("save failed"));
+        let decrypted = decrypt(&fs::read(&source_file).expect("read"), &[identity])
+            .expect("Failed to decrypt");
+        assert_eq!(decrypted, b"token = 2\n");
+
+        // Saving the same plaintext again does not re-encrypt
+        let reencrypted = fs::read(&source_file).expect("read");
+        assert!(!session.save().expect("save failed"));
+        assert_eq!(fs::read(&source_file).expect("read"), reencrypted);
+    }
+
     #[test]
     fn test_find_source_file_prefers_plain() {
         let temp = TempDir::new().expect("Failed to create temp dir");
@@ -588,4 +882,21 @@ mod tests {
         // Should prefer plain file (checked first in candidates list)
         assert_eq!(result.unwrap(), plain_file);
     }
+
+    #[test]
+    fn test_write_temp_file_is_private() {
+        let (temp_dir, path) = write_temp_file("secret.txt", b"token=abc\n").unwrap();
+        assert_eq!(fs::read(&path).unwrap(), b"token=abc\n");
+        if let Some(runtime_dir) = ::dirs::runtime_dir().filter(|dir| dir.is_dir()) {
+            assert!(temp_dir.path().starts_with(runtime_dir));
+        }
+
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            let mode = |path: &Path| fs::metadata(path).unwrap().permissions().mode() & 0o777;
+            assert_eq!(mode(&path), 0o600);
+            assert_eq!(mode(temp_dir.path()), 0o700);
+        }
+    }
 }
@@ -0,0 +1,145 @@
+//! Execute-template command implementation
+//!
+//! Render arbitrary template text with the same context managed files get, to
+//! debug templates without editing the source directory.
+
+use anyhow::{Context, Result};
+use clap::Args;
+use guisu_config::Config;
+use guisu_engine::adapters::template::template_context;
+use std::io::{Read, Write};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+use crate::command::Command;
+use crate::common::RuntimeContext;
+
+/// Execute-template command
+#[derive(Debug, Args)]
+pub struct ExecuteTemplateCommand {
+    /// Template file to render, or `-` for stdin (default)
+    pub file: Option<PathBuf>,
+
+    /// Render with the context of `.guisu.toml.j2` (system info and .guisu/data only)
+    #[arg(long)]
+    pub init: bool,
+}
+
+impl Command for ExecuteTemplateCommand {
+    type Output = ();
+    fn execute(&self, context: &RuntimeContext) -> crate::error::Result<()> {
+        let (name, template) = read_template(self.file.as_deref())?;
+
+        let rendered = if self.init {
+            crate::render_config_template(context.source_dir(), &template, None)?
+        } else {
+            render(
+                &name,
+                &template,
+                context.source_dir(),
+                context.dest_dir().as_path(),
+                &context.config,
+            )?
+        };
+
+        std::io::stdout()
+            .lock()
+            .write_all(rendered.as_bytes())
+            .context("Failed to write rendered template")?;
+        Ok(())
+    }
+}
+
+/// Name and content of the template to render, read from `file` or stdin
+fn read_template(file: Option<&Path>) -> Result<(String, String)> {
+    match file {
+        Some(path) if path != Path::new("-") => {
+            let template = std::fs::read_to_string(path)
+                .with_context(|| format!("Failed to read template: {}", path.display()))?;
+            Ok((path.display().to_string(), template))
+        }
+        _ => {
+            let mut template = String::new();
+            std::io::stdin()
+                .read_to_string(&mut template)
+                .context("Failed to read template from stdin")?;
+            Ok(("<stdin>".to_string(), template))
+        }
+    }
+}
+
+/// Render `template` with the full context: guisu info, variables, and vault functions
+fn render(
+    name: &str,
+    template: &str,
+    source_dir: &Path,
+    dest_dir: &Path,
+    config: &Config,
+) -> Result<String> {
+    // Templates without encrypted values render even when no identity is configured
+    let identities = Arc::new(config.age_identities().unwrap_or_default());
+    let engine = crate::create_template_engine(source_dir, &identities, config)?;
+
+    let context = template_context(
+        source_dir,
+        &config.dotfiles_dir(source_dir),
+        dest_dir,
+        config,
+    )
+    .context("Failed to load variables")?;
+
+    engine
+        .render_named_str(name, template, &context)
+        .map_err(|e| anyhow::anyhow!("Failed to render {name}: {e}"))
+}
+
+#[cfg(test)]
+mod tests {
+    #![allow(clippy::unwrap_used, clippy::panic)]
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_render_with_variables_and_guisu_info() {
+        let temp = TempDir::new().unwrap();
+        let mut config = Config::default();
+        config
+            .variables
+            .insert("email".to_string(), serde_json::json!("me@example.com"));
+
+        let rendered = render(
+            "test",
+            "{{ email }} {{ guisu.rootEntry }}",
+            temp.path(),
+            temp.path(),
+            &config,
+        )
+        .unwrap();
+        assert_eq!(rendered, "me@example.com home");
+    }
+
+    #[test]
+    fn test_render_error_names_template() {
+        let temp = TempDir::new().unwrap();
+        let err = render(
+            "probe.j2",
+            "{{ nope( }}",
+            temp.path(),
+            temp.path(),
+            &Config::default(),
+        )
+        .unwrap_err();
+        assert!(err.to_string().contains("probe.j2"));
+    }
+
+    #[test]
+    fn test_read_template_file() {
+        let temp = TempDir::new().unwrap();
+        let path = temp.path().join("t.j2");
+        std::fs::write(&path, "{{ os }}").unwrap();
+
+        let (name, template) = read_template(Some(&path)).unwrap();
+        assert_eq!(name, path.display().to_string());
+        assert_eq!(template, "{{ os }}");
+    }
+}
@@ -0,0 +1,353 @@
+//! Forget command implementation
+//!
+//! Stop managing targets: remove their source files and recorded state, and
+//! optionally the destination files, so later `status` and `apply` runs no
+//! longer consider them.
+
+use anyhow::{Context, Result};
+use clap::Args;
+use guisu_core::path::{AbsPath, RelPath};
+use guisu_engine::entry::SourceEntry;
+use guisu_engine::state::{Metadata, SourceState};
+use owo_colors::OwoColorize;
+use std::fs;
+use std::path::{Path, PathBuf};
+use tracing::debug;
+
+use crate::command::Command;
+use crate::common::RuntimeContext;
+
+/// Stop managing target files
+#[derive(Debug, Clone, Args)]
+pub struct ForgetCommand {
+    /// Target files or directories to stop managing (e.g., ~/.bashrc)
+    #[arg(required = true)]
+    pub files: Vec<PathBuf>,
+
+    /// Also delete the files from the destination directory
+    #[arg(long)]
+    pub destination: bool,
+
+    /// Skip confirmation prompt
+    #[arg(short, long)]
+    pub yes: bool,
+}
+
+impl Command for ForgetCommand {
+    type Output = ();
+    fn execute(&self, context: &RuntimeContext) -> crate::error::Result<()> {
+        let source_state = SourceState::read(context.dotfiles_dir().to_owned())
+            .context("Failed to read source state")?;
+        let rel_paths =
+            crate::build_filter_paths(&self.files, context.dest_dir(), context.targets())?;
+
+        let mut entries = Vec::new();
+        for (rel_path, file_path) in rel_paths.iter().zip(&self.files) {
+            let managed = managed_entries(&source_state, rel_path);
+            if managed.is_empty() {
+                return Err(
+                    anyhow::anyhow!("File not managed by guisu: {}", file_path.display()).into(),
+                );
+            }
+            entries.extend(managed);
+        }
+        entries.sort_by(|a, b| a.target_path().as_path().cmp(b.target_path().as_path()));
+        entries.dedup_by(|a, b| a.target_path() == b.target_path());
+
+        println!("{}", "Entries to forget:".bold());
+        for entry in &entries {
+            println!(
+                "  {} {} {}",
+                "-".red(),
+                entry.target_path().bright_white(),
+                format!("({})", entry.source_path()).dimmed()
+            );
+        }
+
+        if !self.yes {
+            use dialoguer::{Confirm, theme::ColorfulTheme};
+
+            let prompt = if self.destination {
+                "Remove these entries from the source and destination directories?"
+            } else {
+                "Remove these entries from the source directory?"
+            };
+            let confirmed = Confirm::with_theme(&ColorfulTheme::default())
+                .with_prompt(prompt)
+                .default(false)
+                .interact()
+                .context("Failed to read user input")?;
+
+            if !confirmed {
+                println!("Cancelled.");
+                return Ok(());
+            }
+        }
+
+        forget_entries(
+            context,
+            &source_state,
+            &entries,
+            &rel_paths,
+            self.destination,
+        )?;
+
+        println!(
+            "{} {} {}",
+            "Forgot".bright_green().bold(),
+            entries.len(),
+            if entries.len() == 1 {
+                "entry"
+            } else {
+                "entries"
+            }
+        );
+        Ok(())
+    }
+}
+
+/// Source entries managed at or below a target path
+///
+/// A path naming a managed file yields that file; a directory yields every
+/// managed file inside it.
+pub(crate) fn managed_entries<'a>(
+    source_state: &'a SourceState,
+    rel_path: &RelPath,
+) -> Vec<&'a SourceEntry> {
+    if let Some(entry) = source_state.get(rel_path) {
+        return vec![entry];
+    }
+
+    source_state
+        .entries()
+        .filter(|entry| {
+            entry
+                .target_path()
+                .as_path()
+                .starts_with(rel_path.as_path())
+        })
+        .collect()
+}
+
+/// Remove the entries' source files, recorded state, and optionally destinations
+///
+/// Directories left empty under the forgotten paths are removed as well.
+fn forget_entries(
+    context: &RuntimeContext,
+    source_state: &SourceState,
+    entries: &[&SourceEntry],
+    rel_paths: &[RelPath],
+    destination: bool,
+) -> Result<()> {
+    let source_dir = context.source_dir();
+    let mut metadata = Metadata::load(source_dir).context("Failed to load metadata")?;
+    let mut metadata_changed = false;
+
+    for entry in entries {
+        let target_path = entry.target_path();
+
+        let source_file = source_state.source_file_path(entry.source_path());
+        remove_path(&source_file)
+            .with_context(|| format!("Failed to remove source file: {source_file}"))?;
+
+        guisu_engine::database::delete_entry_state(context.database(), &target_path.to_string())
+            .with_context(|| format!("Failed to delete state of {target_path}"))?;
+        metadata_changed |= metadata.remove_create_once(&target_path.to_string());
+
+        if destination {
+            let dest_file = context.dest_path(target_path);
+            remove_path(&dest_file)
+                .with_context(|| format!("Failed to remove destination file: {dest_file}"))?;
+        }
+
+        debug!(path = %target_path, "Forgot entry");
+    }
+
+    if metadata_changed {
+        metadata
+            .save(source_dir)
+            .context("Failed to save metadata")?;
+    }
+
+    for rel_path in rel_paths {
+        remove_empty_dirs(&source_state.root().join(rel_path));
+        if destination {
+            remove_empty_dirs(&context.dest_path(rel_path));
+        }
+    }
+
+    Ok(())
+}
+
+/// Remove a file or symlink, treating a missing path as already removed
+fn remove_path(path: &AbsPath) -> std::io::Result<()> {
+    match fs::remove_file(path.as_path()) {
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(()),
+        result => result,
+    }
+}
+
+/// Remove `dir` and the directories below it, if they are empty
+///
+/// Directories that still hold unmanaged files are kept.
+fn remove_empty_dirs(dir: &AbsPath) {
+    if !fs::symlink_metadata(dir.as_path()).is_ok_and(|metadata| metadata.is_dir()) {
+        return;
+    }
+
+    // Deepest directories first, so parents are empty by the time they are reached
+    let mut dirs: Vec<PathBuf> = walkdir::WalkDir::new(dir.as_path())
+        .follow_links(false)
+        .into_iter()
+        .filter_map(std::result::Result::ok)
+        .filter(|entry| entry.file_type().is_dir())
+        .map(walkdir::DirEntry::into_path)
+        .collect();
+    dirs.sort_by_key(|path| std::cmp::Reverse(path.components().count()));
+
+    for path in dirs {
+        remove_dir_if_empty(&path);
+    }
+}
+
+/// Remove the destination directories in `parents` that are left empty
+///
+/// `parents` are relative to the destination; the deepest are tried first,
+/// so a directory is removed once the empty ones below it are gone.
+pub(crate) fn remove_empty_parents(
+    parents: impl IntoIterator<Item = PathBuf>,
+    dest_path: impl Fn(&RelPath) -> AbsPath,
+) {
+    let mut parents: Vec<PathBuf> = parents
+        .into_iter()
+        .filter(|dir| !dir.as_os_str().is_empty())
+        .collect();
+    parents.sort_by_key(|dir| std::cmp::Reverse(dir.components().count()));
+    for dir in parents {
+        if let Ok(dir) = RelPath::new(dir) {
+            remove_dir_if_empty(dest_path(&dir).as_path());
+        }
+    }
+}
+
+pub(crate) fn remove_dir_if_empty(path: &Path) {
+    if fs::read_dir(path).is_ok_and(|mut entries| entries.next().is_none())
+        && let Err(e) = fs::remove_dir(path)
+    {
+        debug!(path = %path.display(), error = %e, "Failed to remove empty directory");
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    #![allow(clippy::unwrap_used, clippy::panic)]
+    use super::*;
+    use crate::common::testing::{TestWorkspace, write};
+    use guisu_config::Config;
+
+    /// Source and destination with the given managed files applied to both
+    fn setup(files: &[(&str, &str)]) -> TestWorkspace {
+        let workspace = TestWorkspace::new(Config::default());
+        for (source_path, target_path) in files {
+            write(&workspace.source(source_path), "content");
+            write(&workspace.dest(target_path), "content");
+            guisu_engine::database::save_entry_state(
+                workspace.context.database(),
+                target_path,
+                b"content",
+                None,
+                guisu_engine::database::MergeBase::Keep,
+                &workspace.context.clock.begin_run(),
+            )
+            .unwrap();
+        }
+        workspace
+    }
+
+    fn forget(workspace: &TestWorkspace, targets: &[&str], destination: bool) {
+        let context = &workspace.context;
+        let source_state = SourceState::read(context.dotfiles_dir().to_owned()).unwrap();
+        let rel_paths: Vec<RelPath> = targets
+            .iter()
+            .map(|target| RelPath::new(PathBuf::from(target)).unwrap())
+            .collect();
+        let entries: Vec<&SourceEntry> = rel_paths
+            .iter()
+            .flat_map(|rel_path| managed_entries(&source_state, rel_path))
+            .collect();
+        forget_entries(context, &source_state, &entries, &rel_paths, destination).unwrap();
+    }
+
+    fn has_state(workspace: &TestWorkspace, path: &str) -> bool {
+        guisu_engine::database::get_entry_state(workspace.context.database(), path)
+            .unwrap()
+            .is_some()
+    }
+
+    #[test]
+    fn test_forget_removes_attribute_encoded_source() {
+        let workspace = setup(&[(".bashrc.j2", ".bashrc"), (".zshrc", ".zshrc")]);
+
+        forget(&workspace, &[".bashrc"], false);
+
+        assert!(!workspace.source(".bashrc.j2").exists());
+        assert!(!has_state(&workspace, ".bashrc"));
+        // The destination and other entries are untouched
+        assert!(workspace.dest(".bashrc").exists());
+        assert!(workspace.source(".zshrc").exists());
+        assert!(has_state(&workspace, ".zshrc"));
+    }
+
+    #[test]
+    fn test_forget_with_destination() {
+        let workspace = setup(&[(".bashrc", ".bashrc")]);
+
+        forget(&workspace, &[".bashrc"], true);
+
+        assert!(!workspace.source(".bashrc").exists());
+        assert!(!workspace.dest(".bashrc").exists());
+    }
+
+    #[test]
+    fn test_forget_directory() {
+        let workspace = setup(&[
+            (".config/nvim/init.lua", ".config/nvim/init.lua"),
+            (
+                ".config/nvim/lua/plugins.lua.age",
+                ".config/nvim/lua/plugins.lua",
+            ),
+            (".config/git/config", ".config/git/config"),
+        ]);
+        write(&workspace.dest(".config/nvim/lazy-lock.json"), "{}");
+
+        forget(&workspace, &[".config/nvim"], true);
+
+        assert!(!workspace.source(".config/nvim").exists());
+        assert!(!has_state(&workspace, ".config/nvim/init.lua"));
+        assert!(!has_state(&workspace, ".config/nvim/lua/plugins.lua"));
+        assert!(workspace.source(".config/git/config").exists());
+
+        // Unmanaged files keep their directory in the destination
+        assert!(workspace.dest(".config/nvim/lazy-lock.json").exists());
+        assert!(!workspace.dest(".config/nvim/init.lua").exists());
+        assert!(!workspace.dest(".config/nvim/lua").exists());
+    }
+
+    #[test]
+    fn test_managed_entries_unmanaged_path() {
+        let workspace = setup(&[(".bashrc", ".bashrc")]);
+        let source_state = SourceState::read(workspace.context.dotfiles_dir().to_owned()).unwrap();
+
+        let rel_path = RelPath::new(PathBuf::from(".profile")).unwrap();
+        assert_eq!(
+            managed_entries(&source_state, &rel_path),
+            Vec::<&SourceEntry>::new()
+        );
+        // A prefix of a file name is not a parent directory
+        let rel_path = RelPath::new(PathBuf::from(".bash")).unwrap();
+        assert_eq!(
+            managed_entries(&source_state, &rel_path),
+            Vec::<&SourceEntry>::new()
+        );
+    }
+}
@@ -0,0 +1,306 @@
+//! Git command implementation
+//!
+//! Install a pre-commit hook in the repository of the source directory. The
+//! hook runs `guisu git pre-commit`, which checks the staged files and blocks
+//! the commit when one of them contains an unencrypted secret, when an `.age`
+//! file is staged without being encrypted, or when the plaintext of an
+//! encrypted file is staged next to it. Findings matching the `[security]`
+//! allowlist are ignored, and `git commit --no-verify` skips the hook.
+
+use anyhow::{Context, Result, bail};
+use git2::{Delta, Repository};
+use guisu_config::SecurityConfig;
+use owo_colors::OwoColorize;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::utils::secrets::SecretScanner;
+
+/// Line identifying hooks written by guisu
+const HOOK_MARKER: &str = "# Installed by guisu git install-hooks";
+
+/// Suffix of encrypted source files
+const AGE_SUFFIX: &str = ".age";
+
+/// Cached pattern of inline encrypted values (`age:` + base64), which are
+/// safe to commit
+static INLINE_VALUE: std::sync::LazyLock<regex::Regex> =
+    std::sync::LazyLock::new(|| regex::Regex::new(r"age:[A-Za-z0-9+/]+=*").expect("Valid regex"));
+
+/// Run git install-hooks command
+///
+/// An existing pre-commit hook not written by guisu is only replaced with
+/// `force`, after renaming it to `pre-commit.guisu-backup`.
+///
+/// # Errors
+///
+/// Returns an error if the source directory is not in a git repository, or if
+/// the hook cannot be written
+pub fn run_install_hooks(source_dir: &Path, force: bool) -> Result<()> {
+    let repo = Repository::discover(source_dir).with_context(|| {
+        format!(
+            "{} is not in a git repository. Run `git init` there first.",
+            source_dir.display()
+        )
+    })?;
+    let hook = hooks_dir(&repo)?.join("pre-commit");
+
+    if let Ok(existing) = fs::read_to_string(&hook)
+        && !existing.contains(HOOK_MARKER)
+    {
+        if !force {
+            bail!(
+                "{} already exists. Pass --force to replace it (it is renamed to pre-commit.guisu-backup first).",
+                hook.display()
+            );
+        }
+        let backup = hook.with_file_name("pre-commit.guisu-backup");
+        fs::rename(&hook, &backup)
+            .with_context(|| format!("Failed to back up {}", hook.display()))?;
+        println!("{} {}", "Backed up".yellow(), backup.display());
+    }
+
+    let guisu = std::env::current_exe().context("Failed to locate the guisu executable")?;
+    let source_dir = fs::canonicalize(source_dir)
+        .with_context(|| format!("Failed to resolve {}", source_dir.display()))?;
+    if let Some(parent) = hook.parent() {
+        fs::create_dir_all(parent)
+            .with_context(|| format!("Failed to create {}", parent.display()))?;
+    }
+    fs::write(&hook, hook_script(&guisu, &source_dir))
+        .with_context(|| format!("Failed to write {}", hook.display()))?;
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        fs::set_permissions(&hook, fs::Permissions::from_mode(0o755))
+            .with_context(|| format!("Failed to make {} executable", hook.display()))?;
+    }
+
+    println!("{} {}", "Installed".green(), hook.display());
+    println!(
+        "{}",
+        "Commits with unencrypted secrets are now blocked; `git commit --no-verify` skips the check."
+            .dimmed()
+    );
+    Ok(())
+}
+
+/// Run git pre-commit command, the check done by the installed hook
+///
+/// # Errors
+///
+/// Returns an error if the staged files cannot be read, the `[security]`
+/// allowlist is invalid, or a staged file is blocked
+pub fn run_pre_commit(source_dir: &Path, security: &SecurityConfig) -> Result<()> {
+    let repo = Repository::discover(source_dir)
+        .with_context(|| format!("{} is not in a git repository", source_dir.display()))?;
+    let scanner = SecretScanner::new(security)?;
+    let blocked = staged_findings(&repo, &scanner)?;
+    if blocked.is_empty() {
+        return Ok(());
+    }
+
+    eprintln!(
+        "{}",
+        "Staged files contain unencrypted secrets:".red().bold()
+    );
+    for (path, findings) in &blocked {
+        eprintln!("\n{}\n{findings}", path.bright_white());
+    }
+    eprintln!(
+        "\n{}",
+        "Encrypt them (guisu add --encrypt, or inline age: values), add findings to the \
+         [security] allowlist, or commit with --no-verify."
+            .dimmed()
+    );
+    bail!(
+        "Commit blocked: {} file(s) with unencrypted secrets",
+        blocked.len()
+    )
+}
+
+/// Staged files that must not be committed, with the reasons, sorted by path
+///
+/// # Errors
+///
+/// Returns an error if the index or a staged blob cannot be read
+fn staged_findings(repo: &Repository, scanner: &SecretScanner) -> Result<Vec<(String, String)>> {
+    let index = repo.index().context("Failed to read the git index")?;
+    let head = repo.head().ok().and_then(|head| head.peel_to_tree().ok());
+    let diff = repo
+        .diff_tree_to_index(head.as_ref(), Some(&index), None)
+        .context("Failed to list staged changes")?;
+
+    let mut blocked = Vec::new();
+    for delta in diff.deltas() {
+        if !matches!(
+            delta.status(),
+            Delta::Added | Delta::Modified | Delta::Renamed | Delta::Copied
+        ) {
+            continue;
+        }
+        let file = delta.new_file();
+        let Some(path) = file.path().and_then(Path::to_str) else {
+            continue;
+        };
+        let blob = repo
+            .find_blob(file.id())
+            .with_context(|| format!("Failed to read staged {path}"))?;
+        if let Some(findings) = check_staged(path, blob.content(), scanner, |name| {
+            index.get_path(Path::new(name), 0).is_some()
+        }) {
+            blocked.push((path.to_string(), findings));
+        }
+    }
+    blocked.sort();
+    Ok(blocked)
+}
+
+/// Reasons a staged file must not be committed, `None` if it may be
+///
+/// `is_staged` tells whether another path is in the index, to find the
+/// encrypted versions of a plaintext file.
+fn check_staged(
+    path: &str,
+    content: &[u8],
+    scanner: &SecretScanner,
+    is_staged: impl Fn(&str) -> bool,
+) -> Option<String> {
+    if path.to_lowercase().ends_with(AGE_SUFFIX) {
+        return guisu_crypto::AgeHeader::parse(content)
+            .is_none()
+            .then(|| "  • Named .age but not encrypted".to_string());
+    }
+
+    let mut findings = Vec::new();
+    if let Some(encrypted) = [
+        format!("{path}{AGE_SUFFIX}"),
+        format!("{path}.j2{AGE_SUFFIX}"),
+    ]
+    .into_iter()
+    .find(|encrypted| is_staged(encrypted))
+    {
+        findings.push(format!("  • Plaintext of encrypted {encrypted}"));
+    }
+
+    let content = match std::str::from_utf8(content) {
+        Ok(text) => INLINE_VALUE.replace_all(text, "").into_owned().into_bytes(),
+        Err(_) => content.to_vec(),
+    };
+    if let Some(secrets) = scanner.scan(Path::new(path), &content) {
+        findings.push(secrets);
+    }
+
+    (!findings.is_empty()).then(|| findings.join("\n"))
+}
+
+/// Directory of the repository's hooks, honoring `core.hooksPath`
+fn hooks_dir(repo: &Repository) -> Result<PathBuf> {
+    let configured = repo
+        .config()
+        .ok()
+        .and_then(|config| config.get_path("core.hooksPath").ok());
+    Ok(match configured {
+        Some(path) if path.is_absolute() => path,
+        Some(path) => repo
+            .workdir()
+            .context("Relative core.hooksPath in a bare repository")?
+            .join(path),
+        None => repo.path().join("hooks"),
+    })
+}
+
+/// Pre-commit hook running `guisu git pre-commit` for `source_dir`
+///
+/// Falls back to `guisu` from `PATH` if the executable has moved.
+fn hook_script(guisu: &Path, source_dir: &Path) -> String {
+    format!(
+        "#!/bin/sh\n\
+         {HOOK_MARKER}: blocks commits with unencrypted secrets\n\
+         guisu={}\n\
+         [ -x \"$guisu\" ] || guisu=guisu\n\
+         exec \"$guisu\" --source {} git pre-commit\n",
+        shell_quote(&guisu.to_string_lossy()),
+        shell_quote(&source_dir.to_string_lossy()),
+    )
+}
+
+/// Quote `value` for a POSIX shell
+fn shell_quote(value: &str) -> String {
+    format!("'{}'", value.replace('\'', r"'\''"))
+}
+
+#[cfg(test)]
+mod tests {
+    #![allow(clippy::unwrap_used, clippy::panic)]
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_staged_findings() {
+        let temp = TempDir::new().unwrap();
+        let repo = Repository::init(temp.path()).unwrap();
+        let files: [(&str, &[u8]); 5] = [
+            (".bashrc", b"alias ll='ls -l'\n"),
+            (".netrc", b"machine example.com\n"),
+            (
+                ".netrc.age",
+                b"age-encryption.org/v1\n-> X25519 abc\nYm9keQ\n--- mac\n",
+            ),
+            ("fake.age", b"password = hunter22\n"),
+            (
+                ".gitconfig",
+                b"password = age:YWdlLWVuY3J5cHRpb24ub3JnL3YxCi0+\n",
+            ),
+        ];
+        let mut index = repo.index().unwrap();
+        for (name, content) in files {
+            fs::write(temp.path().join(name), content).unwrap();
+            index.add_path(Path::new(name)).unwrap();
+        }
+        fs::write(temp.path().join(".env"), "API_KEY=abcdefgh12345678\n").unwrap();
+        index.add_path(Path::new(".env")).unwrap();
+        index.write().unwrap();
+
+        let blocked = staged_findings(&repo, &SecretScanner::default()).unwrap();
+        let paths: Vec<&str> = blocked.iter().map(|(path, _)| path.as_str()).collect();
+        assert_eq!(paths, [".env", ".netrc", "fake.age"]);
+        assert!(blocked[1].1.contains("Plaintext of encrypted .netrc.age"));
+        assert!(blocked[2].1.contains("not encrypted"));
+
+        let allowlisted = SecretScanner::new(&SecurityConfig {
+            allowlist: vec!["^\\.env$".to_string()],
+            ..SecurityConfig::default()
+        })
+        .unwrap();
+        assert_eq!(staged_findings(&repo, &allowlisted).unwrap().len(), 2);
+    }
+
+    #[test]
+    fn test_install_hooks() {
+        let temp = TempDir::new().unwrap();
+        let repo = Repository::init(temp.path()).unwrap();
+        let hook = repo.path().join("hooks/pre-commit");
+
+        run_install_hooks(temp.path(), false).unwrap();
+        let script = fs::read_to_string(&hook).unwrap();
+        assert!(script.contains(HOOK_MARKER));
+        assert!(script.contains("git pre-commit"));
+        // Reinstalling replaces guisu's own hook
+        run_install_hooks(temp.path(), false).unwrap();
+
+        fs::write(&hook, "#!/bin/sh\nmake lint\n").unwrap();
+        assert!(run_install_hooks(temp.path(), false).is_err());
+        run_install_hooks(temp.path(), true).unwrap();
+        assert_eq!(
+            fs::read_to_string(hook.with_file_name("pre-commit.guisu-backup")).unwrap(),
+            "#!/bin/sh\nmake lint\n"
+        );
+    }
+
+    #[test]
+    fn test_shell_quote() {
+        assert_eq!(shell_quote("/usr/bin/guisu"), "'/usr/bin/guisu'");
+        assert_eq!(shell_quote("it's"), r"'it'\''s'");
+    }
+}
@@ -5,16 +5,26 @@
 
 use anyhow::{Context, Result};
 use guisu_config::Config;
+use guisu_core::path::AbsPath;
 use guisu_core::platform::CURRENT_PLATFORM;
-use guisu_engine::hooks::{HookLoader, HookRunner, HookStage, TemplateRenderer};
-use guisu_engine::state::{HookStatePersistence, RedbPersistentState};
+use guisu_engine::adapters::template::template_context;
+use guisu_engine::clock::RunStamp;
+use guisu_engine::clock::StateClock;
+use guisu_engine::hooks::{
+    HookCollections, HookLoader, HookRun, HookRunner, HookStage, TemplateRenderer, script_hooks,
+};
+use guisu_engine::state::{HookRunLog, HookStatePersistence, RedbPersistentState, SourceState};
 use owo_colors::OwoColorize;
-use std::io::IsTerminal;
-use std::path::{Path, PathBuf};
+use std::io::{IsTerminal, Write};
+use std::path::Path;
 
+use crate::common::ResolvedPaths;
 use crate::ui::icons::StatusIcon;
 use crate::utils::path::SourceDirExt;
 
+/// Number of runs kept in the hook log for each hook
+const HOOK_LOG_HISTORY: usize = 10;
+
 /// Run hooks
 ///
 /// # Errors
@@ -25,13 +35,17 @@ use crate::utils::path::SourceDirExt;
 /// - User confirmation input fails (when not skipped)
 /// - Template engine creation fails
 /// - Hook execution fails
+#[allow(clippy::too_many_lines)]
 pub fn run_hooks(
-    source_dir: &Path,
+    paths: &ResolvedPaths,
     config: &Config,
     db: &RedbPersistentState,
+    clock: &StateClock,
     skip_confirm: bool,
     hook_filter: Option<&str>,
+    dry_run: bool,
 ) -> Result<()> {
+    let source_dir = paths.source_dir.as_path();
     let is_tty = std::io::stdout().is_terminal();
     let use_nerd_fonts = config.ui.icons.should_show_icons(is_tty);
     // Load hooks using HookLoader
@@ -94,6 +108,10 @@ pub fn run_hooks(
     println!("  Pre hooks: {}", collections.pre.len());
     println!("  Post hooks: {}", collections.post.len());
 
+    if dry_run {
+        return print_dry_run(paths, config, db, &collections);
+    }
+
     // Confirm unless --yes is specified
     if !skip_confirm {
         use dialoguer::{Confirm, theme::ColorfulTheme};
@@ -114,24 +132,29 @@ pub fn run_hooks(
     let mut state = persistence.load()?;
 
     // Create template renderer
-    let renderer = create_template_engine(source_dir, config)?;
+    let renderer = create_template_engine(paths, config)?;
 
     // Create hook runner with builder pattern
     // For `hooks run`, always run hooks regardless of state (once/onchange)
     let runner = HookRunner::builder(&collections, source_dir)
         .template_renderer(renderer)
+        .output_handler(print_hook_output)
         .build();
+    let stamp = clock.begin_run();
 
-    // Run hooks in stages
+    // Run hooks in stages, logging output even when a stage fails
     println!("\n{}", "Running pre hooks...".bold());
-    runner
+    let result = runner
         .run_stage(HookStage::Pre)
-        .context("Pre hooks failed")?;
-
-    println!("\n{}", "Running post hooks...".bold());
-    runner
-        .run_stage(HookStage::Post)
-        .context("Post hooks failed")?;
+        .context("Pre hooks failed")
+        .and_then(|()| {
+            println!("\n{}", "Running post hooks...".bold());
+            runner
+                .run_stage(HookStage::Post)
+                .context("Post hooks failed")
+        });
+    save_hook_logs(db, runner.get_runs(), &stamp)?;
+    result?;
 
     // Get newly executed hooks and merge with state
     for hook_name in runner.get_once_executed() {
@@ -147,7 +170,7 @@ pub fn run_hooks(
     // Update state in database
     let hooks_dir = source_dir.hooks_dir();
     state
-        .update(&hooks_dir)
+        .update(&hooks_dir, &stamp)
         .context("Failed to update hook state")?;
 
     persistence
@@ -318,7 +341,8 @@ pub fn run_check(
 /// # Errors
 ///
 /// Returns an error if loading hooks from the hooks directory fails
-pub fn run_show(source_dir: &Path, config: &Config, hook_name: &str) -> Result<()> {
+pub fn run_show(paths: &ResolvedPaths, config: &Config, hook_name: &str) -> Result<()> {
+    let source_dir = paths.source_dir.as_path();
     let is_tty = std::io::stdout().is_terminal();
     let use_nerd_fonts = config.ui.icons.should_show_icons(is_tty);
 
@@ -336,7 +360,7 @@ pub fn run_show(source_dir: &Path, config: &Config, hook_name: &str) -> Result<(
         display_basic_hook_info(hook, stage);
         display_platform_info(hook);
         display_hook_settings(hook);
-        display_script_or_command(hook, source_dir, config);
+        display_script_or_command(hook, paths, config);
         display_environment_variables(hook);
 
         println!();
@@ -400,9 +424,10 @@ fn display_platform_info(hook: &guisu_engine::hooks::config::Hook) {
 /// Display command or script content
 fn display_script_or_command(
     hook: &guisu_engine::hooks::config::Hook,
-    source_dir: &Path,
+    paths: &ResolvedPaths,
     config: &Config,
 ) {
+    let source_dir = paths.source_dir.as_path();
     if let Some(ref cmd) = hook.cmd {
         println!("{} {}", "Command:".bold(), cmd);
     } else if let Some(ref script) = hook.script {
@@ -413,7 +438,7 @@ fn display_script_or_command(
         if script_path.exists()
             && let Ok(content) = std::fs::read_to_string(&script_path)
         {
-            display_script_file_content(&script_path, &content, source_dir, config);
+            display_script_file_content(&script_path, &content, paths, config);
         }
     }
 }
@@ -422,7 +447,7 @@ fn display_script_or_command(
 fn display_script_file_content(
     script_path: &Path,
     content: &str,
-    source_dir: &Path,
+    paths: &ResolvedPaths,
     config: &Config,
 ) {
     let is_template = script_path
@@ -431,7 +456,7 @@ fn display_script_file_content(
         .is_some_and(|ext| ext == "j2");
 
     let display_content = if is_template {
-        render_template_or_show_error(content, source_dir, config)
+        render_template_or_show_error(content, paths, config)
     } else {
         content.to_string()
     };
@@ -446,8 +471,8 @@ fn display_script_file_content(
 }
 
 /// Render template content or return error message with raw template
-fn render_template_or_show_error(content: &str, source_dir: &Path, config: &Config) -> String {
-    match create_template_engine(source_dir, config) {
+fn render_template_or_show_error(content: &str, paths: &ResolvedPaths, config: &Config) -> String {
+    match create_template_engine(paths, config) {
         Ok(template_renderer) => match template_renderer.render(content) {
             Ok(rendered) => rendered,
             Err(e) => format!("Template rendering failed: {e}\n\nRaw template:\n{content}"),
@@ -466,13 +491,23 @@ fn display_environment_variables(hook: &guisu_engine::hooks::config::Hook) {
     }
 }
 
-/// Display timeout and failfast settings
+/// Display timeout, execution and failfast settings
 fn display_hook_settings(hook: &guisu_engine::hooks::config::Hook) {
     // Only display timeout if it's set (non-zero)
     if hook.timeout > 0 {
         println!("{} {} seconds", "Timeout:".bold(), hook.timeout);
     }
 
+    if let Some(working_dir) = &hook.working_dir {
+        println!("{} {}", "Working dir:".bold(), working_dir);
+    }
+    if let Some(shell) = &hook.shell {
+        println!("{} {}", "Shell:".bold(), shell);
+    }
+    if let Some(user) = &hook.user {
+        println!("{} {}", "User:".bold(), user);
+    }
+
     println!("{} {}", "Failfast:".bold(), hook.failfast);
 }
 
@@ -499,12 +534,15 @@ fn display_hook_not_found(hook_name: &str, use_nerd_fonts: bool) {
 /// - Template engine creation fails
 /// - Pre hook execution fails
 pub fn handle_hooks_pre(
-    source_dir: &Path,
+    paths: &ResolvedPaths,
     config: &Config,
     db: &RedbPersistentState,
+    clock: &StateClock,
 ) -> Result<()> {
     use guisu_engine::hooks::config::HookMode;
 
+    let source_dir = paths.source_dir.as_path();
+
     // Load hooks using HookLoader
     let loader = HookLoader::new(source_dir);
 
@@ -548,15 +586,18 @@ pub fn handle_hooks_pre(
     // Only run hooks if there are active ones, but always update state
     if !active_hooks.is_empty() {
         // Create template renderer
-        let renderer = create_template_engine(source_dir, config)?;
+        let renderer = create_template_engine(paths, config)?;
 
         // Create hook runner with builder pattern and run pre hooks
         // Pass persistent state to respect mode=once and mode=onchange
         let runner = HookRunner::builder(&collections, source_dir)
             .template_renderer(renderer)
             .persistent_state(state.once_executed.clone(), state.onchange_hashes.clone())
+            .output_handler(print_hook_output)
             .build();
-        runner.run_stage(HookStage::Pre)?;
+        let result = runner.run_stage(HookStage::Pre);
+        save_hook_logs(db, runner.get_runs(), &clock.begin_run())?;
+        result?;
 
         // Get newly executed hooks and merge with state
         for hook_name in runner.get_once_executed() {
@@ -573,7 +614,7 @@ pub fn handle_hooks_pre(
     // Always update state in database, even if no hooks ran
     // This marks the hooks directory as "checked" and prevents repeated warnings
     let hooks_dir = source_dir.hooks_dir();
-    state.update_with_collections(&hooks_dir, collections)?;
+    state.update_with_collections(&hooks_dir, collections, &clock.begin_run())?;
     persistence.save(&state)?;
 
     Ok(())
@@ -581,6 +622,9 @@ pub fn handle_hooks_pre(
 
 /// Handle hooks after apply
 ///
+/// `changed_files` are the target paths changed by the apply; hooks with
+/// `paths` only run when one of them matches.
+///
 /// # Errors
 ///
 /// Returns an error if:
@@ -589,12 +633,16 @@ pub fn handle_hooks_pre(
 /// - Template engine creation fails
 /// - Post hook execution fails
 pub fn handle_hooks_post(
-    source_dir: &Path,
+    paths: &ResolvedPaths,
     config: &Config,
     db: &RedbPersistentState,
+    clock: &StateClock,
+    changed_files: &[String],
 ) -> Result<()> {
     use guisu_engine::hooks::config::HookMode;
 
+    let source_dir = paths.source_dir.as_path();
+
     // Load hooks using HookLoader
     let loader = HookLoader::new(source_dir);
 
@@ -638,15 +686,19 @@ pub fn handle_hooks_post(
     // Only run hooks if there are active ones, but always update state
     if !active_hooks.is_empty() {
         // Create template renderer
-        let renderer = create_template_engine(source_dir, config)?;
+        let renderer = create_template_engine(paths, config)?;
 
         // Create hook runner with builder pattern and run post hooks
         // Pass persistent state to respect mode=once and mode=onchange
         let runner = HookRunner::builder(&collections, source_dir)
             .template_renderer(renderer)
             .persistent_state(state.once_executed.clone(), state.onchange_hashes.clone())
+            .output_handler(print_hook_output)
+            .changed_files(changed_files.to_vec())
             .build();
-        runner.run_stage(HookStage::Post)?;
+        let result = runner.run_stage(HookStage::Post);
+        save_hook_logs(db, runner.get_runs(), &clock.begin_run())?;
+        result?;
 
         // Get newly executed hooks and merge with state
         for hook_name in runner.get_once_executed() {
@@ -663,44 +715,258 @@ pub fn handle_hooks_post(
     // Always update state in database, even if no hooks ran
     // This marks the hooks directory as "checked" and prevents repeated warnings
     let hooks_dir = source_dir.hooks_dir();
-    state.update_with_collections(&hooks_dir, collections)?;
+    state.update_with_collections(&hooks_dir, collections, &clock.begin_run())?;
     persistence.save(&state)?;
 
     Ok(())
 }
 
+/// Run the script entries of the source directory for a stage
+///
+/// `run_before_*` scripts run in the pre stage and all other scripts in the
+/// post stage. Their once/onchange state is stored with the hook state.
+///
+/// # Errors
+///
+/// Returns an error if:
+/// - Reading the source state or a script fails
+/// - Database operations fail (loading or saving state)
+/// - Template engine creation fails
+/// - Script execution fails
+pub fn handle_source_scripts(
+    stage: HookStage,
+    paths: &ResolvedPaths,
+    config: &Config,
+    db: &RedbPersistentState,
+    clock: &StateClock,
+) -> Result<()> {
+    let source_dir = paths.source_dir.as_path();
+    let dotfiles_dir = config.dotfiles_dir(source_dir);
+    if !dotfiles_dir.exists() {
+        return Ok(());
+    }
+
+    let matcher = crate::load_ignore_matcher(source_dir, &dotfiles_dir, config).ok();
+    let source_state = SourceState::read_with_matcher(
+        AbsPath::new(dotfiles_dir).context("Invalid dotfiles directory")?,
+        matcher.as_ref(),
+    )
+    .context("Failed to read source state")?;
+    let collections = script_hooks(&source_state).context("Failed to load scripts")?;
+
+    let scripts = match stage {
+        HookStage::Pre => &collections.pre,
+        HookStage::Post => &collections.post,
+    };
+    if scripts.is_empty() {
+        tracing::debug!("No {} scripts in source directory, skipping", stage.name());
+        return Ok(());
+    }
+
+    let persistence = HookStatePersistence::new(db);
+    let mut hook_state = persistence.load()?;
+
+    let renderer = create_template_engine(paths, config)?;
+    let runner = HookRunner::builder(&collections, source_dir)
+        .template_renderer(renderer)
+        .persistent_state(
+            hook_state.once_executed.clone(),
+            hook_state.onchange_hashes.clone(),
+        )
+        .output_handler(print_hook_output)
+        .build();
+    let result = runner.run_stage(stage);
+    save_hook_logs(db, runner.get_runs(), &clock.begin_run())?;
+    result?;
+
+    for script_name in runner.get_once_executed() {
+        hook_state.mark_executed_once(script_name);
+    }
+    for (script_name, content_hash) in runner.get_onchange_hashes() {
+        hook_state.update_onchange_hash(script_name, content_hash);
+    }
+    for (script_name, rendered_content) in runner.get_onchange_rendered() {
+        hook_state.update_onchange_rendered(script_name, rendered_content);
+    }
+    persistence.save(&hook_state)?;
+
+    Ok(())
+}
+
+/// Show what `hooks run` would execute, without running anything
+///
+/// mode=onchange hooks whose content changed since they last ran are shown
+/// with a diff against the stored content.
+fn print_dry_run(
+    paths: &ResolvedPaths,
+    config: &Config,
+    db: &RedbPersistentState,
+    collections: &HookCollections,
+) -> Result<()> {
+    let source_dir = paths.source_dir.as_path();
+    let state = HookStatePersistence::new(db).load()?;
+    let renderer = create_template_engine(paths, config)?;
+
+    // Same runner as `hooks run`, which ignores once/onchange state
+    let runner = HookRunner::builder(collections, source_dir)
+        .template_renderer(renderer)
+        .build();
+
+    println!("\n{}", "Dry run: no hooks will be executed.".yellow());
+
+    for (stage, title) in [
+        (HookStage::Pre, "Pre hooks:"),
+        (HookStage::Post, "Post hooks:"),
+    ] {
+        let planned = runner.plan_stage(stage);
+        println!(
+            "\n{} ({} to run)",
+            title.bold(),
+            planned.iter().filter(|p| p.skip_reason.is_none()).count()
+        );
+
+        for planned in &planned {
+            let hook = planned.hook;
+            if let Some(reason) = &planned.skip_reason {
+                println!(
+                    "  • {} (order: {}) {}",
+                    hook.name.dimmed(),
+                    hook.order,
+                    format!("[skip: {reason}]").dimmed()
+                );
+                continue;
+            }
+
+            println!("  • {} (order: {})", hook.name.green(), hook.order);
+            if let (Some(current), Some(previous)) = (
+                &planned.onchange_content,
+                state.onchange_rendered.get(&hook.name),
+            ) && current != previous
+            {
+                println!("    {}", "content changed since last run:".dimmed());
+                print!(
+                    "{}",
+                    crate::cmd::diff::onchange_diff(hook, previous, current)
+                );
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// Run hooks log command
+///
+/// Without a name, lists every logged run. With a name, also prints the
+/// captured output of each run of that hook.
+///
+/// # Errors
+///
+/// Returns an error if the logs cannot be read from the database
+pub fn run_log(db: &RedbPersistentState, name: Option<&str>) -> Result<()> {
+    let logs =
+        guisu_engine::database::get_hook_logs(db, name).context("Failed to read hook logs")?;
+
+    if logs.is_empty() {
+        match name {
+            Some(name) => println!("No logged runs of hook '{name}'."),
+            None => println!("No hook runs logged."),
+        }
+        return Ok(());
+    }
+
+    for (id, log) in &logs {
+        let run = &log.run;
+        let status = match (&run.error, run.exit_code) {
+            (None, _) => "ok".green().to_string(),
+            (Some(_), Some(code)) => format!("exit {code}").red().to_string(),
+            (Some(_), None) => "failed".red().to_string(),
+        };
+        println!(
+            "{}  {}  {} ({})  {}  {}",
+            id.yellow(),
+            format_ran_at(&log.stamp).dimmed(),
+            run.name.bright_white(),
+            run.stage,
+            status,
+            format!("{}ms", run.duration_ms).dimmed()
+        );
+
+        if name.is_some() {
+            for line in run.stdout.lines() {
+                println!("    {line}");
+            }
+            for line in run.stderr.lines() {
+                println!("    {}", line.red());
+            }
+            if let Some(error) = &run.error {
+                println!("    {}", error.dimmed());
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// Format when the hook ran, in local time
+fn format_ran_at(stamp: &RunStamp) -> String {
+    chrono::DateTime::<chrono::Local>::from(stamp.time())
+        .format("%Y-%m-%d %H:%M:%S")
+        .to_string()
+}
+
+/// Print the captured output of a finished hook as one block
+///
+/// Hooks with the same order run in parallel. Holding the stdout lock for the
+/// whole block keeps their output from interleaving.
+fn print_hook_output(run: &HookRun) {
+    if run.stdout.is_empty() && run.stderr.is_empty() {
+        return;
+    }
+
+    let mut stdout = std::io::stdout().lock();
+    let _ = writeln!(stdout, "{} {}", "──".dimmed(), run.name.bold());
+    for line in run.stdout.lines() {
+        let _ = writeln!(stdout, "  {line}");
+    }
+    for line in run.stderr.lines() {
+        let _ = writeln!(stdout, "  {}", line.red());
+    }
+}
+
+/// Save the output of hook runs to the database
+///
+/// # Errors
+///
+/// Returns an error if a log cannot be saved
+fn save_hook_logs(db: &RedbPersistentState, runs: Vec<HookRun>, stamp: &RunStamp) -> Result<()> {
+    for run in runs {
+        let log = HookRunLog::new(run, stamp);
+        guisu_engine::database::save_hook_log(db, &log, HOOK_LOG_HISTORY)
+            .context("Failed to save hook log")?;
+    }
+    Ok(())
+}
+
 /// Create a template renderer closure for hooks
-fn create_template_engine(source_dir: &Path, config: &Config) -> Result<impl TemplateRenderer> {
-    use guisu_template::TemplateContext;
+///
+/// Hooks are rendered with the same context as file templates.
+fn create_template_engine(paths: &ResolvedPaths, config: &Config) -> Result<impl TemplateRenderer> {
     use std::sync::Arc;
 
     // Load age identities for encryption support in templates
     let identities = Arc::new(config.age_identities().unwrap_or_else(|_| Vec::new()));
 
     // Create template engine with bitwarden provider support
-    let engine = crate::create_template_engine(source_dir, &identities, config);
-
-    // Get destination directory (use home_dir as default if not configured)
-    let dst_dir = config
-        .general
-        .dst_dir
-        .clone()
-        .or_else(dirs::home_dir)
-        .unwrap_or_else(|| PathBuf::from("~"));
-
-    // Create template context with guisu info and all variables
-    let working_tree = guisu_engine::git::find_working_tree(source_dir)
-        .unwrap_or_else(|| source_dir.to_path_buf());
-    let dotfiles_dir = config.dotfiles_dir(source_dir);
-    let context = TemplateContext::new()
-        .with_guisu_info(
-            crate::path_to_string(&dotfiles_dir),
-            crate::path_to_string(&working_tree),
-            crate::path_to_string(&dst_dir),
-            crate::path_to_string(&config.general.root_entry),
-        )
-        .with_loaded_variables(source_dir, config)
-        .map_err(|e| anyhow::anyhow!("Failed to load variables: {e}"))?;
+    let engine = crate::create_template_engine(&paths.source_dir, &identities, config)?;
+
+    let context = template_context(
+        &paths.source_dir,
+        paths.dotfiles_dir.as_path(),
+        paths.dest_dir.as_path(),
+        config,
+    )
+    .context("Failed to load variables")?;
 
     // Return a closure that captures both engine and context
     // No Box needed - the closure implements TemplateRenderer directly
@@ -711,6 +977,20 @@ fn create_template_engine(source_dir: &Path, config: &Config) -> Result<impl Tem
     })
 }
 
+/// Render hook template content, as the executor does
+///
+/// # Errors
+///
+/// Returns an error if the template engine cannot be created or rendering fails
+pub(crate) fn render_hook_template(
+    paths: &ResolvedPaths,
+    content: &str,
+    config: &Config,
+) -> Result<String> {
+    let renderer = create_template_engine(paths, config)?;
+    Ok(renderer.render(content)?)
+}
+
 #[cfg(test)]
 mod tests {
     #![allow(clippy::unwrap_used, clippy::panic)]
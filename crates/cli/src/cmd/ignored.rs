@@ -3,17 +3,19 @@
 //! This module provides commands for viewing ignored files and patterns:
 //! - list: List files that are ignored on the current platform
 //! - show: Show ignore rules for the current platform
+//! - check: Explain whether a destination path is ignored
 
 use anyhow::{Context, Result};
-use guisu_config::IgnoreMatcher;
 use guisu_config::IgnoresConfig;
+use guisu_config::{IgnoreFile, IgnoreMatch, IgnoreMatcher, IgnorePattern, IgnoreScope};
+use guisu_core::path::AbsPath;
 use guisu_core::platform::CURRENT_PLATFORM;
 use guisu_engine::entry::SourceEntry;
 use guisu_engine::state::SourceState;
 use lscolors::{LsColors, Style};
 use owo_colors::OwoColorize;
 use std::io::IsTerminal;
-use std::path::Path;
+use std::path::{Path, PathBuf};
 
 use guisu_config::Config;
 
@@ -24,6 +26,10 @@ use guisu_config::Config;
 /// This includes entries ignored by:
 /// - Global patterns from global section
 /// - Platform-specific patterns from `<platform>` section
+/// - `.guisuignore` files and false conditions in `.guisu/conditions.toml`
+///
+/// An entry is ignored if its target path matches a `dst:` (or unprefixed)
+/// pattern, or its source path matches a `src:` pattern.
 ///
 /// # Errors
 ///
@@ -47,10 +53,9 @@ pub fn run_list(source_dir: &Path, config: &Config) -> Result<()> {
     )?;
     let source_abs = &paths.dotfiles_dir;
 
-    // Load ignore patterns from source_dir/.guisu/ignores.toml
+    // Load ignore patterns from source_dir/.guisu/ignores.toml and .guisuignore files
     // Use dotfiles_dir as the match root so patterns match relative to the dotfiles directory
-    let matcher = IgnoreMatcher::from_ignores_toml(source_dir)
-        .context("Failed to load ignore patterns from .guisu/ignores.toml")?;
+    let matcher = crate::load_ignore_matcher(source_dir, source_abs.as_path(), config)?;
 
     // Read ALL source files (without filtering by ignore patterns)
     let source_state =
@@ -61,36 +66,16 @@ pub fn run_list(source_dir: &Path, config: &Config) -> Result<()> {
 
     for entry in source_state.entries() {
         // Only process files
-        if let SourceEntry::File { .. } = entry {
+        if let SourceEntry::File { source_path, .. } = entry {
             // Get the target path (after processing .j2, .age, etc.)
             let target_path = entry.target_path();
 
-            // Check if target path or any parent directory is ignored
-            // Use relative path for matching (patterns are relative paths)
-            let mut is_ignored = false;
-
-            // Check the file itself (use relative path)
-            if matcher.is_ignored(target_path.as_path(), None) {
-                is_ignored = true;
-            }
-
-            // Check each parent directory (use relative paths)
-            if !is_ignored {
-                let mut current = target_path.as_path();
-                while let Some(parent) = current.parent() {
-                    // Stop at root (empty path)
-                    if parent.as_os_str().is_empty() {
-                        break;
-                    }
-
-                    if matcher.is_ignored(parent, None) {
-                        is_ignored = true;
-                        break;
-                    }
-
-                    current = parent;
-                }
-            }
+            // Check both namespaces (patterns are relative paths)
+            let is_ignored = is_ignored_or_parent(target_path.as_path(), |path, is_dir| {
+                matcher.is_ignored(path, Some(is_dir))
+            }) || is_ignored_or_parent(source_path.as_path(), |path, is_dir| {
+                matcher.is_source_ignored(path, Some(is_dir))
+            }) || matcher.is_excluded(source_path.as_path());
 
             if is_ignored {
                 ignored_files.push(target_path.to_string());
@@ -148,15 +133,30 @@ pub fn run_list(source_dir: &Path, config: &Config) -> Result<()> {
     Ok(())
 }
 
+/// Check whether a file or any of its parent directories is ignored
+fn is_ignored_or_parent(path: &Path, is_ignored: impl Fn(&Path, bool) -> bool) -> bool {
+    if is_ignored(path, false) {
+        return true;
+    }
+
+    // Stop at root (empty path)
+    path.ancestors()
+        .skip(1)
+        .take_while(|parent| !parent.as_os_str().is_empty())
+        .any(|parent| is_ignored(parent, true))
+}
+
 /// Run ignored show command
 ///
 /// Shows the ignore rules that apply to the current platform.
-/// This reads from .guisu/ignores.toml in the source directory.
+/// This reads from .guisu/ignores.toml in the source directory, followed by any
+/// `.guisuignore` files in the dotfiles tree. Each pattern is labelled with the
+/// namespace it matches: `dst` for target paths, `src` for source paths.
 ///
 /// # Errors
 ///
-/// Returns an error if loading .guisu/ignores.toml fails
-pub fn run_show(source_dir: &Path, _config: &Config, show_all: bool) -> Result<()> {
+/// Returns an error if loading .guisu/ignores.toml or a `.guisuignore` file fails
+pub fn run_show(source_dir: &Path, config: &Config, show_all: bool) -> Result<()> {
     let platform = CURRENT_PLATFORM.os;
 
     // Load ignore config from source_dir/.guisu/ignores.toml
@@ -176,8 +176,14 @@ pub fn run_show(source_dir: &Path, _config: &Config, show_all: bool) -> Result<(
         if patterns.is_empty() {
             println!("  {}", "(none)".dimmed());
         } else {
-            for pattern in patterns {
-                println!("  {pattern}");
+            for raw in patterns {
+                let IgnorePattern { scope, pattern } = IgnorePattern::parse(raw);
+                let label = format!("[{}]", scope.label());
+                let label = match scope {
+                    IgnoreScope::Source => label.bright_magenta().to_string(),
+                    IgnoreScope::Target => label.dimmed().to_string(),
+                };
+                println!("  {label} {pattern}");
             }
         }
     };
@@ -226,9 +232,198 @@ pub fn run_show(source_dir: &Path, _config: &Config, show_all: bool) -> Result<(
         display_section(platform, platform_patterns, true);
     }
 
+    // Per-directory ignore files apply on every platform
+    let ignore_files = IgnoreFile::find_all(&config.dotfiles_dir(source_dir))
+        .context("Failed to read .guisuignore files")?;
+    for file in ignore_files {
+        println!();
+        let path = config.general.root_entry.join(file.path());
+        display_section(&path.display().to_string(), &file.patterns, false);
+    }
+
+    Ok(())
+}
+
+/// Why a path is or is not ignored
+#[derive(Debug, PartialEq, Eq)]
+enum Verdict {
+    /// A pattern of `scope` matched `path`, the checked path or one of its parents
+    Ignored {
+        found: IgnoreMatch,
+        scope: IgnoreScope,
+        path: PathBuf,
+    },
+    /// The source lies in a directory excluded by a false condition or another profile
+    Excluded(PathBuf),
+    /// Not ignored, with the negation that re-includes the path if there is one
+    NotIgnored(Option<IgnoreMatch>),
+}
+
+/// Run ignored check command
+///
+/// Explains whether a destination path is ignored on the current platform and
+/// which pattern decides it. When the path is managed, the `src:` patterns,
+/// conditions and profiles of its source file are checked too, as
+/// `ignored list` does.
+///
+/// # Errors
+///
+/// Returns an error if the path is not under the destination directory, or if
+/// loading the ignore patterns or reading the source state fails
+pub fn run_check(
+    source_dir: &Path,
+    dotfiles_dir: &AbsPath,
+    dest_dir: &AbsPath,
+    config: &Config,
+    path: &Path,
+) -> Result<()> {
+    let targets =
+        guisu_config::Targets::load(source_dir).context("Failed to load .guisu/targets.toml")?;
+    let Some(target) = crate::build_filter_paths(&[path.to_path_buf()], dest_dir, &targets)?.pop()
+    else {
+        return Ok(());
+    };
+    let matcher = crate::load_ignore_matcher(source_dir, dotfiles_dir.as_path(), config)?;
+    let source_state =
+        SourceState::read(dotfiles_dir.to_owned()).context("Failed to read source state")?;
+
+    let source = source_state.get(&target).map(|entry| {
+        let is_dir = matches!(entry, SourceEntry::Directory { .. });
+        (entry.source_path().as_path(), is_dir)
+    });
+    let is_dir = source.map_or_else(
+        || targets.resolve(dest_dir, &target).as_path().is_dir(),
+        |(_, is_dir)| is_dir,
+    );
+
+    let display_path = format!("~/{target}");
+    match explain(&matcher, target.as_path(), is_dir, source)? {
+        Verdict::Ignored { found, scope, path } => {
+            println!(
+                "{} {} {}",
+                display_path.bright_white(),
+                "is ignored on".yellow(),
+                CURRENT_PLATFORM.os.bright_cyan()
+            );
+            println!("  {} {}", "Pattern:".dimmed(), found.pattern);
+            println!("  {} {}", "Defined in:".dimmed(), found.origin);
+            if scope == IgnoreScope::Source || path != target.as_path() {
+                println!(
+                    "  {} {}:{}",
+                    "Matched:".dimmed(),
+                    scope.label(),
+                    path.display()
+                );
+            }
+        }
+        Verdict::Excluded(dir) => {
+            println!(
+                "{} {} {}",
+                display_path.bright_white(),
+                "is ignored on".yellow(),
+                CURRENT_PLATFORM.os.bright_cyan()
+            );
+            let profile_dirs = guisu_config::Profiles::load(source_dir)
+                .context("Failed to load .guisu/profiles.toml")?
+                .excluded_dirs(config.general.profile.as_deref())
+                .map_err(|e| anyhow::anyhow!("{e}"))?;
+            if profile_dirs.contains(&dir) {
+                println!(
+                    "  {} {} belongs to another profile in .guisu/profiles.toml",
+                    "Profile:".dimmed(),
+                    dir.display()
+                );
+            } else {
+                println!(
+                    "  {} {} is false in .guisu/conditions.toml",
+                    "Condition:".dimmed(),
+                    dir.display()
+                );
+            }
+        }
+        Verdict::NotIgnored(found) => {
+            println!(
+                "{} {}",
+                display_path.bright_white(),
+                "is not ignored".green()
+            );
+            if let Some(found) = found {
+                println!(
+                    "  {} {} {}",
+                    "Re-included by:".dimmed(),
+                    found.pattern,
+                    format!("({})", found.origin).dimmed()
+                );
+            }
+        }
+    }
+
     Ok(())
 }
 
+/// Decide whether a target path is ignored, in the order `ignored list` checks
+///
+/// `source` is the managed source path and whether it is a directory.
+fn explain(
+    matcher: &IgnoreMatcher,
+    target: &Path,
+    is_dir: bool,
+    source: Option<(&Path, bool)>,
+) -> Result<Verdict> {
+    if let Some((found, path)) = first_ignored(target, is_dir, |path, is_dir| {
+        matcher.explain(path, is_dir, IgnoreScope::Target)
+    })? {
+        return Ok(Verdict::Ignored {
+            found,
+            scope: IgnoreScope::Target,
+            path,
+        });
+    }
+
+    if let Some((source_path, source_is_dir)) = source {
+        if let Some((found, path)) = first_ignored(source_path, source_is_dir, |path, is_dir| {
+            matcher.explain(path, is_dir, IgnoreScope::Source)
+        })? {
+            return Ok(Verdict::Ignored {
+                found,
+                scope: IgnoreScope::Source,
+                path,
+            });
+        }
+        if let Some(dir) = matcher.excluding_dir(source_path) {
+            return Ok(Verdict::Excluded(dir.to_path_buf()));
+        }
+    }
+
+    let reincluded = matcher
+        .explain(target, is_dir, IgnoreScope::Target)?
+        .filter(|found| !found.ignored);
+    Ok(Verdict::NotIgnored(reincluded))
+}
+
+/// The first pattern ignoring a path or one of its parent directories, with
+/// the path it matched
+fn first_ignored(
+    path: &Path,
+    is_dir: bool,
+    explain: impl Fn(&Path, bool) -> guisu_config::Result<Option<IgnoreMatch>>,
+) -> Result<Option<(IgnoreMatch, PathBuf)>> {
+    let parents = path
+        .ancestors()
+        .skip(1)
+        .take_while(|parent| !parent.as_os_str().is_empty())
+        .map(|parent| (parent, true));
+
+    for (candidate, candidate_is_dir) in std::iter::once((path, is_dir)).chain(parents) {
+        if let Some(found) = explain(candidate, candidate_is_dir)?
+            && found.ignored
+        {
+            return Ok(Some((found, candidate.to_path_buf())));
+        }
+    }
+    Ok(None)
+}
+
 #[cfg(test)]
 mod tests {
     #![allow(clippy::unwrap_used, clippy::panic)]
@@ -612,4 +807,110 @@ windows = ["Thumbs.db"]
             "Should work with custom root_entry: {result:?}"
         );
     }
+
+    #[test]
+    fn test_is_ignored_or_parent_checks_both_namespaces() {
+        let temp = TempDir::new().unwrap();
+        let guisu_dir = temp.path().guisu_dir();
+        fs::create_dir_all(&guisu_dir).unwrap();
+        fs::write(
+            guisu_dir.join("ignores.toml"),
+            r#"global = ["cache/", "src:private_*"]"#,
+        )
+        .unwrap();
+        let matcher = guisu_config::IgnoreMatcher::from_ignores_toml(temp.path()).unwrap();
+
+        let target = |path: &Path, is_dir| matcher.is_ignored(path, Some(is_dir));
+        let source = |path: &Path, is_dir| matcher.is_source_ignored(path, Some(is_dir));
+
+        assert!(is_ignored_or_parent(Path::new("cache/data.txt"), target));
+        assert!(!is_ignored_or_parent(Path::new("cache/data.txt"), source));
+
+        assert!(is_ignored_or_parent(
+            Path::new("private_dot_ssh/config"),
+            source
+        ));
+        assert!(!is_ignored_or_parent(
+            Path::new("private_dot_ssh/config"),
+            target
+        ));
+        assert!(!is_ignored_or_parent(Path::new(".ssh/config"), source));
+    }
+
+    #[test]
+    fn test_explain_verdicts() {
+        let temp = TempDir::new().unwrap();
+        let guisu_dir = temp.path().guisu_dir();
+        fs::create_dir_all(&guisu_dir).unwrap();
+        fs::write(
+            guisu_dir.join("ignores.toml"),
+            r#"global = ["cache/", "*.log", "!keep.log", "src:*.orig"]"#,
+        )
+        .unwrap();
+        let matcher = guisu_config::IgnoreMatcher::from_ignores_toml(temp.path())
+            .unwrap()
+            .with_excluded_dirs(vec![PathBuf::from("work")]);
+        let pattern = |verdict: Verdict| match verdict {
+            Verdict::Ignored { found, path, .. } => (found.pattern, path),
+            other => panic!("Expected an ignored verdict, got {other:?}"),
+        };
+
+        // Parent directories are checked after the path itself
+        let verdict = explain(&matcher, Path::new("cache/data/x"), false, None).unwrap();
+        assert_eq!(
+            pattern(verdict),
+            ("cache/".to_string(), PathBuf::from("cache/data/x"))
+        );
+
+        // Source patterns only apply to managed paths
+        let source = Some((Path::new("vimrc.orig"), false));
+        let verdict = explain(&matcher, Path::new("vimrc"), false, source).unwrap();
+        assert_eq!(
+            pattern(verdict),
+            ("src:*.orig".to_string(), PathBuf::from("vimrc.orig"))
+        );
+        assert_eq!(
+            explain(&matcher, Path::new("vimrc"), false, None).unwrap(),
+            Verdict::NotIgnored(None)
+        );
+
+        let source = Some((Path::new("work/gitconfig"), false));
+        assert_eq!(
+            explain(&matcher, Path::new("work/gitconfig"), false, source).unwrap(),
+            Verdict::Excluded(PathBuf::from("work"))
+        );
+
+        let Verdict::NotIgnored(Some(found)) =
+            explain(&matcher, Path::new("keep.log"), false, None).unwrap()
+        else {
+            panic!("Expected a re-included verdict");
+        };
+        assert_eq!(found.pattern, "!keep.log");
+    }
+
+    #[test]
+    fn test_run_check() {
+        let (temp, config) = setup_test_env();
+        let dest = AbsPath::new(fs::canonicalize(temp.path()).unwrap()).unwrap();
+        let dotfiles = AbsPath::new(dest.as_path().join("home")).unwrap();
+
+        run_check(
+            temp.path(),
+            &dotfiles,
+            &dest,
+            &config,
+            &dest.as_path().join("test.log"),
+        )
+        .unwrap();
+        assert!(
+            run_check(
+                temp.path(),
+                &dotfiles,
+                &dest,
+                &config,
+                Path::new("/elsewhere")
+            )
+            .is_err()
+        );
+    }
 }
@@ -0,0 +1,1027 @@
+//! Import command implementation
+//!
+//! Bring an existing dotfiles tree, a directory or a tarball of one, into the
+//! source directory. chezmoi source directories are recognized and their
+//! naming (`dot_`, `private_`, `encrypted_`, `.tmpl`, ...) is translated into
+//! guisu attributes.
+
+use anyhow::{Context, Result};
+use clap::Args;
+use guisu_engine::attr::FileAttributes;
+use guisu_engine::state::{Metadata, SourceState};
+use owo_colors::OwoColorize;
+use std::fs::{self, File};
+use std::io::{BufReader, Read, Seek};
+use std::path::{Component, Path, PathBuf};
+
+use crate::command::Command;
+use crate::common::RuntimeContext;
+
+/// Note for templates carried over from chezmoi
+const GO_TEMPLATE_NOTE: &str = "uses Go template syntax; rewrite it for Jinja";
+
+/// Prefixes of chezmoi source names that guisu does not use
+const CHEZMOI_MARKERS: &[&str] = &[
+    ".chezmoi",
+    "dot_",
+    "private_",
+    "executable_",
+    "encrypted_",
+    "readonly_",
+    "create_",
+    "modify_",
+    "empty_",
+];
+
+/// Import an existing dotfiles tree into the source directory
+#[derive(Debug, Clone, Args)]
+pub struct ImportCommand {
+    /// Directory or tarball (.tar, .tar.gz) to import
+    pub path: PathBuf,
+
+    /// Only print how each entry would be imported
+    #[arg(short = 'n', long)]
+    pub dry_run: bool,
+
+    /// Replace entries the source directory already manages
+    #[arg(short, long)]
+    pub force: bool,
+
+    /// Strip this many leading components from the paths in a tarball
+    #[arg(long, value_name = "N", default_value_t = 0)]
+    pub strip_components: usize,
+}
+
+impl Command for ImportCommand {
+    type Output = ();
+    fn execute(&self, context: &RuntimeContext) -> crate::error::Result<()> {
+        let metadata = fs::metadata(&self.path)
+            .with_context(|| format!("Failed to read {}", self.path.display()))?;
+
+        // Tarballs are unpacked and then imported like a directory
+        let extracted;
+        let root = if metadata.is_dir() {
+            if self.strip_components > 0 {
+                return Err(anyhow::anyhow!("--strip-components only applies to tarballs").into());
+            }
+            self.path.clone()
+        } else {
+            extracted = tempfile::TempDir::new().context("Failed to create temporary directory")?;
+            extract_tarball(&self.path, self.strip_components, extracted.path())?;
+            extracted.path().to_path_buf()
+        };
+
+        let (root, chezmoi) = import_root(&root)?;
+        if chezmoi {
+            println!("{}", "Importing a chezmoi source directory".bold());
+        }
+
+        let dotfiles_dir = context.dotfiles_dir().as_path();
+        let source_state = SourceState::read(context.dotfiles_dir().to_owned())
+            .context("Failed to read source state")?;
+        let mut steps = plan_import(&root, chezmoi)?;
+        resolve_conflicts(&mut steps, &source_state, dotfiles_dir, self.force);
+
+        print_steps(&steps);
+        let imported = steps
+            .iter()
+            .filter(|step| matches!(step.action, Action::File { .. } | Action::Symlink { .. }))
+            .count();
+        let skipped = steps
+            .iter()
+            .filter(|step| matches!(step.action, Action::Skip(_)))
+            .count();
+
+        if self.dry_run {
+            println!("{}", "Dry run: nothing was imported.".dimmed());
+            return Ok(());
+        }
+
+        run_steps(&steps, &root, dotfiles_dir, context.source_dir())?;
+        println!(
+            "{} {} {}{}",
+            "Imported".bright_green().bold(),
+            imported,
+            if imported == 1 { "entry" } else { "entries" },
+            if skipped > 0 {
+                format!(", skipped {skipped}")
+            } else {
+                String::new()
+            }
+        );
+        Ok(())
+    }
+}
+
+/// What happens to one entry of the imported tree
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Action {
+    /// Create a directory in the dotfiles directory
+    Dir { to: PathBuf, mode: Option<u32> },
+    /// Copy a file into the dotfiles directory, with `mode` if given and its
+    /// own permissions otherwise
+    File {
+        to: PathBuf,
+        /// Target path of a managed file, `None` for scripts and ignore files
+        target: Option<PathBuf>,
+        mode: Option<u32>,
+        /// Apply creates the file only if it does not exist yet
+        create_once: bool,
+    },
+    /// Write a `symlink_` file holding the link target
+    Symlink {
+        to: PathBuf,
+        target: PathBuf,
+        link: String,
+    },
+    /// Leave the entry out, for the given reason
+    Skip(String),
+}
+
+/// One entry of the imported tree and what happens to it
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Step {
+    /// Path in the imported tree
+    from: PathBuf,
+    action: Action,
+    /// Source file of an existing entry to remove first, relative to the
+    /// dotfiles directory
+    replaces: Option<PathBuf>,
+    /// Something to check by hand after importing
+    review: Option<&'static str>,
+}
+
+impl Step {
+    fn new(from: PathBuf, action: Action) -> Self {
+        Self {
+            from,
+            action,
+            replaces: None,
+            review: None,
+        }
+    }
+
+    fn skip(from: PathBuf, reason: impl Into<String>) -> Self {
+        Self::new(from, Action::Skip(reason.into()))
+    }
+}
+
+/// Unpack a tarball, gzip-compressed or not, into `into`
+///
+/// The first `strip` components of every path are dropped; entries with no
+/// path left are skipped.
+///
+/// # Errors
+///
+/// Returns an error if the tarball cannot be read, or holds a path leaving
+/// `into`, a path through a symlink it created, or a hard link
+fn extract_tarball(path: &Path, strip: usize, into: &Path) -> Result<()> {
+    let mut file =
+        File::open(path).with_context(|| format!("Failed to open {}", path.display()))?;
+    let mut magic = [0u8; 2];
+    let gzipped = file.read_exact(&mut magic).is_ok() && magic == [0x1f, 0x8b];
+    file.rewind()?;
+    let reader: Box<dyn Read> = if gzipped {
+        Box::new(flate2::read::GzDecoder::new(BufReader::new(file)))
+    } else {
+        Box::new(BufReader::new(file))
+    };
+
+    let mut archive = tar::Archive::new(reader);
+    let entries = archive
+        .entries()
+        .with_context(|| format!("Failed to read tarball {}", path.display()))?;
+    for entry in entries {
+        let mut entry =
+            entry.with_context(|| format!("Failed to read tarball {}", path.display()))?;
+        let entry_path = entry.path()?.into_owned();
+        let stripped: PathBuf = entry_path.components().skip(strip).collect();
+        if stripped.as_os_str().is_empty() {
+            continue;
+        }
+        if !stripped
+            .components()
+            .all(|component| matches!(component, Component::Normal(_)))
+        {
+            anyhow::bail!("Tarball path leaves the import: {}", entry_path.display());
+        }
+        if entry.header().entry_type().is_hard_link() {
+            anyhow::bail!("Hard links cannot be imported: {}", entry_path.display());
+        }
+
+        // An earlier entry may be a symlink out of `into`: writing through it,
+        // or replacing it, would write wherever it points
+        let mut prefix = into.to_path_buf();
+        for component in stripped.components() {
+            prefix.push(component);
+            if fs::symlink_metadata(&prefix).is_ok_and(|meta| meta.file_type().is_symlink()) {
+                anyhow::bail!(
+                    "Tarball path goes through a symlink: {}",
+                    entry_path.display()
+                );
+            }
+        }
+
+        let dest = into.join(&stripped);
+        if let Some(parent) = dest.parent() {
+            fs::create_dir_all(parent)
+                .with_context(|| format!("Failed to create directory: {}", parent.display()))?;
+        }
+        entry
+            .unpack(&dest)
+            .with_context(|| format!("Failed to unpack {}", entry_path.display()))?;
+    }
+    Ok(())
+}
+
+/// Directory to import from, and whether it is a chezmoi source directory
+///
+/// A chezmoi `.chezmoiroot` file moves the root to the directory it names.
+fn import_root(root: &Path) -> Result<(PathBuf, bool)> {
+    let names: Vec<String> = fs::read_dir(root)
+        .with_context(|| format!("Failed to read directory: {}", root.display()))?
+        .filter_map(|entry| entry.ok()?.file_name().into_string().ok())
+        .collect();
+    let chezmoi = names.iter().any(|name| {
+        CHEZMOI_MARKERS
+            .iter()
+            .any(|marker| name.starts_with(marker))
+    });
+
+    let chezmoi_root = root.join(".chezmoiroot");
+    if chezmoi_root.is_file() {
+        let subdir = fs::read_to_string(&chezmoi_root)
+            .with_context(|| format!("Failed to read {}", chezmoi_root.display()))?;
+        return Ok((root.join(subdir.trim()), true));
+    }
+    Ok((root.to_path_buf(), chezmoi))
+}
+
+/// Plan how every entry under `root` is imported, in path order
+fn plan_import(root: &Path, chezmoi: bool) -> Result<Vec<Step>> {
+    let mut steps = Vec::new();
+    plan_dir(
+        root,
+        &PlanDir {
+            from: PathBuf::new(),
+            to: PathBuf::new(),
+            target: PathBuf::new(),
+        },
+        chezmoi,
+        &mut steps,
+    )?;
+    Ok(steps)
+}
+
+/// Where a directory of the imported tree lands
+struct PlanDir {
+    /// Path in the imported tree
+    from: PathBuf,
+    /// Path in the dotfiles directory
+    to: PathBuf,
+    /// Path in the destination
+    target: PathBuf,
+}
+
+fn plan_dir(root: &Path, dir: &PlanDir, chezmoi: bool, steps: &mut Vec<Step>) -> Result<()> {
+    let path = root.join(&dir.from);
+    let mut children: Vec<_> = fs::read_dir(&path)
+        .with_context(|| format!("Failed to read directory: {}", path.display()))?
+        .collect::<std::io::Result<_>>()
+        .with_context(|| format!("Failed to read directory: {}", path.display()))?;
+    children.sort_by_key(fs::DirEntry::file_name);
+
+    for child in children {
+        let from = dir.from.join(child.file_name());
+        let Ok(name) = child.file_name().into_string() else {
+            steps.push(Step::skip(from, "name is not valid UTF-8"));
+            continue;
+        };
+        if name == ".git" {
+            continue;
+        }
+        let file_type = child
+            .file_type()
+            .with_context(|| format!("Failed to read {}", child.path().display()))?;
+
+        if chezmoi {
+            plan_chezmoi_entry(root, dir, &name, file_type, steps)?;
+        } else {
+            plan_plain_entry(root, dir, &name, file_type, steps)?;
+        }
+    }
+    Ok(())
+}
+
+/// Plan an entry of a plain dotfiles tree, imported under its own name
+fn plan_plain_entry(
+    root: &Path,
+    dir: &PlanDir,
+    name: &str,
+    file_type: fs::FileType,
+    steps: &mut Vec<Step>,
+) -> Result<()> {
+    let from = dir.from.join(name);
+    let unchanged = if file_type.is_dir() {
+        FileAttributes::parse_directory_name(name).is_ok_and(|(_, target)| target == name)
+    } else {
+        FileAttributes::parse_from_source(name, None).is_ok_and(|(_, target)| target == name)
+    };
+    if !unchanged {
+        steps.push(Step::skip(
+            from,
+            "name would be read as guisu attributes; add it with guisu add",
+        ));
+        return Ok(());
+    }
+
+    if file_type.is_dir() {
+        let sub = PlanDir {
+            from: from.clone(),
+            to: dir.to.join(name),
+            target: dir.target.join(name),
+        };
+        steps.push(Step::new(
+            from,
+            Action::Dir {
+                to: sub.to.clone(),
+                mode: None,
+            },
+        ));
+        return plan_dir(root, &sub, false, steps);
+    }
+
+    if file_type.is_symlink() {
+        let link = guisu_engine::system::read_symlink(&root.join(&from))
+            .with_context(|| format!("Failed to read symlink: {}", from.display()))?;
+        let Some(link) = link.to_str() else {
+            steps.push(Step::skip(from, "symlink target is not valid UTF-8"));
+            return Ok(());
+        };
+        steps.push(Step::new(
+            from,
+            Action::Symlink {
+                to: dir.to.join(format!("symlink_{name}")),
+                target: dir.target.join(name),
+                link: link.to_string(),
+            },
+        ));
+        return Ok(());
+    }
+
+    steps.push(Step::new(
+        from,
+        Action::File {
+            to: dir.to.join(name),
+            target: Some(dir.target.join(name)),
+            mode: None,
+            create_once: false,
+        },
+    ));
+    Ok(())
+}
+
+/// Plan an entry of a chezmoi source directory
+fn plan_chezmoi_entry(
+    root: &Path,
+    dir: &PlanDir,
+    name: &str,
+    file_type: fs::FileType,
+    steps: &mut Vec<Step>,
+) -> Result<()> {
+    let from = dir.from.join(name);
+
+    if name.starts_with(".chezmoi") {
+        match name {
+            // Scripts that do not belong to a directory
+            ".chezmoiscripts" if file_type.is_dir() => {
+                let sub = PlanDir {
+                    from,
+                    to: dir.to.clone(),
+                    target: dir.target.clone(),
+                };
+                return plan_dir(root, &sub, true, steps);
+            }
+            ".chezmoiroot" => {}
+            ".chezmoiignore" => {
+                let content = fs::read_to_string(root.join(&from))
+                    .with_context(|| format!("Failed to read {}", from.display()))?;
+                if content.contains("{{") {
+                    steps.push(Step::skip(
+                        from,
+                        "templated ignore file; write a .guisuignore by hand",
+                    ));
+                } else {
+                    steps.push(Step::new(
+                        from,
+                        Action::File {
+                            to: dir.to.join(guisu_config::IGNORE_FILE_NAME),
+                            target: None,
+                            mode: None,
+                            create_once: false,
+                        },
+                    ));
+                }
+            }
+            _ => steps.push(Step::skip(from, "chezmoi configuration; not imported")),
+        }
+        return Ok(());
+    }
+    if name.starts_with('.') {
+        steps.push(Step::skip(from, "ignored by chezmoi"));
+        return Ok(());
+    }
+
+    if file_type.is_dir() {
+        match translate_chezmoi_dir(name) {
+            Ok(translated) => {
+                let sub = PlanDir {
+                    from: from.clone(),
+                    to: dir.to.join(&translated.name),
+                    target: dir.target.join(&translated.target),
+                };
+                steps.push(Step::new(
+                    from,
+                    Action::Dir {
+                        to: sub.to.clone(),
+                        mode: translated.mode,
+                    },
+                ));
+                plan_dir(root, &sub, true, steps)?;
+            }
+            Err(reason) => steps.push(Step::skip(from, reason)),
+        }
+        return Ok(());
+    }
+
+    if file_type.is_symlink() {
+        steps.push(Step::skip(
+            from,
+            "symlinks in a chezmoi source directory are not supported",
+        ));
+        return Ok(());
+    }
+
+    match translate_chezmoi_file(name) {
+        Ok(translated) => {
+            let mut step = Step::new(
+                from,
+                Action::File {
+                    to: dir.to.join(&translated.name),
+                    target: (!translated.script).then(|| dir.target.join(&translated.target)),
+                    mode: Some(translated.mode),
+                    create_once: translated.create_once,
+                },
+            );
+            step.review = translated.template.then_some(GO_TEMPLATE_NOTE);
+            steps.push(step);
+        }
+        Err(reason) => steps.push(Step::skip(from, reason)),
+    }
+    Ok(())
+}
+
+/// A chezmoi source name translated into guisu naming
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Translated {
+    /// Source name in guisu
+    name: String,
+    /// Target name
+    target: String,
+    /// Permissions of a file, or of a private directory
+    mode: Option<u32>,
+}
+
+/// A chezmoi source file name translated into guisu naming
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct TranslatedFile {
+    /// Source name in guisu
+    name: String,
+    /// Target name
+    target: String,
+    /// Permissions of the file
+    mode: u32,
+    /// Is the file a template (in Go template syntax)?
+    template: bool,
+    /// Is the file a `run_` script?
+    script: bool,
+    /// Is the file created only if missing (`create_`)?
+    create_once: bool,
+}
+
+/// Strip `prefix` from `rest`, returning whether it was there
+fn take_prefix(rest: &mut &str, prefix: &str) -> bool {
+    match rest.strip_prefix(prefix) {
+        Some(stripped) => {
+            *rest = stripped;
+            true
+        }
+        None => false,
+    }
+}
+
+/// Target name of a chezmoi name once its attribute prefixes are removed
+///
+/// Returns the name and whether it was marked literal, in which case no
+/// suffixes apply.
+fn chezmoi_target_name(rest: &str) -> (String, bool) {
+    if let Some(literal) = rest.strip_prefix("literal_") {
+        (literal.to_string(), true)
+    } else if let Some(dotted) = rest.strip_prefix("dot_") {
+        (format!(".{dotted}"), false)
+    } else {
+        (rest.to_string(), false)
+    }
+}
+
+/// Translate a chezmoi source directory name
+///
+/// `exact_` carries over and `private_` becomes mode `0700`. `readonly_` is
+/// dropped, since files could no longer be written into the directory.
+///
+/// # Errors
+///
+/// Returns the reason if the directory has no guisu equivalent
+fn translate_chezmoi_dir(name: &str) -> std::result::Result<Translated, String> {
+    let mut rest = name;
+    if rest.starts_with("remove_") {
+        return Err("remove_ entries have no guisu equivalent".to_string());
+    }
+    if rest.starts_with("external_") {
+        return Err(
+            "external_ directories have no guisu equivalent; use .guisu/externals.toml".to_string(),
+        );
+    }
+    let exact = take_prefix(&mut rest, "exact_");
+    let private = take_prefix(&mut rest, "private_");
+    take_prefix(&mut rest, "readonly_");
+    let (target, _) = chezmoi_target_name(rest);
+
+    let name = if exact {
+        format!("exact_{target}")
+    } else {
+        target.clone()
+    };
+    match FileAttributes::parse_directory_name(&name) {
+        Ok((attrs, parsed)) if parsed == target && attrs.is_exact() == exact => Ok(Translated {
+            name,
+            target,
+            mode: private.then_some(0o700),
+        }),
+        _ => Err(format!("{target} cannot be named unambiguously in guisu")),
+    }
+}
+
+/// Translate a chezmoi source file name
+///
+/// `encrypted_` files keep their `.age` suffix, `.tmpl` becomes `.j2`, and
+/// `private_`, `readonly_`, and `executable_` become the file's mode, with a
+/// `mode_` prefix when git would not keep it. `create_` files are imported
+/// as regular files to be marked create-once, and `run_` scripts and
+/// `symlink_` files keep their prefixes.
+///
+/// # Errors
+///
+/// Returns the reason if the file has no guisu equivalent
+fn translate_chezmoi_file(name: &str) -> std::result::Result<TranslatedFile, String> {
+    let mut rest = name;
+    if rest.starts_with("modify_") {
+        return Err("modify_ scripts have no guisu equivalent".to_string());
+    }
+    if rest.starts_with("remove_") {
+        return Err("remove_ entries have no guisu equivalent".to_string());
+    }
+
+    let mut prefix = String::new();
+    let mut create_once = false;
+    let (mut encrypted, mut private, mut readonly, mut executable) = (false, false, false, false);
+    if take_prefix(&mut rest, "run_") {
+        prefix.push_str("run_");
+        for modifier in ["once_", "onchange_", "before_", "after_"] {
+            if take_prefix(&mut rest, modifier) {
+                prefix.push_str(modifier);
+            }
+        }
+    } else if take_prefix(&mut rest, "symlink_") {
+        prefix.push_str("symlink_");
+    } else {
+        create_once = take_prefix(&mut rest, "create_");
+        encrypted = take_prefix(&mut rest, "encrypted_");
+        private = take_prefix(&mut rest, "private_");
+        readonly = take_prefix(&mut rest, "readonly_");
+        take_prefix(&mut rest, "empty_");
+        executable = take_prefix(&mut rest, "executable_");
+    }
+    let script = prefix.starts_with("run_");
+
+    let (mut target, literal) = chezmoi_target_name(rest);
+    let mut template = false;
+    if let Some(stripped) = target.strip_suffix(".literal") {
+        target = stripped.to_string();
+    } else if !literal {
+        if encrypted {
+            if Path::new(&target)
+                .extension()
+                .is_some_and(|ext| ext == "asc")
+            {
+                return Err(
+                    "gpg-encrypted files are not supported; re-encrypt them with age".to_string(),
+                );
+            }
+            if let Some(stripped) = target.strip_suffix(".age") {
+                target = stripped.to_string();
+            }
+        }
+        if let Some(stripped) = target.strip_suffix(".tmpl") {
+            target = stripped.to_string();
+            template = true;
+        }
+    }
+
+    let mut mode = if executable || script { 0o755 } else { 0o644 };
+    if private {
+        mode &= 0o700;
+    }
+    if readonly {
+        mode &= !0o222;
+    }
+    // Git keeps only the executable bit, so other modes go into the name
+    if private || readonly {
+        prefix = format!("mode_{mode:04o}_{prefix}");
+    }
+
+    let mut guisu_name = format!("{prefix}{target}");
+    if template {
+        guisu_name.push_str(".j2");
+    }
+    if encrypted {
+        guisu_name.push_str(".age");
+    }
+
+    let parsed = FileAttributes::parse_from_source(&guisu_name, Some(mode));
+    let script_name = parsed.as_ref().is_ok_and(|(attrs, parsed)| {
+        parsed == &target && attrs.is_template() == template && attrs.is_encrypted() == encrypted
+    });
+    if !script_name {
+        return Err(format!("{target} cannot be named unambiguously in guisu"));
+    }
+
+    Ok(TranslatedFile {
+        name: guisu_name,
+        target,
+        mode,
+        template,
+        script,
+        create_once,
+    })
+}
+
+/// Skip entries the source directory already has, or mark them for
+/// replacement with `force`
+fn resolve_conflicts(
+    steps: &mut [Step],
+    source_state: &SourceState,
+    dotfiles_dir: &Path,
+    force: bool,
+) {
+    for step in steps {
+        let (to, target) = match &step.action {
+            Action::File { to, target, .. } => (to, target.as_ref()),
+            Action::Symlink { to, target, .. } => (to, Some(target)),
+            Action::Dir { .. } | Action::Skip(_) => continue,
+        };
+        let existing = target
+            .and_then(|target| guisu_core::path::RelPath::new(target.clone()).ok())
+            .and_then(|target| source_state.get(&target))
+            .map(|entry| entry.source_path().as_path().to_path_buf())
+            .or_else(|| dotfiles_dir.join(to).exists().then(|| to.clone()));
+
+        match existing {
+            Some(_) if !force => {
+                step.action = Action::Skip(
+                    "already in the source directory; use --force to replace it".to_string(),
+                );
+            }
+            Some(existing) if &existing != to => step.replaces = Some(existing),
+            _ => {}
+        }
+    }
+}
+
+/// Print how each entry is imported
+fn print_steps(steps: &[Step]) {
+    for step in steps {
+        let from = step.from.display();
+        match &step.action {
+            Action::Dir { to, mode } => {
+                let mode = mode
+                    .map(|mode| format!(" (mode {mode:04o})"))
+                    .unwrap_or_default();
+                println!(
+                    "  {from}/ {} {}/{}",
+                    "→".dimmed(),
+                    to.display().bright_white(),
+                    mode.dimmed()
+                );
+            }
+            Action::File { to, .. } | Action::Symlink { to, .. } => {
+                let replaces = step
+                    .replaces
+                    .as_ref()
+                    .map(|old| format!(" (replaces {})", old.display()))
+                    .unwrap_or_default();
+                println!(
+                    "  {from} {} {}{}",
+                    "→".dimmed(),
+                    to.display().bright_white(),
+                    replaces.dimmed()
+                );
+                if let Some(review) = step.review {
+                    println!("    {} {review}", "review:".yellow());
+                }
+            }
+            Action::Skip(reason) => println!("  {from} {} {reason}", "skipped:".yellow()),
+        }
+    }
+}
+
+/// Copy the planned entries into the dotfiles directory
+///
+/// Files marked create-once are recorded in the metadata of `source_dir`.
+fn run_steps(steps: &[Step], root: &Path, dotfiles_dir: &Path, source_dir: &Path) -> Result<()> {
+    let mut metadata = Metadata::load(source_dir).context("Failed to load metadata")?;
+    let mut metadata_changed = false;
+
+    for step in steps {
+        if let Some(old) = &step.replaces {
+            let old = dotfiles_dir.join(old);
+            fs::remove_file(&old).with_context(|| format!("Failed to remove {}", old.display()))?;
+        }
+
+        match &step.action {
+            Action::Dir { to, mode } => {
+                let dir = dotfiles_dir.join(to);
+                fs::create_dir_all(&dir)
+                    .with_context(|| format!("Failed to create directory: {}", dir.display()))?;
+                if let Some(mode) = mode {
+                    guisu_engine::system::set_permissions(&dir, *mode)
+                        .with_context(|| format!("Failed to set permissions: {}", dir.display()))?;
+                }
+            }
+            Action::File {
+                to,
+                target,
+                mode,
+                create_once,
+            } => {
+                let dest = dotfiles_dir.join(to);
+                create_parent(&dest)?;
+                fs::copy(root.join(&step.from), &dest)
+                    .with_context(|| format!("Failed to copy {}", step.from.display()))?;
+                if let Some(mode) = mode {
+                    guisu_engine::system::set_permissions(&dest, *mode).with_context(|| {
+                        format!("Failed to set permissions: {}", dest.display())
+                    })?;
+                }
+                if let (true, Some(target)) = (create_once, target) {
+                    metadata.add_create_once(target.to_string_lossy().replace('\\', "/"));
+                    metadata_changed = true;
+                }
+            }
+            Action::Symlink { to, link, .. } => {
+                let dest = dotfiles_dir.join(to);
+                create_parent(&dest)?;
+                fs::write(&dest, format!("{link}\n"))
+                    .with_context(|| format!("Failed to write {}", dest.display()))?;
+            }
+            Action::Skip(_) => {}
+        }
+    }
+
+    if metadata_changed {
+        metadata
+            .save(source_dir)
+            .context("Failed to save metadata")?;
+    }
+    Ok(())
+}
+
+fn create_parent(path: &Path) -> Result<()> {
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)
+            .with_context(|| format!("Failed to create directory: {}", parent.display()))?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    #![allow(clippy::unwrap_used)]
+    use super::*;
+    use tempfile::TempDir;
+
+    fn file(name: &str, target: &str, mode: u32) -> TranslatedFile {
+        TranslatedFile {
+            name: name.to_string(),
+            target: target.to_string(),
+            mode,
+            template: false,
+            script: false,
+            create_once: false,
+        }
+    }
+
+    #[test]
+    fn test_translate_chezmoi_file() {
+        assert_eq!(
+            translate_chezmoi_file("dot_bashrc").unwrap(),
+            file(".bashrc", ".bashrc", 0o644)
+        );
+        assert_eq!(
+            translate_chezmoi_file("executable_dot_local_bin").unwrap(),
+            file(".local_bin", ".local_bin", 0o755)
+        );
+        assert_eq!(
+            translate_chezmoi_file("encrypted_private_dot_netrc.tmpl.age").unwrap(),
+            TranslatedFile {
+                template: true,
+                ..file("mode_0600_.netrc.j2.age", ".netrc", 0o600)
+            }
+        );
+        assert_eq!(
+            translate_chezmoi_file("create_readonly_dot_hushlogin").unwrap(),
+            TranslatedFile {
+                create_once: true,
+                ..file("mode_0444_.hushlogin", ".hushlogin", 0o444)
+            }
+        );
+        assert_eq!(
+            translate_chezmoi_file("run_once_before_install.sh.tmpl").unwrap(),
+            TranslatedFile {
+                template: true,
+                script: true,
+                ..file("run_once_before_install.sh.j2", "install.sh", 0o755)
+            }
+        );
+        assert_eq!(
+            translate_chezmoi_file("symlink_dot_vimrc").unwrap(),
+            file("symlink_.vimrc", ".vimrc", 0o644)
+        );
+        assert_eq!(
+            translate_chezmoi_file("literal_dot_x.tmpl").unwrap(),
+            file("dot_x.tmpl", "dot_x.tmpl", 0o644)
+        );
+
+        assert!(translate_chezmoi_file("modify_dot_zshrc").is_err());
+        assert!(translate_chezmoi_file("encrypted_dot_token.asc").is_err());
+        // Would be read back as a template
+        assert!(translate_chezmoi_file("dot_jinja.j2").is_err());
+    }
+
+    #[test]
+    fn test_translate_chezmoi_dir() {
+        assert_eq!(
+            translate_chezmoi_dir("exact_private_dot_ssh").unwrap(),
+            Translated {
+                name: "exact_.ssh".to_string(),
+                target: ".ssh".to_string(),
+                mode: Some(0o700),
+            }
+        );
+        assert_eq!(translate_chezmoi_dir("dot_config").unwrap().name, ".config");
+        assert!(translate_chezmoi_dir("external_dot_oh-my-zsh").is_err());
+    }
+
+    #[test]
+    fn test_plan_chezmoi_source() {
+        let temp = TempDir::new().unwrap();
+        let root = temp.path();
+        fs::create_dir_all(root.join("private_dot_ssh")).unwrap();
+        fs::create_dir_all(root.join(".chezmoiscripts")).unwrap();
+        fs::write(root.join("private_dot_ssh/config.tmpl"), "Host *\n").unwrap();
+        fs::write(root.join(".chezmoiscripts/run_once_setup.sh"), "true\n").unwrap();
+        fs::write(root.join(".chezmoiignore"), "README.md\n").unwrap();
+        fs::write(root.join(".chezmoi.toml.tmpl"), "").unwrap();
+        fs::write(root.join("modify_dot_zshrc"), "").unwrap();
+
+        let (root, chezmoi) = import_root(root).unwrap();
+        assert!(chezmoi);
+        let steps = plan_import(&root, chezmoi).unwrap();
+        let summary: Vec<(String, String)> = steps
+            .iter()
+            .map(|step| {
+                let action = match &step.action {
+                    Action::Dir { to, .. }
+                    | Action::File { to, .. }
+                    | Action::Symlink { to, .. } => to.display().to_string(),
+                    Action::Skip(_) => "skip".to_string(),
+                };
+                (step.from.display().to_string(), action)
+            })
+            .collect();
+        let expected = [
+            (".chezmoi.toml.tmpl", "skip"),
+            (".chezmoiignore", ".guisuignore"),
+            (".chezmoiscripts/run_once_setup.sh", "run_once_setup.sh"),
+            ("modify_dot_zshrc", "skip"),
+            ("private_dot_ssh", ".ssh"),
+            ("private_dot_ssh/config.tmpl", ".ssh/config.j2"),
+        ];
+        assert_eq!(
+            summary,
+            expected
+                .iter()
+                .map(|(from, to)| ((*from).to_string(), (*to).to_string()))
+                .collect::<Vec<_>>()
+        );
+        assert_eq!(steps[5].review, Some(GO_TEMPLATE_NOTE));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_extract_tarball_rejects_paths_through_symlinks() {
+        let temp = TempDir::new().unwrap();
+        let outside = temp.path().join("outside");
+        fs::create_dir_all(&outside).unwrap();
+
+        let tarball = temp.path().join("evil.tar");
+        let mut builder = tar::Builder::new(File::create(&tarball).unwrap());
+        let mut link = tar::Header::new_gnu();
+        link.set_entry_type(tar::EntryType::Symlink);
+        link.set_size(0);
+        builder
+            .append_link(&mut link, "dotfiles/a", &outside)
+            .unwrap();
+        let mut file = tar::Header::new_gnu();
+        file.set_size(3);
+        file.set_mode(0o644);
+        builder
+            .append_data(&mut file, "dotfiles/a/x", &b"pwn"[..])
+            .unwrap();
+        builder.into_inner().unwrap();
+
+        let extracted = temp.path().join("extracted");
+        let err = extract_tarball(&tarball, 1, &extracted).unwrap_err();
+        assert!(err.to_string().contains("through a symlink"));
+        assert!(!outside.join("x").exists());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_import_plain_tarball() {
+        let temp = TempDir::new().unwrap();
+        let tree = temp.path().join("tree/dotfiles");
+        fs::create_dir_all(tree.join(".config")).unwrap();
+        fs::write(tree.join(".config/app.toml"), "x = 1\n").unwrap();
+        fs::write(tree.join("notes.j2"), "").unwrap();
+        std::os::unix::fs::symlink(".config/app.toml", tree.join(".apprc")).unwrap();
+
+        let tarball = temp.path().join("dotfiles.tar.gz");
+        let mut builder = tar::Builder::new(flate2::write::GzEncoder::new(
+            File::create(&tarball).unwrap(),
+            flate2::Compression::default(),
+        ));
+        builder.follow_symlinks(false);
+        builder.append_dir_all("dotfiles", &tree).unwrap();
+        builder.into_inner().unwrap().finish().unwrap();
+
+        let extracted = temp.path().join("extracted");
+        extract_tarball(&tarball, 1, &extracted).unwrap();
+        let (root, chezmoi) = import_root(&extracted).unwrap();
+        assert!(!chezmoi);
+        let steps = plan_import(&root, chezmoi).unwrap();
+        assert!(matches!(&steps.last().unwrap().action, Action::Skip(_)));
+
+        let dotfiles = temp.path().join("source/home");
+        let source_dir = temp.path().join("source");
+        fs::create_dir_all(&dotfiles).unwrap();
+        run_steps(&steps, &root, &dotfiles, &source_dir).unwrap();
+        assert_eq!(
+            fs::read_to_string(dotfiles.join(".config/app.toml")).unwrap(),
+            "x = 1\n"
+        );
+        assert_eq!(
+            fs::read_to_string(dotfiles.join("symlink_.apprc")).unwrap(),
+            ".config/app.toml\n"
+        );
+        assert!(!dotfiles.join("notes.j2").exists());
+
+        // Importing again skips what is already there, unless forced
+        let source_state =
+            SourceState::read(guisu_core::path::AbsPath::new(dotfiles.clone()).unwrap()).unwrap();
+        let mut again = plan_import(&root, false).unwrap();
+        resolve_conflicts(&mut again, &source_state, &dotfiles, false);
+        assert!(
+            again
+                .iter()
+                .all(|step| matches!(step.action, Action::Dir { .. } | Action::Skip(_)))
+        );
+        let mut forced = plan_import(&root, false).unwrap();
+        resolve_conflicts(&mut forced, &source_state, &dotfiles, true);
+        assert!(
+            forced
+                .iter()
+                .any(|step| matches!(step.action, Action::File { .. }))
+        );
+    }
+}
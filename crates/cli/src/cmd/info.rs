@@ -180,16 +180,7 @@ fn gather_info(source_dir: &Path, config: &Config, all: bool) -> InfoData {
 
 /// Find config file path
 fn find_config_file(source_dir: &Path) -> Option<PathBuf> {
-    let config_path = source_dir.join(".guisu.toml");
-    let template_path = source_dir.join(".guisu.toml.j2");
-
-    if config_path.exists() {
-        Some(config_path)
-    } else if template_path.exists() {
-        Some(template_path)
-    } else {
-        None
-    }
+    guisu_config::ConfigFormat::find(source_dir).map(|(path, _, _)| path)
 }
 
 /// Get git repository information
@@ -239,8 +230,7 @@ fn get_git_info(source_dir: &Path, all: bool) -> GitInfo {
                 opts.include_untracked(true);
                 opts.include_ignored(false);
                 repo.statuses(Some(&mut opts))
-                    .map(|statuses| !statuses.is_empty())
-                    .unwrap_or(false)
+                    .is_ok_and(|statuses| !statuses.is_empty())
             } else {
                 false
             };
@@ -575,14 +565,11 @@ fn print_row(label: &str, value: &str, ok: bool, note: Option<&str>) {
 
 /// Validate configuration file
 fn validate_configuration(source_dir: &Path) -> Result<()> {
-    // Check if .guisu.toml or .guisu.toml.j2 exists
-    let config_file = source_dir.join(".guisu.toml");
-    let config_template = source_dir.join(".guisu.toml.j2");
-
-    if !config_template.exists() && !config_file.exists() {
+    // Check if a config file or template exists
+    if find_config_file(source_dir).is_none() {
         anyhow::bail!(
             "Configuration file not found.\n\
-             Expected: .guisu.toml or .guisu.toml.j2 in {}",
+             Expected: .guisu.toml, .guisu.json, or a .j2 template of either in {}",
             source_dir.display()
         );
     }
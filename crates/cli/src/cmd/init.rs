@@ -1,14 +1,63 @@
 //! Init command implementation
 //!
-//! Initialize a new guisu source directory or clone from GitHub.
+//! Initialize a new guisu source directory, optionally from a starter
+//! template, or clone one from GitHub or another git host.
 
-use anyhow::{Context, Result, anyhow};
-use git2::{FetchOptions, RemoteCallbacks, Repository, SubmoduleUpdateOptions};
+use anyhow::{Context, Result, anyhow, bail};
+use git2::{Repository, SubmoduleUpdateOptions};
+use guisu_engine::git::{GitProvider, SystemGitProvider, authenticated_callbacks, fetch_options};
 use indicatif::{ProgressBar, ProgressStyle};
 use std::fs;
 use std::path::{Path, PathBuf};
 use tracing::{debug, info, warn};
 
+/// Host `user` and `owner/repo` references are cloned from by default
+pub const DEFAULT_GIT_HOST: &str = "github.com";
+
+/// A file of the starter repository written by `guisu init --template`
+struct StarterFile {
+    /// Path relative to the source directory
+    path: &'static str,
+    content: &'static str,
+}
+
+const STARTER_FILES: &[StarterFile] = &[
+    StarterFile {
+        path: ".guisu.toml",
+        content: include_str!("../../starter/.guisu.toml"),
+    },
+    StarterFile {
+        path: ".guisu/hooks/post/10-welcome.toml",
+        content: include_str!("../../starter/.guisu/hooks/post/10-welcome.toml"),
+    },
+    StarterFile {
+        path: "README.md",
+        content: include_str!("../../starter/README.md"),
+    },
+];
+
+/// Options of the init command
+#[derive(Debug, Clone, Copy, Default)]
+#[allow(clippy::struct_excessive_bools)]
+pub struct InitOptions<'a> {
+    /// Create a shallow clone with this many commits
+    pub depth: Option<usize>,
+    /// Branch to clone instead of the default branch
+    pub branch: Option<&'a str>,
+    /// Use SSH instead of HTTPS for guessed URLs
+    pub use_ssh: bool,
+    /// Checkout submodules recursively
+    pub recurse_submodules: bool,
+    /// Host for `user` and `owner/repo` references, [`DEFAULT_GIT_HOST`] if `None`
+    pub host: Option<&'a str>,
+    /// Scaffold a starter repository when initializing a local directory
+    pub template: bool,
+    /// Clone with the `git` binary instead of libgit2
+    pub system_git: bool,
+    /// Refuse to clone, as the network must not be used
+    pub offline: bool,
+}
+
 /// Run the init command
 ///
 /// Returns the path to the initialized source directory if successful
@@ -19,118 +68,170 @@ use tracing::{debug, info, warn};
 /// - The target directory cannot be determined
 /// - Git cloning fails
 /// - Local directory initialization fails
-///
-/// # Panics
-///
-/// Panics if `path_or_repo` is `None` when `is_clone` is `true`.
-/// This should never happen due to the logic in `determine_init_target`.
+/// - `template` is set for a repository reference, or for a non-empty directory
+/// - `offline` is set for a repository reference
 pub fn run(
     path_or_repo: Option<&str>,
     custom_source: Option<&Path>,
-    depth: Option<usize>,
-    branch: Option<&str>,
-    use_ssh: bool,
-    recurse_submodules: bool,
+    options: InitOptions<'_>,
 ) -> Result<Option<PathBuf>> {
-    let (target_path, is_clone) = determine_init_target(path_or_repo, custom_source)?;
-    debug!(path = %target_path.display(), is_clone, "Initializing guisu");
-
-    if is_clone {
-        let repo_url = path_or_repo.expect("path_or_repo is Some when is_clone is true");
-        clone_from_github(
-            repo_url,
+    let host = options.host.unwrap_or(DEFAULT_GIT_HOST);
+    let (target_path, repo_url) =
+        determine_init_target(path_or_repo, custom_source, host, options.use_ssh)?;
+    debug!(path = %target_path.display(), url = ?repo_url, "Initializing guisu");
+
+    if let Some(repo_url) = repo_url {
+        if options.template {
+            bail!("--template scaffolds a new repository and cannot be used when cloning");
+        }
+        if options.offline {
+            bail!("Cannot clone {repo_url} in offline mode");
+        }
+        clone_url(
+            &repo_url,
             &target_path,
-            depth,
-            branch,
-            use_ssh,
-            recurse_submodules,
+            options.depth,
+            options.branch,
+            options.recurse_submodules,
+            options.system_git,
         )?;
         return Ok(Some(target_path));
     }
 
     // Initialize local directory
     initialize_local_directory(&target_path)?;
+    if options.template {
+        scaffold_starter(&target_path)?;
+    }
     Ok(Some(target_path))
 }
 
-/// Determine the target path and whether we're cloning from GitHub
+/// Determine the target path and, when cloning, the repository URL
 fn determine_init_target(
     path_or_repo: Option<&str>,
     custom_source: Option<&Path>,
-) -> Result<(PathBuf, bool)> {
+    host: &str,
+    use_ssh: bool,
+) -> Result<(PathBuf, Option<String>)> {
+    // Use custom source or XDG data directory by default and for cloned repos
+    let default_target = || {
+        custom_source
+            .map(std::path::Path::to_path_buf)
+            .or_else(guisu_config::dirs::data_dir)
+            .ok_or_else(|| anyhow!("Could not determine data directory"))
+    };
+
     match path_or_repo {
-        None => {
-            // Default: use custom source or XDG data directory
-            let target = custom_source
-                .map(std::path::Path::to_path_buf)
-                .or_else(guisu_config::dirs::data_dir)
-                .ok_or_else(|| anyhow!("Could not determine data directory"))?;
-            Ok((target, false))
-        }
-        Some(input) => {
-            // Check if it looks like a GitHub reference
-            if is_github_reference(input) {
-                // Use custom source or XDG data directory for cloned repos
-                let target = custom_source
-                    .map(std::path::Path::to_path_buf)
-                    .or_else(guisu_config::dirs::data_dir)
-                    .ok_or_else(|| anyhow!("Could not determine data directory"))?;
-                Ok((target, true))
-            } else {
-                // Explicit local path (overrides custom_source)
-                Ok((PathBuf::from(input), false))
-            }
-        }
+        None => Ok((default_target()?, None)),
+        Some(input) => match repo_url(input, host, use_ssh) {
+            Some(url) => Ok((default_target()?, Some(url))),
+            // Explicit local path (overrides custom_source)
+            None => Ok((PathBuf::from(input), None)),
+        },
     }
 }
 
-/// Check if the input looks like a GitHub reference (username or owner/repo)
-fn is_github_reference(input: &str) -> bool {
-    // Don't treat paths as GitHub references
-    if input.starts_with('/') || input.starts_with('.') || input.contains('\\') {
-        return false;
+/// Whether `input` is a URL git can clone from as is
+///
+/// Covers `scheme://` URLs and scp-like `user@host:path` references.
+fn is_url(input: &str) -> bool {
+    if input.contains("://") {
+        return true;
+    }
+    match (input.find('@'), input.find(':')) {
+        (Some(at), Some(colon)) => at < colon && !input[..colon].contains('/'),
+        _ => false,
     }
+}
 
-    // Check if it looks like username or owner/repo
-    // Simple heuristic: contains only alphanumeric, dash, underscore, and at most one slash
-    let slash_count = input.matches('/').count();
-    if slash_count > 1 {
-        return false;
+/// Clone URL for a repository reference, `None` for local paths
+///
+/// Accepts full URLs, host-qualified references (`gitlab.com/owner/repo`,
+/// `codeberg.org/user`) and `user` or `owner/repo` on `default_host`. A bare
+/// user refers to their `dotfiles` repository.
+fn repo_url(input: &str, default_host: &str, use_ssh: bool) -> Option<String> {
+    if is_url(input) {
+        return Some(input.to_string());
+    }
+
+    // Don't treat paths as repository references
+    if input.starts_with('/') || input.starts_with('.') || input.starts_with('~') {
+        return None;
+    }
+    if input.contains('\\') || input.is_empty() {
+        return None;
+    }
+
+    let segments: Vec<&str> = input.trim_end_matches('/').split('/').collect();
+    let valid = |segment: &str| {
+        !segment.is_empty()
+            && segment
+                .chars()
+                .all(|c| c.is_alphanumeric() || matches!(c, '-' | '_' | '.'))
+    };
+    if !segments.iter().all(|segment| valid(segment)) {
+        return None;
     }
 
-    // Check if all characters are valid GitHub username/repo characters
-    input
-        .chars()
-        .all(|c| c.is_alphanumeric() || c == '-' || c == '_' || c == '/')
+    // A first segment with a dot is a host when more segments follow
+    let (host, path) = match segments.as_slice() {
+        [host, rest @ ..] if host.contains('.') && !rest.is_empty() => (*host, rest),
+        // A single name with a dot is more likely a directory than a user
+        [user] if user.contains('.') => return None,
+        [_] | [_, _] => (default_host, segments.as_slice()),
+        _ => return None,
+    };
+
+    let path = match path {
+        [user] => format!("{user}/dotfiles"),
+        _ => path.join("/"),
+    };
+    let path = path.strip_suffix(".git").unwrap_or(&path);
+
+    Some(if use_ssh {
+        format!("git@{host}:{path}.git")
+    } else {
+        format!("https://{host}/{path}.git")
+    })
 }
 
-/// Clone a repository from GitHub
-#[allow(clippy::too_many_lines)]
-fn clone_from_github(
-    repo_ref: &str,
+/// Clone a repository into `target_path`
+///
+/// `repo` is either a repository reference on [`DEFAULT_GIT_HOST`], expanded
+/// the same way as `guisu init`, or any URL or path git can clone from.
+///
+/// # Errors
+///
+/// Returns an error if the target directory is not empty or cloning fails
+pub fn clone_repository(
+    repo: &str,
     target_path: &Path,
     depth: Option<usize>,
     branch: Option<&str>,
     use_ssh: bool,
     recurse_submodules: bool,
 ) -> Result<()> {
-    // Build the full repository URL
-    let repo_url = if use_ssh {
-        // Use SSH URL format
-        if repo_ref.contains('/') {
-            format!("git@github.com:{repo_ref}.git")
-        } else {
-            format!("git@github.com:{repo_ref}/dotfiles.git")
-        }
-    } else {
-        // Use HTTPS URL format (default)
-        if repo_ref.contains('/') {
-            format!("https://github.com/{repo_ref}.git")
-        } else {
-            format!("https://github.com/{repo_ref}/dotfiles.git")
-        }
-    };
+    let repo_url = repo_url(repo, DEFAULT_GIT_HOST, use_ssh).unwrap_or_else(|| repo.to_string());
+    clone_url(
+        &repo_url,
+        target_path,
+        depth,
+        branch,
+        recurse_submodules,
+        false,
+    )
+}
 
+/// Clone `repo_url` into `target_path`, skipping directories already cloned
+#[allow(clippy::too_many_lines)]
+fn clone_url(
+    repo_url: &str,
+    target_path: &Path,
+    depth: Option<usize>,
+    branch: Option<&str>,
+    recurse_submodules: bool,
+    system_git: bool,
+) -> Result<()> {
     // Check if directory is already a git repository
     if target_path.exists() {
         if let Ok(existing_repo) = Repository::open(target_path) {
@@ -165,6 +266,15 @@ fn clone_from_github(
 
     info!("Cloning repository from {}", repo_url);
 
+    if system_git {
+        debug!(url = %repo_url, path = %target_path.display(), "Starting git clone with the git binary");
+        SystemGitProvider::new()
+            .clone(repo_url, target_path, depth, branch, recurse_submodules)
+            .with_context(|| format!("Failed to clone repository from {repo_url}"))?;
+        info!("Repository cloned successfully");
+        return Ok(());
+    }
+
     let progress_bar = ProgressBar::new(100);
     progress_bar.set_style(
         ProgressStyle::default_bar()
@@ -173,11 +283,7 @@ fn clone_from_github(
             .progress_chars("━━╸ "),
     );
 
-    let mut callbacks = RemoteCallbacks::new();
-    let git_config = git2::Config::open_default()
-        .unwrap_or_else(|_| git2::Config::new().expect("Failed to create git config"));
-    let mut credential_handler = git2_credentials::CredentialHandler::new(git_config);
-
+    let mut callbacks = authenticated_callbacks();
     callbacks.transfer_progress(|stats| {
         let received = stats.received_objects();
         let total = stats.total_objects();
@@ -203,12 +309,7 @@ fn clone_from_github(
         true
     });
 
-    callbacks.credentials(move |url, username_from_url, allowed_types| {
-        credential_handler.try_next_credential(url, username_from_url, allowed_types)
-    });
-
-    let mut fetch_options = FetchOptions::new();
-    fetch_options.remote_callbacks(callbacks);
+    let mut fetch_options = fetch_options(callbacks);
 
     if let Some(depth_value) = depth {
         #[allow(clippy::cast_possible_truncation, clippy::cast_possible_wrap)]
@@ -224,7 +325,7 @@ fn clone_from_github(
     }
 
     let repo = builder
-        .clone(&repo_url, target_path)
+        .clone(repo_url, target_path)
         .with_context(|| {
             progress_bar.finish_and_clear();
             format!(
@@ -269,19 +370,7 @@ fn init_submodules_recursive(repo: &Repository, repo_path: &Path) -> Result<()>
         let mut update_options = SubmoduleUpdateOptions::new();
 
         // Set up fetch options with credentials
-        let mut fetch_options = FetchOptions::new();
-        let mut callbacks = RemoteCallbacks::new();
-        let git_config = git2::Config::open_default()
-            .unwrap_or_else(|_| git2::Config::new().expect("Failed to create git config"));
-        let mut credential_handler = git2_credentials::CredentialHandler::new(git_config);
-
-        callbacks.transfer_progress(|_| true);
-        callbacks.credentials(move |url, username_from_url, allowed_types| {
-            credential_handler.try_next_credential(url, username_from_url, allowed_types)
-        });
-        fetch_options.remote_callbacks(callbacks);
-
-        update_options.fetch(fetch_options);
+        update_options.fetch(fetch_options(authenticated_callbacks()));
 
         submodule
             .update(true, Some(&mut update_options))
@@ -315,3 +404,142 @@ fn initialize_local_directory(path: &Path) -> Result<()> {
 
     Ok(())
 }
+
+/// Write the starter repository into an empty source directory
+///
+/// Also creates the dotfiles directory and a git repository, so the result is
+/// ready for `guisu add`.
+fn scaffold_starter(path: &Path) -> Result<()> {
+    let is_empty = path
+        .read_dir()
+        .with_context(|| format!("Failed to read directory: {}", path.display()))?
+        .next()
+        .is_none();
+    if !is_empty {
+        bail!(
+            "--template needs an empty directory, but {} is not empty",
+            path.display()
+        );
+    }
+
+    for file in STARTER_FILES {
+        let file_path = path.join(file.path);
+        if let Some(parent) = file_path.parent() {
+            fs::create_dir_all(parent)
+                .with_context(|| format!("Failed to create directory: {}", parent.display()))?;
+        }
+        fs::write(&file_path, file.content)
+            .with_context(|| format!("Failed to write {}", file_path.display()))?;
+        debug!(path = %file_path.display(), "Wrote starter file");
+    }
+
+    let dotfiles_dir = path.join(guisu_config::Config::default().general.root_entry);
+    fs::create_dir_all(&dotfiles_dir)
+        .with_context(|| format!("Failed to create directory: {}", dotfiles_dir.display()))?;
+
+    Repository::init(path)
+        .with_context(|| format!("Failed to create git repository: {}", path.display()))?;
+
+    info!("Scaffolded starter repository; edit .guisu.toml and add files with `guisu add`");
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    #![allow(clippy::unwrap_used, clippy::panic)]
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_repo_url() {
+        let url = |input| repo_url(input, DEFAULT_GIT_HOST, false);
+
+        assert_eq!(
+            url("PaulYuuu").as_deref(),
+            Some("https://github.com/PaulYuuu/dotfiles.git")
+        );
+        assert_eq!(
+            url("owner/repo").as_deref(),
+            Some("https://github.com/owner/repo.git")
+        );
+        assert_eq!(
+            url("gitlab.com/group/sub/repo").as_deref(),
+            Some("https://gitlab.com/group/sub/repo.git")
+        );
+        assert_eq!(
+            url("codeberg.org/user").as_deref(),
+            Some("https://codeberg.org/user/dotfiles.git")
+        );
+        assert_eq!(
+            repo_url("git.example.com/me/dots.git", DEFAULT_GIT_HOST, true).as_deref(),
+            Some("git@git.example.com:me/dots.git")
+        );
+        assert_eq!(
+            repo_url("owner/repo", "codeberg.org", false).as_deref(),
+            Some("https://codeberg.org/owner/repo.git")
+        );
+
+        // Full URLs are cloned as is
+        for full in [
+            "https://gitlab.com/owner/repo.git",
+            "ssh://git@git.example.com:2222/me/dots.git",
+            "git@codeberg.org:me/dots.git",
+        ] {
+            assert_eq!(url(full).as_deref(), Some(full));
+        }
+
+        // Local paths
+        for path in [".", "./dots", "/srv/dots", "~/dots", "dots.d", "a/b/c"] {
+            assert_eq!(url(path), None, "{path}");
+        }
+    }
+
+    #[test]
+    fn test_run_template_scaffolds_empty_directory() {
+        let temp = TempDir::new().unwrap();
+        let source = temp.path().join("source");
+        let options = InitOptions {
+            template: true,
+            ..InitOptions::default()
+        };
+
+        run(Some(source.to_str().unwrap()), None, options).unwrap();
+
+        assert!(source.join(".guisu.toml").is_file());
+        assert!(source.join(".guisu/hooks/post/10-welcome.toml").is_file());
+        assert!(source.join("home").is_dir());
+        assert!(Repository::open(&source).is_ok());
+
+        // Starter files must load as they are
+        let config = guisu_config::Config::load_from_source(&source).unwrap();
+        assert_eq!(config.variables["email"], "you@example.com");
+        let hooks = guisu_engine::hooks::HookLoader::new(&source)
+            .load()
+            .unwrap();
+        assert_eq!(hooks.post.len(), 1);
+
+        // A second scaffold would overwrite the user's files
+        let err = run(Some(source.to_str().unwrap()), None, options).unwrap_err();
+        assert!(err.to_string().contains("empty directory"));
+    }
+
+    #[test]
+    fn test_run_template_rejects_clone() {
+        let options = InitOptions {
+            template: true,
+            ..InitOptions::default()
+        };
+        let err = run(Some("owner/repo"), Some(Path::new("/nonexistent")), options).unwrap_err();
+        assert!(err.to_string().contains("--template"));
+    }
+
+    #[test]
+    fn test_run_offline_rejects_clone() {
+        let options = InitOptions {
+            offline: true,
+            ..InitOptions::default()
+        };
+        let err = run(Some("owner/repo"), Some(Path::new("/nonexistent")), options).unwrap_err();
+        assert!(err.to_string().contains("offline mode"));
+    }
+}
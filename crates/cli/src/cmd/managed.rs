@@ -0,0 +1,276 @@
+//! Managed and unmanaged command implementation
+//!
+//! List the destination paths guisu manages, or the destination files it
+//! leaves alone, to audit what apply will and won't touch.
+
+use anyhow::{Context, Result};
+use clap::Args;
+use guisu_config::IgnoreMatcher;
+use guisu_engine::entry::SourceEntry;
+use guisu_engine::state::SourceState;
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::command::Command;
+use crate::common::RuntimeContext;
+
+/// Kind of a managed destination path
+#[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+pub enum ManagedKind {
+    /// Regular files
+    #[value(alias = "file")]
+    Files,
+    /// Directories holding managed entries
+    #[value(alias = "dir", alias = "directories")]
+    Dirs,
+    /// Symbolic links
+    #[value(alias = "symlink")]
+    Symlinks,
+}
+
+/// List destination paths managed by guisu
+#[derive(Debug, Clone, Args)]
+pub struct ManagedCommand {
+    /// Only list these kinds of entries (comma-separated, all if omitted)
+    #[arg(short, long, value_enum, value_delimiter = ',')]
+    pub include: Vec<ManagedKind>,
+}
+
+/// List destination files not managed by guisu
+#[derive(Debug, Clone, Args)]
+pub struct UnmanagedCommand {}
+
+impl Command for ManagedCommand {
+    type Output = ();
+    fn execute(&self, context: &RuntimeContext) -> crate::error::Result<()> {
+        let managed = read_managed_paths(context)?;
+
+        for (path, kind) in &managed {
+            if self.include.is_empty() || self.include.contains(kind) {
+                println!("{}", path.display());
+            }
+        }
+        Ok(())
+    }
+}
+
+impl Command for UnmanagedCommand {
+    type Output = ();
+    fn execute(&self, context: &RuntimeContext) -> crate::error::Result<()> {
+        let managed = read_managed_paths(context)?;
+        let matcher = crate::load_ignore_matcher(
+            context.source_dir(),
+            context.dotfiles_dir().as_path(),
+            &context.config,
+        )?;
+
+        for path in unmanaged_paths(context.dest_dir().as_path(), &managed, &matcher)? {
+            println!("{}", path.display());
+        }
+        Ok(())
+    }
+}
+
+/// Read the source state and collect the destination paths it manages
+fn read_managed_paths(context: &RuntimeContext) -> Result<BTreeMap<PathBuf, ManagedKind>> {
+    let matcher = crate::load_ignore_matcher(
+        context.source_dir(),
+        context.dotfiles_dir().as_path(),
+        &context.config,
+    )
+    .ok();
+    let source_state =
+        SourceState::read_with_matcher(context.dotfiles_dir().to_owned(), matcher.as_ref())
+            .context("Failed to read source state")?;
+    Ok(managed_paths(&source_state, matcher.as_ref()))
+}
+
+/// Destination paths of the target state, relative to the destination
+///
+/// Entries ignored on this platform are left out, as apply skips them. The
+/// parent directories of managed entries count as managed directories, since
+/// apply creates them.
+fn managed_paths(
+    source_state: &SourceState,
+    matcher: Option<&IgnoreMatcher>,
+) -> BTreeMap<PathBuf, ManagedKind> {
+    let mut managed = BTreeMap::new();
+
+    for entry in source_state.entries() {
+        let kind = match entry {
+            SourceEntry::File { .. } => ManagedKind::Files,
+            SourceEntry::Directory { .. } => ManagedKind::Dirs,
+            SourceEntry::Symlink { .. } => ManagedKind::Symlinks,
+            SourceEntry::Script { .. } => continue,
+        };
+        let target = entry.target_path().as_path();
+        if matcher
+            .is_some_and(|matcher| matcher.is_ignored(target, Some(kind == ManagedKind::Dirs)))
+        {
+            continue;
+        }
+
+        managed.insert(target.to_path_buf(), kind);
+        for dir in target.ancestors().skip(1) {
+            if !dir.as_os_str().is_empty() {
+                managed.insert(dir.to_path_buf(), ManagedKind::Dirs);
+            }
+        }
+    }
+
+    managed
+}
+
+/// Destination paths not managed by guisu, relative to the destination
+///
+/// Only managed directories are descended into: an unmanaged directory is
+/// listed once rather than file by file. Paths matching ignore patterns are
+/// skipped.
+///
+/// # Errors
+///
+/// Returns an error if a managed directory cannot be read
+fn unmanaged_paths(
+    dest_dir: &Path,
+    managed: &BTreeMap<PathBuf, ManagedKind>,
+    matcher: &IgnoreMatcher,
+) -> Result<Vec<PathBuf>> {
+    let mut unmanaged = Vec::new();
+    collect_unmanaged(dest_dir, Path::new(""), managed, matcher, &mut unmanaged)?;
+    Ok(unmanaged)
+}
+
+fn collect_unmanaged(
+    dest_dir: &Path,
+    rel_dir: &Path,
+    managed: &BTreeMap<PathBuf, ManagedKind>,
+    matcher: &IgnoreMatcher,
+    unmanaged: &mut Vec<PathBuf>,
+) -> Result<()> {
+    let dir = dest_dir.join(rel_dir);
+    let mut children: Vec<_> = fs::read_dir(&dir)
+        .with_context(|| format!("Failed to read directory: {}", dir.display()))?
+        .collect::<std::io::Result<_>>()
+        .with_context(|| format!("Failed to read directory: {}", dir.display()))?;
+    children.sort_by_key(fs::DirEntry::file_name);
+
+    for child in children {
+        let rel_path = rel_dir.join(child.file_name());
+        // Symlinks to directories are entries of their own, not descended into
+        let is_dir = child.file_type().is_ok_and(|file_type| file_type.is_dir());
+        if matcher.is_ignored(&rel_path, Some(is_dir)) {
+            continue;
+        }
+
+        match managed.get(&rel_path) {
+            Some(ManagedKind::Dirs) if is_dir => {
+                collect_unmanaged(dest_dir, &rel_path, managed, matcher, unmanaged)?;
+            }
+            Some(_) => {}
+            None => unmanaged.push(rel_path),
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    #![allow(clippy::unwrap_used, clippy::panic)]
+    use super::*;
+    use crate::common::testing::{TestWorkspace, write};
+    use guisu_config::Config;
+
+    /// Source with the given source files and ignore patterns
+    fn setup(files: &[&str], ignores: &str) -> TestWorkspace {
+        let workspace = TestWorkspace::new(Config::default());
+        for file in files {
+            write(&workspace.source(file), "content");
+        }
+        write(&workspace.root.join("source/.guisu/ignores.toml"), ignores);
+        workspace
+    }
+
+    fn matcher(workspace: &TestWorkspace) -> IgnoreMatcher {
+        IgnoreMatcher::from_ignores_toml(workspace.context.source_dir()).unwrap()
+    }
+
+    fn managed(workspace: &TestWorkspace) -> BTreeMap<PathBuf, ManagedKind> {
+        let matcher = matcher(workspace);
+        let root = workspace.context.dotfiles_dir().to_owned();
+        let source_state = SourceState::read_with_matcher(root, Some(&matcher)).unwrap();
+        managed_paths(&source_state, Some(&matcher))
+    }
+
+    #[test]
+    fn test_managed_paths_include_parent_directories() {
+        let workspace = setup(
+            &[".bashrc", ".config/nvim/init.lua.j2", ".config/secret.age"],
+            "global = [\".config/secret\"]\n",
+        );
+
+        let managed: Vec<_> = managed(&workspace)
+            .into_iter()
+            .map(|(path, kind)| (path.display().to_string(), kind))
+            .collect();
+        assert_eq!(
+            managed,
+            vec![
+                (".bashrc".to_string(), ManagedKind::Files),
+                (".config".to_string(), ManagedKind::Dirs),
+                (".config/nvim".to_string(), ManagedKind::Dirs),
+                (".config/nvim/init.lua".to_string(), ManagedKind::Files),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_unmanaged_paths_stop_at_unmanaged_directories() {
+        let workspace = setup(
+            &[".bashrc", ".config/nvim/init.lua"],
+            "global = [\".cache\", \"*.log\"]\n",
+        );
+        for path in [
+            ".bashrc",
+            ".profile",
+            ".config/nvim/init.lua",
+            ".config/nvim/lazy-lock.json",
+            ".config/fish/config.fish",
+            ".config/fish/functions/ls.fish",
+            ".cache/thumbnails/a.png",
+            "debug.log",
+        ] {
+            write(&workspace.dest(path), "content");
+        }
+
+        let unmanaged = unmanaged_paths(
+            workspace.context.dest_dir().as_path(),
+            &managed(&workspace),
+            &matcher(&workspace),
+        )
+        .unwrap();
+        assert_eq!(
+            unmanaged,
+            vec![
+                PathBuf::from(".config/fish"),
+                PathBuf::from(".config/nvim/lazy-lock.json"),
+                PathBuf::from(".profile"),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_unmanaged_paths_skip_type_mismatches() {
+        let workspace = setup(&[".vimrc"], "");
+        fs::create_dir_all(workspace.dest(".vimrc/plugins")).unwrap();
+
+        let unmanaged = unmanaged_paths(
+            workspace.context.dest_dir().as_path(),
+            &managed(&workspace),
+            &matcher(&workspace),
+        )
+        .unwrap();
+        assert_eq!(unmanaged, Vec::<PathBuf>::new());
+    }
+}
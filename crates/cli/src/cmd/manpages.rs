@@ -0,0 +1,76 @@
+//! Manpages command implementation
+//!
+//! Generate roff man pages from the `Cli` definition: `guisu.1` plus one page
+//! per subcommand (`guisu-apply.1`, `guisu-age-encrypt.1`, ...).
+
+use anyhow::{Context, Result};
+use clap::{Args, CommandFactory};
+use owo_colors::OwoColorize;
+use std::fs;
+use std::path::PathBuf;
+
+use crate::Cli;
+
+/// Write man pages for guisu and its subcommands
+#[derive(Debug, Clone, Args)]
+pub struct ManpagesCommand {
+    /// Directory to write the pages to (created if missing)
+    #[arg(long, value_name = "DIR")]
+    pub dir: PathBuf,
+}
+
+impl ManpagesCommand {
+    /// Generate the man pages into `--dir`
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the directory cannot be created or a page cannot be written
+    pub fn run(&self) -> Result<()> {
+        let count = self.generate()?;
+        println!(
+            "{} {count} man pages to {}",
+            "Wrote".green(),
+            self.dir.display()
+        );
+        Ok(())
+    }
+
+    /// Write the pages and return how many were written
+    fn generate(&self) -> Result<usize> {
+        fs::create_dir_all(&self.dir)
+            .with_context(|| format!("Failed to create directory: {}", self.dir.display()))?;
+        clap_mangen::generate_to(Cli::command(), &self.dir)
+            .with_context(|| format!("Failed to write man pages to {}", self.dir.display()))?;
+
+        let count = fs::read_dir(&self.dir)
+            .with_context(|| format!("Failed to read directory: {}", self.dir.display()))?
+            .filter_map(std::result::Result::ok)
+            .filter(|entry| {
+                let name = entry.file_name();
+                let name = name.to_string_lossy();
+                name.starts_with("guisu") && name.ends_with(".1")
+            })
+            .count();
+        Ok(count)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    #![allow(clippy::unwrap_used, clippy::panic)]
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_generate_writes_subcommand_pages() {
+        let temp = TempDir::new().unwrap();
+        let dir = temp.path().join("man1");
+        let count = ManpagesCommand { dir: dir.clone() }.generate().unwrap();
+
+        assert!(count > 1);
+        let main = fs::read_to_string(dir.join("guisu.1")).unwrap();
+        assert!(main.contains(".TH guisu"));
+        assert!(dir.join("guisu-apply.1").exists());
+        assert!(dir.join("guisu-age-encrypt.1").exists());
+    }
+}
@@ -5,14 +5,36 @@
 pub mod add;
 pub mod age;
 pub mod apply;
+pub mod archive;
 pub mod cat;
+pub mod cd;
+pub mod completion;
+pub mod config;
+pub mod conflicts;
 pub mod diff;
+pub mod drift;
 pub mod edit;
+pub mod execute_template;
+pub mod forget;
+pub mod git;
 pub mod hooks;
 pub mod ignored;
+pub mod import;
 pub mod info;
 pub mod init;
+pub mod managed;
+pub mod manpages;
+pub mod new;
+pub mod purge;
+pub mod re_add;
+pub mod rollback;
+pub mod serve;
+pub mod snapshot;
+pub mod state;
 pub mod status;
 pub mod templates;
 pub mod update;
 pub mod variables;
+pub mod vault;
+pub mod verify;
+pub mod watch;
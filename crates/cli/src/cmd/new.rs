@@ -0,0 +1,506 @@
+//! New command implementation
+//!
+//! Scaffold starter managed entries for common applications. Built-in
+//! scaffolds are embedded in the binary; a directory under
+//! `.guisu/scaffolds/<app>/` in the source directory overrides the built-in
+//! scaffold of the same name or adds a new one.
+
+use anyhow::{Context, Result};
+use clap::Args;
+use guisu_core::path::{AbsPath, RelPath};
+use owo_colors::OwoColorize;
+use std::fs;
+use std::path::{Path, PathBuf};
+use walkdir::WalkDir;
+
+use crate::command::Command;
+use crate::common::RuntimeContext;
+use crate::utils::path::SourceDirExt;
+
+/// Scaffold a starter managed entry for a common application
+#[derive(Debug, Clone, Args)]
+pub struct NewCommand {
+    /// Application to scaffold (git, zsh, tmux, ssh, or a directory in .guisu/scaffolds/)
+    #[arg(required_unless_present = "list")]
+    pub app: Option<String>,
+
+    /// List available scaffolds
+    #[arg(short, long, conflicts_with = "app")]
+    pub list: bool,
+
+    /// Overwrite entries that already exist in the source directory
+    #[arg(short, long)]
+    pub force: bool,
+}
+
+/// A scaffold shipped with guisu
+struct BuiltinScaffold {
+    name: &'static str,
+    description: &'static str,
+    /// Variables the templates expect to be defined, checked after scaffolding
+    variables: &'static [&'static str],
+    files: &'static [BuiltinFile],
+}
+
+/// A file of a built-in scaffold
+struct BuiltinFile {
+    /// Source path relative to the dotfiles directory, including attribute extensions
+    path: &'static str,
+    content: &'static str,
+    mode: Option<u32>,
+}
+
+const BUILTIN_SCAFFOLDS: &[BuiltinScaffold] = &[
+    BuiltinScaffold {
+        name: "git",
+        description: "Git user identity and sensible defaults",
+        variables: &["name", "email"],
+        files: &[BuiltinFile {
+            path: ".gitconfig.j2",
+            content: include_str!("../../scaffolds/git/.gitconfig.j2"),
+            mode: None,
+        }],
+    },
+    BuiltinScaffold {
+        name: "ssh",
+        description: "SSH client config with keepalive and agent settings",
+        variables: &[],
+        files: &[BuiltinFile {
+            path: ".ssh/config.j2",
+            content: include_str!("../../scaffolds/ssh/.ssh/config.j2"),
+            mode: Some(0o600),
+        }],
+    },
+    BuiltinScaffold {
+        name: "tmux",
+        description: "tmux with mouse support and a hostname status line",
+        variables: &[],
+        files: &[BuiltinFile {
+            path: ".tmux.conf.j2",
+            content: include_str!("../../scaffolds/tmux/.tmux.conf.j2"),
+            mode: None,
+        }],
+    },
+    BuiltinScaffold {
+        name: "zsh",
+        description: "zsh history, completion, and prompt",
+        variables: &["email"],
+        files: &[BuiltinFile {
+            path: ".zshrc.j2",
+            content: include_str!("../../scaffolds/zsh/.zshrc.j2"),
+            mode: None,
+        }],
+    },
+];
+
+/// A file to write into the source directory
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct ScaffoldFile {
+    /// Source path relative to the dotfiles directory, including attribute extensions
+    path: PathBuf,
+    content: Vec<u8>,
+    mode: Option<u32>,
+}
+
+/// A resolved scaffold, either built-in or from the override directory
+#[derive(Debug)]
+struct Scaffold {
+    files: Vec<ScaffoldFile>,
+    variables: &'static [&'static str],
+    is_override: bool,
+}
+
+impl Command for NewCommand {
+    type Output = ();
+    fn execute(&self, context: &RuntimeContext) -> crate::error::Result<()> {
+        let source_dir = context.source_dir();
+
+        let Some(app) = self.app.as_deref() else {
+            print_scaffolds(source_dir);
+            return Ok(());
+        };
+
+        let scaffold = resolve_scaffold(source_dir, app)?;
+        let created = write_scaffold(context.dotfiles_dir(), &scaffold.files, self.force)?;
+
+        let origin = if scaffold.is_override {
+            format!(" (from {})", source_dir.scaffolds_dir().join(app).display())
+        } else {
+            String::new()
+        };
+        println!(
+            "{} {}{}",
+            "Scaffolded".bright_green().bold(),
+            app.bright_white(),
+            origin.dimmed()
+        );
+        for path in &created {
+            println!("  {} {}", "+".bright_green(), path.display());
+        }
+
+        let variables = crate::cmd::apply::load_all_variables(source_dir, &context.config)?;
+        let missing: Vec<&str> = scaffold
+            .variables
+            .iter()
+            .copied()
+            .filter(|name| !variables.contains_key(*name))
+            .collect();
+        if !missing.is_empty() {
+            println!(
+                "\n{} set {} in [variables] of .guisu.toml or in .guisu/variables/",
+                "Hint:".yellow(),
+                missing.join(", ").bright_white()
+            );
+        }
+
+        println!(
+            "\nPreview with {} and deploy with {}",
+            "guisu diff".bright_cyan(),
+            "guisu apply".bright_cyan()
+        );
+
+        Ok(())
+    }
+}
+
+/// Print built-in scaffolds and overrides found in the source directory
+fn print_scaffolds(source_dir: &Path) {
+    let overrides = override_names(source_dir);
+
+    println!("{}", "Built-in scaffolds:".bright_cyan().bold());
+    for scaffold in BUILTIN_SCAFFOLDS {
+        let marker = if overrides.iter().any(|name| name == scaffold.name) {
+            " (overridden)".dimmed().to_string()
+        } else {
+            String::new()
+        };
+        println!(
+            "  {:<8} {}{}",
+            scaffold.name.bright_white(),
+            scaffold.description.dimmed(),
+            marker
+        );
+    }
+
+    let custom: Vec<&String> = overrides
+        .iter()
+        .filter(|name| !BUILTIN_SCAFFOLDS.iter().any(|s| s.name == name.as_str()))
+        .collect();
+    if !custom.is_empty() {
+        println!("\n{}", "Custom scaffolds:".bright_cyan().bold());
+        for name in custom {
+            println!("  {}", name.bright_white());
+        }
+    }
+}
+
+/// Names of the scaffold directories under `.guisu/scaffolds/`
+fn override_names(source_dir: &Path) -> Vec<String> {
+    let Ok(entries) = fs::read_dir(source_dir.scaffolds_dir()) else {
+        return Vec::new();
+    };
+
+    let mut names: Vec<String> = entries
+        .flatten()
+        .filter(|entry| entry.path().is_dir())
+        .filter_map(|entry| entry.file_name().to_str().map(str::to_string))
+        .collect();
+    names.sort();
+    names
+}
+
+/// Find the scaffold for `app`, preferring the source directory's override
+fn resolve_scaffold(source_dir: &Path, app: &str) -> Result<Scaffold> {
+    if app.is_empty() || app.contains(['/', '\\']) || app.starts_with('.') {
+        anyhow::bail!("Invalid scaffold name: {app}");
+    }
+
+    let builtin = BUILTIN_SCAFFOLDS.iter().find(|s| s.name == app);
+    let override_dir = source_dir.scaffolds_dir().join(app);
+
+    if override_dir.is_dir() {
+        let files = read_override_dir(&override_dir)?;
+        if files.is_empty() {
+            anyhow::bail!("Scaffold directory is empty: {}", override_dir.display());
+        }
+        // Overrides may use any variables, so there is nothing to check for
+        return Ok(Scaffold {
+            files,
+            variables: &[],
+            is_override: true,
+        });
+    }
+
+    let Some(builtin) = builtin else {
+        let mut available: Vec<String> = BUILTIN_SCAFFOLDS
+            .iter()
+            .map(|s| s.name.to_string())
+            .chain(override_names(source_dir))
+            .collect();
+        available.sort();
+        available.dedup();
+        anyhow::bail!(
+            "Unknown scaffold: {app}\n\nAvailable scaffolds: {}\n\
+             Add your own under {}",
+            available.join(", "),
+            source_dir.scaffolds_dir().join(app).display()
+        );
+    };
+
+    Ok(Scaffold {
+        files: builtin
+            .files
+            .iter()
+            .map(|file| ScaffoldFile {
+                path: PathBuf::from(file.path),
+                content: file.content.as_bytes().to_vec(),
+                mode: file.mode,
+            })
+            .collect(),
+        variables: builtin.variables,
+        is_override: false,
+    })
+}
+
+/// Read every file of an override scaffold, keeping its permissions
+fn read_override_dir(dir: &Path) -> Result<Vec<ScaffoldFile>> {
+    let mut files = Vec::new();
+
+    for entry in WalkDir::new(dir).sort_by_file_name() {
+        let entry = entry.with_context(|| format!("Failed to read {}", dir.display()))?;
+        if !entry.file_type().is_file() {
+            continue;
+        }
+
+        let path = entry
+            .path()
+            .strip_prefix(dir)
+            .with_context(|| format!("Path outside scaffold: {}", entry.path().display()))?
+            .to_path_buf();
+        let content = fs::read(entry.path())
+            .with_context(|| format!("Failed to read {}", entry.path().display()))?;
+
+        #[cfg(unix)]
+        let mode = {
+            use std::os::unix::fs::PermissionsExt;
+            Some(entry.metadata()?.permissions().mode() & 0o777)
+        };
+        #[cfg(not(unix))]
+        let mode = None;
+
+        files.push(ScaffoldFile {
+            path,
+            content,
+            mode,
+        });
+    }
+
+    Ok(files)
+}
+
+/// Target path of a source file: the source path without `.age` and `.j2`
+fn target_rel_path(source_path: &Path) -> PathBuf {
+    let mut path = source_path.to_string_lossy().to_string();
+    for suffix in [".age", ".j2"] {
+        if let Some(stripped) = path.strip_suffix(suffix) {
+            path = stripped.to_string();
+        }
+    }
+    PathBuf::from(path)
+}
+
+/// Write scaffold files into the dotfiles directory
+///
+/// Refuses to touch any file unless every entry is new or `force` is set.
+/// With `force`, an existing variant with different attributes (e.g. a plain
+/// `.gitconfig` replaced by `.gitconfig.j2`) is removed first.
+fn write_scaffold(
+    dotfiles_dir: &AbsPath,
+    files: &[ScaffoldFile],
+    force: bool,
+) -> Result<Vec<PathBuf>> {
+    let mut existing = Vec::new();
+    for file in files {
+        let target = RelPath::new(target_rel_path(&file.path))?;
+        if let Some(path) = crate::cmd::add::check_file_exists_in_source(dotfiles_dir, &target) {
+            existing.push(path);
+        }
+    }
+
+    if !existing.is_empty() && !force {
+        let list: Vec<String> = existing
+            .iter()
+            .map(|path| format!("  {}", path.display()))
+            .collect();
+        anyhow::bail!(
+            "These entries are already managed by guisu:\n{}\n\n\
+             To replace them, use: guisu new --force",
+            list.join("\n")
+        );
+    }
+
+    for path in &existing {
+        fs::remove_file(path)
+            .with_context(|| format!("Failed to remove old file: {}", path.display()))?;
+    }
+
+    let mut created = Vec::with_capacity(files.len());
+    for file in files {
+        let dest = dotfiles_dir.as_path().join(&file.path);
+        if let Some(parent) = dest.parent() {
+            create_parent_dirs(parent, file.mode)?;
+        }
+
+        fs::write(&dest, &file.content)
+            .with_context(|| format!("Failed to write file: {}", dest.display()))?;
+
+        #[cfg(unix)]
+        if let Some(mode) = file.mode {
+            use std::os::unix::fs::PermissionsExt;
+            fs::set_permissions(&dest, fs::Permissions::from_mode(mode))
+                .with_context(|| format!("Failed to set permissions: {}", dest.display()))?;
+        }
+
+        created.push(file.path.clone());
+    }
+
+    Ok(created)
+}
+
+/// Create missing parent directories
+///
+/// Directories created for a private file are private as well, so e.g. a
+/// scaffolded `.ssh/config` deploys into a `0700` `~/.ssh`.
+fn create_parent_dirs(dir: &Path, file_mode: Option<u32>) -> Result<()> {
+    if dir.exists() {
+        return Ok(());
+    }
+
+    let mut builder = fs::DirBuilder::new();
+    builder.recursive(true);
+
+    #[cfg(unix)]
+    if file_mode.is_some_and(|mode| matches!(mode & 0o777, 0o600 | 0o700)) {
+        use std::os::unix::fs::DirBuilderExt;
+        builder.mode(0o700);
+    }
+    #[cfg(not(unix))]
+    let _ = file_mode;
+
+    builder
+        .create(dir)
+        .with_context(|| format!("Failed to create directory: {}", dir.display()))
+}
+
+#[cfg(test)]
+mod tests {
+    #![allow(clippy::unwrap_used, clippy::panic)]
+    use super::*;
+    use guisu_template::{TemplateContext, TemplateEngine};
+    use tempfile::TempDir;
+
+    fn dotfiles(temp: &TempDir) -> AbsPath {
+        let dir = fs::canonicalize(temp.path()).unwrap().join("home");
+        fs::create_dir_all(&dir).unwrap();
+        AbsPath::new(dir).unwrap()
+    }
+
+    #[test]
+    fn test_builtin_scaffolds_render() {
+        let engine = TemplateEngine::new();
+        let mut context = TemplateContext::new();
+        context.add_variable("name".to_string(), "Jane Doe".into());
+        context.add_variable("email".to_string(), "jane@example.com".into());
+
+        for scaffold in BUILTIN_SCAFFOLDS {
+            for file in scaffold.files {
+                let rendered = engine
+                    .render_str(file.content, &context)
+                    .unwrap_or_else(|e| panic!("{} failed to render: {e}", file.path));
+                assert!(!rendered.contains("{{"), "{} left tags", file.path);
+            }
+        }
+
+        let git = BUILTIN_SCAFFOLDS.iter().find(|s| s.name == "git").unwrap();
+        let rendered = engine.render_str(git.files[0].content, &context).unwrap();
+        assert!(rendered.contains("name = Jane Doe"));
+        assert!(rendered.contains("email = jane@example.com"));
+    }
+
+    #[test]
+    fn test_target_rel_path_strips_attribute_extensions() {
+        assert_eq!(
+            target_rel_path(Path::new(".gitconfig.j2")),
+            Path::new(".gitconfig")
+        );
+        assert_eq!(
+            target_rel_path(Path::new(".ssh/config.j2.age")),
+            Path::new(".ssh/config")
+        );
+        assert_eq!(target_rel_path(Path::new(".vimrc")), Path::new(".vimrc"));
+    }
+
+    #[test]
+    fn test_resolve_unknown_scaffold() {
+        let temp = TempDir::new().unwrap();
+        let err = resolve_scaffold(temp.path(), "emacs").unwrap_err();
+        assert!(err.to_string().contains("Unknown scaffold: emacs"));
+
+        assert!(resolve_scaffold(temp.path(), "../git").is_err());
+    }
+
+    #[test]
+    fn test_override_directory_takes_precedence() {
+        let temp = TempDir::new().unwrap();
+        let override_dir = temp.path().scaffolds_dir().join("git");
+        fs::create_dir_all(&override_dir).unwrap();
+        fs::write(override_dir.join(".gitconfig"), "[user]\n").unwrap();
+
+        let scaffold = resolve_scaffold(temp.path(), "git").unwrap();
+        assert!(scaffold.is_override);
+        assert_eq!(scaffold.files.len(), 1);
+        assert_eq!(scaffold.files[0].path, Path::new(".gitconfig"));
+        assert_eq!(scaffold.files[0].content, b"[user]\n");
+    }
+
+    #[test]
+    #[cfg(unix)]
+    fn test_write_private_scaffold() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let temp = TempDir::new().unwrap();
+        let dotfiles_dir = dotfiles(&temp);
+        let scaffold = resolve_scaffold(temp.path(), "ssh").unwrap();
+
+        let created = write_scaffold(&dotfiles_dir, &scaffold.files, false).unwrap();
+        assert_eq!(created, [PathBuf::from(".ssh/config.j2")]);
+
+        let file = dotfiles_dir.as_path().join(".ssh/config.j2");
+        let dir_mode = fs::metadata(file.parent().unwrap())
+            .unwrap()
+            .permissions()
+            .mode();
+        let file_mode = fs::metadata(&file).unwrap().permissions().mode();
+        assert_eq!(dir_mode & 0o777, 0o700);
+        assert_eq!(file_mode & 0o777, 0o600);
+    }
+
+    #[test]
+    fn test_write_scaffold_refuses_existing_entry_without_force() {
+        let temp = TempDir::new().unwrap();
+        let dotfiles_dir = dotfiles(&temp);
+        fs::write(dotfiles_dir.as_path().join(".gitconfig"), "mine").unwrap();
+        let scaffold = resolve_scaffold(temp.path(), "git").unwrap();
+
+        let err = write_scaffold(&dotfiles_dir, &scaffold.files, false).unwrap_err();
+        assert!(err.to_string().contains("already managed"));
+        assert_eq!(
+            fs::read_to_string(dotfiles_dir.as_path().join(".gitconfig")).unwrap(),
+            "mine"
+        );
+
+        write_scaffold(&dotfiles_dir, &scaffold.files, true).unwrap();
+        assert!(!dotfiles_dir.as_path().join(".gitconfig").exists());
+        assert!(dotfiles_dir.as_path().join(".gitconfig.j2").exists());
+    }
+}
@@ -0,0 +1,295 @@
+//! Purge command implementation
+//!
+//! Remove every managed target from the destination directory, for example
+//! when decommissioning a machine. Optionally delete the source directory and
+//! the state database as well.
+
+use anyhow::{Context, Result};
+use clap::Args;
+use guisu_core::path::{AbsPath, RelPath};
+use guisu_engine::state::SourceState;
+use owo_colors::OwoColorize;
+use std::collections::BTreeSet;
+use std::fs;
+use std::path::Path;
+use tracing::debug;
+
+use crate::cmd::forget::remove_empty_parents;
+use crate::command::Command;
+use crate::common::RuntimeContext;
+
+/// Remove all managed files from the destination directory
+#[derive(Debug, Clone, Args)]
+pub struct PurgeCommand {
+    /// Show what would be removed without removing anything
+    #[arg(short = 'n', long)]
+    pub dry_run: bool,
+
+    /// Also delete the source directory and the state database
+    #[arg(long)]
+    pub all: bool,
+
+    /// Skip confirmation prompt
+    #[arg(short, long)]
+    pub yes: bool,
+}
+
+impl Command for PurgeCommand {
+    type Output = ();
+    fn execute(&self, context: &RuntimeContext) -> crate::error::Result<()> {
+        let dest_abs = context.dest_dir();
+        let source_dir = context.source_dir();
+
+        let matcher = crate::load_ignore_matcher(
+            source_dir,
+            context.dotfiles_dir().as_path(),
+            &context.config,
+        )
+        .ok();
+        let source_state =
+            SourceState::read_with_matcher(context.dotfiles_dir().to_owned(), matcher.as_ref())
+                .context("Failed to read source state")?;
+        let targets: Vec<RelPath> = source_state
+            .entries()
+            .map(|entry| entry.target_path().clone())
+            .filter(|target| {
+                matcher
+                    .as_ref()
+                    .is_none_or(|matcher| !matcher.is_ignored(target.as_path(), None))
+            })
+            .collect();
+        let present = present_targets(|path| context.dest_path(path), &targets);
+
+        let db_path = if self.all {
+            check_source_outside_destination(source_dir, dest_abs)?;
+            Some(guisu_engine::database::get_db_path().context("Failed to get database path")?)
+        } else {
+            None
+        };
+
+        if present.is_empty() {
+            println!("{}", "No managed files in the destination.".dimmed());
+        } else {
+            println!("{}", "Managed files to remove:".bold());
+            for target in &present {
+                println!(
+                    "  {} {}",
+                    "-".red(),
+                    context.dest_path(target).bright_white()
+                );
+            }
+        }
+        if let Some(db_path) = &db_path {
+            println!("{}", "Also removing:".bold());
+            println!("  {} {}", "-".red(), source_dir.display().bright_white());
+            println!("  {} {}", "-".red(), db_path.display().bright_white());
+        }
+
+        if self.dry_run {
+            println!("{}", "Dry run: nothing was removed.".dimmed());
+            return Ok(());
+        }
+        if present.is_empty() && db_path.is_none() {
+            return Ok(());
+        }
+
+        if !self.yes {
+            use dialoguer::{Confirm, theme::ColorfulTheme};
+
+            let confirmed = Confirm::with_theme(&ColorfulTheme::default())
+                .with_prompt("Permanently remove these files?")
+                .default(false)
+                .interact()
+                .context("Failed to read user input")?;
+
+            if !confirmed {
+                println!("Cancelled.");
+                return Ok(());
+            }
+        }
+
+        let removed = purge_destination(|path| context.dest_path(path), &present)?;
+
+        if let Some(db_path) = &db_path {
+            fs::remove_dir_all(source_dir).with_context(|| {
+                format!(
+                    "Failed to remove source directory: {}",
+                    source_dir.display()
+                )
+            })?;
+            remove_file_if_exists(db_path).with_context(|| {
+                format!("Failed to remove state database: {}", db_path.display())
+            })?;
+        } else {
+            for target in &present {
+                guisu_engine::database::delete_entry_state(context.database(), &target.to_string())
+                    .with_context(|| format!("Failed to delete state of {target}"))?;
+            }
+        }
+
+        println!(
+            "{} {} {}",
+            "Purged".bright_green().bold(),
+            removed,
+            if removed == 1 { "file" } else { "files" }
+        );
+        Ok(())
+    }
+}
+
+/// Targets whose destination is a file or symlink
+///
+/// A directory where a managed file belongs is not guisu's and is left alone.
+fn present_targets(dest_path: impl Fn(&RelPath) -> AbsPath, targets: &[RelPath]) -> Vec<RelPath> {
+    let mut present: Vec<RelPath> = targets
+        .iter()
+        .filter(|target| {
+            fs::symlink_metadata(dest_path(target).as_path()).is_ok_and(|meta| !meta.is_dir())
+        })
+        .cloned()
+        .collect();
+    present.sort_by(|a, b| a.as_path().cmp(b.as_path()));
+    present
+}
+
+/// Remove the targets from the destination, then the directories they leave empty
+///
+/// Returns the number of removed files.
+fn purge_destination(
+    dest_path: impl Fn(&RelPath) -> AbsPath,
+    targets: &[RelPath],
+) -> Result<usize> {
+    let mut removed = 0;
+    let mut parents = BTreeSet::new();
+
+    for target in targets {
+        let path = dest_path(target);
+        if remove_file_if_exists(path.as_path())
+            .with_context(|| format!("Failed to remove {path}"))?
+        {
+            removed += 1;
+            debug!(path = %target, "Purged destination");
+        }
+        parents.extend(target.as_path().ancestors().skip(1).map(Path::to_path_buf));
+    }
+
+    remove_empty_parents(parents, dest_path);
+    Ok(removed)
+}
+
+/// Remove a file or symlink, returning whether it existed
+fn remove_file_if_exists(path: &Path) -> std::io::Result<bool> {
+    match fs::remove_file(path) {
+        Ok(()) => Ok(true),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(false),
+        Err(e) => Err(e),
+    }
+}
+
+/// Refuse to delete a source directory that contains the destination
+fn check_source_outside_destination(source_dir: &Path, dest_abs: &AbsPath) -> Result<()> {
+    let source_dir = fs::canonicalize(source_dir).with_context(|| {
+        format!(
+            "Failed to resolve source directory: {}",
+            source_dir.display()
+        )
+    })?;
+    if dest_abs.as_path().starts_with(&source_dir) {
+        anyhow::bail!(
+            "Refusing to delete source directory {} because it contains the destination {}",
+            source_dir.display(),
+            dest_abs
+        );
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    #![allow(clippy::unwrap_used, clippy::panic)]
+    use super::*;
+    use std::path::PathBuf;
+    use tempfile::TempDir;
+
+    fn rel(path: &str) -> RelPath {
+        RelPath::new(PathBuf::from(path)).unwrap()
+    }
+
+    fn write(path: &Path) {
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        fs::write(path, "content").unwrap();
+    }
+
+    #[test]
+    fn test_purge_destination_prunes_empty_directories() {
+        let temp = TempDir::new().unwrap();
+        let dest = AbsPath::new(fs::canonicalize(temp.path()).unwrap()).unwrap();
+        write(&temp.path().join(".bashrc"));
+        write(&temp.path().join(".config/nvim/lua/plugins.lua"));
+        write(&temp.path().join(".config/git/config"));
+        write(&temp.path().join(".config/git/local"));
+
+        let targets = [
+            rel(".bashrc"),
+            rel(".config/nvim/lua/plugins.lua"),
+            rel(".config/git/config"),
+            rel(".profile"),
+        ];
+        let present = present_targets(|path| dest.join(path), &targets);
+        assert_eq!(present.len(), 3);
+
+        assert_eq!(
+            purge_destination(|path| dest.join(path), &present).unwrap(),
+            3
+        );
+        assert!(!temp.path().join(".bashrc").exists());
+        assert!(!temp.path().join(".config/nvim").exists());
+        // Unmanaged files keep their directories
+        assert!(temp.path().join(".config/git/local").exists());
+        assert!(temp.path().exists());
+    }
+
+    #[test]
+    fn test_present_targets_skips_directories() {
+        let temp = TempDir::new().unwrap();
+        let dest = AbsPath::new(fs::canonicalize(temp.path()).unwrap()).unwrap();
+        fs::create_dir_all(temp.path().join(".vimrc")).unwrap();
+
+        assert_eq!(
+            present_targets(|path| dest.join(path), &[rel(".vimrc")]),
+            Vec::<guisu_engine::RelPath>::new()
+        );
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_purge_destination_removes_symlink_not_target() {
+        let temp = TempDir::new().unwrap();
+        let dest = AbsPath::new(fs::canonicalize(temp.path()).unwrap()).unwrap();
+        write(&temp.path().join("real"));
+        std::os::unix::fs::symlink(temp.path().join("real"), temp.path().join(".zshrc")).unwrap();
+
+        assert_eq!(
+            purge_destination(|path| dest.join(path), &[rel(".zshrc")]).unwrap(),
+            1
+        );
+        assert!(fs::symlink_metadata(temp.path().join(".zshrc")).is_err());
+        assert!(temp.path().join("real").exists());
+    }
+
+    #[test]
+    fn test_check_source_outside_destination() {
+        let temp = TempDir::new().unwrap();
+        let root = fs::canonicalize(temp.path()).unwrap();
+        let source = root.join("source");
+        let dest = root.join("source/home");
+        fs::create_dir_all(&dest).unwrap();
+
+        let dest_abs = AbsPath::new(dest).unwrap();
+        assert!(check_source_outside_destination(&source, &dest_abs).is_err());
+
+        let other = AbsPath::new(root.clone()).unwrap();
+        fs::create_dir_all(root.join("elsewhere")).unwrap();
+        assert!(check_source_outside_destination(&root.join("elsewhere"), &other).is_ok());
+    }
+}
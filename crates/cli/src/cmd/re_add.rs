@@ -0,0 +1,358 @@
+//! Re-add command implementation
+//!
+//! Copy destination files that were edited in place back into the source
+//! directory, keeping each source entry's name and attributes.
+
+use anyhow::{Context, Result};
+use clap::Args;
+use guisu_engine::database::MergeBase;
+use guisu_engine::entry::SourceEntry;
+use guisu_engine::hash::hash_content;
+use guisu_engine::state::SourceState;
+use owo_colors::OwoColorize;
+use std::fs;
+use std::path::PathBuf;
+use tracing::debug;
+
+use crate::cmd::add::encrypt_content;
+use crate::cmd::forget::managed_entries;
+use crate::command::Command;
+use crate::common::RuntimeContext;
+
+/// Sync destination changes back to the source directory
+#[derive(Debug, Clone, Args)]
+pub struct ReAddCommand {
+    /// Target files or directories to re-add (all managed files if omitted)
+    pub files: Vec<PathBuf>,
+
+    /// Also re-add templates and encrypted files
+    #[arg(short, long)]
+    pub force: bool,
+}
+
+/// Result of re-adding a single entry
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum ReAddOutcome {
+    /// Source was updated from the destination
+    Updated,
+    /// Destination matches the source
+    Unchanged,
+    /// Destination is missing or not a regular file
+    Missing,
+    /// Entry needs `--force`, with the reason
+    Skipped(&'static str),
+}
+
+impl Command for ReAddCommand {
+    type Output = ();
+    fn execute(&self, context: &RuntimeContext) -> crate::error::Result<()> {
+        let source_state = SourceState::read(context.dotfiles_dir().to_owned())
+            .context("Failed to read source state")?;
+
+        let mut entries: Vec<&SourceEntry> = if self.files.is_empty() {
+            source_state.entries().collect()
+        } else {
+            let rel_paths =
+                crate::build_filter_paths(&self.files, context.dest_dir(), context.targets())?;
+            let mut entries = Vec::new();
+            for (rel_path, file_path) in rel_paths.iter().zip(&self.files) {
+                let managed = managed_entries(&source_state, rel_path);
+                if managed.is_empty() {
+                    return Err(anyhow::anyhow!(
+                        "File not managed by guisu: {}",
+                        file_path.display()
+                    )
+                    .into());
+                }
+                entries.extend(managed);
+            }
+            entries
+        };
+        entries.sort_by(|a, b| a.target_path().as_path().cmp(b.target_path().as_path()));
+        entries.dedup_by(|a, b| a.target_path() == b.target_path());
+
+        let mut updated = 0;
+        let mut skipped = Vec::new();
+        for entry in entries {
+            let target_path = entry.target_path();
+            let outcome = re_add_entry(context, &source_state, entry, self.force)
+                .with_context(|| format!("Failed to re-add {target_path}"))?;
+
+            match outcome {
+                ReAddOutcome::Updated => {
+                    updated += 1;
+                    println!("  {} {}", "✓".bright_green(), target_path.bright_white());
+                }
+                ReAddOutcome::Skipped(reason) => skipped.push((target_path, reason)),
+                ReAddOutcome::Unchanged | ReAddOutcome::Missing => {}
+            }
+        }
+
+        if !skipped.is_empty() {
+            println!("{}", "Skipped (use --force to re-add):".yellow());
+            for (target_path, reason) in &skipped {
+                println!(
+                    "  {} {} {}",
+                    "-".yellow(),
+                    target_path.bright_white(),
+                    format!("({reason})").dimmed()
+                );
+            }
+        }
+
+        if updated == 0 {
+            println!("{}", "Source is already up to date".dimmed());
+        } else {
+            println!(
+                "{} {} {}",
+                "Re-added".bright_green().bold(),
+                updated,
+                if updated == 1 { "file" } else { "files" }
+            );
+        }
+        Ok(())
+    }
+}
+
+/// Write the destination content of one entry back to its source file
+///
+/// Plain files are compared byte for byte with their source. Encrypted files
+/// are decrypted for the comparison and re-encrypted when written. Templates
+/// cannot be compared with their rendered output, so they count as changed when
+/// the destination differs from what guisu last applied; re-adding one
+/// replaces the template with the destination content.
+fn re_add_entry(
+    context: &RuntimeContext,
+    source_state: &SourceState,
+    entry: &SourceEntry,
+    force: bool,
+) -> Result<ReAddOutcome> {
+    let SourceEntry::File {
+        source_path,
+        target_path,
+        attributes,
+    } = entry
+    else {
+        return Ok(ReAddOutcome::Missing);
+    };
+
+    let dest_path = context.dest_path(target_path);
+    if !fs::symlink_metadata(dest_path.as_path()).is_ok_and(|meta| meta.is_file()) {
+        return Ok(ReAddOutcome::Missing);
+    }
+    let dest_content = fs::read(dest_path.as_path())
+        .with_context(|| format!("Failed to read destination file: {dest_path}"))?;
+
+    let source_file = source_state.source_file_path(source_path);
+    let is_template = attributes.is_template();
+    let is_encrypted = attributes.is_encrypted();
+
+    if !force && is_template {
+        return Ok(ReAddOutcome::Skipped("template"));
+    }
+    if !force && is_encrypted {
+        return Ok(ReAddOutcome::Skipped("encrypted"));
+    }
+
+    let unchanged = if is_template {
+        guisu_engine::database::get_entry_state(context.database(), &target_path.to_string())?
+            .is_none_or(|state| state.content_hash == hash_content(&dest_content))
+    } else {
+        let source_content = fs::read(source_file.as_path())
+            .with_context(|| format!("Failed to read source file: {source_file}"))?;
+        let source_content = if is_encrypted {
+            let identities = context.load_identities()?;
+            guisu_crypto::decrypt(&source_content, &identities)
+                .with_context(|| format!("Failed to decrypt source file: {source_file}"))?
+        } else {
+            source_content
+        };
+        let source_content = if attributes.is_compressed() {
+            guisu_engine::compress::decompress(&source_content)
+                .with_context(|| format!("Failed to decompress source file: {source_file}"))?
+        } else {
+            source_content
+        };
+        source_content == dest_content
+    };
+    if unchanged {
+        return Ok(ReAddOutcome::Unchanged);
+    }
+
+    // Compression is applied before encryption
+    let new_source = if attributes.is_compressed() {
+        guisu_engine::compress::compress(&dest_content)
+            .with_context(|| format!("Failed to compress {target_path}"))?
+    } else {
+        dest_content.clone()
+    };
+    let new_source = if is_encrypted {
+        encrypt_content(&new_source, &context.config, target_path.as_path())?
+    } else {
+        new_source
+    };
+    fs::write(source_file.as_path(), new_source)
+        .with_context(|| format!("Failed to write source file: {source_file}"))?;
+
+    // The destination now matches the target state, so it is no longer drift
+    let base = MergeBase::for_file(
+        is_encrypted || attributes.is_env(),
+        dest_mode(dest_path.as_path()),
+    );
+    guisu_engine::database::save_entry_state(
+        context.database(),
+        &target_path.to_string(),
+        &dest_content,
+        attributes.mode(),
+        base,
+        &context.clock.begin_run(),
+    )
+    .with_context(|| format!("Failed to save state of {target_path}"))?;
+
+    debug!(path = %target_path, "Re-added entry");
+    Ok(ReAddOutcome::Updated)
+}
+
+/// Permission bits of a destination file, owner read/write only where unknown
+fn dest_mode(path: &std::path::Path) -> u32 {
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        fs::metadata(path).map_or(0o600, |meta| meta.permissions().mode() & 0o777)
+    }
+    #[cfg(not(unix))]
+    {
+        let _ = path;
+        0o600
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    #![allow(clippy::unwrap_used, clippy::panic)]
+    use super::*;
+    use crate::common::testing::{TestWorkspace, write};
+    use guisu_config::Config;
+    use guisu_core::path::RelPath;
+    use tempfile::TempDir;
+
+    /// Source with the given (source path, content) files and an empty destination
+    fn setup(config: Config, files: &[(&str, &str)]) -> TestWorkspace {
+        let workspace = TestWorkspace::new(config);
+        for (source_path, content) in files {
+            write(&workspace.source(source_path), content);
+        }
+        workspace
+    }
+
+    fn re_add(workspace: &TestWorkspace, target: &str, force: bool) -> ReAddOutcome {
+        let context = &workspace.context;
+        let source_state = SourceState::read(context.dotfiles_dir().to_owned()).unwrap();
+        let entry = source_state
+            .get(&RelPath::new(PathBuf::from(target)).unwrap())
+            .unwrap();
+        re_add_entry(context, &source_state, entry, force).unwrap()
+    }
+
+    #[test]
+    fn test_re_add_plain_file() {
+        let workspace = setup(Config::default(), &[(".bashrc", "alias ll='ls -l'\n")]);
+
+        assert_eq!(re_add(&workspace, ".bashrc", false), ReAddOutcome::Missing);
+
+        write(&workspace.dest(".bashrc"), b"alias ll='ls -l'\n");
+        assert_eq!(
+            re_add(&workspace, ".bashrc", false),
+            ReAddOutcome::Unchanged
+        );
+
+        write(&workspace.dest(".bashrc"), b"alias ll='ls -la'\n");
+        assert_eq!(re_add(&workspace, ".bashrc", false), ReAddOutcome::Updated);
+        assert_eq!(
+            fs::read(workspace.source(".bashrc")).unwrap(),
+            b"alias ll='ls -la'\n"
+        );
+
+        let state =
+            guisu_engine::database::get_entry_state(workspace.context.database(), ".bashrc")
+                .unwrap()
+                .unwrap();
+        assert_eq!(state.content_hash, hash_content(b"alias ll='ls -la'\n"));
+    }
+
+    #[test]
+    fn test_re_add_template_requires_force() {
+        let workspace = setup(
+            Config::default(),
+            &[(".gitconfig.j2", "name = {{ name }}\n")],
+        );
+        guisu_engine::database::save_entry_state(
+            workspace.context.database(),
+            ".gitconfig",
+            b"name = me\n",
+            None,
+            MergeBase::Keep,
+            &workspace.context.clock.begin_run(),
+        )
+        .unwrap();
+
+        // The destination still holds what was last applied
+        write(&workspace.dest(".gitconfig"), b"name = me\n");
+        assert_eq!(
+            re_add(&workspace, ".gitconfig", true),
+            ReAddOutcome::Unchanged
+        );
+
+        write(&workspace.dest(".gitconfig"), b"name = you\n");
+        assert_eq!(
+            re_add(&workspace, ".gitconfig", false),
+            ReAddOutcome::Skipped("template")
+        );
+        assert_eq!(
+            fs::read(workspace.source(".gitconfig.j2")).unwrap(),
+            b"name = {{ name }}\n"
+        );
+
+        assert_eq!(
+            re_add(&workspace, ".gitconfig", true),
+            ReAddOutcome::Updated
+        );
+        assert_eq!(
+            fs::read(workspace.source(".gitconfig.j2")).unwrap(),
+            b"name = you\n"
+        );
+    }
+
+    #[test]
+    fn test_re_add_encrypted_file_keeps_encryption() {
+        let temp = TempDir::new().unwrap();
+        let identity = guisu_crypto::Identity::generate();
+        let key = temp.path().join("key.txt");
+        guisu_crypto::IdentityFile::save(&key, std::slice::from_ref(&identity)).unwrap();
+
+        let mut config = Config::default();
+        config.age.identity = Some(key);
+        config.age.derive = true;
+
+        let encrypted = guisu_crypto::encrypt(b"token=old\n", &[identity.to_public()]).unwrap();
+        let workspace = setup(config, &[]);
+        write(&workspace.source(".netrc.age"), &encrypted);
+
+        write(&workspace.dest(".netrc"), b"token=old\n");
+        assert_eq!(
+            re_add(&workspace, ".netrc", false),
+            ReAddOutcome::Skipped("encrypted")
+        );
+        assert_eq!(re_add(&workspace, ".netrc", true), ReAddOutcome::Unchanged);
+
+        write(&workspace.dest(".netrc"), b"token=new\n");
+        assert_eq!(re_add(&workspace, ".netrc", true), ReAddOutcome::Updated);
+
+        let source = fs::read(workspace.source(".netrc.age")).unwrap();
+        assert_ne!(source, b"token=new\n");
+        assert_eq!(
+            guisu_crypto::decrypt(&source, &[identity]).unwrap(),
+            b"token=new\n"
+        );
+    }
+}
@@ -0,0 +1,405 @@
+//! Rollback command implementation
+//!
+//! Restore destination files from the apply snapshots saved when
+//! `[apply] snapshots` is set. Without `--to`, the last apply is undone;
+//! with `--to`, every apply since the given time is undone, so each file goes
+//! back to its content before the earliest of them.
+
+use anyhow::{Context, Result, bail};
+use clap::Args;
+use guisu_config::FsyncPolicy;
+use guisu_core::path::{AbsPath, RelPath};
+use guisu_engine::clock::RunStamp;
+use guisu_engine::state::{ApplySnapshot, RedbPersistentState, SnapshotFile};
+use owo_colors::OwoColorize;
+use std::collections::BTreeMap;
+use std::fs;
+use std::io::Write;
+use std::path::PathBuf;
+
+use crate::command::Command;
+use crate::common::RuntimeContext;
+use crate::utils::atomic;
+
+/// Restore destination files saved before an apply
+#[derive(Debug, Clone, Args)]
+pub struct RollbackCommand {
+    /// Target files or directories to restore (all files in the snapshots if not specified)
+    #[arg(value_name = "FILES")]
+    pub files: Vec<PathBuf>,
+
+    /// Undo every apply since TIMESTAMP instead of only the last one
+    ///
+    /// Accepts a snapshot ID, `YYYY-MM-DD`, `YYYY-MM-DD HH:MM[:SS]` (local
+    /// time), or an RFC 3339 timestamp.
+    #[arg(long, value_name = "TIMESTAMP")]
+    pub to: Option<String>,
+
+    /// Dry run - show what would be restored without making changes
+    #[arg(short = 'n', long)]
+    pub dry_run: bool,
+
+    /// Skip confirmation prompt
+    #[arg(short, long)]
+    pub yes: bool,
+}
+
+impl Command for RollbackCommand {
+    type Output = ();
+    fn execute(&self, context: &RuntimeContext) -> crate::error::Result<()> {
+        let db = context.database();
+        let snapshots = guisu_engine::database::get_apply_snapshots(db)
+            .context("Failed to read apply snapshots")?;
+        if snapshots.is_empty() {
+            return Err(anyhow::anyhow!(
+                "No apply snapshots saved.\n\
+                 Set `snapshots = 10` under [apply] in .guisu.toml to snapshot the last 10 applies."
+            )
+            .into());
+        }
+
+        let selected = select_snapshots(&snapshots, self.to.as_deref())?;
+        let filter = crate::build_filter_paths(&self.files, context.dest_dir(), context.targets())?;
+        let dest_path = |path: &RelPath| context.dest_path(path);
+        let plan = plan_rollback(db, &selected, &filter, dest_path)?;
+        if plan.is_empty() {
+            println!("Nothing to roll back; the files already match the snapshot.");
+            return Ok(());
+        }
+
+        println!("{}", "Files to restore:".bold());
+        for file in &plan {
+            if file.content_hash.is_some() {
+                println!("  {} {}", "M".yellow(), file.path.bright_white());
+            } else {
+                println!(
+                    "  {} {} {}",
+                    "-".red(),
+                    file.path.bright_white(),
+                    "(did not exist, will be removed)".dimmed()
+                );
+            }
+        }
+
+        if self.dry_run {
+            println!();
+            println!("{}", "Dry run - no files were modified.".yellow().bold());
+            return Ok(());
+        }
+
+        if !self.yes {
+            use dialoguer::{Confirm, theme::ColorfulTheme};
+
+            let confirmed = Confirm::with_theme(&ColorfulTheme::default())
+                .with_prompt("Restore these files?")
+                .default(false)
+                .interact()
+                .context("Failed to read user input")?;
+            if !confirmed {
+                println!("{}", "Cancelled.".yellow());
+                return Ok(());
+            }
+        }
+
+        // The replaced content is saved too, so a second rollback undoes this one
+        let stamp = context.clock.begin_run();
+        let keep = context.config.apply.snapshots.max(1);
+        snapshot_current(db, &plan, dest_path, &stamp, keep)?;
+
+        for file in &plan {
+            restore_file(db, file, dest_path, context.config.apply.fsync)?;
+        }
+
+        println!(
+            "{} Restored {} {}",
+            "✓".green().bold(),
+            plan.len(),
+            if plan.len() == 1 { "file" } else { "files" }
+        );
+        Ok(())
+    }
+}
+
+/// Snapshots to undo, oldest first
+///
+/// Without `to`, only the most recent snapshot. Otherwise the snapshot with ID
+/// `to` and every later one, or every snapshot taken at or after the time `to`.
+fn select_snapshots<'a>(
+    snapshots: &'a [(String, ApplySnapshot)],
+    to: Option<&str>,
+) -> Result<Vec<&'a ApplySnapshot>> {
+    let Some(to) = to else {
+        return Ok(snapshots.last().map(|(_, s)| s).into_iter().collect());
+    };
+
+    let start = if let Some(index) = snapshots.iter().position(|(id, _)| id == to) {
+        index
+    } else {
+        let since = parse_timestamp(to)?;
+        snapshots
+            .iter()
+            .position(|(_, snapshot)| snapshot.stamp.timestamp >= since)
+            .with_context(|| format!("No apply snapshots taken since {to}"))?
+    };
+    Ok(snapshots[start..].iter().map(|(_, s)| s).collect())
+}
+
+/// Parse a `--to` time into seconds since the Unix epoch
+fn parse_timestamp(value: &str) -> Result<u64> {
+    use chrono::{DateTime, Local, NaiveDate, NaiveDateTime, TimeZone};
+
+    let time = if let Ok(time) = DateTime::parse_from_rfc3339(value) {
+        time.timestamp()
+    } else {
+        let naive = ["%Y-%m-%d %H:%M:%S", "%Y-%m-%d %H:%M", "%Y-%m-%dT%H:%M:%S"]
+            .iter()
+            .find_map(|format| NaiveDateTime::parse_from_str(value, format).ok())
+            .or_else(|| {
+                NaiveDate::parse_from_str(value, "%Y-%m-%d")
+                    .ok()
+                    .and_then(|date| date.and_hms_opt(0, 0, 0))
+            })
+            .with_context(|| {
+                format!(
+                    "Invalid --to value '{value}'. Use a snapshot ID, YYYY-MM-DD, \
+                     'YYYY-MM-DD HH:MM[:SS]', or an RFC 3339 timestamp"
+                )
+            })?;
+        Local
+            .from_local_datetime(&naive)
+            .earliest()
+            .with_context(|| format!("'{value}' does not exist in the local time zone"))?
+            .timestamp()
+    };
+    Ok(u64::try_from(time).unwrap_or(0))
+}
+
+/// Files to restore, sorted by path
+///
+/// Each file is restored to its content in the oldest selected snapshot that
+/// has it. Files outside `filter` (when given) and files whose destination
+/// already matches are left out.
+fn plan_rollback(
+    db: &RedbPersistentState,
+    snapshots: &[&ApplySnapshot],
+    filter: &[RelPath],
+    dest_path: impl Fn(&RelPath) -> AbsPath,
+) -> Result<Vec<SnapshotFile>> {
+    let mut files: BTreeMap<&str, &SnapshotFile> = BTreeMap::new();
+    for snapshot in snapshots {
+        for file in &snapshot.files {
+            files.entry(file.path.as_str()).or_insert(file);
+        }
+    }
+
+    if !filter.is_empty() {
+        files.retain(|path, _| {
+            filter
+                .iter()
+                .any(|wanted| std::path::Path::new(path).starts_with(wanted.as_path()))
+        });
+        if files.is_empty() {
+            bail!("None of the given files were changed by the selected applies");
+        }
+    }
+
+    let mut plan = Vec::new();
+    for file in files.into_values() {
+        let dest = snapshot_dest(file, &dest_path)?;
+        let current = match fs::read(&dest) {
+            Ok(content) => Some(guisu_engine::hash::hash_content(&content)),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => None,
+            Err(e) => {
+                return Err(e).with_context(|| format!("Failed to read {}", dest.display()));
+            }
+        };
+        if current == file.content_hash {
+            continue;
+        }
+        // Fail before anything is restored rather than halfway through
+        if let Some(hash) = file.content_hash
+            && guisu_engine::database::get_snapshot_content(db, &hash)?.is_none()
+        {
+            bail!(
+                "Saved content of {} is missing from the database",
+                file.path
+            );
+        }
+        plan.push(file.clone());
+    }
+    Ok(plan)
+}
+
+/// Save the current content of the files about to be restored
+fn snapshot_current(
+    db: &RedbPersistentState,
+    plan: &[SnapshotFile],
+    dest_path: impl Fn(&RelPath) -> AbsPath,
+    stamp: &RunStamp,
+    keep: usize,
+) -> Result<()> {
+    let mut snapshot = ApplySnapshot::new(stamp);
+    for file in plan {
+        let dest = snapshot_dest(file, &dest_path)?;
+        let content_hash = if dest.exists() {
+            let content =
+                fs::read(&dest).with_context(|| format!("Failed to read {}", dest.display()))?;
+            Some(guisu_engine::database::save_snapshot_content(db, &content)?)
+        } else {
+            None
+        };
+        snapshot.files.push(SnapshotFile {
+            path: file.path.clone(),
+            content_hash,
+            mode: file_mode(&dest),
+        });
+    }
+    guisu_engine::database::save_apply_snapshot(db, &snapshot, keep)
+        .context("Failed to save rollback snapshot")?;
+    Ok(())
+}
+
+/// Destination of a file saved in a snapshot
+fn snapshot_dest(file: &SnapshotFile, dest_path: impl Fn(&RelPath) -> AbsPath) -> Result<PathBuf> {
+    let target = RelPath::new(PathBuf::from(&file.path))?;
+    Ok(dest_path(&target).into_path_buf())
+}
+
+/// Put a destination file back as recorded in a snapshot
+fn restore_file(
+    db: &RedbPersistentState,
+    file: &SnapshotFile,
+    dest_path: impl Fn(&RelPath) -> AbsPath,
+    fsync: FsyncPolicy,
+) -> Result<()> {
+    let dest = snapshot_dest(file, dest_path)?;
+
+    let Some(hash) = file.content_hash else {
+        if dest.exists() {
+            fs::remove_file(&dest)
+                .with_context(|| format!("Failed to remove {}", dest.display()))?;
+        }
+        return Ok(());
+    };
+
+    let content = guisu_engine::database::get_snapshot_content(db, &hash)?.with_context(|| {
+        format!(
+            "Saved content of {} is missing from the database",
+            file.path
+        )
+    })?;
+    if let Some(parent) = dest.parent() {
+        fs::create_dir_all(parent)
+            .with_context(|| format!("Failed to create directory: {}", parent.display()))?;
+    }
+    atomic::replace(&dest, file.mode, fsync, |staged| {
+        staged
+            .write_all(&content)
+            .with_context(|| format!("Failed to write {}", dest.display()))
+    })
+}
+
+/// Permission bits of a file (Unix only)
+fn file_mode(path: &std::path::Path) -> Option<u32> {
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        fs::metadata(path)
+            .ok()
+            .map(|meta| meta.permissions().mode() & 0o7777)
+    }
+    #[cfg(not(unix))]
+    {
+        let _ = path;
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    #![allow(clippy::unwrap_used, clippy::panic)]
+    use super::*;
+    use guisu_engine::clock::StateClock;
+    use tempfile::TempDir;
+
+    fn save(db: &RedbPersistentState, stamp: &RunStamp, files: &[(&str, Option<&[u8]>)]) -> String {
+        let mut snapshot = ApplySnapshot::new(stamp);
+        for (path, content) in files {
+            snapshot.files.push(SnapshotFile {
+                path: (*path).to_string(),
+                content_hash: content.map(|content| {
+                    guisu_engine::database::save_snapshot_content(db, content).unwrap()
+                }),
+                mode: None,
+            });
+        }
+        guisu_engine::database::save_apply_snapshot(db, &snapshot, 10).unwrap()
+    }
+
+    #[test]
+    fn test_rollback_restores_oldest_selected_content() {
+        let temp = TempDir::new().unwrap();
+        let db = RedbPersistentState::new(temp.path().join("state.db")).unwrap();
+        let dest = AbsPath::new(temp.path().join("home")).unwrap();
+        fs::create_dir_all(dest.as_path()).unwrap();
+        fs::write(dest.as_path().join(".bashrc"), "third").unwrap();
+        fs::write(dest.as_path().join(".new"), "created").unwrap();
+
+        let clock = StateClock::fixed(1_700_000_000);
+        let first = save(&db, &clock.begin_run(), &[(".bashrc", Some(b"first"))]);
+        save(
+            &db,
+            &clock.begin_run(),
+            &[(".bashrc", Some(b"second")), (".new", None)],
+        );
+        let snapshots = guisu_engine::database::get_apply_snapshots(&db).unwrap();
+
+        // Only the last apply
+        let dest_path = |path: &RelPath| dest.join(path);
+        let selected = select_snapshots(&snapshots, None).unwrap();
+        let plan = plan_rollback(&db, &selected, &[], dest_path).unwrap();
+        assert_eq!(plan.len(), 2);
+        for file in &plan {
+            restore_file(&db, file, dest_path, FsyncPolicy::None).unwrap();
+        }
+        assert_eq!(
+            fs::read_to_string(dest.as_path().join(".bashrc")).unwrap(),
+            "second"
+        );
+        assert!(!dest.as_path().join(".new").exists());
+
+        // Every apply since the first one
+        let selected = select_snapshots(&snapshots, Some(&first)).unwrap();
+        let filter = vec![RelPath::new(PathBuf::from(".bashrc")).unwrap()];
+        let plan = plan_rollback(&db, &selected, &filter, dest_path).unwrap();
+        restore_file(&db, &plan[0], dest_path, FsyncPolicy::None).unwrap();
+        assert_eq!(
+            fs::read_to_string(dest.as_path().join(".bashrc")).unwrap(),
+            "first"
+        );
+
+        // Already restored
+        assert_eq!(
+            plan_rollback(&db, &selected, &filter, dest_path).unwrap(),
+            Vec::<SnapshotFile>::new()
+        );
+    }
+
+    #[test]
+    fn test_select_snapshots_by_time() {
+        let temp = TempDir::new().unwrap();
+        let db = RedbPersistentState::new(temp.path().join("state.db")).unwrap();
+        let clock = StateClock::fixed(1_700_000_000);
+        save(&db, &clock.begin_run(), &[(".a", None)]);
+        let snapshots = guisu_engine::database::get_apply_snapshots(&db).unwrap();
+
+        assert_eq!(
+            select_snapshots(&snapshots, Some("2023-11-14T00:00:00Z"))
+                .unwrap()
+                .len(),
+            1
+        );
+        assert!(select_snapshots(&snapshots, Some("2030-01-01")).is_err());
+        assert!(select_snapshots(&snapshots, Some("yesterday")).is_err());
+    }
+}
@@ -0,0 +1,676 @@
+//! Serve command implementation
+//!
+//! Expose status, diff, apply, and add over a line-delimited JSON-RPC 2.0
+//! protocol on stdin/stdout, so editor integrations can keep a single guisu
+//! process alive instead of spawning one (and reloading config, identities,
+//! and the state database) for every interaction.
+//!
+//! Each request is one JSON object on a single line; each response is written
+//! as one line. Requests without an `id` are notifications and get no response.
+//!
+//! The state database is only open while a request is handled, so other guisu
+//! commands can run while the server waits for the next one.
+//!
+//! ```text
+//! → {"jsonrpc":"2.0","id":1,"method":"status","params":{"all":true}}
+//! ← {"jsonrpc":"2.0","id":1,"result":{"entries":[{"path":"~/.bashrc","status":"behind","type":"F","binary":false}]}}
+//! ```
+
+use anyhow::{Context, Result};
+use clap::Args;
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use serde_json::{Value, json};
+use similar::TextDiff;
+use std::io::{BufRead, Write};
+use std::path::{Path, PathBuf};
+
+use crate::command::Command;
+use crate::common::{DetachedContext, RuntimeContext};
+use crate::ui::FileStatus as DiffFileStatus;
+use crate::utils::lock::RunLock;
+
+/// JSON-RPC error code: invalid JSON was received
+const PARSE_ERROR: i64 = -32700;
+/// JSON-RPC error code: the JSON sent is not a valid request object
+const INVALID_REQUEST: i64 = -32600;
+/// JSON-RPC error code: the method does not exist
+const METHOD_NOT_FOUND: i64 = -32601;
+/// JSON-RPC error code: invalid method parameters
+const INVALID_PARAMS: i64 = -32602;
+/// JSON-RPC error code: the operation itself failed
+const OPERATION_FAILED: i64 = -32000;
+
+/// Run a long-lived JSON-RPC server for editor integrations
+#[derive(Debug, Clone, Args)]
+pub struct ServeCommand {
+    /// Speak JSON-RPC over stdin/stdout (one message per line)
+    #[arg(long)]
+    pub stdio: bool,
+}
+
+impl ServeCommand {
+    /// Serve requests, opening the state database at `db_path` for each one
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if no transport is selected, or if reading requests or
+    /// writing responses fails
+    pub fn run(&self, context: &DetachedContext, db_path: &Path) -> Result<()> {
+        if !self.stdio {
+            anyhow::bail!("No transport selected. Use `guisu serve --stdio`");
+        }
+
+        let stdin = std::io::stdin().lock();
+        let stdout = std::io::stdout().lock();
+        let lock_path = crate::utils::lock::lock_path()?;
+        let open = || {
+            let database = crate::open_database(db_path)?;
+            Ok(context.attach(std::sync::Arc::new(database)))
+        };
+        serve(&open, &lock_path, stdin, stdout)
+    }
+}
+
+/// Opens the state database for a single request
+type OpenContext<'a> = dyn Fn() -> Result<RuntimeContext> + 'a;
+
+/// Incoming JSON-RPC request
+#[derive(Debug, Deserialize)]
+struct Request {
+    #[serde(default)]
+    jsonrpc: Option<String>,
+    #[serde(default)]
+    id: Option<Value>,
+    method: String,
+    #[serde(default)]
+    params: Value,
+}
+
+/// Outgoing JSON-RPC response
+#[derive(Debug, Serialize)]
+struct Response {
+    jsonrpc: &'static str,
+    id: Value,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    result: Option<Value>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    error: Option<RpcError>,
+}
+
+/// JSON-RPC error object
+#[derive(Debug, Serialize)]
+struct RpcError {
+    code: i64,
+    message: String,
+}
+
+impl Response {
+    fn success(id: Value, result: Value) -> Self {
+        Self {
+            jsonrpc: "2.0",
+            id,
+            result: Some(result),
+            error: None,
+        }
+    }
+
+    fn failure(id: Value, code: i64, message: impl Into<String>) -> Self {
+        Self {
+            jsonrpc: "2.0",
+            id,
+            result: None,
+            error: Some(RpcError {
+                code,
+                message: message.into(),
+            }),
+        }
+    }
+}
+
+/// Parameters for the `status` method
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+struct StatusParams {
+    files: Vec<PathBuf>,
+    all: bool,
+}
+
+/// Parameters for the `diff` method
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+struct DiffParams {
+    files: Vec<PathBuf>,
+}
+
+/// Parameters for the `apply` method
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+struct ApplyParams {
+    files: Vec<PathBuf>,
+    dry_run: bool,
+    force: bool,
+}
+
+/// Parameters for the `add` method
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+#[allow(clippy::struct_excessive_bools)]
+struct AddParams {
+    files: Vec<PathBuf>,
+    #[serde(default)]
+    template: bool,
+    #[serde(default)]
+    encrypt: bool,
+    #[serde(default)]
+    create: bool,
+    #[serde(default)]
+    force: bool,
+}
+
+/// Read requests from `reader` and write responses to `writer` until EOF or `shutdown`
+///
+/// # Errors
+///
+/// Returns an error if reading from `reader` or writing to `writer` fails.
+/// Failures of individual requests are reported as JSON-RPC errors instead.
+///
+/// The server holds neither the run lock (see [`crate::utils::lock`]) nor the
+/// state database while idle. Each request gets its context from `open`, and
+/// requests that write take the lock at `lock_path` first, both for the
+/// request's duration.
+pub(crate) fn serve<R: BufRead, W: Write>(
+    open: &OpenContext<'_>,
+    lock_path: &Path,
+    reader: R,
+    mut writer: W,
+) -> Result<()> {
+    for line in reader.lines() {
+        let line = line.context("Failed to read request")?;
+        if line.trim().is_empty() {
+            continue;
+        }
+
+        let (response, shutdown) = handle_line(open, lock_path, &line);
+
+        if let Some(response) = response {
+            serde_json::to_writer(&mut writer, &response).context("Failed to write response")?;
+            writer.write_all(b"\n")?;
+            writer.flush()?;
+        }
+
+        if shutdown {
+            break;
+        }
+    }
+
+    Ok(())
+}
+
+/// Handle a single request line
+///
+/// Returns the response to send (if any) and whether the server should stop.
+fn handle_line(open: &OpenContext<'_>, lock_path: &Path, line: &str) -> (Option<Response>, bool) {
+    let value: Value = match serde_json::from_str(line) {
+        Ok(value) => value,
+        Err(e) => {
+            return (
+                Some(Response::failure(
+                    Value::Null,
+                    PARSE_ERROR,
+                    format!("Parse error: {e}"),
+                )),
+                false,
+            );
+        }
+    };
+
+    let request: Request = match serde_json::from_value(value) {
+        Ok(request) => request,
+        Err(e) => {
+            return (
+                Some(Response::failure(
+                    Value::Null,
+                    INVALID_REQUEST,
+                    format!("Invalid request: {e}"),
+                )),
+                false,
+            );
+        }
+    };
+
+    let id = request.id.clone();
+    let shutdown = request.method == "shutdown";
+
+    if request.jsonrpc.as_deref().is_some_and(|v| v != "2.0") {
+        return (
+            id.map(|id| Response::failure(id, INVALID_REQUEST, "Unsupported jsonrpc version")),
+            false,
+        );
+    }
+
+    let outcome = dispatch(open, lock_path, &request.method, request.params);
+
+    // Notifications (no id) never get a response
+    let response = id.map(|id| match outcome {
+        Ok(result) => Response::success(id, result),
+        Err((code, message)) => Response::failure(id, code, message),
+    });
+
+    (response, shutdown)
+}
+
+/// Route a method call to its handler
+///
+/// The context is opened after the parameters are checked and the run lock
+/// is taken, and closed again when the handler returns.
+fn dispatch(
+    open: &OpenContext<'_>,
+    lock_path: &Path,
+    method: &str,
+    params: Value,
+) -> std::result::Result<Value, (i64, String)> {
+    let context = || open().map_err(operation_failed);
+    match method {
+        "status" => {
+            let params: StatusParams = parse_params(params)?;
+            handle_status(&context()?, &params)
+        }
+        "diff" => {
+            let params: DiffParams = parse_params(params)?;
+            handle_diff(&context()?, &params)
+        }
+        "apply" => {
+            let params: ApplyParams = parse_params(params)?;
+            let _run_lock = (!params.dry_run)
+                .then(|| lock(lock_path, "serve apply"))
+                .transpose()?;
+            handle_apply(&context()?, params)
+        }
+        "add" => {
+            let params: AddParams = parse_params(params)?;
+            let _run_lock = lock(lock_path, "serve add")?;
+            handle_add(&context()?, &params)
+        }
+        "shutdown" => Ok(Value::Null),
+        _ => Err((METHOD_NOT_FOUND, format!("Method not found: {method}"))),
+    }
+}
+
+/// Take the run lock for a request that writes
+///
+/// Honors `--wait`; without it a request fails while another guisu runs.
+fn lock(lock_path: &Path, command: &str) -> std::result::Result<RunLock, (i64, String)> {
+    RunLock::acquire(lock_path, command, crate::wait_for_lock()).map_err(operation_failed)
+}
+
+/// Deserialize method parameters, treating missing params as defaults
+fn parse_params<T: DeserializeOwned>(params: Value) -> std::result::Result<T, (i64, String)> {
+    let params = if params.is_null() { json!({}) } else { params };
+    serde_json::from_value(params).map_err(|e| (INVALID_PARAMS, format!("Invalid params: {e}")))
+}
+
+/// Convert an operation error into a JSON-RPC error
+fn operation_failed(e: impl std::fmt::Display) -> (i64, String) {
+    (OPERATION_FAILED, format!("{e:#}"))
+}
+
+/// Handle `status`: report the state of managed entries
+fn handle_status(
+    context: &RuntimeContext,
+    params: &StatusParams,
+) -> std::result::Result<Value, (i64, String)> {
+    let entries = crate::cmd::status::collect_status(
+        context.database(),
+        context.source_dir(),
+        context.dest_dir().as_path(),
+        &context.config,
+        &params.files,
+        &crate::common::EntryFilter::default(),
+        false,
+    )
+    .map_err(operation_failed)?
+    .unwrap_or_default();
+
+    let entries: Vec<_> = entries
+        .into_iter()
+        .filter(|entry| entry.file_type != 'D')
+        .filter(|entry| params.all || entry.status != crate::cmd::status::FileStatus::Steady)
+        .collect();
+
+    Ok(json!({ "entries": entries }))
+}
+
+/// Handle `diff`: return a unified diff per changed file
+fn handle_diff(
+    context: &RuntimeContext,
+    params: &DiffParams,
+) -> std::result::Result<Value, (i64, String)> {
+    let file_diffs = crate::cmd::diff::collect_file_diffs(
+        context.source_dir(),
+        context.dest_dir().as_path(),
+        &params.files,
+        &context.config,
+    )
+    .map_err(operation_failed)?;
+
+    let files: Vec<Value> = file_diffs
+        .iter()
+        .map(|file| {
+            let status = match file.status {
+                DiffFileStatus::Added => "added",
+                DiffFileStatus::Modified => "modified",
+                DiffFileStatus::Deleted => "deleted",
+            };
+            let diff = TextDiff::from_lines(&file.old_content, &file.new_content)
+                .unified_diff()
+                .header(&format!("a/{}", file.path), &format!("b/{}", file.path))
+                .to_string();
+            json!({ "path": file.path, "status": status, "diff": diff })
+        })
+        .collect();
+
+    Ok(json!({ "files": files }))
+}
+
+/// Handle `apply`: apply entries without prompting
+fn handle_apply(
+    context: &RuntimeContext,
+    params: ApplyParams,
+) -> std::result::Result<Value, (i64, String)> {
+    let apply_cmd = crate::cmd::apply::ApplyCommand {
+        files: params.files,
+        dry_run: params.dry_run,
+        force: params.force,
+        merge: false,
+        prune: false,
+        interactive: false,
+        include: vec![],
+        exclude: vec![],
+        jobs: None,
+        backup: false,
+        refresh_externals: false,
+        one_shot: None,
+        source_ref: None,
+        timings: false,
+    };
+
+    let report = apply_cmd
+        .execute_unattended(context)
+        .map_err(operation_failed)?;
+
+    serde_json::to_value(report).map_err(operation_failed)
+}
+
+/// Handle `add`: add destination files to the source directory
+fn handle_add(
+    context: &RuntimeContext,
+    params: &AddParams,
+) -> std::result::Result<Value, (i64, String)> {
+    if params.files.is_empty() {
+        return Err((
+            INVALID_PARAMS,
+            "Invalid params: `files` is empty".to_string(),
+        ));
+    }
+
+    let add_cmd = crate::cmd::add::AddCommand {
+        files: params.files.clone(),
+        template: params.template,
+        autotemplate: false,
+        encrypt: params.encrypt,
+        compress: false,
+        create: params.create,
+        force: params.force,
+        recursive: true,
+        follow: false,
+        secrets: None,
+    };
+
+    add_cmd.execute(context).map_err(operation_failed)?;
+
+    Ok(json!({ "added": params.files }))
+}
+
+#[cfg(test)]
+mod tests {
+    #![allow(clippy::unwrap_used, clippy::panic)]
+    use super::*;
+    use crate::common::testing::{TestWorkspace, write};
+    use guisu_config::Config;
+    use std::fs;
+
+    /// Source with a managed `.bashrc` and an empty destination
+    fn fixture() -> TestWorkspace {
+        let workspace = TestWorkspace::new(Config::default());
+        write(&workspace.source(".bashrc"), "export A=1\n");
+        workspace
+    }
+
+    fn lock_path(fx: &TestWorkspace) -> PathBuf {
+        fx.root.join("guisu.lock")
+    }
+
+    fn call(fx: &TestWorkspace, line: &str) -> Value {
+        let (response, _) = handle_line(&|| Ok(fx.context.clone()), &lock_path(fx), line);
+        serde_json::to_value(response.unwrap()).unwrap()
+    }
+
+    #[test]
+    fn test_parse_error() {
+        let fx = fixture();
+        let response = call(&fx, "{not json");
+        assert_eq!(response["error"]["code"], PARSE_ERROR);
+        assert_eq!(response["id"], Value::Null);
+    }
+
+    #[test]
+    fn test_invalid_request_without_method() {
+        let fx = fixture();
+        let response = call(&fx, r#"{"jsonrpc":"2.0","id":1}"#);
+        assert_eq!(response["error"]["code"], INVALID_REQUEST);
+    }
+
+    #[test]
+    fn test_method_not_found() {
+        let fx = fixture();
+        let response = call(&fx, r#"{"jsonrpc":"2.0","id":7,"method":"nope"}"#);
+        assert_eq!(response["id"], 7);
+        assert_eq!(response["error"]["code"], METHOD_NOT_FOUND);
+    }
+
+    #[test]
+    fn test_invalid_params() {
+        let fx = fixture();
+        let response = call(
+            &fx,
+            r#"{"jsonrpc":"2.0","id":1,"method":"status","params":{"bogus":true}}"#,
+        );
+        assert_eq!(response["error"]["code"], INVALID_PARAMS);
+    }
+
+    #[test]
+    fn test_notification_has_no_response() {
+        let fx = fixture();
+        let (response, shutdown) = handle_line(
+            &|| Ok(fx.context.clone()),
+            &lock_path(&fx),
+            r#"{"jsonrpc":"2.0","method":"status"}"#,
+        );
+        assert!(response.is_none());
+        assert!(!shutdown);
+    }
+
+    #[test]
+    fn test_status_reports_latent_entry() {
+        let fx = fixture();
+        let response = call(&fx, r#"{"jsonrpc":"2.0","id":1,"method":"status"}"#);
+        let entries = response["result"]["entries"].as_array().unwrap();
+        assert_eq!(entries.len(), 1);
+        assert_eq!(entries[0]["status"], "latent");
+        assert_eq!(entries[0]["type"], "F");
+    }
+
+    #[test]
+    fn test_diff_reports_added_file() {
+        let fx = fixture();
+        let response = call(&fx, r#"{"jsonrpc":"2.0","id":1,"method":"diff"}"#);
+        let files = response["result"]["files"].as_array().unwrap();
+        assert_eq!(files.len(), 1);
+        assert_eq!(files[0]["status"], "added");
+        assert!(files[0]["diff"].as_str().unwrap().contains("+export A=1"));
+    }
+
+    #[test]
+    fn test_apply_writes_files_and_reports() {
+        let fx = fixture();
+        let response = call(&fx, r#"{"jsonrpc":"2.0","id":1,"method":"apply"}"#);
+        assert_eq!(response["result"]["applied"], json!([".bashrc"]));
+        assert_eq!(
+            fs::read_to_string(fx.dest(".bashrc")).unwrap(),
+            "export A=1\n"
+        );
+
+        // Second apply has nothing left to do
+        let response = call(&fx, r#"{"jsonrpc":"2.0","id":2,"method":"apply"}"#);
+        assert_eq!(response["result"]["applied"], json!([]));
+    }
+
+    #[test]
+    fn test_apply_stamps_state_with_context_clock() {
+        let mut fx = fixture();
+        fx.context = fx
+            .context
+            .with_clock(guisu_engine::StateClock::fixed(1_700_000_000));
+        call(&fx, r#"{"jsonrpc":"2.0","id":1,"method":"apply"}"#);
+
+        let state = guisu_engine::database::get_entry_state(fx.context.database(), ".bashrc")
+            .unwrap()
+            .unwrap();
+        let stamp = state.last_applied.unwrap();
+        assert_eq!(stamp.run_id, "run-1");
+        assert_eq!(stamp.timestamp, 1_700_000_000);
+    }
+
+    #[test]
+    fn test_forced_apply_snapshots_local_changes() {
+        let fx = fixture();
+        call(&fx, r#"{"jsonrpc":"2.0","id":1,"method":"apply"}"#);
+        fs::write(fx.dest(".bashrc"), "export A=1\nalias ll='ls -l'\n").unwrap();
+
+        let response = call(&fx, r#"{"jsonrpc":"2.0","id":2,"method":"apply"}"#);
+        assert_eq!(response["result"]["skipped"], json!([".bashrc"]));
+        assert_eq!(response["result"]["snapshots"], json!([]));
+
+        let response = call(
+            &fx,
+            r#"{"jsonrpc":"2.0","id":3,"method":"apply","params":{"force":true}}"#,
+        );
+        let snapshots = response["result"]["snapshots"].as_array().unwrap();
+        assert_eq!(snapshots.len(), 1);
+        assert_eq!(snapshots[0]["path"], ".bashrc");
+
+        let id = snapshots[0]["id"].as_str().unwrap();
+        let snapshot = guisu_engine::database::get_conflict_snapshot(fx.context.database(), id)
+            .unwrap()
+            .unwrap();
+        assert_eq!(snapshot.content, b"export A=1\nalias ll='ls -l'\n");
+        assert_eq!(
+            fs::read_to_string(fx.dest(".bashrc")).unwrap(),
+            "export A=1\n"
+        );
+    }
+
+    #[test]
+    fn test_apply_waits_for_run_lock() {
+        let fx = fixture();
+        let lock = RunLock::acquire(&lock_path(&fx), "update", false).unwrap();
+
+        let response = call(&fx, r#"{"jsonrpc":"2.0","id":1,"method":"apply"}"#);
+        assert_eq!(response["error"]["code"], OPERATION_FAILED);
+        assert!(!fx.dest(".bashrc").exists());
+
+        // Read-only requests and dry runs do not need the lock
+        let response = call(&fx, r#"{"jsonrpc":"2.0","id":2,"method":"status"}"#);
+        assert!(response.get("error").is_none());
+        let response = call(
+            &fx,
+            r#"{"jsonrpc":"2.0","id":3,"method":"apply","params":{"dry_run":true}}"#,
+        );
+        assert!(response.get("error").is_none());
+
+        drop(lock);
+        let response = call(&fx, r#"{"jsonrpc":"2.0","id":4,"method":"apply"}"#);
+        assert_eq!(response["result"]["applied"], json!([".bashrc"]));
+    }
+
+    #[test]
+    fn test_apply_dry_run_does_not_write() {
+        let fx = fixture();
+        let response = call(
+            &fx,
+            r#"{"jsonrpc":"2.0","id":1,"method":"apply","params":{"dry_run":true}}"#,
+        );
+        assert_eq!(response["result"]["applied"], json!([".bashrc"]));
+        assert!(!fx.dest(".bashrc").exists());
+    }
+
+    #[test]
+    fn test_add_requires_files() {
+        let fx = fixture();
+        let response = call(
+            &fx,
+            r#"{"jsonrpc":"2.0","id":1,"method":"add","params":{"files":[]}}"#,
+        );
+        assert_eq!(response["error"]["code"], INVALID_PARAMS);
+    }
+
+    #[test]
+    fn test_database_open_only_during_requests() {
+        let (_temp, root, context) = fixture().detach();
+        let db_path = root.join("state.db");
+        let open = || {
+            let database = crate::open_database(&db_path)?;
+            Ok(context.attach(std::sync::Arc::new(database)))
+        };
+
+        for line in [
+            r#"{"jsonrpc":"2.0","id":1,"method":"apply"}"#,
+            r#"{"jsonrpc":"2.0","id":2,"method":"status"}"#,
+        ] {
+            let (response, _) = handle_line(&open, &root.join("guisu.lock"), line);
+            assert!(response.unwrap().error.is_none());
+            // Other guisu processes can open the database between requests
+            drop(guisu_engine::database::open(&db_path).unwrap());
+        }
+    }
+
+    #[test]
+    fn test_serve_stops_on_shutdown() {
+        let fx = fixture();
+        let input = concat!(
+            r#"{"jsonrpc":"2.0","id":1,"method":"shutdown"}"#,
+            "\n",
+            r#"{"jsonrpc":"2.0","id":2,"method":"status"}"#,
+            "\n"
+        );
+        let mut output = Vec::new();
+        serve(
+            &|| Ok(fx.context.clone()),
+            &lock_path(&fx),
+            input.as_bytes(),
+            &mut output,
+        )
+        .unwrap();
+
+        let lines: Vec<_> = String::from_utf8(output)
+            .unwrap()
+            .lines()
+            .map(String::from)
+            .collect();
+        assert_eq!(lines.len(), 1);
+        let response: Value = serde_json::from_str(&lines[0]).unwrap();
+        assert_eq!(response["id"], 1);
+        assert_eq!(response["result"], Value::Null);
+    }
+}
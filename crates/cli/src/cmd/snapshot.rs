@@ -0,0 +1,114 @@
+//! Snapshot command operations
+//!
+//! With `[apply] snapshots` set, every apply first saves the destination files
+//! it is about to change. This module provides commands for inspecting those
+//! snapshots; `guisu rollback` restores them:
+//! - list: List saved snapshots, oldest first
+//! - show: List the files saved in a snapshot
+
+use anyhow::{Context, Result, bail};
+use guisu_engine::state::{ApplySnapshot, RedbPersistentState};
+use owo_colors::OwoColorize;
+
+/// Run snapshot list command
+///
+/// # Errors
+///
+/// Returns an error if the snapshots cannot be read from the database
+pub fn run_list(db: &RedbPersistentState) -> Result<()> {
+    let snapshots = guisu_engine::database::get_apply_snapshots(db)
+        .context("Failed to read apply snapshots")?;
+
+    if snapshots.is_empty() {
+        println!("No apply snapshots saved.");
+        println!(
+            "{}",
+            "Set `snapshots = 10` under [apply] to keep the last 10.".dimmed()
+        );
+        return Ok(());
+    }
+
+    for (id, snapshot) in &snapshots {
+        let files = snapshot.files.len();
+        println!(
+            "{}  {}  {files} {}",
+            id.yellow(),
+            format_taken_at(snapshot).dimmed(),
+            if files == 1 { "file" } else { "files" }
+        );
+    }
+
+    Ok(())
+}
+
+/// Run snapshot show command
+///
+/// # Errors
+///
+/// Returns an error if no snapshot has the given ID
+pub fn run_show(db: &RedbPersistentState, id: &str) -> Result<()> {
+    let Some((_, snapshot)) = guisu_engine::database::get_apply_snapshots(db)
+        .context("Failed to read apply snapshots")?
+        .into_iter()
+        .find(|(snapshot_id, _)| snapshot_id == id)
+    else {
+        bail!(
+            "No apply snapshot with ID '{id}'. Run `guisu snapshot list` to see saved snapshots."
+        );
+    };
+
+    println!(
+        "{} {}",
+        "Taken before the apply at".bold(),
+        format_taken_at(&snapshot)
+    );
+    for file in &snapshot.files {
+        if file.content_hash.is_some() {
+            println!("  {} {}", "M".yellow(), file.path.bright_white());
+        } else {
+            println!(
+                "  {} {} {}",
+                "A".green(),
+                file.path.bright_white(),
+                "(did not exist)".dimmed()
+            );
+        }
+    }
+
+    Ok(())
+}
+
+/// Format when the snapshot was taken, in local time
+pub(crate) fn format_taken_at(snapshot: &ApplySnapshot) -> String {
+    chrono::DateTime::<chrono::Local>::from(snapshot.stamp.time())
+        .format("%Y-%m-%d %H:%M:%S")
+        .to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    #![allow(clippy::unwrap_used, clippy::panic)]
+    use super::*;
+    use guisu_engine::clock::StateClock;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_run_show_unknown_id() {
+        let temp = TempDir::new().unwrap();
+        let db = RedbPersistentState::new(temp.path().join("state.db")).unwrap();
+
+        let err = run_show(&db, "deadbeef").unwrap_err();
+        assert!(err.to_string().contains("deadbeef"));
+    }
+
+    #[test]
+    fn test_run_show_and_list_saved_snapshot() {
+        let temp = TempDir::new().unwrap();
+        let db = RedbPersistentState::new(temp.path().join("state.db")).unwrap();
+        let snapshot = ApplySnapshot::new(&StateClock::fixed(1_700_000_000).begin_run());
+        let id = guisu_engine::database::save_apply_snapshot(&db, &snapshot, 1).unwrap();
+
+        run_list(&db).unwrap();
+        run_show(&db, &id).unwrap();
+    }
+}
@@ -0,0 +1,508 @@
+//! State command operations
+//!
+//! Inspect and repair the state database that records what apply, hooks,
+//! and other commands did:
+//! - list: Count the records in each bucket, or list the keys of one
+//! - get: Print a record as JSON
+//! - delete: Remove a record, such as a `once` hook so it runs again
+//! - reset: Remove every record, or those of one bucket
+//! - migrate: Upgrade a database written by an older version
+//!
+//! The single hook state record is also addressed per hook, as
+//! `once/<name>` and `onchange/<name>` keys of the `hookState` bucket.
+
+use anyhow::{Context, Result, bail};
+use guisu_engine::external::ExternalCache;
+use guisu_engine::state::{
+    APPLY_SNAPSHOT_BUCKET, ApplySnapshot, BASE_CONTENT_BUCKET, BUCKETS, CONFIG_METADATA_BUCKET,
+    CONFLICT_SNAPSHOT_BUCKET, ConfigMetadata, ConflictSnapshot, DRIFT_EVENT_BUCKET, DriftEvent,
+    ENTRY_STATE_BUCKET, EXTERNAL_CACHE_BUCKET, EntryState, HOOK_LOG_BUCKET, HOOK_STATE_BUCKET,
+    HOOK_STATE_KEY, HookRunLog, HookState, HookStatePersistence, IDENTITY_HINT_BUCKET,
+    MANAGED_PATH_BUCKET, ManagedKind, PROMPT_ANSWER_BUCKET, PersistentState, RENDER_CACHE_BUCKET,
+    RedbPersistentState, RenderRecord, SNAPSHOT_CONTENT_BUCKET,
+};
+use owo_colors::OwoColorize;
+use serde_json::{Value, json};
+use std::path::Path;
+
+/// Run state list command
+///
+/// Without a bucket, prints every bucket with its number of records.
+///
+/// # Errors
+///
+/// Returns an error if the bucket is unknown or cannot be read
+pub fn run_list(db: &RedbPersistentState, bucket: Option<&str>) -> Result<()> {
+    let Some(bucket) = bucket else {
+        let version = guisu_engine::database::schema_version(db)?;
+        println!("{}", format!("Schema version {version}").dimmed());
+        for bucket in BUCKETS {
+            let count = keys(db, bucket)?.len();
+            println!(
+                "{:<18} {count} {}",
+                bucket.bright_white(),
+                if count == 1 { "record" } else { "records" }.dimmed()
+            );
+        }
+        return Ok(());
+    };
+
+    let bucket = find_bucket(bucket)?;
+    for key in keys(db, bucket)? {
+        println!("{key}");
+    }
+    if bucket == HOOK_STATE_BUCKET {
+        let state = HookStatePersistence::new(db)
+            .load()
+            .context("Failed to read hook state")?;
+        for key in hook_keys(&state) {
+            println!("{key}");
+        }
+    }
+    Ok(())
+}
+
+/// Run state get command
+///
+/// # Errors
+///
+/// Returns an error if the bucket is unknown or has no record with the key
+pub fn run_get(db: &RedbPersistentState, bucket: &str, key: &str) -> Result<()> {
+    let bucket = find_bucket(bucket)?;
+    let value = record_json(db, bucket, key)?;
+    println!(
+        "{}",
+        serde_json::to_string_pretty(&value).context("Failed to format record")?
+    );
+    Ok(())
+}
+
+/// Run state delete command
+///
+/// # Errors
+///
+/// Returns an error if the bucket is unknown, has no record with the key, or
+/// the record cannot be deleted
+pub fn run_delete(db: &RedbPersistentState, bucket: &str, key: &str) -> Result<()> {
+    let bucket = find_bucket(bucket)?;
+
+    if bucket == HOOK_STATE_BUCKET && key.as_bytes() != HOOK_STATE_KEY {
+        let persistence = HookStatePersistence::new(db);
+        let mut state = persistence.load().context("Failed to read hook state")?;
+        let removed = match key.split_once('/') {
+            Some(("once", name)) => state.once_executed.remove(name),
+            Some(("onchange", name)) => {
+                state.onchange_rendered.remove(name);
+                state.onchange_hashes.remove(name).is_some()
+            }
+            _ => false,
+        };
+        if !removed {
+            bail!(not_found(bucket, key));
+        }
+        persistence
+            .save(&state)
+            .context("Failed to save hook state")?;
+    } else {
+        let raw = raw_key(bucket, key);
+        if db.get(bucket, &raw)?.is_none() {
+            bail!(not_found(bucket, key));
+        }
+        db.delete(bucket, &raw)
+            .with_context(|| format!("Failed to delete {bucket}/{key}"))?;
+    }
+
+    println!("{} {bucket}/{key}", "Deleted".green());
+    Ok(())
+}
+
+/// Run state reset command
+///
+/// Deletes every bucket, or only `bucket`, in a single transaction after
+/// confirming unless `yes` is set.
+///
+/// # Errors
+///
+/// Returns an error if the bucket is unknown or the database cannot be written
+pub fn run_reset(db: &RedbPersistentState, bucket: Option<&str>, yes: bool) -> Result<()> {
+    let buckets = match bucket {
+        Some(bucket) => vec![find_bucket(bucket)?],
+        None => BUCKETS.to_vec(),
+    };
+
+    if !yes {
+        use dialoguer::{Confirm, theme::ColorfulTheme};
+
+        let prompt = match bucket {
+            Some(bucket) => format!("Delete every record in {bucket}?"),
+            None => "Delete all guisu state? Hooks with mode = \"once\" will run again".to_string(),
+        };
+        let confirmed = Confirm::with_theme(&ColorfulTheme::default())
+            .with_prompt(prompt)
+            .default(false)
+            .interact()?;
+        if !confirmed {
+            println!("Cancelled.");
+            return Ok(());
+        }
+    }
+
+    db.clear(&buckets).context("Failed to reset state")?;
+    println!(
+        "{} {}",
+        "Reset".green(),
+        bucket.unwrap_or("all state buckets")
+    );
+    Ok(())
+}
+
+/// Run state migrate command
+///
+/// With `dry_run`, the migrations run on a copy of the database.
+///
+/// # Errors
+///
+/// Returns an error if the database cannot be opened, is newer than
+/// supported, or a migration fails
+pub fn run_migrate(db_path: &Path, dry_run: bool) -> Result<()> {
+    use guisu_engine::database::{self, SCHEMA_VERSION};
+
+    let version = {
+        let db = RedbPersistentState::new(db_path).context("Failed to open state database")?;
+        database::schema_version(&db).context("Failed to read schema version")?
+    };
+    let pending = database::pending_migrations(version)?;
+
+    if pending.is_empty() {
+        println!("State database is up to date (schema version {version})");
+        if !dry_run {
+            // Records the version of databases that have none yet
+            database::open(db_path).context("Failed to open state database")?;
+        }
+        return Ok(());
+    }
+
+    println!(
+        "{} schema version {version} to {SCHEMA_VERSION}",
+        if dry_run {
+            "Would migrate"
+        } else {
+            "Migrating"
+        }
+        .bold()
+    );
+    for migration in &pending {
+        println!("  {} {}", migration.version.yellow(), migration.description);
+    }
+
+    if dry_run {
+        database::verify_migrations(db_path)
+            .context("Migration failed on a copy of the database")?;
+        println!(
+            "{} on a copy; the database was not changed",
+            "Migrations succeeded".green()
+        );
+    } else {
+        database::open(db_path).context("Failed to migrate state database")?;
+        println!(
+            "{} (backup: {})",
+            "Migrated".green(),
+            database::backup_path(db_path, version).display()
+        );
+    }
+    Ok(())
+}
+
+/// Look up a bucket by name, ignoring case
+fn find_bucket(name: &str) -> Result<&'static str> {
+    BUCKETS
+        .iter()
+        .copied()
+        .find(|bucket| bucket.eq_ignore_ascii_case(name))
+        .with_context(|| {
+            format!(
+                "Unknown bucket '{name}'. Known buckets: {}",
+                BUCKETS.join(", ")
+            )
+        })
+}
+
+/// Keys of the records in `bucket`, as displayed and accepted by the commands
+fn keys(db: &RedbPersistentState, bucket: &str) -> Result<Vec<String>> {
+    let mut keys = Vec::new();
+    db.for_each(bucket, |key, _| {
+        keys.push(display_key(bucket, key));
+        Ok(())
+    })
+    .with_context(|| format!("Failed to read {bucket}"))?;
+    Ok(keys)
+}
+
+/// Per-hook keys of the hook state record
+fn hook_keys(state: &HookState) -> Vec<String> {
+    let mut once: Vec<&String> = state.once_executed.iter().collect();
+    once.sort();
+    let mut onchange: Vec<&String> = state.onchange_hashes.keys().collect();
+    onchange.sort();
+
+    once.into_iter()
+        .map(|name| format!("once/{name}"))
+        .chain(onchange.into_iter().map(|name| format!("onchange/{name}")))
+        .collect()
+}
+
+/// Display form of a key: text, or hex for the content hashes of snapshots
+fn display_key(bucket: &str, key: &[u8]) -> String {
+    match std::str::from_utf8(key) {
+        Ok(text) if bucket != SNAPSHOT_CONTENT_BUCKET => text.to_string(),
+        _ => hex::encode(key),
+    }
+}
+
+/// Raw key for a key in display form
+fn raw_key(bucket: &str, key: &str) -> Vec<u8> {
+    if bucket == SNAPSHOT_CONTENT_BUCKET
+        && let Ok(bytes) = hex::decode(key)
+    {
+        return bytes;
+    }
+    key.as_bytes().to_vec()
+}
+
+fn not_found(bucket: &str, key: &str) -> String {
+    format!("No record '{key}' in {bucket}. Run `guisu state list {bucket}` to see its keys.")
+}
+
+/// Decode a record into JSON
+fn record_json(db: &RedbPersistentState, bucket: &str, key: &str) -> Result<Value> {
+    if bucket == HOOK_STATE_BUCKET && key.as_bytes() != HOOK_STATE_KEY {
+        let state = HookStatePersistence::new(db)
+            .load()
+            .context("Failed to read hook state")?;
+        return match key.split_once('/') {
+            Some(("once", name)) if state.once_executed.contains(name) => {
+                Ok(json!({ "mode": "once", "executed": true }))
+            }
+            Some(("onchange", name)) if state.onchange_hashes.contains_key(name) => Ok(json!({
+                "mode": "onchange",
+                "content_hash": hex::encode(state.onchange_hashes[name]),
+                "rendered": state.onchange_rendered.get(name),
+            })),
+            _ => bail!(not_found(bucket, key)),
+        };
+    }
+
+    let Some(bytes) = db.get(bucket, &raw_key(bucket, key))? else {
+        bail!(not_found(bucket, key));
+    };
+    Ok(decode(bucket, &bytes).unwrap_or_else(|| json!({ "undecoded_bytes": bytes.len() })))
+}
+
+/// Decode the value of a record in `bucket`, `None` if it has an unknown layout
+#[allow(clippy::too_many_lines)]
+fn decode(bucket: &str, bytes: &[u8]) -> Option<Value> {
+    let mode = |mode: Option<u32>| mode.map(|mode| format!("{mode:o}"));
+
+    Some(match bucket {
+        ENTRY_STATE_BUCKET => {
+            let state = EntryState::from_bytes(bytes)?;
+            json!({
+                "content_hash": hex::encode(state.content_hash),
+                "mode": mode(state.mode),
+                "last_applied": state.last_applied,
+            })
+        }
+        HOOK_STATE_BUCKET => {
+            let state = HookState::from_bytes(bytes)?;
+            let onchange: serde_json::Map<String, Value> = state
+                .onchange_hashes
+                .iter()
+                .map(|(name, hash)| (name.clone(), Value::String(hex::encode(hash))))
+                .collect();
+            let mut once: Vec<&String> = state.once_executed.iter().collect();
+            once.sort();
+            json!({
+                "last_executed": state.last_executed.map(|time| {
+                    time.duration_since(std::time::UNIX_EPOCH).map_or(0, |d| d.as_secs())
+                }),
+                "last_run_id": state.last_run_id,
+                "content_hash": state.content_hash.map(hex::encode),
+                "once_executed": once,
+                "onchange_hashes": onchange,
+            })
+        }
+        CONFIG_METADATA_BUCKET => {
+            let metadata = ConfigMetadata::from_bytes(bytes)?;
+            json!({
+                "template_hash": hex::encode(metadata.template_hash),
+                "rendered_config": metadata.rendered_config,
+            })
+        }
+        IDENTITY_HINT_BUCKET => json!({ "fingerprint": String::from_utf8_lossy(bytes) }),
+        CONFLICT_SNAPSHOT_BUCKET => {
+            let snapshot = ConflictSnapshot::from_bytes(bytes)?;
+            json!({
+                "path": snapshot.path,
+                "mode": mode(snapshot.mode),
+                "stamp": snapshot.stamp,
+                "content": content_json(&snapshot.content),
+            })
+        }
+        DRIFT_EVENT_BUCKET => {
+            let event = DriftEvent::from_bytes(bytes)?;
+            json!({ "path": event.path, "stamp": event.stamp, "diff": event.diff })
+        }
+        EXTERNAL_CACHE_BUCKET => {
+            let cache = ExternalCache::from_bytes(bytes)?;
+            json!({ "size": cache.content.len(), "stamp": cache.stamp })
+        }
+        PROMPT_ANSWER_BUCKET => serde_json::from_slice(bytes).ok()?,
+        HOOK_LOG_BUCKET => {
+            let log = HookRunLog::from_bytes(bytes)?;
+            let run = log.run;
+            json!({
+                "name": run.name,
+                "stage": run.stage,
+                "exit_code": run.exit_code,
+                "error": run.error,
+                "duration_ms": run.duration_ms,
+                "stdout": run.stdout,
+                "stderr": run.stderr,
+                "stamp": log.stamp,
+            })
+        }
+        RENDER_CACHE_BUCKET => {
+            let record = RenderRecord::from_bytes(bytes)?;
+            json!({
+                "input_hash": hex::encode(record.input_hash),
+                "content_hash": hex::encode(record.content_hash),
+                "dependencies": record
+                    .dependencies
+                    .iter()
+                    .map(|dep| json!({ "path": dep.path, "hash": hex::encode(dep.hash) }))
+                    .collect::<Vec<_>>(),
+            })
+        }
+        BASE_CONTENT_BUCKET => json!({ "content": content_json(bytes) }),
+        MANAGED_PATH_BUCKET => {
+            let kind = match ManagedKind::from_bytes(bytes)? {
+                ManagedKind::File => "file",
+                ManagedKind::Directory => "directory",
+                ManagedKind::Symlink => "symlink",
+            };
+            json!({ "kind": kind })
+        }
+        APPLY_SNAPSHOT_BUCKET => {
+            let snapshot = ApplySnapshot::from_bytes(bytes)?;
+            let files: Vec<Value> = snapshot
+                .files
+                .iter()
+                .map(|file| {
+                    json!({
+                        "path": file.path,
+                        "content_hash": file.content_hash.map(hex::encode),
+                        "mode": mode(file.mode),
+                    })
+                })
+                .collect();
+            json!({ "stamp": snapshot.stamp, "files": files })
+        }
+        SNAPSHOT_CONTENT_BUCKET => json!({ "compressed_size": bytes.len() }),
+        _ => return None,
+    })
+}
+
+/// File content as a string, or its size if it is not text
+fn content_json(content: &[u8]) -> Value {
+    match std::str::from_utf8(content) {
+        Ok(text) => Value::String(text.to_string()),
+        Err(_) => json!({ "binary_size": content.len() }),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    #![allow(clippy::unwrap_used, clippy::panic)]
+    use super::*;
+    use tempfile::TempDir;
+
+    fn test_db() -> (TempDir, RedbPersistentState) {
+        let temp = TempDir::new().unwrap();
+        let db = RedbPersistentState::new(temp.path().join("state.db")).unwrap();
+        (temp, db)
+    }
+
+    #[test]
+    fn test_get_and_delete_entry_state() {
+        let (_temp, db) = test_db();
+        let state = EntryState::new(b"hello", Some(0o644));
+        db.set(ENTRY_STATE_BUCKET, b".bashrc", &state.to_bytes().unwrap())
+            .unwrap();
+
+        assert_eq!(keys(&db, ENTRY_STATE_BUCKET).unwrap(), [".bashrc"]);
+        let value = record_json(&db, ENTRY_STATE_BUCKET, ".bashrc").unwrap();
+        assert_eq!(value["mode"], "644");
+        assert_eq!(value["content_hash"], hex::encode(state.content_hash));
+
+        run_delete(&db, "entrystate", ".bashrc").unwrap();
+        assert_eq!(keys(&db, ENTRY_STATE_BUCKET).unwrap(), Vec::<String>::new());
+        assert!(run_delete(&db, ENTRY_STATE_BUCKET, ".bashrc").is_err());
+    }
+
+    #[test]
+    fn test_delete_once_hook() {
+        let (_temp, db) = test_db();
+        let persistence = HookStatePersistence::new(&db);
+        let mut state = HookState::new();
+        state.mark_executed_once("install".to_string());
+        state.update_onchange_hash("brew".to_string(), [1; 32]);
+        persistence.save(&state).unwrap();
+
+        assert_eq!(
+            hook_keys(&persistence.load().unwrap()),
+            ["once/install", "onchange/brew"]
+        );
+        assert_eq!(
+            record_json(&db, HOOK_STATE_BUCKET, "once/install").unwrap()["executed"],
+            true
+        );
+
+        run_delete(&db, HOOK_STATE_BUCKET, "once/install").unwrap();
+        let state = persistence.load().unwrap();
+        assert!(!state.has_executed_once("install"));
+        assert!(state.onchange_hashes.contains_key("brew"));
+        assert!(run_delete(&db, HOOK_STATE_BUCKET, "once/install").is_err());
+    }
+
+    #[test]
+    fn test_reset_one_bucket() {
+        let (_temp, db) = test_db();
+        db.set(IDENTITY_HINT_BUCKET, b"secret.age", b"fingerprint")
+            .unwrap();
+        db.set(
+            MANAGED_PATH_BUCKET,
+            b".bashrc",
+            &ManagedKind::File.to_bytes().unwrap(),
+        )
+        .unwrap();
+
+        run_reset(&db, Some(IDENTITY_HINT_BUCKET), true).unwrap();
+        assert_eq!(
+            keys(&db, IDENTITY_HINT_BUCKET).unwrap(),
+            Vec::<String>::new()
+        );
+        assert_eq!(keys(&db, MANAGED_PATH_BUCKET).unwrap(), [".bashrc"]);
+
+        run_reset(&db, None, true).unwrap();
+        assert_eq!(
+            keys(&db, MANAGED_PATH_BUCKET).unwrap(),
+            Vec::<String>::new()
+        );
+    }
+
+    #[test]
+    fn test_unknown_bucket() {
+        let err = find_bucket("nope").unwrap_err();
+        assert!(err.to_string().contains(ENTRY_STATE_BUCKET));
+    }
+}
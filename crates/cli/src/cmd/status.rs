@@ -4,38 +4,47 @@
 
 use anyhow::{Context, Result};
 use clap::Args;
-use guisu_core::path::{AbsPath, RelPath};
-use guisu_engine::adapters::crypto::CryptoDecryptorAdapter;
-use guisu_engine::adapters::template::TemplateRendererAdapter;
+use guisu_core::path::RelPath;
+use guisu_engine::adapters::crypto::{CryptoDecryptorAdapter, IdentityHints};
+use guisu_engine::adapters::template::{TemplateRendererAdapter, template_context};
+use guisu_engine::attr::FileAttributes;
 use guisu_engine::entry::TargetEntry;
+use guisu_engine::pool::ContentPool;
 use guisu_engine::processor::ContentProcessor;
-use guisu_engine::state::{DestinationState, RedbPersistentState, SourceState, TargetState};
+use guisu_engine::state::{
+    DestinationState, RedbPersistentState, RenderCache, SourceState, TargetState,
+};
 use guisu_engine::system::RealSystem;
 use owo_colors::OwoColorize;
 use rayon::prelude::*;
-use std::collections::BTreeMap;
+use std::collections::{BTreeMap, HashMap};
 use std::io::IsTerminal;
 use std::path::Path;
 use std::path::PathBuf;
 use std::sync::Arc;
 use tracing::debug;
 
+use crate::cmd::diff::{ModeChange, is_binary};
 use crate::command::Command;
-use crate::common::RuntimeContext;
+use crate::common::{EntryFilter, ResolvedPaths, RuntimeContext};
 use crate::conflict::{ThreeWayComparisonResult, compare_three_way};
 use crate::ui::icons::{FileIconInfo, icon_for_file};
-use crate::utils::path::SourceDirExt;
-use guisu_config::Config;
+use crate::utils::hygiene::{check_source_hygiene, fix_source};
+use guisu_config::{ApplyMode, Config};
 use lscolors::{LsColors, Style};
 use nu_ansi_term::Style as AnsiStyle;
 
 /// Output format for status command
-#[derive(Debug, Clone, Copy, PartialEq)]
+#[derive(Debug, Clone, Copy, PartialEq, clap::ValueEnum)]
 pub enum OutputFormat {
     /// Simple list format
     Simple,
     /// Tree structure format
     Tree,
+    /// JSON object with one record per entry
+    Json,
+    /// One `<status letter> <path>` line per entry, for scripts and prompts
+    Porcelain,
 }
 
 impl std::str::FromStr for OutputFormat {
@@ -45,13 +54,18 @@ impl std::str::FromStr for OutputFormat {
         match s.to_lowercase().as_str() {
             "simple" => Ok(OutputFormat::Simple),
             "tree" => Ok(OutputFormat::Tree),
-            _ => anyhow::bail!("Invalid output format: {s}. Use 'simple' or 'tree'"),
+            "json" => Ok(OutputFormat::Json),
+            "porcelain" => Ok(OutputFormat::Porcelain),
+            _ => anyhow::bail!(
+                "Invalid output format: {s}. Use 'simple', 'tree', 'json', or 'porcelain'"
+            ),
         }
     }
 }
 
-#[derive(Debug, PartialEq, Clone, Copy)]
-enum FileStatus {
+#[derive(Debug, PartialEq, Clone, Copy, serde::Serialize)]
+#[serde(rename_all = "lowercase")]
+pub(crate) enum FileStatus {
     /// File exists in source but not in dest (pending deployment)
     Latent,
     /// Destination is ahead of source (local modifications)
@@ -62,6 +76,8 @@ enum FileStatus {
     Conflict,
     /// Files are in steady state (fully synced)
     Steady,
+    /// Entry could not be rendered or read
+    Error,
 }
 
 impl FileStatus {
@@ -72,6 +88,19 @@ impl FileStatus {
             FileStatus::Behind => "[B]",
             FileStatus::Conflict => "[C]",
             FileStatus::Steady => "[S]",
+            FileStatus::Error => "[E]",
+        }
+    }
+
+    /// Letter of the status in porcelain output
+    fn code(self) -> char {
+        match self {
+            FileStatus::Latent => 'L',
+            FileStatus::Ahead => 'A',
+            FileStatus::Behind => 'B',
+            FileStatus::Conflict => 'C',
+            FileStatus::Steady => 'S',
+            FileStatus::Error => 'E',
         }
     }
 
@@ -82,6 +111,7 @@ impl FileStatus {
             FileStatus::Behind => "[B]ehind",
             FileStatus::Conflict => "[C]onflict",
             FileStatus::Steady => "[S]teady",
+            FileStatus::Error => "[E]rror",
         }
     }
 
@@ -92,19 +122,44 @@ impl FileStatus {
             FileStatus::Ahead => text.bright_cyan().to_string(),   // Cyan: local changes
             FileStatus::Conflict => text.bright_red().to_string(), // Red: conflict
             FileStatus::Steady => text.bright_blue().to_string(),  // Blue: steady
+            FileStatus::Error => text.red().to_string(),           // Red: failed
         }
     }
 }
 
 /// Complete file information for display
-#[derive(Debug)]
-struct FileInfo {
-    path: String,
-    status: FileStatus,
-    file_type: char,
+#[derive(Debug, serde::Serialize)]
+pub(crate) struct FileInfo {
+    pub(crate) path: String,
+    pub(crate) status: FileStatus,
+    #[serde(rename = "type")]
+    pub(crate) file_type: char,
+    /// Source attributes of files and directories
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub(crate) attributes: Option<FileAttributes>,
+    /// Permission change the next apply would make
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub(crate) mode: Option<ModeChange>,
+    /// Whether the target or destination content is binary
+    pub(crate) binary: bool,
+    /// Why the entry could not be rendered or read
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub(crate) error: Option<String>,
 }
 
 impl FileInfo {
+    fn new(path: String, status: FileStatus, file_type: char) -> Self {
+        Self {
+            path,
+            status,
+            file_type,
+            attributes: None,
+            mode: None,
+            binary: false,
+            error: None,
+        }
+    }
+
     fn status_str(&self) -> String {
         let label = self.status.label();
         self.status.color_str(label)
@@ -113,6 +168,7 @@ impl FileInfo {
 
 /// Status command
 #[derive(Args)]
+#[allow(clippy::struct_excessive_bools)]
 pub struct StatusCommand {
     /// Specific files to check (all if not specified)
     pub files: Vec<PathBuf>,
@@ -124,40 +180,173 @@ pub struct StatusCommand {
     /// Display output in tree format
     #[arg(long)]
     pub tree: bool,
+
+    /// Output format (`--tree` and `--porcelain` are shorthands)
+    #[arg(long, value_enum, conflicts_with_all = ["tree", "porcelain"])]
+    pub format: Option<OutputFormat>,
+
+    /// Print one `<status letter> <path>` line per entry (`--format porcelain`)
+    #[arg(long, conflicts_with = "tree")]
+    pub porcelain: bool,
+
+    /// Compare hashes recorded by the last apply instead of rendering
+    /// templates and decrypting files (needs `[apply] incremental`)
+    #[arg(long, conflicts_with = "lint")]
+    pub quick: bool,
+
+    /// Check source files for CRLF line endings or missing trailing newlines
+    /// that differ from the deployed files
+    #[arg(long)]
+    pub lint: bool,
+
+    /// Rewrite source files flagged by --lint
+    #[arg(long, requires = "lint")]
+    pub fix: bool,
+
+    /// Include only these entry types or target path globs (comma-separated)
+    #[arg(long, value_delimiter = ',')]
+    pub include: Vec<String>,
+
+    /// Exclude these entry types or target path globs (comma-separated)
+    #[arg(long, value_delimiter = ',')]
+    pub exclude: Vec<String>,
 }
 
 impl Command for StatusCommand {
     type Output = ();
     fn execute(&self, context: &RuntimeContext) -> crate::error::Result<()> {
-        let output_format = if self.tree {
-            OutputFormat::Tree
-        } else {
-            OutputFormat::Simple
-        };
+        if self.lint {
+            return run_lint(context, &self.files, self.fix).map_err(Into::into);
+        }
+        if self.quick && !context.config.apply.incremental {
+            return Err(anyhow::anyhow!(
+                "--quick compares against render records, which [apply] incremental = false turns off"
+            )
+            .into());
+        }
+
+        let entry_filter = EntryFilter::new(&self.include, &self.exclude)?;
         run_impl(
             context.database(),
             context.source_dir(),
             context.dest_dir().as_path(),
             &context.config,
             &self.files,
+            &entry_filter,
             self.all,
-            output_format,
+            self.quick,
+            self.output_format(),
         )
         .map_err(Into::into)
     }
 }
 
+impl StatusCommand {
+    /// Output format selected by `--format`, `--tree`, or `--porcelain`
+    #[must_use]
+    pub fn output_format(&self) -> OutputFormat {
+        match self.format {
+            Some(format) => format,
+            None if self.tree => OutputFormat::Tree,
+            None if self.porcelain => OutputFormat::Porcelain,
+            None => OutputFormat::Simple,
+        }
+    }
+}
+
+/// Report (and optionally fix) source hygiene issues
+fn run_lint(context: &RuntimeContext, files: &[PathBuf], fix: bool) -> Result<()> {
+    let dest_abs = context.dest_dir();
+    let source_state = SourceState::read(context.dotfiles_dir().to_owned())
+        .context("Failed to read source state")?;
+
+    let filter_paths = if files.is_empty() {
+        None
+    } else {
+        Some(crate::build_filter_paths(
+            files,
+            dest_abs,
+            context.targets(),
+        )?)
+    };
+
+    let findings = check_source_hygiene(
+        &source_state,
+        |path| context.paths.dest_path(path),
+        filter_paths.as_deref(),
+    );
+
+    if findings.is_empty() {
+        println!("{}", "No source hygiene issues found.".green());
+        return Ok(());
+    }
+
+    for finding in &findings {
+        let issues: Vec<_> = finding
+            .issues
+            .iter()
+            .map(|issue| issue.description())
+            .collect();
+
+        if fix {
+            fix_source(finding)?;
+            println!(
+                "  {} {}: fixed {}",
+                "✓".green(),
+                finding.target_path,
+                issues.join(", ")
+            );
+        } else {
+            println!(
+                "  {} {}: {}",
+                "⚠".yellow(),
+                finding.target_path,
+                issues.join(", ")
+            );
+        }
+    }
+
+    if !fix {
+        println!(
+            "
+{} source file(s) differ from the deployed line ending policy. \
+             Run {} to rewrite them.",
+            findings.len(),
+            "guisu status --lint --fix".cyan()
+        );
+    }
+
+    Ok(())
+}
+
+/// Target content of files known only by hash, `None` for files whose inputs
+/// changed since the last apply
+type QuickHashes = HashMap<RelPath, Option<[u8; 32]>>;
+
 /// Build target state from source state for status command
+///
+/// Entries that fail to render are left out of the target state; their error
+/// messages are returned alongside it. Files unchanged since the last apply
+/// are taken from `render_cache` without being processed. With `quick`, files
+/// the cache applies to are never processed: they are left out of the target
+/// state too, and the hashes of their output are returned instead.
+#[allow(clippy::too_many_arguments, clippy::too_many_lines)]
 fn build_status_target_state(
     source_state: &SourceState,
     processor: &ContentProcessor<CryptoDecryptorAdapter, TemplateRendererAdapter>,
     template_ctx_value: &serde_json::Value,
     filter_paths: Option<&Vec<RelPath>>,
     identities: &[guisu_crypto::Identity],
-) -> TargetState {
+    mode: ApplyMode,
+    render_cache: Option<&RenderCache>,
+    quick: bool,
+) -> (TargetState, HashMap<RelPath, String>, QuickHashes) {
     use guisu_engine::entry::SourceEntry;
 
     let mut target_state = TargetState::new();
+    let mut errors = HashMap::new();
+    let mut quick_hashes = HashMap::new();
+    let pool = ContentPool::new();
 
     for source_entry in source_state.entries() {
         let target_path = source_entry.target_path();
@@ -169,6 +358,14 @@ fn build_status_target_state(
             continue;
         }
 
+        if let Some(link_target) = source_state.link_target(source_entry, mode) {
+            target_state.add(TargetEntry::Symlink {
+                path: target_path.clone(),
+                target: link_target.as_path().to_path_buf(),
+            });
+            continue;
+        }
+
         // Process this entry manually to handle errors gracefully
         match source_entry {
             SourceEntry::File {
@@ -176,8 +373,39 @@ fn build_status_target_state(
                 target_path,
                 attributes,
             } => {
-                let abs_source_path = source_state.source_file_path(source_path);
-                match processor.process_file(&abs_source_path, attributes, template_ctx_value) {
+                if quick
+                    && let Some(cache) = render_cache
+                    && RenderCache::applies_to(*attributes)
+                {
+                    match cache.cached_hash(source_state, source_path, target_path, *attributes) {
+                        Ok(hash) => {
+                            quick_hashes.insert(target_path.clone(), hash);
+                        }
+                        Err(e) => {
+                            errors.insert(target_path.clone(), e.to_string());
+                        }
+                    }
+                    continue;
+                }
+
+                let processed = match render_cache {
+                    Some(cache) if RenderCache::applies_to(*attributes) => cache
+                        .process_file(
+                            source_state,
+                            source_path,
+                            target_path,
+                            *attributes,
+                            processor,
+                            template_ctx_value,
+                        )
+                        .map(|(content, _)| content),
+                    _ => processor.process_file(
+                        &source_state.source_file_path(source_path),
+                        attributes,
+                        template_ctx_value,
+                    ),
+                };
+                match processed {
                     Ok(mut content) => {
                         // Decrypt inline age: values (sops-like behavior)
                         if !identities.is_empty()
@@ -190,7 +418,7 @@ fn build_status_target_state(
                         }
 
                         let mode = attributes.mode();
-                        let content_hash = guisu_engine::hash::hash_content(&content);
+                        let (content, content_hash) = pool.intern(content);
                         target_state.add(TargetEntry::File {
                             path: target_path.clone(),
                             content,
@@ -204,6 +432,7 @@ fn build_status_target_state(
                             target_path.as_path().display(),
                             e
                         );
+                        errors.insert(target_path.clone(), e.to_string());
                     }
                 }
             }
@@ -219,34 +448,156 @@ fn build_status_target_state(
                 });
             }
             SourceEntry::Symlink {
-                source_path: _,
+                source_path,
                 target_path,
-                link_target,
-            } => {
-                target_state.add(TargetEntry::Symlink {
+                attributes,
+            } => match source_state.symlink_target(
+                source_path,
+                attributes,
+                processor,
+                template_ctx_value,
+            ) {
+                Ok(target) => target_state.add(TargetEntry::Symlink {
                     path: target_path.clone(),
-                    target: link_target.clone(),
-                });
-            }
+                    target,
+                }),
+                Err(e) => {
+                    debug!(
+                        "Warning: Failed to process {}: {}",
+                        target_path.as_path().display(),
+                        e
+                    );
+                    errors.insert(target_path.clone(), e.to_string());
+                }
+            },
+            // Scripts are run during apply, not written to the destination
+            SourceEntry::Script { .. } => {}
         }
     }
 
-    target_state
+    (target_state, errors, quick_hashes)
 }
 
 /// Run the status command implementation
+#[allow(clippy::too_many_arguments)]
 fn run_impl(
     database: &std::sync::Arc<guisu_engine::state::RedbPersistentState>,
     source_dir: &Path,
     dest_dir: &Path,
     config: &Config,
     files: &[PathBuf],
+    entry_filter: &EntryFilter,
     show_all: bool,
+    quick: bool,
     output_format: OutputFormat,
 ) -> Result<()> {
     // Initialize lscolors from environment
     let lscolors = LsColors::from_env().unwrap_or_default();
 
+    let Some(mut file_infos) = collect_status(
+        database,
+        source_dir,
+        dest_dir,
+        config,
+        files,
+        entry_filter,
+        quick,
+    )?
+    else {
+        if output_format == OutputFormat::Json {
+            println!("{}", serde_json::json!({ "entries": [] }));
+        }
+        return Ok(());
+    };
+
+    if output_format == OutputFormat::Json {
+        let is_single_file = files.len() == 1;
+        file_infos.retain(|f| {
+            f.file_type != 'D' && (show_all || is_single_file || f.status != FileStatus::Steady)
+        });
+        println!(
+            "{}",
+            serde_json::to_string_pretty(&serde_json::json!({ "entries": file_infos }))
+                .context("Failed to serialize status")?
+        );
+        return Ok(());
+    }
+
+    if output_format == OutputFormat::Porcelain {
+        for line in porcelain_lines(&file_infos, show_all || files.len() == 1) {
+            println!("{line}");
+        }
+        return Ok(());
+    }
+
+    // Entries that failed to render are only reported in JSON and porcelain output
+    file_infos.retain(|f| f.status != FileStatus::Error);
+
+    if !files.is_empty() && file_infos.is_empty() {
+        println!("No matching files found.");
+        return Ok(());
+    }
+
+    // Check if we're viewing a single file (don't show summary header)
+    let is_single_file = !files.is_empty() && files.len() == 1;
+
+    // Detect if output is to a terminal for icon auto mode
+    let is_tty = std::io::stdout().is_terminal();
+    let show_icons = config.ui.icons.should_show_icons(is_tty);
+
+    // Render output based on format
+    match output_format {
+        OutputFormat::Simple => {
+            render_simple(&file_infos, show_all, is_single_file, &lscolors, show_icons);
+        }
+        OutputFormat::Tree => {
+            render_tree(&file_infos, show_all, is_single_file, &lscolors, show_icons);
+        }
+        OutputFormat::Json | OutputFormat::Porcelain => {
+            unreachable!("JSON and porcelain output are handled above")
+        }
+    }
+
+    // Check and display hooks status
+    let paths = ResolvedPaths::resolve(source_dir, dest_dir, config)?;
+    print_hooks_status(&paths, database, show_all, config);
+
+    Ok(())
+}
+
+/// Porcelain lines, `<status letter> <path>`, of entries other than directories
+///
+/// Steady entries are only included with `show_all`.
+fn porcelain_lines(files: &[FileInfo], show_all: bool) -> Vec<String> {
+    files
+        .iter()
+        .filter(|f| f.file_type != 'D' && (show_all || f.status != FileStatus::Steady))
+        .map(|f| format!("{} {}", f.status.code(), f.path))
+        .collect()
+}
+
+/// Compute the status of every managed entry without printing anything
+///
+/// Only entries selected by `entry_filter` are included. Returns `None` when the
+/// source state is empty, and an empty list when none of the requested files
+/// are managed. Entries are sorted by display path. With `quick`, templates and
+/// encrypted files are compared by the hashes recorded by the last apply
+/// instead of being processed; those whose inputs changed since are reported
+/// as behind, or as conflicts if their destination changed too.
+///
+/// # Errors
+///
+/// Returns an error if paths cannot be resolved or the source state, metadata,
+/// ignore patterns, or variables cannot be loaded.
+pub(crate) fn collect_status(
+    database: &std::sync::Arc<guisu_engine::state::RedbPersistentState>,
+    source_dir: &Path,
+    dest_dir: &Path,
+    config: &Config,
+    files: &[PathBuf],
+    entry_filter: &EntryFilter,
+    quick: bool,
+) -> Result<Option<Vec<FileInfo>>> {
     // Resolve all paths (handles root_entry and canonicalization)
     let paths = crate::common::ResolvedPaths::resolve(source_dir, dest_dir, config)?;
     let source_abs = &paths.dotfiles_dir;
@@ -256,92 +607,85 @@ fn run_impl(
     let metadata =
         guisu_engine::state::Metadata::load(source_dir).context("Failed to load metadata")?;
 
-    // Create ignore matcher from .guisu/ignores.toml
+    // Create ignore matcher from .guisu/ignores.toml and .guisuignore files
     // Use dotfiles_dir as the match root so patterns match relative to the dotfiles directory
-    let ignore_matcher = guisu_config::IgnoreMatcher::from_ignores_toml(source_dir)
-        .context("Failed to load ignore patterns from .guisu/ignores.toml")?;
+    let ignore_matcher = crate::load_ignore_matcher(source_dir, source_abs.as_path(), config)?;
 
     // Read source state with ignore matcher from config
-    let source_state =
-        SourceState::read(source_abs.to_owned()).context("Failed to read source state")?;
+    let mut source_state =
+        SourceState::read_with_matcher(source_abs.to_owned(), Some(&ignore_matcher))
+            .context("Failed to read source state")?;
+    entry_filter.retain(&mut source_state);
 
     if source_state.is_empty() {
-        return Ok(());
+        return Ok(None);
     }
 
     // Load age identities for decryption
     let identities = std::sync::Arc::new(config.age_identities().unwrap_or_default());
 
-    // Load variables from .guisu/variables/ directory
-    let guisu_dir = source_dir.guisu_dir();
-    let platform_name = guisu_core::platform::CURRENT_PLATFORM.os;
-
-    let guisu_variables = if guisu_dir.exists() {
-        guisu_config::variables::load_variables(&guisu_dir, platform_name)
-            .context("Failed to load variables from .guisu/variables/")?
-    } else {
-        indexmap::IndexMap::new()
-    };
-
-    // Merge variables: guisu variables + config variables (config overrides)
-    let mut all_variables = guisu_variables;
-    all_variables.extend(config.variables.clone());
-
     // Create template engine with identities, template directory, and bitwarden provider
-    let template_engine = crate::create_template_engine(source_dir, &identities, config);
+    let template_engine = crate::create_template_engine(source_dir, &identities, config)?;
 
     // Create content processor with real decryptor and renderer
-    // Use Arc to share identity without unnecessary cloning
-    let identity_arc = identities.first().map_or_else(
-        || Arc::new(guisu_crypto::Identity::generate()),
-        |id| Arc::new(id.clone()),
-    );
-    let decryptor = CryptoDecryptorAdapter::from_arc(identity_arc);
+    // Status is read-only: hints recorded by `apply` are consulted but not updated
+    let identity_hints = IdentityHints::load(database).unwrap_or_default();
+    let decryptor = CryptoDecryptorAdapter::from_identities(Arc::clone(&identities))
+        .with_hints(Arc::new(identity_hints));
     let renderer = TemplateRendererAdapter::new(template_engine);
-    let processor = ContentProcessor::new(decryptor, renderer);
+    let processor = ContentProcessor::new(decryptor, renderer).with_eol(config.general.eol);
 
     // Build filter paths if specific files were requested
     let filter_paths = if files.is_empty() {
         None
     } else {
-        let paths = crate::build_filter_paths(files, dest_abs)?;
+        let filter = crate::build_filter_paths(files, dest_abs, &paths.targets)?;
         // Check if any files match
         let has_matches = source_state
             .entries()
-            .any(|entry| paths.iter().any(|p| p == entry.target_path()));
+            .any(|entry| filter.iter().any(|p| p == entry.target_path()));
 
         if !has_matches {
-            println!("No matching files found.");
-            return Ok(());
+            return Ok(Some(Vec::new()));
         }
-        Some(paths)
+        Some(filter)
     };
 
     // Build target state (processes templates and decrypts files)
     // Process files one by one to handle errors gracefully
     // Create template context with system variables and guisu info
-    let working_tree = guisu_engine::git::find_working_tree(source_dir)
-        .unwrap_or_else(|| source_dir.to_path_buf());
-    let template_context = guisu_template::TemplateContext::with_guisu_context(
-        source_abs.to_string(),
-        working_tree.display().to_string(),
-        dest_abs.to_string(),
-        config.general.root_entry.display().to_string(),
-        all_variables,
-    );
+    let template_context =
+        template_context(source_dir, source_abs.as_path(), dest_abs.as_path(), config)
+            .context("Failed to load variables")?;
     let template_ctx_value =
         serde_json::to_value(&template_context).context("Failed to serialize template context")?;
 
-    let target_state = build_status_target_state(
+    // Status is read-only: render records saved by `apply` are used but not updated
+    let render_cache = crate::cmd::apply::load_render_cache(
+        &crate::cmd::apply::RenderCacheInputs {
+            database,
+            source_dir,
+            identities: &identities,
+        },
+        &paths,
+        &template_ctx_value,
+        config,
+    );
+    let (mut target_state, errors, quick_hashes) = build_status_target_state(
         &source_state,
         &processor,
         &template_ctx_value,
         filter_paths.as_ref(),
         &identities,
+        config.apply.mode,
+        render_cache.as_ref(),
+        quick,
     );
+    target_state.normalize_link_targets(config.apply.symlink_targets, |path| paths.dest_path(path));
 
     // Read destination state
-    let mut dest_state = DestinationState::new(dest_abs.to_owned());
+    let mut dest_state =
+        DestinationState::new(dest_abs.to_owned()).with_targets(paths.targets.clone());
     let system = RealSystem;
 
     // Collect file information
@@ -349,35 +693,17 @@ fn run_impl(
         database,
         source_state: &source_state,
         target_state: &target_state,
+        quick_hashes: &quick_hashes,
+        errors: &errors,
         dest_state: &mut dest_state,
         system: &system,
-        dest_root: dest_abs,
+        paths: &paths,
         metadata: &metadata,
         filter_paths: filter_paths.as_ref(),
         ignore_matcher: &ignore_matcher,
     });
 
-    // Check if we're viewing a single file (don't show summary header)
-    let is_single_file = !files.is_empty() && files.len() == 1;
-
-    // Detect if output is to a terminal for icon auto mode
-    let is_tty = std::io::stdout().is_terminal();
-    let show_icons = config.ui.icons.should_show_icons(is_tty);
-
-    // Render output based on format
-    match output_format {
-        OutputFormat::Simple => {
-            render_simple(&file_infos, show_all, is_single_file, &lscolors, show_icons);
-        }
-        OutputFormat::Tree => {
-            render_tree(&file_infos, show_all, is_single_file, &lscolors, show_icons);
-        }
-    }
-
-    // Check and display hooks status
-    print_hooks_status(source_dir, database, show_all, config);
-
-    Ok(())
+    Ok(Some(file_infos))
 }
 
 /// Parameters for collecting file information
@@ -385,9 +711,11 @@ struct CollectParams<'a> {
     database: &'a std::sync::Arc<guisu_engine::state::RedbPersistentState>,
     source_state: &'a SourceState,
     target_state: &'a TargetState,
+    quick_hashes: &'a QuickHashes,
+    errors: &'a HashMap<RelPath, String>,
     dest_state: &'a mut DestinationState,
     system: &'a RealSystem,
-    dest_root: &'a AbsPath,
+    paths: &'a ResolvedPaths,
     metadata: &'a guisu_engine::state::Metadata,
     filter_paths: Option<&'a Vec<RelPath>>,
     ignore_matcher: &'a guisu_config::IgnoreMatcher,
@@ -400,12 +728,13 @@ fn get_entry_file_type(entry: &guisu_engine::entry::SourceEntry) -> char {
         SourceEntry::File { .. } => 'F',
         SourceEntry::Directory { .. } => 'D',
         SourceEntry::Symlink { .. } => 'L',
+        SourceEntry::Script { .. } => 'S',
     }
 }
 
 /// Format path for display with ~/ prefix if under home directory
-fn format_display_path(dest_root: &AbsPath, target_path: &RelPath) -> String {
-    let full_dest_path = dest_root.join(target_path);
+fn format_display_path(paths: &ResolvedPaths, target_path: &RelPath) -> String {
+    let full_dest_path = paths.dest_path(target_path);
     if let Some(home_dir) = dirs::home_dir() {
         // If path is under home, show as ~/relative/path
         if let Ok(rel_path) = full_dest_path.as_path().strip_prefix(&home_dir) {
@@ -433,39 +762,15 @@ fn determine_entry_status(
         .flatten();
 
     match target_entry {
-        TargetEntry::File { content, mode, .. } => {
-            // Compute hashes for three-way comparison
-            use guisu_engine::state::hash_data;
-            let source_hash = hash_data(content);
-            let dest_hash = dest_entry.content.as_ref().map(|c| hash_data(c));
-
-            // Check mode matches
-            let mode_matches = if let Some(expected_mode) = mode {
-                dest_entry.mode == Some(*expected_mode)
-            } else {
-                true
-            };
-
-            // Use unified three-way comparison
-            let dest_hash_vec = dest_hash.unwrap_or_default();
-            let base_hash = base_state.as_ref().map(|s| s.content_hash.as_slice());
-
-            let comparison_result = compare_three_way(&source_hash, &dest_hash_vec, base_hash);
-
-            // Map comparison result to file status
-            match comparison_result {
-                ThreeWayComparisonResult::NoChange | ThreeWayComparisonResult::Converged => {
-                    if mode_matches {
-                        FileStatus::Steady
-                    } else {
-                        FileStatus::Behind // Mode changed
-                    }
-                }
-                ThreeWayComparisonResult::SourceChanged => FileStatus::Behind,
-                ThreeWayComparisonResult::DestinationChanged => FileStatus::Ahead,
-                ThreeWayComparisonResult::BothChanged => FileStatus::Conflict,
-            }
-        }
+        TargetEntry::File { content, mode, .. } => file_status(
+            &guisu_engine::state::hash_data(content),
+            *mode,
+            dest_entry,
+            base_state.as_ref(),
+        ),
+        TargetEntry::LargeFile {
+            content_hash, mode, ..
+        } => file_status(content_hash, *mode, dest_entry, base_state.as_ref()),
         TargetEntry::Directory { mode, .. } => {
             if let Some(expected_mode) = mode {
                 if dest_entry.mode == Some(*expected_mode) {
@@ -491,6 +796,65 @@ fn determine_entry_status(
     }
 }
 
+/// Status of a file from the hash of its target content
+fn file_status(
+    source_hash: &[u8; 32],
+    mode: Option<u32>,
+    dest_entry: &guisu_engine::entry::DestEntry,
+    base_state: Option<&guisu_engine::state::EntryState>,
+) -> FileStatus {
+    let dest_hash = dest_entry
+        .content
+        .as_ref()
+        .map(|c| guisu_engine::state::hash_data(c));
+
+    // Check mode matches
+    let mode_matches = if let Some(expected_mode) = mode {
+        dest_entry.mode == Some(expected_mode)
+    } else {
+        true
+    };
+
+    // Use unified three-way comparison
+    let dest_hash_vec = dest_hash.unwrap_or_default();
+    let base_hash = base_state.map(|s| s.content_hash.as_slice());
+
+    let comparison_result = compare_three_way(source_hash, &dest_hash_vec, base_hash);
+
+    // Map comparison result to file status
+    match comparison_result {
+        ThreeWayComparisonResult::NoChange | ThreeWayComparisonResult::Converged => {
+            if mode_matches {
+                FileStatus::Steady
+            } else {
+                FileStatus::Behind // Mode changed
+            }
+        }
+        ThreeWayComparisonResult::SourceChanged => FileStatus::Behind,
+        ThreeWayComparisonResult::DestinationChanged => FileStatus::Ahead,
+        ThreeWayComparisonResult::BothChanged => FileStatus::Conflict,
+    }
+}
+
+/// Status of a file whose inputs changed since the last apply, without
+/// processing it
+///
+/// The source is taken to be ahead, and the destination too if it no longer
+/// has the content that apply wrote.
+fn stale_file_status(
+    dest_entry: &guisu_engine::entry::DestEntry,
+    base_state: Option<&guisu_engine::state::EntryState>,
+) -> FileStatus {
+    let dest_hash = dest_entry
+        .content
+        .as_ref()
+        .map(|c| guisu_engine::state::hash_data(c));
+    match (dest_hash, base_state) {
+        (Some(dest_hash), Some(base)) if dest_hash == base.content_hash => FileStatus::Behind,
+        _ => FileStatus::Conflict,
+    }
+}
+
 /// Process a single entry for status display
 #[allow(clippy::too_many_arguments)]
 fn process_entry_for_status(
@@ -498,8 +862,10 @@ fn process_entry_for_status(
     entry: &guisu_engine::entry::SourceEntry,
     dest_state_mutex: &std::sync::Mutex<&mut DestinationState>,
     target_state: &TargetState,
+    quick_hashes: &QuickHashes,
+    errors: &HashMap<RelPath, String>,
     system: &RealSystem,
-    dest_root: &AbsPath,
+    paths: &ResolvedPaths,
     metadata: &guisu_engine::state::Metadata,
     filter_paths: Option<&Vec<RelPath>>,
     ignore_matcher: &guisu_config::IgnoreMatcher,
@@ -521,6 +887,15 @@ fn process_entry_for_status(
     }
 
     let path_str = target_path.to_string();
+    let file_type = get_entry_file_type(entry);
+    let display_path = format_display_path(paths, target_path);
+
+    if let Some(error) = errors.get(target_path) {
+        let mut info = FileInfo::new(display_path, FileStatus::Error, file_type);
+        info.attributes = entry_attributes(entry);
+        info.error = Some(error.clone());
+        return Some(info);
+    }
 
     // Read destination entry (thread-safe via mutex)
     let dest_entry = {
@@ -538,25 +913,46 @@ fn process_entry_for_status(
                     target_path.as_path().display(),
                     e
                 );
-                return None;
+                let mut info = FileInfo::new(display_path, FileStatus::Error, file_type);
+                info.attributes = entry_attributes(entry);
+                info.error = Some(format!("{e:#}"));
+                return Some(info);
             }
         }
     };
 
     // Handle create-once files that already exist - show as Steady
     if metadata.is_create_once(&path_str) && dest_entry.kind != EntryKind::Missing {
-        let file_type = get_entry_file_type(entry);
-        let display_path = format_display_path(dest_root, target_path);
+        let mut info = FileInfo::new(display_path, FileStatus::Steady, file_type);
+        info.attributes = entry_attributes(entry);
+        return Some(info);
+    }
 
-        return Some(FileInfo {
-            path: display_path,
-            status: FileStatus::Steady,
-            file_type,
-        });
+    // Files checked by hash alone have no target entry
+    if let Some(hash) = quick_hashes.get(target_path) {
+        let status = if dest_entry.kind == EntryKind::Missing {
+            FileStatus::Latent
+        } else {
+            let base_state = guisu_engine::database::get_entry_state(database, &path_str)
+                .ok()
+                .flatten();
+            let mode = entry_attributes(entry).and_then(|attributes| attributes.mode());
+            match hash {
+                Some(hash) => file_status(hash, mode, &dest_entry, base_state.as_ref()),
+                None => stale_file_status(&dest_entry, base_state.as_ref()),
+            }
+        };
+        let mut info = FileInfo::new(display_path, status, file_type);
+        info.attributes = entry_attributes(entry);
+        info.mode = ModeChange::between(
+            dest_entry.mode,
+            info.attributes.and_then(|attributes| attributes.mode()),
+        );
+        return Some(info);
     }
 
-    // Determine file type
-    let file_type = get_entry_file_type(entry);
+    // Use target_state which has processed content (decrypted + rendered)
+    let target_entry = target_state.get(target_path);
 
     // Determine status based on three-way comparison (Base, Source, Destination)
     let status = if dest_entry.kind == EntryKind::Missing {
@@ -564,10 +960,8 @@ fn process_entry_for_status(
         FileStatus::Latent
     } else {
         // Destination exists, do three-way comparison
-        // Use target_state which has processed content (decrypted + rendered)
-        let Some(target_entry) = target_state.get(target_path) else {
-            // Target entry not found (likely due to template processing error)
-            // Skip this file
+        let Some(target_entry) = target_entry else {
+            // Target entry not found, skip this file
             debug!(
                 "Skipping {}: target entry not found in target state",
                 target_path.as_path().display()
@@ -578,14 +972,41 @@ fn process_entry_for_status(
         determine_entry_status(database, target_entry, &dest_entry, &path_str)
     };
 
-    // Format path for display
-    let display_path = format_display_path(dest_root, target_path);
+    let mut info = FileInfo::new(display_path, status, file_type);
+    info.attributes = entry_attributes(entry);
+    if let Some(target_entry) = target_entry {
+        let (mode, binary) = describe_entry(target_entry, &dest_entry);
+        info.mode = mode;
+        info.binary = binary;
+    }
+    Some(info)
+}
+
+/// Source attributes of file and directory entries
+fn entry_attributes(entry: &guisu_engine::entry::SourceEntry) -> Option<FileAttributes> {
+    use guisu_engine::entry::SourceEntry;
+    match entry {
+        SourceEntry::File { attributes, .. } | SourceEntry::Directory { attributes, .. } => {
+            Some(*attributes)
+        }
+        SourceEntry::Symlink { .. } | SourceEntry::Script { .. } => None,
+    }
+}
 
-    Some(FileInfo {
-        path: display_path,
-        status,
-        file_type,
-    })
+/// Permission change and binary flag for a target entry against the destination
+fn describe_entry(
+    target_entry: &TargetEntry,
+    dest_entry: &guisu_engine::entry::DestEntry,
+) -> (Option<ModeChange>, bool) {
+    match target_entry {
+        TargetEntry::File { content, mode, .. } => {
+            let binary = is_binary(content) || dest_entry.content.as_deref().is_some_and(is_binary);
+            (ModeChange::between(dest_entry.mode, *mode), binary)
+        }
+        TargetEntry::LargeFile { mode, .. } => (ModeChange::between(dest_entry.mode, *mode), true),
+        TargetEntry::Directory { mode, .. } => (ModeChange::between(dest_entry.mode, *mode), false),
+        TargetEntry::Symlink { .. } | TargetEntry::Remove { .. } => (None, false),
+    }
 }
 
 /// Collect file information from source and destination states
@@ -597,9 +1018,11 @@ fn collect_file_info(params: CollectParams) -> Vec<FileInfo> {
         database,
         source_state,
         target_state,
+        quick_hashes,
+        errors,
         dest_state,
         system,
-        dest_root,
+        paths,
         metadata,
         filter_paths,
         ignore_matcher,
@@ -619,8 +1042,10 @@ fn collect_file_info(params: CollectParams) -> Vec<FileInfo> {
                 entry,
                 &dest_state_mutex,
                 target_state,
+                quick_hashes,
+                errors,
                 system,
-                dest_root,
+                paths,
                 metadata,
                 filter_paths,
                 ignore_matcher,
@@ -971,48 +1396,9 @@ fn render_tree(
     }
 }
 
-/// Render hook template content
-fn render_hook_template(source_dir: &Path, content: &str, config: &Config) -> Result<String> {
-    use guisu_template::TemplateContext;
-    use std::sync::Arc;
-
-    // Load age identities for encryption support in templates
-    let identities = Arc::new(config.age_identities().unwrap_or_else(|_| Vec::new()));
-
-    // Create template engine with bitwarden provider support
-    let engine = crate::create_template_engine(source_dir, &identities, config);
-
-    // Get destination directory
-    let dst_dir = config
-        .general
-        .dst_dir
-        .clone()
-        .or_else(dirs::home_dir)
-        .unwrap_or_else(|| std::path::PathBuf::from("~"));
-
-    // Create template context with guisu info and all variables
-    let working_tree = guisu_engine::git::find_working_tree(source_dir)
-        .unwrap_or_else(|| source_dir.to_path_buf());
-    let dotfiles_dir = config.dotfiles_dir(source_dir);
-    let template_ctx = TemplateContext::new()
-        .with_guisu_info(
-            crate::path_to_string(&dotfiles_dir),
-            crate::path_to_string(&working_tree),
-            crate::path_to_string(&dst_dir),
-            crate::path_to_string(&config.general.root_entry),
-        )
-        .with_loaded_variables(source_dir, config)
-        .map_err(|e| anyhow::anyhow!("Failed to load variables: {e}"))?;
-
-    // Render template
-    engine
-        .render_str(content, &template_ctx)
-        .map_err(|e| anyhow::anyhow!("Template rendering error: {e}"))
-}
-
 /// Render script content, handling templates if needed
 fn render_script_content(
-    source_dir: &Path,
+    paths: &ResolvedPaths,
     script: &str,
     content: &str,
     config: &Config,
@@ -1022,7 +1408,7 @@ fn render_script_content(
         .is_some_and(|ext| ext.eq_ignore_ascii_case("j2"));
 
     if is_template {
-        render_hook_template(source_dir, content, config).unwrap_or_else(|e| {
+        crate::cmd::hooks::render_hook_template(paths, content, config).unwrap_or_else(|e| {
             tracing::warn!("Failed to render hook template: {}", e);
             content.to_string()
         })
@@ -1033,7 +1419,7 @@ fn render_script_content(
 
 /// Check and print hooks status
 fn print_hooks_status(
-    source_dir: &Path,
+    paths: &ResolvedPaths,
     db: &RedbPersistentState,
     show_all: bool,
     config: &Config,
@@ -1041,7 +1427,8 @@ fn print_hooks_status(
     use guisu_engine::hooks::config::HookMode;
 
     // Load hooks and state using shared helper
-    let Some((collections, state)) = crate::utils::hooks::load_hooks_and_state(source_dir, db)
+    let Some((collections, state)) =
+        crate::utils::hooks::load_hooks_and_state(&paths.source_dir, db)
     else {
         return;
     };
@@ -1087,7 +1474,7 @@ fn print_hooks_status(
                 {
                     // Render current content and compute hash
                     let rendered = render_script_content(
-                        source_dir,
+                        paths,
                         hook.script.as_ref().unwrap_or(&String::new()),
                         content,
                         config,
@@ -1262,11 +1649,7 @@ mod tests {
 
     #[test]
     fn test_file_info_status_str() {
-        let file = FileInfo {
-            path: "test.txt".to_string(),
-            status: FileStatus::Latent,
-            file_type: 'F',
-        };
+        let file = FileInfo::new("test.txt".to_string(), FileStatus::Latent, 'F');
 
         let status_str = file.status_str();
         // Should contain the label
@@ -1275,11 +1658,7 @@ mod tests {
 
     #[test]
     fn test_file_info_debug() {
-        let file = FileInfo {
-            path: "test.txt".to_string(),
-            status: FileStatus::Ahead,
-            file_type: 'F',
-        };
+        let file = FileInfo::new("test.txt".to_string(), FileStatus::Ahead, 'F');
 
         let debug_str = format!("{file:?}");
         assert!(debug_str.contains("FileInfo"));
@@ -1354,11 +1733,7 @@ mod tests {
 
     #[test]
     fn test_build_tree_single_file() {
-        let file = FileInfo {
-            path: "test.txt".to_string(),
-            status: FileStatus::Latent,
-            file_type: 'F',
-        };
+        let file = FileInfo::new("test.txt".to_string(), FileStatus::Latent, 'F');
         let file_list = vec![&file];
 
         let tree = build_tree(&file_list);
@@ -1373,16 +1748,8 @@ mod tests {
 
     #[test]
     fn test_build_tree_nested_files() {
-        let file1 = FileInfo {
-            path: "dir1/file1.txt".to_string(),
-            status: FileStatus::Latent,
-            file_type: 'F',
-        };
-        let file2 = FileInfo {
-            path: "dir1/file2.txt".to_string(),
-            status: FileStatus::Behind,
-            file_type: 'F',
-        };
+        let file1 = FileInfo::new("dir1/file1.txt".to_string(), FileStatus::Latent, 'F');
+        let file2 = FileInfo::new("dir1/file2.txt".to_string(), FileStatus::Behind, 'F');
         let file_list = vec![&file1, &file2];
 
         let tree = build_tree(&file_list);
@@ -1401,11 +1768,7 @@ mod tests {
 
     #[test]
     fn test_build_tree_deep_nesting() {
-        let file = FileInfo {
-            path: "a/b/c/d/file.txt".to_string(),
-            status: FileStatus::Conflict,
-            file_type: 'F',
-        };
+        let file = FileInfo::new("a/b/c/d/file.txt".to_string(), FileStatus::Conflict, 'F');
         let file_list = vec![&file];
 
         let tree = build_tree(&file_list);
@@ -1429,21 +1792,9 @@ mod tests {
 
     #[test]
     fn test_build_tree_multiple_roots() {
-        let file1 = FileInfo {
-            path: "dir1/file1.txt".to_string(),
-            status: FileStatus::Latent,
-            file_type: 'F',
-        };
-        let file2 = FileInfo {
-            path: "dir2/file2.txt".to_string(),
-            status: FileStatus::Behind,
-            file_type: 'F',
-        };
-        let file3 = FileInfo {
-            path: "file3.txt".to_string(),
-            status: FileStatus::Ahead,
-            file_type: 'F',
-        };
+        let file1 = FileInfo::new("dir1/file1.txt".to_string(), FileStatus::Latent, 'F');
+        let file2 = FileInfo::new("dir2/file2.txt".to_string(), FileStatus::Behind, 'F');
+        let file3 = FileInfo::new("file3.txt".to_string(), FileStatus::Ahead, 'F');
         let file_list = vec![&file1, &file2, &file3];
 
         let tree = build_tree(&file_list);
@@ -1455,16 +1806,8 @@ mod tests {
 
     #[test]
     fn test_build_tree_mixed_depths() {
-        let file1 = FileInfo {
-            path: "a/b/deep.txt".to_string(),
-            status: FileStatus::Latent,
-            file_type: 'F',
-        };
-        let file2 = FileInfo {
-            path: "a/shallow.txt".to_string(),
-            status: FileStatus::Behind,
-            file_type: 'F',
-        };
+        let file1 = FileInfo::new("a/b/deep.txt".to_string(), FileStatus::Latent, 'F');
+        let file2 = FileInfo::new("a/shallow.txt".to_string(), FileStatus::Behind, 'F');
         let file_list = vec![&file1, &file2];
 
         let tree = build_tree(&file_list);
@@ -1480,6 +1823,152 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_output_format_from_str_json() {
+        assert_eq!("json".parse::<OutputFormat>().unwrap(), OutputFormat::Json);
+    }
+
+    #[test]
+    fn test_file_info_json_fields() {
+        let mut file = FileInfo::new("~/.bashrc".to_string(), FileStatus::Behind, 'F');
+        let json = serde_json::to_value(&file).unwrap();
+        assert_eq!(
+            json,
+            serde_json::json!({
+                "path": "~/.bashrc",
+                "status": "behind",
+                "type": "F",
+                "binary": false,
+            })
+        );
+
+        file.status = FileStatus::Error;
+        file.error = Some("template error".to_string());
+        let json = serde_json::to_value(&file).unwrap();
+        assert_eq!(json["status"], "error");
+        assert_eq!(json["error"], "template error");
+    }
+
+    #[test]
+    #[cfg(unix)]
+    fn test_collect_status_details() {
+        use crate::common::testing::{TestWorkspace, write};
+        use std::fs;
+        use std::os::unix::fs::PermissionsExt;
+
+        let workspace = TestWorkspace::new(Config::default());
+        write(&workspace.source("run.sh"), "echo hi\n");
+        write(&workspace.dest("run.sh"), "echo hi\n");
+        fs::set_permissions(
+            workspace.source("run.sh"),
+            fs::Permissions::from_mode(0o755),
+        )
+        .unwrap();
+        fs::set_permissions(workspace.dest("run.sh"), fs::Permissions::from_mode(0o644)).unwrap();
+        write(&workspace.source("data.bin"), b"\0data");
+        write(&workspace.source(".broken.j2"), "{{ missing(");
+
+        let context = &workspace.context;
+        let files = collect_status(
+            context.database(),
+            context.source_dir(),
+            context.dest_dir().as_path(),
+            &context.config,
+            &[],
+            &EntryFilter::default(),
+            false,
+        )
+        .unwrap()
+        .unwrap();
+        let find = |name: &str| {
+            files
+                .iter()
+                .find(|f| f.path.ends_with(name))
+                .unwrap_or_else(|| panic!("missing {name}"))
+        };
+
+        let broken = find(".broken");
+        assert_eq!(broken.status, FileStatus::Error);
+        assert!(broken.error.is_some());
+
+        let script = find("run.sh");
+        assert_eq!(script.status, FileStatus::Behind);
+        assert!(script.attributes.unwrap().is_executable());
+        assert_eq!(
+            script.mode,
+            Some(ModeChange {
+                from: Some("0644".into()),
+                to: "0755".into(),
+            })
+        );
+
+        let data = find("data.bin");
+        assert_eq!(data.status, FileStatus::Latent);
+        assert!(data.binary);
+    }
+
+    #[test]
+    fn test_collect_status_quick_skips_rendering() {
+        use crate::common::testing::{TestWorkspace, write};
+
+        let workspace = TestWorkspace::new(Config::default());
+        write(&workspace.source(".broken.j2"), "{{ missing(");
+        write(&workspace.source(".applied.j2"), "{{ 1 + 1 }}");
+        write(&workspace.dest(".applied"), "2");
+        write(&workspace.source(".edited.j2"), "{{ 1 + 1 }}");
+        write(&workspace.dest(".edited"), "3");
+
+        let context = &workspace.context;
+        let stamp = guisu_engine::clock::StateClock::fixed(0).begin_run();
+        for path in [".applied", ".edited"] {
+            guisu_engine::database::save_entry_state(
+                context.database(),
+                path,
+                b"2",
+                None,
+                guisu_engine::database::MergeBase::Keep,
+                &stamp,
+            )
+            .unwrap();
+        }
+
+        let files = collect_status(
+            context.database(),
+            context.source_dir(),
+            context.dest_dir().as_path(),
+            &context.config,
+            &[],
+            &EntryFilter::default(),
+            true,
+        )
+        .unwrap()
+        .unwrap();
+        let lines = porcelain_lines(&files, true);
+        let status_of = |name: &str| {
+            lines
+                .iter()
+                .find(|line| line.ends_with(name))
+                .and_then(|line| line.chars().next())
+                .unwrap_or_else(|| panic!("missing {name}"))
+        };
+
+        // Without render records every template counts as changed in the source
+        assert_eq!(status_of(".broken"), 'L');
+        assert_eq!(status_of(".applied"), 'B');
+        assert_eq!(status_of(".edited"), 'C');
+    }
+
+    #[test]
+    fn test_porcelain_lines() {
+        let files = [
+            FileInfo::new("~/.config".into(), FileStatus::Behind, 'D'),
+            FileInfo::new("~/.bashrc".into(), FileStatus::Ahead, 'F'),
+            FileInfo::new("~/.vimrc".into(), FileStatus::Steady, 'F'),
+        ];
+        assert_eq!(porcelain_lines(&files, false), ["A ~/.bashrc"]);
+        assert_eq!(porcelain_lines(&files, true), ["A ~/.bashrc", "S ~/.vimrc"]);
+    }
+
     // Tests for StatusCommand
 
     #[test]
@@ -1488,6 +1977,13 @@ mod tests {
             files: vec![],
             all: false,
             tree: false,
+            lint: false,
+            fix: false,
+            format: None,
+            porcelain: false,
+            quick: false,
+            include: vec![],
+            exclude: vec![],
         };
 
         assert!(cmd.files.is_empty());
@@ -1501,6 +1997,13 @@ mod tests {
             files: vec![PathBuf::from("file1.txt"), PathBuf::from("file2.txt")],
             all: false,
             tree: false,
+            lint: false,
+            fix: false,
+            format: None,
+            porcelain: false,
+            quick: false,
+            include: vec![],
+            exclude: vec![],
         };
 
         assert_eq!(cmd.files.len(), 2);
@@ -1514,6 +2017,13 @@ mod tests {
             files: vec![],
             all: true,
             tree: false,
+            lint: false,
+            fix: false,
+            format: None,
+            porcelain: false,
+            quick: false,
+            include: vec![],
+            exclude: vec![],
         };
 
         assert!(cmd.all);
@@ -1526,6 +2036,13 @@ mod tests {
             files: vec![],
             all: false,
             tree: true,
+            lint: false,
+            fix: false,
+            format: None,
+            porcelain: false,
+            quick: false,
+            include: vec![],
+            exclude: vec![],
         };
 
         assert!(!cmd.all);
@@ -1538,6 +2055,13 @@ mod tests {
             files: vec![PathBuf::from("test.txt")],
             all: true,
             tree: true,
+            lint: false,
+            fix: false,
+            format: None,
+            porcelain: false,
+            quick: false,
+            include: vec![],
+            exclude: vec![],
         };
 
         assert_eq!(cmd.files.len(), 1);
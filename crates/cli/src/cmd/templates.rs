@@ -3,14 +3,18 @@
 //! This module provides commands for managing template files:
 //! - list: List available template files for the current platform
 //! - show: Display rendered content of a specific template
+//! - check: Check every template of the source directory without rendering it
 
 use anyhow::{Context, Result};
+use guisu_core::path::AbsPath;
 use guisu_core::platform::CURRENT_PLATFORM;
+use guisu_engine::state::SourceState;
 use guisu_template::TemplateContext;
+use guisu_template::check::{Issue, IssueKind};
 use owo_colors::OwoColorize;
 use std::collections::BTreeSet;
 use std::fs;
-use std::path::Path;
+use std::path::{Path, PathBuf};
 
 use crate::utils::path::SourceDirExt;
 use guisu_config::Config;
@@ -181,6 +185,155 @@ pub fn run_show(
     Ok(())
 }
 
+/// Run templates check command
+///
+/// Checks the templates of the dotfiles directory, the files in
+/// `.guisu/templates/`, and templated hooks against the current variables,
+/// without rendering anything. Every problem is printed as
+/// `path:line: kind: message`, with paths relative to the source directory.
+/// Encrypted templates are skipped, since checking them would need the
+/// identity.
+///
+/// # Errors
+///
+/// Returns an error if the source state or variables cannot be loaded, or if
+/// any template has a problem
+pub fn run_check(source_dir: &Path, dest_dir: &Path, config: &Config) -> Result<()> {
+    let (files, skipped) = collect_templates(source_dir, config)?;
+
+    let all_variables = crate::cmd::apply::load_all_variables(source_dir, config)?;
+    let context = create_template_context(config, source_dir, dest_dir, all_variables);
+    let engine =
+        crate::create_template_engine(source_dir, &std::sync::Arc::new(Vec::new()), config)?;
+
+    let mut problems = 0;
+    let mut failed = 0;
+    for path in &files {
+        let display = path.strip_prefix(source_dir).unwrap_or(path).display();
+        let issues = match fs::read(path) {
+            Ok(bytes) => match String::from_utf8(bytes) {
+                Ok(source) => engine.check(&display.to_string(), &source, &context),
+                Err(_) => vec![Issue {
+                    line: None,
+                    kind: IssueKind::Syntax,
+                    message: "not valid UTF-8".to_string(),
+                }],
+            },
+            Err(e) => {
+                return Err(e).with_context(|| format!("Failed to read {}", path.display()));
+            }
+        };
+        if issues.is_empty() {
+            continue;
+        }
+
+        failed += 1;
+        problems += issues.len();
+        for issue in issues {
+            let location = match issue.line {
+                Some(line) => format!("{display}:{line}"),
+                None => display.to_string(),
+            };
+            let kind = issue.kind.to_string();
+            let kind = match issue.kind {
+                IssueKind::DeprecatedFunction => kind.yellow().to_string(),
+                _ => kind.red().to_string(),
+            };
+            println!("{}: {kind}: {}", location.bright_white(), issue.message);
+        }
+    }
+
+    if skipped > 0 {
+        println!(
+            "{}",
+            format!("Skipped {skipped} encrypted template(s)").dimmed()
+        );
+    }
+    if problems > 0 {
+        anyhow::bail!(
+            "{problems} problem(s) in {failed} of {} template(s)",
+            files.len()
+        );
+    }
+    println!(
+        "{} {} template(s) checked, no problems",
+        "✓".green(),
+        files.len()
+    );
+    Ok(())
+}
+
+/// Template files of the source directory, sorted, and the number of
+/// encrypted templates left out
+///
+/// # Errors
+///
+/// Returns an error if the source state cannot be read
+fn collect_templates(source_dir: &Path, config: &Config) -> Result<(Vec<PathBuf>, usize)> {
+    let mut files = BTreeSet::new();
+    let mut skipped = 0;
+
+    let dotfiles_dir = config.dotfiles_dir(source_dir);
+    if dotfiles_dir.exists() {
+        let matcher = crate::load_ignore_matcher(source_dir, &dotfiles_dir, config)?;
+        let source_state = SourceState::read_with_matcher(
+            AbsPath::new(dotfiles_dir).context("Invalid dotfiles directory")?,
+            Some(&matcher),
+        )
+        .context("Failed to read source state")?;
+        for entry in source_state.entries().chain(source_state.scripts()) {
+            if !entry.is_template() {
+                continue;
+            }
+            if entry.is_encrypted() {
+                skipped += 1;
+                continue;
+            }
+            files.insert(
+                source_state
+                    .source_file_path(entry.source_path())
+                    .as_path()
+                    .to_path_buf(),
+            );
+        }
+    }
+
+    // Everything in .guisu/templates/ is a template
+    for entry in walkdir::WalkDir::new(source_dir.templates_dir())
+        .into_iter()
+        .filter_map(std::result::Result::ok)
+        .filter(|entry| entry.file_type().is_file())
+    {
+        if entry
+            .path()
+            .to_string_lossy()
+            .to_lowercase()
+            .ends_with(".age")
+        {
+            skipped += 1;
+        } else {
+            files.insert(entry.into_path());
+        }
+    }
+
+    for entry in walkdir::WalkDir::new(source_dir.hooks_dir())
+        .into_iter()
+        .filter_map(std::result::Result::ok)
+        .filter(|entry| entry.file_type().is_file())
+    {
+        if entry
+            .path()
+            .to_string_lossy()
+            .to_lowercase()
+            .ends_with(".j2")
+        {
+            files.insert(entry.into_path());
+        }
+    }
+
+    Ok((files.into_iter().collect(), skipped))
+}
+
 /// Create template context with system and guisu information
 fn create_template_context(
     config: &Config,
@@ -200,6 +353,7 @@ fn create_template_context(
             crate::path_to_string(dest_dir),
             root_entry_str,
         )
+        .with_profile(config.general.profile.clone())
         .with_variables(variables)
 }
 
@@ -217,7 +371,7 @@ fn render_template(
         source_dir,
         &std::sync::Arc::new(identities.to_vec()),
         config,
-    );
+    )?;
 
     // Render the template
     engine
@@ -4,7 +4,8 @@
 
 use anyhow::{Context, Result, anyhow};
 use clap::Args;
-use git2::{AnnotatedCommit, AutotagOption, FetchOptions, RemoteCallbacks, Repository};
+use git2::{AnnotatedCommit, AutotagOption, Repository};
+use guisu_engine::git::{SystemGitProvider, authenticated_callbacks, fetch_options};
 use indicatif::{ProgressBar, ProgressStyle};
 use std::path::Path;
 use tracing::{debug, info, warn};
@@ -16,7 +17,7 @@ use crate::common::RuntimeContext;
 #[derive(Args)]
 pub struct UpdateCommand {
     /// Apply changes after pulling (default: true)
-    #[arg(short, long, default_value_t = true, hide_possible_values = true)]
+    #[arg(short, long, default_value_t = true)]
     pub apply: bool,
 
     /// Use rebase instead of merge when branches diverge
@@ -90,9 +91,10 @@ fn get_upstream_refspec(repo: &Repository) -> Result<Option<String>> {
 }
 
 /// Setup and perform fetch with progress bar
-fn setup_fetch_with_progress(repo: &Repository) -> Result<()> {
+///
+/// With `system_git`, the `git` binary fetches instead and reports its own progress.
+fn setup_fetch_with_progress(repo: &Repository, system_git: bool) -> Result<()> {
     let remote_name = get_default_remote(repo)?;
-    let mut remote = repo.find_remote(&remote_name)?;
 
     let refspecs = if let Some(branch) = get_upstream_refspec(repo)? {
         vec![branch]
@@ -100,6 +102,16 @@ fn setup_fetch_with_progress(repo: &Repository) -> Result<()> {
         vec![]
     };
 
+    if system_git {
+        debug!("Fetching from remote with the git binary");
+        let repo_path = repo.workdir().unwrap_or_else(|| repo.path());
+        return SystemGitProvider::new()
+            .fetch_refspecs(repo_path, &remote_name, &refspecs)
+            .context("Failed to fetch from remote");
+    }
+
+    let mut remote = repo.find_remote(&remote_name)?;
+
     let progress_bar = ProgressBar::new(100);
     progress_bar.set_style(
         ProgressStyle::default_bar()
@@ -108,11 +120,7 @@ fn setup_fetch_with_progress(repo: &Repository) -> Result<()> {
             .progress_chars("━━╸ "),
     );
 
-    let mut callbacks = RemoteCallbacks::new();
-    let git_config = git2::Config::open_default()
-        .unwrap_or_else(|_| git2::Config::new().expect("Failed to create git config"));
-    let mut credential_handler = git2_credentials::CredentialHandler::new(git_config);
-
+    let mut callbacks = authenticated_callbacks();
     callbacks.transfer_progress(|stats| {
         let received = stats.received_objects();
         let total = stats.total_objects();
@@ -138,12 +146,7 @@ fn setup_fetch_with_progress(repo: &Repository) -> Result<()> {
         true
     });
 
-    callbacks.credentials(move |url, username_from_url, allowed_types| {
-        credential_handler.try_next_credential(url, username_from_url, allowed_types)
-    });
-
-    let mut fetch_options = FetchOptions::new();
-    fetch_options.remote_callbacks(callbacks);
+    let mut fetch_options = fetch_options(callbacks);
     fetch_options.download_tags(AutotagOption::Auto);
 
     debug!("Fetching from remote");
@@ -239,9 +242,17 @@ fn apply_changes_after_update(context: &RuntimeContext) -> Result<()> {
         files: vec![],
         dry_run: false,
         force: false,
+        merge: false,
+        prune: false,
         interactive: false,
         include: vec![],
         exclude: vec![],
+        jobs: None,
+        backup: false,
+        refresh_externals: false,
+        one_shot: None,
+        source_ref: None,
+        timings: false,
     };
 
     apply_cmd
@@ -264,13 +275,17 @@ fn run_impl(context: &RuntimeContext, apply: bool, rebase: bool) -> Result<()> {
         .and_then(|r| r.url().map(str::to_string))
         .unwrap_or_else(|| source_dir.display().to_string());
 
-    info!("Updating repository from {}", remote_url);
+    if crate::is_offline(&context.config) {
+        warn!("Offline mode: not pulling from {}", remote_url);
+    } else {
+        info!("Updating repository from {}", remote_url);
 
-    setup_fetch_with_progress(&repo)?;
+        setup_fetch_with_progress(&repo, guisu_engine::git::use_system_git(&context.config))?;
 
-    let fetch_commit = analyze_fetch_result(&repo)?;
+        let fetch_commit = analyze_fetch_result(&repo)?;
 
-    handle_merge_scenarios(&repo, &fetch_commit, source_dir, rebase)?;
+        handle_merge_scenarios(&repo, &fetch_commit, source_dir, rebase)?;
+    }
 
     if apply {
         apply_changes_after_update(context)?;
@@ -5,6 +5,7 @@
 use anyhow::{Context, Result};
 use clap::Args;
 use guisu_template::TemplateContext;
+use indexmap::IndexMap;
 use owo_colors::OwoColorize;
 use serde::Serialize;
 use std::collections::BTreeMap;
@@ -16,11 +17,29 @@ use crate::command::Command;
 use crate::common::RuntimeContext;
 use crate::utils::path::SourceDirExt;
 
+/// Output format for the variables command
+#[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+pub enum VariablesFormat {
+    /// Aligned table grouped by kind
+    Pretty,
+    /// JSON document
+    Json,
+    /// YAML document
+    Yaml,
+    /// TOML document
+    Toml,
+}
+
 /// Variables command arguments
 #[derive(Debug, Args)]
+#[allow(clippy::struct_excessive_bools)]
 pub struct VariablesCommand {
-    /// Output in JSON format
-    #[arg(long)]
+    /// Output format
+    #[arg(long, value_enum, default_value = "pretty")]
+    pub format: VariablesFormat,
+
+    /// Output in JSON format (same as --format json)
+    #[arg(long, conflicts_with = "format")]
     pub json: bool,
 
     /// Show only builtin (system) variables
@@ -30,6 +49,14 @@ pub struct VariablesCommand {
     /// Show only user-defined variables
     #[arg(long)]
     pub user: bool,
+
+    /// Show only variables whose dotted name starts with PREFIX (e.g. `git.`)
+    #[arg(long, value_name = "PREFIX")]
+    pub filter: Option<String>,
+
+    /// Show the file or config that defined each variable
+    #[arg(long)]
+    pub origin: bool,
 }
 
 impl Command for VariablesCommand {
@@ -42,7 +69,17 @@ impl Command for VariablesCommand {
             _ => VariableFilter::All, // Both or neither = show all
         };
 
-        run_impl(context.source_dir(), &context.config, self.json, filter).map_err(Into::into)
+        let options = VariablesOptions {
+            format: if self.json {
+                VariablesFormat::Json
+            } else {
+                self.format
+            },
+            filter,
+            prefix: self.filter.clone(),
+            origin: self.origin,
+        };
+        run_impl(context.source_dir(), &context.config, &options).map_err(Into::into)
     }
 }
 
@@ -57,6 +94,20 @@ pub enum VariableFilter {
     UserOnly,
 }
 
+/// What to display and how
+#[derive(Debug, Clone)]
+struct VariablesOptions {
+    format: VariablesFormat,
+    filter: VariableFilter,
+    /// Only variables whose dotted name starts with this
+    prefix: Option<String>,
+    /// Show where each variable was defined
+    origin: bool,
+}
+
+/// Origin of a config (`[variables]`) variable
+const CONFIG_ORIGIN: &str = "config";
+
 /// Data structure for variable output
 #[derive(Debug, Serialize)]
 struct VariableData {
@@ -80,6 +131,13 @@ struct SystemVariables {
     distro_id: String,
     #[serde(rename = "distroVersion", skip_serializing_if = "String::is_empty")]
     distro_version: String,
+    #[serde(skip_serializing_if = "String::is_empty")]
+    kernel: String,
+    wsl: bool,
+    #[serde(skip_serializing_if = "String::is_empty")]
+    container: String,
+    cpus: usize,
+    ssh: bool,
     arch: String,
     hostname: String,
     username: String,
@@ -101,10 +159,14 @@ struct GuisuVariables {
     dst_dir: String,
     #[serde(rename = "rootEntry", skip_serializing_if = "Option::is_none")]
     root_entry: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    profile: Option<String>,
 }
 
 /// Run the variables command (implementation)
-fn run_impl(source_dir: &Path, config: &Config, json: bool, filter: VariableFilter) -> Result<()> {
+fn run_impl(source_dir: &Path, config: &Config, options: &VariablesOptions) -> Result<()> {
+    let filter = options.filter;
+
     // Create template context to get system variables
     let context = TemplateContext::new();
 
@@ -116,6 +178,11 @@ fn run_impl(source_dir: &Path, config: &Config, json: bool, filter: VariableFilt
             distro: context.system.distro.clone(),
             distro_id: context.system.distro_id.clone(),
             distro_version: context.system.distro_version.clone(),
+            kernel: context.system.kernel.clone(),
+            wsl: context.system.wsl,
+            container: context.system.container.clone(),
+            cpus: context.system.cpus,
+            ssh: context.system.ssh,
             arch: context.system.arch.clone(),
             hostname: context.system.hostname.clone(),
             username: context.system.username.clone(),
@@ -147,6 +214,7 @@ fn run_impl(source_dir: &Path, config: &Config, json: bool, filter: VariableFilt
             working_tree: crate::path_to_string(&working_tree),
             dst_dir: dest_dir,
             root_entry: Some(crate::path_to_string(&config.general.root_entry)),
+            profile: config.general.profile.clone(),
         })
     } else {
         None
@@ -180,16 +248,137 @@ fn run_impl(source_dir: &Path, config: &Config, json: bool, filter: VariableFilt
         variables: user_variables,
     };
 
-    // Output based on flag
-    if json {
-        output_json(&data)?;
+    let origins = if options.origin {
+        user_variable_origins(source_dir, config)?
     } else {
-        output_pretty(&data);
+        IndexMap::new()
+    };
+
+    match (options.format, options.origin) {
+        (VariablesFormat::Pretty, _) => {
+            let origins = options.origin.then_some(&origins);
+            output_pretty(&data, options.prefix.as_deref(), origins);
+        }
+        (format, false) => {
+            let mut value = serde_json::to_value(&data).context("Failed to serialize variables")?;
+            if let Some(prefix) = &options.prefix {
+                value = filter_value(value, prefix, "").unwrap_or_default();
+            }
+            println!("{}", serialize(&value, format)?);
+        }
+        (format, true) => {
+            let entries: BTreeMap<String, OriginEntry> = collect_all_variables(&data)
+                .into_iter()
+                .filter(|(key, _)| matches_prefix(key, options.prefix.as_deref()))
+                .map(|(key, value)| {
+                    let origin = variable_origin(&key, &origins);
+                    (key, OriginEntry { value, origin })
+                })
+                .collect();
+            println!("{}", serialize(&entries, format)?);
+        }
     }
 
     Ok(())
 }
 
+/// A variable and where it was defined, for `--origin` with a data format
+#[derive(Debug, Serialize)]
+struct OriginEntry {
+    value: serde_json::Value,
+    origin: String,
+}
+
+/// Where each user variable was defined, keyed by dotted name
+///
+/// Mirrors [`TemplateContext::with_loaded_variables`]: files in `.guisu/`
+/// are shown relative to the source directory, and a top-level config
+/// variable replaces everything loaded under its name.
+fn user_variable_origins(source_dir: &Path, config: &Config) -> Result<IndexMap<String, String>> {
+    let guisu_dir = source_dir.guisu_dir();
+    let platform_name = guisu_core::platform::CURRENT_PLATFORM.os;
+
+    let mut origins: IndexMap<String, String> =
+        guisu_config::variables::variable_origins(&guisu_dir, platform_name)
+            .context("Failed to load variables from .guisu/variables/")?
+            .into_iter()
+            .map(|(key, path)| {
+                let path = path.strip_prefix(source_dir).unwrap_or(&path);
+                (key, crate::path_to_string(path))
+            })
+            .collect();
+
+    let config_origin = CONFIG_ORIGIN.to_string();
+    for (key, value) in &config.variables {
+        let nested = format!("{key}.");
+        origins.retain(|k, _| k != key && !k.starts_with(&nested));
+        guisu_config::variables::record_origins(&mut origins, key, value, &config_origin);
+    }
+
+    Ok(origins)
+}
+
+/// Origin of a variable given its dotted name
+fn variable_origin(key: &str, origins: &IndexMap<String, String>) -> String {
+    if key.starts_with("system.") {
+        "system".to_string()
+    } else if key.starts_with("guisu.") {
+        "guisu".to_string()
+    } else {
+        origins
+            .get(key)
+            .cloned()
+            .unwrap_or_else(|| CONFIG_ORIGIN.to_string())
+    }
+}
+
+/// Whether a dotted variable name passes the `--filter` prefix
+fn matches_prefix(key: &str, prefix: Option<&str>) -> bool {
+    prefix.is_none_or(|prefix| key.starts_with(prefix))
+}
+
+/// Keep the parts of `value` whose dotted path (under `path`) starts with `prefix`
+///
+/// Returns `None` when nothing matches.
+fn filter_value(value: serde_json::Value, prefix: &str, path: &str) -> Option<serde_json::Value> {
+    if !path.is_empty() && path.starts_with(prefix) {
+        return Some(value);
+    }
+
+    match value {
+        serde_json::Value::Object(map) => {
+            let map: serde_json::Map<_, _> = map
+                .into_iter()
+                .filter_map(|(key, value)| {
+                    let child = if path.is_empty() {
+                        key.clone()
+                    } else {
+                        format!("{path}.{key}")
+                    };
+                    filter_value(value, prefix, &child).map(|value| (key, value))
+                })
+                .collect();
+            (!map.is_empty() || path.is_empty()).then_some(serde_json::Value::Object(map))
+        }
+        _ => None,
+    }
+}
+
+/// Serialize variables in a data format
+fn serialize(value: &impl Serialize, format: VariablesFormat) -> Result<String> {
+    match format {
+        VariablesFormat::Json | VariablesFormat::Pretty => {
+            serde_json::to_string_pretty(value).context("Failed to serialize variables to JSON")
+        }
+        VariablesFormat::Yaml => {
+            serde_yaml::to_string(value).context("Failed to serialize variables to YAML")
+        }
+        VariablesFormat::Toml => {
+            toml::to_string_pretty(value).context("Failed to serialize variables to TOML")
+        }
+    }
+}
+
 /// Collect system variables into key-value pairs
 fn collect_system_variables(system: &SystemVariables) -> Vec<(String, serde_json::Value)> {
     let mut vars = vec![
@@ -221,6 +410,30 @@ fn collect_system_variables(system: &SystemVariables) -> Vec<(String, serde_json
             serde_json::Value::String(system.distro_version.clone()),
         ));
     }
+    if !system.kernel.is_empty() {
+        vars.push((
+            "system.kernel".to_string(),
+            serde_json::Value::String(system.kernel.clone()),
+        ));
+    }
+    vars.push((
+        "system.wsl".to_string(),
+        serde_json::Value::Bool(system.wsl),
+    ));
+    if !system.container.is_empty() {
+        vars.push((
+            "system.container".to_string(),
+            serde_json::Value::String(system.container.clone()),
+        ));
+    }
+    vars.push((
+        "system.cpus".to_string(),
+        serde_json::Value::from(system.cpus),
+    ));
+    vars.push((
+        "system.ssh".to_string(),
+        serde_json::Value::Bool(system.ssh),
+    ));
 
     vars.extend_from_slice(&[
         (
@@ -280,6 +493,13 @@ fn collect_guisu_variables(guisu: &GuisuVariables) -> Vec<(String, serde_json::V
         ));
     }
 
+    if let Some(ref profile) = guisu.profile {
+        vars.push((
+            "guisu.profile".to_string(),
+            serde_json::Value::String(profile.clone()),
+        ));
+    }
+
     vars
 }
 
@@ -302,20 +522,31 @@ fn collect_all_variables(data: &VariableData) -> Vec<(String, serde_json::Value)
     all_vars
 }
 
-/// Display a section of variables
-fn display_variable_section(title: &str, vars: &[(String, serde_json::Value)], max_key_len: usize) {
+/// Display a section of variables, with their origins if given
+fn display_variable_section(
+    title: &str,
+    vars: &[(String, serde_json::Value)],
+    max_key_len: usize,
+    origins: Option<&IndexMap<String, String>>,
+) {
     if !vars.is_empty() {
         println!("\n{}", title.bright_cyan().bold());
         println!("{}", "─".repeat(60).dimmed());
         for (key, value) in vars {
-            print_variable_aligned(key, value, max_key_len);
+            let origin = origins.map(|origins| variable_origin(key, origins));
+            print_variable_aligned(key, value, max_key_len, origin.as_deref());
         }
     }
 }
 
 /// Output in pretty/table format
-fn output_pretty(data: &VariableData) {
-    let all_vars = collect_all_variables(data);
+fn output_pretty(
+    data: &VariableData,
+    prefix: Option<&str>,
+    origins: Option<&IndexMap<String, String>>,
+) {
+    let mut all_vars = collect_all_variables(data);
+    all_vars.retain(|(key, _)| matches_prefix(key, prefix));
 
     // Calculate maximum key length
     let max_key_len = all_vars.iter().map(|(k, _)| k.len()).max().unwrap_or(20);
@@ -337,9 +568,9 @@ fn output_pretty(data: &VariableData) {
         .map(|(k, v)| (k.clone(), v.clone()))
         .collect();
 
-    display_variable_section("System variables:", &system_vars, max_key_len);
-    display_variable_section("Guisu variables:", &guisu_vars, max_key_len);
-    display_variable_section("User variables:", &user_vars, max_key_len);
+    display_variable_section("System variables:", &system_vars, max_key_len, origins);
+    display_variable_section("Guisu variables:", &guisu_vars, max_key_len, origins);
+    display_variable_section("User variables:", &user_vars, max_key_len, origins);
 
     println!();
 }
@@ -376,7 +607,12 @@ fn flatten_json_map(
 }
 
 /// Print a single variable in pretty format with dynamic alignment
-fn print_variable_aligned(key: &str, value: &serde_json::Value, width: usize) {
+fn print_variable_aligned(
+    key: &str,
+    value: &serde_json::Value,
+    width: usize,
+    origin: Option<&str>,
+) {
     let formatted_value = match value {
         serde_json::Value::String(s) => s.clone(),
         serde_json::Value::Number(n) => n.to_string(),
@@ -387,20 +623,21 @@ fn print_variable_aligned(key: &str, value: &serde_json::Value, width: usize) {
         }
     };
 
-    println!(
-        "  {:<width$} {}",
-        key.bright_yellow(),
-        formatted_value.bright_white(),
-        width = width
-    );
-}
-
-/// Output in JSON format
-fn output_json(data: &VariableData) -> Result<()> {
-    let json =
-        serde_json::to_string_pretty(data).context("Failed to serialize variables to JSON")?;
-    println!("{json}");
-    Ok(())
+    match origin {
+        Some(origin) => println!(
+            "  {:<width$} {}  {}",
+            key.bright_yellow(),
+            formatted_value.bright_white(),
+            format!("({origin})").dimmed(),
+            width = width
+        ),
+        None => println!(
+            "  {:<width$} {}",
+            key.bright_yellow(),
+            formatted_value.bright_white(),
+            width = width
+        ),
+    }
 }
 
 #[cfg(test)]
@@ -540,6 +777,11 @@ mod tests {
             distro: String::new(),
             distro_id: String::new(),
             distro_version: String::new(),
+            kernel: "23.6.0".to_string(),
+            wsl: false,
+            container: String::new(),
+            cpus: 8,
+            ssh: false,
             arch: "aarch64".to_string(),
             hostname: "test-host".to_string(),
             username: "testuser".to_string(),
@@ -553,9 +795,11 @@ mod tests {
 
         assert_eq!(json["os"], "darwin");
         assert_eq!(json["arch"], "aarch64");
+        assert_eq!(json["cpus"], 8);
         // Empty strings should be skipped
         assert!(json.get("distro").is_none());
         assert!(json.get("distroId").is_none());
+        assert!(json.get("container").is_none());
     }
 
     #[test]
@@ -565,6 +809,7 @@ mod tests {
             working_tree: "/path/to/repo".to_string(),
             dst_dir: "/home/user".to_string(),
             root_entry: Some("home".to_string()),
+            profile: Some("work".to_string()),
         };
 
         let json = serde_json::to_value(&guisu_vars).expect("Failed to serialize");
@@ -573,6 +818,7 @@ mod tests {
         assert_eq!(json["workingTree"], "/path/to/repo");
         assert_eq!(json["dstDir"], "/home/user");
         assert_eq!(json["rootEntry"], "home");
+        assert_eq!(json["profile"], "work");
     }
 
     #[test]
@@ -582,12 +828,14 @@ mod tests {
             working_tree: "/path/to/repo".to_string(),
             dst_dir: "/home/user".to_string(),
             root_entry: None,
+            profile: None,
         };
 
         let json = serde_json::to_value(&guisu_vars).expect("Failed to serialize");
 
-        // None root_entry should be skipped
+        // None root_entry and profile should be skipped
         assert!(json.get("rootEntry").is_none());
+        assert!(json.get("profile").is_none());
     }
 
     #[test]
@@ -602,6 +850,11 @@ mod tests {
                 distro: "Ubuntu".to_string(),
                 distro_id: "ubuntu".to_string(),
                 distro_version: "22.04".to_string(),
+                kernel: "6.8.0-45-generic".to_string(),
+                wsl: false,
+                container: "docker".to_string(),
+                cpus: 4,
+                ssh: true,
                 arch: "x86_64".to_string(),
                 hostname: "myhost".to_string(),
                 username: "myuser".to_string(),
@@ -615,6 +868,7 @@ mod tests {
                 working_tree: "/repo".to_string(),
                 dst_dir: "/home".to_string(),
                 root_entry: Some("home".to_string()),
+                profile: None,
             }),
             variables: vars,
         };
@@ -690,4 +944,60 @@ mod tests {
         assert_eq!(result.get("app.server.ports.https"), Some(&json!(8443)));
         assert_eq!(result.get("app.enabled"), Some(&json!(true)));
     }
+
+    #[test]
+    fn test_filter_value_keeps_matching_subtrees() {
+        let value = json!({
+            "system": {"os": "linux"},
+            "variables": {"git": {"name": "me", "email": "me@example.com"}, "editor": "vim"}
+        });
+
+        let filtered = filter_value(value.clone(), "variables.git.", "").unwrap();
+        assert_eq!(
+            filtered,
+            json!({"variables": {"git": {"name": "me", "email": "me@example.com"}}})
+        );
+
+        let none = filter_value(value, "nothing", "").unwrap();
+        assert_eq!(none, json!({}));
+    }
+
+    #[test]
+    fn test_user_variable_origins_config_overrides_files() {
+        let temp = tempfile::TempDir::new().unwrap();
+        let variables_dir = temp.path().join(".guisu/variables");
+        std::fs::create_dir_all(&variables_dir).unwrap();
+        std::fs::write(
+            variables_dir.join("git.toml"),
+            "[git]\nname = \"file\"\nemail = \"file@example.com\"\n",
+        )
+        .unwrap();
+
+        let mut config = Config::default();
+        config
+            .variables
+            .insert("git".to_string(), json!({"name": "config"}));
+        config.variables.insert("editor".to_string(), json!("vim"));
+
+        let origins = user_variable_origins(temp.path(), &config).unwrap();
+        assert_eq!(variable_origin("git.name", &origins), "config");
+        assert_eq!(variable_origin("editor", &origins), "config");
+        assert!(!origins.contains_key("git.email"));
+        assert_eq!(variable_origin("system.os", &origins), "system");
+    }
+
+    #[test]
+    fn test_serialize_formats() {
+        let value = json!({"git": {"name": "me"}});
+        assert!(
+            serialize(&value, VariablesFormat::Yaml)
+                .unwrap()
+                .contains("name: me")
+        );
+        assert!(
+            serialize(&value, VariablesFormat::Toml)
+                .unwrap()
+                .contains("[git]")
+        );
+    }
 }
@@ -0,0 +1,58 @@
+//! Vault command operations
+//!
+//! Manage the encrypted disk cache of Bitwarden responses kept when
+//! `[vault] cacheTtl` is set:
+//! - cache clear: Delete every cached response
+
+use anyhow::{Context, Result};
+use guisu_vault::DiskCache;
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::time::Duration;
+
+/// Directory of the vault disk cache, in the state directory
+///
+/// # Errors
+///
+/// Returns an error if the state directory cannot be determined
+pub fn cache_dir() -> Result<PathBuf> {
+    let state_dir = guisu_config::state_dir().context("Failed to determine the state directory")?;
+    Ok(state_dir.join("vault-cache"))
+}
+
+/// The vault disk cache, `None` if disabled or without identities to encrypt to
+pub(crate) fn disk_cache(
+    config: &guisu_config::Config,
+    identities: &Arc<Vec<guisu_crypto::Identity>>,
+) -> Option<DiskCache> {
+    if config.vault.cache_ttl == 0 || identities.is_empty() {
+        return None;
+    }
+    let dir = cache_dir().ok()?;
+    Some(DiskCache::new(
+        dir,
+        Arc::clone(identities),
+        Duration::from_secs(config.vault.cache_ttl),
+    ))
+}
+
+/// Run vault cache clear command
+///
+/// # Errors
+///
+/// Returns an error if the cache directory cannot be read or an entry cannot
+/// be deleted
+pub fn run_cache_clear() -> Result<()> {
+    let dir = cache_dir()?;
+    let removed = DiskCache::clear(&dir)
+        .with_context(|| format!("Failed to clear vault cache in {}", dir.display()))?;
+    println!(
+        "Removed {removed} cached vault {}",
+        if removed == 1 {
+            "response"
+        } else {
+            "responses"
+        }
+    );
+    Ok(())
+}
@@ -0,0 +1,350 @@
+//! Verify command implementation
+//!
+//! Check whether the destination matches the target state without printing
+//! diffs. The exit status is 0 when everything matches and 1 otherwise, for
+//! use in shell prompts and provisioning checks.
+
+use anyhow::{Context, Result};
+use clap::Args;
+use guisu_engine::entry::{EntryKind, TargetEntry};
+use owo_colors::OwoColorize;
+use rayon::prelude::*;
+use std::fs;
+use std::path::PathBuf;
+
+use crate::cmd::apply::mode_differs;
+use crate::cmd::diff::build_diff_plan;
+use crate::command::Command;
+use crate::common::RuntimeContext;
+use crate::error::CommandError;
+use crate::utils::dest::DestProbe;
+
+/// Output format for the verify command
+#[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+pub enum VerifyFormat {
+    /// One line per entry that does not match
+    Text,
+    /// JSON object with the status of every entry
+    Json,
+}
+
+/// Check that the destination matches the target state
+#[derive(Debug, Clone, Args)]
+pub struct VerifyCommand {
+    /// Specific files to verify (all if not specified)
+    pub files: Vec<PathBuf>,
+
+    /// Output format
+    #[arg(long, value_enum, default_value = "text")]
+    pub format: VerifyFormat,
+}
+
+/// How a destination compares with its target entry
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Serialize)]
+#[serde(rename_all = "lowercase")]
+enum VerifyStatus {
+    /// Destination matches the target
+    Ok,
+    /// Destination does not exist
+    Missing,
+    /// Content or symlink target differs
+    Modified,
+    /// Only the permissions differ
+    Mode,
+    /// Destination is a different kind of entry
+    Type,
+    /// Target could not be rendered or destination could not be read
+    Error,
+}
+
+impl VerifyStatus {
+    fn label(self) -> &'static str {
+        match self {
+            Self::Ok => "ok",
+            Self::Missing => "missing",
+            Self::Modified => "modified",
+            Self::Mode => "mode",
+            Self::Type => "type",
+            Self::Error => "error",
+        }
+    }
+}
+
+/// Verification result of a single entry
+#[derive(Debug, serde::Serialize)]
+struct VerifyEntry {
+    /// Target path relative to the destination
+    path: String,
+    status: VerifyStatus,
+}
+
+/// Verification result of every entry
+#[derive(Debug, serde::Serialize)]
+struct VerifyReport {
+    /// Whether every entry matches
+    clean: bool,
+    entries: Vec<VerifyEntry>,
+}
+
+impl Command for VerifyCommand {
+    type Output = ();
+    fn execute(&self, context: &RuntimeContext) -> crate::error::Result<()> {
+        let report = build_report(context, &self.files)?;
+
+        match self.format {
+            VerifyFormat::Json => println!(
+                "{}",
+                serde_json::to_string_pretty(&report)
+                    .context("Failed to serialize verify report")?
+            ),
+            VerifyFormat::Text => {
+                for entry in report
+                    .entries
+                    .iter()
+                    .filter(|e| e.status != VerifyStatus::Ok)
+                {
+                    println!("{:<9}{}", entry.status.label().yellow(), entry.path);
+                }
+            }
+        }
+
+        let differing = report
+            .entries
+            .iter()
+            .filter(|entry| entry.status != VerifyStatus::Ok)
+            .count();
+        if differing > 0 {
+            return Err(CommandError::VerifyFailed { differing });
+        }
+        Ok(())
+    }
+}
+
+/// Compare every target entry with its destination
+fn build_report(context: &RuntimeContext, files: &[PathBuf]) -> Result<VerifyReport> {
+    let Some(plan) = build_diff_plan(
+        context.source_dir(),
+        context.dest_dir().as_path(),
+        files,
+        &crate::common::EntryFilter::default(),
+        &context.config,
+        None,
+        &std::sync::Arc::new(crate::stats::Timings::new()),
+        false,
+    )?
+    else {
+        return Ok(VerifyReport {
+            clean: true,
+            entries: Vec::new(),
+        });
+    };
+
+    let targets: Vec<&TargetEntry> = plan
+        .target_state
+        .entries()
+        .filter(|entry| !matches!(entry, TargetEntry::Remove { .. }))
+        .collect();
+    let mut entries: Vec<VerifyEntry> = targets
+        .par_iter()
+        .filter_map(|entry| {
+            let path = entry.path().to_string();
+            let dest = DestProbe::new(plan.paths.dest_path(entry.path()));
+
+            // Create-once files are only checked for existence
+            if plan.metadata.is_create_once(&path) && dest.is_present() {
+                return None;
+            }
+
+            Some(VerifyEntry {
+                status: verify_entry(entry, &dest),
+                path,
+            })
+        })
+        .collect();
+    entries.extend(plan.failed.iter().map(|(path, _)| VerifyEntry {
+        path: path.to_string(),
+        status: VerifyStatus::Error,
+    }));
+    entries.sort_by(|a, b| a.path.cmp(&b.path));
+
+    Ok(VerifyReport {
+        clean: entries.iter().all(|entry| entry.status == VerifyStatus::Ok),
+        entries,
+    })
+}
+
+/// Compare one target entry with its destination
+///
+/// File contents are compared by hash, so no diff is computed.
+fn verify_entry(entry: &TargetEntry, dest: &DestProbe) -> VerifyStatus {
+    match entry {
+        TargetEntry::File {
+            content_hash, mode, ..
+        }
+        | TargetEntry::LargeFile {
+            content_hash, mode, ..
+        } => {
+            if !dest.exists() {
+                return VerifyStatus::Missing;
+            }
+            if dest.is_dir() {
+                return VerifyStatus::Type;
+            }
+            match dest.content_hash() {
+                Ok(hash) if hash != *content_hash => VerifyStatus::Modified,
+                Ok(_) if mode_differs(*mode, dest) => VerifyStatus::Mode,
+                Ok(_) => VerifyStatus::Ok,
+                Err(_) => VerifyStatus::Error,
+            }
+        }
+        TargetEntry::Directory { mode, .. } => match dest.kind() {
+            EntryKind::Missing => VerifyStatus::Missing,
+            EntryKind::Directory if mode_differs(*mode, dest) => VerifyStatus::Mode,
+            EntryKind::Directory => VerifyStatus::Ok,
+            _ => VerifyStatus::Type,
+        },
+        TargetEntry::Symlink { target, .. } => match dest.kind() {
+            EntryKind::Missing => VerifyStatus::Missing,
+            EntryKind::Symlink => match fs::read_link(dest.path().as_path()) {
+                Ok(current) if current == *target => VerifyStatus::Ok,
+                Ok(_) => VerifyStatus::Modified,
+                Err(_) => VerifyStatus::Error,
+            },
+            _ => VerifyStatus::Type,
+        },
+        TargetEntry::Remove { .. } => VerifyStatus::Ok,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    #![allow(clippy::unwrap_used, clippy::panic)]
+    use super::*;
+    use crate::common::testing::{TestWorkspace, write};
+    use guisu_config::Config;
+    use guisu_core::path::{AbsPath, RelPath};
+    use guisu_engine::hash::hash_content;
+    use tempfile::TempDir;
+
+    fn file_entry(path: &str, content: &[u8], mode: Option<u32>) -> TargetEntry {
+        TargetEntry::File {
+            path: RelPath::new(PathBuf::from(path)).unwrap(),
+            content_hash: hash_content(content),
+            content: content.to_vec().into(),
+            mode,
+        }
+    }
+
+    fn probe(dest: &TempDir, path: &str) -> DestProbe {
+        DestProbe::new(AbsPath::new(dest.path().join(path)).unwrap())
+    }
+
+    #[test]
+    fn test_verify_file_entry() {
+        let dest = TempDir::new().unwrap();
+        let entry = file_entry(".bashrc", b"alias ll='ls -l'\n", None);
+        assert_eq!(
+            verify_entry(&entry, &probe(&dest, ".bashrc")),
+            VerifyStatus::Missing
+        );
+
+        fs::write(dest.path().join(".bashrc"), "alias ll='ls -la'\n").unwrap();
+        assert_eq!(
+            verify_entry(&entry, &probe(&dest, ".bashrc")),
+            VerifyStatus::Modified
+        );
+
+        fs::write(dest.path().join(".bashrc"), "alias ll='ls -l'\n").unwrap();
+        assert_eq!(
+            verify_entry(&entry, &probe(&dest, ".bashrc")),
+            VerifyStatus::Ok
+        );
+
+        fs::create_dir(dest.path().join(".vimrc")).unwrap();
+        assert_eq!(
+            verify_entry(&file_entry(".vimrc", b"", None), &probe(&dest, ".vimrc")),
+            VerifyStatus::Type
+        );
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_verify_mode_and_symlink() {
+        use std::os::unix::fs::{PermissionsExt, symlink};
+
+        let dest = TempDir::new().unwrap();
+        let script = dest.path().join("run.sh");
+        fs::write(&script, "#!/bin/sh\n").unwrap();
+        fs::set_permissions(&script, fs::Permissions::from_mode(0o644)).unwrap();
+        assert_eq!(
+            verify_entry(
+                &file_entry("run.sh", b"#!/bin/sh\n", Some(0o755)),
+                &probe(&dest, "run.sh")
+            ),
+            VerifyStatus::Mode
+        );
+
+        symlink("run.sh", dest.path().join("link")).unwrap();
+        let link = |target: &str| TargetEntry::Symlink {
+            path: RelPath::new(PathBuf::from("link")).unwrap(),
+            target: PathBuf::from(target),
+        };
+        assert_eq!(
+            verify_entry(&link("run.sh"), &probe(&dest, "link")),
+            VerifyStatus::Ok
+        );
+        assert_eq!(
+            verify_entry(&link("other.sh"), &probe(&dest, "link")),
+            VerifyStatus::Modified
+        );
+        assert_eq!(
+            verify_entry(&link("run.sh"), &probe(&dest, "run.sh")),
+            VerifyStatus::Type
+        );
+    }
+
+    #[test]
+    fn test_build_report() {
+        let workspace = TestWorkspace::new(Config::default());
+        write(&workspace.source(".bashrc"), "export A=1\n");
+        write(&workspace.source(".profile.j2"), "{{ 1 + 1 }}\n");
+        let context = &workspace.context;
+
+        write(&workspace.dest(".bashrc"), "export A=1\n");
+        let report = build_report(context, &[]).unwrap();
+        assert!(!report.clean);
+        let statuses: Vec<_> = report
+            .entries
+            .iter()
+            .map(|entry| (entry.path.as_str(), entry.status))
+            .collect();
+        assert_eq!(
+            statuses,
+            vec![
+                (".bashrc", VerifyStatus::Ok),
+                (".profile", VerifyStatus::Missing)
+            ]
+        );
+
+        write(&workspace.dest(".profile"), "2\n");
+        assert!(build_report(context, &[]).unwrap().clean);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_build_report_symlink_mode() {
+        let mut config = Config::default();
+        config.apply.mode = guisu_config::ApplyMode::Symlink;
+        let workspace = TestWorkspace::new(config);
+        write(&workspace.source(".bashrc"), "export A=1\n");
+        let context = &workspace.context;
+
+        // A copy with the same content is not the expected symlink
+        write(&workspace.dest(".bashrc"), "export A=1\n");
+        assert!(!build_report(context, &[]).unwrap().clean);
+
+        fs::remove_file(workspace.dest(".bashrc")).unwrap();
+        std::os::unix::fs::symlink(workspace.source(".bashrc"), workspace.dest(".bashrc")).unwrap();
+        assert!(build_report(context, &[]).unwrap().clean);
+    }
+}
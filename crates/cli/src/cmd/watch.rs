@@ -0,0 +1,470 @@
+//! Watch command implementation
+//!
+//! Apply the source directory again whenever it changes, so template edits
+//! show up in the destination without re-running `guisu apply`.
+//!
+//! File system events (inotify, `FSEvents`, ...) wake the watcher; it then scans
+//! the source to find what changed and to wait for it to settle. Where events
+//! are unavailable, it falls back to scanning on an interval.
+
+use anyhow::{Context, Result};
+use owo_colors::OwoColorize;
+use std::collections::HashMap;
+use std::fmt::Write as FmtWrite;
+use std::path::{Component, Path, PathBuf};
+use std::sync::Arc;
+use std::sync::mpsc::{Receiver, RecvTimeoutError, Sender};
+use std::time::{Duration, Instant, SystemTime};
+use tracing::{debug, warn};
+use walkdir::WalkDir;
+
+use crate::cmd::apply::{ApplyCommand, ApplyReport};
+use crate::common::{ResolvedPaths, RuntimeContext};
+
+/// Modification time and size of every file under the source directory
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+struct SourceSnapshot {
+    files: HashMap<PathBuf, (Option<SystemTime>, u64)>,
+}
+
+impl SourceSnapshot {
+    /// Scan `root`, skipping the `.git` directory
+    ///
+    /// Entries that vanish or cannot be read while scanning are left out.
+    fn capture(root: &Path) -> Self {
+        let files = WalkDir::new(root)
+            .into_iter()
+            .filter_entry(|entry| entry.file_name() != ".git")
+            .filter_map(std::result::Result::ok)
+            .filter(|entry| !entry.file_type().is_dir())
+            .filter_map(|entry| {
+                let metadata = entry.metadata().ok()?;
+                let path = entry.path().strip_prefix(root).ok()?.to_path_buf();
+                Some((path, (metadata.modified().ok(), metadata.len())))
+            })
+            .collect();
+
+        Self { files }
+    }
+
+    /// Paths added, removed or modified in `newer`, sorted
+    fn changes(&self, newer: &Self) -> Vec<PathBuf> {
+        let mut changed: Vec<PathBuf> = newer
+            .files
+            .iter()
+            .filter(|(path, stat)| self.files.get(*path) != Some(*stat))
+            .map(|(path, _)| path.clone())
+            .chain(
+                self.files
+                    .keys()
+                    .filter(|path| !newer.files.contains_key(*path))
+                    .cloned(),
+            )
+            .collect();
+        changed.sort();
+        changed
+    }
+}
+
+/// Debounced change detection for the source directory
+///
+/// A change is reported once the source has stayed the same for the debounce
+/// period, so saving several files (or an editor's write-and-rename) leads to
+/// a single apply.
+#[derive(Debug)]
+pub struct SourceWatcher {
+    root: PathBuf,
+    debounce: Duration,
+    /// Source as of the last reported change
+    applied: SourceSnapshot,
+    /// Latest changed snapshot and when it was first seen
+    pending: Option<(SourceSnapshot, Instant)>,
+}
+
+impl SourceWatcher {
+    /// Create a watcher for `root`, taking the current contents as the baseline
+    #[must_use]
+    pub fn new(root: PathBuf, debounce: Duration) -> Self {
+        let applied = SourceSnapshot::capture(&root);
+        Self {
+            root,
+            debounce,
+            applied,
+            pending: None,
+        }
+    }
+
+    /// Scan the source and return the changed paths once they have settled
+    ///
+    /// Returns `None` while the source is unchanged or still changing.
+    pub fn poll(&mut self, now: Instant) -> Option<Vec<PathBuf>> {
+        let current = SourceSnapshot::capture(&self.root);
+
+        match &self.pending {
+            Some((pending, since)) if *pending == current => {
+                if now.duration_since(*since) < self.debounce {
+                    return None;
+                }
+            }
+            _ => {
+                if current == self.applied {
+                    self.pending = None;
+                } else {
+                    self.pending = Some((current, now));
+                }
+                return None;
+            }
+        }
+
+        let (settled, _) = self.pending.take()?;
+        let changes = self.applied.changes(&settled);
+        self.applied = settled;
+        Some(changes)
+    }
+
+    /// Whether a change has been seen but not yet reported
+    #[must_use]
+    pub fn is_settling(&self) -> bool {
+        self.pending.is_some()
+    }
+}
+
+/// Watch `root` recursively, sending on `tx` when anything outside `.git` changes
+///
+/// Errors from the event backend are sent as changes too, so that a missed
+/// event leads to a scan instead of a stale destination.
+fn watch_events(root: &Path, tx: Sender<()>) -> notify::Result<notify::RecommendedWatcher> {
+    use notify::Watcher;
+
+    let mut watcher = notify::recommended_watcher(move |event: notify::Result<notify::Event>| {
+        let relevant = event.map_or(true, |event| {
+            event.paths.is_empty() || event.paths.iter().any(|path| !in_git_dir(path))
+        });
+        if relevant {
+            let _ = tx.send(());
+        }
+    })?;
+    watcher.watch(root, notify::RecursiveMode::Recursive)?;
+    Ok(watcher)
+}
+
+/// Whether `path` is inside a `.git` directory
+fn in_git_dir(path: &Path) -> bool {
+    path.components()
+        .any(|component| component == Component::Normal(".git".as_ref()))
+}
+
+/// Block until the source may have changed
+///
+/// With events, waits for the next one, or for `interval` while a change is
+/// settling. Without events, sleeps for `interval`.
+fn wait_for_change(events: Option<&Receiver<()>>, settling: bool, interval: Duration) {
+    let Some(events) = events else {
+        std::thread::sleep(interval);
+        return;
+    };
+
+    let woken = if settling {
+        events.recv_timeout(interval)
+    } else {
+        events.recv().map_err(|_| RecvTimeoutError::Disconnected)
+    };
+    match woken {
+        // Several events usually arrive for one save
+        Ok(()) => while events.try_recv().is_ok() {},
+        Err(RecvTimeoutError::Timeout) => {}
+        // The backend stopped; keep going by polling
+        Err(RecvTimeoutError::Disconnected) => std::thread::sleep(interval),
+    }
+}
+
+/// Run watch command
+///
+/// Applies once at startup, then again after each settled change. The source
+/// is scanned when an event arrives and every `interval` milliseconds while a
+/// change settles; without events it is scanned every `interval`. Config,
+/// variables and templates are reloaded for every apply. The state database
+/// is opened only while applying, so other guisu commands can run in between.
+///
+/// Each apply runs like `guisu serve` does: hooks and source scripts are not
+/// run, and destinations modified locally are skipped rather than overwritten.
+///
+/// # Errors
+///
+/// Returns an error if the source directory does not exist
+pub fn run_watch(
+    db_path: &Path,
+    source_dir: &Path,
+    dest_dir: &Path,
+    config_path: Option<&Path>,
+    profile: Option<&str>,
+    interval: u64,
+    debounce: u64,
+) -> Result<()> {
+    if !source_dir.is_dir() {
+        anyhow::bail!("Source directory not found: {}", source_dir.display());
+    }
+
+    let mut watcher = SourceWatcher::new(source_dir.to_path_buf(), Duration::from_millis(debounce));
+    let interval = Duration::from_millis(interval);
+
+    // The watcher stops sending events when dropped, so keep it alive
+    let (tx, rx) = std::sync::mpsc::channel();
+    let (_events, rx) = match watch_events(source_dir, tx) {
+        Ok(events) => (Some(events), Some(rx)),
+        Err(e) => {
+            warn!("File system events unavailable, polling instead: {e}");
+            (None, None)
+        }
+    };
+
+    println!(
+        "Watching {} for changes (Ctrl-C to stop)",
+        source_dir.display().bright_white()
+    );
+    run_cycle(db_path, source_dir, dest_dir, config_path, profile, &[]);
+
+    loop {
+        wait_for_change(rx.as_ref(), watcher.is_settling(), interval);
+
+        if let Some(changes) = watcher.poll(Instant::now()) {
+            debug!(count = changes.len(), "Source changed");
+            run_cycle(
+                db_path,
+                source_dir,
+                dest_dir,
+                config_path,
+                profile,
+                &changes,
+            );
+        }
+    }
+}
+
+/// Apply once and print a summary line, reporting failures as warnings
+fn run_cycle(
+    db_path: &Path,
+    source_dir: &Path,
+    dest_dir: &Path,
+    config_path: Option<&Path>,
+    profile: Option<&str>,
+    changes: &[PathBuf],
+) {
+    let start = Instant::now();
+    let result = apply_once(db_path, source_dir, dest_dir, config_path, profile);
+    let elapsed = start.elapsed();
+
+    let time = chrono::Local::now().format("%H:%M:%S").to_string();
+    let trigger = match changes {
+        [] => "initial apply".to_string(),
+        [path] => path.display().to_string(),
+        [path, rest @ ..] => format!("{} and {} more", path.display(), rest.len()),
+    };
+
+    match result {
+        Ok(report) => {
+            println!(
+                "{}  {}  {}",
+                time.dimmed(),
+                format_summary(&report),
+                format!("{trigger}, {}ms", elapsed.as_millis()).dimmed()
+            );
+            for path in &report.skipped {
+                println!("  {} {} (modified locally)", "skipped".yellow(), path);
+            }
+            for failure in &report.failed {
+                println!("  {} {}: {}", "failed".red(), failure.path, failure.error);
+            }
+        }
+        Err(e) => {
+            warn!("Watch apply failed: {e:#}");
+            println!(
+                "{}  {} {:#}  {}",
+                time.dimmed(),
+                "Apply failed:".red(),
+                e,
+                trigger.dimmed()
+            );
+        }
+    }
+}
+
+/// Load config and state, then apply the whole source unattended
+fn apply_once(
+    db_path: &Path,
+    source_dir: &Path,
+    dest_dir: &Path,
+    config_path: Option<&Path>,
+    profile: Option<&str>,
+) -> Result<ApplyReport> {
+    // Wait for other writing commands rather than interleave with them
+    let _run_lock =
+        crate::utils::lock::RunLock::acquire(&crate::utils::lock::lock_path()?, "watch", true)?;
+    let database =
+        Arc::new(guisu_engine::database::open(db_path).context("Failed to open state database")?);
+    let mut config =
+        crate::load_config_with_template_support(config_path, source_dir, Some(&database))?;
+    crate::activate_profile(&mut config, source_dir, profile)?;
+    let paths = ResolvedPaths::resolve(source_dir, dest_dir, &config)?;
+    let context = RuntimeContext::from_parts_with_db(Arc::new(config), paths, database);
+
+    let apply_cmd = ApplyCommand {
+        files: vec![],
+        dry_run: false,
+        force: false,
+        merge: false,
+        prune: false,
+        interactive: false,
+        include: vec![],
+        exclude: vec![],
+        jobs: None,
+        backup: false,
+        refresh_externals: false,
+        one_shot: None,
+        source_ref: None,
+        timings: false,
+    };
+
+    apply_cmd.execute_unattended(&context)
+}
+
+/// One-line summary of an apply, e.g. "● 2 applied ● 1 skipped"
+fn format_summary(report: &ApplyReport) -> String {
+    let mut summary = format!(
+        "{} {} applied",
+        "●".bright_green(),
+        report.applied.len().to_string().bright_green().bold()
+    );
+    if !report.skipped.is_empty() {
+        let _ = write!(
+            summary,
+            " {} {} skipped",
+            "●".yellow(),
+            report.skipped.len().to_string().yellow().bold()
+        );
+    }
+    if !report.failed.is_empty() {
+        let _ = write!(
+            summary,
+            " {} {} failed",
+            "●".bright_red(),
+            report.failed.len().to_string().bright_red().bold()
+        );
+    }
+    summary
+}
+
+#[cfg(test)]
+mod tests {
+    #![allow(clippy::unwrap_used, clippy::panic)]
+    use super::*;
+    use std::fs;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_snapshot_changes() {
+        let temp = TempDir::new().unwrap();
+        fs::write(temp.path().join("kept"), "a").unwrap();
+        fs::write(temp.path().join("removed"), "a").unwrap();
+        let before = SourceSnapshot::capture(temp.path());
+
+        fs::write(temp.path().join("kept"), "changed").unwrap();
+        fs::remove_file(temp.path().join("removed")).unwrap();
+        fs::write(temp.path().join("added"), "a").unwrap();
+        let after = SourceSnapshot::capture(temp.path());
+
+        assert_eq!(
+            before.changes(&after),
+            vec![
+                PathBuf::from("added"),
+                PathBuf::from("kept"),
+                PathBuf::from("removed")
+            ]
+        );
+    }
+
+    #[test]
+    fn test_snapshot_skips_git_directory() {
+        let temp = TempDir::new().unwrap();
+        fs::create_dir(temp.path().join(".git")).unwrap();
+        fs::write(temp.path().join(".git/index"), "a").unwrap();
+        fs::write(temp.path().join(".guisu.toml"), "").unwrap();
+
+        let snapshot = SourceSnapshot::capture(temp.path());
+
+        assert_eq!(snapshot.files.len(), 1);
+        assert!(snapshot.files.contains_key(Path::new(".guisu.toml")));
+    }
+
+    #[test]
+    fn test_watcher_reports_change_after_debounce() {
+        let temp = TempDir::new().unwrap();
+        let debounce = Duration::from_millis(300);
+        let mut watcher = SourceWatcher::new(temp.path().to_path_buf(), debounce);
+        let start = Instant::now();
+
+        assert_eq!(watcher.poll(start), None);
+
+        fs::write(temp.path().join("dot_zshrc"), "export A=1").unwrap();
+        assert_eq!(watcher.poll(start), None);
+        assert_eq!(watcher.poll(start + Duration::from_millis(100)), None);
+        assert_eq!(
+            watcher.poll(start + debounce),
+            Some(vec![PathBuf::from("dot_zshrc")])
+        );
+
+        // Reported once
+        assert_eq!(watcher.poll(start + debounce * 2), None);
+    }
+
+    #[test]
+    fn test_watcher_restarts_debounce_on_further_changes() {
+        let temp = TempDir::new().unwrap();
+        let debounce = Duration::from_millis(300);
+        let mut watcher = SourceWatcher::new(temp.path().to_path_buf(), debounce);
+        let start = Instant::now();
+
+        fs::write(temp.path().join("a"), "1").unwrap();
+        assert_eq!(watcher.poll(start), None);
+
+        fs::write(temp.path().join("b"), "1").unwrap();
+        assert_eq!(watcher.poll(start + Duration::from_millis(200)), None);
+        assert_eq!(watcher.poll(start + Duration::from_millis(400)), None);
+        assert_eq!(
+            watcher.poll(start + Duration::from_millis(500)),
+            Some(vec![PathBuf::from("a"), PathBuf::from("b")])
+        );
+    }
+
+    #[test]
+    fn test_in_git_dir() {
+        assert!(in_git_dir(Path::new("/src/.git/index")));
+        assert!(!in_git_dir(Path::new("/src/home/.gitconfig")));
+        assert!(!in_git_dir(Path::new("/src/home/dot_git")));
+    }
+
+    #[test]
+    fn test_events_wake_on_source_changes() {
+        let temp = TempDir::new().unwrap();
+        let root = fs::canonicalize(temp.path()).unwrap();
+        let (tx, rx) = std::sync::mpsc::channel();
+        let _events = watch_events(&root, tx).unwrap();
+
+        fs::write(root.join("dot_zshrc"), "export A=1").unwrap();
+        assert!(rx.recv_timeout(Duration::from_secs(5)).is_ok());
+    }
+
+    #[test]
+    fn test_watcher_ignores_reverted_change() {
+        let temp = TempDir::new().unwrap();
+        let mut watcher = SourceWatcher::new(temp.path().to_path_buf(), Duration::ZERO);
+        let start = Instant::now();
+
+        fs::write(temp.path().join("tmp"), "1").unwrap();
+        assert_eq!(watcher.poll(start), None);
+        fs::remove_file(temp.path().join("tmp")).unwrap();
+
+        assert_eq!(watcher.poll(start), None);
+        assert_eq!(watcher.poll(start), None);
+    }
+}
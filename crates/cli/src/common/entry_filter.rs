@@ -0,0 +1,257 @@
+//! Entry filters for `--include` and `--exclude`
+//!
+//! Shared by the commands that walk managed entries (apply, status, diff,
+//! cat) so a filter selects the same entries everywhere.
+
+use anyhow::{Context, Result};
+use globset::{Glob, GlobSet, GlobSetBuilder};
+use guisu_core::path::RelPath;
+use guisu_engine::entry::{SourceEntry, TargetEntry};
+use guisu_engine::state::{ManagedKind, SourceState};
+use std::path::Path;
+
+/// Entry types accepted by `--include` and `--exclude`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EntryType {
+    /// Regular files, including templates and encrypted files
+    Files,
+    /// Directories
+    Dirs,
+    /// Symbolic links
+    Symlinks,
+    /// Template files (`.j2`)
+    Templates,
+    /// Encrypted files (`.age`)
+    Encrypted,
+}
+
+impl std::str::FromStr for EntryType {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s.to_lowercase().as_str() {
+            "files" | "file" => Ok(EntryType::Files),
+            "dirs" | "dir" | "directories" => Ok(EntryType::Dirs),
+            "symlinks" | "symlink" => Ok(EntryType::Symlinks),
+            "templates" | "template" => Ok(EntryType::Templates),
+            "encrypted" | "encrypt" => Ok(EntryType::Encrypted),
+            _ => anyhow::bail!(
+                "Invalid entry type: {s}. Valid types: files, dirs, symlinks, templates, encrypted"
+            ),
+        }
+    }
+}
+
+/// Types and globs from one of `--include` or `--exclude`
+#[derive(Debug, Clone, Default)]
+struct Selector {
+    types: Vec<EntryType>,
+    globs: Option<GlobSet>,
+}
+
+impl Selector {
+    /// Parse values that are either entry types or glob patterns
+    ///
+    /// A value containing `*`, `?`, `[`, `{`, `/` or `.` is a glob matched
+    /// against the target path; anything else must be an entry type.
+    fn parse(values: &[String]) -> Result<Self> {
+        let mut types = Vec::new();
+        let mut globs = GlobSetBuilder::new();
+        let mut has_globs = false;
+
+        for value in values {
+            if value.contains(['*', '?', '[', '{', '/', '.']) {
+                globs.add(
+                    Glob::new(value).with_context(|| format!("Invalid glob pattern: {value}"))?,
+                );
+                has_globs = true;
+            } else {
+                types.push(value.parse()?);
+            }
+        }
+
+        let globs = if has_globs {
+            Some(globs.build().context("Failed to build glob patterns")?)
+        } else {
+            None
+        };
+        Ok(Self { types, globs })
+    }
+
+    fn is_empty(&self) -> bool {
+        self.types.is_empty() && self.globs.is_none()
+    }
+
+    fn matches_type(&self, types: &[EntryType]) -> bool {
+        self.types.iter().any(|t| types.contains(t))
+    }
+
+    fn matches_glob(&self, path: &Path) -> bool {
+        self.globs
+            .as_ref()
+            .is_some_and(|globs| globs.is_match(path))
+    }
+}
+
+/// Entry filter built from `--include` and `--exclude` values
+///
+/// An entry is selected when it has one of the included types (if any are
+/// given) and matches one of the included globs (if any are given), and
+/// neither has an excluded type nor matches an excluded glob. Templates and
+/// encrypted files are files too, so `files` selects them as well.
+#[derive(Debug, Clone, Default)]
+pub struct EntryFilter {
+    include: Selector,
+    exclude: Selector,
+}
+
+impl EntryFilter {
+    /// Parse `--include` and `--exclude` values
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if a value is neither an entry type nor a valid glob
+    pub fn new(include: &[String], exclude: &[String]) -> Result<Self> {
+        Ok(Self {
+            include: Selector::parse(include)?,
+            exclude: Selector::parse(exclude)?,
+        })
+    }
+
+    /// Check if the filter selects every entry
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.include.is_empty() && self.exclude.is_empty()
+    }
+
+    /// Check if a source entry is selected
+    #[must_use]
+    pub fn matches_source(&self, entry: &SourceEntry) -> bool {
+        let types: &[EntryType] = match entry {
+            SourceEntry::File { .. } => match (entry.is_template(), entry.is_encrypted()) {
+                (true, true) => &[EntryType::Files, EntryType::Templates, EntryType::Encrypted],
+                (true, false) => &[EntryType::Files, EntryType::Templates],
+                (false, true) => &[EntryType::Files, EntryType::Encrypted],
+                (false, false) => &[EntryType::Files],
+            },
+            SourceEntry::Directory { .. } => &[EntryType::Dirs],
+            SourceEntry::Symlink { .. } => &[EntryType::Symlinks],
+            SourceEntry::Script { .. } => &[],
+        };
+        self.matches(entry.target_path(), types)
+    }
+
+    /// Check if a target entry without a source entry (an external or a
+    /// removal) is selected
+    #[must_use]
+    pub fn matches_target(&self, entry: &TargetEntry) -> bool {
+        match ManagedKind::of(entry) {
+            Some(kind) => self.matches_managed(entry.path(), kind),
+            None => self.matches(entry.path(), &[]),
+        }
+    }
+
+    /// Check if a managed target of the given kind is selected
+    #[must_use]
+    pub fn matches_managed(&self, path: &RelPath, kind: ManagedKind) -> bool {
+        let entry_type = match kind {
+            ManagedKind::File => EntryType::Files,
+            ManagedKind::Directory => EntryType::Dirs,
+            ManagedKind::Symlink => EntryType::Symlinks,
+        };
+        self.matches(path, &[entry_type])
+    }
+
+    /// Drop the source entries the filter does not select
+    pub fn retain(&self, source_state: &mut SourceState) {
+        if !self.is_empty() {
+            source_state.retain(|entry| self.matches_source(entry));
+        }
+    }
+
+    fn matches(&self, path: &RelPath, types: &[EntryType]) -> bool {
+        let path = path.as_path();
+        let included = (self.include.types.is_empty() || self.include.matches_type(types))
+            && (self.include.globs.is_none() || self.include.matches_glob(path));
+        included && !self.exclude.matches_type(types) && !self.exclude.matches_glob(path)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    #![allow(clippy::unwrap_used, clippy::panic)]
+    use super::*;
+    use guisu_core::path::SourceRelPath;
+    use guisu_engine::attr::FileAttributes;
+    use std::path::PathBuf;
+
+    fn filter(include: &[&str], exclude: &[&str]) -> EntryFilter {
+        let to_vec = |values: &[&str]| values.iter().map(ToString::to_string).collect::<Vec<_>>();
+        EntryFilter::new(&to_vec(include), &to_vec(exclude)).unwrap()
+    }
+
+    fn file(path: &str, attributes: FileAttributes) -> SourceEntry {
+        SourceEntry::File {
+            source_path: SourceRelPath::new(PathBuf::from(path)).unwrap(),
+            target_path: RelPath::new(PathBuf::from(path)).unwrap(),
+            attributes,
+        }
+    }
+
+    #[test]
+    fn test_empty_filter_selects_everything() {
+        let filter = filter(&[], &[]);
+        assert!(filter.is_empty());
+        assert!(filter.matches_source(&file(".bashrc", FileAttributes::new())));
+    }
+
+    #[test]
+    fn test_types() {
+        let mut template = FileAttributes::new();
+        template.set_template(true);
+        let template = file(".gitconfig", template);
+        let plain = file(".bashrc", FileAttributes::new());
+
+        let templates = filter(&["templates"], &[]);
+        assert!(templates.matches_source(&template));
+        assert!(!templates.matches_source(&plain));
+
+        // Templates are files too
+        assert!(filter(&["files"], &[]).matches_source(&template));
+        assert!(!filter(&["files"], &["templates"]).matches_source(&template));
+        assert!(filter(&["files"], &["templates"]).matches_source(&plain));
+    }
+
+    #[test]
+    fn test_globs() {
+        let zsh = file(".config/zsh/aliases.zsh", FileAttributes::new());
+        let bashrc = file(".bashrc", FileAttributes::new());
+
+        let include = filter(&[".config/**"], &[]);
+        assert!(include.matches_source(&zsh));
+        assert!(!include.matches_source(&bashrc));
+
+        assert!(!filter(&[], &["*.zsh"]).matches_source(&zsh));
+        assert!(filter(&[], &["*.zsh"]).matches_source(&bashrc));
+
+        // Types and globs must both match
+        assert!(!filter(&["dirs", ".config/**"], &[]).matches_source(&zsh));
+    }
+
+    #[test]
+    fn test_managed_and_removals() {
+        let path = RelPath::new(PathBuf::from(".config")).unwrap();
+        assert!(filter(&["dirs"], &[]).matches_managed(&path, ManagedKind::Directory));
+        assert!(!filter(&["files"], &[]).matches_managed(&path, ManagedKind::Directory));
+
+        let removal = TargetEntry::Remove { path };
+        assert!(!filter(&["files"], &[]).matches_target(&removal));
+        assert!(filter(&[], &["templates"]).matches_target(&removal));
+    }
+
+    #[test]
+    fn test_invalid_value() {
+        let err = EntryFilter::new(&["fils".to_string()], &[]).unwrap_err();
+        assert!(err.to_string().contains("Invalid entry type"));
+    }
+}
@@ -1,8 +1,15 @@
 //! Common utilities and types shared across CLI commands
 
+pub mod entry_filter;
+#[cfg(test)]
+pub(crate) mod testing;
+
+pub use entry_filter::{EntryFilter, EntryType};
+
 use anyhow::{Context, Result};
-use guisu_config::Config;
-use guisu_core::path::AbsPath;
+use guisu_config::{Config, Targets};
+use guisu_core::path::{AbsPath, RelPath};
+use guisu_engine::clock::StateClock;
 use guisu_engine::state::RedbPersistentState;
 use std::fs;
 use std::path::{Path, PathBuf};
@@ -19,6 +26,8 @@ pub struct ResolvedPaths {
     pub dest_dir: AbsPath,
     /// Canonicalized dotfiles directory (accounts for `root_entry` config)
     pub dotfiles_dir: AbsPath,
+    /// Destination roots of subtrees applied outside `dest_dir`
+    pub targets: Targets,
 }
 
 impl ResolvedPaths {
@@ -30,6 +39,7 @@ impl ResolvedPaths {
     /// - Canonicalizing the dotfiles directory fails
     /// - Canonicalizing the destination directory fails
     /// - Creating absolute path wrappers fails
+    /// - Loading `.guisu/targets.toml` fails
     pub fn resolve(source_dir: &Path, dest_dir: &Path, config: &Config) -> Result<Self> {
         let dotfiles_dir = config.dotfiles_dir(source_dir);
 
@@ -52,12 +62,52 @@ impl ResolvedPaths {
             format!("Destination directory not found: {}", dest_dir.display())
         })?)?;
 
+        let targets = Targets::load(source_dir).context("Failed to load .guisu/targets.toml")?;
+
         Ok(Self {
             source_dir: source_dir.to_path_buf(),
             dest_dir: dest_abs,
             dotfiles_dir: dotfiles_abs,
+            targets,
         })
     }
+
+    /// Absolute destination of a target path, honoring `.guisu/targets.toml`
+    #[must_use]
+    pub fn dest_path(&self, target: &RelPath) -> AbsPath {
+        self.targets.resolve(&self.dest_dir, target)
+    }
+}
+
+/// Runtime context without a state database
+///
+/// The database is locked for as long as it is open, so long-running commands
+/// keep this between operations and [`attach`](Self::attach) a freshly opened
+/// database for each one.
+#[derive(Clone)]
+pub struct DetachedContext {
+    config: Arc<Config>,
+    paths: ResolvedPaths,
+    clock: StateClock,
+    identities_cache: Arc<std::sync::OnceLock<Arc<[guisu_crypto::Identity]>>>,
+    guisu_dir_cache: Arc<std::sync::OnceLock<PathBuf>>,
+    templates_dir_cache: Arc<std::sync::OnceLock<Option<PathBuf>>>,
+}
+
+impl DetachedContext {
+    /// Runtime context using `database`, sharing this context's caches
+    #[must_use]
+    pub fn attach(&self, database: Arc<RedbPersistentState>) -> RuntimeContext {
+        RuntimeContext {
+            config: Arc::clone(&self.config),
+            paths: self.paths.clone(),
+            database,
+            clock: self.clock.clone(),
+            identities_cache: Arc::clone(&self.identities_cache),
+            guisu_dir_cache: Arc::clone(&self.guisu_dir_cache),
+            templates_dir_cache: Arc::clone(&self.templates_dir_cache),
+        }
+    }
 }
 
 /// Runtime context for CLI commands
@@ -71,6 +121,8 @@ pub struct RuntimeContext {
     pub paths: ResolvedPaths,
     /// Database instance for persistent state
     pub database: Arc<RedbPersistentState>,
+    /// Timestamp and run ID source for state writes
+    pub clock: StateClock,
     identities_cache: Arc<std::sync::OnceLock<Arc<[guisu_crypto::Identity]>>>,
     guisu_dir_cache: Arc<std::sync::OnceLock<PathBuf>>,
     templates_dir_cache: Arc<std::sync::OnceLock<Option<PathBuf>>>,
@@ -89,12 +141,13 @@ impl RuntimeContext {
         let db_path =
             guisu_engine::database::get_db_path().context("Failed to get database path")?;
         let database =
-            RedbPersistentState::new(&db_path).context("Failed to create database instance")?;
+            guisu_engine::database::open(&db_path).context("Failed to create database instance")?;
 
         Ok(Self {
             config: Arc::new(config),
             paths,
             database: Arc::new(database),
+            clock: StateClock::system(),
             identities_cache: Arc::new(std::sync::OnceLock::new()),
             guisu_dir_cache: Arc::new(std::sync::OnceLock::new()),
             templates_dir_cache: Arc::new(std::sync::OnceLock::new()),
@@ -112,12 +165,13 @@ impl RuntimeContext {
         // Initialize database (panic on failure since this is a convenience constructor)
         let db_path = guisu_engine::database::get_db_path().expect("Failed to get database path");
         let database =
-            RedbPersistentState::new(&db_path).expect("Failed to create database instance");
+            guisu_engine::database::open(&db_path).expect("Failed to create database instance");
 
         Self {
             config,
             paths,
             database: Arc::new(database),
+            clock: StateClock::system(),
             identities_cache: Arc::new(std::sync::OnceLock::new()),
             guisu_dir_cache: Arc::new(std::sync::OnceLock::new()),
             templates_dir_cache: Arc::new(std::sync::OnceLock::new()),
@@ -138,12 +192,28 @@ impl RuntimeContext {
             config,
             paths,
             database,
+            clock: StateClock::system(),
             identities_cache: Arc::new(std::sync::OnceLock::new()),
             guisu_dir_cache: Arc::new(std::sync::OnceLock::new()),
             templates_dir_cache: Arc::new(std::sync::OnceLock::new()),
         }
     }
 
+    /// Close this context's handle on the state database, keeping the rest
+    ///
+    /// The database is closed once no other clone of the context holds it.
+    #[must_use]
+    pub fn detach(self) -> DetachedContext {
+        DetachedContext {
+            config: self.config,
+            paths: self.paths,
+            clock: self.clock,
+            identities_cache: self.identities_cache,
+            guisu_dir_cache: self.guisu_dir_cache,
+            templates_dir_cache: self.templates_dir_cache,
+        }
+    }
+
     /// Get the source directory (original input, may contain .guisu)
     #[inline]
     #[must_use]
@@ -158,6 +228,20 @@ impl RuntimeContext {
         &self.paths.dest_dir
     }
 
+    /// Get the destination roots of subtrees applied outside the destination directory
+    #[inline]
+    #[must_use]
+    pub fn targets(&self) -> &Targets {
+        &self.paths.targets
+    }
+
+    /// Get the absolute destination of a target path
+    #[inline]
+    #[must_use]
+    pub fn dest_path(&self, target: &RelPath) -> AbsPath {
+        self.paths.dest_path(target)
+    }
+
     /// Get the dotfiles directory (canonicalized, includes `root_entry` if configured)
     #[inline]
     #[must_use]
@@ -209,6 +293,15 @@ impl RuntimeContext {
             .unwrap_or_else(guisu_crypto::Identity::generate))
     }
 
+    /// Replace the clock used to stamp state writes
+    ///
+    /// Tests use this with [`StateClock::fixed`] to get deterministic timestamps and run IDs.
+    #[must_use]
+    pub fn with_clock(mut self, clock: StateClock) -> Self {
+        self.clock = clock;
+        self
+    }
+
     /// Get the .guisu directory path
     #[must_use]
     pub fn guisu_dir(&self) -> &PathBuf {
@@ -237,12 +330,13 @@ impl RuntimeContext {
             .unwrap_or_else(|| self.source_dir().to_path_buf())
     }
 
-    /// Create runtime context with a custom database path (test only)
+    /// Create runtime context with a custom database path
+    ///
+    /// Keeps tests and benchmarks away from the user's state database.
     ///
     /// # Errors
     ///
     /// Returns an error if resolving paths fails or database creation fails
-    #[cfg(test)]
     pub fn new_with_db_path(
         config: Config,
         source_dir: &Path,
@@ -253,12 +347,13 @@ impl RuntimeContext {
 
         // Initialize database with custom path
         let database =
-            RedbPersistentState::new(db_path).context("Failed to create database instance")?;
+            guisu_engine::database::open(db_path).context("Failed to create database instance")?;
 
         Ok(Self {
             config: Arc::new(config),
             paths,
             database: Arc::new(database),
+            clock: StateClock::system(),
             identities_cache: Arc::new(std::sync::OnceLock::new()),
             guisu_dir_cache: Arc::new(std::sync::OnceLock::new()),
             templates_dir_cache: Arc::new(std::sync::OnceLock::new()),
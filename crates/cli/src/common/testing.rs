@@ -0,0 +1,70 @@
+//! Test helpers for commands that work on a source and destination
+#![allow(clippy::unwrap_used)]
+
+use super::{DetachedContext, RuntimeContext};
+use guisu_config::Config;
+use std::fs;
+use std::path::{Path, PathBuf};
+use tempfile::TempDir;
+
+/// Temporary source and destination with a runtime context for them
+///
+/// Under a canonicalized temporary root, the source is `source` (with an
+/// empty `source/home`), the destination is `dest`, and the state database
+/// is `state.db`, so tests never touch the real state.
+pub(crate) struct TestWorkspace {
+    /// Removed with the workspace
+    temp: TempDir,
+    /// Directory holding the source, destination and state database
+    pub root: PathBuf,
+    /// Context for the source and destination
+    pub context: RuntimeContext,
+}
+
+impl TestWorkspace {
+    /// Create an empty source and destination using `config`
+    pub(crate) fn new(config: Config) -> Self {
+        let temp = TempDir::new().unwrap();
+        let root = fs::canonicalize(temp.path()).unwrap();
+        fs::create_dir_all(root.join("source/home")).unwrap();
+        fs::create_dir_all(root.join("dest")).unwrap();
+
+        let context = RuntimeContext::new_with_db_path(
+            config,
+            &root.join("source"),
+            &root.join("dest"),
+            &root.join("state.db"),
+        )
+        .unwrap();
+
+        Self {
+            temp,
+            root,
+            context,
+        }
+    }
+
+    /// Path of `path` in the dotfiles directory
+    pub(crate) fn source(&self, path: &str) -> PathBuf {
+        self.context.dotfiles_dir().as_path().join(path)
+    }
+
+    /// Path of `path` in the destination
+    pub(crate) fn dest(&self, path: &str) -> PathBuf {
+        self.context.dest_dir().as_path().join(path)
+    }
+
+    /// Close the state database, keeping the temporary root and the context
+    ///
+    /// For commands that open the database themselves, at `state.db` under
+    /// the root.
+    pub(crate) fn detach(self) -> (TempDir, PathBuf, DetachedContext) {
+        (self.temp, self.root, self.context.detach())
+    }
+}
+
+/// Write `content` to `path`, creating its parent directories
+pub(crate) fn write(path: &Path, content: impl AsRef<[u8]>) {
+    fs::create_dir_all(path.parent().unwrap()).unwrap();
+    fs::write(path, content).unwrap();
+}
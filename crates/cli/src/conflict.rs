@@ -4,7 +4,7 @@
 
 use anyhow::{Context, Result, anyhow};
 use guisu_core::path::AbsPath;
-use guisu_engine::entry::TargetEntry;
+use guisu_engine::entry::{EntryKind, TargetEntry};
 use owo_colors::OwoColorize;
 use std::fs;
 use subtle::ConstantTimeEq;
@@ -12,6 +12,7 @@ use subtle::ConstantTimeEq;
 use crate::ui::{
     ChangePreview, ChangeSummary, ConflictAction, ConflictPrompt, DiffFormat, DiffViewer,
 };
+use crate::utils::secrets::KeyChanges;
 use guisu_config::Config;
 
 // File permission constants
@@ -26,6 +27,25 @@ pub enum ChangeType {
     SourceUpdate,
     /// Both source and destination were modified (true conflict)
     TrueConflict,
+    /// The destination is a different kind of entry than the source
+    /// (e.g. a directory where the source has a file)
+    TypeMismatch {
+        /// Kind of entry in the target state
+        expected: EntryKind,
+        /// Kind of entry found at the destination
+        found: EntryKind,
+    },
+}
+
+/// Describe an entry kind for messages ("a file", "a directory", ...)
+#[must_use]
+pub fn describe_kind(kind: EntryKind) -> &'static str {
+    match kind {
+        EntryKind::File => "a file",
+        EntryKind::Directory => "a directory",
+        EntryKind::Symlink => "a symlink",
+        EntryKind::Missing => "nothing",
+    }
 }
 
 /// Result of three-way comparison
@@ -164,13 +184,9 @@ impl ConflictHandler {
         identities: &[guisu_crypto::Identity],
     ) -> Result<Option<ChangeType>> {
         // Only check files
-        let TargetEntry::File {
-            content: target_content,
-            ..
-        } = entry
-        else {
+        if !matches!(entry, TargetEntry::File { .. }) {
             return Ok(None);
-        };
+        }
 
         let dest_path = dest_abs.join(entry.path());
 
@@ -183,31 +199,55 @@ impl ConflictHandler {
         let actual_content = fs::read(dest_path.as_path())
             .with_context(|| format!("Failed to read destination file: {dest_path}"))?;
 
+        Ok(Self::classify_change(
+            entry,
+            &actual_content,
+            last_written_hash,
+            identities,
+        ))
+    }
+
+    /// Classify the change for a file whose destination content is already known
+    ///
+    /// Same three-way comparison as [`Self::detect_change_type`], for callers
+    /// that have read the destination themselves. Returns `None` for non-file
+    /// entries.
+    #[must_use]
+    pub fn classify_change(
+        entry: &TargetEntry,
+        actual_content: &[u8],
+        last_written_hash: Option<&[u8]>,
+        identities: &[guisu_crypto::Identity],
+    ) -> Option<ChangeType> {
+        let TargetEntry::File {
+            content: target_content,
+            ..
+        } = entry
+        else {
+            return None;
+        };
+
         // Decrypt inline age: values in target_content before hashing (to match status behavior)
-        let target_content_decrypted = if identities.is_empty() {
-            target_content.clone()
-        } else if let Ok(content_str) = String::from_utf8(target_content.clone()) {
-            if content_str.contains("age:") {
-                if let Ok(decrypted) = guisu_crypto::decrypt_file_content(&content_str, identities)
-                {
-                    decrypted.into_bytes()
-                } else {
-                    target_content.clone()
-                }
-            } else {
-                target_content.clone()
-            }
+        let decrypted = if identities.is_empty() {
+            None
         } else {
-            target_content.clone()
+            std::str::from_utf8(target_content)
+                .ok()
+                .filter(|content_str| content_str.contains("age:"))
+                .and_then(|content_str| {
+                    guisu_crypto::decrypt_file_content(content_str, identities).ok()
+                })
         };
+        let target_content_decrypted = decrypted
+            .as_ref()
+            .map_or(&**target_content, String::as_bytes);
 
         // Compute hashes for three-way comparison
-        let target_hash = guisu_engine::hash::hash_content(&target_content_decrypted);
-        let actual_hash = guisu_engine::hash::hash_content(&actual_content);
+        let target_hash = guisu_engine::hash::hash_content(target_content_decrypted);
+        let actual_hash = guisu_engine::hash::hash_content(actual_content);
 
         // Use the unified three-way comparison function
-        let result = compare_three_way(&target_hash, &actual_hash, last_written_hash);
-        Ok(result.into())
+        compare_three_way(&target_hash, &actual_hash, last_written_hash).into()
     }
 
     /// Check if a conflict exists (for backward compatibility)
@@ -229,7 +269,7 @@ impl ConflictHandler {
     /// # Arguments
     ///
     /// * `entry` - The target entry with the change
-    /// * `dest_abs` - Absolute path to the destination directory
+    /// * `dest_path` - Absolute path of the entry's destination
     /// * `last_written_content` - Last written content from database (for merge)
     /// * `change_type` - Type of change detected
     ///
@@ -246,40 +286,69 @@ impl ConflictHandler {
     pub fn prompt_action(
         &mut self,
         entry: &TargetEntry,
-        dest_abs: &AbsPath,
+        dest_path: &AbsPath,
         _last_written_content: Option<&[u8]>,
         change_type: ChangeType,
+        env_file: bool,
     ) -> Result<ConflictAction> {
         // If override_all is set, always return Override
         if self.override_all {
             return Ok(ConflictAction::Override);
         }
 
-        let TargetEntry::File {
-            content: target_content,
-            mode: _target_mode,
-            ..
-        } = entry
-        else {
-            return Err(anyhow!("Cannot handle conflict for non-file entry"));
+        if let ChangeType::TypeMismatch { expected, found } = change_type {
+            return self.prompt_type_mismatch(entry, expected, found);
+        }
+
+        // Use appropriate messaging based on change type
+        let title = match change_type {
+            ChangeType::LocalModification => "Local modification:",
+            ChangeType::SourceUpdate => "Source updated:",
+            ChangeType::TrueConflict => "Conflict:",
+            ChangeType::TypeMismatch { .. } => "Type conflict:",
+        };
+
+        let target_content = match entry {
+            TargetEntry::File { content, .. } => content,
+            TargetEntry::LargeFile { .. } => {
+                println!(
+                    "\n{} {} (large file)",
+                    title.yellow().bold(),
+                    entry.path().bright_white()
+                );
+                println!("{}", "Large files cannot be merged or previewed.".dimmed());
+                println!("Choose Override to use source version, or Skip to keep destination.\n");
+                return Self::simple_prompt("Large file - choose action");
+            }
+            _ => return Err(anyhow!("Cannot handle conflict for non-file entry")),
         };
 
         // Decrypt inline age: values in target_content before displaying
         let target_content = self.decrypt_inline_age_values(target_content);
 
-        let dest_path = dest_abs.join(entry.path());
         let actual_content = fs::read(dest_path.as_path())
             .with_context(|| format!("Failed to read destination file: {dest_path}"))?;
 
+        // Secret env files are summarized by key name, never previewed
+        if env_file && !is_binary(&target_content) && !is_binary(&actual_content) {
+            println!(
+                "\n{} {} (secret env file)",
+                title.yellow().bold(),
+                entry.path().bright_white()
+            );
+            let changes = KeyChanges::between(
+                &String::from_utf8_lossy(&actual_content),
+                &String::from_utf8_lossy(&target_content),
+            );
+            for line in changes.lines() {
+                println!("  {line}");
+            }
+            println!("Choose Override to use source version, or Skip to keep destination.\n");
+            return Self::simple_prompt("Secret env file - choose action");
+        }
+
         // Check if binary
         if is_binary(&target_content) || is_binary(&actual_content) {
-            // Use appropriate messaging based on change type
-            let title = match change_type {
-                ChangeType::LocalModification => "Local modification:",
-                ChangeType::SourceUpdate => "Source updated:",
-                ChangeType::TrueConflict => "Conflict:",
-            };
-
             println!(
                 "\n{} {} (binary file)",
                 title.yellow().bold(),
@@ -289,7 +358,7 @@ impl ConflictHandler {
             println!("Choose Override to use source version, or Skip to keep destination.\n");
 
             // Simple prompt for binary files
-            return Self::simple_prompt(entry);
+            return Self::simple_prompt("Binary file - choose action");
         }
 
         // Generate change summary and preview
@@ -310,7 +379,7 @@ impl ConflictHandler {
             match action {
                 ConflictAction::Diff => {
                     // Show full diff
-                    self.show_diff(entry, dest_abs)?;
+                    self.show_diff(entry, dest_path)?;
                     println!("\nPress Enter to continue...");
                     let mut input = String::new();
                     std::io::stdin().read_line(&mut input)?;
@@ -325,8 +394,62 @@ impl ConflictHandler {
         }
     }
 
-    /// Simple prompt for binary files (no preview/merge available)
-    fn simple_prompt(_entry: &TargetEntry) -> Result<ConflictAction> {
+    /// Prompt for a destination of the wrong kind (no diff available)
+    ///
+    /// Replacing renames the existing destination to a backup first.
+    fn prompt_type_mismatch(
+        &mut self,
+        entry: &TargetEntry,
+        expected: EntryKind,
+        found: EntryKind,
+    ) -> Result<ConflictAction> {
+        use dialoguer::{Select, theme::ColorfulTheme};
+
+        println!(
+            "\n{} {}",
+            "Type conflict:".yellow().bold(),
+            entry.path().bright_white()
+        );
+        println!(
+            "The source has {}, but the destination is {}.",
+            describe_kind(expected),
+            describe_kind(found)
+        );
+        println!(
+            "{}\n",
+            "Replacing renames the existing entry to <name>.guisu-backup first.".dimmed()
+        );
+
+        let options = vec![
+            "Replace - back up the destination and apply source",
+            "Skip - keep destination as-is",
+            "All Override - apply source for all remaining",
+            "Quit - exit operation",
+        ];
+
+        let theme = ColorfulTheme::default();
+        let selection = Select::with_theme(&theme)
+            .with_prompt("Type conflict - choose action")
+            .items(&options)
+            .default(1)
+            .interact()
+            .context("Failed to read user input")?;
+
+        match selection {
+            0 => Ok(ConflictAction::Override),
+            1 => Ok(ConflictAction::Skip),
+            2 => {
+                self.override_all = true;
+                Ok(ConflictAction::Override)
+            }
+            3 => Ok(ConflictAction::Quit),
+            _ => unreachable!(),
+        }
+    }
+
+    /// Simple prompt for binary files and secret env files (no preview/merge
+    /// available)
+    fn simple_prompt(prompt: &str) -> Result<ConflictAction> {
         use dialoguer::{Select, theme::ColorfulTheme};
 
         let options = vec![
@@ -339,7 +462,7 @@ impl ConflictHandler {
 
         let theme = ColorfulTheme::default();
         let selection = Select::with_theme(&theme)
-            .with_prompt("Binary file - choose action")
+            .with_prompt(prompt)
             .items(&options)
             .default(0)
             .interact()
@@ -356,7 +479,7 @@ impl ConflictHandler {
     }
 
     /// Show a diff between target and actual states
-    fn show_diff(&self, entry: &TargetEntry, dest_abs: &AbsPath) -> Result<()> {
+    fn show_diff(&self, entry: &TargetEntry, dest_path: &AbsPath) -> Result<()> {
         let TargetEntry::File {
             content: target_content,
             mode: target_mode,
@@ -366,7 +489,6 @@ impl ConflictHandler {
             return Ok(());
         };
 
-        let dest_path = dest_abs.join(entry.path());
         let actual_content = fs::read(dest_path.as_path())
             .with_context(|| format!("Failed to read destination file: {dest_path}"))?;
 
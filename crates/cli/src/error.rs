@@ -70,6 +70,16 @@ pub enum CommandError {
         total: usize,
     },
 
+    /// Destination does not match the target state
+    ///
+    /// The verify command has already reported the entries, so this is not
+    /// printed again.
+    #[error("{differing} entries do not match the target state")]
+    VerifyFailed {
+        /// Number of entries that differ
+        differing: usize,
+    },
+
     /// File not found
     #[error("File not found: {0}")]
     FileNotFound(PathBuf),
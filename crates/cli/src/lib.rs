@@ -15,8 +15,10 @@ pub mod utils;
 
 use anyhow::{Context, Result};
 use clap::{Parser, Subcommand};
+use guisu_engine::hooks::HookStage;
 use owo_colors::OwoColorize;
 use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
 
 use command::Command;
 use common::RuntimeContext;
@@ -36,6 +38,7 @@ Features:
   • Age encryption for sensitive files
   • Git integration for version control
   • Cross-platform (macOS, Linux, Windows)")]
+#[allow(clippy::struct_excessive_bools)]
 pub struct Cli {
     /// Path to the source directory
     #[arg(long, env = "GUISU_SOURCE_DIR", value_name = "DIR")]
@@ -57,6 +60,30 @@ pub struct Cli {
     #[arg(long, env = "GUISU_LOG_FILE", value_name = "FILE")]
     pub log_file: Option<PathBuf>,
 
+    /// Log format; JSON logs are written to stderr, one object per line
+    #[arg(long, env = "GUISU_LOG_FORMAT", value_enum, default_value = "text")]
+    pub log_format: logging::LogFormat,
+
+    /// Enable trace output: TRACE level logs with per-file spans and their durations
+    #[arg(long)]
+    pub trace: bool,
+
+    /// Profile from .guisu/profiles.toml to use (overrides `[general] profile`)
+    #[arg(long, global = true, env = "GUISU_PROFILE", value_name = "NAME")]
+    pub profile: Option<String>,
+
+    /// Wait for another running guisu to finish instead of failing
+    #[arg(long, global = true, overrides_with = "no_wait")]
+    pub wait: bool,
+
+    /// Fail at once if another guisu is running (default)
+    #[arg(long, global = true, overrides_with = "wait")]
+    pub no_wait: bool,
+
+    /// Never touch the network (overrides `[general] offline`)
+    #[arg(long, global = true, env = "GUISU_OFFLINE")]
+    pub offline: bool,
+
     /// Subcommand to execute
     #[command(subcommand)]
     pub command: Commands,
@@ -65,21 +92,26 @@ pub struct Cli {
 /// Available commands for guisu CLI
 #[derive(Subcommand)]
 pub enum Commands {
-    /// Initialize a new source directory or clone from GitHub
+    /// Initialize a new source directory or clone an existing one
     Init {
-        /// Path to initialize, GitHub username, or GitHub repo (owner/repo).
+        /// Path to initialize, username, repo (owner/repo), or repository URL.
         ///
         /// If not specified, defaults to ~/.local/share/guisu
         #[arg(
             value_name = "PATH_OR_REPO",
-            long_help = "Path to initialize, GitHub username, or GitHub repo (owner/repo).
+            long_help = "Path to initialize, username, repo (owner/repo), or repository URL.
 
+Usernames and owner/repo refer to --host (GitHub by default); prefix them with
+another host (gitlab.com/owner/repo) or pass a full https or ssh URL instead.
 If not specified, defaults to ~/.local/share/guisu
 
 Examples:
   • guisu init
       → Initialize at ~/.local/share/guisu (default)
 
+  • guisu init --template
+      → Start a new repository with a commented .guisu.toml and example hook
+
   • guisu init .
       → Initialize at current directory
 
@@ -89,6 +121,12 @@ Examples:
   • guisu init owner/repo
       → Clone github.com/owner/repo to ~/.local/share/guisu
 
+  • guisu init codeberg.org/owner/repo
+      → Clone codeberg.org/owner/repo to ~/.local/share/guisu
+
+  • guisu init git@git.example.com:me/dotfiles.git
+      → Clone the URL as is
+
   • guisu --source /custom/path init username
       → Clone to custom path /custom/path"
         )]
@@ -113,11 +151,40 @@ Examples:
         /// Checkout submodules recursively
         #[arg(long)]
         recurse_submodules: bool,
+
+        /// Host for usernames and owner/repo references (default: github.com)
+        #[arg(long, env = "GUISU_GIT_HOST", value_name = "HOST")]
+        host: Option<String>,
+
+        /// Scaffold a starter repository (.guisu.toml, .guisu/ layout, example hook)
+        #[arg(long)]
+        template: bool,
     },
 
     /// Add a file to the source directory
     Add(cmd::add::AddCommand),
 
+    /// Scaffold a starter managed entry for a common application
+    #[command(
+        long_about = "Scaffold a starter managed entry for a common application
+
+Writes starter templates for the application into the source directory, with
+variables such as name, email, and hostname wired in. Directories under
+.guisu/scaffolds/<app>/ override the built-in scaffold of the same name or add
+new ones.
+
+Examples:
+  • guisu new --list
+      → Show available scaffolds
+
+  • guisu new git
+      → Create .gitconfig.j2 using the name and email variables
+
+  • guisu new ssh --force
+      → Replace an existing .ssh/config entry"
+    )]
+    New(cmd::new::NewCommand),
+
     /// Apply the source state to the destination
     #[command(name = "apply")]
     Apply(cmd::apply::ApplyCommand),
@@ -129,15 +196,175 @@ Examples:
     #[command(subcommand)]
     Age(AgeCommands),
 
+    /// Manage secret vault integration
+    #[command(subcommand)]
+    Vault(VaultCommands),
+
     /// Show status of managed files
     Status(cmd::status::StatusCommand),
 
     /// Display file contents (decrypt and render templates)
     Cat(cmd::cat::CatCommand),
 
+    /// Write the rendered target state to an archive
+    #[command(long_about = "Write the rendered target state to an archive
+
+Renders templates and writes every managed file, directory, and symlink to a
+tar.gz or zip archive at its path relative to the destination directory,
+keeping file modes. The archive can be unpacked on machines without guisu or
+inspected offline. Encrypted files and secret env files are left out unless
+--include-secrets is given; inline age values stay encrypted then too.
+
+Examples:
+  • guisu archive -o dotfiles.tar.gz
+      → Archive everything except secrets
+
+  • guisu archive --format zip --include-secrets -o dotfiles.zip
+      → Archive everything, with encrypted files decrypted
+
+  • guisu archive | ssh host tar -xzf - -C '~'
+      → Unpack the configuration on a machine without guisu")]
+    Archive(cmd::archive::ArchiveCommand),
+
+    /// Import an existing dotfiles tree into the source directory
+    #[command(
+        long_about = "Import an existing dotfiles tree into the source directory
+
+Copies a directory, or a tar or tar.gz archive of one, into the source
+directory. Entries keep their names and modes, and symlinks become symlink_
+files. A chezmoi source directory is detected and its naming translated:
+dot_ becomes a leading dot, private_ and readonly_ become mode_ prefixes,
+encrypted_ files keep their .age suffix, .tmpl becomes .j2, create_ files are
+marked create-once, and .chezmoiignore becomes .guisuignore. Entries without
+a guisu equivalent, such as modify_ scripts, are skipped with a reason.
+Templates imported from chezmoi use Go template syntax and are listed for
+review.
+
+Examples:
+  • guisu import ~/.local/share/chezmoi --dry-run
+      → Show how a chezmoi source directory would be imported
+
+  • guisu import dotfiles.tar.gz --strip-components 1
+      → Import a tarball whose files sit in a top-level directory
+
+  • guisu import ~/old-dotfiles --force
+      → Import a plain tree, replacing entries already managed"
+    )]
+    Import(cmd::import::ImportCommand),
+
     /// Edit the source state of a target file
     Edit(cmd::edit::EditCommand),
 
+    /// Start a shell in the source directory
+    #[command(long_about = "Start a shell in the source directory
+
+Runs $SHELL (or --shell) in the source directory with GUISU_SUBSHELL=1 set,
+and returns to the current directory when it exits.
+
+Examples:
+  • guisu cd
+      → Commit or inspect source changes, then exit to come back")]
+    Cd(cmd::cd::CdCommand),
+
+    /// Stop managing target files
+    #[command(
+        visible_alias = "remove",
+        long_about = "Stop managing target files
+
+Removes the source files of the targets (whatever attribute extensions they
+carry) and their recorded state, so status and apply no longer consider them.
+The destination files are left in place unless --destination is given.
+Directories forget every managed file inside them.
+
+Examples:
+  • guisu forget ~/.bashrc
+      → Stop managing .bashrc, keep ~/.bashrc
+
+  • guisu forget --destination ~/.config/nvim
+      → Stop managing the nvim config and delete it from the destination"
+    )]
+    Forget(cmd::forget::ForgetCommand),
+
+    /// Copy destination changes back to the source directory
+    #[command(
+        name = "re-add",
+        long_about = "Copy destination changes back to the source directory
+
+Writes the content of destination files that were edited in place back into
+their source entries, keeping the source names and attributes. Templates and
+encrypted files are skipped unless --force is given: encrypted files are then
+re-encrypted, and templates are replaced by the destination content.
+
+Examples:
+  • guisu re-add
+      → Sync every edited plain file back to the source
+
+  • guisu re-add --force ~/.ssh/config
+      → Re-add one file even if it is encrypted or a template"
+    )]
+    ReAdd(cmd::re_add::ReAddCommand),
+
+    /// Remove all managed files from the destination
+    #[command(long_about = "Remove all managed files from the destination
+
+Deletes every managed file and symlink from the destination directory and
+prunes the directories this leaves empty. Unmanaged files are never touched.
+With --all, the source directory and the state database are deleted as well,
+for example when decommissioning a machine.
+
+Examples:
+  • guisu purge --dry-run
+      → List the files that would be removed
+
+  • guisu purge --all --yes
+      → Remove managed files, the source directory and the database")]
+    Purge(cmd::purge::PurgeCommand),
+
+    /// List destination paths managed by guisu
+    #[command(long_about = "List destination paths managed by guisu
+
+Prints every destination path produced by the target state, relative to the
+destination directory. Directories holding managed entries are listed too.
+Entries ignored on this platform are left out.
+
+Examples:
+  • guisu managed
+      → List all managed paths
+
+  • guisu managed --include files,symlinks
+      → List managed files and symlinks only")]
+    Managed(cmd::managed::ManagedCommand),
+
+    /// List destination files not managed by guisu
+    #[command(long_about = "List destination files not managed by guisu
+
+Walks the destination directory and prints the paths guisu does not manage,
+relative to the destination directory. A directory without managed entries
+is listed once instead of file by file. Paths matching ignore patterns are
+skipped.
+
+Examples:
+  • guisu unmanaged
+      → List everything in the destination that guisu won't touch")]
+    Unmanaged(cmd::managed::UnmanagedCommand),
+
+    /// Check that the destination matches the target state
+    #[command(long_about = "Check that the destination matches the target state
+
+Compares every managed destination with its target entry without computing
+diffs, and lists the ones that differ. Exits with status 0 when everything
+matches and 1 otherwise, so it can be used in shell prompts and CI checks.
+
+Statuses: missing, modified, mode, type (wrong kind of entry), error
+
+Examples:
+  • guisu verify && echo clean
+      → Check the whole destination
+
+  • guisu verify --format json ~/.ssh/config
+      → Report the status of one file as JSON")]
+    Verify(cmd::verify::VerifyCommand),
+
     /// View ignored files and patterns
     #[command(subcommand)]
     Ignored(IgnoredCommands),
@@ -160,15 +387,213 @@ Examples:
       → Use rebase when branches diverge")]
     Update(cmd::update::UpdateCommand),
 
+    /// Guard the git repository of the source directory
+    #[command(subcommand)]
+    Git(GitCommands),
+
     /// Display guisu status information and validate configuration
     Info(cmd::info::InfoCommand),
 
+    /// Inspect the effective configuration
+    #[command(subcommand)]
+    Config(ConfigCommands),
+
     /// Display all template variables
     Variables(cmd::variables::VariablesCommand),
 
-    /// Manage hooks (run, list, show)
+    /// Render template text from a file or stdin
+    #[command(
+        name = "execute-template",
+        long_about = "Render template text from a file or stdin
+
+Renders the template with the same context managed files get: variables,
+guisu info, password manager and vault functions, and .guisu/templates for
+includes. With --init, the template is rendered like .guisu.toml.j2 instead,
+with only system info, .guisu/data, and the prompt functions.
+
+Examples:
+  • echo '{{ hostname }}' | guisu execute-template
+      → Print the hostname as templates see it
+
+  • guisu execute-template --init .guisu.toml.j2
+      → Preview the rendered configuration"
+    )]
+    ExecuteTemplate(cmd::execute_template::ExecuteTemplateCommand),
+
+    /// Manage hooks (run, list, show, log)
     #[command(subcommand)]
     Hooks(HooksCommands),
+
+    /// Inspect local changes that apply overwrote
+    #[command(
+        subcommand,
+        long_about = "Inspect local changes that apply overwrote
+
+Before apply overwrites a destination file that was modified locally (after
+confirming, with --force, or from interactive mode), the local content is
+saved. The apply summary prints the ID of each saved snapshot.
+
+Examples:
+  • guisu conflicts list
+      → Show saved snapshots with their IDs
+
+  • guisu conflicts show 3f2a9c1e > ~/.zshrc
+      → Restore the local version of .zshrc"
+    )]
+    Conflicts(ConflictsCommands),
+
+    /// Inspect destination files saved before each apply
+    #[command(
+        subcommand,
+        long_about = "Inspect destination files saved before each apply
+
+With `snapshots = N` under [apply], every apply first saves the destination
+files it is about to change, compressed and deduplicated in the state
+database. The last N snapshots are kept; `guisu rollback` restores them.
+
+Examples:
+  • guisu snapshot list
+      → Show saved snapshots with their IDs
+
+  • guisu snapshot show 3f2a9c1e
+      → List the files saved before that apply"
+    )]
+    Snapshot(SnapshotCommands),
+
+    /// Inspect and repair the state database
+    #[command(
+        subcommand,
+        long_about = "Inspect and repair the state database
+
+guisu records applied files, hook runs, the rendered config, prompt answers,
+snapshots, and caches in a database in the XDG state directory. Records are
+grouped in buckets; `list` shows them. Hooks with mode = \"once\" or
+\"onchange\" are tracked as once/<name> and onchange/<name> in hookState.
+
+Examples:
+  • guisu state list hookState
+      → Show the hooks guisu remembers running
+
+  • guisu state delete hookState once/install-packages
+      → Run that once hook again on the next apply
+
+  • guisu state get entryState .zshrc
+      → Print the last applied state of .zshrc as JSON
+
+  • guisu state migrate --dry-run
+      → Check that an old database upgrades cleanly"
+    )]
+    State(StateCommands),
+
+    /// Restore destination files saved before an apply
+    #[command(long_about = "Restore destination files saved before an apply
+
+Puts back the destination files the last apply changed, as saved in its
+snapshot (see `guisu snapshot`); files the apply created are removed. With
+--to, every apply since then is undone. The replaced content is saved as a
+snapshot too, so running rollback again undoes the rollback.
+
+Examples:
+  • guisu rollback
+      → Undo the last apply
+
+  • guisu rollback ~/.zshrc
+      → Restore only .zshrc
+
+  • guisu rollback --to '2025-01-31 18:00'
+      → Undo every apply since then")]
+    Rollback(cmd::rollback::RollbackCommand),
+
+    /// Track changes made to managed files outside guisu
+    #[command(
+        subcommand,
+        long_about = "Track changes made to managed files outside guisu
+
+`drift watch` polls every applied destination file and records each change
+that guisu did not apply, with a diff, in the state database. Use it to find
+tools that keep rewriting your configs. Changes written by `guisu apply` are
+recognized and not recorded.
+
+Examples:
+  • guisu drift watch --interval 10
+      → Poll managed files every 10 seconds until Ctrl-C
+
+  • guisu drift report .zshrc
+      → Show recorded changes to .zshrc with their diffs"
+    )]
+    Drift(DriftCommands),
+
+    /// Apply the source directory again whenever it changes
+    #[command(long_about = "Apply the source directory again whenever it changes
+
+Watches the source directory, including .guisu/ config, templates and variables,
+and re-runs apply once changes have settled for the debounce period. Changes are
+noticed through file system events, or by scanning every --interval where those
+are unavailable. Each apply
+prints a summary line. Only entries whose target changed are written.
+
+Hooks and source scripts are not run, and destinations modified locally are
+skipped rather than overwritten; use `guisu apply` for those.
+
+Examples:
+  • guisu watch
+      → Apply on every save until Ctrl-C
+
+  • guisu watch --debounce 1000
+      → Wait for a second of quiet before applying")]
+    Watch {
+        /// Milliseconds between scans while a change settles, or always
+        /// if file system events are unavailable
+        #[arg(long, default_value_t = 500, value_parser = clap::value_parser!(u64).range(1..))]
+        interval: u64,
+
+        /// Milliseconds the source must stay unchanged before applying
+        #[arg(long, default_value_t = 300)]
+        debounce: u64,
+    },
+
+    /// Serve status/diff/apply/add over JSON-RPC for editor integrations
+    #[command(
+        long_about = "Serve status/diff/apply/add over JSON-RPC for editor integrations
+
+Reads one JSON-RPC 2.0 request per line from stdin and writes one response
+per line to stdout. Logs go to stderr. Hooks are not run by `apply`, and
+conflicting local modifications are skipped unless `force` is set.
+
+Methods:
+  • status   {files?, all?}
+  • diff     {files?}
+  • apply    {files?, dry_run?, force?}
+  • add      {files, template?, encrypt?, create?, force?}
+  • shutdown
+
+Examples:
+  • guisu serve --stdio"
+    )]
+    Serve(cmd::serve::ServeCommand),
+
+    /// Generate shell completion scripts
+    #[command(long_about = "Generate shell completion scripts
+
+Prints the script for the given shell to stdout.
+
+Examples:
+  • guisu completion bash > /usr/share/bash-completion/completions/guisu
+  • guisu completion zsh > \"${fpath[1]}/_guisu\"
+  • guisu completion fish > ~/.config/fish/completions/guisu.fish
+  • eval \"$(guisu completion --integration zsh)\"
+      → Define guisu_prompt_info, which prints guisu:N when N entries are
+        out of sync, for use in the prompt")]
+    Completion(cmd::completion::CompletionCommand),
+
+    /// Generate man pages
+    #[command(long_about = "Generate man pages
+
+Writes guisu.1 and one page per subcommand (guisu-apply.1, ...) to DIR.
+
+Examples:
+  • guisu manpages --dir /usr/share/man/man1")]
+    Manpages(cmd::manpages::ManpagesCommand),
 }
 
 /// Age encryption management commands
@@ -179,6 +604,10 @@ pub enum AgeCommands {
         /// Output file (default: ~/.config/guisu/key.txt)
         #[arg(short, long)]
         output: Option<PathBuf>,
+
+        /// Protect the identity file with a passphrase
+        #[arg(long)]
+        passphrase: bool,
     },
 
     /// Show the public key for the current identity
@@ -239,6 +668,108 @@ pub enum AgeCommands {
         #[arg(short, long)]
         yes: bool,
     },
+
+    /// Convert encrypted files to the format set by `age.armor`
+    ///
+    /// Rewrites `.age` files in the source directory as ASCII armor or binary.
+    /// The encrypted data is unchanged, so no identity is needed.
+    Convert {
+        /// Dry run - show what would be converted without making changes
+        #[arg(short = 'n', long)]
+        dry_run: bool,
+    },
+
+    /// Replace the age identity with a newly generated one
+    ///
+    /// Re-encrypts all encrypted files and inline encrypted values to the new
+    /// identity, replaces the old public key in the config file (including
+    /// `.guisu.toml.j2`), and saves the new key at the configured identity path.
+    /// The old key is kept next to it as `<name>.guisu-backup`.
+    Rotate {
+        /// Identity file to rotate (required when several are configured)
+        #[arg(long, value_name = "PATH")]
+        identity: Option<PathBuf>,
+
+        /// Protect the new identity file with a passphrase
+        #[arg(long)]
+        passphrase: bool,
+
+        /// Commit the re-encrypted files and config in the source repository
+        #[arg(long)]
+        commit: bool,
+
+        /// Dry run - show what would be rotated without making changes
+        #[arg(short = 'n', long)]
+        dry_run: bool,
+
+        /// Skip confirmation prompt
+        #[arg(short, long)]
+        yes: bool,
+    },
+
+    /// Manage the recipients files are encrypted for
+    #[command(subcommand)]
+    Recipients(RecipientsCommands),
+}
+
+/// Commands for secret vaults
+#[derive(Subcommand)]
+pub enum VaultCommands {
+    /// Manage the encrypted disk cache of vault responses
+    #[command(subcommand)]
+    Cache(VaultCacheCommands),
+}
+
+/// Commands for the vault disk cache
+#[derive(Subcommand)]
+pub enum VaultCacheCommands {
+    /// Delete every cached vault response
+    Clear,
+}
+
+/// Commands for managing age recipients
+#[derive(Subcommand)]
+pub enum RecipientsCommands {
+    /// List the configured recipients
+    List,
+
+    /// Add recipients and re-encrypt the files they apply to
+    Add {
+        /// Public keys to add (age1... or ssh-...)
+        #[arg(required = true)]
+        recipients: Vec<String>,
+
+        /// Add to the recipients of an `[age.scopes]` pattern instead of the global ones
+        #[arg(long, value_name = "PATTERN")]
+        scope: Option<String>,
+
+        /// Dry run - show what would be re-encrypted without making changes
+        #[arg(short = 'n', long)]
+        dry_run: bool,
+
+        /// Skip confirmation prompt
+        #[arg(short, long)]
+        yes: bool,
+    },
+
+    /// Remove recipients and re-encrypt the files they applied to
+    Remove {
+        /// Public keys to remove
+        #[arg(required = true)]
+        recipients: Vec<String>,
+
+        /// Remove from the recipients of an `[age.scopes]` pattern instead of the global ones
+        #[arg(long, value_name = "PATTERN")]
+        scope: Option<String>,
+
+        /// Dry run - show what would be re-encrypted without making changes
+        #[arg(short = 'n', long)]
+        dry_run: bool,
+
+        /// Skip confirmation prompt
+        #[arg(short, long)]
+        yes: bool,
+    },
 }
 
 /// Commands for viewing ignored files and patterns
@@ -253,6 +784,12 @@ pub enum IgnoredCommands {
         #[arg(short, long)]
         all: bool,
     },
+
+    /// Explain whether a destination path is ignored and which pattern matched
+    Check {
+        /// Destination path to check (e.g. ~/.config/app/cache.log)
+        path: PathBuf,
+    },
 }
 
 /// Commands for managing template files
@@ -267,6 +804,10 @@ pub enum TemplatesCommands {
         #[arg(required = true)]
         name: String,
     },
+
+    /// Check all templates for syntax errors, undefined variables, unknown
+    /// filters, and deprecated functions, without rendering them
+    Check,
 }
 
 /// Commands for managing and executing hooks
@@ -281,6 +822,10 @@ pub enum HooksCommands {
         /// Run only the specified hook by name (optional)
         #[arg(long)]
         hook: Option<String>,
+
+        /// Show which hooks would run, and why onchange hooks changed, without running them
+        #[arg(long)]
+        dry_run: bool,
     },
 
     /// List configured hooks
@@ -295,6 +840,152 @@ pub enum HooksCommands {
         /// Name of the hook to show
         name: String,
     },
+
+    /// Show captured output of recent hook runs, oldest first
+    Log {
+        /// Only show runs of this hook, with their full output
+        name: Option<String>,
+    },
+}
+
+/// Commands for inspecting overwritten local changes
+#[derive(Subcommand)]
+pub enum ConflictsCommands {
+    /// List saved snapshots of overwritten local changes
+    List,
+
+    /// Print the saved content of a snapshot
+    Show {
+        /// Snapshot ID from the apply summary or `guisu conflicts list`
+        #[arg(required = true)]
+        id: String,
+    },
+}
+
+/// Commands for inspecting apply snapshots
+#[derive(Subcommand)]
+pub enum SnapshotCommands {
+    /// List saved snapshots, oldest first
+    List,
+
+    /// List the files saved in a snapshot
+    Show {
+        /// Snapshot ID from `guisu snapshot list`
+        #[arg(required = true)]
+        id: String,
+    },
+}
+
+/// Commands for the git repository of the source directory
+#[derive(Subcommand)]
+pub enum GitCommands {
+    /// Install a pre-commit hook that blocks commits with unencrypted secrets
+    ///
+    /// The hook rejects staged files with secrets the [security] scanner
+    /// finds, `.age` files that are not encrypted, and plaintext copies of
+    /// encrypted files. `git commit --no-verify` skips it.
+    InstallHooks {
+        /// Replace an existing pre-commit hook (it is backed up first)
+        #[arg(long)]
+        force: bool,
+    },
+
+    /// Check the staged files, as the installed hook does
+    #[command(hide = true)]
+    PreCommit,
+}
+
+/// Commands for inspecting the effective configuration
+#[derive(Subcommand)]
+pub enum ConfigCommands {
+    /// Print the merged, rendered config and where each section came from
+    ///
+    /// Combines the config file (after rendering .guisu.toml.j2),
+    /// .guisu/variables/, .guisu/ignores.toml and the active profile.
+    /// Values that look like secrets are shown as <redacted>.
+    Show {
+        /// Output format
+        #[arg(long, value_enum, default_value = "toml")]
+        format: cmd::config::ConfigFormatArg,
+    },
+
+    /// Open the config file in the editor, saving it once it loads
+    ///
+    /// The cached rendering of .guisu.toml.j2 is dropped, so the next command
+    /// uses the new config.
+    Edit,
+
+    /// Set a key in the config file, keeping its comments and layout
+    ///
+    /// Example: guisu config set age.identity ~/.config/guisu/key.txt
+    Set {
+        /// Dotted key, such as age.identity or ui.contextLines
+        key: String,
+        /// Value, read as TOML (`true`, `3`, `["a"]`) when it parses and as a string otherwise
+        value: String,
+    },
+}
+
+/// Commands for inspecting and repairing the state database
+#[derive(Subcommand)]
+pub enum StateCommands {
+    /// Count the records in each bucket, or list the keys of one bucket
+    List {
+        /// Bucket to list the keys of
+        bucket: Option<String>,
+    },
+
+    /// Print a record as JSON
+    Get {
+        /// Bucket of the record
+        bucket: String,
+        /// Key of the record, from `guisu state list <bucket>`
+        key: String,
+    },
+
+    /// Delete a record
+    Delete {
+        /// Bucket of the record
+        bucket: String,
+        /// Key of the record, from `guisu state list <bucket>`
+        key: String,
+    },
+
+    /// Upgrade a database written by an older version of guisu
+    ///
+    /// Other commands migrate automatically; the file is backed up first.
+    Migrate {
+        /// Run the migrations on a copy and report, leaving the database unchanged
+        #[arg(long)]
+        dry_run: bool,
+    },
+
+    /// Delete every record, or every record in one bucket
+    Reset {
+        /// Only reset this bucket
+        bucket: Option<String>,
+
+        /// Skip confirmation prompt
+        #[arg(short, long)]
+        yes: bool,
+    },
+}
+
+/// Commands for tracking out-of-band destination changes
+#[derive(Subcommand)]
+pub enum DriftCommands {
+    /// Poll managed files and record changes made outside guisu
+    Watch {
+        /// Seconds between polls
+        #[arg(long, default_value_t = 5, value_parser = clap::value_parser!(u64).range(1..))]
+        interval: u64,
+    },
+
+    /// Show recorded changes with their diffs, oldest first
+    Report {
+        /// Only show changes to these target paths (e.g., .zshrc)
+        paths: Vec<String>,
+    },
 }
 
 /// Main entry point for the CLI logic
@@ -342,51 +1033,204 @@ fn determine_directories(
     Ok((source_dir, dest_dir))
 }
 
+/// Name of `command` if it writes to the destination, source, or state
+///
+/// These commands hold the run lock (see [`utils::lock`]) until they return,
+/// so concurrent invocations never interleave their writes.
+fn mutating_command(command: &Commands) -> Option<&'static str> {
+    match command {
+        Commands::Init { .. } => Some("init"),
+        Commands::Add(_) => Some("add"),
+        Commands::New(_) => Some("new"),
+        Commands::Import(import_cmd) if !import_cmd.dry_run => Some("import"),
+        Commands::Apply(apply_cmd) if !apply_cmd.dry_run => Some("apply"),
+        Commands::Status(status_cmd) if status_cmd.fix => Some("status --fix"),
+        Commands::Edit(_) => Some("edit"),
+        Commands::Forget(_) => Some("forget"),
+        Commands::ReAdd(_) => Some("re-add"),
+        Commands::Purge(_) => Some("purge"),
+        Commands::Update(_) => Some("update"),
+        Commands::Rollback(_) => Some("rollback"),
+        Commands::Hooks(HooksCommands::Run { dry_run: false, .. }) => Some("hooks run"),
+        Commands::Age(AgeCommands::Migrate { .. }) => Some("age migrate"),
+        Commands::Age(AgeCommands::Convert { dry_run: false }) => Some("age convert"),
+        Commands::Age(AgeCommands::Rotate { .. }) => Some("age rotate"),
+        Commands::Config(ConfigCommands::Edit) => Some("config edit"),
+        Commands::Config(ConfigCommands::Set { .. }) => Some("config set"),
+        Commands::State(StateCommands::Delete { .. }) => Some("state delete"),
+        Commands::State(StateCommands::Reset { .. }) => Some("state reset"),
+        Commands::State(StateCommands::Migrate { dry_run: false }) => Some("state migrate"),
+        _ => None,
+    }
+}
+
+/// Open the state database, naming the guisu process using it if it is in use
+pub(crate) fn open_database(db_path: &Path) -> Result<guisu_engine::state::RedbPersistentState> {
+    guisu_engine::database::open(db_path).map_err(|e| {
+        let holder = utils::lock::lock_path()
+            .ok()
+            .and_then(|path| utils::lock::RunLock::holder(&path));
+        match holder {
+            // Holding the run lock ourselves, the database is open in a process that ignores it
+            Some(holder) if utils::lock::is_own(&holder) => {
+                anyhow::Error::new(e).context("The state database is in use by another process")
+            }
+            Some(holder) => anyhow::anyhow!(
+                "The state database is in use by another guisu process ({holder}). \
+                 Try again once it has finished."
+            ),
+            None => anyhow::Error::new(e).context("Failed to create database instance"),
+        }
+    })
+}
+
 /// Handle init command separately (doesn't need config before directory creation)
-#[allow(clippy::too_many_arguments)]
 fn handle_init_command(
     path_or_repo: Option<&String>,
     custom_source: Option<&PathBuf>,
-    depth: Option<usize>,
-    branch: Option<&String>,
-    ssh: bool,
-    recurse_submodules: bool,
+    options: cmd::init::InitOptions<'_>,
     apply: bool,
     dest_dir: &Path,
     config_path: Option<&Path>,
+    profile: Option<&str>,
 ) -> Result<()> {
     let init_result = crate::cmd::init::run(
         path_or_repo.map(String::as_str),
         custom_source.map(std::path::PathBuf::as_path),
-        depth,
-        branch.map(String::as_str),
-        ssh,
-        recurse_submodules,
+        options,
     )?;
 
     // Apply if requested
     if apply && let Some(source_path) = init_result {
         println!("\nApplying changes...");
-        // Now load config after source directory is created (no caching needed for init)
-        let config = load_config_with_template_support(config_path, &source_path, None)?;
+        // Now load config after source directory is created, keeping prompt answers
+        let db_path =
+            guisu_engine::database::get_db_path().context("Failed to get database path")?;
+        let database = std::sync::Arc::new(open_database(&db_path)?);
+        let mut config =
+            load_config_with_template_support(config_path, &source_path, Some(&database))?;
+        activate_profile(&mut config, &source_path, profile)?;
+        if config.age.keyring {
+            guisu_crypto::set_passphrase_cache(cmd::age::KeyringPassphraseCache::new());
+        }
 
         // Create ApplyCommand with default options (all files)
         let apply_cmd = cmd::apply::ApplyCommand {
             files: vec![],
             dry_run: false,
             force: false,
+            merge: false,
+            prune: false,
             interactive: false,
             include: vec![],
             exclude: vec![],
+            jobs: None,
+            backup: false,
+            refresh_externals: false,
+            one_shot: None,
+            source_ref: None,
+            timings: false,
         };
 
-        // Create RuntimeContext and execute
-        let context = RuntimeContext::new(config, &source_path, dest_dir)?;
+        // Create RuntimeContext and execute (reuses the database instance)
+        let paths = crate::common::ResolvedPaths::resolve(&source_path, dest_dir, &config)?;
+        let context =
+            RuntimeContext::from_parts_with_db(std::sync::Arc::new(config), paths, database);
         apply_cmd.execute(&context)?;
     }
     Ok(())
 }
 
+/// Clone `repo` to a temporary directory, apply it, and remove it again
+///
+/// The state database lives in the same temporary directory, so nothing is left
+/// behind apart from the applied files.
+fn handle_one_shot_apply(
+    repo: &str,
+    apply_cmd: &cmd::apply::ApplyCommand,
+    dest_dir: &Path,
+    config_path: Option<&Path>,
+    profile: Option<&str>,
+) -> Result<()> {
+    let temp_dir = tempfile::Builder::new()
+        .prefix("guisu-one-shot-")
+        .tempdir()
+        .context("Failed to create temporary directory")?;
+    let source_path = temp_dir.path().join("source");
+
+    // History is not needed, but libgit2 cannot make shallow clones of local paths
+    let is_local = Path::new(repo).exists();
+    if !is_local && OFFLINE.load(Ordering::Relaxed) {
+        anyhow::bail!("Cannot clone {repo} in offline mode");
+    }
+    let depth = (!is_local).then_some(1);
+    cmd::init::clone_repository(repo, &source_path, depth, None, false, true)?;
+
+    let mut config = load_config_with_template_support(config_path, &source_path, None)?;
+    activate_profile(&mut config, &source_path, profile)?;
+    let context = RuntimeContext::new_with_db_path(
+        config,
+        &source_path,
+        dest_dir,
+        &temp_dir.path().join("state.db"),
+    )?;
+    let result = handle_apply_command(apply_cmd, &context);
+
+    // Close the database before its directory is removed
+    drop(context);
+    temp_dir
+        .close()
+        .context("Failed to remove temporary source directory")?;
+    result
+}
+
+/// Apply the source directory as of `revision`
+///
+/// The files of the revision are exported from the git object database to a
+/// temporary directory, which is applied like the source directory and removed
+/// afterwards. Unlike one-shot applies, the regular state database is used.
+fn handle_ref_apply(
+    revision: &str,
+    apply_cmd: &cmd::apply::ApplyCommand,
+    source_dir: &Path,
+    dest_dir: &Path,
+    database: std::sync::Arc<guisu_engine::state::RedbPersistentState>,
+    config_path: Option<&Path>,
+    profile: Option<&str>,
+) -> Result<()> {
+    let source = guisu_engine::git::GitTreeSource::open(source_dir, revision)?;
+    let temp_dir = tempfile::Builder::new()
+        .prefix("guisu-ref-")
+        .tempdir()
+        .context("Failed to create temporary directory")?;
+    let source_path = temp_dir.path().join("source");
+    let files = source.export(&source_path)?;
+    tracing::debug!(revision, commit = %source.commit_id(), files, "Exported source revision");
+
+    let mut config = load_config_with_template_support(config_path, &source_path, Some(&database))?;
+    activate_profile(&mut config, &source_path, profile)?;
+    if config.age.keyring {
+        guisu_crypto::set_passphrase_cache(cmd::age::KeyringPassphraseCache::new());
+    }
+    let paths = crate::common::ResolvedPaths::resolve(&source_path, dest_dir, &config)?;
+    let context = RuntimeContext::from_parts_with_db(std::sync::Arc::new(config), paths, database);
+    let result = handle_apply_command(apply_cmd, &context);
+    if result.is_ok() {
+        let commit = source.commit_id();
+        println!(
+            "{} {revision} ({})",
+            "Applied".green(),
+            commit.get(..12).unwrap_or(&commit)
+        );
+    }
+
+    drop(context);
+    temp_dir
+        .close()
+        .context("Failed to remove temporary source directory")?;
+    result
+}
+
 /// Handle apply command with pre and post hooks
 fn handle_apply_command(
     apply_cmd: &cmd::apply::ApplyCommand,
@@ -394,8 +1238,12 @@ fn handle_apply_command(
 ) -> Result<()> {
     // Handle pre-apply hooks (unless it's a dry run)
     if !apply_cmd.dry_run
-        && let Err(e) =
-            cmd::hooks::handle_hooks_pre(context.source_dir(), &context.config, &context.database)
+        && let Err(e) = cmd::hooks::handle_hooks_pre(
+            &context.paths,
+            &context.config,
+            &context.database,
+            &context.clock,
+        )
     {
         tracing::warn!("Pre-apply hooks failed: {}", e);
         println!(
@@ -406,6 +1254,12 @@ fn handle_apply_command(
         println!("Continuing with file application...\n");
     }
 
+    // Source scripts only run when applying everything (and not on a dry run)
+    let run_scripts = !apply_cmd.dry_run && apply_cmd.files.is_empty();
+    if run_scripts {
+        run_source_scripts(HookStage::Pre, context);
+    }
+
     // Execute apply command and get stats
     let is_single_file = apply_cmd.files.len() == 1;
     let dry_run = apply_cmd.dry_run;
@@ -413,10 +1267,19 @@ fn handle_apply_command(
 
     // Database will be automatically closed when RuntimeContext is dropped
 
+    if run_scripts {
+        run_source_scripts(HookStage::Post, context);
+    }
+
     // Handle post-apply hooks (unless it's a dry run)
     if !dry_run
-        && let Err(e) =
-            cmd::hooks::handle_hooks_post(context.source_dir(), &context.config, &context.database)
+        && let Err(e) = cmd::hooks::handle_hooks_post(
+            &context.paths,
+            &context.config,
+            &context.database,
+            &context.clock,
+            &stats.changed_paths(),
+        )
     {
         tracing::warn!("Post-apply hooks failed: {}", e);
         println!(
@@ -431,10 +1294,59 @@ fn handle_apply_command(
         println!();
         stats.print_summary(dry_run);
     }
+    stats.print_conflict_snapshots();
+    stats.print_type_backups();
+    stats.print_file_backups();
 
     Ok(())
 }
 
+/// Run the `run_*` scripts of the source directory for a stage, warning on failure
+fn run_source_scripts(stage: HookStage, context: &RuntimeContext) {
+    if let Err(e) = cmd::hooks::handle_source_scripts(
+        stage,
+        &context.paths,
+        &context.config,
+        &context.database,
+        &context.clock,
+    ) {
+        tracing::warn!("{} scripts failed: {}", stage.name(), e);
+        println!(
+            "{}: {} scripts encountered issues: {}",
+            "Warning".yellow(),
+            match stage {
+                HookStage::Pre => "Before-apply",
+                HookStage::Post => "After-apply",
+            },
+            e
+        );
+    }
+}
+
+/// Commit source changes made by `command` when `[git] autoCommit` is set
+///
+/// Failures are reported as warnings: the command itself already succeeded.
+fn commit_source_changes(command: &str, context: &RuntimeContext) {
+    match utils::autocommit::auto_commit(context, command) {
+        Ok(Some(commit)) => {
+            let short_id = commit.commit_id.get(..7).unwrap_or(&commit.commit_id);
+            let summary = commit.message.lines().next().unwrap_or_default();
+            println!(
+                "{} {} {}{}",
+                "Committed".green(),
+                short_id.dimmed(),
+                summary,
+                if commit.pushed { " (pushed)" } else { "" }
+            );
+        }
+        Ok(None) => {}
+        Err(e) => {
+            tracing::warn!("Auto-commit failed: {:#}", e);
+            println!("{}: Auto-commit failed: {:#}", "Warning".yellow(), e);
+        }
+    }
+}
+
 /// Execute the command based on the command type
 #[allow(clippy::too_many_lines)]
 fn execute_command(command: Commands, context: &RuntimeContext) -> Result<()> {
@@ -444,6 +1356,11 @@ fn execute_command(command: Commands, context: &RuntimeContext) -> Result<()> {
         }
         Commands::Add(add_cmd) => {
             add_cmd.execute(context)?;
+            commit_source_changes("add", context);
+        }
+        Commands::New(new_cmd) => {
+            new_cmd.execute(context)?;
+            commit_source_changes("new", context);
         }
         Commands::Apply(apply_cmd) => {
             handle_apply_command(&apply_cmd, context)?;
@@ -452,8 +1369,8 @@ fn execute_command(command: Commands, context: &RuntimeContext) -> Result<()> {
             diff_cmd.execute(context)?;
         }
         Commands::Age(age_cmd) => match age_cmd {
-            AgeCommands::Generate { output } => {
-                cmd::age::generate(output)?;
+            AgeCommands::Generate { output, passphrase } => {
+                cmd::age::generate(output, passphrase)?;
             }
             AgeCommands::Show => {
                 cmd::age::show(&context.config)?;
@@ -480,8 +1397,59 @@ fn execute_command(command: Commands, context: &RuntimeContext) -> Result<()> {
                     &new_identities,
                     dry_run,
                     yes,
+                    &context.config,
                 )?;
             }
+            AgeCommands::Convert { dry_run } => {
+                cmd::age::convert(context.source_dir(), dry_run, &context.config)?;
+            }
+            AgeCommands::Rotate {
+                identity,
+                passphrase,
+                commit,
+                dry_run,
+                yes,
+            } => {
+                let options = cmd::age::RotateOptions {
+                    identity,
+                    passphrase,
+                    commit,
+                    dry_run,
+                    yes,
+                };
+                cmd::age::rotate(context.source_dir(), &options, &context.config)?;
+            }
+            AgeCommands::Recipients(recipients_cmd) => match recipients_cmd {
+                RecipientsCommands::List => cmd::age::list_recipients(&context.config)?,
+                RecipientsCommands::Add {
+                    recipients,
+                    scope,
+                    dry_run,
+                    yes,
+                } => cmd::age::edit_recipients(
+                    context.source_dir(),
+                    cmd::age::RecipientChange::Add,
+                    &recipients,
+                    scope.as_deref(),
+                    dry_run,
+                    yes,
+                    &context.config,
+                )?,
+                RecipientsCommands::Remove {
+                    recipients,
+                    scope,
+                    dry_run,
+                    yes,
+                } => cmd::age::edit_recipients(
+                    context.source_dir(),
+                    cmd::age::RecipientChange::Remove,
+                    &recipients,
+                    scope.as_deref(),
+                    dry_run,
+                    yes,
+                    &context.config,
+                )?,
+            },
         },
         Commands::Status(status_cmd) => {
             status_cmd.execute(context)?;
@@ -489,8 +1457,38 @@ fn execute_command(command: Commands, context: &RuntimeContext) -> Result<()> {
         Commands::Cat(cat_cmd) => {
             cat_cmd.execute(context)?;
         }
+        Commands::Archive(archive_cmd) => {
+            archive_cmd.execute(context)?;
+        }
+        Commands::Import(import_cmd) => {
+            import_cmd.execute(context)?;
+            if !import_cmd.dry_run {
+                commit_source_changes("import", context);
+            }
+        }
         Commands::Edit(edit_cmd) => {
             edit_cmd.execute(context)?;
+            commit_source_changes("edit", context);
+        }
+        Commands::Forget(forget_cmd) => {
+            forget_cmd.execute(context)?;
+            commit_source_changes("forget", context);
+        }
+        Commands::ReAdd(re_add_cmd) => {
+            re_add_cmd.execute(context)?;
+            commit_source_changes("re-add", context);
+        }
+        Commands::Purge(purge_cmd) => {
+            purge_cmd.execute(context)?;
+        }
+        Commands::Managed(managed_cmd) => {
+            managed_cmd.execute(context)?;
+        }
+        Commands::Unmanaged(unmanaged_cmd) => {
+            unmanaged_cmd.execute(context)?;
+        }
+        Commands::Verify(verify_cmd) => {
+            verify_cmd.execute(context)?;
         }
         Commands::Ignored(ignored_cmd) => match ignored_cmd {
             IgnoredCommands::List => {
@@ -499,6 +1497,15 @@ fn execute_command(command: Commands, context: &RuntimeContext) -> Result<()> {
             IgnoredCommands::Rules { all } => {
                 cmd::ignored::run_show(context.source_dir(), &context.config, all)?;
             }
+            IgnoredCommands::Check { path } => {
+                cmd::ignored::run_check(
+                    context.source_dir(),
+                    context.dotfiles_dir(),
+                    context.dest_dir(),
+                    &context.config,
+                    &path,
+                )?;
+            }
         },
         Commands::Templates(templates_cmd) => match templates_cmd {
             TemplatesCommands::List => {
@@ -512,6 +1519,13 @@ fn execute_command(command: Commands, context: &RuntimeContext) -> Result<()> {
                     &context.config,
                 )?;
             }
+            TemplatesCommands::Check => {
+                cmd::templates::run_check(
+                    context.source_dir(),
+                    context.dest_dir().as_path(),
+                    &context.config,
+                )?;
+            }
         },
         Commands::Update(update_cmd) => {
             update_cmd.execute(context)?;
@@ -522,28 +1536,123 @@ fn execute_command(command: Commands, context: &RuntimeContext) -> Result<()> {
         Commands::Variables(vars_cmd) => {
             vars_cmd.execute(context)?;
         }
+        Commands::Config(config_cmd) => match config_cmd {
+            ConfigCommands::Show { format } => {
+                cmd::config::run_show(
+                    context.source_dir(),
+                    &context.config,
+                    context.database(),
+                    format,
+                )?;
+            }
+            ConfigCommands::Edit | ConfigCommands::Set { .. } => {
+                unreachable!("Config edit and set already handled before loading the config")
+            }
+        },
+        Commands::ExecuteTemplate(execute_cmd) => {
+            execute_cmd.execute(context)?;
+        }
+        Commands::Serve(_) => {
+            unreachable!("Serve already handled after loading the config")
+        }
         Commands::Hooks(hooks_cmd) => match hooks_cmd {
-            HooksCommands::Run { yes, hook } => {
+            HooksCommands::Run { yes, hook, dry_run } => {
                 cmd::hooks::run_hooks(
-                    context.source_dir(),
+                    &context.paths,
                     &context.config,
                     &context.database,
+                    &context.clock,
                     yes,
                     hook.as_deref(),
+                    dry_run,
                 )?;
             }
             HooksCommands::List { format } => {
                 cmd::hooks::run_list(context.source_dir(), &context.config, &format)?;
             }
             HooksCommands::Show { name } => {
-                cmd::hooks::run_show(context.source_dir(), &context.config, &name)?;
+                cmd::hooks::run_show(&context.paths, &context.config, &name)?;
+            }
+            HooksCommands::Log { name } => {
+                cmd::hooks::run_log(context.database(), name.as_deref())?;
+            }
+        },
+        Commands::Conflicts(conflicts_cmd) => match conflicts_cmd {
+            ConflictsCommands::List => {
+                cmd::conflicts::run_list(context.database())?;
+            }
+            ConflictsCommands::Show { id } => {
+                cmd::conflicts::run_show(context.database(), &id)?;
             }
         },
+        Commands::Snapshot(snapshot_cmd) => match snapshot_cmd {
+            SnapshotCommands::List => {
+                cmd::snapshot::run_list(context.database())?;
+            }
+            SnapshotCommands::Show { id } => {
+                cmd::snapshot::run_show(context.database(), &id)?;
+            }
+        },
+        Commands::State(state_cmd) => match state_cmd {
+            StateCommands::List { bucket } => {
+                cmd::state::run_list(context.database(), bucket.as_deref())?;
+            }
+            StateCommands::Get { bucket, key } => {
+                cmd::state::run_get(context.database(), &bucket, &key)?;
+            }
+            StateCommands::Delete { bucket, key } => {
+                cmd::state::run_delete(context.database(), &bucket, &key)?;
+            }
+            StateCommands::Reset { bucket, yes } => {
+                cmd::state::run_reset(context.database(), bucket.as_deref(), yes)?;
+            }
+            StateCommands::Migrate { .. } => {
+                unreachable!("State migrate already handled before opening the database")
+            }
+        },
+        Commands::Rollback(rollback_cmd) => {
+            rollback_cmd.execute(context)?;
+        }
+        Commands::Drift(drift_cmd) => match drift_cmd {
+            DriftCommands::Watch { .. } => {
+                unreachable!("Drift watch already handled before opening the database")
+            }
+            DriftCommands::Report { paths } => {
+                cmd::drift::run_report(context.database(), &paths)?;
+            }
+        },
+        Commands::Watch { .. } => {
+            unreachable!("Watch already handled before opening the database")
+        }
+        Commands::Git(_) | Commands::Cd(_) | Commands::Vault(_) => {
+            unreachable!("Git, cd and vault commands already handled before opening the database")
+        }
+        Commands::Completion(_) | Commands::Manpages(_) => {
+            unreachable!("Completion and manpages already handled above")
+        }
     }
 
     Ok(())
 }
 
+/// Set by `--offline` for the whole run
+static OFFLINE: AtomicBool = AtomicBool::new(false);
+
+/// Set by `--wait` for the whole run
+static WAIT: AtomicBool = AtomicBool::new(false);
+
+/// Whether to wait for another guisu process holding the run lock
+///
+/// Used by `serve`, which takes the lock per request instead of per run.
+pub(crate) fn wait_for_lock() -> bool {
+    WAIT.load(Ordering::Relaxed)
+}
+
+/// Whether network access is disabled by `--offline` or `[general] offline`
+pub(crate) fn is_offline(config: &guisu_config::Config) -> bool {
+    OFFLINE.load(Ordering::Relaxed) || config.general.offline
+}
+
 /// # Errors
 ///
 /// Returns an error if:
@@ -551,9 +1660,45 @@ fn execute_command(command: Commands, context: &RuntimeContext) -> Result<()> {
 /// - Configuration loading fails
 /// - Source or destination directories cannot be determined
 /// - Command execution fails
+#[allow(clippy::too_many_lines)]
 pub fn run(cli: Cli) -> Result<()> {
     // Initialize logging based on verbosity
-    crate::logging::init(cli.verbose, cli.log_file.as_deref())?;
+    // The server speaks its protocol on stdout, archives can be written to it,
+    // and porcelain status is read by prompts, so their logs must go to stderr
+    let log_to_stderr = matches!(&cli.command, Commands::Serve(_))
+        || matches!(&cli.command, Commands::Archive(archive) if archive.output.is_none())
+        || matches!(&cli.command, Commands::Status(status)
+            if status.output_format() == cmd::status::OutputFormat::Porcelain);
+    crate::logging::init(&logging::LogOptions {
+        verbose: cli.verbose,
+        trace: cli.trace,
+        format: cli.log_format,
+        log_file: cli.log_file.as_deref(),
+        to_stderr: log_to_stderr,
+    })?;
+
+    // Generated from the CLI definition alone, without config or database
+    match &cli.command {
+        Commands::Completion(completion_cmd) => return completion_cmd.run(),
+        Commands::Manpages(manpages_cmd) => return manpages_cmd.run(),
+        _ => {}
+    }
+
+    OFFLINE.store(cli.offline, Ordering::Relaxed);
+    WAIT.store(cli.wait, Ordering::Relaxed);
+
+    // Commands that write hold the run lock until they return
+    let _run_lock = match mutating_command(&cli.command) {
+        Some(name) => Some(utils::lock::RunLock::acquire(
+            &utils::lock::lock_path()?,
+            name,
+            cli.wait,
+        )?),
+        None => None,
+    };
+
+    // Encrypted identity files ask for their passphrase on first use
+    guisu_crypto::set_passphrase_prompt(cmd::age::prompt_passphrase);
 
     // Save custom source for init command before it's consumed
     let custom_source = cli.source.clone();
@@ -570,31 +1715,126 @@ pub fn run(cli: Cli) -> Result<()> {
         branch,
         ssh,
         recurse_submodules,
+        host,
+        template,
     } = cli.command
     {
         return handle_init_command(
             path_or_repo.as_ref(),
             custom_source.as_ref(),
-            depth,
-            branch.as_ref(),
-            ssh,
-            recurse_submodules,
+            cmd::init::InitOptions {
+                depth,
+                branch: branch.as_deref(),
+                use_ssh: ssh,
+                recurse_submodules,
+                host: host.as_deref(),
+                template,
+                system_git: guisu_engine::git::use_system_git(&base_config),
+                offline: cli.offline || base_config.general.offline,
+            },
             apply,
             &dest_dir,
             cli.config.as_deref(),
+            cli.profile.as_deref(),
+        );
+    }
+
+    // One-shot applies use a throwaway source directory and database
+    if let Commands::Apply(apply_cmd) = &cli.command
+        && let Some(repo) = &apply_cmd.one_shot
+    {
+        return handle_one_shot_apply(
+            repo,
+            apply_cmd,
+            &dest_dir,
+            cli.config.as_deref(),
+            cli.profile.as_deref(),
         );
     }
 
     // For all other commands, create database first to enable config caching
     let db_path = guisu_engine::database::get_db_path().context("Failed to get database path")?;
-    let database = std::sync::Arc::new(
-        guisu_engine::state::RedbPersistentState::new(&db_path)
-            .context("Failed to create database instance")?,
-    );
+
+    // Migrating opens the database itself, reporting what changes
+    if let Commands::State(StateCommands::Migrate { dry_run }) = cli.command {
+        return cmd::state::run_migrate(&db_path, dry_run);
+    }
+
+    // The watchers open the database only while polling, so it must not hold it here
+    if let Commands::Drift(DriftCommands::Watch { interval }) = cli.command {
+        return cmd::drift::run_watch(&db_path, &dest_dir, interval);
+    }
+    if let Commands::Watch { interval, debounce } = cli.command {
+        return cmd::watch::run_watch(
+            &db_path,
+            &source_dir,
+            &dest_dir,
+            cli.config.as_deref(),
+            cli.profile.as_deref(),
+            interval,
+            debounce,
+        );
+    }
+
+    // The vault cache lives outside the database
+    if let Commands::Vault(VaultCommands::Cache(VaultCacheCommands::Clear)) = &cli.command {
+        return cmd::vault::run_cache_clear();
+    }
+
+    // The shell may run guisu itself, so it must not hold the database
+    if let Commands::Cd(cd_cmd) = &cli.command {
+        return cd_cmd.run(&source_dir);
+    }
+
+    // The hook runs inside commits made by guisu itself, which hold the database
+    if let Commands::Git(git_cmd) = &cli.command {
+        return match git_cmd {
+            GitCommands::InstallHooks { force } => cmd::git::run_install_hooks(&source_dir, *force),
+            GitCommands::PreCommit => {
+                let security = match load_config_with_template_support(None, &source_dir, None) {
+                    Ok(config) => config.security,
+                    Err(e) => {
+                        tracing::warn!("Checking without the [security] allowlist: {e}");
+                        guisu_config::SecurityConfig::default()
+                    }
+                };
+                cmd::git::run_pre_commit(&source_dir, &security)
+            }
+        };
+    }
+    let database = std::sync::Arc::new(open_database(&db_path)?);
+
+    // Editing the config must work while it fails to load
+    if let Commands::Config(config_cmd @ (ConfigCommands::Edit | ConfigCommands::Set { .. })) =
+        &cli.command
+    {
+        return run_config_edit(config_cmd, &source_dir, &database, base_config);
+    }
+
+    // Applying a revision reads the source from git instead of the working tree
+    if let Commands::Apply(apply_cmd) = &cli.command
+        && let Some(revision) = &apply_cmd.source_ref
+    {
+        return handle_ref_apply(
+            revision,
+            apply_cmd,
+            &source_dir,
+            &dest_dir,
+            database,
+            cli.config.as_deref(),
+            cli.profile.as_deref(),
+        );
+    }
 
     // Load config with database caching enabled
-    let config =
+    let mut config =
         load_config_with_template_support(cli.config.as_deref(), &source_dir, Some(&database))?;
+    activate_profile(&mut config, &source_dir, cli.profile.as_deref())?;
+
+    // Remember identity passphrases across runs if configured
+    if config.age.keyring {
+        guisu_crypto::set_passphrase_cache(cmd::age::KeyringPassphraseCache::new());
+    }
 
     // Create RuntimeContext for commands (reuses the database instance)
     let paths = crate::common::ResolvedPaths::resolve(&source_dir, &dest_dir, &config)?;
@@ -604,6 +1844,11 @@ pub fn run(cli: Cli) -> Result<()> {
         database,
     );
 
+    // The server opens the database for each request, so it must not hold it between them
+    if let Commands::Serve(serve_cmd) = &cli.command {
+        return serve_cmd.run(&context.detach(), &db_path);
+    }
+
     // Execute the command
     execute_command(cli.command, &context)
 }
@@ -621,19 +1866,22 @@ pub fn run(cli: Cli) -> Result<()> {
 ///
 /// * `files` - List of file paths provided by the user
 /// * `dest_abs` - Absolute path to the destination directory
+/// * `targets` - Destination roots of subtrees applied outside `dest_abs`
 ///
 /// # Returns
 ///
-/// Returns a vector of `RelPath` entries representing the files relative to `dest_dir`.
+/// Returns a vector of `RelPath` target paths: relative to `dest_dir`, or
+/// mapped back into the source subtree of a destination root.
 ///
 /// # Errors
 ///
 /// Returns an error if:
 /// - A file path cannot be canonicalized
-/// - A file path is not under the destination directory
+/// - A file path is neither under the destination directory nor a destination root
 pub(crate) fn build_filter_paths(
     files: &[std::path::PathBuf],
     dest_abs: &guisu_core::path::AbsPath,
+    targets: &guisu_config::Targets,
 ) -> Result<Vec<guisu_core::path::RelPath>> {
     files
         .iter()
@@ -642,8 +1890,8 @@ pub(crate) fn build_filter_paths(
             let expanded_path = expand_tilde(file_path);
             let file_abs = resolve_absolute_path(&expanded_path)?;
 
-            // Convert to relative path under dest_dir
-            file_abs.strip_prefix(dest_abs).map_err(|_| {
+            // Convert to a target path under dest_dir or a destination root
+            targets.target_path(dest_abs, &file_abs).ok_or_else(|| {
                 anyhow::anyhow!(
                     "File {} is not under destination directory {}",
                     file_abs.as_path().display(),
@@ -702,7 +1950,8 @@ fn resolve_absolute_path(path: &std::path::Path) -> Result<guisu_core::path::Abs
 
 /// Load configuration with template support and optional database caching
 ///
-/// Handles both static `.guisu.toml` and templated `.guisu.toml.j2` configurations.
+/// Handles both static `.guisu.toml` and templated `.guisu.toml.j2` configurations,
+/// and their JSON (`.guisu.json`) equivalents, detected by extension.
 ///
 /// For `.guisu.toml.j2` templates:
 /// - If database is provided, checks cache first using template hash
@@ -731,21 +1980,20 @@ pub(crate) fn load_config_with_template_support(
 ) -> Result<guisu_config::Config> {
     use std::fs;
 
-    let toml_path = source_dir.join(".guisu.toml");
-    let template_path = source_dir.join(".guisu.toml.j2");
+    let found = guisu_config::ConfigFormat::find(source_dir);
 
-    // If .guisu.toml exists, use the standard loader
-    if toml_path.exists() {
+    // If a plain config file exists, use the standard loader
+    if let Some((_, _, false)) = found {
         return guisu_config::Config::load_with_variables(None, source_dir)
             .map_err(|e| anyhow::anyhow!("Failed to load config: {e}"));
     }
 
-    // If .guisu.toml.j2 exists, render it (with optional database caching)
-    if template_path.exists() {
+    // If a config template exists, render it (with optional database caching)
+    if let Some((template_path, format, true)) = found {
         let template_content = fs::read_to_string(&template_path)?;
 
         // Try to use cached config if database is available
-        let rendered_toml = if let Some(db) = database {
+        let rendered_config = if let Some(db) = database {
             match guisu_engine::database::get_config_metadata(db) {
                 Ok(Some(metadata)) if metadata.template_matches(&template_content) => {
                     // Cache hit - use cached rendered config
@@ -753,7 +2001,7 @@ pub(crate) fn load_config_with_template_support(
                 }
                 _ => {
                     // Cache miss or invalid - render and cache
-                    let rendered = render_config_template(source_dir, &template_content)?;
+                    let rendered = render_config_template(source_dir, &template_content, database)?;
                     // Save to cache (ignore errors - caching is optional)
                     let _ = guisu_engine::database::save_config_metadata(
                         db,
@@ -765,12 +2013,13 @@ pub(crate) fn load_config_with_template_support(
             }
         } else {
             // No database - render without caching
-            render_config_template(source_dir, &template_content)?
+            render_config_template(source_dir, &template_content, None)?
         };
 
-        // Parse the rendered TOML
-        let mut config = guisu_config::Config::from_toml_str(&rendered_toml, source_dir)
-            .map_err(|e| anyhow::anyhow!("Failed to parse rendered config: {e}"))?;
+        // Parse the rendered config
+        let mut config =
+            guisu_config::Config::from_str_with_format(&rendered_config, format, source_dir)
+                .map_err(|e| anyhow::anyhow!("Failed to parse rendered config: {e}"))?;
 
         // Load and merge platform-specific variables and ignores (same as load_with_variables)
         let platform = guisu_core::platform::CURRENT_PLATFORM.os;
@@ -795,10 +2044,10 @@ pub(crate) fn load_config_with_template_support(
         return Ok(config);
     }
 
-    // Neither file exists, return error
+    // No config file exists, return error
     Err(anyhow::anyhow!(
         "Configuration file not found in source directory.\n\
-         Expected: .guisu.toml or .guisu.toml.j2 in {}\n\
+         Expected: .guisu.toml, .guisu.json, or a .j2 template of either in {}\n\
          \n\
          Create .guisu.toml with:\n\
          cat > .guisu.toml << 'EOF'\n\
@@ -811,6 +2060,28 @@ pub(crate) fn load_config_with_template_support(
     ))
 }
 
+/// Run `config edit` or `config set`, which work without loading the config
+///
+/// `base_config` picks the editor when the config in `source_dir` does not load.
+fn run_config_edit(
+    command: &ConfigCommands,
+    source_dir: &std::path::Path,
+    database: &std::sync::Arc<guisu_engine::state::RedbPersistentState>,
+    base_config: guisu_config::Config,
+) -> Result<()> {
+    match command {
+        ConfigCommands::Edit => {
+            let editor_config = load_config_with_template_support(None, source_dir, Some(database))
+                .unwrap_or(base_config);
+            cmd::config::run_edit(source_dir, &editor_config, database)
+        }
+        ConfigCommands::Set { key, value } => {
+            cmd::config::run_set(source_dir, database, key, value)
+        }
+        ConfigCommands::Show { .. } => unreachable!("Config show needs the loaded config"),
+    }
+}
+
 /// Render config template with minimal context (system variables only)
 ///
 /// Uses a minimal template engine to avoid circular dependency:
@@ -826,25 +2097,51 @@ pub(crate) fn load_config_with_template_support(
 /// # Returns
 ///
 /// Rendered TOML configuration string
-fn render_config_template(source_dir: &std::path::Path, template_content: &str) -> Result<String> {
+pub(crate) fn render_config_template(
+    source_dir: &std::path::Path,
+    template_content: &str,
+    database: Option<&std::sync::Arc<guisu_engine::state::RedbPersistentState>>,
+) -> Result<String> {
+    // Answers to prompt*() are remembered so later renders don't ask again
+    let saved_answers = database
+        .and_then(|db| guisu_engine::database::get_prompt_answers(db).ok())
+        .unwrap_or_default();
+    let answers = guisu_template::functions::PromptAnswers::new(saved_answers.clone());
+
     // Create a minimal template engine for rendering config template
-    // Use system variables only (no user variables since we haven't loaded config yet)
-    let engine = guisu_template::TemplateEngine::new();
+    // Use system info and .guisu/data only (no user variables since we haven't loaded config yet)
+    let engine = guisu_template::TemplateEngine::new().with_prompt_answers(&answers);
+
+    let data = guisu_config::data::load_data(&source_dir.join(".guisu"))
+        .context("Failed to load template data from .guisu/data/")?;
 
-    // Create context with only system info
+    // Create context with system info and template data
     let working_tree = guisu_engine::git::find_working_tree(source_dir)
         .unwrap_or_else(|| source_dir.to_path_buf());
-    let context = guisu_template::TemplateContext::new().with_guisu_info(
-        path_to_string(source_dir),
-        path_to_string(&working_tree),
-        path_to_string(&dirs::home_dir().unwrap_or_default()),
-        "home".to_string(),
-    );
+    let context = guisu_template::TemplateContext::new()
+        .with_guisu_info(
+            path_to_string(source_dir),
+            path_to_string(&working_tree),
+            path_to_string(&dirs::home_dir().unwrap_or_default()),
+            "home".to_string(),
+        )
+        .with_variables(data);
 
     // Render the template
-    engine
+    let rendered = engine
         .render_str(template_content, &context)
-        .map_err(|e| anyhow::anyhow!("Failed to render .guisu.toml.j2 template: {e}"))
+        .map_err(|e| anyhow::anyhow!("Failed to render config template: {e}"))?;
+
+    if let Some(db) = database {
+        for (prompt, answer) in answers.to_map() {
+            if saved_answers.get(&prompt) != Some(&answer) {
+                guisu_engine::database::save_prompt_answer(db, &prompt, &answer)
+                    .context("Failed to save prompt answer")?;
+            }
+        }
+    }
+
+    Ok(rendered)
 }
 
 /// Create a template engine with common configuration (crate-internal use only)
@@ -864,11 +2161,18 @@ fn render_config_template(source_dir: &std::path::Path, template_content: &str)
 /// - Age identities for inline decryption
 /// - Template directory (if .guisu/templates exists)
 /// - Bitwarden provider configuration
+/// - password-store command for `pass()`
+/// - Commands `output()` may run
+/// - Functions defined in `.guisu/functions/*.rhai`
+///
+/// # Errors
+///
+/// Returns an error if a script in `.guisu/functions/` cannot be loaded
 pub(crate) fn create_template_engine(
     source_dir: &std::path::Path,
     identities: &std::sync::Arc<Vec<guisu_crypto::Identity>>,
     config: &guisu_config::Config,
-) -> guisu_template::TemplateEngine {
+) -> Result<guisu_template::TemplateEngine> {
     let templates_dir = source_dir.join(".guisu").join("templates");
 
     guisu_template::TemplateEngine::with_identities_arc_template_dir_and_bitwarden_provider(
@@ -880,4 +2184,107 @@ pub(crate) fn create_template_engine(
         },
         &config.bitwarden.provider,
     )
+    .with_pass_command(&config.pass.command)
+    .with_vault_retries(config.vault.retries)
+    .with_vault_offline(is_offline(config))
+    .with_vault_cache(cmd::vault::disk_cache(config, identities))
+    .with_vault_prefetch(config.vault.prefetch_jobs)
+    .with_allowed_commands(&config.template.allow_exec)
+    // Key writing is only switched on by apply, see `with_ssh_keygen`
+    .with_ssh_keygen(if config.template.ssh_keygen {
+        guisu_template::SshKeygen::Preview
+    } else {
+        guisu_template::SshKeygen::Disabled
+    })
+    .with_script_functions(&utils::path::SourceDirExt::functions_dir(source_dir))
+    .context("Failed to load template functions from .guisu/functions")
+}
+
+/// Activate the profile chosen with `--profile` or `[general] profile`
+///
+/// `profile` takes precedence over the config. The profile's variables are
+/// merged into the config here; its directories are applied by
+/// [`load_ignore_matcher`].
+pub(crate) fn activate_profile(
+    config: &mut guisu_config::Config,
+    source_dir: &std::path::Path,
+    profile: Option<&str>,
+) -> Result<()> {
+    if let Some(profile) = profile {
+        config.general.profile = Some(profile.to_string());
+    }
+    if config.general.profile.is_none() {
+        return Ok(());
+    }
+
+    let profiles =
+        guisu_config::Profiles::load(source_dir).context("Failed to load .guisu/profiles.toml")?;
+    profiles
+        .apply_variables(config)
+        .map_err(|e| anyhow::anyhow!("{e}"))
+}
+
+/// Load the ignore matcher for a source directory (crate-internal use only)
+///
+/// Combines `.guisu/ignores.toml`, `.guisuignore` files, the directories of
+/// profiles other than the active one, and the subtrees excluded by
+/// `.guisu/conditions.toml`. Conditions are evaluated here, once, against the
+/// same variables file templates see.
+///
+/// # Errors
+///
+/// Returns an error if ignore patterns, profiles or conditions cannot be
+/// loaded, if the active profile is not defined, or if a condition fails to
+/// evaluate
+pub(crate) fn load_ignore_matcher(
+    source_dir: &std::path::Path,
+    dotfiles_dir: &std::path::Path,
+    config: &guisu_config::Config,
+) -> Result<guisu_config::IgnoreMatcher> {
+    let matcher = guisu_config::IgnoreMatcher::load(source_dir, dotfiles_dir)
+        .context("Failed to load ignore patterns")?;
+
+    // Directories of profiles other than the active one
+    let mut excluded = guisu_config::Profiles::load(source_dir)
+        .context("Failed to load .guisu/profiles.toml")?
+        .excluded_dirs(config.general.profile.as_deref())
+        .map_err(|e| anyhow::anyhow!("{e}"))?;
+
+    let conditions = guisu_config::Conditions::load(source_dir)
+        .context("Failed to load .guisu/conditions.toml")?;
+    if conditions.is_empty() {
+        return Ok(matcher.with_excluded_dirs(excluded));
+    }
+
+    // Conditions select files, so they cannot depend on decrypting anything
+    let engine = create_template_engine(source_dir, &std::sync::Arc::new(Vec::new()), config)?;
+    let working_tree = guisu_engine::git::find_working_tree(source_dir)
+        .unwrap_or_else(|| source_dir.to_path_buf());
+    let dst_dir = config
+        .general
+        .dst_dir
+        .clone()
+        .or_else(dirs::home_dir)
+        .unwrap_or_default();
+    let context = guisu_template::TemplateContext::new()
+        .with_guisu_info(
+            path_to_string(dotfiles_dir),
+            path_to_string(&working_tree),
+            path_to_string(&dst_dir),
+            path_to_string(&config.general.root_entry),
+        )
+        .with_profile(config.general.profile.clone())
+        .with_loaded_variables(source_dir, config)
+        .map_err(|e| anyhow::anyhow!("Failed to load variables: {e}"))?;
+
+    let excluded_by_conditions = conditions
+        .excluded_dirs(|expression| {
+            engine
+                .eval_condition(expression, &context)
+                .map_err(|e| guisu_core::Error::Message(e.to_string()))
+        })
+        .map_err(|e| anyhow::anyhow!("{e}"))?;
+    excluded.extend(excluded_by_conditions);
+
+    Ok(matcher.with_excluded_dirs(excluded))
 }
@@ -1,27 +1,85 @@
 //! Logging configuration for guisu CLI
 //!
 //! Provides beautiful terminal output and optional file logging using tracing.
+//! With `--log-format json`, events are written as one JSON object per line
+//! together with the spans they occurred in, for consumption by automation.
 
 use anyhow::Result;
+use clap::ValueEnum;
 use std::path::Path;
+use tracing_subscriber::fmt::MakeWriter;
+use tracing_subscriber::fmt::format::FmtSpan;
+use tracing_subscriber::fmt::writer::BoxMakeWriter;
+use tracing_subscriber::registry::LookupSpan;
 use tracing_subscriber::{EnvFilter, Layer, fmt, layer::SubscriberExt, util::SubscriberInitExt};
 
+/// Format of log output
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, ValueEnum)]
+pub enum LogFormat {
+    /// Human-readable lines
+    #[default]
+    Text,
+    /// One JSON object per event, with the enclosing spans
+    Json,
+}
+
+/// How logs are written
+#[derive(Debug, Clone, Copy, Default)]
+pub struct LogOptions<'a> {
+    /// Enable debug level logging
+    pub verbose: bool,
+    /// Enable trace level logging, including per-file spans and their durations
+    pub trace: bool,
+    /// Format of console and file logs
+    pub format: LogFormat,
+    /// Optional path to write logs to a file
+    pub log_file: Option<&'a Path>,
+    /// Write console logs to stderr instead of stdout (used by `serve`, where
+    /// stdout carries the protocol). JSON logs always go to stderr, so that
+    /// stdout keeps the command's own output.
+    pub to_stderr: bool,
+}
+
+impl LogOptions<'_> {
+    /// Level of guisu's own events on the console
+    fn level(&self) -> &'static str {
+        if self.trace {
+            "trace"
+        } else if self.verbose {
+            "debug"
+        } else {
+            "info"
+        }
+    }
+
+    /// Span lifecycle events to log: span closes, with their busy and idle
+    /// time, when tracing
+    fn span_events(&self) -> FmtSpan {
+        if self.trace {
+            FmtSpan::CLOSE
+        } else {
+            FmtSpan::NONE
+        }
+    }
+}
+
 /// Initialize the logging system
 ///
-/// # Arguments
-/// * `verbose` - Enable debug/trace level logging
-/// * `log_file` - Optional path to write logs to a file
-///
 /// # Examples
 /// ```ignore
 /// // Basic usage with info level
-/// init(false, None)?;
+/// init(&LogOptions::default())?;
 ///
 /// // Verbose mode with debug level
-/// init(true, None)?;
+/// init(&LogOptions { verbose: true, ..LogOptions::default() })?;
 ///
-/// // Write logs to file
-/// init(true, Some(Path::new("debug.log")))?;
+/// // JSON events with per-file spans, written to a file as well
+/// init(&LogOptions {
+///     trace: true,
+///     format: LogFormat::Json,
+///     log_file: Some(Path::new("debug.log")),
+///     ..LogOptions::default()
+/// })?;
 /// ```
 ///
 /// # Errors
@@ -34,12 +92,11 @@ use tracing_subscriber::{EnvFilter, Layer, fmt, layer::SubscriberExt, util::Subs
 ///
 /// Panics if:
 /// - The default environment filter cannot be created (hardcoded filter string is invalid)
-/// - The hardcoded "debug" filter string is invalid for file logging
+/// - The hardcoded file filter string is invalid for file logging
 ///
 /// These panics should never happen as the filter strings are validated at compile time.
-pub fn init(verbose: bool, log_file: Option<&Path>) -> Result<()> {
-    // Determine log level based on verbose flag
-    let level = if verbose { "debug" } else { "info" };
+pub fn init(options: &LogOptions<'_>) -> Result<()> {
+    let level = options.level();
 
     // Create environment filter
     // Allows overriding with RUST_LOG env var
@@ -51,103 +108,172 @@ pub fn init(verbose: bool, log_file: Option<&Path>) -> Result<()> {
         })
         .expect("failed to create default env filter");
 
-    // Build different subscribers based on verbose mode and log file
-    match (verbose, log_file) {
-        (true, Some(log_path)) => {
-            // Verbose mode with file logging
-            let stdout_layer = fmt::layer()
-                .with_target(false)
-                .with_thread_ids(false)
-                .with_thread_names(false)
-                .with_file(false)
-                .with_line_number(false)
-                .compact()
-                .with_ansi(true)
-                .with_filter(env_filter);
+    let console_layer = console_layer(options).with_filter(env_filter);
 
+    let file_layer = match options.log_file {
+        Some(log_path) => {
             let file = std::fs::OpenOptions::new()
                 .create(true)
                 .append(true)
                 .open(log_path)?;
+            let file_level = if options.trace { "trace" } else { "debug" };
+            Some(
+                file_layer(options, file)
+                    .with_filter(EnvFilter::try_new(file_level).expect("level is a valid filter")),
+            )
+        }
+        None => None,
+    };
+
+    tracing_subscriber::registry()
+        .with(console_layer)
+        .with(file_layer)
+        .try_init()?;
+
+    Ok(())
+}
+
+/// Layer writing logs to the console
+fn console_layer<S>(options: &LogOptions<'_>) -> Box<dyn Layer<S> + Send + Sync>
+where
+    S: tracing::Subscriber + for<'span> LookupSpan<'span>,
+{
+    if options.format == LogFormat::Json {
+        return json_layer(options, console_writer(true));
+    }
+
+    let layer = fmt::layer()
+        .with_writer(console_writer(options.to_stderr))
+        .with_target(false)
+        .with_thread_ids(false)
+        .with_thread_names(false)
+        .with_file(false)
+        .with_line_number(false)
+        .with_span_events(options.span_events())
+        .compact()
+        .with_ansi(true);
+    if options.verbose || options.trace {
+        layer.boxed()
+    } else {
+        // No timestamps in normal mode
+        layer.without_time().boxed()
+    }
+}
+
+/// Layer writing logs to the log file
+fn file_layer<S>(options: &LogOptions<'_>, file: std::fs::File) -> Box<dyn Layer<S> + Send + Sync>
+where
+    S: tracing::Subscriber + for<'span> LookupSpan<'span>,
+{
+    if options.format == LogFormat::Json {
+        return json_layer(options, file);
+    }
+
+    fmt::layer()
+        .with_writer(file)
+        .with_ansi(false)
+        .with_target(true)
+        .with_thread_ids(true)
+        .with_file(true)
+        .with_line_number(true)
+        .with_span_events(options.span_events())
+        .pretty()
+        .boxed()
+}
+
+/// Layer writing one JSON object per event to `writer`
+fn json_layer<S, W>(options: &LogOptions<'_>, writer: W) -> Box<dyn Layer<S> + Send + Sync>
+where
+    S: tracing::Subscriber + for<'span> LookupSpan<'span>,
+    W: for<'writer> MakeWriter<'writer> + Send + Sync + 'static,
+{
+    fmt::layer()
+        .json()
+        .with_writer(writer)
+        .with_current_span(true)
+        .with_span_list(true)
+        .with_span_events(options.span_events())
+        .boxed()
+}
+
+/// Select the writer used for console log output
+fn console_writer(to_stderr: bool) -> BoxMakeWriter {
+    if to_stderr {
+        BoxMakeWriter::new(std::io::stderr)
+    } else {
+        BoxMakeWriter::new(std::io::stdout)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    #![allow(clippy::unwrap_used, clippy::panic)]
+    use super::*;
+    use std::sync::{Arc, Mutex};
 
-            let file_layer = fmt::layer()
-                .with_writer(file)
-                .with_ansi(false)
-                .with_target(true)
-                .with_thread_ids(true)
-                .with_file(true)
-                .with_line_number(true)
-                .pretty()
-                .with_filter(EnvFilter::try_new("debug").expect("'debug' is a valid filter"));
-
-            tracing_subscriber::registry()
-                .with(stdout_layer)
-                .with(file_layer)
-                .init();
+    /// Writer collecting everything written to it
+    #[derive(Clone, Default)]
+    struct Buffer(Arc<Mutex<Vec<u8>>>);
+
+    impl std::io::Write for Buffer {
+        fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+            self.0.lock().unwrap().extend_from_slice(buf);
+            Ok(buf.len())
         }
-        (true, None) => {
-            // Verbose mode without file logging
-            let stdout_layer = fmt::layer()
-                .with_target(false)
-                .with_thread_ids(false)
-                .with_thread_names(false)
-                .with_file(false)
-                .with_line_number(false)
-                .compact()
-                .with_ansi(true)
-                .with_filter(env_filter);
-
-            tracing_subscriber::registry().with(stdout_layer).init();
+
+        fn flush(&mut self) -> std::io::Result<()> {
+            Ok(())
         }
-        (false, Some(log_path)) => {
-            // Normal mode with file logging
-            let stdout_layer = fmt::layer()
-                .with_target(false)
-                .with_thread_ids(false)
-                .with_thread_names(false)
-                .with_file(false)
-                .with_line_number(false)
-                .without_time() // No timestamps in normal mode
-                .compact()
-                .with_ansi(true)
-                .with_filter(env_filter);
+    }
 
-            let file = std::fs::OpenOptions::new()
-                .create(true)
-                .append(true)
-                .open(log_path)?;
+    impl<'a> MakeWriter<'a> for Buffer {
+        type Writer = Self;
 
-            let file_layer = fmt::layer()
-                .with_writer(file)
-                .with_ansi(false)
-                .with_target(true)
-                .with_thread_ids(true)
-                .with_file(true)
-                .with_line_number(true)
-                .pretty()
-                .with_filter(EnvFilter::try_new("debug").expect("'debug' is a valid filter"));
-
-            tracing_subscriber::registry()
-                .with(stdout_layer)
-                .with(file_layer)
-                .init();
-        }
-        (false, None) => {
-            // Normal mode without file logging
-            let stdout_layer = fmt::layer()
-                .with_target(false)
-                .with_thread_ids(false)
-                .with_thread_names(false)
-                .with_file(false)
-                .with_line_number(false)
-                .without_time() // No timestamps in normal mode
-                .compact()
-                .with_ansi(true)
-                .with_filter(env_filter);
-
-            tracing_subscriber::registry().with(stdout_layer).init();
+        fn make_writer(&'a self) -> Self::Writer {
+            self.clone()
         }
     }
 
-    Ok(())
+    #[test]
+    fn test_level() {
+        assert_eq!(LogOptions::default().level(), "info");
+        let verbose = LogOptions {
+            verbose: true,
+            ..LogOptions::default()
+        };
+        assert_eq!(verbose.level(), "debug");
+        let trace = LogOptions {
+            verbose: true,
+            trace: true,
+            ..LogOptions::default()
+        };
+        assert_eq!(trace.level(), "trace");
+    }
+
+    #[test]
+    fn test_json_events_include_spans_and_durations() {
+        let buffer = Buffer::default();
+        let options = LogOptions {
+            trace: true,
+            format: LogFormat::Json,
+            ..LogOptions::default()
+        };
+        let subscriber = tracing_subscriber::registry().with(json_layer(&options, buffer.clone()));
+
+        tracing::subscriber::with_default(subscriber, || {
+            let _span = tracing::trace_span!("apply_entry", path = ".bashrc").entered();
+            tracing::info!("applied");
+        });
+
+        let output = String::from_utf8(buffer.0.lock().unwrap().clone()).unwrap();
+        let events: Vec<serde_json::Value> = output
+            .lines()
+            .map(|line| serde_json::from_str(line).unwrap())
+            .collect();
+        assert_eq!(events.len(), 2, "{output}");
+        assert_eq!(events[0]["fields"]["message"], "applied");
+        assert_eq!(events[0]["span"]["path"], ".bashrc");
+        assert_eq!(events[1]["fields"]["message"], "close");
+        assert!(events[1]["fields"]["time.busy"].is_string(), "{output}");
+    }
 }
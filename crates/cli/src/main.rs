@@ -23,6 +23,11 @@ fn main() {
 
     // Run and display errors with miette formatting
     if let Err(e) = guisu::run(cli) {
+        // verify has already reported the differences; only the status matters
+        if let Some(guisu::error::CommandError::VerifyFailed { .. }) = e.downcast_ref() {
+            std::process::exit(1);
+        }
+
         // Convert anyhow error to miette for beautiful display
         let miette_error = miette::Report::msg(format!("{e:#}"));
         eprintln!("{miette_error:?}");
@@ -1,6 +1,10 @@
 //! Thread-safe statistics tracking for parallel operations
 
-use std::sync::atomic::{AtomicU32, Ordering};
+use guisu_engine::content::{Decryptor, TemplateRenderer};
+use std::fmt::Write as _;
+use std::sync::atomic::{AtomicU32, AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
 
 /// Thread-safe statistics for apply operations
 ///
@@ -16,6 +20,16 @@ pub struct ApplyStats {
     symlinks: AtomicU32,
     /// Number of failed operations
     failed: AtomicU32,
+    /// Number of targets deleted from the source and removed from the destination
+    removed: AtomicU32,
+    /// Conflict snapshots taken before overwriting local changes, as `(id, path)`
+    conflict_snapshots: Mutex<Vec<(String, String)>>,
+    /// Destinations of the wrong type moved aside before replacing, as `(path, backup)`
+    type_backups: Mutex<Vec<(String, String)>>,
+    /// Locally modified files copied aside before overwriting, as `(path, backup)`
+    file_backups: Mutex<Vec<(String, String)>>,
+    /// Target paths written, merged or removed in this run
+    changed: Mutex<Vec<String>>,
 }
 
 impl ApplyStats {
@@ -45,6 +59,11 @@ impl ApplyStats {
         self.failed.fetch_add(1, Ordering::Relaxed);
     }
 
+    /// Increment removed target count
+    pub fn inc_removed(&self) {
+        self.removed.fetch_add(1, Ordering::Relaxed);
+    }
+
     /// Get current file count
     pub fn files(&self) -> usize {
         self.files.load(Ordering::Relaxed) as usize
@@ -65,11 +84,93 @@ impl ApplyStats {
         self.failed.load(Ordering::Relaxed) as usize
     }
 
-    /// Get total count (excludes failed)
+    /// Get current removed target count
+    pub fn removed(&self) -> usize {
+        self.removed.load(Ordering::Relaxed) as usize
+    }
+
+    /// Get total count (excludes failed and removed)
     pub fn total(&self) -> usize {
         self.files() + self.directories() + self.symlinks()
     }
 
+    /// Record that the target at `path` was changed
+    pub fn record_change(&self, path: String) {
+        self.changed
+            .lock()
+            .unwrap_or_else(std::sync::PoisonError::into_inner)
+            .push(path);
+    }
+
+    /// Get the changed target paths, sorted
+    pub fn changed_paths(&self) -> Vec<String> {
+        let mut paths = self
+            .changed
+            .lock()
+            .unwrap_or_else(std::sync::PoisonError::into_inner)
+            .clone();
+        paths.sort();
+        paths.dedup();
+        paths
+    }
+
+    /// Record a conflict snapshot taken before overwriting `path`
+    pub fn record_conflict_snapshot(&self, id: String, path: String) {
+        self.conflict_snapshots
+            .lock()
+            .unwrap_or_else(std::sync::PoisonError::into_inner)
+            .push((id, path));
+    }
+
+    /// Get recorded conflict snapshots as `(id, path)`, sorted by path
+    pub fn conflict_snapshots(&self) -> Vec<(String, String)> {
+        let mut snapshots = self
+            .conflict_snapshots
+            .lock()
+            .unwrap_or_else(std::sync::PoisonError::into_inner)
+            .clone();
+        snapshots.sort_by(|a, b| a.1.cmp(&b.1));
+        snapshots
+    }
+
+    /// Record that `path` was moved to `backup` because it had the wrong type
+    pub fn record_type_backup(&self, path: String, backup: String) {
+        self.type_backups
+            .lock()
+            .unwrap_or_else(std::sync::PoisonError::into_inner)
+            .push((path, backup));
+    }
+
+    /// Get recorded type backups as `(path, backup)`, sorted by path
+    pub fn type_backups(&self) -> Vec<(String, String)> {
+        let mut backups = self
+            .type_backups
+            .lock()
+            .unwrap_or_else(std::sync::PoisonError::into_inner)
+            .clone();
+        backups.sort();
+        backups
+    }
+
+    /// Record that the local content of `path` was copied to `backup`
+    pub fn record_file_backup(&self, path: String, backup: String) {
+        self.file_backups
+            .lock()
+            .unwrap_or_else(std::sync::PoisonError::into_inner)
+            .push((path, backup));
+    }
+
+    /// Get recorded file backups as `(path, backup)`, sorted by path
+    pub fn file_backups(&self) -> Vec<(String, String)> {
+        let mut backups = self
+            .file_backups
+            .lock()
+            .unwrap_or_else(std::sync::PoisonError::into_inner)
+            .clone();
+        backups.sort();
+        backups
+    }
+
     /// Create a snapshot of current stats
     ///
     /// This is needed because `ApplyStats` uses atomics and cannot be cloned directly
@@ -80,6 +181,11 @@ impl ApplyStats {
             directories: AtomicU32::new(self.directories.load(Ordering::Relaxed)),
             symlinks: AtomicU32::new(self.symlinks.load(Ordering::Relaxed)),
             failed: AtomicU32::new(self.failed.load(Ordering::Relaxed)),
+            removed: AtomicU32::new(self.removed.load(Ordering::Relaxed)),
+            conflict_snapshots: Mutex::new(self.conflict_snapshots()),
+            type_backups: Mutex::new(self.type_backups()),
+            file_backups: Mutex::new(self.file_backups()),
+            changed: Mutex::new(self.changed_paths()),
         }
     }
 
@@ -129,6 +235,89 @@ impl ApplyStats {
             }
             println!("  {}", parts.join(", ").dimmed());
         }
+
+        let removed = self.removed();
+        if removed > 0 {
+            println!(
+                "{} {} {}",
+                "●".bright_red(),
+                removed.to_string().bright_red().bold(),
+                if dry_run {
+                    "would be removed"
+                } else {
+                    "removed"
+                }
+            );
+        }
+    }
+
+    /// Print references to local changes saved before being overwritten
+    pub fn print_conflict_snapshots(&self) {
+        use owo_colors::OwoColorize;
+
+        let snapshots = self.conflict_snapshots();
+        if snapshots.is_empty() {
+            return;
+        }
+
+        println!(
+            "{} {} local change(s) overwritten, saved for recovery:",
+            "●".yellow(),
+            snapshots.len().to_string().yellow().bold()
+        );
+        for (id, path) in &snapshots {
+            println!(
+                "  {} {}",
+                path.bright_white(),
+                format!("→ guisu conflicts show {id}").dimmed()
+            );
+        }
+    }
+
+    /// Print where destinations of the wrong type were moved before being replaced
+    pub fn print_type_backups(&self) {
+        use owo_colors::OwoColorize;
+
+        let backups = self.type_backups();
+        if backups.is_empty() {
+            return;
+        }
+
+        println!(
+            "{} {} destination(s) of the wrong type replaced, moved aside:",
+            "●".yellow(),
+            backups.len().to_string().yellow().bold()
+        );
+        for (path, backup) in &backups {
+            println!(
+                "  {} {}",
+                path.bright_white(),
+                format!("→ {backup}").dimmed()
+            );
+        }
+    }
+
+    /// Print where locally modified files were backed up before being overwritten
+    pub fn print_file_backups(&self) {
+        use owo_colors::OwoColorize;
+
+        let backups = self.file_backups();
+        if backups.is_empty() {
+            return;
+        }
+
+        println!(
+            "{} {} modified file(s) overwritten, backed up:",
+            "●".yellow(),
+            backups.len().to_string().yellow().bold()
+        );
+        for (path, backup) in &backups {
+            println!(
+                "  {} {}",
+                path.bright_white(),
+                format!("→ {backup}").dimmed()
+            );
+        }
     }
 }
 
@@ -143,6 +332,8 @@ pub struct DiffStats {
     modified: AtomicU32,
     /// Number of unchanged files
     unchanged: AtomicU32,
+    /// Number of files deleted from the source
+    removed: AtomicU32,
     /// Number of errors encountered
     errors: AtomicU32,
 }
@@ -169,6 +360,11 @@ impl DiffStats {
         self.unchanged.fetch_add(1, Ordering::Relaxed);
     }
 
+    /// Increment removed file count
+    pub fn inc_removed(&self) {
+        self.removed.fetch_add(1, Ordering::Relaxed);
+    }
+
     /// Increment error count
     pub fn inc_errors(&self) {
         self.errors.fetch_add(1, Ordering::Relaxed);
@@ -189,6 +385,11 @@ impl DiffStats {
         self.unchanged.load(Ordering::Relaxed) as usize
     }
 
+    /// Get current removed file count
+    pub fn removed(&self) -> usize {
+        self.removed.load(Ordering::Relaxed) as usize
+    }
+
     /// Get current error count
     pub fn errors(&self) -> usize {
         self.errors.load(Ordering::Relaxed) as usize
@@ -263,6 +464,179 @@ impl StatusStats {
     }
 }
 
+/// Phase of an apply or diff run measured by `--timings`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Phase {
+    /// Reading the source directory, externals and managed state
+    Scan,
+    /// Rendering templates
+    Render,
+    /// Loading identities and decrypting files and inline values
+    Decrypt,
+    /// Hashing contents to detect changes
+    Hash,
+    /// Reading and writing destinations
+    Io,
+}
+
+impl Phase {
+    /// All phases, in report order
+    pub const ALL: [Self; 5] = [
+        Self::Scan,
+        Self::Render,
+        Self::Decrypt,
+        Self::Hash,
+        Self::Io,
+    ];
+
+    /// Name shown in the report
+    #[must_use]
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Scan => "scan",
+            Self::Render => "render",
+            Self::Decrypt => "decrypt",
+            Self::Hash => "hash",
+            Self::Io => "io",
+        }
+    }
+}
+
+/// Thread-safe time spent per phase
+///
+/// Time spent on worker threads is summed, so a phase that runs in parallel
+/// can add up to more than the wall-clock time of the run.
+#[derive(Debug)]
+pub struct Timings {
+    /// Nanoseconds per phase, indexed like [`Phase::ALL`]
+    nanos: [AtomicU64; 5],
+    started: Instant,
+}
+
+impl Default for Timings {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Timings {
+    /// Create new timings, starting the wall clock now
+    #[must_use]
+    pub fn new() -> Self {
+        Self {
+            nanos: Default::default(),
+            started: Instant::now(),
+        }
+    }
+
+    /// Add `elapsed` to `phase`
+    pub fn record(&self, phase: Phase, elapsed: Duration) {
+        let nanos = u64::try_from(elapsed.as_nanos()).unwrap_or(u64::MAX);
+        self.nanos[phase as usize].fetch_add(nanos, Ordering::Relaxed);
+    }
+
+    /// Run `f` and add the time it took to `phase`
+    pub fn time<T>(&self, phase: Phase, f: impl FnOnce() -> T) -> T {
+        let start = Instant::now();
+        let result = f();
+        self.record(phase, start.elapsed());
+        result
+    }
+
+    /// Time spent in `phase`
+    #[must_use]
+    pub fn get(&self, phase: Phase) -> Duration {
+        Duration::from_nanos(self.nanos[phase as usize].load(Ordering::Relaxed))
+    }
+
+    /// Phase-by-phase breakdown with each phase's share of the measured time
+    #[must_use]
+    pub fn report(&self) -> String {
+        let measured: Duration = Phase::ALL.iter().map(|phase| self.get(*phase)).sum();
+        let mut report = format!(
+            "Timings (wall {}, threads {})\n",
+            format_duration(self.started.elapsed()),
+            rayon::current_num_threads()
+        );
+        for phase in Phase::ALL {
+            let elapsed = self.get(phase);
+            let share = if measured.is_zero() {
+                0.0
+            } else {
+                elapsed.as_secs_f64() / measured.as_secs_f64() * 100.0
+            };
+            let _ = writeln!(
+                report,
+                "  {:<8} {:>10} {share:>5.1}%",
+                phase.name(),
+                format_duration(elapsed)
+            );
+        }
+        report
+    }
+}
+
+/// Format a duration with a unit suited to its size
+fn format_duration(duration: Duration) -> String {
+    if duration >= Duration::from_secs(1) {
+        format!("{:.2}s", duration.as_secs_f64())
+    } else {
+        format!("{:.1}ms", duration.as_secs_f64() * 1000.0)
+    }
+}
+
+/// Decryptor or renderer that adds its time to a phase of [`Timings`]
+pub struct Timed<T> {
+    inner: T,
+    timings: Arc<Timings>,
+}
+
+impl<T> Timed<T> {
+    /// Wrap `inner`, recording into `timings`
+    pub fn new(inner: T, timings: Arc<Timings>) -> Self {
+        Self { inner, timings }
+    }
+}
+
+impl<D: Decryptor> Decryptor for Timed<D> {
+    type Error = D::Error;
+
+    fn decrypt(&self, encrypted: &[u8]) -> Result<Vec<u8>, Self::Error> {
+        self.timings
+            .time(Phase::Decrypt, || self.inner.decrypt(encrypted))
+    }
+
+    fn decrypt_file(&self, path: &str, encrypted: &[u8]) -> Result<Vec<u8>, Self::Error> {
+        self.timings
+            .time(Phase::Decrypt, || self.inner.decrypt_file(path, encrypted))
+    }
+
+    fn decrypt_stream(
+        &self,
+        path: &str,
+        encrypted: &mut dyn std::io::Read,
+        output: &mut dyn std::io::Write,
+    ) -> Result<(), Self::Error> {
+        self.timings.time(Phase::Decrypt, || {
+            self.inner.decrypt_stream(path, encrypted, output)
+        })
+    }
+
+    fn decrypt_inline(&self, text: &str) -> Result<String, Self::Error> {
+        self.timings
+            .time(Phase::Decrypt, || self.inner.decrypt_inline(text))
+    }
+}
+
+impl<R: TemplateRenderer> TemplateRenderer for Timed<R> {
+    type Error = R::Error;
+
+    fn render(&self, template: &str, context: &serde_json::Value) -> Result<String, Self::Error> {
+        self.timings
+            .time(Phase::Render, || self.inner.render(template, context))
+    }
+}
+
 #[cfg(test)]
 mod tests {
     #![allow(clippy::unwrap_used, clippy::panic)]
@@ -357,6 +731,34 @@ mod tests {
         assert_eq!(stats.files(), 3);
     }
 
+    #[test]
+    fn test_apply_stats_conflict_snapshots() {
+        let stats = ApplyStats::new();
+        stats.record_conflict_snapshot("bbbb".to_string(), ".zshrc".to_string());
+        stats.record_conflict_snapshot("aaaa".to_string(), ".bashrc".to_string());
+
+        let expected = vec![
+            ("aaaa".to_string(), ".bashrc".to_string()),
+            ("bbbb".to_string(), ".zshrc".to_string()),
+        ];
+        assert_eq!(stats.conflict_snapshots(), expected);
+        assert_eq!(stats.snapshot().conflict_snapshots(), expected);
+    }
+
+    #[test]
+    fn test_apply_stats_type_backups() {
+        let stats = ApplyStats::new();
+        stats.record_type_backup(".zshrc".to_string(), "/h/.zshrc.guisu-backup".to_string());
+        stats.record_type_backup(".bashrc".to_string(), "/h/.bashrc.guisu-backup".to_string());
+
+        let expected = vec![
+            (".bashrc".to_string(), "/h/.bashrc.guisu-backup".to_string()),
+            (".zshrc".to_string(), "/h/.zshrc.guisu-backup".to_string()),
+        ];
+        assert_eq!(stats.type_backups(), expected);
+        assert_eq!(stats.snapshot().type_backups(), expected);
+    }
+
     #[test]
     fn test_apply_stats_mixed_operations() {
         let stats = ApplyStats::new();
@@ -537,4 +939,37 @@ mod tests {
         assert_eq!(stats.added(), 1);
         assert_eq!(stats.removed(), 1);
     }
+
+    #[test]
+    fn test_timings_record_per_phase() {
+        let timings = Timings::new();
+        timings.record(Phase::Render, Duration::from_millis(30));
+        timings.record(Phase::Render, Duration::from_millis(10));
+        timings.record(Phase::Io, Duration::from_millis(60));
+
+        assert_eq!(timings.get(Phase::Render), Duration::from_millis(40));
+        assert_eq!(timings.get(Phase::Io), Duration::from_millis(60));
+        assert_eq!(timings.get(Phase::Scan), Duration::ZERO);
+        assert_eq!(timings.time(Phase::Hash, || 7), 7);
+
+        let report = timings.report();
+        for phase in Phase::ALL {
+            assert!(report.contains(phase.name()), "{report}");
+        }
+        assert!(report.contains("40.0ms"), "{report}");
+        assert!(report.contains("60.0%"), "{report}");
+    }
+
+    #[test]
+    fn test_timed_renderer_records_render() {
+        let timings = Arc::new(Timings::new());
+        let renderer = Timed::new(guisu_engine::content::NoOpRenderer, Arc::clone(&timings));
+        let output = renderer
+            .render("{{ name }}", &serde_json::Value::Null)
+            .unwrap();
+
+        assert_eq!(output, "{{ name }}");
+        assert!(timings.get(Phase::Render) > Duration::ZERO);
+        assert_eq!(timings.get(Phase::Decrypt), Duration::ZERO);
+    }
 }
@@ -28,177 +28,175 @@ impl MergeResult {
     }
 }
 
-/// Perform three-way merge
-///
-/// # Arguments
-/// * `base` - Common ancestor (last synchronized version from database)
-/// * `local` - Current destination file content
-/// * `remote` - Source file content
-///
-/// # Returns
-/// `MergeResult::Success` if merge completed cleanly
-/// `MergeResult::Conflicts` if there are conflicts (includes conflict markers)
-///
-/// Add conflict markers to merge result
-fn add_conflict_marker(result: &mut Vec<String>, local_line: &str, remote_line: &str) {
-    result.push("<<<<<<< LOCAL (destination)".to_string());
-    result.push(local_line.to_string());
-    result.push("=======".to_string());
-    result.push(remote_line.to_string());
-    result.push(">>>>>>> REMOTE (source)".to_string());
+/// Conflict marker opening the destination side
+const LOCAL_MARKER: &str = "<<<<<<< LOCAL (destination)";
+/// Conflict marker opening the common ancestor
+const BASE_MARKER: &str = "||||||| BASE (last applied)";
+/// Conflict marker opening the source side
+const SEPARATOR_MARKER: &str = "=======";
+/// Conflict marker closing the source side
+const REMOTE_MARKER: &str = ">>>>>>> REMOTE (source)";
+
+/// A changed region of one side, as line ranges in the base and in that side
+#[derive(Debug, Clone, Copy)]
+struct Hunk {
+    base_start: usize,
+    base_end: usize,
+    side_start: usize,
+    side_end: usize,
 }
 
-/// Handle case where all three versions have the same line
-fn handle_unchanged_line(result: &mut Vec<String>, line: &str) -> (usize, usize, usize) {
-    result.push(line.to_string());
-    (1, 1, 1) // Advance all indices
-}
+/// Changed regions from `base` to `side`, in base order
+///
+/// Adjacent edits are joined, so hunks of one side never touch.
+fn diff_hunks(base: &[&str], side: &[&str]) -> Vec<Hunk> {
+    let mut hunks: Vec<Hunk> = Vec::new();
 
-/// Handle case where only remote changed
-fn handle_remote_only_change(result: &mut Vec<String>, remote: &str) -> (usize, usize, usize) {
-    result.push(remote.to_string());
-    (1, 1, 1) // Advance all indices
-}
+    for op in similar::capture_diff_slices(similar::Algorithm::Myers, base, side) {
+        if matches!(op, similar::DiffOp::Equal { .. }) {
+            continue;
+        }
+        let (base_range, side_range) = (op.old_range(), op.new_range());
+        match hunks.last_mut() {
+            Some(last)
+                if last.base_end == base_range.start && last.side_end == side_range.start =>
+            {
+                last.base_end = base_range.end;
+                last.side_end = side_range.end;
+            }
+            _ => hunks.push(Hunk {
+                base_start: base_range.start,
+                base_end: base_range.end,
+                side_start: side_range.start,
+                side_end: side_range.end,
+            }),
+        }
+    }
 
-/// Handle case where only local changed
-fn handle_local_only_change(result: &mut Vec<String>, local: &str) -> (usize, usize, usize) {
-    result.push(local.to_string());
-    (1, 1, 1) // Advance all indices
+    hunks
 }
 
-/// Handle case where both local and remote changed
-fn handle_both_changes(
-    result: &mut Vec<String>,
-    local: &str,
-    remote: &str,
-    has_conflicts: &mut bool,
-) -> (usize, usize, usize) {
-    if local == remote {
-        // Same change in both
-        result.push(local.to_string());
-    } else {
-        // Conflict: different changes
-        *has_conflicts = true;
-        add_conflict_marker(result, local, remote);
-    }
-    (1, 1, 1) // Advance all indices
+/// Lines of one side covering base lines `start..end`
+///
+/// `hunks` are that side's hunks within the range; around them the side
+/// matches the base. Returns `None` if the side has no hunks there.
+fn side_region<'a, 'b>(
+    side: &'b [&'a str],
+    hunks: &[Hunk],
+    start: usize,
+    end: usize,
+) -> Option<&'b [&'a str]> {
+    let (first, last) = (hunks.first()?, hunks.last()?);
+    let side_start = first.side_start - (first.base_start - start);
+    let side_end = last.side_end + (end - last.base_end);
+    Some(&side[side_start..side_end])
 }
 
-/// Handle case where lines were added in both local and remote
-fn handle_additions(
-    result: &mut Vec<String>,
-    local: &str,
-    remote: &str,
-    has_conflicts: &mut bool,
-) -> (usize, usize, usize) {
-    if local == remote {
-        result.push(local.to_string());
-        (0, 1, 1) // Don't advance base
-    } else {
-        // Conflict: different additions
-        *has_conflicts = true;
-        add_conflict_marker(result, local, remote);
-        (0, 1, 1) // Don't advance base
+/// Append a diff3-style conflict region to `out`
+fn push_conflict(out: &mut String, local: &[&str], base: &[&str], remote: &[&str]) {
+    for (marker, lines) in [
+        (LOCAL_MARKER, local),
+        (BASE_MARKER, base),
+        (SEPARATOR_MARKER, remote),
+    ] {
+        out.push_str(marker);
+        out.push('\n');
+        for line in lines {
+            out.push_str(line);
+        }
+        if !out.ends_with('\n') {
+            out.push('\n');
+        }
     }
+    out.push_str(REMOTE_MARKER);
+    out.push('\n');
 }
 
+/// Perform three-way merge
+///
+/// Changes made on either side since `base` are combined line by line. Where
+/// both sides changed the same (or adjacent) lines differently, the region is
+/// written diff3-style: destination lines, base lines and source lines
+/// between conflict markers.
+///
+/// # Arguments
+/// * `base` - Common ancestor (last applied version from database)
+/// * `local` - Current destination file content
+/// * `remote` - Source file content
+///
+/// # Returns
+/// `MergeResult::Success` if merge completed cleanly
+/// `MergeResult::Conflicts` if there are conflicts (includes conflict markers)
+///
 /// # Errors
 ///
 /// Currently never returns an error (Result is for future compatibility)
 pub fn three_way_merge(base: &str, local: &str, remote: &str) -> Result<MergeResult> {
-    // Compute diffs from base to local and base to remote
-    let _diff_local = TextDiff::from_lines(base, local);
-    let _diff_remote = TextDiff::from_lines(base, remote);
+    let base_lines: Vec<&str> = base.split_inclusive('\n').collect();
+    let local_lines: Vec<&str> = local.split_inclusive('\n').collect();
+    let remote_lines: Vec<&str> = remote.split_inclusive('\n').collect();
 
-    // Build line-by-line merge
-    let base_lines: Vec<&str> = base.lines().collect();
-    let local_lines: Vec<&str> = local.lines().collect();
-    let remote_lines: Vec<&str> = remote.lines().collect();
+    let local_hunks = diff_hunks(&base_lines, &local_lines);
+    let remote_hunks = diff_hunks(&base_lines, &remote_lines);
 
-    let mut result = Vec::new();
+    let mut merged = String::new();
     let mut has_conflicts = false;
-
-    // Track position in base, local, and remote
-    let mut base_idx = 0;
-    let mut local_idx = 0;
-    let mut remote_idx = 0;
-
-    // Simple merge algorithm:
-    // For each line in base, check if it was changed in local or remote
-    // - If unchanged in both: keep original
-    // - If changed in one: use the changed version
-    // - If changed in both differently: conflict
-
-    while base_idx < base_lines.len()
-        || local_idx < local_lines.len()
-        || remote_idx < remote_lines.len()
-    {
-        let base_line = base_lines.get(base_idx).copied();
-        let local_line = local_lines.get(local_idx).copied();
-        let remote_line = remote_lines.get(remote_idx).copied();
-
-        let (base_inc, local_inc, remote_inc) = match (base_line, local_line, remote_line) {
-            (Some(b), Some(l), Some(r)) if b == l && b == r => {
-                handle_unchanged_line(&mut result, b)
-            }
-            (Some(b), Some(l), Some(r)) if b == l && b != r => {
-                handle_remote_only_change(&mut result, r)
-            }
-            (Some(b), Some(l), Some(r)) if b != l && b == r => {
-                handle_local_only_change(&mut result, l)
-            }
-            (Some(b), Some(l), Some(r)) if b != l && b != r => {
-                handle_both_changes(&mut result, l, r, &mut has_conflicts)
-            }
-            (None, Some(l), Some(r)) => handle_additions(&mut result, l, r, &mut has_conflicts),
-            (None, Some(l), None) => {
-                // Added in local only
-                result.push(l.to_string());
-                (0, 1, 0)
-            }
-            (None, None, Some(r)) => {
-                // Added in remote only
-                result.push(r.to_string());
-                (0, 0, 1)
-            }
-            (Some(_), None, Some(r)) => {
-                // Deleted in local, maybe modified in remote
-                result.push(r.to_string());
-                (1, 0, 1)
-            }
-            (Some(_), Some(l), None) => {
-                // Deleted in remote, maybe modified in local
-                result.push(l.to_string());
-                (1, 1, 0)
-            }
-            (Some(_), None, None) => {
-                // Deleted in both
-                (1, 0, 0)
-            }
-            (None, None, None) => {
-                // Should not happen
+    let mut base_pos = 0;
+    let (mut l, mut r) = (0, 0);
+
+    loop {
+        // Start a region at the earliest remaining hunk, then pull in every
+        // hunk of either side that overlaps or touches it
+        let region_start = match (local_hunks.get(l), remote_hunks.get(r)) {
+            (Some(a), Some(b)) => a.base_start.min(b.base_start),
+            (Some(a), None) => a.base_start,
+            (None, Some(b)) => b.base_start,
+            (None, None) => break,
+        };
+        let mut region_end = region_start;
+        let (local_from, remote_from) = (l, r);
+        loop {
+            if let Some(hunk) = local_hunks.get(l).filter(|h| h.base_start <= region_end) {
+                region_end = region_end.max(hunk.base_end);
+                l += 1;
+            } else if let Some(hunk) = remote_hunks.get(r).filter(|h| h.base_start <= region_end) {
+                region_end = region_end.max(hunk.base_end);
+                r += 1;
+            } else {
                 break;
             }
-            _ => {
-                // Handle remaining cases
-                if let Some(l) = local_line {
-                    result.push(l.to_string());
-                    (0, 1, 0)
-                } else if let Some(r) = remote_line {
-                    result.push(r.to_string());
-                    (0, 0, 1)
-                } else {
-                    break;
-                }
-            }
-        };
+        }
 
-        base_idx += base_inc;
-        local_idx += local_inc;
-        remote_idx += remote_inc;
+        merged.extend(base_lines[base_pos..region_start].iter().copied());
+        base_pos = region_end;
+
+        let base_region = &base_lines[region_start..region_end];
+        let local_region = side_region(
+            &local_lines,
+            &local_hunks[local_from..l],
+            region_start,
+            region_end,
+        )
+        .unwrap_or(base_region);
+        let remote_region = side_region(
+            &remote_lines,
+            &remote_hunks[remote_from..r],
+            region_start,
+            region_end,
+        )
+        .unwrap_or(base_region);
+
+        if local_from == l || local_region == remote_region {
+            merged.extend(remote_region.iter().copied());
+        } else if remote_from == r {
+            merged.extend(local_region.iter().copied());
+        } else {
+            has_conflicts = true;
+            push_conflict(&mut merged, local_region, base_region, remote_region);
+        }
     }
 
-    let merged = result.join("\n");
+    merged.extend(base_lines[base_pos..].iter().copied());
 
     if has_conflicts {
         Ok(MergeResult::Conflicts(merged))
@@ -379,9 +377,8 @@ mod tests {
         let remote = "line1\nline3";
 
         let result = three_way_merge(base, local, remote).unwrap();
-        // This is a simple algorithm, may not perfectly handle insertions
-        // Just verify it completes without panic
-        let _content = result.content();
+        assert!(!result.has_conflicts());
+        assert_eq!(result.content(), "line1\nline2\nline3");
     }
 
     #[test]
@@ -392,9 +389,8 @@ mod tests {
         let remote = "line1\nline2\nline3";
 
         let result = three_way_merge(base, local, remote).unwrap();
-        // This is a simple algorithm, may not perfectly handle insertions
-        // Just verify it completes without panic
-        let _content = result.content();
+        assert!(!result.has_conflicts());
+        assert_eq!(result.content(), "line1\nline2\nline3");
     }
 
     #[test]
@@ -456,8 +452,40 @@ mod tests {
         let remote = "line1\nline3";
 
         let result = three_way_merge(base, local, remote).unwrap();
-        // Simple algorithm behavior - just verify it completes without panic
-        let _content = result.content();
+        assert!(!result.has_conflicts());
+        assert_eq!(result.content(), "line1\nline3");
+    }
+
+    #[test]
+    fn test_three_way_merge_combines_separate_changes() {
+        // Local and remote changed different parts of the file
+        let base = "a\nb\nc\nd\ne\n";
+        let local = "a\nlocal b\nc\nd\ne\n";
+        let remote = "a\nb\nc\nd\nremote e\nf\n";
+
+        let result = three_way_merge(base, local, remote).unwrap();
+        assert!(!result.has_conflicts());
+        assert_eq!(result.content(), "a\nlocal b\nc\nd\nremote e\nf\n");
+    }
+
+    #[test]
+    fn test_three_way_merge_conflict_diff3_markers() {
+        // Conflicting region shows destination, base and source
+        let base = "a\nb\nc\nd\n";
+        let local = "a\nlocal b\nc\nlocal d\n";
+        let remote = "a\nremote b\nc\nd\n";
+
+        let result = three_way_merge(base, local, remote).unwrap();
+        assert!(result.has_conflicts());
+        assert_eq!(
+            result.content(),
+            "a\n\
+             <<<<<<< LOCAL (destination)\nlocal b\n\
+             ||||||| BASE (last applied)\nb\n\
+             =======\nremote b\n\
+             >>>>>>> REMOTE (source)\n\
+             c\nlocal d\n"
+        );
     }
 
     // Tests for two_way_merge
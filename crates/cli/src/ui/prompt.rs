@@ -184,6 +184,7 @@ impl ConflictPrompt {
             ChangeType::LocalModification => "Local modification",
             ChangeType::SourceUpdate => "Source updated",
             ChangeType::TrueConflict => "Conflict",
+            ChangeType::TypeMismatch { .. } => "Type conflict",
         };
 
         let title = format!("{}: {}", prefix, self.file_path);
@@ -0,0 +1,225 @@
+//! Crash-safe writes of destination files
+//!
+//! [`replace`] writes the new content to a temporary file in the destination's
+//! directory and renames it over the destination, so an apply interrupted by a
+//! crash or power loss leaves either the old or the new file, never a truncated
+//! one. How much is synced to disk along the way is set by [`FsyncPolicy`].
+//!
+//! Some destinations are overwritten in place with [`overwrite`] instead:
+//! files with other hard links, which a rename would split from them, and files
+//! a rename cannot replace, such as single files bind-mounted into a container
+//! (`EXDEV`, or `EBUSY` on Linux).
+
+use anyhow::{Context, Result};
+use guisu_config::FsyncPolicy;
+use std::fs::{self, File};
+use std::io::{self, Seek};
+use std::path::Path;
+
+/// Prefix of temporary files, so ones left behind by a crash are recognizable
+const TEMP_PREFIX: &str = ".guisu-";
+
+/// Replace `path` with what `write` produces, through a renamed temporary file
+///
+/// On Unix the temporary file gets `mode` before any content is written, so
+/// secrets are never readable by others; without a mode it is only readable
+/// by its owner. Elsewhere the mode is applied after writing.
+///
+/// # Errors
+///
+/// Returns an error if `write` fails, or if the file cannot be written,
+/// synced, or moved into place
+pub fn replace(
+    path: &Path,
+    mode: Option<u32>,
+    fsync: FsyncPolicy,
+    write: impl FnOnce(&mut File) -> Result<()>,
+) -> Result<()> {
+    let dir = path
+        .parent()
+        .filter(|dir| !dir.as_os_str().is_empty())
+        .unwrap_or(Path::new("."));
+    let mut temp = match tempfile::Builder::new()
+        .prefix(TEMP_PREFIX)
+        .suffix(".tmp")
+        .tempfile_in(dir)
+    {
+        Ok(temp) => temp,
+        // A writable file in a read-only directory can still be written in place
+        Err(e) if e.kind() == io::ErrorKind::PermissionDenied && path.is_file() => {
+            return overwrite(path, mode, fsync, write);
+        }
+        Err(e) => {
+            return Err(e)
+                .with_context(|| format!("Failed to create temporary file in {}", dir.display()));
+        }
+    };
+
+    #[cfg(unix)]
+    if let Some(mode) = mode {
+        guisu_engine::system::set_permissions(temp.path(), mode)
+            .with_context(|| format!("Failed to set permissions: {}", path.display()))?;
+    }
+    write(temp.as_file_mut())?;
+    #[cfg(not(unix))]
+    if let Some(mode) = mode {
+        guisu_engine::system::set_permissions(temp.path(), mode)
+            .with_context(|| format!("Failed to set permissions: {}", path.display()))?;
+    }
+
+    if fsync != FsyncPolicy::None {
+        temp.as_file()
+            .sync_all()
+            .with_context(|| format!("Failed to sync file: {}", path.display()))?;
+    }
+
+    match temp.persist(path) {
+        Ok(_) => {}
+        Err(e) if is_unrenameable(&e.error) => {
+            let mut temp = e.file;
+            temp.rewind()
+                .with_context(|| format!("Failed to read temporary file for {}", path.display()))?;
+            return overwrite(path, mode, fsync, |file| {
+                io::copy(temp.as_file_mut(), file)
+                    .map(drop)
+                    .with_context(|| format!("Failed to write file content: {}", path.display()))
+            });
+        }
+        Err(e) => {
+            return Err(e.error)
+                .with_context(|| format!("Failed to move file into place: {}", path.display()));
+        }
+    }
+
+    if fsync == FsyncPolicy::Dir {
+        sync_dir(dir)?;
+    }
+    Ok(())
+}
+
+/// Overwrite `path` in place with what `write` produces
+///
+/// Keeps the file's inode, and with it any hard links, but a crash mid-write
+/// leaves the file truncated. A new file is created with `mode`, and an
+/// existing one is changed to it before the content is written.
+///
+/// # Errors
+///
+/// Returns an error if `write` fails, or if the file cannot be opened,
+/// written, or synced
+pub fn overwrite(
+    path: &Path,
+    mode: Option<u32>,
+    fsync: FsyncPolicy,
+    write: impl FnOnce(&mut File) -> Result<()>,
+) -> Result<()> {
+    let mut options = fs::OpenOptions::new();
+    options.write(true).create(true).truncate(true);
+    #[cfg(unix)]
+    if let Some(mode) = mode {
+        use std::os::unix::fs::OpenOptionsExt;
+        options.mode(mode);
+    }
+    let mut file = options
+        .open(path)
+        .with_context(|| format!("Failed to create file: {}", path.display()))?;
+
+    // An existing file keeps its permissions when opened, so tighten them
+    // before any content (e.g. an env file) is written
+    #[cfg(unix)]
+    if let Some(mode) = mode {
+        use std::os::unix::fs::PermissionsExt;
+        let current = file
+            .metadata()
+            .with_context(|| format!("Failed to read metadata: {}", path.display()))?
+            .permissions()
+            .mode();
+        if current & 0o7777 != mode & 0o7777 {
+            file.set_permissions(fs::Permissions::from_mode(mode))
+                .with_context(|| format!("Failed to set permissions: {}", path.display()))?;
+        }
+    }
+    write(&mut file)?;
+    #[cfg(not(unix))]
+    if let Some(mode) = mode {
+        guisu_engine::system::set_permissions(path, mode)
+            .with_context(|| format!("Failed to set permissions: {}", path.display()))?;
+    }
+
+    if fsync != FsyncPolicy::None {
+        file.sync_all()
+            .with_context(|| format!("Failed to sync file: {}", path.display()))?;
+    }
+    Ok(())
+}
+
+/// Whether a rename failed because the destination cannot be replaced by one
+fn is_unrenameable(error: &io::Error) -> bool {
+    matches!(
+        error.kind(),
+        io::ErrorKind::CrossesDevices | io::ErrorKind::ResourceBusy
+    )
+}
+
+/// Sync a directory, so renames into it survive a power loss
+fn sync_dir(dir: &Path) -> Result<()> {
+    // Directories cannot be opened as files on Windows, where renames are
+    // journaled by the filesystem instead
+    #[cfg(unix)]
+    File::open(dir)
+        .and_then(|dir| dir.sync_all())
+        .with_context(|| format!("Failed to sync directory: {}", dir.display()))?;
+    #[cfg(not(unix))]
+    let _ = dir;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    #![allow(clippy::unwrap_used)]
+    use super::*;
+    use std::io::Write;
+    use tempfile::TempDir;
+
+    fn write_text(text: &str) -> impl FnOnce(&mut File) -> Result<()> + '_ {
+        move |file| Ok(file.write_all(text.as_bytes())?)
+    }
+
+    #[test]
+    fn test_replace_swaps_in_new_file() {
+        let temp = TempDir::new().unwrap();
+        let path = temp.path().join("config");
+        fs::write(&path, "old").unwrap();
+
+        replace(&path, Some(0o640), FsyncPolicy::Dir, write_text("new")).unwrap();
+        assert_eq!(fs::read_to_string(&path).unwrap(), "new");
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            let mode = fs::metadata(&path).unwrap().permissions().mode();
+            assert_eq!(mode & 0o777, 0o640);
+        }
+
+        // A failed write leaves the old file and no temporary file behind
+        let failed = replace(&path, None, FsyncPolicy::File, |file| {
+            file.write_all(b"partial")?;
+            anyhow::bail!("interrupted")
+        });
+        assert!(failed.is_err());
+        assert_eq!(fs::read_to_string(&path).unwrap(), "new");
+        assert_eq!(fs::read_dir(temp.path()).unwrap().count(), 1);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_overwrite_keeps_hard_links() {
+        let temp = TempDir::new().unwrap();
+        let path = temp.path().join("config");
+        let link = temp.path().join("link");
+        fs::write(&path, "old").unwrap();
+        fs::hard_link(&path, &link).unwrap();
+
+        overwrite(&path, Some(0o600), FsyncPolicy::None, write_text("new")).unwrap();
+        assert_eq!(fs::read_to_string(&link).unwrap(), "new");
+    }
+}
@@ -0,0 +1,168 @@
+//! Automatic commits of source changes
+//!
+//! With `[git] autoCommit` or `autoPush` set, commands that modify the source
+//! directory commit their changes afterwards, and optionally push them.
+
+use anyhow::{Context, Result};
+use guisu_engine::git::{create_provider, find_working_tree};
+use guisu_template::TemplateContext;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use tracing::{debug, warn};
+
+use super::path::SourceDirExt;
+use crate::common::RuntimeContext;
+
+/// Result of an automatic commit
+#[derive(Debug)]
+pub struct AutoCommit {
+    /// ID of the new commit
+    pub commit_id: String,
+    /// Rendered commit message
+    pub message: String,
+    /// Whether the commit was pushed
+    pub pushed: bool,
+}
+
+/// Commit (and push) changes in the source directory after `command`
+///
+/// Only the dotfiles and `.guisu` directories are staged, so unrelated work in
+/// the repository is left alone. Returns `None` when automatic commits are
+/// disabled, the source is not a git repository, or nothing changed.
+///
+/// # Errors
+///
+/// Returns an error if staging, rendering the message, committing, or pushing fails
+pub fn auto_commit(context: &RuntimeContext, command: &str) -> Result<Option<AutoCommit>> {
+    let git = &context.config.git;
+    if !git.auto_commit && !git.auto_push {
+        return Ok(None);
+    }
+
+    let source_dir = context.source_dir();
+    let Some(working_tree) = find_working_tree(source_dir) else {
+        debug!("Source directory is not a git repository, skipping auto-commit");
+        return Ok(None);
+    };
+
+    let pathspecs: Vec<PathBuf> = [context.dotfiles_dir().as_path(), &source_dir.guisu_dir()]
+        .into_iter()
+        .filter_map(|path| relative_to(path, &working_tree))
+        .collect();
+
+    let provider = create_provider(&context.config);
+    provider
+        .stage(&working_tree, &pathspecs)
+        .context("Failed to stage source changes")?;
+    let changes = provider
+        .staged_changes(&working_tree)
+        .context("Failed to read staged changes")?;
+    if changes.is_empty() {
+        return Ok(None);
+    }
+
+    let message = render_commit_message(context, &git.commit_message_template, command, &changes)?;
+    let commit_id = provider
+        .commit(&working_tree, &message)
+        .context("Failed to commit source changes")?;
+
+    let push = git.auto_push && !crate::is_offline(&context.config);
+    if push {
+        provider
+            .push(&working_tree)
+            .context("Failed to push source changes")?;
+    } else if git.auto_push {
+        warn!("Offline mode: not pushing source changes");
+    }
+
+    Ok(Some(AutoCommit {
+        commit_id,
+        message,
+        pushed: push,
+    }))
+}
+
+/// Path relative to the working tree, or `None` if it is outside or missing
+pub(crate) fn relative_to(path: &Path, working_tree: &Path) -> Option<PathBuf> {
+    let path = fs::canonicalize(path).ok()?;
+    let working_tree = fs::canonicalize(working_tree).ok()?;
+    path.strip_prefix(&working_tree).ok().map(Path::to_path_buf)
+}
+
+/// Render the commit message template with `command` and `paths` variables
+fn render_commit_message(
+    context: &RuntimeContext,
+    template: &str,
+    command: &str,
+    changes: &[PathBuf],
+) -> Result<String> {
+    let config = &context.config;
+    let identities = Arc::new(config.age_identities().unwrap_or_default());
+    let engine = crate::create_template_engine(context.source_dir(), &identities, config)?;
+
+    let mut template_ctx = TemplateContext::new()
+        .with_loaded_variables(context.source_dir(), config)
+        .map_err(|e| anyhow::anyhow!("Failed to load variables: {e}"))?;
+    template_ctx.add_variable("command".to_string(), command.into());
+    template_ctx.add_variable(
+        "paths".to_string(),
+        changes
+            .iter()
+            .map(|path| path.to_string_lossy().into_owned())
+            .collect::<Vec<_>>()
+            .into(),
+    );
+
+    let message = engine
+        .render_str(template, &template_ctx)
+        .map_err(|e| anyhow::anyhow!("Failed to render commit message template: {e}"))?;
+    Ok(message.trim().to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    #![allow(clippy::unwrap_used, clippy::panic)]
+    use super::*;
+    use crate::common::testing::{TestWorkspace, write};
+    use git2::Repository;
+    use guisu_config::Config;
+
+    #[test]
+    fn test_auto_commit_stages_source_paths_only() {
+        let mut workspace = TestWorkspace::new(Config::default());
+        let source = workspace.context.source_dir().to_path_buf();
+        let repo = Repository::init(&source).unwrap();
+        let mut git_config = repo.config().unwrap();
+        git_config.set_str("user.name", "Guisu Test").unwrap();
+        git_config
+            .set_str("user.email", "test@example.com")
+            .unwrap();
+
+        write(&workspace.source(".bashrc"), "export A=1\n");
+        write(&source.join("scratch.txt"), "not managed\n");
+
+        // Disabled by default
+        assert!(auto_commit(&workspace.context, "add").unwrap().is_none());
+
+        let mut config = Config::default();
+        config.git.auto_commit = true;
+        config.git.commit_message_template = "{{ command }}: {{ paths | join(\", \") }}".into();
+        workspace.context.config = Arc::new(config);
+        let commit = auto_commit(&workspace.context, "add").unwrap().unwrap();
+        assert_eq!(commit.message, "add: home/.bashrc");
+        assert!(!commit.pushed);
+
+        let head = repo.head().unwrap().peel_to_commit().unwrap();
+        assert_eq!(head.id().to_string(), commit.commit_id);
+        assert!(
+            head.tree()
+                .unwrap()
+                .get_path(Path::new("scratch.txt"))
+                .is_err()
+        );
+
+        // Nothing left to commit
+        assert!(auto_commit(&workspace.context, "edit").unwrap().is_none());
+    }
+}
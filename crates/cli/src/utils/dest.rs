@@ -0,0 +1,233 @@
+//! Single-stat view of a destination path
+//!
+//! Apply and diff inspect every destination several times per entry: whether
+//! it exists, what kind of file it is, its mode, and its content. A
+//! [`DestProbe`] answers all of these from one `lstat` (plus a `stat` when the
+//! destination is a symlink) and reads the content at most once, so the compare
+//! and write phases share the same view of the file.
+//!
+//! A probe is a snapshot: it is not refreshed after the destination is written.
+
+use anyhow::{Context, Result};
+use guisu_core::path::AbsPath;
+use guisu_engine::entry::EntryKind;
+use std::fs::{self, Metadata};
+use std::io;
+use std::sync::OnceLock;
+
+/// Metadata and lazily read content of a destination path
+#[derive(Debug)]
+pub struct DestProbe {
+    path: AbsPath,
+    /// Metadata of the path itself (`lstat`)
+    link_metadata: Option<Metadata>,
+    /// Metadata with symlinks followed (`stat`), `None` if missing or dangling
+    metadata: Option<Metadata>,
+    content: OnceLock<io::Result<Vec<u8>>>,
+    content_hash: OnceLock<io::Result<[u8; 32]>>,
+}
+
+impl DestProbe {
+    /// Stat `path` once and remember the result
+    #[must_use]
+    pub fn new(path: AbsPath) -> Self {
+        let link_metadata = fs::symlink_metadata(path.as_path()).ok();
+        let metadata = match &link_metadata {
+            // Follow the link so symlinked dotfiles report their real type and mode
+            Some(meta) if meta.file_type().is_symlink() => fs::metadata(path.as_path()).ok(),
+            other => other.clone(),
+        };
+
+        Self {
+            path,
+            link_metadata,
+            metadata,
+            content: OnceLock::new(),
+            content_hash: OnceLock::new(),
+        }
+    }
+
+    /// Destination path
+    #[must_use]
+    pub fn path(&self) -> &AbsPath {
+        &self.path
+    }
+
+    /// Whether the destination exists, following symlinks like [`std::path::Path::exists`]
+    #[must_use]
+    pub fn exists(&self) -> bool {
+        self.metadata.is_some()
+    }
+
+    /// Whether anything, including a dangling symlink, occupies the destination
+    #[must_use]
+    pub fn is_present(&self) -> bool {
+        self.link_metadata.is_some()
+    }
+
+    /// Whether the destination is a directory, following symlinks like [`std::path::Path::is_dir`]
+    #[must_use]
+    pub fn is_dir(&self) -> bool {
+        self.metadata.as_ref().is_some_and(Metadata::is_dir)
+    }
+
+    /// Whether the destination itself is a symlink
+    #[must_use]
+    pub fn is_symlink(&self) -> bool {
+        self.link_metadata
+            .as_ref()
+            .is_some_and(|meta| meta.file_type().is_symlink())
+    }
+
+    /// Kind of entry occupying the destination, without following symlinks
+    #[must_use]
+    pub fn kind(&self) -> EntryKind {
+        match &self.link_metadata {
+            None => EntryKind::Missing,
+            Some(meta) if meta.file_type().is_symlink() => EntryKind::Symlink,
+            Some(meta) if meta.is_dir() => EntryKind::Directory,
+            Some(_) => EntryKind::File,
+        }
+    }
+
+    /// Full Unix mode of the destination (file type and permission bits)
+    ///
+    /// Always `None` on non-Unix platforms.
+    #[must_use]
+    pub fn mode(&self) -> Option<u32> {
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            self.metadata.as_ref().map(|meta| meta.permissions().mode())
+        }
+        #[cfg(not(unix))]
+        {
+            None
+        }
+    }
+
+    /// Number of hard links to the destination, following symlinks
+    ///
+    /// Always 1 on non-Unix platforms, and 0 if the destination is missing.
+    #[must_use]
+    pub fn hard_links(&self) -> u64 {
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::MetadataExt;
+            self.metadata.as_ref().map_or(0, MetadataExt::nlink)
+        }
+        #[cfg(not(unix))]
+        {
+            u64::from(self.metadata.is_some())
+        }
+    }
+
+    /// Content of the destination, read on first use
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the destination cannot be read
+    pub fn content(&self) -> Result<&[u8]> {
+        self.content
+            .get_or_init(|| fs::read(self.path.as_path()))
+            .as_deref()
+            .map_err(|e| anyhow::anyhow!("{e}"))
+            .with_context(|| format!("Failed to read destination file: {}", self.path))
+    }
+
+    /// Blake3 hash of the destination's content, computed on first use
+    ///
+    /// Content that was already read is hashed as is; otherwise the file is
+    /// hashed while it is read, so large files are not held in memory.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the destination cannot be read
+    pub fn content_hash(&self) -> Result<[u8; 32]> {
+        let hash = self.content_hash.get_or_init(|| match self.content.get() {
+            Some(Ok(content)) => Ok(guisu_engine::hash::hash_content(content)),
+            _ => guisu_engine::hash::hash_file(self.path.as_path()),
+        });
+        hash.as_ref()
+            .copied()
+            .map_err(|e| anyhow::anyhow!("{e}"))
+            .with_context(|| format!("Failed to read destination file: {}", self.path))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    #![allow(clippy::unwrap_used, clippy::panic)]
+    use super::*;
+    use std::path::Path;
+    use tempfile::TempDir;
+
+    fn probe(path: &Path) -> DestProbe {
+        DestProbe::new(AbsPath::new(path.to_path_buf()).unwrap())
+    }
+
+    #[test]
+    fn test_probe_missing() {
+        let temp = TempDir::new().unwrap();
+        let dest = probe(&temp.path().join("missing"));
+
+        assert!(!dest.exists());
+        assert!(!dest.is_present());
+        assert!(!dest.is_dir());
+        assert_eq!(dest.kind(), EntryKind::Missing);
+        assert_eq!(dest.mode(), None);
+        assert!(dest.content().is_err());
+    }
+
+    #[test]
+    fn test_probe_file_content_is_read_once() {
+        let temp = TempDir::new().unwrap();
+        let file = temp.path().join("file");
+        fs::write(&file, "before").unwrap();
+
+        let dest = probe(&file);
+        assert!(dest.exists());
+        assert!(!dest.is_dir());
+        assert_eq!(dest.kind(), EntryKind::File);
+        assert_eq!(probe(temp.path()).kind(), EntryKind::Directory);
+        assert_eq!(dest.content().unwrap(), b"before");
+
+        // Later reads come from the probe, not the disk
+        fs::write(&file, "after").unwrap();
+        assert_eq!(dest.content().unwrap(), b"before");
+        assert_eq!(
+            dest.content_hash().unwrap(),
+            guisu_engine::hash::hash_content(b"before")
+        );
+        assert_eq!(
+            probe(&file).content_hash().unwrap(),
+            guisu_engine::hash::hash_content(b"after")
+        );
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_probe_follows_symlinks() {
+        use std::os::unix::fs::{PermissionsExt, symlink};
+
+        let temp = TempDir::new().unwrap();
+        let real = temp.path().join("real");
+        fs::write(&real, "x").unwrap();
+        fs::set_permissions(&real, fs::Permissions::from_mode(0o640)).unwrap();
+        let link = temp.path().join("link");
+        symlink(&real, &link).unwrap();
+
+        let dest = probe(&link);
+        assert!(dest.exists());
+        assert!(dest.is_symlink());
+        assert_eq!(dest.kind(), EntryKind::Symlink);
+        assert_eq!(dest.mode().unwrap() & 0o777, 0o640);
+
+        let dangling = temp.path().join("dangling");
+        symlink(temp.path().join("nowhere"), &dangling).unwrap();
+        let dest = probe(&dangling);
+        assert!(!dest.exists());
+        assert!(dest.is_present());
+        assert!(dest.is_symlink());
+    }
+}
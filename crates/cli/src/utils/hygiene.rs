@@ -0,0 +1,229 @@
+//! Source hygiene checks
+//!
+//! Flags source files whose line endings or trailing newline disagree with the
+//! deployed destination file. Editors and tools that normalize the destination
+//! otherwise produce a one-line diff on every `status`/`diff` that `apply` can
+//! never settle.
+
+use anyhow::{Context, Result};
+use guisu_core::path::{AbsPath, RelPath};
+use guisu_engine::entry::SourceEntry;
+use guisu_engine::state::SourceState;
+use std::fs;
+use std::path::PathBuf;
+
+/// A hygiene problem in a raw source file
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum HygieneIssue {
+    /// Source uses CRLF line endings but the destination uses LF
+    CrlfLineEndings,
+    /// Source lacks a trailing newline but the destination ends with one
+    MissingTrailingNewline,
+}
+
+impl HygieneIssue {
+    /// Short human-readable description
+    pub(crate) fn description(self) -> &'static str {
+        match self {
+            Self::CrlfLineEndings => "CRLF line endings",
+            Self::MissingTrailingNewline => "missing trailing newline",
+        }
+    }
+}
+
+/// Hygiene issues found for one managed entry
+#[derive(Debug)]
+pub(crate) struct HygieneFinding {
+    /// Target path relative to the destination directory
+    pub(crate) target_path: RelPath,
+    /// Absolute path of the raw source file
+    pub(crate) source_file: PathBuf,
+    /// Issues found, in a stable order
+    pub(crate) issues: Vec<HygieneIssue>,
+}
+
+/// Check every plain-text source file against its deployed destination
+///
+/// Encrypted entries, binary files, and entries not yet deployed are skipped:
+/// without a destination file there is no policy to compare against.
+pub(crate) fn check_source_hygiene(
+    source_state: &SourceState,
+    dest_path: impl Fn(&RelPath) -> AbsPath,
+    filter_paths: Option<&[RelPath]>,
+) -> Vec<HygieneFinding> {
+    let mut findings = Vec::new();
+
+    for entry in source_state.entries() {
+        let SourceEntry::File {
+            source_path,
+            target_path,
+            attributes,
+        } = entry
+        else {
+            continue;
+        };
+
+        if attributes.is_encrypted() || attributes.is_compressed() {
+            continue;
+        }
+
+        if let Some(filter) = filter_paths
+            && !filter.contains(target_path)
+        {
+            continue;
+        }
+
+        let source_file = source_state.source_file_path(source_path);
+        let Ok(source) = fs::read(source_file.as_path()) else {
+            continue;
+        };
+        let Ok(dest) = fs::read(dest_path(target_path).as_path()) else {
+            continue;
+        };
+
+        let issues = detect_issues(&source, &dest);
+        if !issues.is_empty() {
+            findings.push(HygieneFinding {
+                target_path: target_path.clone(),
+                source_file: source_file.as_path().to_path_buf(),
+                issues,
+            });
+        }
+    }
+
+    findings.sort_by(|a, b| a.target_path.as_path().cmp(b.target_path.as_path()));
+    findings
+}
+
+/// Compare raw source bytes with the destination's line ending policy
+fn detect_issues(source: &[u8], dest: &[u8]) -> Vec<HygieneIssue> {
+    // Only text files have a line ending policy
+    if source.is_empty() || std::str::from_utf8(source).is_err() {
+        return Vec::new();
+    }
+
+    let mut issues = Vec::new();
+
+    if contains_crlf(source) && !contains_crlf(dest) {
+        issues.push(HygieneIssue::CrlfLineEndings);
+    }
+
+    if !source.ends_with(b"\n") && dest.ends_with(b"\n") {
+        issues.push(HygieneIssue::MissingTrailingNewline);
+    }
+
+    issues
+}
+
+fn contains_crlf(content: &[u8]) -> bool {
+    content.windows(2).any(|pair| pair == b"\r\n")
+}
+
+/// Rewrite a source file so it matches the destination policy
+///
+/// # Errors
+///
+/// Returns an error if the source file cannot be read or written
+pub(crate) fn fix_source(finding: &HygieneFinding) -> Result<()> {
+    let content = fs::read_to_string(&finding.source_file)
+        .with_context(|| format!("Failed to read {}", finding.source_file.display()))?;
+
+    let mut fixed = if finding.issues.contains(&HygieneIssue::CrlfLineEndings) {
+        content.replace("\r\n", "\n")
+    } else {
+        content
+    };
+
+    if finding
+        .issues
+        .contains(&HygieneIssue::MissingTrailingNewline)
+    {
+        fixed.push('\n');
+    }
+
+    fs::write(&finding.source_file, fixed)
+        .with_context(|| format!("Failed to write {}", finding.source_file.display()))
+}
+
+#[cfg(test)]
+mod tests {
+    #![allow(clippy::unwrap_used, clippy::panic)]
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_detect_issues_clean() {
+        assert_eq!(
+            detect_issues(b"a\nb\n", b"a\nb\n"),
+            Vec::<HygieneIssue>::new()
+        );
+    }
+
+    #[test]
+    fn test_detect_issues_crlf_against_lf_destination() {
+        assert_eq!(
+            detect_issues(b"a\r\nb\r\n", b"a\nb\n"),
+            vec![HygieneIssue::CrlfLineEndings]
+        );
+        // Destination that keeps CRLF matches the source policy
+        assert_eq!(
+            detect_issues(b"a\r\nb\r\n", b"a\r\nb\r\n"),
+            Vec::<HygieneIssue>::new()
+        );
+    }
+
+    #[test]
+    fn test_detect_issues_missing_trailing_newline() {
+        assert_eq!(
+            detect_issues(b"a\nb", b"a\nb\n"),
+            vec![HygieneIssue::MissingTrailingNewline]
+        );
+        assert_eq!(detect_issues(b"a\nb", b"a\nb"), Vec::<HygieneIssue>::new());
+    }
+
+    #[test]
+    fn test_detect_issues_skips_binary_and_empty() {
+        assert_eq!(
+            detect_issues(&[0xff, 0xfe, b'\r', b'\n'], b"\n"),
+            Vec::<HygieneIssue>::new()
+        );
+        assert_eq!(detect_issues(b"", b"\n"), Vec::<HygieneIssue>::new());
+    }
+
+    #[test]
+    fn test_check_and_fix_source_hygiene() {
+        let temp = TempDir::new().unwrap();
+        let root = fs::canonicalize(temp.path()).unwrap();
+        let source_dir = root.join("src");
+        let dest_dir = root.join("dst");
+        fs::create_dir_all(&source_dir).unwrap();
+        fs::create_dir_all(&dest_dir).unwrap();
+
+        fs::write(source_dir.join(".bashrc"), "export A=1\r\nexport B=2").unwrap();
+        fs::write(dest_dir.join(".bashrc"), "export A=1\nexport B=2\n").unwrap();
+        fs::write(source_dir.join(".vimrc"), "set nu\n").unwrap();
+        fs::write(dest_dir.join(".vimrc"), "set nu\n").unwrap();
+        fs::write(source_dir.join(".latent"), "no newline").unwrap();
+
+        let source_state = SourceState::read(AbsPath::new(source_dir.clone()).unwrap()).unwrap();
+        let dest_abs = AbsPath::new(dest_dir).unwrap();
+
+        let findings = check_source_hygiene(&source_state, |path| dest_abs.join(path), None);
+        assert_eq!(findings.len(), 1);
+        assert_eq!(findings[0].target_path.to_string(), ".bashrc");
+        assert_eq!(
+            findings[0].issues,
+            vec![
+                HygieneIssue::CrlfLineEndings,
+                HygieneIssue::MissingTrailingNewline
+            ]
+        );
+
+        fix_source(&findings[0]).unwrap();
+        assert_eq!(
+            fs::read_to_string(source_dir.join(".bashrc")).unwrap(),
+            "export A=1\nexport B=2\n"
+        );
+        assert!(check_source_hygiene(&source_state, |path| dest_abs.join(path), None).is_empty());
+    }
+}
@@ -0,0 +1,163 @@
+//! Lock serializing commands that modify the destination or state
+//!
+//! Commands such as apply, update, and add take an advisory lock on
+//! `guisu.lock` in the state directory, so a cron `guisu update` and a manual
+//! `guisu apply` never interleave their writes. The lock file records the PID
+//! and command of the holder for error messages. Read-only commands do not
+//! take the lock.
+
+use anyhow::{Context, Result, bail};
+use owo_colors::OwoColorize;
+use std::fs::{File, OpenOptions, TryLockError};
+use std::io::{Read, Seek, Write};
+use std::path::{Path, PathBuf};
+
+/// Name of the lock file in the state directory
+const LOCK_FILE: &str = "guisu.lock";
+
+/// Path of the lock file, next to the state database
+///
+/// # Errors
+///
+/// Returns an error if the state directory cannot be determined or created
+pub fn lock_path() -> Result<PathBuf> {
+    let db_path = guisu_engine::database::get_db_path().context("Failed to get database path")?;
+    Ok(db_path.with_file_name(LOCK_FILE))
+}
+
+/// Held lock, released when dropped
+#[derive(Debug)]
+pub struct RunLock {
+    _file: File,
+}
+
+impl RunLock {
+    /// Take the lock at `path` for `command`
+    ///
+    /// If another process holds it, waits for it to be released when `wait`
+    /// is set, and fails otherwise.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the lock file cannot be opened or written, or if
+    /// the lock is held and `wait` is not set
+    pub fn acquire(path: &Path, command: &str, wait: bool) -> Result<Self> {
+        let mut file = OpenOptions::new()
+            .read(true)
+            .write(true)
+            .create(true)
+            .truncate(false)
+            .open(path)
+            .with_context(|| format!("Failed to open lock file: {}", path.display()))?;
+
+        match file.try_lock() {
+            Ok(()) => {}
+            Err(TryLockError::WouldBlock) => {
+                let holder = read_holder(&mut file);
+                if !wait {
+                    bail!(
+                        "Another guisu process is running ({holder}). \
+                         Pass --wait to wait for it to finish."
+                    );
+                }
+                eprintln!(
+                    "{} for another guisu process to finish ({holder})",
+                    "Waiting".yellow()
+                );
+                file.lock()
+                    .with_context(|| format!("Failed to lock {}", path.display()))?;
+            }
+            Err(TryLockError::Error(e)) => {
+                return Err(e).with_context(|| format!("Failed to lock {}", path.display()));
+            }
+        }
+
+        // Only the holder writes, so the contents always describe the holder
+        file.set_len(0)
+            .and_then(|()| file.rewind())
+            .and_then(|()| writeln!(file, "pid {} running {command}", std::process::id()))
+            .with_context(|| format!("Failed to write lock file: {}", path.display()))?;
+
+        Ok(Self { _file: file })
+    }
+
+    /// Describe the process holding the lock at `path`, `None` if it is free
+    #[must_use]
+    pub fn holder(path: &Path) -> Option<String> {
+        let mut file = File::open(path).ok()?;
+        match file.try_lock_shared() {
+            Err(TryLockError::WouldBlock) => Some(read_holder(&mut file)),
+            Ok(()) | Err(TryLockError::Error(_)) => None,
+        }
+    }
+}
+
+/// Whether `holder`, as described by [`RunLock::holder`], is this process
+#[must_use]
+pub fn is_own(holder: &str) -> bool {
+    holder
+        .strip_prefix("pid ")
+        .and_then(|rest| rest.split_whitespace().next())
+        .and_then(|pid| pid.parse::<u32>().ok())
+        == Some(std::process::id())
+}
+
+/// Read the holder recorded in the lock file
+fn read_holder(file: &mut File) -> String {
+    let mut holder = String::new();
+    if file.rewind().is_err() || file.read_to_string(&mut holder).is_err() {
+        holder.clear();
+    }
+    let holder = holder.trim();
+    if holder.is_empty() {
+        "unknown process".to_string()
+    } else {
+        holder.to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    #![allow(clippy::unwrap_used, clippy::panic)]
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_lock_is_exclusive_until_dropped() {
+        let temp = TempDir::new().unwrap();
+        let path = temp.path().join(LOCK_FILE);
+
+        let lock = RunLock::acquire(&path, "apply", false).unwrap();
+        let holder = RunLock::holder(&path).unwrap();
+        assert!(holder.contains(&std::process::id().to_string()));
+        assert!(holder.contains("apply"));
+        assert!(is_own(&holder));
+        assert!(!is_own("pid 0 running apply"));
+        assert!(!is_own("unknown process"));
+
+        let err = RunLock::acquire(&path, "update", false).unwrap_err();
+        assert!(err.to_string().contains("running apply"));
+        assert!(err.to_string().contains("--wait"));
+
+        drop(lock);
+        assert!(RunLock::holder(&path).is_none());
+        let _lock = RunLock::acquire(&path, "update", false).unwrap();
+    }
+
+    #[test]
+    fn test_wait_for_lock() {
+        let temp = TempDir::new().unwrap();
+        let path = temp.path().join(LOCK_FILE);
+
+        let lock = RunLock::acquire(&path, "apply", false).unwrap();
+        let waiter = {
+            let path = path.clone();
+            std::thread::spawn(move || RunLock::acquire(&path, "update", true).map(|_| ()))
+        };
+        std::thread::sleep(std::time::Duration::from_millis(100));
+        assert!(!waiter.is_finished());
+
+        drop(lock);
+        waiter.join().unwrap().unwrap();
+    }
+}
@@ -1,4 +1,11 @@
 //! Utility modules for CLI operations
 
+pub mod atomic;
+pub mod autocommit;
+pub mod dest;
 pub mod hooks;
+pub mod hygiene;
+pub mod lock;
 pub mod path;
+pub mod secrets;
+pub mod sudo;
@@ -28,6 +28,18 @@ pub trait SourceDirExt {
     /// Path to `.guisu/templates/` directory
     fn templates_dir(&self) -> PathBuf;
 
+    /// Get the scaffolds directory path
+    ///
+    /// # Returns
+    /// Path to `.guisu/scaffolds/` directory
+    fn scaffolds_dir(&self) -> PathBuf;
+
+    /// Get the template functions directory path
+    ///
+    /// # Returns
+    /// Path to `.guisu/functions/` directory
+    fn functions_dir(&self) -> PathBuf;
+
     /// Get the pre-hooks directory path
     ///
     /// # Returns
@@ -54,6 +66,14 @@ impl SourceDirExt for Path {
         self.guisu_dir().join("templates")
     }
 
+    fn scaffolds_dir(&self) -> PathBuf {
+        self.guisu_dir().join("scaffolds")
+    }
+
+    fn functions_dir(&self) -> PathBuf {
+        self.guisu_dir().join("functions")
+    }
+
     fn pre_hooks_dir(&self) -> PathBuf {
         self.hooks_dir().join("pre")
     }
@@ -92,6 +112,24 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_scaffolds_dir() {
+        let source = Path::new("/home/user/dotfiles");
+        assert_eq!(
+            source.scaffolds_dir(),
+            Path::new("/home/user/dotfiles/.guisu/scaffolds")
+        );
+    }
+
+    #[test]
+    fn test_functions_dir() {
+        let source = Path::new("/home/user/dotfiles");
+        assert_eq!(
+            source.functions_dir(),
+            Path::new("/home/user/dotfiles/.guisu/functions")
+        );
+    }
+
     #[test]
     fn test_pre_hooks_dir() {
         let source = Path::new("/home/user/dotfiles");
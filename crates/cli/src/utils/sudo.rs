@@ -0,0 +1,181 @@
+//! Writing destinations through `sudo`
+//!
+//! Roots in .guisu/targets.toml marked `sudo = true` (such as `/etc`) are
+//! usually owned by root. When the current user cannot write to such a root,
+//! apply asks for the password once with `sudo -v` and then performs each
+//! write with `sudo -n`, so no command ever prompts in the middle of a run.
+
+use anyhow::{Context, Result, bail};
+use guisu_config::{TargetRoot, Targets};
+use owo_colors::OwoColorize;
+use std::ffi::OsStr;
+use std::fs;
+use std::io::Write;
+use std::path::Path;
+use std::process::{Command, Stdio};
+
+/// Roots that are marked `sudo = true` and not writable by the current user
+#[must_use]
+pub fn roots_needing_sudo(targets: &Targets) -> Vec<&TargetRoot> {
+    targets
+        .roots()
+        .iter()
+        .filter(|root| root.sudo && !is_writable(&root.root))
+        .collect()
+}
+
+/// Whether the current user can write to `path`, or to the directory it would be created in
+#[cfg(unix)]
+fn is_writable(path: &Path) -> bool {
+    let Some(existing) = path.ancestors().find(|dir| dir.exists()) else {
+        return false;
+    };
+    rustix::fs::access(existing, rustix::fs::Access::WRITE_OK).is_ok()
+}
+
+#[cfg(not(unix))]
+fn is_writable(_path: &Path) -> bool {
+    true
+}
+
+/// Ask for the `sudo` password up front
+///
+/// # Errors
+///
+/// Returns an error if `sudo` is missing or authentication fails
+pub fn authenticate(roots: &[&TargetRoot]) -> Result<()> {
+    for root in roots {
+        println!(
+            "{} {} requires root; sudo may ask for your password",
+            "Writing to".dimmed(),
+            root.root.display().bright_white()
+        );
+    }
+
+    let status = Command::new("sudo")
+        .arg("-v")
+        .status()
+        .context("Failed to run sudo")?;
+    if !status.success() {
+        bail!("sudo authentication failed");
+    }
+    Ok(())
+}
+
+/// Write `content` to `path` as root with the given mode
+///
+/// # Errors
+///
+/// Returns an error if any `sudo` command fails
+pub fn write_file(path: &Path, content: &[u8], mode: u32) -> Result<()> {
+    write_file_with(path, mode, |staged| {
+        staged
+            .write_all(content)
+            .context("Failed to write temporary file")
+    })
+}
+
+/// Write what `write` produces to `path` as root with the given mode
+///
+/// The content is staged in a temporary file, so it can be written a chunk
+/// at a time.
+///
+/// # Errors
+///
+/// Returns an error if `write` or any `sudo` command fails
+pub fn write_file_with(
+    path: &Path,
+    mode: u32,
+    write: impl FnOnce(&mut fs::File) -> Result<()>,
+) -> Result<()> {
+    let mut staged = tempfile::NamedTempFile::new().context("Failed to create temporary file")?;
+    write(staged.as_file_mut())?;
+
+    if let Some(parent) = path.parent() {
+        create_dir(parent)?;
+    }
+    let mode = format!("{mode:o}");
+    run([
+        OsStr::new("install"),
+        OsStr::new("-m"),
+        OsStr::new(&mode),
+        staged.path().as_os_str(),
+        path.as_os_str(),
+    ])
+}
+
+/// Create a directory and its parents as root
+///
+/// # Errors
+///
+/// Returns an error if the `sudo` command fails
+pub fn create_dir(path: &Path) -> Result<()> {
+    run([OsStr::new("mkdir"), OsStr::new("-p"), path.as_os_str()])
+}
+
+/// Set the permissions of `path` as root
+///
+/// # Errors
+///
+/// Returns an error if the `sudo` command fails
+pub fn set_mode(path: &Path, mode: u32) -> Result<()> {
+    let mode = format!("{mode:o}");
+    run([OsStr::new("chmod"), OsStr::new(&mode), path.as_os_str()])
+}
+
+/// Replace the file or symlink at `path` with a symlink to `target` as root
+///
+/// A directory at `path` is not removed; back it up with [`rename`] first.
+///
+/// # Errors
+///
+/// Returns an error if any `sudo` command fails
+pub fn symlink(target: &Path, path: &Path) -> Result<()> {
+    run([OsStr::new("rm"), OsStr::new("-f"), path.as_os_str()])?;
+    if let Some(parent) = path.parent() {
+        create_dir(parent)?;
+    }
+    run([
+        OsStr::new("ln"),
+        OsStr::new("-s"),
+        target.as_os_str(),
+        path.as_os_str(),
+    ])
+}
+
+/// Move `from` to `to` as root
+///
+/// # Errors
+///
+/// Returns an error if the `sudo` command fails
+pub fn rename(from: &Path, to: &Path) -> Result<()> {
+    run([OsStr::new("mv"), from.as_os_str(), to.as_os_str()])
+}
+
+/// Remove `path` recursively as root
+///
+/// # Errors
+///
+/// Returns an error if the `sudo` command fails
+pub fn remove(path: &Path) -> Result<()> {
+    run([OsStr::new("rm"), OsStr::new("-rf"), path.as_os_str()])
+}
+
+/// Run a command with `sudo -n`, failing with its stderr
+fn run<'a>(args: impl IntoIterator<Item = &'a OsStr>) -> Result<()> {
+    let args: Vec<&OsStr> = args.into_iter().collect();
+    let output = Command::new("sudo")
+        .arg("-n")
+        .args(&args)
+        .stdin(Stdio::null())
+        .output()
+        .context("Failed to run sudo")?;
+    if !output.status.success() {
+        bail!(
+            "sudo {} failed: {}",
+            args[0].to_string_lossy(),
+            String::from_utf8_lossy(&output.stderr).trim()
+        );
+    }
+    Ok(())
+}
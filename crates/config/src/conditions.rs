@@ -0,0 +1,192 @@
+//! Conditional source subtrees from .guisu/conditions.toml
+
+use crate::Result;
+use indexmap::IndexMap;
+use serde::Deserialize;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// Template conditions that include or exclude whole source subtrees
+///
+/// Maps directory prefixes (relative to the dotfiles directory) to template
+/// expressions. A subtree is only read from the source directory when its
+/// expression is true; nested prefixes are evaluated independently.
+///
+/// Example:
+/// ```toml
+/// ".config/karabiner" = 'os == "darwin"'
+/// "work" = 'os == "darwin" and hostname == "work-laptop"'
+/// ```
+#[derive(Debug, Default, Deserialize)]
+#[serde(transparent)]
+pub struct Conditions {
+    /// Expression for each directory prefix
+    pub entries: IndexMap<String, String>,
+}
+
+impl Conditions {
+    /// Load conditions from .guisu/conditions.toml
+    ///
+    /// # Errors
+    ///
+    /// Returns error if file cannot be read or TOML parsing fails
+    pub fn load(source_dir: &Path) -> Result<Self> {
+        let conditions_path = source_dir.join(".guisu").join("conditions.toml");
+
+        if !conditions_path.exists() {
+            return Ok(Self::default());
+        }
+
+        let content = fs::read_to_string(&conditions_path).map_err(|e| {
+            guisu_core::Error::Message(format!(
+                "Failed to read {}: {}",
+                conditions_path.display(),
+                e
+            ))
+        })?;
+
+        let conditions: Self = toml::from_str(&content).map_err(|e| {
+            guisu_core::Error::Message(format!(
+                "Failed to parse {}: {}",
+                conditions_path.display(),
+                e
+            ))
+        })?;
+
+        Ok(conditions)
+    }
+
+    /// Whether no conditions are configured
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// Evaluate every condition and return the directories to exclude
+    ///
+    /// `eval` evaluates a template expression. Prefixes are returned relative
+    /// to the dotfiles directory, without leading or trailing slashes.
+    ///
+    /// # Errors
+    ///
+    /// Returns error if a prefix is not a relative path inside the dotfiles
+    /// directory, or if `eval` fails for an expression
+    pub fn excluded_dirs<F>(&self, mut eval: F) -> Result<Vec<PathBuf>>
+    where
+        F: FnMut(&str) -> Result<bool>,
+    {
+        let mut excluded = Vec::new();
+
+        for (prefix, expression) in &self.entries {
+            let Some(dir) = dotfiles_subdir(prefix) else {
+                return Err(guisu_core::Error::Message(format!(
+                    "Invalid condition prefix '{prefix}': expected a directory inside the dotfiles directory"
+                )));
+            };
+
+            let included = eval(expression).map_err(|e| {
+                guisu_core::Error::Message(format!(
+                    "Failed to evaluate condition for '{prefix}': {e}"
+                ))
+            })?;
+            if !included {
+                excluded.push(dir);
+            }
+        }
+
+        Ok(excluded)
+    }
+}
+
+/// Parse a directory prefix relative to the dotfiles directory
+///
+/// Leading and trailing slashes are dropped. Returns `None` for an empty prefix
+/// or one that leaves the dotfiles directory.
+pub(crate) fn dotfiles_subdir(prefix: &str) -> Option<PathBuf> {
+    let dir = PathBuf::from(prefix.trim_matches('/'));
+    let is_relative = dir
+        .components()
+        .all(|c| matches!(c, std::path::Component::Normal(_)));
+    (!dir.as_os_str().is_empty() && is_relative).then_some(dir)
+}
+
+#[cfg(test)]
+mod tests {
+    #![allow(clippy::unwrap_used, clippy::panic)]
+    use super::*;
+    use tempfile::TempDir;
+
+    fn create_conditions(temp: &TempDir, content: &str) {
+        let guisu_dir = temp.path().join(".guisu");
+        fs::create_dir_all(&guisu_dir).unwrap();
+        fs::write(guisu_dir.join("conditions.toml"), content).unwrap();
+    }
+
+    #[test]
+    fn test_conditions_load_missing_file() {
+        let temp = TempDir::new().unwrap();
+
+        let conditions = Conditions::load(temp.path()).unwrap();
+        assert!(conditions.is_empty());
+    }
+
+    #[test]
+    fn test_conditions_load() {
+        let temp = TempDir::new().unwrap();
+        create_conditions(
+            &temp,
+            r#"
+"work/" = 'hostname == "work"'
+".config/karabiner" = 'os == "darwin"'
+"#,
+        );
+
+        let conditions = Conditions::load(temp.path()).unwrap();
+
+        assert_eq!(conditions.entries.len(), 2);
+        assert_eq!(conditions.entries["work/"], r#"hostname == "work""#);
+        assert_eq!(conditions.entries[".config/karabiner"], r#"os == "darwin""#);
+    }
+
+    #[test]
+    fn test_excluded_dirs() {
+        let temp = TempDir::new().unwrap();
+        create_conditions(
+            &temp,
+            r#"
+"/work/" = "false"
+".config/karabiner" = "true"
+"#,
+        );
+        let conditions = Conditions::load(temp.path()).unwrap();
+
+        let excluded = conditions.excluded_dirs(|expr| Ok(expr == "true")).unwrap();
+
+        assert_eq!(excluded, [PathBuf::from("work")]);
+    }
+
+    #[test]
+    fn test_excluded_dirs_rejects_escaping_prefix() {
+        let mut conditions = Conditions::default();
+        conditions
+            .entries
+            .insert("../outside".to_string(), "true".to_string());
+
+        let err = conditions.excluded_dirs(|_| Ok(true)).unwrap_err();
+        assert!(err.to_string().contains("Invalid condition prefix"));
+    }
+
+    #[test]
+    fn test_excluded_dirs_reports_prefix_on_error() {
+        let mut conditions = Conditions::default();
+        conditions
+            .entries
+            .insert("work".to_string(), "os ==".to_string());
+
+        let err = conditions
+            .excluded_dirs(|_| Err(guisu_core::Error::Message("syntax error".to_string())))
+            .unwrap_err();
+        assert!(err.to_string().contains("'work'"));
+        assert!(err.to_string().contains("syntax error"));
+    }
+}
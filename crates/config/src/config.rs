@@ -5,6 +5,7 @@
 use crate::Result;
 use crate::variables::load_variables;
 use guisu_core::platform::CURRENT_PLATFORM;
+use ignore::gitignore::GitignoreBuilder;
 use indexmap::IndexMap;
 use serde::{Deserialize, Serialize};
 use std::fs;
@@ -52,6 +53,30 @@ impl IconMode {
     }
 }
 
+/// Line endings of text files written to the destination
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Eol {
+    /// CRLF on Windows, LF elsewhere
+    Native,
+    /// Unix line endings
+    Lf,
+    /// Windows line endings
+    Crlf,
+}
+
+impl Eol {
+    /// Whether lines end with CRLF on this platform
+    #[must_use]
+    pub fn is_crlf(self) -> bool {
+        match self {
+            Self::Native => cfg!(windows),
+            Self::Lf => false,
+            Self::Crlf => true,
+        }
+    }
+}
+
 /// General configuration section
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct GeneralConfig {
@@ -91,6 +116,29 @@ pub struct GeneralConfig {
     /// Arguments to pass to the editor
     #[serde(default, rename = "editorArgs")]
     pub editor_args: Vec<String>,
+
+    /// Convert line endings of text files when applying (unchanged if unset)
+    #[serde(default)]
+    pub eol: Option<Eol>,
+
+    /// Profile from `.guisu/profiles.toml` to apply on this machine
+    #[serde(default)]
+    pub profile: Option<String>,
+
+    /// Binary files of at least this many bytes are stored zstd compressed
+    /// when added (never compressed automatically if unset)
+    #[serde(default, rename = "compressThreshold")]
+    pub compress_threshold: Option<u64>,
+
+    /// Source files of at least this many bytes that are not templates are
+    /// streamed when applied instead of read into memory (64 MiB by default)
+    #[serde(default = "default_stream_threshold", rename = "streamThreshold")]
+    pub stream_threshold: Option<u64>,
+
+    /// Work without network access: secrets are only taken from caches,
+    /// externals are not downloaded, and `update` and `init` do not fetch
+    #[serde(default)]
+    pub offline: bool,
 }
 
 impl Default for GeneralConfig {
@@ -105,6 +153,11 @@ impl Default for GeneralConfig {
             use_builtin_git: AutoBool::Auto,
             editor: None,
             editor_args: Vec::new(),
+            eol: None,
+            profile: None,
+            compress_threshold: None,
+            stream_threshold: default_stream_threshold(),
+            offline: false,
         }
     }
 }
@@ -158,6 +211,306 @@ impl Default for BitwardenConfig {
     }
 }
 
+/// password-store configuration
+///
+/// Configure which password-store CLI the `pass()` template function uses
+///
+/// ```toml
+/// [pass]
+/// command = "gopass"  # or "pass" (default)
+/// ```
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PassConfig {
+    /// Which password-store CLI to use: "pass" or "gopass"
+    #[serde(default = "default_pass_command")]
+    pub command: String,
+}
+
+fn default_pass_command() -> String {
+    "pass".to_string()
+}
+
+impl Default for PassConfig {
+    fn default() -> Self {
+        Self {
+            command: default_pass_command(),
+        }
+    }
+}
+
+/// Vault configuration shared by all secret providers
+///
+/// ```toml
+/// [vault]
+/// retries = 3       # retry transient failures with exponential backoff
+/// cacheTtl = 86400  # keep Bitwarden responses encrypted on disk for a day
+/// prefetchJobs = 4  # lookups run at once before rendering (0 = no prefetch)
+/// ```
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct VaultConfig {
+    /// How often to retry a failed vault call (default: 0 = no retries)
+    #[serde(default)]
+    pub retries: u32,
+
+    /// Seconds to keep Bitwarden responses in the encrypted disk cache
+    /// (default: 0 = not cached on disk)
+    #[serde(default, rename = "cacheTtl")]
+    pub cache_ttl: u64,
+
+    /// How many vault lookups found in templates run at once before
+    /// rendering (default: 4, 0 = fetched one at a time while rendering)
+    #[serde(default = "default_prefetch_jobs", rename = "prefetchJobs")]
+    pub prefetch_jobs: usize,
+}
+
+fn default_prefetch_jobs() -> usize {
+    4
+}
+
+impl Default for VaultConfig {
+    fn default() -> Self {
+        Self {
+            retries: 0,
+            cache_ttl: 0,
+            prefetch_jobs: default_prefetch_jobs(),
+        }
+    }
+}
+
+/// Template configuration
+///
+/// ```toml
+/// [template]
+/// allowExec = ["brew", "go"]  # commands `output()` may run
+/// sshKeygen = true             # let `generateSshKeyIfMissing()` write keys
+/// ```
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct TemplateConfig {
+    /// Commands the `output()` template function is allowed to run
+    #[serde(default, rename = "allowExec", alias = "allow_exec")]
+    pub allow_exec: Vec<String>,
+
+    /// Allow the `generateSshKeyIfMissing()` template function to write keys
+    ///
+    /// Keys are only written by `guisu apply` without `--dry-run`.
+    #[serde(default, rename = "sshKeygen")]
+    pub ssh_keygen: bool,
+}
+
+/// Git configuration
+///
+/// Commit (and push) changes to the source repository automatically after
+/// commands that modify it, such as `add`, `edit`, and `re-add`. The commit
+/// message is a template with `command` and `paths` variables.
+///
+/// Clone, fetch, and push use libgit2 by default. With `useSystemGit` they
+/// run the `git` binary instead, so its credential helpers, SSH config, and
+/// proxy settings apply unchanged.
+///
+/// ```toml
+/// [git]
+/// autoCommit = true
+/// autoPush = true  # implies autoCommit
+/// commitMessageTemplate = "{{ command }}: {{ paths | join(\", \") }}"
+/// useSystemGit = true
+/// ```
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct GitConfig {
+    /// Commit source changes after modifying commands
+    #[serde(default, rename = "autoCommit", alias = "auto_commit")]
+    pub auto_commit: bool,
+
+    /// Push after each automatic commit
+    #[serde(default, rename = "autoPush", alias = "auto_push")]
+    pub auto_push: bool,
+
+    /// Template for automatic commit messages
+    #[serde(
+        default = "default_commit_message_template",
+        rename = "commitMessageTemplate",
+        alias = "commit_message_template"
+    )]
+    pub commit_message_template: String,
+
+    /// Run the `git` binary for clone, fetch, and push instead of libgit2
+    #[serde(default, rename = "useSystemGit", alias = "use_system_git")]
+    pub use_system_git: bool,
+}
+
+fn default_commit_message_template() -> String {
+    "Update {{ paths | join(\", \") }}".to_string()
+}
+
+impl Default for GitConfig {
+    fn default() -> Self {
+        Self {
+            auto_commit: false,
+            auto_push: false,
+            commit_message_template: default_commit_message_template(),
+            use_system_git: false,
+        }
+    }
+}
+
+/// How apply writes plain files to the destination
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum ApplyMode {
+    /// Copy file contents to the destination
+    #[default]
+    Copy,
+    /// Symlink the destination to the file in the source directory
+    ///
+    /// Templates, encrypted files, and files with inline age values are
+    /// still copied, as their contents differ from the source.
+    Symlink,
+}
+
+/// What apply syncs to disk when writing a file
+///
+/// Files are always written to a temporary file and renamed over the
+/// destination, so an interrupted apply never leaves a truncated file; the
+/// policy decides whether that also holds after a power loss.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum FsyncPolicy {
+    /// Sync the file before renaming it into place
+    #[default]
+    File,
+    /// Also sync the directory after the rename, so the rename itself persists
+    Dir,
+    /// Leave syncing to the operating system
+    None,
+}
+
+/// How the targets of managed symlinks are written
+///
+/// `guisu add` stores link targets in this form, and apply creates links
+/// with it, so a repository can be shared across machines whose home
+/// directories differ.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum SymlinkTargets {
+    /// Keep targets as they are
+    #[default]
+    Keep,
+    /// Make absolute targets relative to the directory of the link
+    Relative,
+    /// Make relative targets absolute
+    Absolute,
+}
+
+/// Apply configuration
+///
+/// ```toml
+/// [apply]
+/// mode = "symlink"      # or "copy" (default)
+/// incremental = false   # always render templates (default: true)
+/// preserveXattrs = true # keep extended attributes and file flags (default: false)
+/// backup = true         # copy modified files to `<name>.bak` before overwriting them
+/// fsync = "dir"         # or "file" (default), "none"
+/// symlinkTargets = "relative" # or "absolute", "keep" (default)
+/// ```
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ApplyConfig {
+    /// How plain files are written to the destination
+    #[serde(default)]
+    pub mode: ApplyMode,
+
+    /// Skip rendering templates and decrypting files whose inputs are unchanged
+    #[serde(default = "default_incremental")]
+    pub incremental: bool,
+
+    /// Keep the extended attributes and file flags of rewritten files
+    ///
+    /// The `com.apple.quarantine` attribute is removed instead of kept.
+    #[serde(default, rename = "preserveXattrs", alias = "preserve_xattrs")]
+    pub preserve_xattrs: bool,
+
+    /// Number of apply snapshots to keep for `guisu rollback` (0 disables them)
+    ///
+    /// Each apply first saves the destination files it is about to change.
+    #[serde(default)]
+    pub snapshots: usize,
+
+    /// Copy locally modified destination files aside before overwriting them
+    #[serde(default)]
+    pub backup: bool,
+
+    /// Suffix of backups written next to the file
+    #[serde(
+        default = "default_backup_suffix",
+        rename = "backupSuffix",
+        alias = "backup_suffix"
+    )]
+    pub backup_suffix: String,
+
+    /// Store backups under `<backupDir>/<timestamp>/` instead of next to the file
+    #[serde(default, rename = "backupDir", alias = "backup_dir")]
+    pub backup_dir: Option<PathBuf>,
+
+    /// What to sync to disk when writing files
+    #[serde(default)]
+    pub fsync: FsyncPolicy,
+
+    /// Whether symlinks point to relative or absolute paths
+    #[serde(default, rename = "symlinkTargets", alias = "symlink_targets")]
+    pub symlink_targets: SymlinkTargets,
+}
+
+impl Default for ApplyConfig {
+    fn default() -> Self {
+        Self {
+            mode: ApplyMode::default(),
+            incremental: default_incremental(),
+            preserve_xattrs: false,
+            snapshots: 0,
+            backup: false,
+            backup_suffix: default_backup_suffix(),
+            backup_dir: None,
+            fsync: FsyncPolicy::default(),
+            symlink_targets: SymlinkTargets::default(),
+        }
+    }
+}
+
+/// What to do when a plaintext secret is found
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum SecretAction {
+    /// Do not check for secrets
+    Ignore,
+    /// Warn and continue
+    #[default]
+    Warning,
+    /// Stop with an error
+    Error,
+}
+
+/// Security configuration
+///
+/// ```toml
+/// [security]
+/// add = "error"      # `guisu add` of an unencrypted file containing a secret
+/// apply = "warning"  # apply writing a secret to a world-readable file
+/// allowlist = ["EXAMPLE", "^\\.config/test/"]
+/// ```
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct SecurityConfig {
+    /// Action when a file added to the source contains a secret
+    #[serde(default)]
+    pub add: SecretAction,
+
+    /// Action when apply would write a secret to a world-readable file
+    #[serde(default)]
+    pub apply: SecretAction,
+
+    /// Regular expressions for findings to ignore, matched against the
+    /// detected text and the file path
+    #[serde(default)]
+    pub allowlist: Vec<String>,
+}
+
 /// UI configuration section
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct UiConfig {
@@ -224,11 +577,13 @@ impl Default for UiConfig {
 /// identity = "~/.config/guisu/key.txt"
 /// symmetric = true
 ///
-/// # SSH key support
+/// # SSH key support (ssh-ed25519 and ssh-rsa)
 /// [age]
 /// identity = "~/.ssh/id_ed25519"
+/// recipients = ["ssh-ed25519 AAAAC3NzaC1lZDI1NTE5... me@laptop"]
 /// ```
-#[derive(Debug, Clone, Serialize, Deserialize, Default)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[allow(clippy::struct_excessive_bools)]
 pub struct AgeConfig {
     /// Single identity file path (age or SSH key)
     /// Can use ~ for home directory
@@ -239,11 +594,11 @@ pub struct AgeConfig {
     /// Mutually exclusive with `identity`
     pub identities: Option<Vec<PathBuf>>,
 
-    /// Single recipient public key
+    /// Single recipient public key (`age1...` or an SSH public key)
     /// Mutually exclusive with `recipients`
     pub recipient: Option<String>,
 
-    /// Multiple recipient public keys
+    /// Multiple recipient public keys (`age1...` or SSH public keys)
     #[serde(default)]
     pub recipients: Vec<String>,
 
@@ -284,6 +639,118 @@ pub struct AgeConfig {
         rename = "failOnDecryptError"
     )]
     pub fail_on_decrypt_error: bool,
+
+    /// Per-path recipient scopes
+    ///
+    /// Maps gitignore-style globs (matched against target paths relative to the
+    /// destination directory) to the recipients that entries under them are
+    /// encrypted to. The first matching scope in declaration order wins; entries
+    /// outside every scope use the global `recipient`/`recipients`.
+    ///
+    /// ```toml
+    /// [age.scopes]
+    /// "work/**" = ["age1work..."]
+    /// ".ssh/id_work*" = ["age1work...", "age1laptop..."]
+    /// ```
+    #[serde(default)]
+    pub scopes: IndexMap<String, Vec<String>>,
+
+    /// Remember identity file passphrases in the system keyring
+    ///
+    /// When true, the passphrase of a passphrase-protected identity is stored in
+    /// the OS keychain after it first unlocks, so later runs do not prompt.
+    #[serde(default)]
+    pub keyring: bool,
+
+    /// Store encrypted files in ASCII armor format
+    ///
+    /// When true (default), `.age` files are written as text
+    /// (`-----BEGIN AGE ENCRYPTED FILE-----`), which git diffs and review
+    /// tools handle. When false they are written in age's smaller binary
+    /// format. Either format is read regardless of this setting, and
+    /// `guisu age convert` rewrites existing files to match it.
+    #[serde(default = "default_armor")]
+    pub armor: bool,
+}
+
+impl Default for AgeConfig {
+    fn default() -> Self {
+        Self {
+            identity: None,
+            identities: None,
+            recipient: None,
+            recipients: Vec::new(),
+            derive: false,
+            fail_on_decrypt_error: default_fail_on_decrypt_error(),
+            scopes: IndexMap::new(),
+            keyring: false,
+            armor: default_armor(),
+        }
+    }
+}
+
+/// Configuration file format, detected from the file extension
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ConfigFormat {
+    /// `.guisu.toml`
+    Toml,
+    /// `.guisu.json`
+    Json,
+    /// `.guisu.yaml` / `.guisu.yml` (recognized, but not parsed by this build)
+    Yaml,
+}
+
+impl ConfigFormat {
+    /// Config file names in lookup order, each with its format
+    ///
+    /// Templated variants are the same names with a `.j2` suffix.
+    pub const FILE_NAMES: [(&'static str, Self); 4] = [
+        (".guisu.toml", Self::Toml),
+        (".guisu.json", Self::Json),
+        (".guisu.yaml", Self::Yaml),
+        (".guisu.yml", Self::Yaml),
+    ];
+
+    /// Detect the format of a config file from its extension
+    ///
+    /// A trailing `.j2` is ignored, so `.guisu.json.j2` is JSON.
+    #[must_use]
+    pub fn from_path(path: &Path) -> Option<Self> {
+        let name = path.file_name()?.to_str()?;
+        let name = name.strip_suffix(".j2").unwrap_or(name);
+        match Path::new(name).extension()?.to_str()? {
+            "toml" => Some(Self::Toml),
+            "json" => Some(Self::Json),
+            "yaml" | "yml" => Some(Self::Yaml),
+            _ => None,
+        }
+    }
+
+    /// Find the config file in `source_dir`
+    ///
+    /// Formats are tried in [`Self::FILE_NAMES`] order, a plain file before its
+    /// `.j2` template. Returns the path, its format, and whether it is a template.
+    #[must_use]
+    pub fn find(source_dir: &Path) -> Option<(PathBuf, Self, bool)> {
+        Self::FILE_NAMES.iter().find_map(|&(name, format)| {
+            let path = source_dir.join(name);
+            if path.exists() {
+                return Some((path, format, false));
+            }
+            let template = source_dir.join(format!("{name}.j2"));
+            template.exists().then_some((template, format, true))
+        })
+    }
+
+    /// Human-readable format name
+    #[must_use]
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Toml => "TOML",
+            Self::Json => "JSON",
+            Self::Yaml => "YAML",
+        }
+    }
 }
 
 /// Guisu configuration
@@ -301,6 +768,30 @@ pub struct Config {
     #[serde(default)]
     pub bitwarden: BitwardenConfig,
 
+    /// password-store configuration
+    #[serde(default)]
+    pub pass: PassConfig,
+
+    /// Vault configuration
+    #[serde(default)]
+    pub vault: VaultConfig,
+
+    /// Apply configuration
+    #[serde(default)]
+    pub apply: ApplyConfig,
+
+    /// Template configuration
+    #[serde(default)]
+    pub template: TemplateConfig,
+
+    /// Git configuration
+    #[serde(default)]
+    pub git: GitConfig,
+
+    /// Security configuration
+    #[serde(default)]
+    pub security: SecurityConfig,
+
     /// UI configuration
     #[serde(default)]
     pub ui: UiConfig,
@@ -327,22 +818,86 @@ fn default_progress() -> bool {
     true
 }
 
+#[allow(clippy::unnecessary_wraps)]
+fn default_stream_threshold() -> Option<u64> {
+    Some(64 * 1024 * 1024)
+}
+
 fn default_root_entry() -> PathBuf {
     PathBuf::from("home")
 }
 
+fn default_incremental() -> bool {
+    true
+}
+
+fn default_backup_suffix() -> String {
+    ".bak".to_string()
+}
+
 fn default_fail_on_decrypt_error() -> bool {
     true // Default to failing loudly for security (matches chezmoi)
 }
 
+fn default_armor() -> bool {
+    true
+}
+
+impl AgeConfig {
+    /// Check that every configured recipient parses
+    ///
+    /// Recipients are age public keys (`age1...`) or SSH public keys
+    /// (`ssh-ed25519 ...`, `ssh-rsa ...`). Checks the global recipients and
+    /// every `[age.scopes]` entry, so a typo in one scope is reported before
+    /// anything is encrypted.
+    ///
+    /// # Errors
+    ///
+    /// Returns error naming the first recipient that fails to parse, or a scope
+    /// without recipients
+    pub fn validate(&self) -> Result<()> {
+        for recipient in self.recipient.iter().chain(&self.recipients) {
+            parse_recipient(recipient, None)?;
+        }
+        for (pattern, recipients) in &self.scopes {
+            if recipients.is_empty() {
+                return Err(guisu_core::Error::Message(format!(
+                    "Age scope '{pattern}' has no recipients"
+                )));
+            }
+            for recipient in recipients {
+                parse_recipient(recipient, Some(pattern))?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Parse a configured recipient, naming the scope it belongs to in errors
+fn parse_recipient(recipient: &str, scope: Option<&str>) -> Result<guisu_crypto::Recipient> {
+    recipient
+        .trim()
+        .parse::<guisu_crypto::Recipient>()
+        .map_err(|e| {
+            guisu_core::Error::Message(match scope {
+                Some(pattern) => {
+                    format!("Failed to parse recipient '{recipient}' in age scope '{pattern}': {e}")
+                }
+                None => format!("Failed to parse recipient '{recipient}': {e}"),
+            })
+        })
+}
+
 impl Config {
     /// Load configuration from a file
     ///
     /// This is primarily used for testing. In production, use `load_from_source()` instead.
     ///
+    /// The format is detected from the file extension, defaulting to TOML.
+    ///
     /// # Errors
     ///
-    /// Returns error if file cannot be read or TOML parsing fails
+    /// Returns error if file cannot be read or parsing fails
     pub fn load<P: AsRef<Path>>(path: P) -> Result<Self> {
         let content = fs::read_to_string(path.as_ref()).map_err(|e| {
             guisu_core::Error::Message(format!(
@@ -351,7 +906,8 @@ impl Config {
             ))
         })?;
 
-        let mut config: Self = toml::from_str(&content).map_err(|e| {
+        let format = ConfigFormat::from_path(path.as_ref()).unwrap_or(ConfigFormat::Toml);
+        let mut config = Self::parse(&content, format).map_err(|e| {
             guisu_core::Error::Message(format!(
                 "Failed to parse config file {}: {e}",
                 path.as_ref().display()
@@ -377,8 +933,24 @@ impl Config {
     ///
     /// Returns error if TOML parsing fails
     pub fn from_toml_str(toml_content: &str, source_dir: &Path) -> Result<Self> {
-        let mut config: Self = toml::from_str(toml_content)
-            .map_err(|e| guisu_core::Error::Message(format!("Failed to parse config TOML: {e}")))?;
+        Self::from_str_with_format(toml_content, ConfigFormat::Toml, source_dir)
+    }
+
+    /// Load configuration from a string in the given format
+    ///
+    /// Like [`Self::from_toml_str`], for any supported [`ConfigFormat`].
+    ///
+    /// # Errors
+    ///
+    /// Returns error if parsing fails or the format is not supported
+    pub fn from_str_with_format(
+        content: &str,
+        format: ConfigFormat,
+        source_dir: &Path,
+    ) -> Result<Self> {
+        let mut config = Self::parse(content, format).map_err(|e| {
+            guisu_core::Error::Message(format!("Failed to parse config {}: {e}", format.name()))
+        })?;
 
         // Store the source directory for relative path resolution
         config.resolve_relative_paths(source_dir);
@@ -386,9 +958,21 @@ impl Config {
         Ok(config)
     }
 
-    /// Load configuration from source directory (.guisu.toml)
+    /// Deserialize configuration content, without resolving paths
+    fn parse(content: &str, format: ConfigFormat) -> std::result::Result<Self, String> {
+        match format {
+            ConfigFormat::Toml => toml::from_str(content).map_err(|e| e.to_string()),
+            ConfigFormat::Json => serde_json::from_str(content).map_err(|e| e.to_string()),
+            ConfigFormat::Yaml => Err(
+                "YAML configuration is not supported, use .guisu.toml or .guisu.json".to_string(),
+            ),
+        }
+    }
+
+    /// Load configuration from source directory (.guisu.toml or .guisu.json)
     ///
-    /// This method looks for .guisu.toml in the source directory and parses it directly.
+    /// This method looks for the config file in the source directory and parses it
+    /// directly. See [`ConfigFormat::find`] for the lookup order.
     ///
     /// Note: For template support (.guisu.toml.j2), use the CLI wrapper which handles
     /// template rendering before calling this method.
@@ -399,25 +983,27 @@ impl Config {
     ///
     /// Returns error if config file is missing or cannot be read/parsed
     pub fn load_from_source(source_dir: &Path) -> Result<Self> {
-        let config_path = source_dir.join(".guisu.toml");
-        let template_path = source_dir.join(".guisu.toml.j2");
-
-        // Check if .guisu.toml exists
-        if !config_path.exists() {
-            // If .guisu.toml.j2 exists, provide helpful error
-            if template_path.exists() {
-                return Err(guisu_core::Error::Message(
-                    "Found .guisu.toml.j2 template but .guisu.toml is missing.\n\
+        let found = ConfigFormat::find(source_dir);
+
+        // Check if a plain config file exists
+        let Some((config_path, format, false)) = found else {
+            // If only a template exists, provide helpful error
+            if let Some((template_path, _, true)) = found {
+                return Err(guisu_core::Error::Message(format!(
+                    "Found {} template but no plain config file.\n\
                      \n\
                      Template rendering should be handled by CLI layer.\n\
-                     This is likely a bug - please use Config::load_with_variables() instead."
-                        .to_string(),
-                ));
+                     This is likely a bug - please use Config::load_with_variables() instead.",
+                    template_path
+                        .file_name()
+                        .unwrap_or_default()
+                        .to_string_lossy()
+                )));
             }
 
             return Err(guisu_core::Error::Message(format!(
                 "Configuration file not found in source directory.\n\
-                 Expected: .guisu.toml in {}\n\
+                 Expected: .guisu.toml or .guisu.json in {}\n\
                  \n\
                  Create one with:\n\
                  cat > .guisu.toml << 'EOF'\n\
@@ -430,9 +1016,9 @@ impl Config {
                  EOF",
                 source_dir.display()
             )));
-        }
+        };
 
-        // Read and parse TOML config
+        // Read and parse config
         let content = fs::read_to_string(&config_path).map_err(|e| {
             guisu_core::Error::Message(format!(
                 "Failed to read config file {}: {e}",
@@ -440,7 +1026,7 @@ impl Config {
             ))
         })?;
 
-        Self::from_toml_str(&content, source_dir)
+        Self::from_str_with_format(&content, format, source_dir)
     }
 
     /// Resolve relative paths in configuration
@@ -472,6 +1058,10 @@ impl Config {
                     .collect(),
             );
         }
+
+        if let Some(ref backup_dir) = self.apply.backup_dir {
+            self.apply.backup_dir = Some(Self::resolve_path(backup_dir, base_dir));
+        }
     }
 
     /// Resolve a single path: expand ~/ and resolve relative paths
@@ -689,20 +1279,67 @@ impl Config {
             return Ok(Vec::new());
         }
 
-        // Parse all recipient strings
-        let mut recipients = Vec::new();
-        for recipient_str in recipient_strings {
-            let recipient = recipient_str
-                .parse::<guisu_crypto::Recipient>()
-                .map_err(|e| {
-                    guisu_core::Error::Message(format!(
-                        "Failed to parse recipient '{recipient_str}': {e}"
-                    ))
-                })?;
-            recipients.push(recipient);
+        recipient_strings
+            .iter()
+            .map(|recipient| parse_recipient(recipient, None))
+            .collect()
+    }
+
+    /// Find the age scope pattern that applies to a target path
+    ///
+    /// `target_path` is relative to the destination directory. Scopes are checked
+    /// in declaration order and the first matching pattern is returned.
+    ///
+    /// # Errors
+    ///
+    /// Returns error if a scope pattern is not a valid glob
+    pub fn age_scope_for(&self, target_path: &Path) -> Result<Option<&str>> {
+        for pattern in self.age.scopes.keys() {
+            let mut builder = GitignoreBuilder::new("");
+            builder.add_line(None, pattern).map_err(|e| {
+                guisu_core::Error::Message(format!("Invalid age scope pattern '{pattern}': {e}"))
+            })?;
+            let matcher = builder.build().map_err(|e| {
+                guisu_core::Error::Message(format!("Invalid age scope pattern '{pattern}': {e}"))
+            })?;
+
+            if matcher
+                .matched_path_or_any_parents(target_path, false)
+                .is_ignore()
+            {
+                return Ok(Some(pattern));
+            }
+        }
+
+        Ok(None)
+    }
+
+    /// Get the recipients an entry should be encrypted to
+    ///
+    /// Returns the recipients of the first `[age.scopes]` pattern matching
+    /// `target_path`, falling back to [`Config::age_recipients`] when no scope
+    /// applies.
+    ///
+    /// # Errors
+    ///
+    /// Returns error if a scope pattern is invalid, a scope has no recipients,
+    /// or recipient parsing fails
+    pub fn age_recipients_for(&self, target_path: &Path) -> Result<Vec<guisu_crypto::Recipient>> {
+        let Some(pattern) = self.age_scope_for(target_path)? else {
+            return self.age_recipients();
+        };
+
+        let recipient_strings = &self.age.scopes[pattern];
+        if recipient_strings.is_empty() {
+            return Err(guisu_core::Error::Message(format!(
+                "Age scope '{pattern}' has no recipients"
+            )));
         }
 
-        Ok(recipients)
+        recipient_strings
+            .iter()
+            .map(|recipient| parse_recipient(recipient, Some(pattern)))
+            .collect()
     }
 
     /// Load all age identities from configuration
@@ -951,6 +1588,8 @@ mod tests {
         assert!(config.dst_dir.is_none());
         assert!(config.editor.is_none());
         assert!(config.editor_args.is_empty());
+        assert!(config.eol.is_none());
+        assert!(!config.offline);
     }
 
     #[test]
@@ -976,6 +1615,7 @@ mod tests {
         assert!(config.recipient.is_none());
         assert!(config.recipients.is_empty());
         assert!(!config.derive);
+        assert!(config.armor);
     }
 
     #[test]
@@ -1004,6 +1644,8 @@ mod tests {
 rootEntry = "dotfiles"
 color = false
 progress = false
+eol = "crlf"
+offline = true
 "#;
         let (_temp_dir, config_path) = create_test_config(toml);
         let config = Config::load(&config_path).unwrap();
@@ -1011,6 +1653,15 @@ progress = false
         assert_eq!(config.general.root_entry, PathBuf::from("dotfiles"));
         assert!(!config.general.color);
         assert!(!config.general.progress);
+        assert_eq!(config.general.eol, Some(Eol::Crlf));
+        assert!(config.general.offline);
+    }
+
+    #[test]
+    fn test_eol_is_crlf() {
+        assert!(Eol::Crlf.is_crlf());
+        assert!(!Eol::Lf.is_crlf());
+        assert_eq!(Eol::Native.is_crlf(), cfg!(windows));
     }
 
     #[test]
@@ -1089,6 +1740,142 @@ provider = "rbw"
         let config = Config::load(&config_path).unwrap();
 
         assert_eq!(config.bitwarden.provider, "rbw");
+        assert_eq!(config.pass.command, "pass");
+    }
+
+    #[test]
+    fn test_load_config_with_pass_section() {
+        let toml = r#"
+[pass]
+command = "gopass"
+"#;
+        let (_temp_dir, config_path) = create_test_config(toml);
+        let config = Config::load(&config_path).unwrap();
+
+        assert_eq!(config.pass.command, "gopass");
+    }
+
+    #[test]
+    fn test_load_config_with_vault_section() {
+        let (_temp_dir, config_path) =
+            create_test_config("[vault]\nretries = 3\ncacheTtl = 3600\nprefetchJobs = 8\n");
+        let config = Config::load(&config_path).unwrap();
+
+        assert_eq!(config.vault.retries, 3);
+        assert_eq!(config.vault.cache_ttl, 3600);
+        assert_eq!(config.vault.prefetch_jobs, 8);
+        assert_eq!(Config::default().vault.retries, 0);
+        assert_eq!(Config::default().vault.cache_ttl, 0);
+        assert_eq!(Config::default().vault.prefetch_jobs, 4);
+
+        let (_temp_dir, config_path) = create_test_config("[vault]\nretries = 1\n");
+        assert_eq!(Config::load(&config_path).unwrap().vault.prefetch_jobs, 4);
+    }
+
+    #[test]
+    fn test_load_config_with_apply_section() {
+        let toml = r#"
+[apply]
+mode = "symlink"
+"#;
+        let (_temp_dir, config_path) = create_test_config(toml);
+        let config = Config::load(&config_path).unwrap();
+
+        assert_eq!(config.apply.mode, ApplyMode::Symlink);
+        assert!(config.apply.incremental);
+        assert_eq!(Config::default().apply.mode, ApplyMode::Copy);
+        assert!(Config::default().apply.incremental);
+
+        let (_temp_dir, config_path) = create_test_config("[apply]\nincremental = false\n");
+        assert!(!Config::load(&config_path).unwrap().apply.incremental);
+        assert!(!Config::default().apply.preserve_xattrs);
+
+        let (_temp_dir, config_path) = create_test_config("[apply]\npreserve_xattrs = true\n");
+        assert!(Config::load(&config_path).unwrap().apply.preserve_xattrs);
+
+        let (temp_dir, config_path) =
+            create_test_config("[apply]\nbackup = true\nbackupDir = \"./backups\"\n");
+        let config = Config::load(&config_path).unwrap();
+        assert!(config.apply.backup);
+        assert_eq!(config.apply.backup_suffix, ".bak");
+        assert_eq!(
+            config.apply.backup_dir,
+            Some(temp_dir.path().join("./backups"))
+        );
+
+        assert_eq!(Config::default().apply.fsync, FsyncPolicy::File);
+        let (_temp_dir, config_path) = create_test_config("[apply]\nfsync = \"dir\"\n");
+        assert_eq!(
+            Config::load(&config_path).unwrap().apply.fsync,
+            FsyncPolicy::Dir
+        );
+
+        assert_eq!(
+            Config::default().apply.symlink_targets,
+            SymlinkTargets::Keep
+        );
+        let (_temp_dir, config_path) =
+            create_test_config("[apply]\nsymlinkTargets = \"relative\"\n");
+        assert_eq!(
+            Config::load(&config_path).unwrap().apply.symlink_targets,
+            SymlinkTargets::Relative
+        );
+    }
+
+    #[test]
+    fn test_load_config_with_template_section() {
+        let (_temp_dir, config_path) =
+            create_test_config("[template]\nallow_exec = [\"brew\", \"go\"]\nsshKeygen = true\n");
+        let config = Config::load(&config_path).unwrap();
+
+        assert_eq!(config.template.allow_exec, ["brew", "go"]);
+        assert!(config.template.ssh_keygen);
+        assert_eq!(Config::default().template.allow_exec, Vec::<String>::new());
+        assert!(!Config::default().template.ssh_keygen);
+    }
+
+    #[test]
+    fn test_load_config_with_git_section() {
+        let toml = r#"
+[git]
+auto_commit = true
+autoPush = true
+commitMessageTemplate = "{{ command }}"
+use_system_git = true
+"#;
+        let (_temp_dir, config_path) = create_test_config(toml);
+        let config = Config::load(&config_path).unwrap();
+
+        assert!(config.git.auto_commit);
+        assert!(config.git.auto_push);
+        assert_eq!(config.git.commit_message_template, "{{ command }}");
+        assert!(config.git.use_system_git);
+
+        let default = Config::default();
+        assert!(!default.git.auto_commit && !default.git.auto_push);
+        assert!(!default.git.use_system_git);
+        assert!(default.git.commit_message_template.contains("paths"));
+    }
+
+    #[test]
+    fn test_load_config_with_security_section() {
+        let toml = r#"
+[security]
+add = "error"
+apply = "ignore"
+allowlist = ["EXAMPLE"]
+"#;
+        let (_temp_dir, config_path) = create_test_config(toml);
+        let config = Config::load(&config_path).unwrap();
+
+        assert_eq!(config.security.add, SecretAction::Error);
+        assert_eq!(config.security.apply, SecretAction::Ignore);
+        assert_eq!(config.security.allowlist, ["EXAMPLE"]);
+
+        let default = Config::default();
+        assert_eq!(default.security.add, SecretAction::Warning);
+        assert_eq!(default.security.apply, SecretAction::Warning);
+        assert_eq!(default.security.allowlist, Vec::<String>::new());
     }
 
     #[test]
@@ -1395,6 +2182,77 @@ identity = "./key.txt"
         assert!(!config.general.color);
     }
 
+    #[test]
+    fn test_load_from_source_json() {
+        let temp_dir = TempDir::new().unwrap();
+        fs::write(
+            temp_dir.path().join(".guisu.json"),
+            r#"{"general": {"color": false}, "age": {"failOnDecryptError": false}}"#,
+        )
+        .unwrap();
+
+        let config = Config::load_from_source(temp_dir.path()).unwrap();
+        assert!(!config.general.color);
+        assert!(!config.age.fail_on_decrypt_error);
+    }
+
+    #[test]
+    fn test_load_from_source_prefers_toml() {
+        let temp_dir = TempDir::new().unwrap();
+        fs::write(
+            temp_dir.path().join(".guisu.toml"),
+            "[general]\ncolor = false",
+        )
+        .unwrap();
+        fs::write(
+            temp_dir.path().join(".guisu.json"),
+            r#"{"general": {"color": true}}"#,
+        )
+        .unwrap();
+
+        let config = Config::load_from_source(temp_dir.path()).unwrap();
+        assert!(!config.general.color);
+    }
+
+    #[test]
+    fn test_load_from_source_yaml_unsupported() {
+        let temp_dir = TempDir::new().unwrap();
+        fs::write(
+            temp_dir.path().join(".guisu.yaml"),
+            "general:\n  color: false\n",
+        )
+        .unwrap();
+
+        let err = Config::load_from_source(temp_dir.path()).unwrap_err();
+        assert!(
+            err.to_string()
+                .contains("YAML configuration is not supported")
+        );
+    }
+
+    #[test]
+    fn test_config_format_detection() {
+        let detect = |name: &str| ConfigFormat::from_path(Path::new(name));
+
+        assert_eq!(detect(".guisu.toml"), Some(ConfigFormat::Toml));
+        assert_eq!(detect(".guisu.json.j2"), Some(ConfigFormat::Json));
+        assert_eq!(detect(".guisu.yml"), Some(ConfigFormat::Yaml));
+        assert_eq!(detect(".guisu.ini"), None);
+    }
+
+    #[test]
+    fn test_config_format_find_template() {
+        let temp_dir = TempDir::new().unwrap();
+        assert!(ConfigFormat::find(temp_dir.path()).is_none());
+
+        fs::write(temp_dir.path().join(".guisu.json.j2"), "{}").unwrap();
+        let (path, format, template) = ConfigFormat::find(temp_dir.path()).unwrap();
+
+        assert_eq!(path, temp_dir.path().join(".guisu.json.j2"));
+        assert_eq!(format, ConfigFormat::Json);
+        assert!(template);
+    }
+
     #[test]
     fn test_source_dir_and_dest_dir_accessors() {
         let mut config = Config::default();
@@ -1454,6 +2312,100 @@ identity = "./key.txt"
         assert!(result.is_empty());
     }
 
+    #[test]
+    fn test_age_scope_for_matches_first_declared_pattern() {
+        let work = guisu_crypto::Identity::generate().to_public().to_string();
+        let mut config = Config::default();
+        config
+            .age
+            .scopes
+            .insert("work/**".to_string(), vec![work.clone()]);
+        config
+            .age
+            .scopes
+            .insert("work/notes.txt".to_string(), vec![work]);
+
+        assert_eq!(
+            config.age_scope_for(Path::new("work/notes.txt")).unwrap(),
+            Some("work/**")
+        );
+        assert_eq!(config.age_scope_for(Path::new(".bashrc")).unwrap(), None);
+    }
+
+    #[test]
+    fn test_age_recipients_for_scoped_and_global() {
+        let global = guisu_crypto::Identity::generate().to_public().to_string();
+        let work = guisu_crypto::Identity::generate().to_public().to_string();
+        let mut config = Config::default();
+        config.age.recipient = Some(global.clone());
+        config
+            .age
+            .scopes
+            .insert(".config/work".to_string(), vec![work.clone()]);
+
+        let scoped = config
+            .age_recipients_for(Path::new(".config/work/token"))
+            .unwrap();
+        assert_eq!(scoped.len(), 1);
+        assert_eq!(scoped[0].to_string(), work);
+
+        let unscoped = config.age_recipients_for(Path::new(".bashrc")).unwrap();
+        assert_eq!(unscoped.len(), 1);
+        assert_eq!(unscoped[0].to_string(), global);
+    }
+
+    #[test]
+    fn test_age_recipients_for_empty_scope() {
+        let mut config = Config::default();
+        config.age.scopes.insert("work/**".to_string(), Vec::new());
+
+        let Err(err) = config.age_recipients_for(Path::new("work/file")) else {
+            panic!("empty scope should be rejected");
+        };
+        assert!(err.to_string().contains("has no recipients"));
+    }
+
+    #[test]
+    fn test_age_validate_ssh_recipients() {
+        let mut config = Config::default();
+        config.age.recipients = vec![
+            "ssh-ed25519 AAAAC3NzaC1lZDI1NTE5AAAAILjytih1LJ1uASD3CzhaZM3G2XE3qmJFzrYQCzGYjsQO me@host"
+                .to_string(),
+        ];
+        config.age.validate().unwrap();
+        assert_eq!(config.age_recipients().unwrap().len(), 1);
+
+        config.age.scopes.insert(
+            "work/**".to_string(),
+            vec!["ecdsa-sha2-nistp256 AAAAE2VjZHNhLXNoYTItbmlzdHAyNTYAAAAIbmlzdHAyNTYAAABBBDTksAo6NFEr/Xb4o6l1+BXp+g/efotflUpBXKHZBRzvDUKwe3unnsKzQkbR6CrABTMJu0y1xlZH5RqxvJRNODU=".to_string()],
+        );
+        let Err(err) = config.age.validate() else {
+            panic!("ecdsa recipient should be rejected");
+        };
+        let err = err.to_string();
+        assert!(err.contains("age scope 'work/**'"), "{err}");
+        assert!(err.contains("Unsupported SSH key type"), "{err}");
+    }
+
+    #[test]
+    fn test_age_scopes_from_toml() {
+        let work = guisu_crypto::Identity::generate().to_public().to_string();
+        let (_temp_dir, config_path) =
+            create_test_config(&format!("[age.scopes]\n\"work/**\" = [\"{work}\"]\n"));
+        let config = Config::load(&config_path).unwrap();
+
+        assert_eq!(config.age.scopes.get("work/**"), Some(&vec![work]));
+    }
+
+    #[test]
+    fn test_age_keyring_from_toml() {
+        let (_temp_dir, config_path) = create_test_config("[age]\nkeyring = true\n");
+        let config = Config::load(&config_path).unwrap();
+        assert!(config.age.keyring);
+
+        assert!(!Config::default().age.keyring);
+    }
+
     #[test]
     fn test_age_identities_none_configured() {
         let config = Config::default();
@@ -0,0 +1,170 @@
+//! Template data loading from .guisu/data/ directory
+//!
+//! Each file in `.guisu/data/` is exposed to templates under its file stem:
+//! `.guisu/data/hosts.toml` becomes `{{ hosts.* }}`. Files sharing a stem
+//! (e.g. `hosts.toml` and `hosts.json`) are deep-merged in file name order.
+
+use crate::Result;
+use crate::variables::merge_variables;
+use indexmap::IndexMap;
+use serde_json::Value as JsonValue;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// Load template data from .guisu/data/
+///
+/// Supported formats are TOML (`.toml`) and JSON (`.json`); other files are
+/// ignored. Files are read in file name order, so later files win on
+/// conflicting keys.
+///
+/// # Errors
+///
+/// Returns error if a data file cannot be read or parsed, or is a YAML file
+pub fn load_data(guisu_dir: &Path) -> Result<IndexMap<String, JsonValue>> {
+    let mut data = IndexMap::new();
+
+    for path in data_file_paths(guisu_dir) {
+        let Some(stem) = path.file_stem().and_then(|s| s.to_str()) else {
+            continue;
+        };
+        let Some(value) = load_data_file(&path)? else {
+            continue;
+        };
+        merge_variables(&mut data, IndexMap::from([(stem.to_string(), value)]));
+    }
+
+    Ok(data)
+}
+
+/// Files in .guisu/data/, in the order they are loaded
+pub(crate) fn data_file_paths(guisu_dir: &Path) -> Vec<PathBuf> {
+    let Ok(entries) = fs::read_dir(guisu_dir.join("data")) else {
+        return Vec::new();
+    };
+
+    let mut paths: Vec<PathBuf> = entries
+        .flatten()
+        .map(|e| e.path())
+        .filter(|p| p.is_file())
+        .collect();
+    paths.sort();
+    paths
+}
+
+/// Parse a single data file, returning `None` for unsupported extensions
+pub(crate) fn load_data_file(path: &Path) -> Result<Option<JsonValue>> {
+    let extension = path.extension().and_then(|s| s.to_str());
+    if matches!(extension, Some("yaml" | "yml")) {
+        return Err(guisu_core::Error::Message(format!(
+            "YAML data files are not supported, convert {} to TOML or JSON",
+            path.display()
+        )));
+    }
+    if !matches!(extension, Some("toml" | "json")) {
+        return Ok(None);
+    }
+
+    let content = fs::read_to_string(path).map_err(|e| {
+        guisu_core::Error::Message(format!("Failed to read {}: {}", path.display(), e))
+    })?;
+
+    let value = if extension == Some("toml") {
+        let value: toml::Value = toml::from_str(&content).map_err(|e| {
+            guisu_core::Error::Message(format!(
+                "Failed to parse TOML from {}: {}",
+                path.display(),
+                e
+            ))
+        })?;
+        serde_json::to_value(value).map_err(|e| {
+            guisu_core::Error::Message(format!("Failed to convert TOML to JSON: {e}"))
+        })?
+    } else {
+        serde_json::from_str(&content).map_err(|e| {
+            guisu_core::Error::Message(format!(
+                "Failed to parse JSON from {}: {}",
+                path.display(),
+                e
+            ))
+        })?
+    };
+
+    Ok(Some(value))
+}
+
+#[cfg(test)]
+mod tests {
+    #![allow(clippy::unwrap_used, clippy::panic)]
+    use super::*;
+    use serde_json::json;
+    use tempfile::TempDir;
+
+    fn data_dir(temp: &TempDir) -> PathBuf {
+        let dir = temp.path().join("data");
+        fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    #[test]
+    fn test_load_data_nonexistent_directory() {
+        let temp = TempDir::new().unwrap();
+
+        let result = load_data(temp.path()).unwrap();
+        assert!(result.is_empty());
+    }
+
+    #[test]
+    fn test_load_data_toml_and_json() {
+        let temp = TempDir::new().unwrap();
+        let dir = data_dir(&temp);
+        fs::write(dir.join("hosts.toml"), "[laptop]\nip = \"10.0.0.2\"\n").unwrap();
+        fs::write(dir.join("users.json"), r#"["alice", "bob"]"#).unwrap();
+        fs::write(dir.join("README.md"), "not data").unwrap();
+
+        let result = load_data(temp.path()).unwrap();
+
+        assert_eq!(result.len(), 2);
+        assert_eq!(result["hosts"]["laptop"]["ip"], json!("10.0.0.2"));
+        assert_eq!(result["users"], json!(["alice", "bob"]));
+    }
+
+    #[test]
+    fn test_load_data_deep_merges_same_stem() {
+        let temp = TempDir::new().unwrap();
+        let dir = data_dir(&temp);
+        fs::write(
+            dir.join("hosts.json"),
+            r#"{"laptop": {"ip": "10.0.0.2", "user": "me"}}"#,
+        )
+        .unwrap();
+        fs::write(dir.join("hosts.toml"), "[laptop]\nip = \"10.0.0.3\"\n").unwrap();
+
+        let result = load_data(temp.path()).unwrap();
+
+        // hosts.toml sorts after hosts.json, so its values win
+        assert_eq!(
+            result["hosts"],
+            json!({"laptop": {"ip": "10.0.0.3", "user": "me"}})
+        );
+    }
+
+    #[test]
+    fn test_load_data_invalid_file_errors() {
+        let temp = TempDir::new().unwrap();
+        let dir = data_dir(&temp);
+        fs::write(dir.join("broken.json"), "{ not json").unwrap();
+
+        let err = load_data(temp.path()).unwrap_err();
+        assert!(err.to_string().contains("broken.json"));
+    }
+
+    #[test]
+    fn test_load_data_rejects_yaml() {
+        let temp = TempDir::new().unwrap();
+        let dir = data_dir(&temp);
+        fs::write(dir.join("hosts.yaml"), "laptop: {}\n").unwrap();
+
+        let err = load_data(temp.path()).unwrap_err();
+        assert!(err.to_string().contains("YAML"));
+    }
+}
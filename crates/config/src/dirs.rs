@@ -1,12 +1,16 @@
 //! XDG directory utilities
 //!
-//! This module provides XDG-compliant directory paths for guisu.
-//! It follows the XDG Base Directory specification using the `xdg` crate:
+//! This module provides XDG-compliant directory paths for guisu and for the
+//! user's base directories. It follows the XDG Base Directory specification
+//! using the `xdg` crate:
 //! - `XDG_DATA_HOME` defaults to ~/.local/share
 //! - `XDG_CONFIG_HOME` defaults to ~/.config
+//! - `XDG_CACHE_HOME` defaults to ~/.cache
 //! - `XDG_STATE_HOME` defaults to ~/.local/state
+//!
+//! Relative values of these variables are ignored, as the specification requires.
 
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 use xdg::BaseDirectories;
 
 /// Get the guisu data directory
@@ -27,6 +31,55 @@ pub fn state_dir() -> Option<PathBuf> {
     BaseDirectories::with_prefix("guisu").get_state_home()
 }
 
+/// Get the user's config home
+///
+/// Returns `$XDG_CONFIG_HOME` or `~/.config`
+#[must_use]
+pub fn config_home() -> Option<PathBuf> {
+    BaseDirectories::new()
+        .get_config_home()
+        .as_deref()
+        .map(normalize)
+}
+
+/// Get the user's data home
+///
+/// Returns `$XDG_DATA_HOME` or `~/.local/share`
+#[must_use]
+pub fn data_home() -> Option<PathBuf> {
+    BaseDirectories::new()
+        .get_data_home()
+        .as_deref()
+        .map(normalize)
+}
+
+/// Get the user's cache home
+///
+/// Returns `$XDG_CACHE_HOME` or `~/.cache`
+#[must_use]
+pub fn cache_home() -> Option<PathBuf> {
+    BaseDirectories::new()
+        .get_cache_home()
+        .as_deref()
+        .map(normalize)
+}
+
+/// Get the user's state home
+///
+/// Returns `$XDG_STATE_HOME` or `~/.local/state`
+#[must_use]
+pub fn state_home() -> Option<PathBuf> {
+    BaseDirectories::new()
+        .get_state_home()
+        .as_deref()
+        .map(normalize)
+}
+
+/// Drop the trailing separator `xdg` leaves when no prefix is set
+fn normalize(path: &Path) -> PathBuf {
+    path.components().collect()
+}
+
 /// Get the default source directory for dotfiles
 ///
 /// Returns `$XDG_DATA_HOME/guisu` or `~/.local/share/guisu`
@@ -264,6 +317,46 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_base_homes_honor_env_overrides() {
+        temp_env::with_vars(
+            [
+                ("XDG_CONFIG_HOME", Some("/xdg/config")),
+                ("XDG_DATA_HOME", Some("/xdg/data")),
+                ("XDG_CACHE_HOME", Some("/xdg/cache")),
+                ("XDG_STATE_HOME", Some("/xdg/state")),
+            ],
+            || {
+                assert_eq!(config_home(), Some(PathBuf::from("/xdg/config")));
+                assert_eq!(data_home(), Some(PathBuf::from("/xdg/data")));
+                assert_eq!(cache_home(), Some(PathBuf::from("/xdg/cache")));
+                assert_eq!(state_home(), Some(PathBuf::from("/xdg/state")));
+            },
+        );
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_base_homes_ignore_relative_overrides() {
+        temp_env::with_vars(
+            [
+                ("HOME", Some("/home/someone")),
+                ("XDG_CONFIG_HOME", Some("relative/config")),
+                ("XDG_CACHE_HOME", None),
+            ],
+            || {
+                assert_eq!(
+                    config_home(),
+                    Some(Path::new("/home/someone").join(".config"))
+                );
+                assert_eq!(
+                    cache_home(),
+                    Some(Path::new("/home/someone").join(".cache"))
+                );
+            },
+        );
+    }
+
     #[test]
     fn test_multiple_calls_return_same_value() {
         // Multiple calls should return consistent values
@@ -7,7 +7,9 @@ use std::path::Path;
 
 /// Configuration for ignore patterns loaded from .guisu/ignores.toml
 ///
-/// Supports gitignore-style patterns with negation using ! prefix.
+/// Supports gitignore-style patterns with negation using ! prefix. Patterns
+/// prefixed with `src:` match source paths instead of target paths; see
+/// [`IgnorePattern`](crate::IgnorePattern).
 ///
 /// Example:
 /// ```toml
@@ -4,14 +4,18 @@
 //! - Configuration loading and validation
 //! - XDG directory management
 //! - Git integration
-//! - Variable loading
+//! - Variable and template data loading
 //! - Hook configuration
 //! - Database helpers
 
+pub mod conditions;
 pub mod config;
+pub mod data;
 pub mod dirs;
 pub mod ignores;
 pub mod patterns;
+pub mod profiles;
+pub mod targets;
 pub mod variables;
 
 // Re-export error types from core
@@ -26,10 +30,18 @@ pub type Variables = IndexMap<String, JsonValue>;
 
 // Re-export main types
 pub use config::{
-    AgeConfig, BitwardenConfig, Config, GeneralConfig, IconMode, IgnoreConfig, UiConfig,
+    AgeConfig, ApplyConfig, ApplyMode, BitwardenConfig, Config, ConfigFormat, Eol, FsyncPolicy,
+    GeneralConfig, GitConfig, IconMode, IgnoreConfig, PassConfig, SecretAction, SecurityConfig,
+    SymlinkTargets, TemplateConfig, UiConfig, VaultConfig,
 };
 // NOTE: database module moved to guisu-engine
 // CLI should import from engine::database directly
+pub use conditions::Conditions;
 pub use dirs::{data_dir, default_source_dir, state_dir};
 pub use ignores::IgnoresConfig;
-pub use patterns::IgnoreMatcher;
+pub use patterns::{
+    IGNORE_FILE_NAME, IgnoreFile, IgnoreMatch, IgnoreMatcher, IgnorePattern, IgnoreScope,
+    PatternOrigin,
+};
+pub use profiles::{Profile, Profiles};
+pub use targets::{TargetRoot, Targets};
@@ -3,27 +3,219 @@
 //! Uses the `ignore` crate (from ripgrep) for gitignore-style pattern matching.
 //! Supports negation using ! prefix.
 //!
+//! Patterns match target paths (relative to the destination directory) by
+//! default. A `src:` prefix matches paths in the source directory instead,
+//! relative to the dotfiles directory and including `.j2`/`.age` suffixes, so
+//! source-only artifacts can be ignored by their on-disk name. `dst:` makes the
+//! default explicit. Negation goes inside or before the prefix (`src:!x` or
+//! `!src:x`).
+//!
+//! Besides the central `.guisu/ignores.toml`, any directory in the dotfiles tree
+//! can hold a `.guisuignore` file with one pattern per line. Its patterns are
+//! relative to that directory and take precedence over patterns from parent
+//! directories and `ignores.toml`, so a nested `!pattern` can re-include files
+//! under a broadly ignored directory.
+//!
 //! Example:
 //! ```toml
 //! global = [
 //!     ".config/*",        # Ignore all .config contents
 //!     "!.config/atuin/",  # Re-include .config/atuin/
 //!     "!.config/bat/",    # Re-include .config/bat/
+//!     "src:*.j2.orig",    # Ignore merge leftovers next to templates
 //! ]
 //! ```
 
 use crate::{IgnoresConfig, Result};
 use guisu_core::platform::CURRENT_PLATFORM;
 use ignore::gitignore::{Gitignore, GitignoreBuilder};
-use std::path::Path;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// Name of per-directory ignore files in the dotfiles tree
+pub const IGNORE_FILE_NAME: &str = ".guisuignore";
+
+/// Prefix for patterns matched against source paths
+const SOURCE_PREFIX: &str = "src:";
+/// Prefix for patterns matched against target paths
+const TARGET_PREFIX: &str = "dst:";
+
+/// Path namespace an ignore pattern is matched against
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum IgnoreScope {
+    /// Source paths relative to the dotfiles directory, with attribute suffixes
+    Source,
+    /// Target paths relative to the destination directory
+    Target,
+}
+
+impl IgnoreScope {
+    /// Prefix label used in `.guisu/ignores.toml`, without the colon
+    #[must_use]
+    pub fn label(self) -> &'static str {
+        match self {
+            Self::Source => "src",
+            Self::Target => "dst",
+        }
+    }
+}
+
+/// An entry from `.guisu/ignores.toml` split into its scope and gitignore pattern
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct IgnorePattern {
+    /// Namespace the pattern applies to
+    pub scope: IgnoreScope,
+    /// Gitignore pattern with the scope prefix removed (negation kept)
+    pub pattern: String,
+}
+
+impl IgnorePattern {
+    /// Parse a raw pattern, defaulting to the target scope when unprefixed
+    #[must_use]
+    pub fn parse(raw: &str) -> Self {
+        let (negated, rest) = match raw.strip_prefix('!') {
+            Some(rest) if rest.starts_with(SOURCE_PREFIX) || rest.starts_with(TARGET_PREFIX) => {
+                (true, rest)
+            }
+            _ => (false, raw),
+        };
+
+        let (scope, pattern) = if let Some(pattern) = rest.strip_prefix(SOURCE_PREFIX) {
+            (IgnoreScope::Source, pattern)
+        } else if let Some(pattern) = rest.strip_prefix(TARGET_PREFIX) {
+            (IgnoreScope::Target, pattern)
+        } else {
+            (IgnoreScope::Target, rest)
+        };
+
+        let pattern = if negated {
+            format!("!{pattern}")
+        } else {
+            pattern.to_string()
+        };
+
+        Self { scope, pattern }
+    }
+}
+
+/// Where an ignore pattern is defined
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PatternOrigin {
+    /// A section of `.guisu/ignores.toml`: `global` or a platform name
+    IgnoresToml(&'static str),
+    /// A `.guisuignore` file, relative to the dotfiles directory
+    IgnoreFile(PathBuf),
+}
+
+impl std::fmt::Display for PatternOrigin {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::IgnoresToml(section) => write!(f, ".guisu/ignores.toml [{section}]"),
+            Self::IgnoreFile(path) => write!(f, "{}", path.display()),
+        }
+    }
+}
+
+/// The pattern that decides whether a path is ignored
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct IgnoreMatch {
+    /// Pattern as written, including any scope prefix
+    pub pattern: String,
+    /// Where the pattern is defined
+    pub origin: PatternOrigin,
+    /// Whether the path is ignored; `false` when a negation re-includes it
+    pub ignored: bool,
+}
+
+/// Patterns read from a `.guisuignore` file
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct IgnoreFile {
+    /// Directory containing the file, relative to the dotfiles directory
+    pub dir: PathBuf,
+    /// Raw patterns, without blank and comment lines
+    pub patterns: Vec<String>,
+}
+
+impl IgnoreFile {
+    /// Find all `.guisuignore` files under `dotfiles_dir`, sorted by path
+    ///
+    /// Symlinked directories are not followed.
+    ///
+    /// # Errors
+    ///
+    /// Returns error if a directory or ignore file cannot be read
+    pub fn find_all(dotfiles_dir: &Path) -> Result<Vec<Self>> {
+        let mut files = Vec::new();
+        if dotfiles_dir.is_dir() {
+            collect_ignore_files(dotfiles_dir, Path::new(""), &mut files)?;
+        }
+        files.sort_by(|a, b| a.dir.cmp(&b.dir));
+        Ok(files)
+    }
+
+    /// Path of the file relative to the dotfiles directory
+    #[must_use]
+    pub fn path(&self) -> PathBuf {
+        self.dir.join(IGNORE_FILE_NAME)
+    }
+}
+
+/// Recursively collect ignore files below `dir` (`rel` is relative to the root)
+fn collect_ignore_files(dir: &Path, rel: &Path, files: &mut Vec<IgnoreFile>) -> Result<()> {
+    for entry in fs::read_dir(dir)? {
+        let entry = entry?;
+        let file_type = entry.file_type()?;
+        let name = entry.file_name();
+
+        if file_type.is_dir() {
+            collect_ignore_files(&entry.path(), &rel.join(&name), files)?;
+        } else if file_type.is_file() && name == IGNORE_FILE_NAME {
+            let content = fs::read_to_string(entry.path())?;
+            let patterns = content
+                .lines()
+                .map(str::trim_end)
+                .filter(|line| !line.is_empty() && !line.starts_with('#'))
+                .map(str::to_string)
+                .collect();
+            files.push(IgnoreFile {
+                dir: rel.to_path_buf(),
+                patterns,
+            });
+        }
+    }
+    Ok(())
+}
+
+/// Compiled matchers for one `.guisuignore` file
+#[derive(Debug)]
+struct NestedIgnore {
+    /// Directory containing the file, relative to the dotfiles directory
+    dir: PathBuf,
+    /// Compiled matcher for target patterns
+    gitignore: Gitignore,
+    /// Compiled matcher for source patterns
+    source_gitignore: Gitignore,
+    /// Raw patterns, kept for [`IgnoreMatcher::explain`]
+    patterns: Vec<String>,
+}
 
 /// Ignore pattern matcher using ripgrep's gitignore implementation
 ///
 /// This is a thin wrapper around `ignore::gitignore::Gitignore` that handles
 /// loading patterns from `.guisu/ignores.toml` and platform-specific filtering.
+/// Source and target patterns are compiled into separate matchers.
+#[derive(Debug)]
 pub struct IgnoreMatcher {
-    /// The compiled gitignore matcher from the ignore crate
+    /// Compiled matcher for target (`dst:` and unprefixed) patterns
     gitignore: Gitignore,
+    /// Compiled matcher for source (`src:`) patterns
+    source_gitignore: Gitignore,
+    /// Raw patterns with their `ignores.toml` section, kept for [`Self::explain`]
+    patterns: Vec<(&'static str, String)>,
+    /// Matchers from `.guisuignore` files, deepest directory first
+    nested: Vec<NestedIgnore>,
+    /// Source directories excluded by `.guisu/conditions.toml`
+    excluded_dirs: Vec<PathBuf>,
 }
 
 impl IgnoreMatcher {
@@ -42,72 +234,133 @@ impl IgnoreMatcher {
         let platform = CURRENT_PLATFORM.os;
 
         // Collect all patterns for current platform
-        let mut all_patterns = config.global.clone();
+        let mut all_patterns: Vec<(&'static str, String)> = config
+            .global
+            .into_iter()
+            .map(|raw| ("global", raw))
+            .collect();
 
         // Add platform-specific patterns
-        match platform {
-            "darwin" => all_patterns.extend(config.darwin),
-            "linux" => all_patterns.extend(config.linux),
-            "windows" => all_patterns.extend(config.windows),
-            _ => {}
+        let platform_patterns = match platform {
+            "darwin" => config.darwin,
+            "linux" => config.linux,
+            "windows" => config.windows,
+            _ => Vec::new(),
+        };
+        all_patterns.extend(platform_patterns.into_iter().map(|raw| (platform, raw)));
+
+        // Build one gitignore matcher per scope using ignore crate
+        let mut target_builder = GitignoreBuilder::new(source_dir);
+        let mut source_builder = GitignoreBuilder::new(source_dir);
+
+        for (_, raw) in &all_patterns {
+            let IgnorePattern { scope, pattern } = IgnorePattern::parse(raw);
+            let builder = match scope {
+                IgnoreScope::Source => &mut source_builder,
+                IgnoreScope::Target => &mut target_builder,
+            };
+            add_pattern(builder, &pattern)?;
         }
 
-        // Build gitignore matcher using ignore crate
-        let mut builder = GitignoreBuilder::new(source_dir);
-
-        for pattern in all_patterns {
-            // add_line returns error if pattern is invalid
-            // We use None for the source path (means pattern is not from a file)
+        let build = |builder: GitignoreBuilder| {
             builder
-                .add_line(None, &pattern)
-                .map_err(|e| crate::Error::Io(std::io::Error::other(e.to_string())))?;
-
-            // For patterns that might match directories, also add a pattern to match their contents
-            // This is needed because ignore crate's directory patterns only match the directory itself,
-            // not its contents. In a tree-walking scenario, you'd skip into ignored directories,
-            // but guisu checks individual paths.
-            //
-            // Add /** suffix for:
-            // 1. Patterns ending with / (explicit directory patterns)
-            // 2. Patterns without / that could be directory names (like node_modules, .git)
-            //
-            // Don't add for patterns that already have glob wildcards in the last component
-            // (like *.log, test-*.txt) as these are clearly file patterns.
-            let needs_content_pattern = if pattern.ends_with('/') {
-                !pattern.ends_with("**/")
-            } else {
-                // Check if the last path component contains wildcards
-                let last_component = pattern.rsplit('/').next().unwrap_or(&pattern);
-                !last_component.contains('*') && !last_component.contains('?')
-            };
+                .build()
+                .map_err(|e| crate::Error::Io(std::io::Error::other(e.to_string())))
+        };
+
+        Ok(Self {
+            gitignore: build(target_builder)?,
+            source_gitignore: build(source_builder)?,
+            patterns: all_patterns,
+            nested: Vec::new(),
+            excluded_dirs: Vec::new(),
+        })
+    }
 
-            if needs_content_pattern {
-                // Remove trailing / if present
-                let base = pattern.strip_suffix('/').unwrap_or(&pattern);
-
-                // Add **/ prefix if pattern doesn't start with / (meaning it should match at any level)
-                let content_pattern = if base.starts_with('/') {
-                    // Pattern starts with / - only matches at root
-                    format!("{base}/**")
-                } else {
-                    // Pattern doesn't start with / - should match at any level
-                    format!("**/{base}/**")
-                };
+    /// Exclude whole source subtrees, such as those whose condition is false
+    ///
+    /// `dirs` are relative to the dotfiles directory.
+    #[must_use]
+    pub fn with_excluded_dirs(mut self, dirs: Vec<PathBuf>) -> Self {
+        self.excluded_dirs = dirs;
+        self
+    }
 
-                builder
-                    .add_line(None, &content_pattern)
-                    .map_err(|e| crate::Error::Io(std::io::Error::other(e.to_string())))?;
+    /// Check if a source path lies in an excluded subtree
+    ///
+    /// `path` is relative to the dotfiles directory. Unlike ignore patterns,
+    /// excluded subtrees are skipped without reading their contents.
+    #[must_use]
+    pub fn is_excluded(&self, path: &Path) -> bool {
+        self.excluding_dir(path).is_some()
+    }
+
+    /// The excluded subtree a source path lies in, if any
+    #[must_use]
+    pub fn excluding_dir(&self, path: &Path) -> Option<&Path> {
+        self.excluded_dirs
+            .iter()
+            .map(PathBuf::as_path)
+            .find(|dir| path.starts_with(dir))
+    }
+
+    /// Create from .guisu/ignores.toml and the `.guisuignore` files in the
+    /// dotfiles tree
+    ///
+    /// Paths passed to [`is_ignored`](Self::is_ignored) and
+    /// [`is_source_ignored`](Self::is_source_ignored) are relative to
+    /// `dotfiles_dir`. Patterns in a `.guisuignore` file are relative to its
+    /// directory; the deepest matching file decides, then `ignores.toml`.
+    ///
+    /// # Errors
+    ///
+    /// Returns error if the ignores config or an ignore file cannot be loaded
+    pub fn load(source_dir: &Path, dotfiles_dir: &Path) -> Result<Self> {
+        let mut matcher = Self::from_ignores_toml(source_dir)?;
+
+        for file in IgnoreFile::find_all(dotfiles_dir)? {
+            let root = dotfiles_dir.join(&file.dir);
+            let mut target_builder = GitignoreBuilder::new(&root);
+            let mut source_builder = GitignoreBuilder::new(&root);
+
+            for raw in &file.patterns {
+                let IgnorePattern { scope, pattern } = IgnorePattern::parse(raw);
+                let builder = match scope {
+                    IgnoreScope::Source => &mut source_builder,
+                    IgnoreScope::Target => &mut target_builder,
+                };
+                add_pattern(builder, &pattern)?;
             }
+
+            let build = |builder: GitignoreBuilder| {
+                builder.build().map_err(|e| {
+                    crate::Error::Io(std::io::Error::other(format!(
+                        "{}: {e}",
+                        file.path().display()
+                    )))
+                })
+            };
+
+            matcher.nested.push(NestedIgnore {
+                gitignore: build(target_builder)?,
+                source_gitignore: build(source_builder)?,
+                patterns: file.patterns,
+                dir: file.dir,
+            });
         }
 
-        let gitignore = builder
-            .build()
-            .map_err(|e| crate::Error::Io(std::io::Error::other(e.to_string())))?;
+        // Deeper files override their parents
+        matcher
+            .nested
+            .sort_by_key(|nested| std::cmp::Reverse(nested.dir.components().count()));
 
-        Ok(Self { gitignore })
+        Ok(matcher)
     }
 
-    /// Check if path should be ignored
+    /// Check if a target path should be ignored
+    ///
+    /// `path` is relative to the destination directory; only `dst:` and
+    /// unprefixed patterns apply.
     ///
     /// Uses ripgrep's gitignore matching logic which correctly handles:
     /// - Glob patterns (*, ?, [])
@@ -145,16 +398,192 @@ impl IgnoreMatcher {
         // The gitignore matcher needs to know if the path is a directory
         // If not explicitly provided, try to check if it exists and is a dir
         let is_dir = is_dir.unwrap_or_else(|| path.is_dir());
+        let nested = self.nested.iter().map(|n| (n.dir.as_path(), &n.gitignore));
+        matches_layers(nested, &self.gitignore, path, is_dir)
+    }
+
+    /// Check if a source path should be ignored
+    ///
+    /// Like [`is_ignored`](Self::is_ignored), but for `src:` patterns. `path`
+    /// is relative to the dotfiles directory and keeps attribute suffixes such
+    /// as `.j2` and `.age`.
+    #[must_use]
+    pub fn is_source_ignored(&self, path: &Path, is_dir: Option<bool>) -> bool {
+        let is_dir = is_dir.unwrap_or_else(|| path.is_dir());
+        let nested = self
+            .nested
+            .iter()
+            .map(|n| (n.dir.as_path(), &n.source_gitignore));
+        matches_layers(nested, &self.source_gitignore, path, is_dir)
+    }
+
+    /// Find the pattern that decides whether a path is ignored
+    ///
+    /// Checks the same layers as [`is_ignored`](Self::is_ignored), or
+    /// [`is_source_ignored`](Self::is_source_ignored) for source paths, and
+    /// returns the last matching pattern of the first layer with one. Returns
+    /// `None` if no pattern matches.
+    ///
+    /// # Errors
+    ///
+    /// Returns error if a pattern cannot be compiled
+    pub fn explain(
+        &self,
+        path: &Path,
+        is_dir: bool,
+        scope: IgnoreScope,
+    ) -> Result<Option<IgnoreMatch>> {
+        for nested in &self.nested {
+            let Ok(relative) = path.strip_prefix(&nested.dir) else {
+                continue;
+            };
+            if relative.as_os_str().is_empty() {
+                continue;
+            }
+            let origin = PatternOrigin::IgnoreFile(nested.dir.join(IGNORE_FILE_NAME));
+            let patterns = nested
+                .patterns
+                .iter()
+                .map(|raw| (origin.clone(), raw.as_str()));
+            if let Some(found) = last_match(patterns, scope, relative, is_dir)? {
+                return Ok(Some(found));
+            }
+        }
+
+        let patterns = self
+            .patterns
+            .iter()
+            .map(|(section, raw)| (PatternOrigin::IgnoresToml(section), raw.as_str()));
+        last_match(patterns, scope, path, is_dir)
+    }
+}
 
-        // matched() returns Match enum:
-        // - Match::None: not matched
-        // - Match::Ignore(_): matched an ignore pattern (should be ignored)
-        // - Match::Whitelist(_): matched a negation pattern (should NOT be ignored)
-        match self.gitignore.matched(path, is_dir) {
-            ignore::Match::Ignore(_) => true, // Matched ignore pattern
-            ignore::Match::None | ignore::Match::Whitelist(_) => false, // Not matched or whitelisted
+/// Find the last pattern of `scope` that matches a path
+///
+/// Each pattern is compiled on its own, so this is only meant for explaining
+/// a single decision.
+fn last_match<'a>(
+    patterns: impl DoubleEndedIterator<Item = (PatternOrigin, &'a str)>,
+    scope: IgnoreScope,
+    path: &Path,
+    is_dir: bool,
+) -> Result<Option<IgnoreMatch>> {
+    for (origin, raw) in patterns.rev() {
+        let parsed = IgnorePattern::parse(raw);
+        if parsed.scope != scope {
+            continue;
         }
+
+        let mut builder = GitignoreBuilder::new(".");
+        add_pattern(&mut builder, &parsed.pattern)?;
+        let gitignore = builder
+            .build()
+            .map_err(|e| crate::Error::Io(std::io::Error::other(e.to_string())))?;
+        let ignored = match gitignore.matched(path, is_dir) {
+            ignore::Match::Ignore(_) => true,
+            ignore::Match::Whitelist(_) => false,
+            ignore::Match::None => continue,
+        };
+        return Ok(Some(IgnoreMatch {
+            pattern: raw.to_string(),
+            origin,
+            ignored,
+        }));
+    }
+    Ok(None)
+}
+
+/// Check a path against nested matchers (deepest first), then the root matcher
+///
+/// The first nested matcher with an opinion decides, so a `.guisuignore`
+/// negation re-includes paths ignored further up.
+fn matches_layers<'a>(
+    nested: impl Iterator<Item = (&'a Path, &'a Gitignore)>,
+    root: &Gitignore,
+    path: &Path,
+    is_dir: bool,
+) -> bool {
+    for (dir, gitignore) in nested {
+        // Like .gitignore, a file never applies to its own directory
+        let Ok(relative) = path.strip_prefix(dir) else {
+            continue;
+        };
+        if relative.as_os_str().is_empty() {
+            continue;
+        }
+        match gitignore.matched(relative, is_dir) {
+            ignore::Match::Ignore(_) => return true,
+            ignore::Match::Whitelist(_) => return false,
+            ignore::Match::None => {}
+        }
+    }
+    matches_ignore(root, path, is_dir)
+}
+
+/// Check a path against one compiled matcher
+fn matches_ignore(gitignore: &Gitignore, path: &Path, is_dir: bool) -> bool {
+    // matched() returns Match enum:
+    // - Match::None: not matched
+    // - Match::Ignore(_): matched an ignore pattern (should be ignored)
+    // - Match::Whitelist(_): matched a negation pattern (should NOT be ignored)
+    match gitignore.matched(path, is_dir) {
+        ignore::Match::Ignore(_) => true, // Matched ignore pattern
+        ignore::Match::None | ignore::Match::Whitelist(_) => false, // Not matched or whitelisted
+    }
+}
+
+/// Add a gitignore pattern, plus a contents pattern for directory-like patterns
+fn add_pattern(builder: &mut GitignoreBuilder, pattern: &str) -> Result<()> {
+    // add_line returns error if pattern is invalid
+    // We use None for the source path (means pattern is not from a file)
+    builder
+        .add_line(None, pattern)
+        .map_err(|e| crate::Error::Io(std::io::Error::other(e.to_string())))?;
+
+    // For patterns that might match directories, also add a pattern to match their contents
+    // This is needed because ignore crate's directory patterns only match the directory itself,
+    // not its contents. In a tree-walking scenario, you'd skip into ignored directories,
+    // but guisu checks individual paths.
+    //
+    // Add /** suffix for:
+    // 1. Patterns ending with / (explicit directory patterns)
+    // 2. Patterns without / that could be directory names (like node_modules, .git)
+    //
+    // Don't add for patterns that already have glob wildcards in the last component
+    // (like *.log, test-*.txt) as these are clearly file patterns.
+    let needs_content_pattern = if pattern.ends_with('/') {
+        !pattern.ends_with("**/")
+    } else {
+        // Check if the last path component contains wildcards
+        let last_component = pattern.rsplit('/').next().unwrap_or(pattern);
+        !last_component.contains('*') && !last_component.contains('?')
+    };
+
+    if needs_content_pattern {
+        // Keep negation in front of the generated pattern
+        let (negation, pattern) = match pattern.strip_prefix('!') {
+            Some(rest) => ("!", rest),
+            None => ("", pattern),
+        };
+
+        // Remove trailing / if present
+        let base = pattern.strip_suffix('/').unwrap_or(pattern);
+
+        // Add **/ prefix if pattern doesn't start with / (meaning it should match at any level)
+        let content_pattern = if base.starts_with('/') {
+            // Pattern starts with / - only matches at root
+            format!("{negation}{base}/**")
+        } else {
+            // Pattern doesn't start with / - should match at any level
+            format!("{negation}**/{base}/**")
+        };
+
+        builder
+            .add_line(None, &content_pattern)
+            .map_err(|e| crate::Error::Io(std::io::Error::other(e.to_string())))?;
     }
+
+    Ok(())
 }
 
 #[cfg(test)]
@@ -378,4 +807,170 @@ global = [".config/*", "!.config/atuin/", ".config/atuin/secret"]
             Some(false)
         ));
     }
+
+    #[test]
+    fn test_ignore_pattern_parse_scopes() {
+        let parse = IgnorePattern::parse;
+        assert_eq!(
+            parse("*.log"),
+            IgnorePattern {
+                scope: IgnoreScope::Target,
+                pattern: "*.log".to_string()
+            }
+        );
+        assert_eq!(parse("dst:*.log"), parse("*.log"));
+        assert_eq!(
+            parse("src:*.j2.orig"),
+            IgnorePattern {
+                scope: IgnoreScope::Source,
+                pattern: "*.j2.orig".to_string()
+            }
+        );
+        // Negation works inside or before the prefix
+        assert_eq!(parse("!src:keep.j2").pattern, "!keep.j2");
+        assert_eq!(parse("!src:keep.j2"), parse("src:!keep.j2"));
+        assert_eq!(parse("!.config/atuin/").scope, IgnoreScope::Target);
+    }
+
+    #[test]
+    fn test_source_and_target_scopes_are_separate() {
+        let temp = TempDir::new().unwrap();
+        let content = r#"
+global = ["*.log", "src:*.age", "src:!keep.age", "dst:.cache/"]
+"#;
+        let source_dir = create_test_ignores(&temp, content);
+
+        let matcher = IgnoreMatcher::from_ignores_toml(&source_dir).unwrap();
+
+        // Target patterns only match target paths
+        assert!(matcher.is_ignored(Path::new("debug.log"), Some(false)));
+        assert!(matcher.is_ignored(Path::new(".cache/data"), Some(false)));
+        assert!(!matcher.is_source_ignored(Path::new("debug.log"), Some(false)));
+
+        // Source patterns only match source paths
+        assert!(matcher.is_source_ignored(Path::new(".ssh/id_ed25519.age"), Some(false)));
+        assert!(!matcher.is_source_ignored(Path::new("keep.age"), Some(false)));
+        assert!(!matcher.is_ignored(Path::new(".ssh/id_ed25519.age"), Some(false)));
+    }
+
+    #[test]
+    fn test_negated_directory_reincludes_contents() {
+        let temp = TempDir::new().unwrap();
+        let content = r#"global = [".cache/", "!.cache/keep/"]"#;
+        let source_dir = create_test_ignores(&temp, content);
+
+        let matcher = IgnoreMatcher::from_ignores_toml(&source_dir).unwrap();
+
+        assert!(matcher.is_ignored(Path::new(".cache/data"), Some(false)));
+        assert!(!matcher.is_ignored(Path::new(".cache/keep/file"), Some(false)));
+    }
+
+    #[test]
+    fn test_find_ignore_files() {
+        let temp = TempDir::new().unwrap();
+        let dotfiles = temp.path().join("home");
+        fs::create_dir_all(dotfiles.join(".config/nvim")).unwrap();
+        fs::write(dotfiles.join(IGNORE_FILE_NAME), "*.log\n").unwrap();
+        fs::write(
+            dotfiles.join(".config").join(IGNORE_FILE_NAME),
+            "# comment\n\n*\n!nvim/\n",
+        )
+        .unwrap();
+
+        let files = IgnoreFile::find_all(&dotfiles).unwrap();
+
+        assert_eq!(files.len(), 2);
+        assert_eq!(files[0].dir, PathBuf::new());
+        assert_eq!(files[0].patterns, ["*.log"]);
+        assert_eq!(files[1].path(), Path::new(".config").join(IGNORE_FILE_NAME));
+        assert_eq!(files[1].patterns, ["*", "!nvim/"]);
+    }
+
+    #[test]
+    fn test_nested_ignore_files() {
+        let temp = TempDir::new().unwrap();
+        let source_dir = create_test_ignores(&temp, r#"global = [".cache/"]"#);
+        let dotfiles = source_dir.join("home");
+        fs::create_dir_all(dotfiles.join(".config")).unwrap();
+        fs::create_dir_all(dotfiles.join(".cache")).unwrap();
+        fs::write(
+            dotfiles.join(".config").join(IGNORE_FILE_NAME),
+            "*\n!nvim/\nsrc:*.orig\n",
+        )
+        .unwrap();
+        fs::write(
+            dotfiles.join(".cache").join(IGNORE_FILE_NAME),
+            "!keep.txt\n",
+        )
+        .unwrap();
+
+        let matcher = IgnoreMatcher::load(&source_dir, &dotfiles).unwrap();
+
+        // Patterns are relative to the directory holding the file
+        assert!(matcher.is_ignored(Path::new(".config/fish/config.fish"), Some(false)));
+        assert!(!matcher.is_ignored(Path::new(".config/nvim/init.lua"), Some(false)));
+        assert!(!matcher.is_ignored(Path::new(".bashrc"), Some(false)));
+        assert!(!matcher.is_ignored(Path::new(".config"), Some(true)));
+        assert!(matcher.is_source_ignored(Path::new(".config/nvim/init.lua.orig"), Some(false)));
+
+        // A nested negation re-includes a file under a directory ignored in ignores.toml
+        assert!(matcher.is_ignored(Path::new(".cache/data"), Some(false)));
+        assert!(!matcher.is_ignored(Path::new(".cache/keep.txt"), Some(false)));
+    }
+
+    #[test]
+    fn test_explain() {
+        let temp = TempDir::new().unwrap();
+        let platform = CURRENT_PLATFORM.os;
+        let source_dir = create_test_ignores(
+            &temp,
+            &format!(
+                "global = [\"*.log\", \"src:*.orig\"]\n{platform} = [\".config/*\", \"!.config/nvim/\"]\n"
+            ),
+        );
+        let dotfiles = source_dir.join("home");
+        fs::create_dir_all(dotfiles.join(".cache")).unwrap();
+        fs::write(dotfiles.join(".cache").join(IGNORE_FILE_NAME), "*.log\n").unwrap();
+        let matcher = IgnoreMatcher::load(&source_dir, &dotfiles).unwrap();
+        let explain = |path: &str, scope| {
+            matcher
+                .explain(Path::new(path), false, scope)
+                .unwrap()
+                .map(|found| (found.pattern, found.origin.to_string(), found.ignored))
+        };
+
+        assert_eq!(
+            explain("debug.log", IgnoreScope::Target),
+            Some((
+                "*.log".to_string(),
+                ".guisu/ignores.toml [global]".to_string(),
+                true
+            ))
+        );
+        // The last matching pattern decides
+        assert_eq!(
+            explain(".config/nvim/init.lua", IgnoreScope::Target),
+            Some((
+                "!.config/nvim/".to_string(),
+                format!(".guisu/ignores.toml [{platform}]"),
+                false
+            ))
+        );
+        // A .guisuignore file takes precedence
+        let (_, origin, _) = explain(".cache/app.log", IgnoreScope::Target).unwrap();
+        assert_eq!(
+            origin,
+            Path::new(".cache")
+                .join(IGNORE_FILE_NAME)
+                .display()
+                .to_string()
+        );
+        // Scopes are kept apart
+        assert_eq!(explain("init.lua.orig", IgnoreScope::Target), None);
+        assert_eq!(
+            explain("init.lua.orig", IgnoreScope::Source).map(|(pattern, ..)| pattern),
+            Some("src:*.orig".to_string())
+        );
+        assert_eq!(explain(".bashrc", IgnoreScope::Target), None);
+    }
 }
@@ -0,0 +1,235 @@
+//! Machine profiles from .guisu/profiles.toml
+
+use crate::conditions::dotfiles_subdir;
+use crate::variables::merge_variables;
+use crate::{Config, Result};
+use indexmap::IndexMap;
+use serde::Deserialize;
+use serde_json::Value as JsonValue;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// A named set of source directories and variable overrides
+#[derive(Debug, Default, Deserialize)]
+pub struct Profile {
+    /// Directories of the dotfiles tree that belong to this profile
+    #[serde(default)]
+    pub dirs: Vec<String>,
+
+    /// Variables that override the configured ones while the profile is active
+    #[serde(default)]
+    pub variables: IndexMap<String, JsonValue>,
+}
+
+/// Profiles that select parts of one repository per machine
+///
+/// Directories listed by a profile are only part of the source state when
+/// that profile is active; everything else is shared by all profiles. Without
+/// an active profile, every directory is included.
+///
+/// Example:
+/// ```toml
+/// [work]
+/// dirs = ["work", ".config/slack"]
+/// variables = { email = "me@work.example" }
+///
+/// [personal]
+/// dirs = ["personal"]
+/// ```
+#[derive(Debug, Default, Deserialize)]
+#[serde(transparent)]
+pub struct Profiles {
+    /// Profiles by name
+    pub entries: IndexMap<String, Profile>,
+}
+
+impl Profiles {
+    /// Load profiles from .guisu/profiles.toml
+    ///
+    /// # Errors
+    ///
+    /// Returns error if file cannot be read or TOML parsing fails
+    pub fn load(source_dir: &Path) -> Result<Self> {
+        let profiles_path = source_dir.join(".guisu").join("profiles.toml");
+
+        if !profiles_path.exists() {
+            return Ok(Self::default());
+        }
+
+        let content = fs::read_to_string(&profiles_path).map_err(|e| {
+            guisu_core::Error::Message(format!("Failed to read {}: {}", profiles_path.display(), e))
+        })?;
+
+        toml::from_str(&content).map_err(|e| {
+            guisu_core::Error::Message(format!(
+                "Failed to parse {}: {}",
+                profiles_path.display(),
+                e
+            ))
+        })
+    }
+
+    /// Whether no profiles are defined
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// Look up a profile by name
+    ///
+    /// # Errors
+    ///
+    /// Returns error if no profile has that name
+    pub fn get(&self, name: &str) -> Result<&Profile> {
+        self.entries.get(name).ok_or_else(|| {
+            let available = if self.is_empty() {
+                "none are defined in .guisu/profiles.toml".to_string()
+            } else {
+                let names: Vec<&str> = self.entries.keys().map(String::as_str).collect();
+                format!("available: {}", names.join(", "))
+            };
+            guisu_core::Error::Message(format!("Unknown profile '{name}' ({available})"))
+        })
+    }
+
+    /// Merge the variables of the profile selected in `[general] profile` into
+    /// the config's variables
+    ///
+    /// # Errors
+    ///
+    /// Returns error if the selected profile is not defined
+    pub fn apply_variables(&self, config: &mut Config) -> Result<()> {
+        if let Some(name) = &config.general.profile {
+            merge_variables(&mut config.variables, self.get(name)?.variables.clone());
+        }
+        Ok(())
+    }
+
+    /// Directories to leave out of the source state when `active` is selected
+    ///
+    /// These are the directories of the other profiles that the active profile
+    /// does not list too. Directories are relative to the dotfiles directory.
+    ///
+    /// # Errors
+    ///
+    /// Returns error if `active` is not defined, or if a directory is not a
+    /// relative path inside the dotfiles directory
+    pub fn excluded_dirs(&self, active: Option<&str>) -> Result<Vec<PathBuf>> {
+        let Some(active) = active else {
+            return Ok(Vec::new());
+        };
+        let included = Self::dirs(active, self.get(active)?)?;
+
+        let mut excluded = Vec::new();
+        for (name, profile) in &self.entries {
+            if name == active {
+                continue;
+            }
+            for dir in Self::dirs(name, profile)? {
+                if !included.contains(&dir) && !excluded.contains(&dir) {
+                    excluded.push(dir);
+                }
+            }
+        }
+        Ok(excluded)
+    }
+
+    /// Parsed directories of a profile
+    fn dirs(name: &str, profile: &Profile) -> Result<Vec<PathBuf>> {
+        profile
+            .dirs
+            .iter()
+            .map(|prefix| {
+                dotfiles_subdir(prefix).ok_or_else(|| {
+                    guisu_core::Error::Message(format!(
+                        "Invalid directory '{prefix}' in profile '{name}': expected a directory inside the dotfiles directory"
+                    ))
+                })
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    #![allow(clippy::unwrap_used, clippy::panic)]
+    use super::*;
+    use tempfile::TempDir;
+
+    fn load(content: &str) -> Profiles {
+        let temp = TempDir::new().unwrap();
+        let guisu_dir = temp.path().join(".guisu");
+        fs::create_dir_all(&guisu_dir).unwrap();
+        fs::write(guisu_dir.join("profiles.toml"), content).unwrap();
+        Profiles::load(temp.path()).unwrap()
+    }
+
+    #[test]
+    fn test_profiles_load_missing_file() {
+        let temp = TempDir::new().unwrap();
+        assert!(Profiles::load(temp.path()).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_excluded_dirs() {
+        let profiles = load(
+            r#"
+[work]
+dirs = ["work/", ".config/shared"]
+variables = { email = "me@work.example" }
+
+[personal]
+dirs = ["personal", ".config/shared"]
+"#,
+        );
+
+        assert_eq!(
+            profiles.get("work").unwrap().variables["email"],
+            "me@work.example"
+        );
+        assert_eq!(
+            profiles.excluded_dirs(Some("work")).unwrap(),
+            [PathBuf::from("personal")]
+        );
+        assert_eq!(
+            profiles.excluded_dirs(Some("personal")).unwrap(),
+            [PathBuf::from("work")]
+        );
+        assert_eq!(profiles.excluded_dirs(None).unwrap(), Vec::<PathBuf>::new());
+    }
+
+    #[test]
+    fn test_apply_variables() {
+        let profiles = load("[work.variables]\ngit = { email = \"me@work.example\" }\n");
+        let mut config = Config::default();
+        config.variables.insert(
+            "git".to_string(),
+            serde_json::json!({ "name": "Me", "email": "me@home.example" }),
+        );
+
+        // Nothing changes until a profile is selected
+        profiles.apply_variables(&mut config).unwrap();
+        assert_eq!(config.variables["git"]["email"], "me@home.example");
+
+        config.general.profile = Some("work".to_string());
+        profiles.apply_variables(&mut config).unwrap();
+        assert_eq!(config.variables["git"]["email"], "me@work.example");
+        assert_eq!(config.variables["git"]["name"], "Me");
+    }
+
+    #[test]
+    fn test_unknown_profile() {
+        let profiles = load("[work]\n[personal]\n");
+        let err = profiles.excluded_dirs(Some("server")).unwrap_err();
+        assert!(err.to_string().contains("Unknown profile 'server'"));
+        assert!(err.to_string().contains("available: "));
+        assert!(err.to_string().contains("personal"));
+    }
+
+    #[test]
+    fn test_invalid_dir() {
+        let profiles = load("[work]\ndirs = [\"../outside\"]\n");
+        let err = profiles.excluded_dirs(Some("work")).unwrap_err();
+        assert!(err.to_string().contains("'../outside'"));
+    }
+}
@@ -0,0 +1,303 @@
+//! Destination roots from .guisu/targets.toml
+
+use crate::Result;
+use crate::conditions::dotfiles_subdir;
+use guisu_core::path::{AbsPath, RelPath};
+use indexmap::IndexMap;
+use serde::Deserialize;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// A destination root as written in .guisu/targets.toml
+#[derive(Debug, Deserialize)]
+#[serde(untagged)]
+enum RootSpec {
+    /// Just the root path
+    Path(String),
+    /// The root path and options
+    Table {
+        path: String,
+        #[serde(default)]
+        sudo: bool,
+    },
+}
+
+/// A source subtree applied to its own destination root
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TargetRoot {
+    /// Directory of the dotfiles tree, relative to the dotfiles directory
+    pub dir: PathBuf,
+    /// Absolute destination the directory maps to
+    pub root: PathBuf,
+    /// Write with `sudo` when the root is not writable
+    pub sudo: bool,
+}
+
+/// Source subtrees that are applied outside the destination directory
+///
+/// Maps directories of the dotfiles tree to absolute roots. Roots may start
+/// with `~` or an environment variable; `$XDG_CONFIG_HOME`, `$XDG_DATA_HOME`,
+/// `$XDG_CACHE_HOME` and `$XDG_STATE_HOME` fall back to their defaults when
+/// unset. Everything else is applied to the destination directory.
+///
+/// Example:
+/// ```toml
+/// "config" = "$XDG_CONFIG_HOME"
+/// "etc" = { path = "/etc", sudo = true }
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct Targets {
+    /// Roots, longest directory first so nested directories win
+    roots: Vec<TargetRoot>,
+}
+
+impl Targets {
+    /// Load destination roots from .guisu/targets.toml
+    ///
+    /// # Errors
+    ///
+    /// Returns error if file cannot be read or TOML parsing fails, or if an
+    /// entry is not a directory of the dotfiles tree mapped to an absolute path
+    pub fn load(source_dir: &Path) -> Result<Self> {
+        let targets_path = source_dir.join(".guisu").join("targets.toml");
+
+        if !targets_path.exists() {
+            return Ok(Self::default());
+        }
+
+        let content = fs::read_to_string(&targets_path).map_err(|e| {
+            guisu_core::Error::Message(format!("Failed to read {}: {}", targets_path.display(), e))
+        })?;
+
+        let specs: IndexMap<String, RootSpec> = toml::from_str(&content).map_err(|e| {
+            guisu_core::Error::Message(format!("Failed to parse {}: {}", targets_path.display(), e))
+        })?;
+
+        Self::from_specs(specs)
+    }
+
+    fn from_specs(specs: IndexMap<String, RootSpec>) -> Result<Self> {
+        let mut roots = Vec::with_capacity(specs.len());
+        for (prefix, spec) in specs {
+            let Some(dir) = dotfiles_subdir(&prefix) else {
+                return Err(guisu_core::Error::Message(format!(
+                    "Invalid target directory '{prefix}': expected a directory inside the dotfiles directory"
+                )));
+            };
+            let (path, sudo) = match spec {
+                RootSpec::Path(path) => (path, false),
+                RootSpec::Table { path, sudo } => (path, sudo),
+            };
+            let root = expand_root(&path).map_err(|reason| {
+                guisu_core::Error::Message(format!(
+                    "Invalid target root '{path}' for '{prefix}': {reason}"
+                ))
+            })?;
+            roots.push(TargetRoot { dir, root, sudo });
+        }
+
+        roots.sort_by_key(|root| std::cmp::Reverse(root.dir.components().count()));
+        Ok(Self { roots })
+    }
+
+    /// Whether every target is applied to the destination directory
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.roots.is_empty()
+    }
+
+    /// The configured roots, longest directory first
+    #[must_use]
+    pub fn roots(&self) -> &[TargetRoot] {
+        &self.roots
+    }
+
+    /// The root a target path is applied to, if it is not the destination directory
+    #[must_use]
+    pub fn root_for(&self, target: &RelPath) -> Option<&TargetRoot> {
+        self.roots
+            .iter()
+            .find(|root| target.as_path().starts_with(&root.dir))
+    }
+
+    /// Absolute destination of a target path
+    #[must_use]
+    pub fn resolve(&self, dest_dir: &AbsPath, target: &RelPath) -> AbsPath {
+        let Some(root) = self.root_for(target) else {
+            return dest_dir.join(target);
+        };
+        let rest = target
+            .as_path()
+            .strip_prefix(&root.dir)
+            .unwrap_or(target.as_path());
+        let path = if rest.as_os_str().is_empty() {
+            root.root.clone()
+        } else {
+            root.root.join(rest)
+        };
+        // Roots are checked to be absolute when loading
+        AbsPath::new(path).unwrap_or_else(|_| dest_dir.join(target))
+    }
+
+    /// Target path of an absolute destination path
+    ///
+    /// Paths inside a root map into its directory of the dotfiles tree; other
+    /// paths must be inside `dest_dir`. Returns `None` for paths in neither.
+    #[must_use]
+    pub fn target_path(&self, dest_dir: &AbsPath, path: &AbsPath) -> Option<RelPath> {
+        let mapped = self
+            .roots
+            .iter()
+            .filter_map(|root| {
+                let rest = path.as_path().strip_prefix(&root.root).ok()?;
+                Some((root.root.components().count(), root.dir.join(rest)))
+            })
+            .max_by_key(|(depth, _)| *depth);
+        match mapped {
+            Some((_, target)) => RelPath::new(target).ok(),
+            None => path.strip_prefix(dest_dir).ok(),
+        }
+    }
+}
+
+/// Expand a leading `~` or environment variable and check the result is absolute
+fn expand_root(path: &str) -> std::result::Result<PathBuf, String> {
+    let expanded = if path == "~" || path.starts_with("~/") {
+        let home = ::dirs::home_dir().ok_or("home directory not found")?;
+        home.join(path.trim_start_matches('~').trim_start_matches('/'))
+    } else if let Some(var) = path.strip_prefix('$') {
+        let (name, rest) = match var.strip_prefix('{') {
+            Some(braced) => braced.split_once('}').ok_or("unclosed '${'")?,
+            None => var.split_at(var.find('/').unwrap_or(var.len())),
+        };
+        let value = match std::env::var_os(name) {
+            Some(value) if !value.is_empty() => PathBuf::from(value),
+            _ => xdg_default(name).ok_or_else(|| format!("${name} is not set"))?,
+        };
+        value.join(rest.trim_start_matches('/'))
+    } else {
+        PathBuf::from(path)
+    };
+
+    if expanded.is_absolute() {
+        Ok(expanded.components().collect())
+    } else {
+        Err("expected an absolute path".to_string())
+    }
+}
+
+/// Default of an unset XDG base directory variable
+fn xdg_default(name: &str) -> Option<PathBuf> {
+    match name {
+        "XDG_CONFIG_HOME" => crate::dirs::config_home(),
+        "XDG_DATA_HOME" => crate::dirs::data_home(),
+        "XDG_CACHE_HOME" => crate::dirs::cache_home(),
+        "XDG_STATE_HOME" => crate::dirs::state_home(),
+        "HOME" => ::dirs::home_dir(),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    #![allow(clippy::unwrap_used, clippy::panic)]
+    use super::*;
+    use tempfile::TempDir;
+
+    fn parse(content: &str) -> Result<Targets> {
+        Targets::from_specs(toml::from_str(content).unwrap())
+    }
+
+    fn rel(path: &str) -> RelPath {
+        RelPath::new(PathBuf::from(path)).unwrap()
+    }
+
+    #[test]
+    fn test_targets_load_missing_file() {
+        let temp = TempDir::new().unwrap();
+        assert!(Targets::load(temp.path()).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_resolve_and_target_path() {
+        let targets = parse(
+            r#"
+            "etc" = { path = "/etc", sudo = true }
+            "etc/ssh" = "/opt/ssh"
+            "#,
+        )
+        .unwrap();
+        let dest = AbsPath::new(PathBuf::from("/home/user")).unwrap();
+
+        assert!(targets.root_for(&rel("etc/hosts")).unwrap().sudo);
+        assert_eq!(
+            targets.resolve(&dest, &rel("etc/hosts")).as_path(),
+            Path::new("/etc/hosts")
+        );
+        assert_eq!(
+            targets.resolve(&dest, &rel("etc")).as_path(),
+            Path::new("/etc")
+        );
+        // Nested directories win
+        assert_eq!(
+            targets.resolve(&dest, &rel("etc/ssh/config")).as_path(),
+            Path::new("/opt/ssh/config")
+        );
+        // Other targets stay in the destination directory
+        assert_eq!(
+            targets.resolve(&dest, &rel(".etcrc")).as_path(),
+            Path::new("/home/user/.etcrc")
+        );
+
+        let abs = |path: &str| AbsPath::new(PathBuf::from(path)).unwrap();
+        assert_eq!(
+            targets.target_path(&dest, &abs("/etc/hosts")),
+            Some(rel("etc/hosts"))
+        );
+        assert_eq!(
+            targets.target_path(&dest, &abs("/opt/ssh/config")),
+            Some(rel("etc/ssh/config"))
+        );
+        assert_eq!(
+            targets.target_path(&dest, &abs("/home/user/.bashrc")),
+            Some(rel(".bashrc"))
+        );
+        assert_eq!(targets.target_path(&dest, &abs("/var/log")), None);
+    }
+
+    #[test]
+    fn test_expand_root() {
+        temp_env::with_vars(
+            [
+                ("XDG_CONFIG_HOME", None),
+                ("HOME", Some("/home/someone")),
+                ("GUISU_TEST_ROOT", Some("/srv/root")),
+            ],
+            || {
+                assert_eq!(
+                    expand_root("${GUISU_TEST_ROOT}/etc").unwrap(),
+                    PathBuf::from("/srv/root/etc")
+                );
+                assert_eq!(
+                    expand_root("$GUISU_TEST_ROOT").unwrap(),
+                    PathBuf::from("/srv/root")
+                );
+                assert_eq!(
+                    expand_root("$XDG_CONFIG_HOME").unwrap(),
+                    PathBuf::from("/home/someone/.config")
+                );
+                assert!(expand_root("$GUISU_TEST_UNSET").is_err());
+                assert!(expand_root("relative").is_err());
+            },
+        );
+    }
+
+    #[test]
+    fn test_invalid_directory() {
+        let err = parse(r#""../etc" = "/etc""#).unwrap_err();
+        assert!(
+            err.to_string()
+                .contains("Invalid target directory '../etc'")
+        );
+    }
+}
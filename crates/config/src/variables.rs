@@ -4,28 +4,30 @@ use crate::Result;
 use indexmap::IndexMap;
 use serde_json::Value as JsonValue;
 use std::fs;
-use std::path::Path;
+use std::path::{Path, PathBuf};
 
 /// Load variables from .guisu/variables/ directory
 ///
 /// Loading order:
-/// 1. Load all *.toml from variables/ (all platforms)
-/// 2. Load all *.toml from variables/{platform}/ (platform-specific, overwrites same keys)
+/// 1. Load template data from data/ (see [`crate::data::load_data`])
+/// 2. Load all *.toml from variables/ (all platforms, deep-merged over data)
+/// 3. Load all *.toml from variables/{platform}/ (platform-specific, overwrites same keys)
 ///
 /// # Errors
 ///
-/// Returns error if TOML files cannot be read or parsed
+/// Returns error if TOML files cannot be read or parsed, or template data
+/// cannot be loaded
 pub fn load_variables(guisu_dir: &Path, platform: &str) -> Result<IndexMap<String, JsonValue>> {
     use rayon::prelude::*;
 
-    let mut variables = IndexMap::new();
+    let mut variables = crate::data::load_data(guisu_dir)?;
 
     let variables_dir = guisu_dir.join("variables");
     if !variables_dir.exists() {
         return Ok(variables);
     }
 
-    // 1. Load platform-agnostic variables (parallel file reading + parsing)
+    // 2. Load platform-agnostic variables (parallel file reading + parsing)
     if let Ok(entries) = fs::read_dir(&variables_dir) {
         let paths: Vec<_> = entries
             .flatten()
@@ -47,7 +49,7 @@ pub fn load_variables(guisu_dir: &Path, platform: &str) -> Result<IndexMap<Strin
         }
     }
 
-    // 2. Load platform-specific variables (parallel, overwrites)
+    // 3. Load platform-specific variables (parallel, overwrites)
     let platform_dir = variables_dir.join(platform);
     if platform_dir.exists()
         && let Ok(entries) = fs::read_dir(&platform_dir)
@@ -75,6 +77,79 @@ pub fn load_variables(guisu_dir: &Path, platform: &str) -> Result<IndexMap<Strin
     Ok(variables)
 }
 
+/// File that defined each variable returned by [`load_variables`]
+///
+/// Keys are dotted paths to the leaf values (`git.email`); arrays count as
+/// leaves. Files are replayed in loading order, so a key maps to the file
+/// whose value won. Files that fail to load are skipped here; use
+/// [`load_variables`] to report them.
+///
+/// # Errors
+///
+/// Returns error if a data file cannot be parsed
+pub fn variable_origins(guisu_dir: &Path, platform: &str) -> Result<IndexMap<String, PathBuf>> {
+    let mut origins = IndexMap::new();
+
+    for path in crate::data::data_file_paths(guisu_dir) {
+        let Some(stem) = path.file_stem().and_then(|s| s.to_str()) else {
+            continue;
+        };
+        if let Some(value) = crate::data::load_data_file(&path)? {
+            record_origins(&mut origins, stem, &value, &path);
+        }
+    }
+
+    let variables_dir = guisu_dir.join("variables");
+    for dir in [variables_dir.clone(), variables_dir.join(platform)] {
+        let Ok(entries) = fs::read_dir(&dir) else {
+            continue;
+        };
+        let mut paths: Vec<PathBuf> = entries
+            .flatten()
+            .map(|e| e.path())
+            .filter(|p| p.is_file())
+            .collect();
+        paths.sort();
+
+        for path in paths {
+            if let Ok(Some(file)) = load_variable_file(&path) {
+                let value = JsonValue::Object(file.variables.into_iter().collect());
+                record_origins(&mut origins, &file.stem, &value, &path);
+            }
+        }
+    }
+
+    Ok(origins)
+}
+
+/// Record `origin` for every leaf of `value`, which is merged in at `key`
+///
+/// Objects merge key by key like [`merge_variables`]; any other value
+/// replaces whatever was under its key before.
+pub fn record_origins<T: Clone>(
+    origins: &mut IndexMap<String, T>,
+    key: &str,
+    value: &JsonValue,
+    origin: &T,
+) {
+    if let JsonValue::Object(map) = value {
+        // An object replaces a leaf of the same name
+        origins.shift_remove(key);
+        for (child, value) in map {
+            record_origins(origins, &format!("{key}.{child}"), value, origin);
+        }
+    } else {
+        let nested = format!("{key}.");
+        origins.retain(|k, _| !k.starts_with(&nested));
+        // A leaf replaces a parent that was a leaf too
+        let parents: Vec<_> = key.match_indices('.').map(|(i, _)| &key[..i]).collect();
+        for parent in parents {
+            origins.shift_remove(parent);
+        }
+        origins.insert(key.to_string(), origin.clone());
+    }
+}
+
 /// Represents a loaded variable file with its name and contents
 #[derive(Debug)]
 struct VariableFile {
@@ -124,7 +199,10 @@ fn load_variable_file(path: &Path) -> Result<Option<VariableFile>> {
 }
 
 /// Deep merge two variable maps (second overwrites first on conflicts)
-fn merge_variables(base: &mut IndexMap<String, JsonValue>, overlay: IndexMap<String, JsonValue>) {
+pub(crate) fn merge_variables(
+    base: &mut IndexMap<String, JsonValue>,
+    overlay: IndexMap<String, JsonValue>,
+) {
     for (key, value) in overlay {
         match (base.get_mut(&key), &value) {
             (Some(JsonValue::Object(base_obj)), JsonValue::Object(overlay_obj)) => {
@@ -251,6 +329,41 @@ platform_key = "linux-specific"
         assert_eq!(app["platform_key"], json!("linux-specific"));
     }
 
+    #[test]
+    fn test_variable_origins() {
+        let temp = TempDir::new().unwrap();
+        let guisu_dir = temp.path();
+        let vars_dir = guisu_dir.join("variables");
+        let linux_dir = vars_dir.join("linux");
+        fs::create_dir_all(&linux_dir).unwrap();
+        fs::create_dir_all(guisu_dir.join("data")).unwrap();
+        fs::write(guisu_dir.join("data/app.json"), r#"{"tags": ["a"]}"#).unwrap();
+        fs::write(
+            vars_dir.join("app.toml"),
+            "name = \"app\"\nenv = \"default\"\n",
+        )
+        .unwrap();
+        fs::write(linux_dir.join("app.toml"), "env = \"linux\"\n").unwrap();
+
+        let origins = variable_origins(guisu_dir, "linux").unwrap();
+
+        assert_eq!(origins["app.tags"], guisu_dir.join("data/app.json"));
+        assert_eq!(origins["app.name"], vars_dir.join("app.toml"));
+        assert_eq!(origins["app.env"], linux_dir.join("app.toml"));
+        assert_eq!(origins.len(), 3);
+    }
+
+    #[test]
+    fn test_record_origins_replaces_subtrees() {
+        let mut origins = IndexMap::new();
+        record_origins(&mut origins, "git", &json!({"user": {"name": "a"}}), &1);
+        record_origins(&mut origins, "git.user", &json!("b"), &2);
+        assert_eq!(origins, IndexMap::from([("git.user".to_string(), 2)]));
+
+        record_origins(&mut origins, "git.user.email", &json!("c"), &3);
+        assert_eq!(origins, IndexMap::from([("git.user.email".to_string(), 3)]));
+    }
+
     #[test]
     fn test_load_variables_ignores_non_toml() {
         let temp = TempDir::new().unwrap();
@@ -472,4 +585,27 @@ ip = "192.168.1.2"
             assert!(result.contains_key(&key));
         }
     }
+
+    #[test]
+    fn test_load_variables_merges_over_data() {
+        let temp = TempDir::new().unwrap();
+        let guisu_dir = temp.path();
+        fs::create_dir_all(guisu_dir.join("data")).unwrap();
+        fs::create_dir_all(guisu_dir.join("variables")).unwrap();
+        fs::write(
+            guisu_dir.join("data").join("git.json"),
+            r#"{"name": "Data", "editor": "vim"}"#,
+        )
+        .unwrap();
+        fs::write(
+            guisu_dir.join("variables").join("git.toml"),
+            "name = \"Variables\"\n",
+        )
+        .unwrap();
+
+        let result = load_variables(guisu_dir, "linux").unwrap();
+
+        assert_eq!(result["git"]["name"], json!("Variables"));
+        assert_eq!(result["git"]["editor"], json!("vim"));
+    }
 }
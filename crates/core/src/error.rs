@@ -166,6 +166,16 @@ pub enum Error {
         source: Box<dyn std::error::Error + Send + Sync>,
     },
 
+    /// Decompression error
+    #[error("Decompression failed for {path}: {source}")]
+    Decompression {
+        /// Path to the compressed file
+        path: String,
+        /// Underlying error
+        #[source]
+        source: std::io::Error,
+    },
+
     /// Inline decryption error (for template content)
     #[error("Inline decryption failed: {message}")]
     InlineDecryption {
@@ -3,7 +3,7 @@
 //! This module provides the core encryption and decryption functionality using the
 //! age encryption format. It supports:
 //!
-//! - Standard file encryption with ASCII armor format
+//! - Standard file encryption in ASCII armor or binary format
 //! - Inline(SOPS-like) encryption with compact base64 encoding (age:base64...)
 //! - Both age native keys and SSH keys
 //! - Multiple recipients and identities
@@ -17,6 +17,12 @@ use tracing::warn;
 /// Prefix for inline encrypted values: "age:"
 const INLINE_PREFIX: &str = "age:";
 
+/// First line of ASCII-armored age data
+const ARMOR_BEGIN: &[u8] = b"-----BEGIN AGE ENCRYPTED FILE-----";
+
+/// Start of binary age data
+const BINARY_MAGIC: &[u8] = b"age-encryption.org/";
+
 /// Cached regex pattern for matching inline encrypted values.
 static INLINE_PATTERN: LazyLock<regex::Regex> = LazyLock::new(|| {
     // Pattern matches age:base64 with greedy quantifier
@@ -110,6 +116,20 @@ fn map_decrypt_error(e: age::DecryptError) -> Error {
 /// let encrypted = encrypt(plaintext, &[recipient]).unwrap();
 /// ```
 pub fn encrypt(data: &[u8], recipients: &[Recipient]) -> Result<Vec<u8>> {
+    encrypt_with_armor(data, recipients, true)
+}
+
+/// Encrypt data with the given recipients, in ASCII armor or binary format
+///
+/// Armored output is what [`encrypt`] produces. Binary output is about a
+/// quarter smaller but not text, so git shows it as a binary change.
+/// [`decrypt`] reads either.
+///
+/// # Errors
+///
+/// - Returns [`Error::NoRecipients`] if the recipients slice is empty
+/// - Returns [`Error::Age`] if encryption fails
+pub fn encrypt_with_armor(data: &[u8], recipients: &[Recipient], armor: bool) -> Result<Vec<u8>> {
     if recipients.is_empty() {
         return Err(Error::NoRecipients);
     }
@@ -124,11 +144,10 @@ pub fn encrypt(data: &[u8], recipients: &[Recipient]) -> Result<Vec<u8>> {
         .map_err(|_| Error::Age("Failed to create encryptor with recipients".to_string()))?;
 
     let mut encrypted = Vec::new();
-    let armor =
-        age::armor::ArmoredWriter::wrap_output(&mut encrypted, age::armor::Format::AsciiArmor)
-            .map_err(age_error)?;
+    let output = age::armor::ArmoredWriter::wrap_output(&mut encrypted, armor_format(armor))
+        .map_err(age_error)?;
 
-    let mut writer = encryptor.wrap_output(armor).map_err(age_error)?;
+    let mut writer = encryptor.wrap_output(output).map_err(age_error)?;
     writer.write_all(data).map_err(age_error)?;
     writer
         .finish()
@@ -138,10 +157,61 @@ pub fn encrypt(data: &[u8], recipients: &[Recipient]) -> Result<Vec<u8>> {
     Ok(encrypted)
 }
 
+/// Check if encrypted data is in ASCII armor format
+#[must_use]
+pub fn is_armored(data: &[u8]) -> bool {
+    data.trim_ascii_start().starts_with(ARMOR_BEGIN)
+}
+
+/// Convert encrypted data between ASCII armor and binary format
+///
+/// Armor is only an encoding of the binary format, so no identity is needed
+/// and the recipients stay the same. Data already in the requested format is
+/// returned unchanged.
+///
+/// # Errors
+///
+/// Returns [`Error::Age`] if the data is not in age format
+pub fn convert_armor(data: &[u8], armor: bool) -> Result<Vec<u8>> {
+    if is_armored(data) == armor {
+        return Ok(data.to_vec());
+    }
+
+    // The reader removes armor if there is any
+    let mut binary = Vec::new();
+    age::armor::ArmoredReader::new(data)
+        .read_to_end(&mut binary)
+        .map_err(age_error)?;
+    if !binary.starts_with(BINARY_MAGIC) {
+        return Err(Error::Age("Not an age encrypted file".to_string()));
+    }
+    if !armor {
+        return Ok(binary);
+    }
+
+    let mut armored = Vec::new();
+    let mut writer = age::armor::ArmoredWriter::wrap_output(&mut armored, armor_format(true))
+        .map_err(age_error)?;
+    writer.write_all(&binary).map_err(age_error)?;
+    writer.finish().map_err(age_error)?;
+    Ok(armored)
+}
+
+/// Output format of the age armor writer
+fn armor_format(armor: bool) -> age::armor::Format {
+    if armor {
+        age::armor::Format::AsciiArmor
+    } else {
+        age::armor::Format::Binary
+    }
+}
+
 /// Decrypt data with the given identities (supports armor and binary formats).
 ///
 /// Decrypts age-encrypted data using one or more identities (private keys).
 /// Automatically detects and handles both ASCII-armored and binary formats.
+/// With several identities, only those matching the age header are tried
+/// (see [`decrypt_with_hint`]).
 ///
 /// # Arguments
 ///
@@ -180,27 +250,115 @@ pub fn decrypt(data: &[u8], identities: &[Identity]) -> Result<Vec<u8>> {
         return decrypt_single(data, &identities[0]);
     }
 
-    let age_identities: Vec<&dyn age::Identity> =
-        identities.iter().map(Identity::as_dyn_identity).collect();
+    decrypt_with_hint(data, identities, None).map(|decrypted| decrypted.plaintext)
+}
 
-    let mut decrypted = Vec::new();
+/// Decrypt from a reader into a writer, a chunk at a time
+///
+/// Works like [`decrypt`] without holding the whole file in memory, for
+/// files too large to decrypt at once. Both ASCII armor and binary format
+/// are accepted. Returns the number of plaintext bytes written.
+///
+/// # Errors
+///
+/// - Returns [`Error::NoIdentity`] if the identities slice is empty
+/// - Returns [`Error::WrongKey`] if no identity can decrypt the data
+/// - Returns [`Error::DecryptionFailed`] or [`Error::Age`] if the data is malformed
+/// - Returns [`Error::Io`] if writing the plaintext fails
+pub fn decrypt_stream(
+    input: impl Read,
+    output: &mut dyn Write,
+    identities: &[Identity],
+) -> Result<u64> {
+    if identities.is_empty() {
+        return Err(Error::NoIdentity);
+    }
 
-    // Try armored format first
-    if let Ok(decryptor) = age::Decryptor::new(age::armor::ArmoredReader::new(data)) {
-        let mut reader = decryptor
-            .decrypt(age_identities.iter().copied())
-            .map_err(map_decrypt_error)?;
-        reader.read_to_end(&mut decrypted).map_err(age_error)?;
-    } else {
-        // Fall back to binary format
-        let decryptor = age::Decryptor::new(data).map_err(age_error)?;
-        let mut reader = decryptor
-            .decrypt(age_identities.iter().copied())
-            .map_err(map_decrypt_error)?;
-        reader.read_to_end(&mut decrypted).map_err(age_error)?;
+    // The armored reader passes binary data through unchanged
+    let decryptor = age::Decryptor::new_buffered(age::armor::ArmoredReader::new(
+        std::io::BufReader::new(input),
+    ))
+    .map_err(map_decrypt_error)?;
+    let mut reader = decryptor
+        .decrypt(identities.iter().map(Identity::as_dyn_identity))
+        .map_err(map_decrypt_error)?;
+
+    // Plaintext chunks fail to read when the data is corrupted, so read
+    // errors are reported as age errors and write errors as I/O errors
+    let mut buffer = vec![0; 64 * 1024];
+    let mut written = 0;
+    loop {
+        let read = reader.read(&mut buffer).map_err(age_error)?;
+        if read == 0 {
+            return Ok(written);
+        }
+        output.write_all(&buffer[..read])?;
+        written += read as u64;
     }
+}
 
-    Ok(decrypted)
+/// Result of [`decrypt_with_hint`]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Decrypted {
+    /// The decrypted plaintext
+    pub plaintext: Vec<u8>,
+    /// Index of the identity that decrypted the data
+    pub identity: usize,
+}
+
+/// Decrypt data, picking the identity from the age header
+///
+/// Only identities that could unwrap one of the header's recipient stanzas are
+/// tried (see [`candidate_identities`](crate::header::candidate_identities)),
+/// starting with the one whose [`Identity::fingerprint`] equals `hint`. Callers
+/// can remember the returned identity index to pass as the hint next time.
+///
+/// # Errors
+///
+/// - Returns [`Error::NoIdentity`] if the identities slice is empty
+/// - Returns [`Error::WrongKey`] if no identity can decrypt the data
+/// - Returns [`Error::DecryptionFailed`] or [`Error::Age`] if the data is malformed
+///
+/// # Examples
+///
+/// ```no_run
+/// use guisu_crypto::{encrypt, decrypt_with_hint, Identity};
+///
+/// let personal = Identity::generate();
+/// let work = Identity::generate();
+///
+/// let encrypted = encrypt(b"secret", &[work.to_public()]).unwrap();
+/// let identities = [personal, work];
+/// let decrypted = decrypt_with_hint(&encrypted, &identities, None).unwrap();
+/// assert_eq!(decrypted.identity, 1);
+///
+/// // Next time, try the work identity first
+/// let hint = identities[decrypted.identity].fingerprint();
+/// decrypt_with_hint(&encrypted, &identities, Some(&hint)).unwrap();
+/// ```
+pub fn decrypt_with_hint(
+    data: &[u8],
+    identities: &[Identity],
+    hint: Option<&str>,
+) -> Result<Decrypted> {
+    if identities.is_empty() {
+        return Err(Error::NoIdentity);
+    }
+
+    for index in crate::header::candidate_identities(data, identities, hint) {
+        match decrypt_single(data, &identities[index]) {
+            Ok(plaintext) => {
+                return Ok(Decrypted {
+                    plaintext,
+                    identity: index,
+                });
+            }
+            Err(Error::WrongKey) => {}
+            Err(e) => return Err(e),
+        }
+    }
+
+    Err(Error::WrongKey)
 }
 
 /// Optimized decryption for single identity (avoids vec allocation)
@@ -1013,6 +1171,91 @@ mod tests {
         assert_eq!(plaintext, decrypted.as_slice());
     }
 
+    #[test]
+    fn test_decrypt_with_hint_reports_identity() {
+        let id1 = test_identity();
+        let id2 = test_identity();
+        let encrypted = encrypt(b"hinted", &[id2.to_public()]).expect("Encryption failed");
+        let identities = [id1, id2];
+
+        let decrypted =
+            decrypt_with_hint(&encrypted, &identities, None).expect("Decryption failed");
+        assert_eq!(decrypted.plaintext, b"hinted");
+        assert_eq!(decrypted.identity, 1);
+
+        // A stale hint is only an ordering preference
+        let stale = identities[0].fingerprint();
+        let decrypted =
+            decrypt_with_hint(&encrypted, &identities, Some(&stale)).expect("Decryption failed");
+        assert_eq!(decrypted.identity, 1);
+    }
+
+    #[test]
+    fn test_decrypt_with_hint_wrong_keys() {
+        let encrypted =
+            encrypt(b"secret", &[test_identity().to_public()]).expect("Encryption failed");
+
+        let result = decrypt_with_hint(&encrypted, &[test_identity(), test_identity()], None);
+        assert!(matches!(result, Err(Error::WrongKey)));
+    }
+
+    #[test]
+    fn test_binary_format_and_conversion() {
+        let identity = test_identity();
+        let recipient = identity.to_public();
+
+        let binary = encrypt_with_armor(b"secret", &[recipient], false).expect("Encryption failed");
+        assert!(binary.starts_with(BINARY_MAGIC));
+        assert!(!is_armored(&binary));
+        assert_eq!(
+            decrypt(&binary, std::slice::from_ref(&identity)).unwrap(),
+            b"secret"
+        );
+
+        let armored = convert_armor(&binary, true).unwrap();
+        assert!(is_armored(&armored));
+        assert_eq!(
+            decrypt(&armored, std::slice::from_ref(&identity)).unwrap(),
+            b"secret"
+        );
+        assert_eq!(convert_armor(&armored, true).unwrap(), armored);
+        assert_eq!(convert_armor(&armored, false).unwrap(), binary);
+
+        assert!(convert_armor(b"plain text", true).is_err());
+    }
+
+    #[test]
+    fn test_decrypt_stream() {
+        let identity = test_identity();
+        let recipient = identity.to_public();
+        // Larger than one age chunk (64 KiB)
+        let plaintext: Vec<u8> = (0..200_000u32).map(|i| (i % 251) as u8).collect();
+
+        for armor in [true, false] {
+            let encrypted = encrypt_with_armor(&plaintext, std::slice::from_ref(&recipient), armor)
+                .expect("Encryption failed");
+            let mut decrypted = Vec::new();
+            let written = decrypt_stream(
+                encrypted.as_slice(),
+                &mut decrypted,
+                &[test_identity(), identity.clone()],
+            )
+            .unwrap();
+            assert_eq!(written, plaintext.len() as u64);
+            assert_eq!(decrypted, plaintext);
+        }
+
+        let encrypted = encrypt(b"secret", &[recipient]).expect("Encryption failed");
+        assert!(matches!(
+            decrypt_stream(encrypted.as_slice(), &mut Vec::new(), &[]),
+            Err(Error::NoIdentity)
+        ));
+        assert!(matches!(
+            decrypt_stream(encrypted.as_slice(), &mut Vec::new(), &[test_identity()]),
+            Err(Error::WrongKey)
+        ));
+    }
+
     #[test]
     fn test_armor_format_detection() {
         let identity = test_identity();
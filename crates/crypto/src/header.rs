@@ -0,0 +1,256 @@
+//! Age header inspection
+//!
+//! Reads the recipient stanzas from an age file header without decrypting it.
+//! SSH stanzas carry a tag derived from the recipient's public key, so the
+//! identity that can unwrap the file key is known up front. X25519 stanzas only
+//! carry an ephemeral share, which narrows the candidates to native age
+//! identities but not to a single one.
+
+use crate::identity::Identity;
+use base64::Engine;
+use base64::engine::general_purpose::{STANDARD, STANDARD_NO_PAD};
+use sha2::{Digest, Sha256};
+
+/// First line of every binary age file
+const VERSION_LINE: &str = "age-encryption.org/v1";
+/// Armor begin marker
+const ARMOR_BEGIN: &str = "-----BEGIN AGE ENCRYPTED FILE-----";
+/// Armor end marker
+const ARMOR_END: &str = "-----END AGE ENCRYPTED FILE-----";
+/// Stanza type for native age recipients
+const X25519_STANZA: &str = "X25519";
+
+/// A single recipient stanza from an age header
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct HeaderStanza {
+    /// Stanza type (`X25519`, `ssh-ed25519`, `ssh-rsa`, ...)
+    pub kind: String,
+    /// Stanza arguments following the type
+    pub args: Vec<String>,
+}
+
+/// Recipient stanzas of an age file
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AgeHeader {
+    stanzas: Vec<HeaderStanza>,
+}
+
+impl AgeHeader {
+    /// Parse the header of an armored or binary age file
+    ///
+    /// Returns `None` if the data does not start with a well-formed age header.
+    #[must_use]
+    pub fn parse(data: &[u8]) -> Option<Self> {
+        let trimmed = data.trim_ascii_start();
+        if trimmed.starts_with(ARMOR_BEGIN.as_bytes()) {
+            Self::parse_binary(&dearmor_header(trimmed)?)
+        } else {
+            Self::parse_binary(data)
+        }
+    }
+
+    fn parse_binary(data: &[u8]) -> Option<Self> {
+        let mut lines = data.split(|&b| b == b'\n');
+        if lines.next()? != VERSION_LINE.as_bytes() {
+            return None;
+        }
+
+        let mut stanzas = Vec::new();
+        for line in lines {
+            let line = std::str::from_utf8(line).ok()?;
+            if line.starts_with("---") {
+                return Some(Self { stanzas });
+            }
+            // Lines without the stanza prefix are wrapped stanza bodies
+            if let Some(stanza) = line.strip_prefix("-> ") {
+                let mut parts = stanza.split(' ');
+                let kind = parts.next()?.to_string();
+                stanzas.push(HeaderStanza {
+                    kind,
+                    args: parts.map(str::to_string).collect(),
+                });
+            }
+        }
+
+        // Header never terminated
+        None
+    }
+
+    /// Get the recipient stanzas in header order
+    #[must_use]
+    pub fn stanzas(&self) -> &[HeaderStanza] {
+        &self.stanzas
+    }
+
+    /// Check whether an identity could unwrap one of the stanzas
+    ///
+    /// Exact for SSH identities. Native age identities match any X25519 stanza,
+    /// since those do not identify their recipient.
+    #[must_use]
+    pub fn may_unwrap(&self, identity: &Identity) -> bool {
+        match identity {
+            Identity::Age(_) => self.stanzas.iter().any(|s| s.kind == X25519_STANZA),
+            Identity::Ssh { recipient, .. } => {
+                let Some((kind, tag)) = ssh_stanza_tag(&recipient.to_string()) else {
+                    return true;
+                };
+                self.stanzas
+                    .iter()
+                    .any(|s| s.kind == kind && s.args.first() == Some(&tag))
+            }
+        }
+    }
+}
+
+/// Decode enough of an armored file to cover the header
+///
+/// Armor lines are 64 characters (48 bytes), so each full line decodes on its
+/// own and decoding can stop as soon as the header's MAC line has been seen.
+fn dearmor_header(data: &[u8]) -> Option<Vec<u8>> {
+    let text = std::str::from_utf8(data).ok()?;
+    let mut decoded = Vec::new();
+
+    for line in text.lines().skip(1) {
+        let line = line.trim();
+        if line == ARMOR_END {
+            break;
+        }
+        decoded.extend(STANDARD.decode(line).ok()?);
+        if decoded.windows(4).any(|w| w == b"\n---") {
+            break;
+        }
+    }
+
+    Some(decoded)
+}
+
+/// Compute the stanza type and key tag for an SSH recipient string
+///
+/// The tag is the first four bytes of the SHA-256 of the SSH wire-format
+/// public key, base64 encoded without padding.
+fn ssh_stanza_tag(recipient: &str) -> Option<(String, String)> {
+    let mut parts = recipient.split_whitespace();
+    let kind = parts.next()?;
+    let key = STANDARD.decode(parts.next()?).ok()?;
+    let digest = Sha256::digest(&key);
+    Some((kind.to_string(), STANDARD_NO_PAD.encode(&digest[..4])))
+}
+
+/// Order identities by how likely they are to decrypt `data`
+///
+/// Returns indices into `identities`: the identity whose fingerprint matches
+/// `hint` first (when it could unwrap the header), then every other identity
+/// that could. Identities that cannot match any stanza are left out. When the
+/// header cannot be parsed, all identities are returned in their original
+/// order so age can report the real error.
+#[must_use]
+pub fn candidate_identities(
+    data: &[u8],
+    identities: &[Identity],
+    hint: Option<&str>,
+) -> Vec<usize> {
+    let header = AgeHeader::parse(data);
+    let mut candidates: Vec<usize> = (0..identities.len())
+        .filter(|&i| header.as_ref().is_none_or(|h| h.may_unwrap(&identities[i])))
+        .collect();
+
+    if let Some(hint) = hint
+        && let Some(pos) = candidates
+            .iter()
+            .position(|&i| identities[i].fingerprint() == hint)
+    {
+        let hinted = candidates.remove(pos);
+        candidates.insert(0, hinted);
+    }
+
+    candidates
+}
+
+#[cfg(test)]
+mod tests {
+    #![allow(clippy::unwrap_used, clippy::panic)]
+    use super::*;
+    use crate::encrypt;
+
+    #[test]
+    fn test_parse_armored_header() {
+        let identity = Identity::generate();
+        let encrypted = encrypt(b"secret", &[identity.to_public()]).unwrap();
+
+        // age may add random "grease" stanzas next to the real one
+        let header = AgeHeader::parse(&encrypted).unwrap();
+        let x25519 = header.stanzas().iter().filter(|s| s.kind == "X25519");
+        assert_eq!(x25519.count(), 1);
+        assert!(header.may_unwrap(&identity));
+    }
+
+    #[test]
+    fn test_parse_binary_header() {
+        let data = b"age-encryption.org/v1\n-> X25519 abc\nYm9keQ\n-> ssh-ed25519 tag1 share\nYm9keQ\n--- mac\npayload";
+
+        let header = AgeHeader::parse(data).unwrap();
+        assert_eq!(
+            header.stanzas(),
+            &[
+                HeaderStanza {
+                    kind: "X25519".to_string(),
+                    args: vec!["abc".to_string()],
+                },
+                HeaderStanza {
+                    kind: "ssh-ed25519".to_string(),
+                    args: vec!["tag1".to_string(), "share".to_string()],
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_parse_rejects_non_age_data() {
+        assert!(AgeHeader::parse(b"plain text").is_none());
+        assert!(AgeHeader::parse(b"age-encryption.org/v1\n-> X25519 abc\n").is_none());
+    }
+
+    #[test]
+    fn test_ssh_stanza_tag_matches_encrypted_header() {
+        let recipient =
+            "ssh-ed25519 AAAAC3NzaC1lZDI1NTE5AAAAIHsKLqeplhpW+uObz5dvMgjz1OxfM/XXUB+VHtZ6isGN";
+        let encrypted = encrypt(b"secret", &[recipient.parse().unwrap()]).unwrap();
+
+        let header = AgeHeader::parse(&encrypted).unwrap();
+        let (kind, tag) = ssh_stanza_tag(recipient).unwrap();
+        let stanza = header.stanzas().iter().find(|s| s.kind == kind).unwrap();
+        assert_eq!(stanza.args[0], tag);
+    }
+
+    #[test]
+    fn test_candidates_skip_ssh_identity_without_stanza() {
+        let data = b"age-encryption.org/v1\n-> ssh-ed25519 AAAAAA share\nYm9keQ\n--- mac\n";
+        let age_identity = Identity::generate();
+
+        assert_eq!(
+            candidate_identities(data, &[age_identity], None),
+            Vec::<usize>::new()
+        );
+    }
+
+    #[test]
+    fn test_candidates_put_hint_first() {
+        let first = Identity::generate();
+        let second = Identity::generate();
+        let encrypted = encrypt(b"secret", &[second.to_public()]).unwrap();
+        let identities = [first, second];
+
+        assert_eq!(candidate_identities(&encrypted, &identities, None), [0, 1]);
+        let hint = identities[1].fingerprint();
+        assert_eq!(
+            candidate_identities(&encrypted, &identities, Some(&hint)),
+            [1, 0]
+        );
+    }
+
+    #[test]
+    fn test_candidates_fall_back_to_all_for_unparseable_data() {
+        let identities = [Identity::generate(), Identity::generate()];
+        assert_eq!(candidate_identities(b"garbage", &identities, None), [0, 1]);
+    }
+}
@@ -7,7 +7,7 @@ use age::secrecy::ExposeSecret;
 use age::x25519;
 use std::fmt;
 use std::fs;
-use std::io::{BufRead, BufReader, Write};
+use std::io::{BufReader, Write};
 use std::path::Path;
 use std::str::FromStr;
 use tracing::warn;
@@ -53,6 +53,15 @@ impl Identity {
         }
     }
 
+    /// Get a stable, non-secret fingerprint for this identity
+    ///
+    /// This is the public key string, so it can be persisted to identify which
+    /// identity decrypted a file without storing key material.
+    #[must_use]
+    pub fn fingerprint(&self) -> String {
+        self.to_public().to_string()
+    }
+
     /// Get a reference to the inner identity as a trait object
     pub(crate) fn as_dyn_identity(&self) -> &dyn age::Identity {
         match self {
@@ -108,60 +117,43 @@ impl IdentityFile {
     pub fn load<P: AsRef<Path>>(path: P) -> Result<Self> {
         let path_ref = path.as_ref();
         let path_str = path_ref.to_string_lossy().to_string();
-
-        let file = fs::File::open(path_ref).map_err(|e| {
-            if e.kind() == std::io::ErrorKind::NotFound {
-                Error::IdentityNotFound {
-                    path: path_str.clone(),
-                }
-            } else {
-                Error::IdentityFile {
-                    operation: "read".to_string(),
-                    path: path_str.clone(),
-                    source: e,
-                }
-            }
-        })?;
-
-        let reader = BufReader::new(file);
-        let mut identities = Vec::new();
-        let mut line_num = 0;
-
-        for line in reader.lines() {
-            line_num += 1;
-
-            let line = line.map_err(|e| Error::IdentityFile {
+        let content = read_identity_file(path_ref, &path_str)?;
+
+        let identities = if crate::is_encrypted_identity(&content) {
+            crate::passphrase::unlock(&path_str, &content, |text| {
+                parse_identities(text, &path_str)
+            })?
+        } else {
+            let text = String::from_utf8(content).map_err(|e| Error::IdentityFile {
                 operation: "read".to_string(),
                 path: path_str.clone(),
-                source: e,
+                source: std::io::Error::new(std::io::ErrorKind::InvalidData, e),
             })?;
+            parse_identities(&text, &path_str)?
+        };
 
-            let line = line.trim();
-
-            // Skip empty lines and comments
-            if line.is_empty() || line.starts_with('#') {
-                continue;
-            }
+        Ok(Self {
+            path: path_str,
+            identities,
+        })
+    }
 
-            // Try to parse as an identity
-            match Identity::from_str(line) {
-                Ok(identity) => identities.push(identity),
-                Err(e) => {
-                    // Warn about invalid lines - could indicate configuration errors
-                    warn!(
-                        "Skipping invalid identity on line {} in {}: {}",
-                        line_num, path_str, e
-                    );
-                }
-            }
-        }
+    /// Load identities from a passphrase-protected file
+    ///
+    /// # Errors
+    ///
+    /// Returns error if the file cannot be read, the passphrase is incorrect,
+    /// or the decrypted content contains no valid identities
+    pub fn load_with_passphrase<P: AsRef<Path>>(
+        path: P,
+        passphrase: &age::secrecy::SecretString,
+    ) -> Result<Self> {
+        let path_ref = path.as_ref();
+        let path_str = path_ref.to_string_lossy().to_string();
+        let content = read_identity_file(path_ref, &path_str)?;
 
-        if identities.is_empty() {
-            return Err(Error::InvalidIdentity {
-                reason: "No valid identities found in file".to_string(),
-                path: path_str,
-            });
-        }
+        let text = crate::passphrase::decrypt_with_passphrase(&content, passphrase, &path_str)?;
+        let identities = parse_identities(&text, &path_str)?;
 
         Ok(Self {
             path: path_str,
@@ -177,64 +169,26 @@ impl IdentityFile {
     ///
     /// Returns error if file cannot be created or written to
     pub fn save<P: AsRef<Path>>(path: P, identities: &[Identity]) -> Result<()> {
-        use chrono::Utc;
-        use std::fmt::Write as _;
-
-        let path_ref = path.as_ref();
-        let path_str = path_ref.to_string_lossy().to_string();
-
-        let mut content = String::new();
-
-        // Add creation timestamp in RFC3339 format (UTC for consistency across machines)
-        let now = Utc::now();
-        writeln!(content, "# created: {}", now.to_rfc3339())
-            .expect("writing to String cannot fail");
-
-        // Add public key(s) and secret key(s)
-        for identity in identities {
-            let public_key = identity.to_public();
-            writeln!(content, "# public key: {public_key}").expect("writing to String cannot fail");
-            content.push_str(&identity.to_string());
-            content.push('\n');
-        }
-
-        // On Unix, create file with restricted permissions atomically to avoid race condition
-        #[cfg(unix)]
-        {
-            use std::io::Write;
-            use std::os::unix::fs::OpenOptionsExt;
-
-            let mut file = fs::OpenOptions::new()
-                .write(true)
-                .create(true)
-                .truncate(true)
-                .mode(0o600) // Set permissions on creation - no race condition
-                .open(path_ref)
-                .map_err(|e| Error::IdentityFile {
-                    operation: "write".to_string(),
-                    path: path_str.clone(),
-                    source: e,
-                })?;
-
-            file.write_all(content.as_bytes())
-                .map_err(|e| Error::IdentityFile {
-                    operation: "write".to_string(),
-                    path: path_str,
-                    source: e,
-                })?;
-        }
-
-        // On non-Unix platforms, use regular write (no permission restrictions)
-        #[cfg(not(unix))]
-        {
-            fs::write(path_ref, content).map_err(|e| Error::IdentityFile {
-                operation: "write".to_string(),
-                path: path_str,
-                source: e,
-            })?;
-        }
+        write_private(path.as_ref(), render_identities(identities).as_bytes())
+    }
 
-        Ok(())
+    /// Save identities to a file encrypted with a passphrase
+    ///
+    /// The file is ASCII-armored age, the same format as `age -p`.
+    ///
+    /// # Errors
+    ///
+    /// Returns error if encryption fails or the file cannot be written
+    pub fn save_with_passphrase<P: AsRef<Path>>(
+        path: P,
+        identities: &[Identity],
+        passphrase: &age::secrecy::SecretString,
+    ) -> Result<()> {
+        let encrypted = crate::passphrase::encrypt_with_passphrase(
+            render_identities(identities).as_bytes(),
+            passphrase,
+        )?;
+        write_private(path.as_ref(), &encrypted)
     }
 
     /// Get the path to the identity file
@@ -308,6 +262,127 @@ impl IdentityFile {
     }
 }
 
+/// Read an identity file, mapping a missing file to [`Error::IdentityNotFound`]
+fn read_identity_file(path: &Path, path_str: &str) -> Result<Vec<u8>> {
+    fs::read(path).map_err(|e| {
+        if e.kind() == std::io::ErrorKind::NotFound {
+            Error::IdentityNotFound {
+                path: path_str.to_string(),
+            }
+        } else {
+            Error::IdentityFile {
+                operation: "read".to_string(),
+                path: path_str.to_string(),
+                source: e,
+            }
+        }
+    })
+}
+
+/// Parse identity file content, one identity per line
+///
+/// Empty lines and `#` comments are skipped; invalid lines are skipped with a
+/// warning.
+fn parse_identities(text: &str, path_str: &str) -> Result<Vec<Identity>> {
+    let mut identities = Vec::new();
+
+    for (index, line) in text.lines().enumerate() {
+        let line = line.trim();
+
+        // Skip empty lines and comments
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+
+        // Try to parse as an identity
+        match Identity::from_str(line) {
+            Ok(identity) => identities.push(identity),
+            Err(e) => {
+                // Warn about invalid lines - could indicate configuration errors
+                warn!(
+                    "Skipping invalid identity on line {} in {}: {}",
+                    index + 1,
+                    path_str,
+                    e
+                );
+            }
+        }
+    }
+
+    if identities.is_empty() {
+        return Err(Error::InvalidIdentity {
+            reason: "No valid identities found in file".to_string(),
+            path: path_str.to_string(),
+        });
+    }
+
+    Ok(identities)
+}
+
+/// Identity file content: a creation timestamp, then each public key comment
+/// and secret key
+fn render_identities(identities: &[Identity]) -> String {
+    use chrono::Utc;
+    use std::fmt::Write as _;
+
+    let mut content = String::new();
+
+    // Add creation timestamp in RFC3339 format (UTC for consistency across machines)
+    let now = Utc::now();
+    writeln!(content, "# created: {}", now.to_rfc3339()).expect("writing to String cannot fail");
+
+    // Add public key(s) and secret key(s)
+    for identity in identities {
+        let public_key = identity.to_public();
+        writeln!(content, "# public key: {public_key}").expect("writing to String cannot fail");
+        content.push_str(&identity.to_string());
+        content.push('\n');
+    }
+
+    content
+}
+
+/// Write an identity file, readable only by the owner on Unix
+fn write_private(path: &Path, content: &[u8]) -> Result<()> {
+    let path_str = path.to_string_lossy().to_string();
+
+    // On Unix, create file with restricted permissions atomically to avoid race condition
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::OpenOptionsExt;
+
+        let mut file = fs::OpenOptions::new()
+            .write(true)
+            .create(true)
+            .truncate(true)
+            .mode(0o600) // Set permissions on creation - no race condition
+            .open(path)
+            .map_err(|e| Error::IdentityFile {
+                operation: "write".to_string(),
+                path: path_str.clone(),
+                source: e,
+            })?;
+
+        file.write_all(content).map_err(|e| Error::IdentityFile {
+            operation: "write".to_string(),
+            path: path_str,
+            source: e,
+        })?;
+    }
+
+    // On non-Unix platforms, use regular write (no permission restrictions)
+    #[cfg(not(unix))]
+    {
+        fs::write(path, content).map_err(|e| Error::IdentityFile {
+            operation: "write".to_string(),
+            path: path_str,
+            source: e,
+        })?;
+    }
+
+    Ok(())
+}
+
 /// Load identities from a file (supports both age and SSH keys)
 ///
 /// # Arguments
@@ -1115,4 +1190,72 @@ mod tests {
         // Should start with correct prefix
         assert!(display1.starts_with("AGE-SECRET-KEY-"));
     }
+
+    /// Write identities encrypted with a tiny scrypt work factor so tests stay fast
+    fn save_encrypted_fast(path: &Path, identities: &[Identity], passphrase: &str) {
+        let mut recipient =
+            age::scrypt::Recipient::new(age::secrecy::SecretString::from(passphrase));
+        recipient.set_work_factor(2);
+        let encrypted = crate::passphrase::encrypt_to_scrypt(
+            render_identities(identities).as_bytes(),
+            &recipient,
+        )
+        .unwrap();
+        fs::write(path, encrypted).unwrap();
+    }
+
+    #[test]
+    fn test_identity_file_load_with_passphrase() {
+        let temp_file = NamedTempFile::new().unwrap();
+        let identity = Identity::generate();
+        save_encrypted_fast(temp_file.path(), std::slice::from_ref(&identity), "hunter2");
+
+        let passphrase = age::secrecy::SecretString::from("hunter2");
+        let loaded = IdentityFile::load_with_passphrase(temp_file.path(), &passphrase).unwrap();
+
+        assert_eq!(loaded.identities().len(), 1);
+        assert_eq!(loaded.identities()[0].fingerprint(), identity.fingerprint());
+    }
+
+    #[test]
+    fn test_identity_file_load_with_wrong_passphrase() {
+        let temp_file = NamedTempFile::new().unwrap();
+        save_encrypted_fast(temp_file.path(), &[Identity::generate()], "hunter2");
+
+        let passphrase = age::secrecy::SecretString::from("wrong");
+        let result = IdentityFile::load_with_passphrase(temp_file.path(), &passphrase);
+
+        assert!(matches!(result, Err(Error::WrongPassphrase { .. })));
+    }
+
+    #[test]
+    fn test_identity_file_load_encrypted_without_prompt() {
+        let temp_file = NamedTempFile::new().unwrap();
+        save_encrypted_fast(temp_file.path(), &[Identity::generate()], "hunter2");
+
+        // No prompt is registered in tests, so the passphrase cannot be asked for
+        let result = IdentityFile::load(temp_file.path());
+
+        assert!(matches!(result, Err(Error::PassphraseRequired { .. })));
+    }
+
+    #[test]
+    fn test_identity_file_save_with_passphrase_is_encrypted() {
+        let temp_file = NamedTempFile::new().unwrap();
+        let identity = Identity::generate();
+        let passphrase = age::secrecy::SecretString::from("hunter2");
+
+        IdentityFile::save_with_passphrase(
+            temp_file.path(),
+            std::slice::from_ref(&identity),
+            &passphrase,
+        )
+        .unwrap();
+
+        let content = fs::read(temp_file.path()).unwrap();
+        assert!(crate::is_encrypted_identity(&content));
+        assert!(!String::from_utf8_lossy(&content).contains("AGE-SECRET-KEY-"));
+        let loaded = IdentityFile::load_with_passphrase(temp_file.path(), &passphrase).unwrap();
+        assert_eq!(loaded.identities()[0].fingerprint(), identity.fingerprint());
+    }
 }
@@ -6,14 +6,22 @@
 //! using the age encryption format with identity-based keys.
 
 pub mod age;
+pub mod header;
 pub mod identity;
+pub mod passphrase;
 pub mod recipient;
 
+pub use ::age::secrecy::SecretString;
 pub use age::{
-    decrypt, decrypt_file_content, decrypt_inline, decrypt_string, encrypt, encrypt_file_content,
-    encrypt_inline, encrypt_string,
+    Decrypted, convert_armor, decrypt, decrypt_file_content, decrypt_inline, decrypt_stream,
+    decrypt_string, decrypt_with_hint, encrypt, encrypt_file_content, encrypt_inline,
+    encrypt_string, encrypt_with_armor, is_armored,
 };
+pub use header::{AgeHeader, candidate_identities};
 pub use identity::{Identity, IdentityFile, load_identities};
+pub use passphrase::{
+    PassphraseCache, is_encrypted_identity, set_passphrase_cache, set_passphrase_prompt,
+};
 pub use recipient::Recipient;
 
 /// Convert a slice of identities to their corresponding recipients (public keys)
@@ -107,9 +115,11 @@ pub enum Error {
          \n\
          [age]\n\
          recipient = \"age1ql3z7hjy54pw3hyww5ayyfg7zqgvc7w3j2elw8zmrj2kg5sfn9aqmcac8p\"\n\
+         # or an SSH public key:\n\
+         # recipient = \"ssh-ed25519 AAAAC3NzaC1lZDI1NTE5...\"\n\
          \n\
-         2. Or generate a recipient from your identity:\n\
-            guisu age generate --show-recipient"
+         2. Or show the recipient of your identity:\n\
+            guisu age show"
     )]
     NoRecipients,
 
@@ -170,16 +180,38 @@ pub enum Error {
         path: String,
     },
 
+    /// Encrypted identity file could not be unlocked without a passphrase
+    #[error(
+        "Identity file is protected by a passphrase: {path}\n\
+         \n\
+         To fix this:\n\
+         1. Run guisu from a terminal to be prompted for the passphrase\n\
+         2. Or use an identity file without a passphrase"
+    )]
+    PassphraseRequired {
+        /// Path to the encrypted identity file
+        path: String,
+    },
+
+    /// Incorrect passphrase for an encrypted identity file
+    #[error("Incorrect passphrase for identity file: {path}")]
+    WrongPassphrase {
+        /// Path to the encrypted identity file
+        path: String,
+    },
+
     /// Invalid recipient format
     #[error(
         "Invalid recipient: {recipient}\n\
          Reason: {reason}\n\
          \n\
-         Expected format: age1ql3z7hjy54pw3hyww5ayyfg7zqgvc7w3j2elw8zmrj2kg5sfn9aqmcac8p\n\
+         Expected formats:\n\
+         - age recipient:   age1ql3z7hjy54pw3hyww5ayyfg7zqgvc7w3j2elw8zmrj2kg5sfn9aqmcac8p\n\
+         - SSH public key:  ssh-ed25519 AAAAC3NzaC1lZDI1NTE5... (or ssh-rsa)\n\
          \n\
          To fix this:\n\
-         1. Get recipient from identity:  guisu age generate --show-recipient\n\
-         2. Or from public key file:      cat ~/.config/guisu/key.txt.pub\n\
+         1. Get your age recipient:     guisu age show\n\
+         2. Or use an SSH public key:   cat ~/.ssh/id_ed25519.pub\n\
          3. Check the recipient string carefully"
     )]
     InvalidRecipient {
@@ -68,22 +68,49 @@ impl FromStr for Recipient {
 
     /// Parse a recipient from a string
     ///
-    /// This will try to parse as an age recipient first, then as an SSH recipient.
+    /// Accepts native age recipients (`age1...`) and SSH public keys
+    /// (`ssh-ed25519 ...`, `ssh-rsa ...`, as found in `~/.ssh/*.pub`, with or
+    /// without a trailing comment).
     fn from_str(s: &str) -> crate::Result<Self> {
+        let invalid = |reason: String| crate::Error::InvalidRecipient {
+            recipient: s.to_string(),
+            reason,
+        };
+
         // Try parsing as age recipient first (starts with "age1")
         if let Ok(recipient) = s.parse::<x25519::Recipient>() {
             return Ok(Self::Age(recipient));
         }
 
         // Try parsing as SSH recipient
-        if let Ok(recipient) = s.parse::<ssh::Recipient>() {
-            return Ok(Self::Ssh(recipient));
+        match s.parse::<ssh::Recipient>() {
+            Ok(recipient) => Ok(Self::Ssh(recipient)),
+            Err(_) if s.starts_with("age1") => Err(invalid(
+                "Malformed age1... recipient (check for truncation or typos)".to_string(),
+            )),
+            Err(ssh::ParseRecipientKeyError::Unsupported(key_type)) => Err(invalid(format!(
+                "Unsupported SSH key type {key_type}; only ssh-ed25519 and ssh-rsa keys can be recipients"
+            ))),
+            Err(ssh::ParseRecipientKeyError::Ignore) => Err(invalid(
+                "Unsupported SSH key type; only ssh-ed25519 and ssh-rsa keys can be recipients"
+                    .to_string(),
+            )),
+            Err(ssh::ParseRecipientKeyError::RsaModulusTooSmall) => {
+                Err(invalid("ssh-rsa key is smaller than 2048 bits".to_string()))
+            }
+            Err(ssh::ParseRecipientKeyError::RsaModulusTooLarge) => {
+                Err(invalid("ssh-rsa key is larger than supported".to_string()))
+            }
+            Err(ssh::ParseRecipientKeyError::Invalid(reason)) if s.starts_with("ssh-") => {
+                let key_type = s.split_whitespace().next().unwrap_or(s);
+                Err(invalid(format!(
+                    "Malformed {key_type} public key: {reason}"
+                )))
+            }
+            Err(_) => Err(invalid(
+                "Expected age1... or an ssh-ed25519/ssh-rsa public key".to_string(),
+            )),
         }
-
-        Err(crate::Error::InvalidRecipient {
-            recipient: s.to_string(),
-            reason: "Expected age1... or ssh-... format".to_string(),
-        })
     }
 }
 
@@ -1,8 +1,12 @@
 //! Crypto adapter that implements the Decryptor trait from engine
 
 use crate::content::Decryptor;
+use crate::database;
+use crate::state::RedbPersistentState;
 use guisu_crypto::Identity;
-use std::sync::Arc;
+use std::collections::HashMap;
+use std::io::{Read, Write};
+use std::sync::{Arc, Mutex, PoisonError};
 use thiserror::Error;
 
 /// Error type for crypto adapter
@@ -13,28 +17,102 @@ pub enum CryptoError {
     Crypto(#[from] guisu_crypto::Error),
 }
 
+/// Remembers which identity decrypted each encrypted file
+///
+/// With several identities configured, the hint lets the next run try the
+/// right one first. Hints are loaded from the database once; mappings learned
+/// during the run stay in memory until [`IdentityHints::save`] writes them back
+/// in a single transaction.
+#[derive(Debug, Default)]
+pub struct IdentityHints {
+    known: HashMap<String, String>,
+    learned: Mutex<HashMap<String, String>>,
+}
+
+impl IdentityHints {
+    /// Load the hints stored in the database
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the hints cannot be read from the database
+    pub fn load(db: &RedbPersistentState) -> crate::Result<Self> {
+        Ok(Self {
+            known: database::get_identity_hints(db)?,
+            learned: Mutex::default(),
+        })
+    }
+
+    /// Get the fingerprint of the identity that last decrypted `path`
+    #[must_use]
+    pub fn get(&self, path: &str) -> Option<String> {
+        let learned = self.learned.lock().unwrap_or_else(PoisonError::into_inner);
+        learned.get(path).or_else(|| self.known.get(path)).cloned()
+    }
+
+    /// Record that the identity with `fingerprint` decrypted `path`
+    pub fn record(&self, path: &str, fingerprint: String) {
+        if self.known.get(path) == Some(&fingerprint) {
+            return;
+        }
+        self.learned
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .insert(path.to_string(), fingerprint);
+    }
+
+    /// Write mappings learned since loading back to the database
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the hints cannot be written
+    pub fn save(&self, db: &RedbPersistentState) -> crate::Result<()> {
+        let learned: Vec<(String, String)> = self
+            .learned
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .drain()
+            .collect();
+        database::save_identity_hints(db, &learned)
+    }
+}
+
 /// Adapter that wraps crypto functions to implement `engine::content::Decryptor`
+///
+/// Each file is decrypted with the identity picked from its age header, trying
+/// the identity recorded in [`IdentityHints`] first when hints are attached.
+#[derive(Clone)]
 pub struct CryptoDecryptorAdapter {
-    identity: Arc<Identity>,
+    identities: Arc<Vec<Identity>>,
+    hints: Option<Arc<IdentityHints>>,
 }
 
 impl CryptoDecryptorAdapter {
     /// Create a new crypto adapter with the given identity
     #[must_use]
     pub fn new(identity: Identity) -> Self {
-        Self::from_arc(Arc::new(identity))
+        Self::from_identities(Arc::new(vec![identity]))
+    }
+
+    /// Create a new crypto adapter from shared identities (zero-copy)
+    #[must_use]
+    pub fn from_identities(identities: Arc<Vec<Identity>>) -> Self {
+        Self {
+            identities,
+            hints: None,
+        }
     }
 
-    /// Create a new crypto adapter from an Arc<Identity> (zero-copy)
+    /// Attach identity hints to consult and update while decrypting files
     #[must_use]
-    pub fn from_arc(identity: Arc<Identity>) -> Self {
-        Self { identity }
+    pub fn with_hints(mut self, hints: Arc<IdentityHints>) -> Self {
+        self.hints = Some(hints);
+        self
     }
 
-    /// Get a reference to the identity
+    /// Get the identities used for decryption
     #[must_use]
-    pub fn identity(&self) -> &Identity {
-        &self.identity
+    pub fn identities(&self) -> &[Identity] {
+        &self.identities
     }
 }
 
@@ -42,19 +120,38 @@ impl Decryptor for CryptoDecryptorAdapter {
     type Error = CryptoError;
 
     fn decrypt(&self, encrypted: &[u8]) -> Result<Vec<u8>, Self::Error> {
-        guisu_crypto::decrypt(encrypted, &[self.identity.as_ref().clone()]).map_err(Into::into)
+        guisu_crypto::decrypt(encrypted, &self.identities).map_err(Into::into)
     }
 
-    fn decrypt_inline(&self, text: &str) -> Result<String, Self::Error> {
-        guisu_crypto::decrypt_inline(text, &[self.identity.as_ref().clone()]).map_err(Into::into)
-    }
-}
+    fn decrypt_file(&self, path: &str, encrypted: &[u8]) -> Result<Vec<u8>, Self::Error> {
+        let Some(hints) = &self.hints else {
+            return self.decrypt(encrypted);
+        };
 
-impl Clone for CryptoDecryptorAdapter {
-    fn clone(&self) -> Self {
-        Self {
-            identity: Arc::clone(&self.identity),
+        let hint = hints.get(path);
+        let decrypted =
+            guisu_crypto::decrypt_with_hint(encrypted, &self.identities, hint.as_deref())?;
+
+        let fingerprint = self.identities[decrypted.identity].fingerprint();
+        if hint.as_ref() != Some(&fingerprint) {
+            hints.record(path, fingerprint);
         }
+
+        Ok(decrypted.plaintext)
+    }
+
+    fn decrypt_stream(
+        &self,
+        _path: &str,
+        encrypted: &mut dyn Read,
+        output: &mut dyn Write,
+    ) -> Result<(), Self::Error> {
+        guisu_crypto::decrypt_stream(encrypted, output, &self.identities)?;
+        Ok(())
+    }
+
+    fn decrypt_inline(&self, text: &str) -> Result<String, Self::Error> {
+        guisu_crypto::decrypt_inline(text, &self.identities).map_err(Into::into)
     }
 }
 
@@ -71,18 +168,18 @@ mod tests {
         let adapter = CryptoDecryptorAdapter::new(identity.clone());
 
         // Verify identity is stored correctly
-        assert_eq!(adapter.identity().to_string(), identity.to_string());
+        assert_eq!(adapter.identities().len(), 1);
+        assert_eq!(adapter.identities()[0].to_string(), identity.to_string());
     }
 
     #[test]
-    fn test_crypto_adapter_from_arc() {
-        let identity = Identity::generate();
-        let arc_identity = Arc::new(identity.clone());
-        let adapter = CryptoDecryptorAdapter::from_arc(Arc::clone(&arc_identity));
+    fn test_crypto_adapter_from_identities() {
+        let identities = Arc::new(vec![Identity::generate(), Identity::generate()]);
+        let adapter = CryptoDecryptorAdapter::from_identities(Arc::clone(&identities));
 
         // Verify Arc is shared (same pointer)
-        assert_eq!(Arc::strong_count(&arc_identity), 2);
-        assert_eq!(adapter.identity().to_string(), identity.to_string());
+        assert_eq!(Arc::strong_count(&identities), 2);
+        assert_eq!(adapter.identities().len(), 2);
     }
 
     #[test]
@@ -148,11 +245,8 @@ mod tests {
         let adapter1 = CryptoDecryptorAdapter::new(identity.clone());
         let adapter2 = adapter1.clone();
 
-        // Verify both adapters share the same identity Arc
-        assert_eq!(
-            adapter1.identity().to_string(),
-            adapter2.identity().to_string()
-        );
+        // Verify both adapters share the same identities Arc
+        assert!(Arc::ptr_eq(&adapter1.identities, &adapter2.identities));
 
         // Test that both can decrypt
         let plaintext = b"test data";
@@ -199,4 +293,53 @@ mod tests {
             _ => panic!("Expected CryptoError::Crypto variant"),
         }
     }
+
+    #[test]
+    fn test_decrypt_with_any_configured_identity() {
+        let personal = Identity::generate();
+        let work = Identity::generate();
+        let encrypted = encrypt(b"work secret", &[work.to_public()]).expect("Encryption failed");
+
+        let adapter = CryptoDecryptorAdapter::from_identities(Arc::new(vec![personal, work]));
+        assert_eq!(adapter.decrypt(&encrypted).unwrap(), b"work secret");
+    }
+
+    #[test]
+    fn test_decrypt_file_records_and_persists_hints() {
+        let temp = tempfile::TempDir::new().unwrap();
+        let db = RedbPersistentState::new(temp.path().join("state.db")).unwrap();
+
+        let personal = Identity::generate();
+        let work = Identity::generate();
+        let work_fingerprint = work.fingerprint();
+        let encrypted = encrypt(b"work secret", &[work.to_public()]).expect("Encryption failed");
+        let identities = Arc::new(vec![personal, work]);
+
+        let hints = Arc::new(IdentityHints::load(&db).unwrap());
+        let adapter = CryptoDecryptorAdapter::from_identities(Arc::clone(&identities))
+            .with_hints(Arc::clone(&hints));
+
+        let decrypted = adapter.decrypt_file("/src/token.age", &encrypted).unwrap();
+        assert_eq!(decrypted, b"work secret");
+        assert_eq!(hints.get("/src/token.age"), Some(work_fingerprint.clone()));
+
+        hints.save(&db).unwrap();
+        let reloaded = IdentityHints::load(&db).unwrap();
+        assert_eq!(reloaded.get("/src/token.age"), Some(work_fingerprint));
+
+        // Nothing new learned when the stored hint is still correct
+        let adapter =
+            CryptoDecryptorAdapter::from_identities(identities).with_hints(Arc::new(reloaded));
+        adapter.decrypt_file("/src/token.age", &encrypted).unwrap();
+        assert!(
+            adapter
+                .hints
+                .as_ref()
+                .unwrap()
+                .learned
+                .lock()
+                .unwrap()
+                .is_empty()
+        );
+    }
 }
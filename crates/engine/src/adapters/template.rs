@@ -1,10 +1,42 @@
 //! Template adapter that implements the `TemplateRenderer` trait from engine
 
 use crate::content::TemplateRenderer;
+use guisu_config::Config;
 use guisu_template::{TemplateContext, TemplateEngine};
+use std::path::Path;
 use std::sync::Arc;
 use thiserror::Error;
 
+/// Build the context that file templates and hook templates are rendered with
+///
+/// Both go through this function so they see the same `guisu` info (source,
+/// working tree and destination directories, root entry, profile), system
+/// facts, environment, and variables from `.guisu/data/`, `.guisu/variables/`
+/// and the config (config overrides files).
+///
+/// # Errors
+///
+/// Returns an error if the variables cannot be loaded
+pub fn template_context(
+    source_dir: &Path,
+    dotfiles_dir: &Path,
+    dest_dir: &Path,
+    config: &Config,
+) -> crate::Result<TemplateContext> {
+    let working_tree =
+        crate::git::find_working_tree(source_dir).unwrap_or_else(|| source_dir.to_path_buf());
+
+    TemplateContext::new()
+        .with_guisu_info(
+            dotfiles_dir.display().to_string(),
+            working_tree.display().to_string(),
+            dest_dir.display().to_string(),
+            config.general.root_entry.display().to_string(),
+        )
+        .with_profile(config.general.profile.clone())
+        .with_loaded_variables(source_dir, config)
+}
+
 /// Error type for template adapter
 #[derive(Error, Debug)]
 pub enum TemplateError {
@@ -56,6 +88,10 @@ impl TemplateRenderer for TemplateRendererAdapter {
             .render_str(template, &template_context)
             .map_err(Into::into)
     }
+
+    fn prefetch(&self, templates: &[&str]) {
+        self.engine.prefetch_secrets(templates);
+    }
 }
 
 impl Clone for TemplateRendererAdapter {
@@ -305,4 +341,40 @@ mod tests {
             _ => panic!("Expected TemplateError::Template variant"),
         }
     }
+
+    #[test]
+    fn test_template_context_matches_file_templates() {
+        let temp = tempfile::TempDir::new().unwrap();
+        let source_dir = temp.path();
+        std::fs::create_dir_all(source_dir.join(".guisu/variables")).unwrap();
+        std::fs::write(
+            source_dir.join(".guisu/variables/git.toml"),
+            "email = \"me@example.com\"\n",
+        )
+        .unwrap();
+
+        let mut config = Config::default();
+        config.general.profile = Some("work".to_string());
+        config.variables.insert("name".to_string(), json!("Ada"));
+        let dest_dir = source_dir.join("dest");
+
+        let context = template_context(
+            source_dir,
+            &config.dotfiles_dir(source_dir),
+            &dest_dir,
+            &config,
+        )
+        .unwrap();
+        let rendered = TemplateEngine::new()
+            .render_str(
+                "{{ guisu.dstDir }}|{{ guisu.profile }}|{{ git.email }}|{{ name }}",
+                &context,
+            )
+            .unwrap();
+
+        assert_eq!(
+            rendered,
+            format!("{}|work|me@example.com|Ada", dest_dir.display())
+        );
+    }
 }
@@ -10,6 +10,23 @@
 //! - `.j2` - File is a Jinja2 template
 //! - `.age` - File is encrypted with age
 //! - `.j2.age` - Template that is encrypted (edit decrypts, render encrypts)
+//! - `run_` prefix - File is a script executed during apply, optionally
+//!   followed by `once_` or `onchange_`, then `before_` or `after_`
+//! - `mode_<octal>_` prefix - Explicit permissions, overriding those of the
+//!   source file (e.g. `mode_0640_sudoers`)
+//! - `owner_<name>_` / `group_<name>_` prefixes - Ownership applied when
+//!   running as root, see [`Ownership`]
+//! - `env_` prefix - Secret env file: always written with mode `0600`, shown
+//!   only by key name in diffs, and skipped by `cat` unless asked for. Must
+//!   come before any other prefix except `owner_`/`group_`
+//! - `compress_` prefix - Binary file stored zstd compressed (before any
+//!   encryption), decompressed when the target state is computed. Comes after
+//!   `mode_` and cannot be combined with `env_`, `run_`, or `.j2`
+//! - `symlink_` prefix - Symlink whose target is the file's content, which
+//!   may be a template (`.j2`) or encrypted. Comes first except for
+//!   `owner_`/`group_`, and cannot be combined with other prefixes
+//! - `exact_` prefix (directories only) - Anything in the destination
+//!   directory that the source does not have is removed on apply
 //! - File permissions (Unix):
 //!   - `0600` / `0700` - Private files/directories
 //!   - `0755` - Executable files
@@ -18,6 +35,12 @@
 //! - `.gitconfig.j2` → `~/.gitconfig`
 //! - `secrets.age` → `~/secrets`
 //! - `config.j2.age` → `~/config`
+//! - `run_once_before_install.sh.j2` → script `install.sh`
+//! - `owner_root_mode_0440_sudoers` → `sudoers`, owned by root with mode `0440`
+//! - `env_.env.j2` → `~/.env`, mode `0600`
+//! - `compress_font.ttf.age` → `~/font.ttf`, decrypted and then decompressed
+//! - `symlink_.vimrc.j2` → `~/.vimrc`, linking to the rendered content
+//! - `exact_.vim/colors/x.vim` → `~/.vim/colors/x.vim`, with `~/.vim` kept exact
 //!
 //! # Examples
 //!
@@ -53,10 +76,13 @@ const READONLY: u32 = 0o444;
 const READONLY_EXEC: u32 = 0o555;
 const STANDARD_EXEC: u32 = 0o755;
 
+// Explicit modes are stored in the attribute bits above the flags
+const EXPLICIT_MODE_SHIFT: u32 = 16;
+
 bitflags::bitflags! {
     /// Attributes that can be encoded in a filename
     #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
-    pub struct FileAttributes: u8 {
+    pub struct FileAttributes: u32 {
         /// Should this file be hidden (start with a dot)?
         const DOT = 1 << 0;
         /// Should this file have restrictive permissions (private)?
@@ -69,6 +95,28 @@ bitflags::bitflags! {
         const TEMPLATE = 1 << 4;
         /// Is this file encrypted?
         const ENCRYPTED = 1 << 5;
+        /// Is this file a script to run instead of a file to write?
+        const SCRIPT = 1 << 6;
+        /// Should this script run only once?
+        const ONCE = 1 << 7;
+        /// Should this script run only when its content changes?
+        const ONCHANGE = 1 << 8;
+        /// Should this script run before files are applied?
+        const BEFORE = 1 << 9;
+        /// Should this script run after files are applied?
+        const AFTER = 1 << 10;
+        /// Does this file have an explicit mode from a `mode_` prefix?
+        const MODE = 1 << 11;
+        /// Should unmanaged entries of this directory be removed?
+        const EXACT = 1 << 12;
+        /// Is this file a secret env file?
+        const ENV = 1 << 13;
+        /// Is this file stored zstd compressed?
+        const COMPRESSED = 1 << 14;
+        /// Is this file a symlink whose target is its content?
+        const SYMLINK = 1 << 15;
+        // Explicit mode bits, see `explicit_mode`
+        const _ = !0;
     }
 }
 
@@ -121,6 +169,77 @@ impl FileAttributes {
         self.contains(Self::ENCRYPTED)
     }
 
+    /// Check if file is a script to run during apply
+    #[inline]
+    #[must_use]
+    pub fn is_script(&self) -> bool {
+        self.contains(Self::SCRIPT)
+    }
+
+    /// Check if script should run only once
+    #[inline]
+    #[must_use]
+    pub fn is_once(&self) -> bool {
+        self.contains(Self::ONCE)
+    }
+
+    /// Check if script should run only when its content changes
+    #[inline]
+    #[must_use]
+    pub fn is_onchange(&self) -> bool {
+        self.contains(Self::ONCHANGE)
+    }
+
+    /// Check if script should run before files are applied
+    #[inline]
+    #[must_use]
+    pub fn is_before(&self) -> bool {
+        self.contains(Self::BEFORE)
+    }
+
+    /// Check if script should run after files are applied
+    #[inline]
+    #[must_use]
+    pub fn is_after(&self) -> bool {
+        self.contains(Self::AFTER)
+    }
+
+    /// Check if directory should contain only managed entries
+    #[inline]
+    #[must_use]
+    pub fn is_exact(&self) -> bool {
+        self.contains(Self::EXACT)
+    }
+
+    /// Check if file is a secret env file
+    #[inline]
+    #[must_use]
+    pub fn is_env(&self) -> bool {
+        self.contains(Self::ENV)
+    }
+
+    /// Check if file is stored zstd compressed
+    #[inline]
+    #[must_use]
+    pub fn is_compressed(&self) -> bool {
+        self.contains(Self::COMPRESSED)
+    }
+
+    /// Check if file is a symlink whose target is its content
+    #[inline]
+    #[must_use]
+    pub fn is_symlink(&self) -> bool {
+        self.contains(Self::SYMLINK)
+    }
+
+    /// Explicit permission mode from a `mode_` prefix
+    #[inline]
+    #[must_use]
+    pub fn explicit_mode(&self) -> Option<u32> {
+        self.contains(Self::MODE)
+            .then(|| (self.bits() >> EXPLICIT_MODE_SHIFT) & PERMISSION_MASK)
+    }
+
     /// Set whether file should be hidden (start with a dot)
     #[inline]
     pub fn set_dot(&mut self, value: bool) {
@@ -157,9 +276,77 @@ impl FileAttributes {
         self.set(Self::ENCRYPTED, value);
     }
 
+    /// Set whether file is a script to run during apply
+    #[inline]
+    pub fn set_script(&mut self, value: bool) {
+        self.set(Self::SCRIPT, value);
+    }
+
+    /// Set whether script should run only once
+    #[inline]
+    pub fn set_once(&mut self, value: bool) {
+        self.set(Self::ONCE, value);
+    }
+
+    /// Set whether script should run only when its content changes
+    #[inline]
+    pub fn set_onchange(&mut self, value: bool) {
+        self.set(Self::ONCHANGE, value);
+    }
+
+    /// Set whether script should run before files are applied
+    #[inline]
+    pub fn set_before(&mut self, value: bool) {
+        self.set(Self::BEFORE, value);
+    }
+
+    /// Set whether script should run after files are applied
+    #[inline]
+    pub fn set_after(&mut self, value: bool) {
+        self.set(Self::AFTER, value);
+    }
+
+    /// Set whether directory should contain only managed entries
+    #[inline]
+    pub fn set_exact(&mut self, value: bool) {
+        self.set(Self::EXACT, value);
+    }
+
+    /// Set whether file is a secret env file
+    #[inline]
+    pub fn set_env(&mut self, value: bool) {
+        self.set(Self::ENV, value);
+    }
+
+    /// Set whether file is stored zstd compressed
+    #[inline]
+    pub fn set_compressed(&mut self, value: bool) {
+        self.set(Self::COMPRESSED, value);
+    }
+
+    /// Set whether file is a symlink whose target is its content
+    #[inline]
+    pub fn set_symlink(&mut self, value: bool) {
+        self.set(Self::SYMLINK, value);
+    }
+
+    /// Set or clear the explicit permission mode
+    ///
+    /// Only permission bits (`0o777`) are kept.
+    pub fn set_explicit_mode(&mut self, mode: Option<u32>) {
+        let flags = self.bits() & !(PERMISSION_MASK << EXPLICIT_MODE_SHIFT) & !Self::MODE.bits();
+        *self = Self::from_bits_retain(match mode {
+            Some(mode) => {
+                flags | Self::MODE.bits() | ((mode & PERMISSION_MASK) << EXPLICIT_MODE_SHIFT)
+            }
+            None => flags,
+        });
+    }
+
     /// Parse attributes from a source file
     ///
     /// Returns the parsed attributes and the target filename (with extensions stripped).
+    /// For scripts, the `run_` prefix and its modifiers are stripped as well.
     ///
     /// # Arguments
     ///
@@ -187,13 +374,43 @@ impl FileAttributes {
     /// let (attrs, name) = FileAttributes::parse_from_source("deploy.sh", Some(0o755))?;
     /// assert!(attrs.is_executable());
     /// assert_eq!(name, "deploy.sh");
+    ///
+    /// // Script run once, before files are applied
+    /// let (attrs, name) = FileAttributes::parse_from_source("run_once_before_setup.sh", None)?;
+    /// assert!(attrs.is_script() && attrs.is_once() && attrs.is_before());
+    /// assert_eq!(name, "setup.sh");
+    ///
+    /// // Explicit mode overrides the source file permissions
+    /// let (attrs, name) = FileAttributes::parse_from_source("mode_0640_app.conf", Some(0o644))?;
+    /// assert_eq!(attrs.mode(), Some(0o640));
+    /// assert_eq!(name, "app.conf");
+    ///
+    /// // Secret env files are always private
+    /// let (attrs, name) = FileAttributes::parse_from_source("env_.env.j2", Some(0o644))?;
+    /// assert!(attrs.is_env() && attrs.is_template());
+    /// assert_eq!(attrs.mode(), Some(0o600));
+    /// assert_eq!(name, ".env");
+    ///
+    /// // Compressed binary, decrypted and then decompressed
+    /// let (attrs, name) = FileAttributes::parse_from_source("compress_font.ttf.age", None)?;
+    /// assert!(attrs.is_compressed() && attrs.is_encrypted());
+    /// assert_eq!(name, "font.ttf");
+    ///
+    /// // Symlink with a templated target
+    /// let (attrs, name) = FileAttributes::parse_from_source("symlink_.vimrc.j2", None)?;
+    /// assert!(attrs.is_symlink() && attrs.is_template());
+    /// assert_eq!(name, ".vimrc");
     /// # Ok(())
     /// # }
     /// ```
     ///
     /// # Errors
     ///
-    /// Returns an error if the filename cannot be parsed (e.g., invalid encoding)
+    /// Returns an error if the filename cannot be parsed (e.g., invalid encoding,
+    /// invalid octal mode, no name left after the prefixes, an `env_` file
+    /// that is also a script or has an explicit mode, or a compressed file
+    /// that is also an env file, a script, or a template, or a symlink with
+    /// other prefixes)
     pub fn parse_from_source(filename: &str, mode: Option<u32>) -> Result<(Self, String)> {
         let mut attrs = Self::new();
         let mut target_name = filename.to_string();
@@ -214,14 +431,170 @@ impl FileAttributes {
             target_name.truncate(target_name.len() - ext_len);
         }
 
-        // Parse permissions from Unix mode
-        if let Some(mode) = mode {
+        // Check for symlink_ prefix, which takes no other prefixes
+        if let Some(rest) = target_name.strip_prefix("symlink_") {
+            return Self::parse_symlink(filename, rest, attrs);
+        }
+
+        // Check for env_ prefix
+        if let Some(rest) = target_name.strip_prefix("env_") {
+            if rest.is_empty() {
+                return Err(guisu_core::Error::InvalidAttributes {
+                    filename: filename.to_string(),
+                    reason: "file has no name after its env prefix".to_string(),
+                });
+            }
+            if rest.starts_with("mode_") || rest.starts_with("run_") {
+                return Err(guisu_core::Error::InvalidAttributes {
+                    filename: filename.to_string(),
+                    reason: "env files are always written with mode 0600 and cannot be scripts"
+                        .to_string(),
+                });
+            }
+            attrs.set_env(true);
+            target_name = rest.to_string();
+        }
+
+        // Check for mode_<octal>_ prefix
+        if let Some(rest) = target_name.strip_prefix("mode_") {
+            let (digits, rest) = rest.split_once('_').unwrap_or((rest, ""));
+            let explicit = u32::from_str_radix(digits, 8)
+                .ok()
+                .filter(|mode| *mode <= PERMISSION_MASK)
+                .ok_or_else(|| guisu_core::Error::InvalidAttributes {
+                    filename: filename.to_string(),
+                    reason: format!("invalid mode '{digits}', expected octal up to 0777"),
+                })?;
+            if rest.is_empty() {
+                return Err(guisu_core::Error::InvalidAttributes {
+                    filename: filename.to_string(),
+                    reason: "file has no name after its mode prefix".to_string(),
+                });
+            }
+            attrs.set_explicit_mode(Some(explicit));
+            target_name = rest.to_string();
+        }
+
+        // Check for compress_ prefix
+        if let Some(rest) = target_name.strip_prefix("compress_") {
+            if rest.is_empty() {
+                return Err(guisu_core::Error::InvalidAttributes {
+                    filename: filename.to_string(),
+                    reason: "file has no name after its compress prefix".to_string(),
+                });
+            }
+            if attrs.is_env() || attrs.is_template() || rest.starts_with("run_") {
+                return Err(guisu_core::Error::InvalidAttributes {
+                    filename: filename.to_string(),
+                    reason:
+                        "compressed files are binary and cannot be env files, scripts, or templates"
+                            .to_string(),
+                });
+            }
+            attrs.set_compressed(true);
+            target_name = rest.to_string();
+        }
+
+        // Check for run_ prefix followed by optional once_/onchange_ and before_/after_
+        if let Some(rest) = target_name.strip_prefix("run_") {
+            attrs.set_script(true);
+            let mut rest = rest;
+            if let Some(stripped) = rest.strip_prefix("once_") {
+                attrs.set_once(true);
+                rest = stripped;
+            } else if let Some(stripped) = rest.strip_prefix("onchange_") {
+                attrs.set_onchange(true);
+                rest = stripped;
+            }
+            if let Some(stripped) = rest.strip_prefix("before_") {
+                attrs.set_before(true);
+                rest = stripped;
+            } else if let Some(stripped) = rest.strip_prefix("after_") {
+                attrs.set_after(true);
+                rest = stripped;
+            }
+
+            if rest.is_empty() {
+                return Err(guisu_core::Error::InvalidAttributes {
+                    filename: filename.to_string(),
+                    reason: "script has no name after its prefix".to_string(),
+                });
+            }
+            target_name = rest.to_string();
+        }
+
+        // Parse permissions from the explicit mode, or else the Unix mode
+        if attrs.is_env() {
+            attrs.parse_permissions(PRIVATE_FILE);
+        } else if let Some(mode) = attrs.explicit_mode().or(mode) {
             attrs.parse_permissions(mode);
         }
 
         Ok((attrs, target_name))
     }
 
+    /// Finish parsing a source filename from what follows its `symlink_` prefix
+    fn parse_symlink(filename: &str, rest: &str, mut attrs: Self) -> Result<(Self, String)> {
+        if rest.is_empty() {
+            return Err(guisu_core::Error::InvalidAttributes {
+                filename: filename.to_string(),
+                reason: "file has no name after its symlink prefix".to_string(),
+            });
+        }
+        if ["env_", "mode_", "compress_", "run_"]
+            .iter()
+            .any(|prefix| rest.starts_with(prefix))
+        {
+            return Err(guisu_core::Error::InvalidAttributes {
+                filename: filename.to_string(),
+                reason: "symlinks cannot have a mode or be env files, compressed, or scripts"
+                    .to_string(),
+            });
+        }
+        attrs.set_symlink(true);
+        Ok((attrs, rest.to_string()))
+    }
+
+    /// Parse attributes from a source directory name
+    ///
+    /// Only the `exact_` prefix applies to directories. Returns the parsed
+    /// attributes and the target directory name.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use guisu_engine::attr::FileAttributes;
+    ///
+    /// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+    /// let (attrs, name) = FileAttributes::parse_directory_name("exact_.vim")?;
+    /// assert!(attrs.is_exact());
+    /// assert_eq!(name, ".vim");
+    ///
+    /// let (attrs, name) = FileAttributes::parse_directory_name(".config")?;
+    /// assert!(!attrs.is_exact());
+    /// assert_eq!(name, ".config");
+    /// # Ok(())
+    /// # }
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if no name is left after the prefix
+    pub fn parse_directory_name(dirname: &str) -> Result<(Self, &str)> {
+        let mut attrs = Self::new();
+        let Some(rest) = dirname.strip_prefix("exact_") else {
+            return Ok((attrs, dirname));
+        };
+        if rest.is_empty() {
+            return Err(guisu_core::Error::InvalidAttributes {
+                filename: dirname.to_string(),
+                reason: "directory has no name after its exact prefix".to_string(),
+            });
+        }
+        attrs.set_exact(true);
+        Ok((attrs, rest))
+    }
+
     /// Parse Unix permissions to set attributes
     ///
     /// Detects private, executable, and readonly attributes from file mode.
@@ -248,7 +621,9 @@ impl FileAttributes {
 
     /// Get the Unix file permission mode for these attributes
     ///
-    /// Returns `None` if no specific permissions are required (use defaults).
+    /// An explicit mode is returned as is, and secret env files are always
+    /// `0600`. Otherwise returns `None` if no specific permissions are
+    /// required (use defaults).
     ///
     /// # Examples
     ///
@@ -268,6 +643,12 @@ impl FileAttributes {
     /// ```
     #[must_use]
     pub fn mode(&self) -> Option<u32> {
+        if let Some(mode) = self.explicit_mode() {
+            return Some(mode);
+        }
+        if self.is_env() {
+            return Some(PRIVATE_FILE);
+        }
         match (self.is_private(), self.is_readonly(), self.is_executable()) {
             (true, false, true) => Some(PRIVATE_DIR), // private + executable
             (true, false, false) => Some(PRIVATE_FILE), // private only
@@ -287,13 +668,23 @@ impl Serialize for FileAttributes {
         S: Serializer,
     {
         use serde::ser::SerializeStruct;
-        let mut state = serializer.serialize_struct("FileAttributes", 6)?;
+        let mut state = serializer.serialize_struct("FileAttributes", 16)?;
         state.serialize_field("is_dot", &self.is_dot())?;
         state.serialize_field("is_private", &self.is_private())?;
         state.serialize_field("is_readonly", &self.is_readonly())?;
         state.serialize_field("is_executable", &self.is_executable())?;
         state.serialize_field("is_template", &self.is_template())?;
         state.serialize_field("is_encrypted", &self.is_encrypted())?;
+        state.serialize_field("is_script", &self.is_script())?;
+        state.serialize_field("is_once", &self.is_once())?;
+        state.serialize_field("is_onchange", &self.is_onchange())?;
+        state.serialize_field("is_before", &self.is_before())?;
+        state.serialize_field("is_after", &self.is_after())?;
+        state.serialize_field("is_exact", &self.is_exact())?;
+        state.serialize_field("is_env", &self.is_env())?;
+        state.serialize_field("is_compressed", &self.is_compressed())?;
+        state.serialize_field("is_symlink", &self.is_symlink())?;
+        state.serialize_field("mode", &self.explicit_mode())?;
         state.end()
     }
 }
@@ -301,6 +692,7 @@ impl Serialize for FileAttributes {
 // Custom Deserialize to parse user-friendly JSON/TOML format
 // Reads individual boolean fields and converts them to bitflags representation
 impl<'de> Deserialize<'de> for FileAttributes {
+    #[allow(clippy::too_many_lines)]
     fn deserialize<D>(deserializer: D) -> std::result::Result<Self, D::Error>
     where
         D: Deserializer<'de>,
@@ -317,6 +709,16 @@ impl<'de> Deserialize<'de> for FileAttributes {
             IsExecutable,
             IsTemplate,
             IsEncrypted,
+            IsScript,
+            IsOnce,
+            IsOnchange,
+            IsBefore,
+            IsAfter,
+            IsExact,
+            IsEnv,
+            IsCompressed,
+            IsSymlink,
+            Mode,
         }
 
         struct FileAttributesVisitor;
@@ -360,6 +762,46 @@ impl<'de> Deserialize<'de> for FileAttributes {
                             let value: bool = map.next_value()?;
                             attrs.set(FileAttributes::ENCRYPTED, value);
                         }
+                        Field::IsScript => {
+                            let value: bool = map.next_value()?;
+                            attrs.set(FileAttributes::SCRIPT, value);
+                        }
+                        Field::IsOnce => {
+                            let value: bool = map.next_value()?;
+                            attrs.set(FileAttributes::ONCE, value);
+                        }
+                        Field::IsOnchange => {
+                            let value: bool = map.next_value()?;
+                            attrs.set(FileAttributes::ONCHANGE, value);
+                        }
+                        Field::IsBefore => {
+                            let value: bool = map.next_value()?;
+                            attrs.set(FileAttributes::BEFORE, value);
+                        }
+                        Field::IsAfter => {
+                            let value: bool = map.next_value()?;
+                            attrs.set(FileAttributes::AFTER, value);
+                        }
+                        Field::IsExact => {
+                            let value: bool = map.next_value()?;
+                            attrs.set(FileAttributes::EXACT, value);
+                        }
+                        Field::IsEnv => {
+                            let value: bool = map.next_value()?;
+                            attrs.set(FileAttributes::ENV, value);
+                        }
+                        Field::IsCompressed => {
+                            let value: bool = map.next_value()?;
+                            attrs.set(FileAttributes::COMPRESSED, value);
+                        }
+                        Field::IsSymlink => {
+                            let value: bool = map.next_value()?;
+                            attrs.set(FileAttributes::SYMLINK, value);
+                        }
+                        Field::Mode => {
+                            let value: Option<u32> = map.next_value()?;
+                            attrs.set_explicit_mode(value);
+                        }
                     }
                 }
 
@@ -374,6 +816,16 @@ impl<'de> Deserialize<'de> for FileAttributes {
             "is_executable",
             "is_template",
             "is_encrypted",
+            "is_script",
+            "is_once",
+            "is_onchange",
+            "is_before",
+            "is_after",
+            "is_exact",
+            "is_env",
+            "is_compressed",
+            "is_symlink",
+            "mode",
         ];
         deserializer.deserialize_struct("FileAttributes", FIELDS, FileAttributesVisitor)
     }
@@ -385,6 +837,76 @@ impl Default for FileAttributes {
     }
 }
 
+/// Owner and group of a destination file, from `owner_` and `group_` prefixes
+///
+/// Names are taken up to the next underscore, so `owner_root_group_wheel_sudoers`
+/// targets `sudoers` owned by `root:wheel`. Numeric IDs are accepted as well.
+/// Ownership is only applied when running as root.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct Ownership {
+    /// User name or numeric UID
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub owner: Option<String>,
+    /// Group name or numeric GID
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub group: Option<String>,
+}
+
+impl Ownership {
+    /// Strip leading `owner_<name>_` and `group_<name>_` prefixes from a filename
+    ///
+    /// Returns the ownership and the rest of the filename, which still carries
+    /// its other attributes.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use guisu_engine::attr::Ownership;
+    ///
+    /// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+    /// let (ownership, rest) = Ownership::parse_prefix("owner_root_mode_0440_sudoers")?;
+    /// assert_eq!(ownership.owner.as_deref(), Some("root"));
+    /// assert_eq!(ownership.group, None);
+    /// assert_eq!(rest, "mode_0440_sudoers");
+    /// # Ok(())
+    /// # }
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if a name is empty or nothing is left after the prefixes
+    pub fn parse_prefix(filename: &str) -> Result<(Self, &str)> {
+        let mut ownership = Self::default();
+        let mut rest = filename;
+
+        for (prefix, slot) in [
+            ("owner_", &mut ownership.owner),
+            ("group_", &mut ownership.group),
+        ] {
+            let Some(stripped) = rest.strip_prefix(prefix) else {
+                continue;
+            };
+            let (name, remaining) = stripped.split_once('_').unwrap_or((stripped, ""));
+            if name.is_empty() || remaining.is_empty() {
+                return Err(guisu_core::Error::InvalidAttributes {
+                    filename: filename.to_string(),
+                    reason: format!("expected {prefix}<name>_ followed by the file name"),
+                });
+            }
+            *slot = Some(name.to_string());
+            rest = remaining;
+        }
+
+        Ok((ownership, rest))
+    }
+
+    /// Check if neither owner nor group is set
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.owner.is_none() && self.group.is_none()
+    }
+}
+
 #[cfg(test)]
 mod tests {
     #![allow(clippy::unwrap_used, clippy::panic)]
@@ -735,4 +1257,190 @@ mod tests {
         let cloned = attrs;
         assert_eq!(attrs, cloned);
     }
+
+    #[test]
+    fn test_parse_script_prefixes() {
+        let (attrs, name) =
+            FileAttributes::parse_from_source("run_install.sh", None).expect("parse failed");
+        assert!(attrs.is_script());
+        assert!(!attrs.is_once() && !attrs.is_onchange());
+        assert!(!attrs.is_before() && !attrs.is_after());
+        assert_eq!(name, "install.sh");
+
+        let (attrs, name) =
+            FileAttributes::parse_from_source("run_onchange_after_brew.sh.j2", None)
+                .expect("parse failed");
+        assert!(attrs.is_script() && attrs.is_onchange() && attrs.is_after());
+        assert!(attrs.is_template());
+        assert_eq!(name, "brew.sh");
+
+        let (attrs, name) =
+            FileAttributes::parse_from_source("run_before_setup.py", None).expect("parse failed");
+        assert!(attrs.is_script() && attrs.is_before());
+        assert_eq!(name, "setup.py");
+    }
+
+    #[test]
+    fn test_parse_script_prefix_only_at_start() {
+        let (attrs, name) =
+            FileAttributes::parse_from_source("prerun_once.sh", None).expect("parse failed");
+        assert!(!attrs.is_script());
+        assert_eq!(name, "prerun_once.sh");
+
+        assert!(FileAttributes::parse_from_source("run_once_", None).is_err());
+    }
+
+    #[test]
+    fn test_serialize_script_flags() {
+        let (attrs, _) =
+            FileAttributes::parse_from_source("run_once_after_x.sh", None).expect("parse failed");
+        let json = serde_json::to_string(&attrs).expect("serialize failed");
+        let deserialized: FileAttributes = serde_json::from_str(&json).expect("deserialize failed");
+        assert_eq!(attrs, deserialized);
+    }
+
+    #[test]
+    fn test_parse_explicit_mode() {
+        let (attrs, name) = FileAttributes::parse_from_source("mode_0640_app.conf.j2", Some(0o644))
+            .expect("parse failed");
+        assert_eq!(name, "app.conf");
+        assert!(attrs.is_template());
+        assert_eq!(attrs.explicit_mode(), Some(0o640));
+        assert_eq!(attrs.mode(), Some(0o640));
+
+        // Flags follow the explicit mode rather than the source file
+        let (attrs, _) =
+            FileAttributes::parse_from_source("mode_700_tool", Some(0o644)).expect("parse failed");
+        assert!(attrs.is_private() && attrs.is_executable());
+
+        let json = serde_json::to_string(&attrs).expect("serialize failed");
+        let deserialized: FileAttributes = serde_json::from_str(&json).expect("deserialize failed");
+        assert_eq!(attrs, deserialized);
+
+        for invalid in ["mode_0999_x", "mode_1777_x", "mode_0644_", "mode_rw_x"] {
+            assert!(
+                FileAttributes::parse_from_source(invalid, None).is_err(),
+                "{invalid} should be rejected"
+            );
+        }
+    }
+
+    #[test]
+    fn test_set_explicit_mode_keeps_flags() {
+        let mut attrs = FileAttributes::TEMPLATE | FileAttributes::ENCRYPTED;
+        attrs.set_explicit_mode(Some(0o600));
+        attrs.set_explicit_mode(Some(0o644));
+        assert_eq!(attrs.explicit_mode(), Some(0o644));
+        assert!(attrs.is_template() && attrs.is_encrypted());
+
+        attrs.set_explicit_mode(None);
+        assert_eq!(attrs, FileAttributes::TEMPLATE | FileAttributes::ENCRYPTED);
+    }
+
+    #[test]
+    fn test_parse_env_file() {
+        let (attrs, name) = FileAttributes::parse_from_source("env_.env.j2.age", Some(0o755))
+            .expect("parse failed");
+        assert_eq!(name, ".env");
+        assert!(attrs.is_env() && attrs.is_template() && attrs.is_encrypted());
+        assert!(attrs.is_private() && !attrs.is_executable());
+        assert_eq!(attrs.mode(), Some(0o600));
+
+        let json = serde_json::to_string(&attrs).expect("serialize failed");
+        let deserialized: FileAttributes = serde_json::from_str(&json).expect("deserialize failed");
+        assert_eq!(attrs, deserialized);
+
+        for invalid in ["env_", "env_mode_0644_x", "env_run_x.sh"] {
+            assert!(
+                FileAttributes::parse_from_source(invalid, None).is_err(),
+                "{invalid} should be rejected"
+            );
+        }
+    }
+
+    #[test]
+    fn test_parse_compressed_file() {
+        let (attrs, name) =
+            FileAttributes::parse_from_source("mode_0755_compress_starship.age", None)
+                .expect("parse failed");
+        assert_eq!(name, "starship");
+        assert!(attrs.is_compressed() && attrs.is_encrypted());
+        assert_eq!(attrs.mode(), Some(0o755));
+
+        let json = serde_json::to_string(&attrs).expect("serialize failed");
+        let deserialized: FileAttributes = serde_json::from_str(&json).expect("deserialize failed");
+        assert_eq!(attrs, deserialized);
+
+        for invalid in [
+            "compress_",
+            "env_compress_x",
+            "compress_x.j2",
+            "compress_run_x.sh",
+        ] {
+            assert!(
+                FileAttributes::parse_from_source(invalid, None).is_err(),
+                "{invalid} should be rejected"
+            );
+        }
+    }
+
+    #[test]
+    fn test_parse_symlink() {
+        let (attrs, name) = FileAttributes::parse_from_source("symlink_.vimrc.j2.age", Some(0o755))
+            .expect("parse failed");
+        assert_eq!(name, ".vimrc");
+        assert!(attrs.is_symlink() && attrs.is_template() && attrs.is_encrypted());
+        // The permissions of the source file don't apply to the link
+        assert_eq!(attrs.mode(), None);
+
+        let json = serde_json::to_string(&attrs).expect("serialize failed");
+        let deserialized: FileAttributes = serde_json::from_str(&json).expect("deserialize failed");
+        assert_eq!(attrs, deserialized);
+
+        for invalid in [
+            "symlink_",
+            "symlink_mode_0644_x",
+            "symlink_env_.env",
+            "symlink_compress_x",
+            "symlink_run_x.sh",
+        ] {
+            assert!(
+                FileAttributes::parse_from_source(invalid, None).is_err(),
+                "{invalid} should be rejected"
+            );
+        }
+    }
+
+    #[test]
+    fn test_parse_directory_name() {
+        let (attrs, name) = FileAttributes::parse_directory_name("exact_.vim").unwrap();
+        assert!(attrs.is_exact());
+        assert_eq!(name, ".vim");
+
+        // The prefix only counts at the start, and files never get it
+        let (attrs, name) = FileAttributes::parse_directory_name("not_exact_dir").unwrap();
+        assert!(!attrs.is_exact());
+        assert_eq!(name, "not_exact_dir");
+        let (attrs, name) = FileAttributes::parse_from_source("exact_file", None).unwrap();
+        assert!(!attrs.is_exact());
+        assert_eq!(name, "exact_file");
+
+        assert!(FileAttributes::parse_directory_name("exact_").is_err());
+    }
+
+    #[test]
+    fn test_parse_ownership_prefix() {
+        let (ownership, rest) =
+            Ownership::parse_prefix("owner_root_group_0_mode_0440_sudoers").expect("parse failed");
+        assert_eq!(ownership.owner.as_deref(), Some("root"));
+        assert_eq!(ownership.group.as_deref(), Some("0"));
+        assert_eq!(rest, "mode_0440_sudoers");
+
+        let (ownership, rest) = Ownership::parse_prefix(".bashrc").expect("parse failed");
+        assert!(ownership.is_empty());
+        assert_eq!(rest, ".bashrc");
+
+        assert!(Ownership::parse_prefix("owner__x").is_err());
+        assert!(Ownership::parse_prefix("group_wheel").is_err());
+    }
 }
@@ -0,0 +1,260 @@
+//! Injectable time and run ID sources for state records
+//!
+//! Every write to persistent state (applied entries, hook runs) is stamped with
+//! a timestamp and the ID of the run that produced it. Both come from a
+//! [`StateClock`], so tests can pin them to fixed values instead of asserting
+//! against the wall clock.
+
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+/// Source of the current time
+pub trait Clock: Send + Sync + fmt::Debug {
+    /// Get the current time
+    fn now(&self) -> SystemTime;
+}
+
+/// Clock backed by the system wall clock
+#[derive(Debug, Default, Clone, Copy)]
+pub struct SystemClock;
+
+impl Clock for SystemClock {
+    fn now(&self) -> SystemTime {
+        SystemTime::now()
+    }
+}
+
+/// Clock that only moves when told to
+#[derive(Debug)]
+pub struct FixedClock {
+    now: Mutex<SystemTime>,
+}
+
+impl FixedClock {
+    /// Create a clock stopped at the given time
+    #[must_use]
+    pub fn new(now: SystemTime) -> Self {
+        Self {
+            now: Mutex::new(now),
+        }
+    }
+
+    /// Create a clock stopped at the given number of seconds since the Unix epoch
+    #[must_use]
+    pub fn from_secs(secs: u64) -> Self {
+        Self::new(UNIX_EPOCH + Duration::from_secs(secs))
+    }
+
+    /// Move the clock forward
+    pub fn advance(&self, by: Duration) {
+        let mut now = self
+            .now
+            .lock()
+            .unwrap_or_else(std::sync::PoisonError::into_inner);
+        *now += by;
+    }
+}
+
+impl Clock for FixedClock {
+    fn now(&self) -> SystemTime {
+        *self
+            .now
+            .lock()
+            .unwrap_or_else(std::sync::PoisonError::into_inner)
+    }
+}
+
+/// Source of run IDs
+pub trait RunIdSource: Send + Sync + fmt::Debug {
+    /// Generate the ID for a run starting at `now`
+    fn next_run_id(&self, now: SystemTime) -> String;
+}
+
+/// Run IDs built from the start time, process ID, and a per-process counter
+///
+/// IDs look like `67a1c3f0-3e8a-1`: unique across concurrent processes and
+/// sortable by start time.
+#[derive(Debug, Default)]
+pub struct DefaultRunIds {
+    counter: AtomicU64,
+}
+
+impl RunIdSource for DefaultRunIds {
+    fn next_run_id(&self, now: SystemTime) -> String {
+        let n = self.counter.fetch_add(1, Ordering::Relaxed) + 1;
+        format!("{:x}-{:x}-{n}", unix_secs(now), std::process::id())
+    }
+}
+
+/// Run IDs of the form `{prefix}-1`, `{prefix}-2`, ...
+#[derive(Debug)]
+pub struct SequentialRunIds {
+    prefix: String,
+    next: AtomicU64,
+}
+
+impl SequentialRunIds {
+    /// Create a sequence starting at `{prefix}-1`
+    #[must_use]
+    pub fn new(prefix: impl Into<String>) -> Self {
+        Self {
+            prefix: prefix.into(),
+            next: AtomicU64::new(1),
+        }
+    }
+}
+
+impl RunIdSource for SequentialRunIds {
+    fn next_run_id(&self, _now: SystemTime) -> String {
+        let n = self.next.fetch_add(1, Ordering::Relaxed);
+        format!("{}-{n}", self.prefix)
+    }
+}
+
+/// Timestamp and run ID attached to a state record
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, bincode::Encode, bincode::Decode)]
+pub struct RunStamp {
+    /// ID of the run that wrote the record
+    pub run_id: String,
+    /// Seconds since the Unix epoch when the run started
+    pub timestamp: u64,
+}
+
+impl RunStamp {
+    /// Get the timestamp as a `SystemTime`
+    #[must_use]
+    pub fn time(&self) -> SystemTime {
+        UNIX_EPOCH + Duration::from_secs(self.timestamp)
+    }
+}
+
+/// Clock and run ID source used for all state writes
+///
+/// Cheap to clone; clones share the underlying sources.
+#[derive(Debug, Clone)]
+pub struct StateClock {
+    clock: Arc<dyn Clock>,
+    run_ids: Arc<dyn RunIdSource>,
+}
+
+impl StateClock {
+    /// Create a state clock from explicit sources
+    #[must_use]
+    pub fn new(clock: impl Clock + 'static, run_ids: impl RunIdSource + 'static) -> Self {
+        Self {
+            clock: Arc::new(clock),
+            run_ids: Arc::new(run_ids),
+        }
+    }
+
+    /// Wall clock time with process-unique run IDs
+    #[must_use]
+    pub fn system() -> Self {
+        Self::new(SystemClock, DefaultRunIds::default())
+    }
+
+    /// Deterministic clock stopped at `secs` with run IDs `run-1`, `run-2`, ...
+    #[must_use]
+    pub fn fixed(secs: u64) -> Self {
+        Self::new(FixedClock::from_secs(secs), SequentialRunIds::new("run"))
+    }
+
+    /// Get the current time
+    #[must_use]
+    pub fn now(&self) -> SystemTime {
+        self.clock.now()
+    }
+
+    /// Start a new run, returning the stamp to attach to its state records
+    #[must_use]
+    pub fn begin_run(&self) -> RunStamp {
+        let now = self.now();
+        RunStamp {
+            run_id: self.run_ids.next_run_id(now),
+            timestamp: unix_secs(now),
+        }
+    }
+}
+
+impl Default for StateClock {
+    fn default() -> Self {
+        Self::system()
+    }
+}
+
+/// Seconds since the Unix epoch, clamping pre-epoch times to zero
+fn unix_secs(time: SystemTime) -> u64 {
+    time.duration_since(UNIX_EPOCH)
+        .map_or(0, |duration| duration.as_secs())
+}
+
+#[cfg(test)]
+mod tests {
+    #![allow(clippy::unwrap_used, clippy::panic)]
+    use super::*;
+
+    #[test]
+    fn test_fixed_clock_advance() {
+        let clock = FixedClock::from_secs(100);
+        assert_eq!(clock.now(), UNIX_EPOCH + Duration::from_secs(100));
+
+        clock.advance(Duration::from_secs(5));
+        assert_eq!(clock.now(), UNIX_EPOCH + Duration::from_secs(105));
+    }
+
+    #[test]
+    fn test_sequential_run_ids() {
+        let ids = SequentialRunIds::new("test");
+        assert_eq!(ids.next_run_id(UNIX_EPOCH), "test-1");
+        assert_eq!(ids.next_run_id(UNIX_EPOCH), "test-2");
+    }
+
+    #[test]
+    fn test_default_run_ids_are_unique() {
+        let ids = DefaultRunIds::default();
+        let now = SystemTime::now();
+        assert_ne!(ids.next_run_id(now), ids.next_run_id(now));
+    }
+
+    #[test]
+    fn test_fixed_state_clock_is_deterministic() {
+        let clock = StateClock::fixed(1_700_000_000);
+
+        let first = clock.begin_run();
+        let second = clock.begin_run();
+
+        assert_eq!(
+            first,
+            RunStamp {
+                run_id: "run-1".to_string(),
+                timestamp: 1_700_000_000,
+            }
+        );
+        assert_eq!(second.run_id, "run-2");
+        assert_eq!(
+            first.time(),
+            UNIX_EPOCH + Duration::from_secs(1_700_000_000)
+        );
+    }
+
+    #[test]
+    fn test_state_clock_clones_share_sources() {
+        let clock = StateClock::fixed(0);
+        let cloned = clock.clone();
+
+        assert_eq!(clock.begin_run().run_id, "run-1");
+        assert_eq!(cloned.begin_run().run_id, "run-2");
+    }
+
+    #[test]
+    fn test_run_stamp_bincode_roundtrip() {
+        let stamp = StateClock::fixed(42).begin_run();
+        let bytes = bincode::encode_to_vec(&stamp, bincode::config::standard()).unwrap();
+        let (decoded, _): (RunStamp, usize) =
+            bincode::decode_from_slice(&bytes, bincode::config::standard()).unwrap();
+        assert_eq!(decoded, stamp);
+    }
+}
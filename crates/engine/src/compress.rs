@@ -0,0 +1,75 @@
+//! zstd compression of source file contents
+//!
+//! Large binary files such as fonts or prompt binaries can be stored
+//! compressed in the source directory, marked by the `compress_` prefix.
+//! Compression is applied before encryption, so a compressed encrypted file
+//! is decrypted first and decompressed after.
+
+use std::io::Result as IoResult;
+
+/// zstd level used for source files
+///
+/// Files are compressed once when added and decompressed on every apply, so
+/// a higher level than zstd's default pays off.
+const LEVEL: i32 = 19;
+
+/// Compress `data` for storing in the source directory
+///
+/// # Examples
+///
+/// ```
+/// use guisu_engine::compress::{compress, decompress};
+///
+/// # fn main() -> std::io::Result<()> {
+/// let data = vec![0u8; 4096];
+/// let compressed = compress(&data)?;
+/// assert!(compressed.len() < data.len());
+/// assert_eq!(decompress(&compressed)?, data);
+/// # Ok(())
+/// # }
+/// ```
+///
+/// # Errors
+///
+/// Returns an error if the compressor fails
+pub fn compress(data: &[u8]) -> IoResult<Vec<u8>> {
+    zstd::encode_all(data, LEVEL)
+}
+
+/// Decompress the content of a compressed source file
+///
+/// # Errors
+///
+/// Returns an error if `data` is not zstd compressed or is truncated
+pub fn decompress(data: &[u8]) -> IoResult<Vec<u8>> {
+    zstd::decode_all(data)
+}
+
+/// Check if a file should be compressed when it is added
+///
+/// Only binary content (containing a NUL byte) of at least `threshold` bytes
+/// is compressed; text stays readable in the source repository.
+#[must_use]
+pub fn should_compress(data: &[u8], threshold: Option<u64>) -> bool {
+    threshold.is_some_and(|threshold| data.len() as u64 >= threshold) && data.contains(&0)
+}
+
+#[cfg(test)]
+mod tests {
+    #![allow(clippy::unwrap_used, clippy::panic)]
+    use super::*;
+
+    #[test]
+    fn test_decompress_rejects_plain_content() {
+        assert!(decompress(b"not compressed").is_err());
+    }
+
+    #[test]
+    fn test_should_compress() {
+        let binary = [0u8; 16];
+        assert!(should_compress(&binary, Some(16)));
+        assert!(!should_compress(&binary, Some(17)));
+        assert!(!should_compress(&binary, None));
+        assert!(!should_compress(&[b'a'; 16], Some(1)));
+    }
+}
@@ -3,6 +3,8 @@
 //! This module defines the traits for content decryption and template rendering.
 //! Engine uses these traits without depending on specific implementations.
 
+use std::io::{Read, Write};
+
 /// Trait for content decryption
 ///
 /// Implementations of this trait provide age decryption capabilities.
@@ -26,6 +28,33 @@ pub trait Decryptor: Send + Sync {
     /// Decrypted data or an error
     fn decrypt(&self, encrypted: &[u8]) -> Result<Vec<u8>, Self::Error>;
 
+    /// Decrypt the encrypted content of a source file
+    ///
+    /// Implementations may use `path` to remember per-file state, such as which
+    /// identity decrypted the file last time. Defaults to [`Decryptor::decrypt`].
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if decryption fails (e.g., invalid format, wrong key, corrupted data)
+    fn decrypt_file(&self, path: &str, encrypted: &[u8]) -> Result<Vec<u8>, Self::Error> {
+        let _ = path;
+        self.decrypt(encrypted)
+    }
+
+    /// Decrypt a source file from a reader into a writer, a chunk at a time
+    ///
+    /// Used for files too large to hold in memory.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if reading, decryption, or writing fails
+    fn decrypt_stream(
+        &self,
+        path: &str,
+        encrypted: &mut dyn Read,
+        output: &mut dyn Write,
+    ) -> Result<(), Self::Error>;
+
     /// Decrypt inline encrypted text (for use in templates)
     ///
     /// # Arguments
@@ -65,6 +94,14 @@ pub trait TemplateRenderer: Send + Sync {
     ///
     /// Rendered string or an error
     fn render(&self, template: &str, context: &serde_json::Value) -> Result<String, Self::Error>;
+
+    /// Prepare for rendering `templates`, e.g. by fetching data they need
+    ///
+    /// Called once with all templates about to be rendered, before any of
+    /// them is. Does nothing by default; failures surface when rendering.
+    fn prefetch(&self, templates: &[&str]) {
+        let _ = templates;
+    }
 }
 
 /// No-op decryptor for testing or when encryption is disabled
@@ -78,6 +115,15 @@ impl Decryptor for NoOpDecryptor {
         Ok(encrypted.to_vec())
     }
 
+    fn decrypt_stream(
+        &self,
+        _path: &str,
+        encrypted: &mut dyn Read,
+        output: &mut dyn Write,
+    ) -> Result<(), Self::Error> {
+        std::io::copy(encrypted, output).map(|_| ())
+    }
+
     fn decrypt_inline(&self, text: &str) -> Result<String, Self::Error> {
         // Return text as-is
         Ok(text.to_string())
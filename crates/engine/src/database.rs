@@ -2,14 +2,26 @@
 //!
 //! This module provides database utility functions for managing persistent state.
 //! The database instance is managed by `RuntimeContext` and passed explicitly.
+//!
+//! The layout of the database is versioned. [`open`] upgrades databases
+//! written by older versions in place, after backing up the file, by running
+//! the [`Migration`]s between their schema version and [`SCHEMA_VERSION`].
 
+use crate::clock::RunStamp;
+use crate::external::ExternalCache;
 use crate::state::{
-    CONFIG_METADATA_BUCKET, ConfigMetadata, ENTRY_STATE_BUCKET, EntryState, PersistentState,
-    RedbPersistentState,
+    APPLY_SNAPSHOT_BUCKET, ApplySnapshot, BASE_CONTENT_BUCKET, CONFIG_METADATA_BUCKET,
+    CONFLICT_SNAPSHOT_BUCKET, ConfigMetadata, ConflictSnapshot, DRIFT_EVENT_BUCKET, DriftEvent,
+    ENTRY_STATE_BUCKET, EXTERNAL_CACHE_BUCKET, EntryState, HOOK_LOG_BUCKET, HOOK_STATE_BUCKET,
+    HOOK_STATE_KEY, HookRunLog, HookState, IDENTITY_HINT_BUCKET, MANAGED_PATH_BUCKET, META_BUCKET,
+    ManagedKind, PROMPT_ANSWER_BUCKET, PersistentState, RENDER_CACHE_BUCKET, RedbPersistentState,
+    RenderRecord, SNAPSHOT_CONTENT_BUCKET,
 };
 use guisu_config::dirs;
 use guisu_core::{Error, Result};
-use std::path::PathBuf;
+use std::collections::HashSet;
+use std::io::{Read, Write};
+use std::path::{Path, PathBuf};
 
 /// Get the database path in XDG state directory
 ///
@@ -32,8 +44,47 @@ pub fn get_db_path() -> Result<PathBuf> {
     Ok(state_dir.join("state.db"))
 }
 
+/// Largest file whose content is kept as a merge base
+const MAX_BASE_CONTENT_SIZE: usize = 1024 * 1024;
+
+/// Whether the content saved with an entry state is kept as its merge base
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MergeBase {
+    /// Keep the content as the base, if it is text up to 1 MiB
+    Keep,
+    /// Keep no base, and delete any base kept before
+    Omit,
+}
+
+impl MergeBase {
+    /// The merge base of a file written with `mode`
+    ///
+    /// Secrets (decrypted files, env files and decrypted inline age values)
+    /// are never kept, nor files that only their owner can read, so their
+    /// contents never end up in the database.
+    #[must_use]
+    pub fn for_file(secret: bool, mode: u32) -> Self {
+        if !secret && mode & 0o044 != 0 {
+            Self::Keep
+        } else {
+            Self::Omit
+        }
+    }
+}
+
+/// Whether `content` is kept as the base for three-way merges
+fn keeps_base_content(content: &[u8], base: MergeBase) -> bool {
+    base == MergeBase::Keep
+        && content.len() <= MAX_BASE_CONTENT_SIZE
+        && std::str::from_utf8(content).is_ok()
+}
+
 /// Save entry state to database
 ///
+/// The record is stamped with the run that applied it. The content is kept
+/// as the entry's merge base as `base` allows; otherwise any base kept before
+/// is deleted.
+///
 /// # Errors
 ///
 /// Returns an error if the state cannot be saved (e.g., serialization failure, write error)
@@ -42,24 +93,71 @@ pub fn save_entry_state(
     path: &str,
     content: &[u8],
     mode: Option<u32>,
+    base: MergeBase,
+    stamp: &RunStamp,
 ) -> Result<()> {
-    let state = EntryState::new(content, mode);
+    let state = EntryState::new(content, mode).with_stamp(stamp);
     db.set(ENTRY_STATE_BUCKET, path.as_bytes(), &state.to_bytes()?)
         .map_err(|e| Error::State(format!("Failed to save state for {path}: {e}")))?;
+
+    if keeps_base_content(content, base) {
+        db.set(BASE_CONTENT_BUCKET, path.as_bytes(), content)
+            .map_err(|e| Error::State(format!("Failed to save base content for {path}: {e}")))?;
+    } else {
+        delete_base_content(db, path)?;
+    }
     Ok(())
 }
 
+/// Delete the merge base kept for `path`, if any
+///
+/// Checked first, as most entries without a base never had one and a delete
+/// is a write transaction of its own.
+fn delete_base_content(db: &RedbPersistentState, path: &str) -> Result<()> {
+    let kept = db
+        .get(BASE_CONTENT_BUCKET, path.as_bytes())
+        .map_err(|e| Error::State(format!("Failed to get base content for {path}: {e}")))?;
+    if kept.is_some() {
+        db.delete(BASE_CONTENT_BUCKET, path.as_bytes())
+            .map_err(|e| Error::State(format!("Failed to delete base content for {path}: {e}")))?;
+    }
+    Ok(())
+}
+
+/// Save the state of a file known only by its content hash
+///
+/// Used for large files, which are streamed instead of held in memory. No
+/// merge base is kept for them.
+///
+/// # Errors
+///
+/// Returns an error if the state cannot be saved (e.g., serialization failure, write error)
+pub fn save_entry_hash(
+    db: &RedbPersistentState,
+    path: &str,
+    content_hash: [u8; 32],
+    mode: Option<u32>,
+    stamp: &RunStamp,
+) -> Result<()> {
+    let state = EntryState::from_hash(content_hash, mode).with_stamp(stamp);
+    db.set(ENTRY_STATE_BUCKET, path.as_bytes(), &state.to_bytes()?)
+        .map_err(|e| Error::State(format!("Failed to save state for {path}: {e}")))
+}
+
 /// Save multiple entry states to database in a single transaction
 ///
 /// This is more efficient than calling `save_entry_state()` multiple times
-/// as it batches all writes into a single database transaction.
+/// as it batches all writes into a single database transaction. Every record
+/// is stamped with the same run. Base contents are saved in a second
+/// transaction, and bases of entries that no longer keep one are deleted.
 ///
 /// # Errors
 ///
 /// Returns an error if any state cannot be saved (e.g., serialization failure, write error)
 pub fn save_entry_states_batch(
     db: &RedbPersistentState,
-    entries: &[(String, Vec<u8>, Option<u32>)],
+    entries: &[(String, Vec<u8>, Option<u32>, MergeBase)],
+    stamp: &RunStamp,
 ) -> Result<()> {
     if entries.is_empty() {
         return Ok(());
@@ -68,8 +166,8 @@ pub fn save_entry_states_batch(
     // Pre-serialize all entries to detect serialization errors early
     let serialized: Result<Vec<(Vec<u8>, Vec<u8>)>> = entries
         .iter()
-        .map(|(path, content, mode)| {
-            let state = EntryState::new(content, *mode);
+        .map(|(path, content, mode, _)| {
+            let state = EntryState::new(content, *mode).with_stamp(stamp);
             let serialized_state = state.to_bytes()?;
             Ok((path.as_bytes().to_vec(), serialized_state))
         })
@@ -86,6 +184,21 @@ pub fn save_entry_states_batch(
     db.set_batch(ENTRY_STATE_BUCKET, &batch_entries)
         .map_err(|e| Error::State(format!("Failed to save batch entries: {e}")))?;
 
+    let (bases, omitted): (Vec<_>, Vec<_>) = entries
+        .iter()
+        .partition(|(_, content, _, base)| keeps_base_content(content, *base));
+    let bases: Vec<(&[u8], &[u8])> = bases
+        .iter()
+        .map(|(path, content, _, _)| (path.as_bytes(), content.as_slice()))
+        .collect();
+    if !bases.is_empty() {
+        db.set_batch(BASE_CONTENT_BUCKET, &bases)
+            .map_err(|e| Error::State(format!("Failed to save base contents: {e}")))?;
+    }
+    for (path, ..) in omitted {
+        delete_base_content(db, path)?;
+    }
+
     Ok(())
 }
 
@@ -102,7 +215,29 @@ pub fn get_entry_state(db: &RedbPersistentState, path: &str) -> Result<Option<En
     Ok(bytes.and_then(|b| EntryState::from_bytes(&b)))
 }
 
-/// Delete entry state from database
+/// Get the content last applied to a file, for use as a three-way merge base
+///
+/// Returns `None` if no base was kept for the file, or if the kept base no
+/// longer matches the entry state (the file was saved without a base since).
+///
+/// # Errors
+///
+/// Returns an error if the state cannot be retrieved (e.g., read error)
+pub fn get_base_content(db: &RedbPersistentState, path: &str) -> Result<Option<Vec<u8>>> {
+    let Some(state) = get_entry_state(db, path)? else {
+        return Ok(None);
+    };
+    let base = db
+        .get(BASE_CONTENT_BUCKET, path.as_bytes())
+        .map_err(|e| Error::State(format!("Failed to get base content for {path}: {e}")))?;
+
+    Ok(base.filter(|content| crate::hash::hash_content(content) == state.content_hash))
+}
+
+/// Delete entry state, base content and managed path record from database
+///
+/// Afterwards the target is no longer considered managed, so apply will not
+/// offer to remove it from the destination.
 ///
 /// # Errors
 ///
@@ -110,9 +245,61 @@ pub fn get_entry_state(db: &RedbPersistentState, path: &str) -> Result<Option<En
 pub fn delete_entry_state(db: &RedbPersistentState, path: &str) -> Result<()> {
     db.delete(ENTRY_STATE_BUCKET, path.as_bytes())
         .map_err(|e| Error::State(format!("Failed to delete state for {path}: {e}")))?;
+    db.delete(BASE_CONTENT_BUCKET, path.as_bytes())
+        .map_err(|e| Error::State(format!("Failed to delete base content for {path}: {e}")))?;
+    db.delete(MANAGED_PATH_BUCKET, path.as_bytes())
+        .map_err(|e| Error::State(format!("Failed to delete managed path {path}: {e}")))?;
     Ok(())
 }
 
+/// Record targets as managed, in a single transaction
+///
+/// Paths recorded by earlier runs are kept; they are only dropped by
+/// [`delete_entry_state`] once the target is removed or forgotten.
+///
+/// # Errors
+///
+/// Returns an error if any record cannot be saved (e.g., serialization failure, write error)
+pub fn save_managed_paths(db: &RedbPersistentState, paths: &[(String, ManagedKind)]) -> Result<()> {
+    if paths.is_empty() {
+        return Ok(());
+    }
+
+    let serialized = paths
+        .iter()
+        .map(|(path, kind)| Ok((path.as_bytes().to_vec(), kind.to_bytes()?)))
+        .collect::<Result<Vec<_>>>()?;
+    let batch: Vec<(&[u8], &[u8])> = serialized
+        .iter()
+        .map(|(k, v)| (k.as_slice(), v.as_slice()))
+        .collect();
+
+    db.set_batch(MANAGED_PATH_BUCKET, &batch)
+        .map_err(|e| Error::State(format!("Failed to save managed paths: {e}")))?;
+
+    Ok(())
+}
+
+/// Get all managed paths with their kind, sorted by path
+///
+/// # Errors
+///
+/// Returns an error if the records cannot be retrieved from the database
+pub fn get_managed_paths(
+    db: &RedbPersistentState,
+) -> Result<std::collections::BTreeMap<String, ManagedKind>> {
+    let mut paths = std::collections::BTreeMap::new();
+
+    db.for_each(MANAGED_PATH_BUCKET, |key, value| {
+        if let Some(kind) = ManagedKind::from_bytes(value) {
+            paths.insert(String::from_utf8_lossy(key).to_string(), kind);
+        }
+        Ok(())
+    })?;
+
+    Ok(paths)
+}
+
 /// Get all entry states from database
 ///
 /// Returns a `HashMap` of all entries in the database, keyed by path.
@@ -140,6 +327,54 @@ pub fn get_all_entry_states(
     Ok(entries)
 }
 
+/// Save render records in a single transaction
+///
+/// # Errors
+///
+/// Returns an error if any record cannot be saved (e.g., serialization failure, write error)
+pub fn save_render_records(
+    db: &RedbPersistentState,
+    records: &[(String, RenderRecord)],
+) -> Result<()> {
+    if records.is_empty() {
+        return Ok(());
+    }
+
+    let serialized = records
+        .iter()
+        .map(|(path, record)| Ok((path.as_bytes().to_vec(), record.to_bytes()?)))
+        .collect::<Result<Vec<_>>>()?;
+    let batch: Vec<(&[u8], &[u8])> = serialized
+        .iter()
+        .map(|(k, v)| (k.as_slice(), v.as_slice()))
+        .collect();
+
+    db.set_batch(RENDER_CACHE_BUCKET, &batch)
+        .map_err(|e| Error::State(format!("Failed to save render records: {e}")))?;
+
+    Ok(())
+}
+
+/// Get all render records, keyed by target path
+///
+/// # Errors
+///
+/// Returns an error if records cannot be retrieved from the database
+pub fn get_render_records(
+    db: &RedbPersistentState,
+) -> Result<std::collections::HashMap<String, RenderRecord>> {
+    let mut records = std::collections::HashMap::new();
+
+    db.for_each(RENDER_CACHE_BUCKET, |key, value| {
+        if let Some(record) = RenderRecord::from_bytes(value) {
+            records.insert(String::from_utf8_lossy(key).to_string(), record);
+        }
+        Ok(())
+    })?;
+
+    Ok(records)
+}
+
 /// Save config metadata to database
 ///
 /// Stores the rendered configuration along with the template source hash for cache validation.
@@ -188,6 +423,601 @@ pub fn delete_config_metadata(db: &RedbPersistentState) -> Result<()> {
     Ok(())
 }
 
+/// Get all identity hints from database
+///
+/// Returns a map from encrypted source file path to the fingerprint of the
+/// identity that last decrypted it.
+///
+/// # Errors
+///
+/// Returns an error if the hints cannot be read from the database
+pub fn get_identity_hints(
+    db: &RedbPersistentState,
+) -> Result<std::collections::HashMap<String, String>> {
+    let mut hints = std::collections::HashMap::new();
+
+    db.for_each(IDENTITY_HINT_BUCKET, |key, value| {
+        hints.insert(
+            String::from_utf8_lossy(key).to_string(),
+            String::from_utf8_lossy(value).to_string(),
+        );
+        Ok(())
+    })?;
+
+    Ok(hints)
+}
+
+/// Save identity hints to database in a single transaction
+///
+/// # Errors
+///
+/// Returns an error if the hints cannot be written
+pub fn save_identity_hints(db: &RedbPersistentState, hints: &[(String, String)]) -> Result<()> {
+    let batch: Vec<(&[u8], &[u8])> = hints
+        .iter()
+        .map(|(path, fingerprint)| (path.as_bytes(), fingerprint.as_bytes()))
+        .collect();
+
+    db.set_batch(IDENTITY_HINT_BUCKET, &batch)
+        .map_err(|e| Error::State(format!("Failed to save identity hints: {e}")))
+}
+
+/// Save a conflict snapshot to database
+///
+/// Returns the snapshot ID, which is also its key in the database.
+///
+/// # Errors
+///
+/// Returns an error if the snapshot cannot be saved (e.g., serialization failure, write error)
+pub fn save_conflict_snapshot(
+    db: &RedbPersistentState,
+    snapshot: &ConflictSnapshot,
+) -> Result<String> {
+    let id = snapshot.id();
+    db.set(
+        CONFLICT_SNAPSHOT_BUCKET,
+        id.as_bytes(),
+        &snapshot.to_bytes()?,
+    )
+    .map_err(|e| {
+        Error::State(format!(
+            "Failed to save conflict snapshot for {}: {e}",
+            snapshot.path
+        ))
+    })?;
+    Ok(id)
+}
+
+/// Get a conflict snapshot from database by ID
+///
+/// # Errors
+///
+/// Returns an error if the snapshot cannot be read from the database
+pub fn get_conflict_snapshot(
+    db: &RedbPersistentState,
+    id: &str,
+) -> Result<Option<ConflictSnapshot>> {
+    let bytes = db
+        .get(CONFLICT_SNAPSHOT_BUCKET, id.as_bytes())
+        .map_err(|e| Error::State(format!("Failed to get conflict snapshot {id}: {e}")))?;
+
+    Ok(bytes.and_then(|b| ConflictSnapshot::from_bytes(&b)))
+}
+
+/// Get all conflict snapshots from database
+///
+/// Returns `(id, snapshot)` pairs, oldest run first and by path within a run.
+///
+/// # Errors
+///
+/// Returns an error if the snapshots cannot be read from the database
+pub fn get_all_conflict_snapshots(
+    db: &RedbPersistentState,
+) -> Result<Vec<(String, ConflictSnapshot)>> {
+    let mut snapshots = Vec::new();
+
+    db.for_each(CONFLICT_SNAPSHOT_BUCKET, |key, value| {
+        if let Some(snapshot) = ConflictSnapshot::from_bytes(value) {
+            snapshots.push((String::from_utf8_lossy(key).to_string(), snapshot));
+        }
+        Ok(())
+    })?;
+
+    snapshots.sort_by(|(_, a), (_, b)| {
+        (a.stamp.timestamp, &a.stamp.run_id, &a.path).cmp(&(
+            b.stamp.timestamp,
+            &b.stamp.run_id,
+            &b.path,
+        ))
+    });
+    Ok(snapshots)
+}
+
+/// Save a drift event to database
+///
+/// Returns the event ID, which is also its key in the database.
+///
+/// # Errors
+///
+/// Returns an error if the event cannot be saved (e.g., serialization failure, write error)
+pub fn save_drift_event(db: &RedbPersistentState, event: &DriftEvent) -> Result<String> {
+    let id = event.id();
+    db.set(DRIFT_EVENT_BUCKET, id.as_bytes(), &event.to_bytes()?)
+        .map_err(|e| {
+            Error::State(format!(
+                "Failed to save drift event for {}: {e}",
+                event.path
+            ))
+        })?;
+    Ok(id)
+}
+
+/// Get all drift events from database
+///
+/// Returns `(id, event)` pairs, oldest first and by path within a poll.
+///
+/// # Errors
+///
+/// Returns an error if the events cannot be read from the database
+pub fn get_all_drift_events(db: &RedbPersistentState) -> Result<Vec<(String, DriftEvent)>> {
+    let mut events = Vec::new();
+
+    db.for_each(DRIFT_EVENT_BUCKET, |key, value| {
+        if let Some(event) = DriftEvent::from_bytes(value) {
+            events.push((String::from_utf8_lossy(key).to_string(), event));
+        }
+        Ok(())
+    })?;
+
+    events.sort_by(|(_, a), (_, b)| {
+        (a.stamp.timestamp, &a.stamp.run_id, &a.path).cmp(&(
+            b.stamp.timestamp,
+            &b.stamp.run_id,
+            &b.path,
+        ))
+    });
+    Ok(events)
+}
+
+/// Save file content for an apply snapshot
+///
+/// Content is compressed and stored under its hash, so content already saved
+/// by an earlier snapshot is not stored again. Returns the hash to record in
+/// the snapshot.
+///
+/// # Errors
+///
+/// Returns an error if the content cannot be compressed or saved
+pub fn save_snapshot_content(db: &RedbPersistentState, content: &[u8]) -> Result<[u8; 32]> {
+    let hash = crate::hash::hash_content(content);
+    if db
+        .get(SNAPSHOT_CONTENT_BUCKET, &hash)
+        .map_err(|e| Error::State(format!("Failed to read snapshot content: {e}")))?
+        .is_some()
+    {
+        return Ok(hash);
+    }
+
+    let mut encoder = flate2::write::ZlibEncoder::new(Vec::new(), flate2::Compression::default());
+    let compressed = encoder
+        .write_all(content)
+        .and_then(|()| encoder.finish())
+        .map_err(|e| Error::State(format!("Failed to compress snapshot content: {e}")))?;
+    db.set(SNAPSHOT_CONTENT_BUCKET, &hash, &compressed)
+        .map_err(|e| Error::State(format!("Failed to save snapshot content: {e}")))?;
+    Ok(hash)
+}
+
+/// Get file content saved for an apply snapshot by its hash
+///
+/// # Errors
+///
+/// Returns an error if the content cannot be read or decompressed
+pub fn get_snapshot_content(db: &RedbPersistentState, hash: &[u8; 32]) -> Result<Option<Vec<u8>>> {
+    let Some(compressed) = db
+        .get(SNAPSHOT_CONTENT_BUCKET, hash)
+        .map_err(|e| Error::State(format!("Failed to read snapshot content: {e}")))?
+    else {
+        return Ok(None);
+    };
+
+    let mut content = Vec::new();
+    flate2::read::ZlibDecoder::new(compressed.as_slice())
+        .read_to_end(&mut content)
+        .map_err(|e| Error::State(format!("Failed to decompress snapshot content: {e}")))?;
+    Ok(Some(content))
+}
+
+/// Save an apply snapshot to database
+///
+/// Only the `keep` most recent snapshots are kept; older snapshots and the
+/// content no remaining snapshot refers to are deleted. Returns the snapshot
+/// ID, which is also its key in the database.
+///
+/// # Errors
+///
+/// Returns an error if the snapshot cannot be saved or old snapshots cannot be deleted
+pub fn save_apply_snapshot(
+    db: &RedbPersistentState,
+    snapshot: &ApplySnapshot,
+    keep: usize,
+) -> Result<String> {
+    let id = snapshot.id();
+    db.set(APPLY_SNAPSHOT_BUCKET, id.as_bytes(), &snapshot.to_bytes()?)
+        .map_err(|e| Error::State(format!("Failed to save apply snapshot {id}: {e}")))?;
+
+    let snapshots = get_apply_snapshots(db)?;
+    let (old, kept) = snapshots.split_at(snapshots.len().saturating_sub(keep));
+    if old.is_empty() {
+        return Ok(id);
+    }
+    for (old_id, _) in old {
+        db.delete(APPLY_SNAPSHOT_BUCKET, old_id.as_bytes())
+            .map_err(|e| Error::State(format!("Failed to delete apply snapshot {old_id}: {e}")))?;
+    }
+
+    let referenced: HashSet<[u8; 32]> = kept
+        .iter()
+        .flat_map(|(_, snapshot)| &snapshot.files)
+        .filter_map(|file| file.content_hash)
+        .collect();
+    let mut unreferenced = Vec::new();
+    db.for_each(SNAPSHOT_CONTENT_BUCKET, |key, _| {
+        if !<[u8; 32]>::try_from(key).is_ok_and(|hash| referenced.contains(&hash)) {
+            unreferenced.push(key.to_vec());
+        }
+        Ok(())
+    })?;
+    for key in &unreferenced {
+        db.delete(SNAPSHOT_CONTENT_BUCKET, key)
+            .map_err(|e| Error::State(format!("Failed to delete snapshot content: {e}")))?;
+    }
+
+    Ok(id)
+}
+
+/// Get all apply snapshots from database
+///
+/// Returns `(id, snapshot)` pairs, oldest first.
+///
+/// # Errors
+///
+/// Returns an error if the snapshots cannot be read from the database
+pub fn get_apply_snapshots(db: &RedbPersistentState) -> Result<Vec<(String, ApplySnapshot)>> {
+    let mut snapshots = Vec::new();
+
+    db.for_each(APPLY_SNAPSHOT_BUCKET, |key, value| {
+        if let Some(snapshot) = ApplySnapshot::from_bytes(value) {
+            snapshots.push((String::from_utf8_lossy(key).to_string(), snapshot));
+        }
+        Ok(())
+    })?;
+
+    snapshots.sort_by(|(_, a), (_, b)| {
+        (a.stamp.timestamp, &a.stamp.run_id).cmp(&(b.stamp.timestamp, &b.stamp.run_id))
+    });
+    Ok(snapshots)
+}
+
+/// Save the output of a hook run to database
+///
+/// Only the `keep` most recent runs of each hook are kept; older runs of the
+/// same hook are deleted. Returns the log ID, which is also its key in the
+/// database.
+///
+/// # Errors
+///
+/// Returns an error if the log cannot be saved or old logs cannot be deleted
+pub fn save_hook_log(db: &RedbPersistentState, log: &HookRunLog, keep: usize) -> Result<String> {
+    let id = log.id();
+    db.set(HOOK_LOG_BUCKET, id.as_bytes(), &log.to_bytes()?)
+        .map_err(|e| {
+            Error::State(format!(
+                "Failed to save log of hook '{}': {e}",
+                log.run.name
+            ))
+        })?;
+
+    let runs: Vec<String> = get_hook_logs(db, Some(&log.run.name))?
+        .into_iter()
+        .map(|(id, _)| id)
+        .collect();
+    for old_id in &runs[..runs.len().saturating_sub(keep)] {
+        db.delete(HOOK_LOG_BUCKET, old_id.as_bytes())
+            .map_err(|e| Error::State(format!("Failed to delete hook log {old_id}: {e}")))?;
+    }
+
+    Ok(id)
+}
+
+/// Get hook run logs from database
+///
+/// Returns `(id, log)` pairs for the hook named `name`, or for all hooks if
+/// `name` is `None`, oldest run first.
+///
+/// # Errors
+///
+/// Returns an error if the logs cannot be read from the database
+pub fn get_hook_logs(
+    db: &RedbPersistentState,
+    name: Option<&str>,
+) -> Result<Vec<(String, HookRunLog)>> {
+    let mut logs = Vec::new();
+
+    db.for_each(HOOK_LOG_BUCKET, |key, value| {
+        if let Some(log) = HookRunLog::from_bytes(value)
+            && name.is_none_or(|name| log.run.name == name)
+        {
+            logs.push((String::from_utf8_lossy(key).to_string(), log));
+        }
+        Ok(())
+    })?;
+
+    logs.sort_by(|(_, a), (_, b)| {
+        (a.stamp.timestamp, &a.stamp.run_id, &a.run.name).cmp(&(
+            b.stamp.timestamp,
+            &b.stamp.run_id,
+            &b.run.name,
+        ))
+    });
+    Ok(logs)
+}
+
+/// Save the downloaded content of an external URL to database
+///
+/// # Errors
+///
+/// Returns an error if the content cannot be saved (e.g., serialization failure, write error)
+pub fn save_external_cache(
+    db: &RedbPersistentState,
+    url: &str,
+    cache: &ExternalCache,
+) -> Result<()> {
+    db.set(EXTERNAL_CACHE_BUCKET, url.as_bytes(), &cache.to_bytes()?)
+        .map_err(|e| Error::State(format!("Failed to cache external {url}: {e}")))
+}
+
+/// Get the cached content of an external URL from database
+///
+/// # Errors
+///
+/// Returns an error if the cache cannot be read from the database
+pub fn get_external_cache(db: &RedbPersistentState, url: &str) -> Result<Option<ExternalCache>> {
+    let bytes = db
+        .get(EXTERNAL_CACHE_BUCKET, url.as_bytes())
+        .map_err(|e| Error::State(format!("Failed to get cached external {url}: {e}")))?;
+
+    Ok(bytes.and_then(|b| ExternalCache::from_bytes(&b)))
+}
+
+/// Save the answer given to a config template prompt
+///
+/// # Errors
+///
+/// Returns an error if the answer cannot be saved (e.g., serialization failure, write error)
+pub fn save_prompt_answer(
+    db: &RedbPersistentState,
+    prompt: &str,
+    answer: &serde_json::Value,
+) -> Result<()> {
+    let bytes = serde_json::to_vec(answer)
+        .map_err(|e| Error::State(format!("Failed to serialize answer to '{prompt}': {e}")))?;
+    db.set(PROMPT_ANSWER_BUCKET, prompt.as_bytes(), &bytes)
+        .map_err(|e| Error::State(format!("Failed to save answer to '{prompt}': {e}")))
+}
+
+/// Get all saved config template prompt answers, keyed by prompt
+///
+/// # Errors
+///
+/// Returns an error if the answers cannot be read from the database
+pub fn get_prompt_answers(
+    db: &RedbPersistentState,
+) -> Result<indexmap::IndexMap<String, serde_json::Value>> {
+    let mut answers = indexmap::IndexMap::new();
+
+    db.for_each(PROMPT_ANSWER_BUCKET, |key, value| {
+        if let Ok(answer) = serde_json::from_slice(value) {
+            answers.insert(String::from_utf8_lossy(key).to_string(), answer);
+        }
+        Ok(())
+    })?;
+
+    Ok(answers)
+}
+
+/// Schema version of databases written by this version of guisu
+pub const SCHEMA_VERSION: u32 = 1;
+
+/// Key of the schema version in [`META_BUCKET`]
+const SCHEMA_VERSION_KEY: &[u8] = b"schemaVersion";
+
+/// An in-place upgrade of the database layout
+pub struct Migration {
+    /// Schema version the migration upgrades to, from the one before it
+    pub version: u32,
+    /// What the migration changes
+    pub description: &'static str,
+    run: fn(&RedbPersistentState) -> Result<()>,
+}
+
+/// Every migration, in order of version
+const MIGRATIONS: &[Migration] = &[Migration {
+    version: 1,
+    description: "Rewrite entry and hook state records without run stamps in the current layout",
+    run: upgrade_legacy_records,
+}];
+
+/// Open the state database at `path`, upgrading it if it has an older schema
+///
+/// Before any migration runs, the file is copied next to itself as
+/// `<name>.v<version>.bak` (see [`backup_path`]).
+///
+/// # Errors
+///
+/// Returns an error if the database cannot be opened, was written by a newer
+/// version of guisu, or cannot be backed up or migrated
+pub fn open(path: &Path) -> Result<RedbPersistentState> {
+    let db = RedbPersistentState::new(path)?;
+    let version = schema_version(&db)?;
+
+    if !pending_migrations(version)?.is_empty() {
+        let backup = backup_path(path, version);
+        std::fs::copy(path, &backup).map_err(|e| {
+            Error::State(format!(
+                "Failed to back up {} to {}: {e}",
+                path.display(),
+                backup.display()
+            ))
+        })?;
+        tracing::info!(
+            from = version,
+            to = SCHEMA_VERSION,
+            backup = %backup.display(),
+            "Migrating state database"
+        );
+    }
+    migrate(&db)?;
+
+    Ok(db)
+}
+
+/// Path the database at `path` is backed up to before migrating from `version`
+#[must_use]
+pub fn backup_path(path: &Path, version: u32) -> PathBuf {
+    let mut name = path.file_name().unwrap_or_default().to_os_string();
+    name.push(format!(".v{version}.bak"));
+    path.with_file_name(name)
+}
+
+/// Schema version of the database
+///
+/// Databases without a recorded version were written before versioning and
+/// are version 0, unless they hold no records at all.
+///
+/// # Errors
+///
+/// Returns an error if the database cannot be read or the version is malformed
+pub fn schema_version(db: &RedbPersistentState) -> Result<u32> {
+    if let Some(bytes) = db.get(META_BUCKET, SCHEMA_VERSION_KEY)? {
+        let bytes: [u8; 4] = bytes
+            .as_slice()
+            .try_into()
+            .map_err(|_| Error::State("Malformed schema version in state database".to_string()))?;
+        return Ok(u32::from_le_bytes(bytes));
+    }
+
+    for bucket in crate::state::BUCKETS {
+        let mut empty = true;
+        db.for_each(bucket, |_, _| {
+            empty = false;
+            Ok(())
+        })?;
+        if !empty {
+            return Ok(0);
+        }
+    }
+    Ok(SCHEMA_VERSION)
+}
+
+/// Migrations that upgrade a database from `version` to [`SCHEMA_VERSION`]
+///
+/// # Errors
+///
+/// Returns an error if `version` is newer than [`SCHEMA_VERSION`]
+pub fn pending_migrations(version: u32) -> Result<Vec<&'static Migration>> {
+    if version > SCHEMA_VERSION {
+        return Err(Error::State(format!(
+            "State database has schema version {version}, but this guisu only supports up to \
+             {SCHEMA_VERSION}. Upgrade guisu, or reset the state with `guisu state reset`."
+        )));
+    }
+    Ok(MIGRATIONS
+        .iter()
+        .filter(|migration| migration.version > version)
+        .collect())
+}
+
+/// Run the pending migrations, returning those that ran
+///
+/// The schema version is recorded after each migration, so an interrupted
+/// upgrade resumes where it stopped.
+///
+/// # Errors
+///
+/// Returns an error if the database is newer than supported or a migration fails
+pub fn migrate(db: &RedbPersistentState) -> Result<Vec<&'static Migration>> {
+    let version = schema_version(db)?;
+    let pending = pending_migrations(version)?;
+
+    for migration in &pending {
+        (migration.run)(db).map_err(|e| {
+            Error::State(format!(
+                "Failed to migrate state database to version {}: {e}",
+                migration.version
+            ))
+        })?;
+        set_schema_version(db, migration.version)?;
+    }
+    if db.get(META_BUCKET, SCHEMA_VERSION_KEY)?.is_none() {
+        set_schema_version(db, SCHEMA_VERSION)?;
+    }
+
+    Ok(pending)
+}
+
+/// Run the pending migrations on a copy of the database at `path`
+///
+/// The database itself is left untouched; use this to check that an upgrade
+/// succeeds before running it.
+///
+/// # Errors
+///
+/// Returns an error if the database cannot be copied, opened, or migrated
+pub fn verify_migrations(path: &Path) -> Result<Vec<&'static Migration>> {
+    let temp_dir = tempfile::tempdir()
+        .map_err(|e| Error::State(format!("Failed to create temporary directory: {e}")))?;
+    let copy = temp_dir.path().join("state.db");
+    std::fs::copy(path, &copy)
+        .map_err(|e| Error::State(format!("Failed to copy {}: {e}", path.display())))?;
+
+    let db = RedbPersistentState::new(&copy)?;
+    migrate(&db)
+}
+
+fn set_schema_version(db: &RedbPersistentState, version: u32) -> Result<()> {
+    db.set(META_BUCKET, SCHEMA_VERSION_KEY, &version.to_le_bytes())
+}
+
+/// Migration 1: re-encode records still in a layout decoded through a fallback
+fn upgrade_legacy_records(db: &RedbPersistentState) -> Result<()> {
+    let mut upgraded = Vec::new();
+    db.for_each(ENTRY_STATE_BUCKET, |key, value| {
+        if let Some(state) = EntryState::from_bytes(value) {
+            let bytes = state.to_bytes()?;
+            if bytes != value {
+                upgraded.push((key.to_vec(), bytes));
+            }
+        }
+        Ok(())
+    })?;
+    let batch: Vec<(&[u8], &[u8])> = upgraded
+        .iter()
+        .map(|(key, value)| (key.as_slice(), value.as_slice()))
+        .collect();
+    db.set_batch(ENTRY_STATE_BUCKET, &batch)?;
+
+    if let Some(value) = db.get(HOOK_STATE_BUCKET, HOOK_STATE_KEY)?
+        && let Some(state) = HookState::from_bytes(&value)
+    {
+        let bytes = state.to_bytes()?;
+        if bytes != value {
+            db.set(HOOK_STATE_BUCKET, HOOK_STATE_KEY, &bytes)?;
+        }
+    }
+    Ok(())
+}
+
 #[cfg(test)]
 mod tests {
     #![allow(clippy::unwrap_used, clippy::panic)]
@@ -896,4 +1726,456 @@ mod tests {
         assert_eq!(path1, path2);
         assert!(path1.to_string_lossy().contains("state.db"));
     }
+
+    #[test]
+    fn test_save_entry_states_batch_records_run_stamp() {
+        let (_temp, db) = test_db_setup();
+        let clock = crate::clock::StateClock::fixed(1_700_000_000);
+        let stamp = clock.begin_run();
+
+        save_entry_states_batch(
+            &db,
+            &[
+                ("a.txt".to_string(), b"a".to_vec(), None, MergeBase::Keep),
+                (
+                    "b.txt".to_string(),
+                    b"b".to_vec(),
+                    Some(0o600),
+                    MergeBase::Omit,
+                ),
+            ],
+            &stamp,
+        )
+        .unwrap();
+        save_entry_state(
+            &db,
+            "c.txt",
+            b"c",
+            None,
+            MergeBase::Keep,
+            &clock.begin_run(),
+        )
+        .unwrap();
+
+        let a = get_entry_state(&db, "a.txt").unwrap().unwrap();
+        let b = get_entry_state(&db, "b.txt").unwrap().unwrap();
+        let c = get_entry_state(&db, "c.txt").unwrap().unwrap();
+        assert_eq!(a.last_applied, Some(stamp.clone()));
+        assert_eq!(b.last_applied, Some(stamp));
+        assert_eq!(c.last_applied.unwrap().run_id, "run-2");
+    }
+
+    #[test]
+    fn test_render_records_round_trip() {
+        let (_temp, db) = test_db_setup();
+        let record = RenderRecord {
+            input_hash: [1; 32],
+            content_hash: [2; 32],
+            dependencies: vec![crate::state::RenderDependency {
+                path: "/src/home/.gitconfig.common".to_string(),
+                hash: [3; 32],
+            }],
+            volatile: false,
+        };
+
+        save_render_records(&db, &[(".gitconfig".to_string(), record.clone())]).unwrap();
+        save_render_records(&db, &[]).unwrap();
+
+        let records = get_render_records(&db).unwrap();
+        assert_eq!(records.len(), 1);
+        assert_eq!(records[".gitconfig"], record);
+    }
+
+    #[test]
+    fn test_base_content_kept_for_shared_text_files() {
+        let (_temp, db) = test_db_setup();
+        let stamp = crate::clock::StateClock::fixed(1_700_000_000).begin_run();
+
+        save_entry_states_batch(
+            &db,
+            &[
+                (
+                    ".zshrc".to_string(),
+                    b"export A=1\n".to_vec(),
+                    Some(0o644),
+                    MergeBase::for_file(false, 0o644),
+                ),
+                (
+                    ".netrc".to_string(),
+                    b"password\n".to_vec(),
+                    Some(0o600),
+                    MergeBase::for_file(false, 0o600),
+                ),
+                (
+                    ".env".to_string(),
+                    b"TOKEN=secret\n".to_vec(),
+                    Some(0o644),
+                    MergeBase::for_file(true, 0o644),
+                ),
+                ("blob".to_string(), vec![0xff, 0xfe], None, MergeBase::Keep),
+            ],
+            &stamp,
+        )
+        .unwrap();
+
+        assert_eq!(
+            get_base_content(&db, ".zshrc").unwrap().as_deref(),
+            Some(&b"export A=1\n"[..])
+        );
+        assert!(get_base_content(&db, ".netrc").unwrap().is_none());
+        assert!(get_base_content(&db, ".env").unwrap().is_none());
+        assert!(get_base_content(&db, "blob").unwrap().is_none());
+
+        // A file that stops qualifying has its base deleted
+        save_entry_state(
+            &db,
+            ".zshrc",
+            b"#!binary\xff",
+            None,
+            MergeBase::Keep,
+            &stamp,
+        )
+        .unwrap();
+        assert!(db.get(BASE_CONTENT_BUCKET, b".zshrc").unwrap().is_none());
+        save_entry_state(
+            &db,
+            ".zshrc",
+            b"export A=2\n",
+            None,
+            MergeBase::Keep,
+            &stamp,
+        )
+        .unwrap();
+        save_entry_state(
+            &db,
+            ".zshrc",
+            b"export A=3\n",
+            None,
+            MergeBase::Omit,
+            &stamp,
+        )
+        .unwrap();
+        assert!(db.get(BASE_CONTENT_BUCKET, b".zshrc").unwrap().is_none());
+
+        save_entry_state(
+            &db,
+            ".zshrc",
+            b"export A=2\n",
+            None,
+            MergeBase::Keep,
+            &stamp,
+        )
+        .unwrap();
+        delete_entry_state(&db, ".zshrc").unwrap();
+        assert!(db.get(BASE_CONTENT_BUCKET, b".zshrc").unwrap().is_none());
+    }
+
+    #[test]
+    fn test_managed_paths_kept_until_entry_deleted() {
+        let (_temp, db) = test_db_setup();
+
+        save_managed_paths(
+            &db,
+            &[
+                (".zshrc".to_string(), ManagedKind::File),
+                (".config".to_string(), ManagedKind::Directory),
+            ],
+        )
+        .unwrap();
+        save_managed_paths(&db, &[(".vimrc".to_string(), ManagedKind::Symlink)]).unwrap();
+
+        let paths = get_managed_paths(&db).unwrap();
+        assert_eq!(
+            paths.into_iter().collect::<Vec<_>>(),
+            vec![
+                (".config".to_string(), ManagedKind::Directory),
+                (".vimrc".to_string(), ManagedKind::Symlink),
+                (".zshrc".to_string(), ManagedKind::File),
+            ]
+        );
+
+        delete_entry_state(&db, ".vimrc").unwrap();
+        assert!(!get_managed_paths(&db).unwrap().contains_key(".vimrc"));
+    }
+
+    #[test]
+    fn test_entry_state_decodes_legacy_layout() {
+        #[derive(bincode::Encode)]
+        struct LegacyEntryState {
+            content_hash: [u8; 32],
+            mode: Option<u32>,
+        }
+
+        let legacy = LegacyEntryState {
+            content_hash: crate::hash::hash_content(b"content"),
+            mode: Some(0o644),
+        };
+        let bytes = bincode::encode_to_vec(&legacy, bincode::config::standard()).unwrap();
+
+        let state = EntryState::from_bytes(&bytes).unwrap();
+        assert_eq!(state, EntryState::new(b"content", Some(0o644)));
+        assert!(state.last_applied.is_none());
+    }
+
+    #[test]
+    fn test_identity_hints_roundtrip() {
+        let (_temp, db) = test_db_setup();
+        assert!(get_identity_hints(&db).unwrap().is_empty());
+
+        save_identity_hints(
+            &db,
+            &[
+                ("/src/a.age".to_string(), "age1first".to_string()),
+                ("/src/b.age".to_string(), "age1second".to_string()),
+            ],
+        )
+        .unwrap();
+        save_identity_hints(&db, &[("/src/a.age".to_string(), "age1third".to_string())]).unwrap();
+
+        let hints = get_identity_hints(&db).unwrap();
+        assert_eq!(hints.len(), 2);
+        assert_eq!(hints["/src/a.age"], "age1third");
+        assert_eq!(hints["/src/b.age"], "age1second");
+    }
+
+    #[test]
+    fn test_conflict_snapshots_roundtrip() {
+        use crate::clock::StateClock;
+
+        let (_temp, db) = test_db_setup();
+        let clock = StateClock::fixed(1_700_000_000);
+        let first = clock.begin_run();
+        let second = clock.begin_run();
+
+        let older = ConflictSnapshot::new(".zshrc", b"local tweak".to_vec(), Some(0o644), &first);
+        let newer = ConflictSnapshot::new(".bashrc", b"alias ll".to_vec(), None, &second);
+        let newer_id = save_conflict_snapshot(&db, &newer).unwrap();
+        let older_id = save_conflict_snapshot(&db, &older).unwrap();
+
+        assert_ne!(older_id, newer_id);
+        assert_eq!(get_conflict_snapshot(&db, &older_id).unwrap(), Some(older));
+        assert!(get_conflict_snapshot(&db, "missing").unwrap().is_none());
+
+        let ids: Vec<String> = get_all_conflict_snapshots(&db)
+            .unwrap()
+            .into_iter()
+            .map(|(id, _)| id)
+            .collect();
+        assert_eq!(ids, [older_id, newer_id]);
+    }
+
+    #[test]
+    fn test_drift_events_roundtrip() {
+        use crate::clock::StateClock;
+
+        let (_temp, db) = test_db_setup();
+        let clock = StateClock::fixed(1_700_000_000);
+        let first = clock.begin_run();
+        let second = clock.begin_run();
+
+        let newer = DriftEvent::new(".zshrc", "-a\n+b\n".to_string(), &second);
+        let older = DriftEvent::new(".zshrc", "-x\n+a\n".to_string(), &first);
+        let newer_id = save_drift_event(&db, &newer).unwrap();
+        let older_id = save_drift_event(&db, &older).unwrap();
+
+        assert_ne!(older_id, newer_id);
+        assert_eq!(
+            get_all_drift_events(&db).unwrap(),
+            [(older_id, older), (newer_id, newer)]
+        );
+    }
+
+    #[test]
+    fn test_hook_logs_keep_most_recent_runs() {
+        use crate::clock::StateClock;
+        use crate::hooks::HookRun;
+
+        let (_temp, db) = test_db_setup();
+        let clock = StateClock::fixed(1_700_000_000);
+        let run = |name: &str, stdout: &str| HookRun {
+            name: name.to_string(),
+            stage: "post".to_string(),
+            stdout: stdout.to_string(),
+            stderr: String::new(),
+            exit_code: Some(0),
+            error: None,
+            duration_ms: 5,
+        };
+
+        save_hook_log(
+            &db,
+            &HookRunLog::new(run("build", "1"), &clock.begin_run()),
+            2,
+        )
+        .unwrap();
+        save_hook_log(
+            &db,
+            &HookRunLog::new(run("other", "x"), &clock.begin_run()),
+            2,
+        )
+        .unwrap();
+        save_hook_log(
+            &db,
+            &HookRunLog::new(run("build", "2"), &clock.begin_run()),
+            2,
+        )
+        .unwrap();
+        save_hook_log(
+            &db,
+            &HookRunLog::new(run("build", "3"), &clock.begin_run()),
+            2,
+        )
+        .unwrap();
+
+        let build: Vec<String> = get_hook_logs(&db, Some("build"))
+            .unwrap()
+            .into_iter()
+            .map(|(_, log)| log.run.stdout)
+            .collect();
+        assert_eq!(build, ["2", "3"]);
+        assert_eq!(get_hook_logs(&db, None).unwrap().len(), 3);
+    }
+
+    #[test]
+    fn test_save_and_get_prompt_answers() {
+        let (_temp, db) = test_db_setup();
+        assert!(get_prompt_answers(&db).unwrap().is_empty());
+
+        save_prompt_answer(&db, "email", &serde_json::json!("me@example.com")).unwrap();
+        save_prompt_answer(&db, "work", &serde_json::json!(true)).unwrap();
+        save_prompt_answer(&db, "work", &serde_json::json!(false)).unwrap();
+
+        let answers = get_prompt_answers(&db).unwrap();
+        assert_eq!(answers.len(), 2);
+        assert_eq!(answers["email"], serde_json::json!("me@example.com"));
+        assert_eq!(answers["work"], serde_json::json!(false));
+    }
+
+    #[test]
+    fn test_apply_snapshots_share_content_and_keep_most_recent() {
+        use crate::clock::StateClock;
+        use crate::state::SnapshotFile;
+
+        let (_temp, db) = test_db_setup();
+        let clock = StateClock::fixed(1_700_000_000);
+        let snapshot = |content: &[u8]| {
+            let mut snapshot = ApplySnapshot::new(&clock.begin_run());
+            snapshot.files.push(SnapshotFile {
+                path: ".bashrc".to_string(),
+                content_hash: Some(save_snapshot_content(&db, content).unwrap()),
+                mode: Some(0o644),
+            });
+            snapshot
+        };
+
+        let first = snapshot(b"one");
+        save_apply_snapshot(&db, &first, 2).unwrap();
+        let second = snapshot(b"one");
+        save_apply_snapshot(&db, &second, 2).unwrap();
+        assert_eq!(
+            first.files[0].content_hash, second.files[0].content_hash,
+            "identical content is stored once"
+        );
+
+        let third = snapshot(b"two");
+        let id = save_apply_snapshot(&db, &third, 2).unwrap();
+
+        let ids: Vec<String> = get_apply_snapshots(&db)
+            .unwrap()
+            .into_iter()
+            .map(|(id, _)| id)
+            .collect();
+        assert_eq!(ids, vec![second.id(), id]);
+
+        let hash = third.files[0].content_hash.unwrap();
+        assert_eq!(get_snapshot_content(&db, &hash).unwrap().unwrap(), b"two");
+        // Content of the dropped snapshot is still referenced by the second one
+        let hash = second.files[0].content_hash.unwrap();
+        assert_eq!(get_snapshot_content(&db, &hash).unwrap().unwrap(), b"one");
+    }
+
+    #[test]
+    fn test_apply_snapshot_content_collected_when_unreferenced() {
+        use crate::clock::StateClock;
+
+        let (_temp, db) = test_db_setup();
+        let clock = StateClock::fixed(1_700_000_000);
+        let hash = save_snapshot_content(&db, b"orphan").unwrap();
+
+        save_apply_snapshot(&db, &ApplySnapshot::new(&clock.begin_run()), 1).unwrap();
+        save_apply_snapshot(&db, &ApplySnapshot::new(&clock.begin_run()), 1).unwrap();
+
+        assert!(get_snapshot_content(&db, &hash).unwrap().is_none());
+    }
+
+    /// Write an entry state in the layout before `last_applied` was added
+    fn write_legacy_entry(path: &Path) -> Vec<u8> {
+        let db = RedbPersistentState::new(path).unwrap();
+        let legacy = bincode::encode_to_vec(
+            (crate::state::hash_data(b"content"), Some(0o644_u32)),
+            bincode::config::standard(),
+        )
+        .unwrap();
+        db.set(ENTRY_STATE_BUCKET, b".bashrc", &legacy).unwrap();
+        legacy
+    }
+
+    #[test]
+    fn test_open_stamps_new_database() {
+        let temp = TempDir::new().unwrap();
+        let path = temp.path().join("state.db");
+
+        let db = open(&path).unwrap();
+        assert_eq!(schema_version(&db).unwrap(), SCHEMA_VERSION);
+        assert!(db.get(META_BUCKET, SCHEMA_VERSION_KEY).unwrap().is_some());
+        assert!(!backup_path(&path, 0).exists());
+    }
+
+    #[test]
+    fn test_open_migrates_legacy_database() {
+        let temp = TempDir::new().unwrap();
+        let path = temp.path().join("state.db");
+        let legacy = write_legacy_entry(&path);
+
+        let db = open(&path).unwrap();
+        assert_eq!(schema_version(&db).unwrap(), SCHEMA_VERSION);
+        let upgraded = db.get(ENTRY_STATE_BUCKET, b".bashrc").unwrap().unwrap();
+        assert_ne!(upgraded, legacy);
+        let state = get_entry_state(&db, ".bashrc").unwrap().unwrap();
+        assert_eq!(state.mode, Some(0o644));
+        assert_eq!(state.content_hash, crate::state::hash_data(b"content"));
+
+        let backup = backup_path(&path, 0);
+        assert_eq!(backup, temp.path().join("state.db.v0.bak"));
+        drop(db);
+        let backup = RedbPersistentState::new(&backup).unwrap();
+        assert_eq!(schema_version(&backup).unwrap(), 0);
+    }
+
+    #[test]
+    fn test_verify_migrations_leaves_database_untouched() {
+        let temp = TempDir::new().unwrap();
+        let path = temp.path().join("state.db");
+        write_legacy_entry(&path);
+
+        let ran = verify_migrations(&path).unwrap();
+        assert_eq!(ran.len(), MIGRATIONS.len());
+
+        let db = RedbPersistentState::new(&path).unwrap();
+        assert_eq!(schema_version(&db).unwrap(), 0);
+    }
+
+    #[test]
+    fn test_open_rejects_newer_schema() {
+        let temp = TempDir::new().unwrap();
+        let path = temp.path().join("state.db");
+        let db = RedbPersistentState::new(&path).unwrap();
+        set_schema_version(&db, SCHEMA_VERSION + 1).unwrap();
+        drop(db);
+
+        let Err(err) = open(&path) else {
+            panic!("newer schema should be rejected");
+        };
+        assert!(err.to_string().contains("Upgrade guisu"));
+    }
 }
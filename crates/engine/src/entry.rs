@@ -7,6 +7,7 @@
 //! - [`DestEntry`]: Entries in the destination (filesystem)
 
 use crate::attr::FileAttributes;
+use crate::pool::SharedContent;
 use guisu_core::path::{RelPath, SourceRelPath};
 use serde::{Deserialize, Serialize};
 use std::path::PathBuf;
@@ -41,16 +42,28 @@ pub enum SourceEntry {
         attributes: FileAttributes,
     },
 
-    /// A symbolic link
+    /// A symbolic link, stored as a `symlink_` file holding its target
     Symlink {
-        /// Path in the source directory
+        /// Path in the source directory (with encoded attributes)
         source_path: SourceRelPath,
 
         /// Path in the target/destination
         target_path: RelPath,
 
-        /// Where the symlink points to
-        link_target: PathBuf,
+        /// Parsed attributes from the filename
+        attributes: FileAttributes,
+    },
+
+    /// A script executed during apply instead of being written
+    Script {
+        /// Path in the source directory (with encoded attributes)
+        source_path: SourceRelPath,
+
+        /// Script name (without encoded attributes), used to track its runs
+        target_path: RelPath,
+
+        /// Parsed attributes from the filename
+        attributes: FileAttributes,
     },
 }
 
@@ -61,7 +74,8 @@ impl SourceEntry {
         match self {
             SourceEntry::File { source_path, .. }
             | SourceEntry::Directory { source_path, .. }
-            | SourceEntry::Symlink { source_path, .. } => source_path,
+            | SourceEntry::Symlink { source_path, .. }
+            | SourceEntry::Script { source_path, .. } => source_path,
         }
     }
 
@@ -71,7 +85,8 @@ impl SourceEntry {
         match self {
             SourceEntry::File { target_path, .. }
             | SourceEntry::Directory { target_path, .. }
-            | SourceEntry::Symlink { target_path, .. } => target_path,
+            | SourceEntry::Symlink { target_path, .. }
+            | SourceEntry::Script { target_path, .. } => target_path,
         }
     }
 
@@ -79,10 +94,10 @@ impl SourceEntry {
     #[must_use]
     pub fn attributes(&self) -> Option<&FileAttributes> {
         match self {
-            SourceEntry::File { attributes, .. } | SourceEntry::Directory { attributes, .. } => {
-                Some(attributes)
-            }
-            SourceEntry::Symlink { .. } => None,
+            SourceEntry::File { attributes, .. }
+            | SourceEntry::Directory { attributes, .. }
+            | SourceEntry::Symlink { attributes, .. }
+            | SourceEntry::Script { attributes, .. } => Some(attributes),
         }
     }
 
@@ -97,6 +112,12 @@ impl SourceEntry {
         self.attributes()
             .is_some_and(super::attr::FileAttributes::is_encrypted)
     }
+
+    /// Check if this entry is a secret env file
+    pub fn is_env(&self) -> bool {
+        self.attributes()
+            .is_some_and(super::attr::FileAttributes::is_env)
+    }
 }
 
 /// A target entry representing the desired state
@@ -110,8 +131,9 @@ pub enum TargetEntry {
         /// Path in the destination
         path: RelPath,
 
-        /// File content (after template rendering and decryption)
-        content: Vec<u8>,
+        /// File content (after template rendering and decryption), shared
+        /// with other entries that rendered to the same bytes
+        content: SharedContent,
 
         /// Content hash (blake3) for fast drift detection
         content_hash: [u8; 32],
@@ -120,6 +142,30 @@ pub enum TargetEntry {
         mode: Option<u32>,
     },
 
+    /// A regular file too large to hold in memory
+    ///
+    /// Its content is streamed from the source file through the content
+    /// processor when it is written.
+    LargeFile {
+        /// Path in the destination
+        path: RelPath,
+
+        /// Source file the content is streamed from
+        source: PathBuf,
+
+        /// Attributes of the source file (encrypted, compressed)
+        attributes: FileAttributes,
+
+        /// Content hash (blake3) for fast drift detection
+        content_hash: [u8; 32],
+
+        /// Size of the content in bytes
+        size: u64,
+
+        /// Unix file permissions mode (optional)
+        mode: Option<u32>,
+    },
+
     /// A directory
     Directory {
         /// Path in the destination
@@ -152,6 +198,7 @@ impl TargetEntry {
     pub fn path(&self) -> &RelPath {
         match self {
             TargetEntry::File { path, .. }
+            | TargetEntry::LargeFile { path, .. }
             | TargetEntry::Directory { path, .. }
             | TargetEntry::Symlink { path, .. }
             | TargetEntry::Remove { path } => path,
@@ -163,7 +210,9 @@ impl TargetEntry {
     #[must_use]
     pub fn mode(&self) -> Option<u32> {
         match self {
-            TargetEntry::File { mode, .. } | TargetEntry::Directory { mode, .. } => *mode,
+            TargetEntry::File { mode, .. }
+            | TargetEntry::LargeFile { mode, .. }
+            | TargetEntry::Directory { mode, .. } => *mode,
             _ => None,
         }
     }
@@ -265,7 +314,18 @@ impl DestEntry {
     pub fn matches(&self, target: &TargetEntry) -> bool {
         match (self.kind, target) {
             (EntryKind::File, TargetEntry::File { content, mode, .. }) => {
-                self.content.as_ref() == Some(content) && self.mode == *mode
+                self.content.as_deref() == Some(&**content) && self.mode == *mode
+            }
+            (
+                EntryKind::File,
+                TargetEntry::LargeFile {
+                    content_hash, mode, ..
+                },
+            ) => {
+                self.content
+                    .as_deref()
+                    .is_some_and(|content| crate::hash::hash_content(content) == *content_hash)
+                    && self.mode == *mode
             }
             (EntryKind::Directory, TargetEntry::Directory { mode, .. }) => self.mode == *mode,
             (EntryKind::Symlink, TargetEntry::Symlink { target, .. }) => {
@@ -0,0 +1,885 @@
+//! External files and archives
+//!
+//! Some dotfiles are better downloaded than vendored into the source
+//! directory: shell plugin frameworks, fonts, single-binary tools.
+//! `.guisu/externals.toml` maps target paths to URLs:
+//!
+//! ```toml
+//! [".oh-my-zsh"]
+//! type = "archive"
+//! url = "https://github.com/ohmyzsh/ohmyzsh/archive/master.tar.gz"
+//! strip_components = 1
+//! refresh_period = 604800
+//!
+//! [".local/bin/jq"]
+//! type = "file"
+//! url = "https://github.com/jqlang/jq/releases/download/jq-1.7.1/jq-linux-amd64"
+//! checksum = "sha256:5942c9b0934e510ee61eb3e30273f1b3fe2590df93933a93d7c58b81d19c8ff5"
+//! executable = true
+//! ```
+//!
+//! Downloads are cached in the state database and reused until they are
+//! older than `refresh_period` seconds (never, if unset). Archives
+//! (`.tar.gz`, `.tgz`, `.tar`, `.zip`) are extracted in memory into target
+//! entries under their path; members whose paths leave the target are
+//! rejected. Downloading uses `curl`.
+
+use crate::clock::RunStamp;
+use crate::database;
+use crate::entry::TargetEntry;
+use crate::hash::hash_content;
+use crate::state::RedbPersistentState;
+use guisu_core::path::RelPath;
+use guisu_core::{Error, Result};
+use indexmap::IndexMap;
+use serde::Deserialize;
+use std::collections::BTreeMap;
+use std::fs;
+use std::io::Read;
+use std::path::{Component, Path, PathBuf};
+use std::process::Command;
+
+/// Location of the externals configuration, relative to the source directory
+pub const EXTERNALS_FILE: &str = ".guisu/externals.toml";
+
+/// Kind of an external
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ExternalKind {
+    /// A single file written to the target path
+    File,
+    /// An archive extracted into the target path
+    Archive,
+}
+
+/// An external file or archive declared in `.guisu/externals.toml`
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct External {
+    /// Whether the URL points to a file or an archive
+    #[serde(rename = "type")]
+    pub kind: ExternalKind,
+
+    /// URL to download
+    pub url: String,
+
+    /// Expected checksum of the download, as `sha256:<hex>`
+    #[serde(default)]
+    pub checksum: Option<String>,
+
+    /// Seconds after which the download is fetched again (0 = never)
+    #[serde(default)]
+    pub refresh_period: u64,
+
+    /// Leading path components to strip from archive members
+    #[serde(default)]
+    pub strip_components: usize,
+
+    /// Make a downloaded file executable
+    #[serde(default)]
+    pub executable: bool,
+}
+
+/// Externals declared in `.guisu/externals.toml`, keyed by target path
+#[derive(Debug, Default)]
+pub struct Externals {
+    entries: IndexMap<RelPath, External>,
+}
+
+impl Externals {
+    /// Load externals from `.guisu/externals.toml` in the source directory
+    ///
+    /// A missing file means there are no externals.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the file cannot be read or parsed, or a target path
+    /// is not a plain relative path
+    pub fn load(source_dir: &Path) -> Result<Self> {
+        let path = source_dir.join(EXTERNALS_FILE);
+        if !path.exists() {
+            return Ok(Self::default());
+        }
+
+        let content = fs::read_to_string(&path).map_err(|e| Error::FileRead {
+            path: path.clone(),
+            source: e,
+        })?;
+        Self::parse(&content)
+    }
+
+    /// Parse externals from TOML
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the TOML is invalid or a target path is not a plain
+    /// relative path (no `.`, `..` or root components)
+    pub fn parse(content: &str) -> Result<Self> {
+        let raw: IndexMap<String, External> =
+            toml::from_str(content).map_err(|e| Error::InvalidConfig {
+                message: format!("Failed to parse {EXTERNALS_FILE}: {e}"),
+            })?;
+
+        let mut entries = IndexMap::with_capacity(raw.len());
+        for (path, external) in raw {
+            let target = PathBuf::from(&path);
+            let plain = !target.as_os_str().is_empty()
+                && target
+                    .components()
+                    .all(|component| matches!(component, Component::Normal(_)));
+            let target = RelPath::new(target).ok().filter(|_| plain).ok_or_else(|| {
+                Error::InvalidConfig {
+                    message: format!(
+                        "External target must be a relative path without `.` or `..`: {path}"
+                    ),
+                }
+            })?;
+            entries.insert(target, external);
+        }
+        Ok(Self { entries })
+    }
+
+    /// Check if no externals are declared
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// Iterate over target paths and their externals
+    pub fn iter(&self) -> impl Iterator<Item = (&RelPath, &External)> {
+        self.entries.iter()
+    }
+}
+
+/// Downloads the content of a URL
+pub trait Fetcher: Send + Sync {
+    /// Download `url`
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the download fails
+    fn fetch(&self, url: &str) -> Result<Vec<u8>>;
+}
+
+/// Fetcher backed by the `curl` command
+#[derive(Debug, Default, Clone, Copy)]
+pub struct CurlFetcher;
+
+impl Fetcher for CurlFetcher {
+    fn fetch(&self, url: &str) -> Result<Vec<u8>> {
+        tracing::debug!(url, "Downloading external");
+        let output = Command::new("curl")
+            .args([
+                "--fail",
+                "--silent",
+                "--show-error",
+                "--location",
+                "--",
+                url,
+            ])
+            .output()
+            .map_err(|e| Error::Message(format!("Failed to run curl (is it installed?): {e}")))?;
+
+        if !output.status.success() {
+            return Err(Error::Message(format!(
+                "Failed to download {url}: {}",
+                String::from_utf8_lossy(&output.stderr).trim()
+            )));
+        }
+        Ok(output.stdout)
+    }
+}
+
+/// Downloaded content of an external, cached in the state database
+#[derive(Debug, Clone, PartialEq, Eq, bincode::Encode, bincode::Decode)]
+pub struct ExternalCache {
+    /// Downloaded bytes
+    pub content: Vec<u8>,
+    /// Run that downloaded the content
+    pub stamp: RunStamp,
+}
+
+impl ExternalCache {
+    /// Serialize to bytes using bincode
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if serialization fails (e.g., encoding error)
+    pub fn to_bytes(&self) -> Result<Vec<u8>> {
+        bincode::encode_to_vec(self, bincode::config::standard())
+            .map_err(|e| Error::State(format!("Failed to serialize ExternalCache: {e}")))
+    }
+
+    /// Deserialize from bytes using bincode
+    #[must_use]
+    pub fn from_bytes(bytes: &[u8]) -> Option<Self> {
+        match bincode::decode_from_slice(bytes, bincode::config::standard()) {
+            Ok((value, len)) if len == bytes.len() => Some(value),
+            _ => None,
+        }
+    }
+
+    /// Check if the cached content is still fresh at `now`
+    fn is_fresh(&self, refresh_period: u64, now: &RunStamp) -> bool {
+        refresh_period == 0 || now.timestamp.saturating_sub(self.stamp.timestamp) < refresh_period
+    }
+}
+
+/// How [`resolve_externals`] uses cached downloads
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum FetchMode {
+    /// Reuse fresh cached downloads and fetch the rest
+    #[default]
+    Cached,
+    /// Fetch every URL again
+    Refresh,
+    /// Only use cached downloads, however old, and never fetch
+    Offline,
+}
+
+/// Resolve every external into target entries
+///
+/// Cached downloads are reused while fresh and matching their checksum;
+/// otherwise the URL is fetched again and the cache updated. See [`FetchMode`]
+/// for forcing or avoiding downloads.
+///
+/// # Errors
+///
+/// Returns an error if a download fails, does not match its checksum, or an
+/// archive cannot be extracted, or, offline, if an external was never downloaded
+pub fn resolve_externals(
+    externals: &Externals,
+    db: &RedbPersistentState,
+    fetcher: &dyn Fetcher,
+    stamp: &RunStamp,
+    mode: FetchMode,
+) -> Result<Vec<TargetEntry>> {
+    let mut entries = Vec::new();
+
+    for (target, external) in externals.iter() {
+        let content = fetch_cached(external, db, fetcher, stamp, mode)?;
+        match external.kind {
+            ExternalKind::File => {
+                let content_hash = hash_content(&content);
+                entries.push(TargetEntry::File {
+                    path: target.clone(),
+                    content: content.into(),
+                    content_hash,
+                    mode: external.executable.then_some(0o755),
+                });
+            }
+            ExternalKind::Archive => {
+                entries.extend(extract_archive(target, external, &content)?);
+            }
+        }
+    }
+
+    Ok(entries)
+}
+
+/// Get the content of an external from the cache, downloading it if needed
+fn fetch_cached(
+    external: &External,
+    db: &RedbPersistentState,
+    fetcher: &dyn Fetcher,
+    stamp: &RunStamp,
+    mode: FetchMode,
+) -> Result<Vec<u8>> {
+    if mode != FetchMode::Refresh
+        && let Some(cache) = database::get_external_cache(db, &external.url)?
+        && (mode == FetchMode::Offline || cache.is_fresh(external.refresh_period, stamp))
+        && verify_checksum(external, &cache.content).is_ok()
+    {
+        tracing::debug!(url = %external.url, "Using cached external");
+        return Ok(cache.content);
+    }
+
+    if mode == FetchMode::Offline {
+        return Err(Error::Message(format!(
+            "Offline mode: {} has not been downloaded yet",
+            external.url
+        )));
+    }
+
+    let content = fetcher.fetch(&external.url)?;
+    verify_checksum(external, &content)?;
+
+    let cache = ExternalCache {
+        content,
+        stamp: stamp.clone(),
+    };
+    database::save_external_cache(db, &external.url, &cache)?;
+    Ok(cache.content)
+}
+
+/// Check downloaded content against the declared checksum
+fn verify_checksum(external: &External, content: &[u8]) -> Result<()> {
+    use sha2::{Digest, Sha256};
+
+    let Some(checksum) = &external.checksum else {
+        return Ok(());
+    };
+    let Some(expected) = checksum.strip_prefix("sha256:") else {
+        return Err(Error::InvalidConfig {
+            message: format!("Unsupported checksum for {}: {checksum}", external.url),
+        });
+    };
+
+    let actual = Sha256::digest(content)
+        .iter()
+        .fold(String::with_capacity(64), |mut hex, byte| {
+            use std::fmt::Write;
+            let _ = write!(hex, "{byte:02x}");
+            hex
+        });
+    if !actual.eq_ignore_ascii_case(expected) {
+        return Err(Error::Message(format!(
+            "Checksum mismatch for {}: expected sha256:{expected}, got sha256:{actual}",
+            external.url
+        )));
+    }
+    Ok(())
+}
+
+/// Archive formats recognized from the URL
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum ArchiveFormat {
+    TarGz,
+    Tar,
+    Zip,
+}
+
+impl ArchiveFormat {
+    /// Detect the format from the URL path, ignoring any query string
+    // The path is lowercased before comparing
+    #[allow(clippy::case_sensitive_file_extension_comparisons)]
+    fn from_url(url: &str) -> Option<Self> {
+        let path = url
+            .split(['?', '#'])
+            .next()
+            .unwrap_or(url)
+            .to_ascii_lowercase();
+        if path.ends_with(".tar.gz") || path.ends_with(".tgz") {
+            Some(Self::TarGz)
+        } else if path.ends_with(".tar") {
+            Some(Self::Tar)
+        } else if path.ends_with(".zip") {
+            Some(Self::Zip)
+        } else {
+            None
+        }
+    }
+}
+
+/// Content of an archive member
+#[derive(Debug, Clone)]
+enum Member {
+    Directory,
+    File { content: Vec<u8>, executable: bool },
+    Symlink(PathBuf),
+}
+
+/// Extract an archive into target entries under `target`
+fn extract_archive(
+    target: &RelPath,
+    external: &External,
+    content: &[u8],
+) -> Result<Vec<TargetEntry>> {
+    let format = ArchiveFormat::from_url(&external.url).ok_or_else(|| Error::InvalidConfig {
+        message: format!(
+            "Unknown archive format for {} (expected .tar.gz, .tgz, .tar or .zip)",
+            external.url
+        ),
+    })?;
+
+    let members = match format {
+        ArchiveFormat::TarGz => read_tar(flate2::read::GzDecoder::new(content), external)?,
+        ArchiveFormat::Tar => read_tar(content, external)?,
+        ArchiveFormat::Zip => read_zip(content, external)?,
+    };
+    archive_entries(target, external, members)
+}
+
+/// Error for an archive that cannot be read
+fn extract_failed(external: &External, e: impl std::fmt::Display) -> Error {
+    Error::Message(format!("Failed to extract {}: {e}", external.url))
+}
+
+/// Path of an archive member below the target, with `strip_components` removed
+///
+/// Returns `None` for members stripped entirely, and an error for paths that
+/// are absolute or contain `..`.
+fn member_path(name: &Path, external: &External) -> Result<Option<PathBuf>> {
+    let mut parts = Vec::new();
+    for component in name.components() {
+        match component {
+            Component::Normal(part) => parts.push(part),
+            Component::CurDir => {}
+            Component::ParentDir | Component::RootDir | Component::Prefix(_) => {
+                return Err(extract_failed(
+                    external,
+                    format!("member path leaves the target: {}", name.display()),
+                ));
+            }
+        }
+    }
+
+    let stripped: PathBuf = parts.into_iter().skip(external.strip_components).collect();
+    Ok((!stripped.as_os_str().is_empty()).then_some(stripped))
+}
+
+/// Read the members of a tar archive
+///
+/// Hard links become copies of the file they link to. Other special entries
+/// (devices, FIFOs, PAX headers) are skipped.
+fn read_tar(reader: impl Read, external: &External) -> Result<BTreeMap<PathBuf, Member>> {
+    let failed = |e: std::io::Error| extract_failed(external, e);
+    let mut members = BTreeMap::new();
+
+    let mut archive = tar::Archive::new(reader);
+    for entry in archive.entries().map_err(failed)? {
+        let mut entry = entry.map_err(failed)?;
+        let Some(path) = member_path(&entry.path().map_err(failed)?, external)? else {
+            continue;
+        };
+
+        let kind = entry.header().entry_type();
+        let member = if kind.is_dir() {
+            Member::Directory
+        } else if kind.is_file() {
+            let executable = entry.header().mode().is_ok_and(|mode| mode & 0o100 != 0);
+            let mut content = Vec::new();
+            entry.read_to_end(&mut content).map_err(failed)?;
+            Member::File {
+                content,
+                executable,
+            }
+        } else if kind.is_symlink() || kind.is_hard_link() {
+            let link = entry
+                .link_name()
+                .map_err(failed)?
+                .ok_or_else(|| {
+                    extract_failed(external, format!("link without target: {}", path.display()))
+                })?
+                .into_owned();
+            if kind.is_symlink() {
+                Member::Symlink(link)
+            } else {
+                member_path(&link, external)?
+                    .and_then(|link| members.get(&link).cloned())
+                    .filter(|member| matches!(member, Member::File { .. }))
+                    .ok_or_else(|| {
+                        extract_failed(
+                            external,
+                            format!("hard link to a missing file: {}", link.display()),
+                        )
+                    })?
+            }
+        } else {
+            continue;
+        };
+        members.insert(path, member);
+    }
+
+    Ok(members)
+}
+
+/// Read the members of a zip archive
+fn read_zip(content: &[u8], external: &External) -> Result<BTreeMap<PathBuf, Member>> {
+    let failed = |e: zip::result::ZipError| extract_failed(external, e);
+    let mut members = BTreeMap::new();
+
+    let mut archive = zip::ZipArchive::new(std::io::Cursor::new(content)).map_err(failed)?;
+    for index in 0..archive.len() {
+        let mut file = archive.by_index(index).map_err(failed)?;
+        let name = file.enclosed_name().ok_or_else(|| {
+            extract_failed(
+                external,
+                format!("member path leaves the target: {}", file.name()),
+            )
+        })?;
+        let Some(path) = member_path(&name, external)? else {
+            continue;
+        };
+
+        let member = if file.is_dir() {
+            Member::Directory
+        } else {
+            let mut content = Vec::new();
+            file.read_to_end(&mut content)
+                .map_err(|e| extract_failed(external, e))?;
+            if file.is_symlink() {
+                Member::Symlink(PathBuf::from(String::from_utf8_lossy(&content).as_ref()))
+            } else {
+                Member::File {
+                    content,
+                    executable: file.unix_mode().is_some_and(|mode| mode & 0o100 != 0),
+                }
+            }
+        };
+        members.insert(path, member);
+    }
+
+    Ok(members)
+}
+
+/// Turn archive members into target entries under `target`, sorted by path
+///
+/// Directories that the archive leaves out are added. A member below a file
+/// or symlink is an error, since writing it would go through a symlink or
+/// replace the file.
+fn archive_entries(
+    target: &RelPath,
+    external: &External,
+    mut members: BTreeMap<PathBuf, Member>,
+) -> Result<Vec<TargetEntry>> {
+    let parents: Vec<PathBuf> = members
+        .keys()
+        .flat_map(|path| path.ancestors().skip(1))
+        .filter(|parent| !parent.as_os_str().is_empty())
+        .map(Path::to_path_buf)
+        .collect();
+    for parent in parents {
+        let member = members.entry(parent.clone()).or_insert(Member::Directory);
+        if !matches!(member, Member::Directory) {
+            return Err(extract_failed(
+                external,
+                format!("member below a non-directory: {}", parent.display()),
+            ));
+        }
+    }
+
+    let mut entries = vec![TargetEntry::Directory {
+        path: target.clone(),
+        mode: None,
+    }];
+    for (path, member) in members {
+        let path = target.join(&RelPath::new(path)?);
+        entries.push(match member {
+            Member::Directory => TargetEntry::Directory { path, mode: None },
+            Member::Symlink(link) => TargetEntry::Symlink { path, target: link },
+            Member::File {
+                content,
+                executable,
+            } => {
+                let content_hash = hash_content(&content);
+                TargetEntry::File {
+                    path,
+                    content: content.into(),
+                    content_hash,
+                    mode: executable.then_some(0o755),
+                }
+            }
+        });
+    }
+
+    Ok(entries)
+}
+
+#[cfg(test)]
+mod tests {
+    #![allow(clippy::unwrap_used, clippy::panic)]
+    use super::*;
+    use std::collections::HashMap;
+    use std::sync::Mutex;
+    use tempfile::TempDir;
+
+    /// Fetcher serving fixed content and counting downloads
+    #[derive(Default)]
+    struct FakeFetcher {
+        content: HashMap<String, Vec<u8>>,
+        fetched: Mutex<usize>,
+    }
+
+    impl FakeFetcher {
+        fn with(url: &str, content: &[u8]) -> Self {
+            Self {
+                content: HashMap::from([(url.to_string(), content.to_vec())]),
+                fetched: Mutex::new(0),
+            }
+        }
+
+        fn fetched(&self) -> usize {
+            *self.fetched.lock().unwrap()
+        }
+    }
+
+    impl Fetcher for FakeFetcher {
+        fn fetch(&self, url: &str) -> Result<Vec<u8>> {
+            *self.fetched.lock().unwrap() += 1;
+            self.content
+                .get(url)
+                .cloned()
+                .ok_or_else(|| Error::Message(format!("404: {url}")))
+        }
+    }
+
+    fn stamp(timestamp: u64) -> RunStamp {
+        RunStamp {
+            run_id: format!("run-{timestamp}"),
+            timestamp,
+        }
+    }
+
+    fn open_db(temp: &TempDir) -> RedbPersistentState {
+        RedbPersistentState::new(temp.path().join("state.db")).unwrap()
+    }
+
+    const JQ: &str = r#"
+[".local/bin/jq"]
+type = "file"
+url = "https://example.com/jq"
+refresh_period = 100
+executable = true
+"#;
+
+    #[test]
+    fn test_parse_externals() {
+        let externals = Externals::parse(JQ).unwrap();
+        let (target, external) = externals.iter().next().unwrap();
+        assert_eq!(target.to_string(), ".local/bin/jq");
+        assert_eq!(external.kind, ExternalKind::File);
+        assert_eq!(external.refresh_period, 100);
+        assert!(external.executable);
+
+        assert!(Externals::parse("[\"/abs\"]\ntype = \"file\"\nurl = \"x\"\n").is_err());
+        assert!(Externals::parse("[\"a/../../b\"]\ntype = \"file\"\nurl = \"x\"\n").is_err());
+        assert!(Externals::parse("[\"./a\"]\ntype = \"file\"\nurl = \"x\"\n").is_err());
+        assert!(Externals::parse("[\"\"]\ntype = \"file\"\nurl = \"x\"\n").is_err());
+        assert!(Externals::parse("[a]\ntype = \"file\"\nurl = \"x\"\ntypo = 1\n").is_err());
+    }
+
+    #[test]
+    fn test_resolve_file_uses_cache_until_stale() {
+        let temp = TempDir::new().unwrap();
+        let db = open_db(&temp);
+        let externals = Externals::parse(JQ).unwrap();
+        let fetcher = FakeFetcher::with("https://example.com/jq", b"binary");
+
+        let entries =
+            resolve_externals(&externals, &db, &fetcher, &stamp(1000), FetchMode::Cached).unwrap();
+        assert_eq!(fetcher.fetched(), 1);
+        match &entries[..] {
+            [TargetEntry::File { content, mode, .. }] => {
+                assert_eq!(content.as_ref(), b"binary");
+                assert_eq!(*mode, Some(0o755));
+            }
+            other => panic!("unexpected entries: {other:?}"),
+        }
+
+        resolve_externals(&externals, &db, &fetcher, &stamp(1050), FetchMode::Cached).unwrap();
+        assert_eq!(fetcher.fetched(), 1);
+
+        resolve_externals(&externals, &db, &fetcher, &stamp(1100), FetchMode::Cached).unwrap();
+        assert_eq!(fetcher.fetched(), 2);
+
+        resolve_externals(&externals, &db, &fetcher, &stamp(1101), FetchMode::Refresh).unwrap();
+        assert_eq!(fetcher.fetched(), 3);
+    }
+
+    #[test]
+    fn test_resolve_offline_uses_stale_cache_only() {
+        let temp = TempDir::new().unwrap();
+        let db = open_db(&temp);
+        let externals = Externals::parse(JQ).unwrap();
+        let fetcher = FakeFetcher::with("https://example.com/jq", b"binary");
+
+        let err = resolve_externals(&externals, &db, &fetcher, &stamp(1000), FetchMode::Offline)
+            .unwrap_err();
+        assert!(err.to_string().contains("has not been downloaded"));
+        assert_eq!(fetcher.fetched(), 0);
+
+        resolve_externals(&externals, &db, &fetcher, &stamp(1000), FetchMode::Cached).unwrap();
+        let entries =
+            resolve_externals(&externals, &db, &fetcher, &stamp(5000), FetchMode::Offline).unwrap();
+        assert_eq!(entries.len(), 1);
+        assert_eq!(fetcher.fetched(), 1);
+    }
+
+    #[test]
+    fn test_checksum_verification() {
+        let temp = TempDir::new().unwrap();
+        let db = open_db(&temp);
+        let fetcher = FakeFetcher::with("https://example.com/f", b"hello");
+        let toml = |checksum: &str| {
+            format!(
+                "[f]\ntype = \"file\"\nurl = \"https://example.com/f\"\nchecksum = \"{checksum}\"\n"
+            )
+        };
+
+        let good = Externals::parse(&toml(
+            "sha256:2cf24dba5fb0a30e26e83b2ac5b9e29e1b161e5c1fa7425e73043362938b9824",
+        ))
+        .unwrap();
+        assert!(resolve_externals(&good, &db, &fetcher, &stamp(1), FetchMode::Cached).is_ok());
+
+        let bad = Externals::parse(&toml("sha256:00")).unwrap();
+        let err = resolve_externals(&bad, &db, &fetcher, &stamp(2), FetchMode::Cached).unwrap_err();
+        assert!(err.to_string().contains("Checksum mismatch"));
+    }
+
+    #[test]
+    fn test_archive_format_from_url() {
+        assert_eq!(
+            ArchiveFormat::from_url("https://x/master.tar.gz?raw=1"),
+            Some(ArchiveFormat::TarGz)
+        );
+        assert_eq!(
+            ArchiveFormat::from_url("https://x/a.TGZ"),
+            Some(ArchiveFormat::TarGz)
+        );
+        assert_eq!(
+            ArchiveFormat::from_url("https://x/a.zip"),
+            Some(ArchiveFormat::Zip)
+        );
+        assert_eq!(ArchiveFormat::from_url("https://x/a"), None);
+    }
+
+    /// Tar archive of `(path, content)` members, directories ending in `/`
+    fn tar_archive(members: &[(&str, &str)]) -> Vec<u8> {
+        let mut builder = tar::Builder::new(Vec::new());
+        for (path, content) in members {
+            let mut header = tar::Header::new_gnu();
+            if path.ends_with('/') {
+                header.set_entry_type(tar::EntryType::Directory);
+                header.set_mode(0o755);
+            } else {
+                header.set_mode(0o644);
+            }
+            header.set_size(content.len() as u64);
+            header.set_cksum();
+            builder
+                .append_data(&mut header, path, content.as_bytes())
+                .unwrap();
+        }
+        builder.into_inner().unwrap()
+    }
+
+    fn archive_external(url: &str, strip_components: usize) -> External {
+        External {
+            kind: ExternalKind::Archive,
+            url: url.to_string(),
+            checksum: None,
+            refresh_period: 0,
+            strip_components,
+            executable: false,
+        }
+    }
+
+    fn paths(entries: &[TargetEntry]) -> Vec<String> {
+        entries.iter().map(|e| e.path().to_string()).collect()
+    }
+
+    #[test]
+    fn test_resolve_archive_strips_components() {
+        let temp = TempDir::new().unwrap();
+        let db = open_db(&temp);
+
+        let tar = tar_archive(&[
+            ("omz-master/", ""),
+            ("omz-master/oh-my-zsh.sh", "# omz\n"),
+            ("omz-master/plugins/git/git.plugin.zsh", "alias g=git\n"),
+        ]);
+        let mut archive = flate2::write::GzEncoder::new(Vec::new(), flate2::Compression::fast());
+        std::io::Write::write_all(&mut archive, &tar).unwrap();
+
+        let externals = Externals::parse(
+            "[\".oh-my-zsh\"]\ntype = \"archive\"\nurl = \"https://example.com/omz.tar.gz\"\nstrip_components = 1\n",
+        )
+        .unwrap();
+        let fetcher =
+            FakeFetcher::with("https://example.com/omz.tar.gz", &archive.finish().unwrap());
+
+        let entries =
+            resolve_externals(&externals, &db, &fetcher, &stamp(1), FetchMode::Cached).unwrap();
+        assert_eq!(
+            paths(&entries),
+            vec![
+                ".oh-my-zsh",
+                ".oh-my-zsh/oh-my-zsh.sh",
+                ".oh-my-zsh/plugins",
+                ".oh-my-zsh/plugins/git",
+                ".oh-my-zsh/plugins/git/git.plugin.zsh",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_extract_zip_archive() {
+        let mut zip = zip::ZipWriter::new(std::io::Cursor::new(Vec::new()));
+        let options = zip::write::SimpleFileOptions::default().unix_permissions(0o755);
+        zip.start_file("tool-1.0/bin/tool", options).unwrap();
+        std::io::Write::write_all(&mut zip, b"#!/bin/sh\n").unwrap();
+        zip.add_symlink("tool-1.0/tool", "bin/tool", options)
+            .unwrap();
+        let content = zip.finish().unwrap().into_inner();
+
+        let target = RelPath::new(PathBuf::from(".local/tool")).unwrap();
+        let external = archive_external("https://example.com/tool.zip", 1);
+        let entries = extract_archive(&target, &external, &content).unwrap();
+
+        assert_eq!(
+            paths(&entries),
+            vec![
+                ".local/tool",
+                ".local/tool/bin",
+                ".local/tool/bin/tool",
+                ".local/tool/tool"
+            ]
+        );
+        match &entries[2] {
+            TargetEntry::File { content, mode, .. } => {
+                assert_eq!(content.as_ref(), b"#!/bin/sh\n");
+                assert_eq!(*mode, Some(0o755));
+            }
+            other => panic!("unexpected entry: {other:?}"),
+        }
+        match &entries[3] {
+            TargetEntry::Symlink { target, .. } => assert_eq!(target, Path::new("bin/tool")),
+            other => panic!("unexpected entry: {other:?}"),
+        }
+    }
+
+    #[test]
+    fn test_extract_rejects_members_leaving_target() {
+        let target = RelPath::new(PathBuf::from(".plugin")).unwrap();
+        let external = archive_external("https://example.com/p.tar", 0);
+
+        // tar::Builder refuses `..`, so write the name into the header directly
+        let mut header = tar::Header::new_old();
+        header.as_old_mut().name[..12].copy_from_slice(b"../.bashrc\0\0");
+        header.set_mode(0o644);
+        header.set_size(4);
+        header.set_cksum();
+        let mut builder = tar::Builder::new(Vec::new());
+        builder.append(&header, &b"evil"[..]).unwrap();
+        let content = builder.into_inner().unwrap();
+
+        let err = extract_archive(&target, &external, &content).unwrap_err();
+        assert!(err.to_string().contains("leaves the target"), "{err}");
+    }
+
+    #[test]
+    fn test_extract_rejects_members_below_symlinks() {
+        let target = RelPath::new(PathBuf::from(".plugin")).unwrap();
+        let external = archive_external("https://example.com/p.tar", 0);
+
+        let mut builder = tar::Builder::new(Vec::new());
+        let mut header = tar::Header::new_gnu();
+        header.set_entry_type(tar::EntryType::Symlink);
+        header.set_size(0);
+        builder
+            .append_link(&mut header, "escape", "/home/user")
+            .unwrap();
+        let mut header = tar::Header::new_gnu();
+        header.set_mode(0o644);
+        header.set_size(4);
+        builder
+            .append_data(&mut header, "escape/.bashrc", &b"evil"[..])
+            .unwrap();
+        let content = builder.into_inner().unwrap();
+
+        let err = extract_archive(&target, &external, &content).unwrap_err();
+        assert!(err.to_string().contains("below a non-directory"), "{err}");
+    }
+}
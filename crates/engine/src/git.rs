@@ -7,8 +7,12 @@
 //! The abstraction allows switching between implementations based on configuration
 //! or availability, similar to chezmoi's approach.
 
+use crate::source::{SourceNode, SourceNodeKind, SourceReader};
 use guisu_core::Result;
-use std::path::Path;
+use std::ffi::OsStr;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use std::sync::Mutex;
 
 /// Helper function to convert git2 errors to `guisu_core` errors
 #[inline]
@@ -17,6 +21,34 @@ fn git_err(e: git2::Error) -> guisu_core::Error {
     guisu_core::Error::Message(format!("Git error: {e}"))
 }
 
+/// Remote callbacks that authenticate like the git command line
+///
+/// Credentials are tried in turn from the SSH agent, the default keys in
+/// `~/.ssh`, and the configured git credential helper, before prompting.
+#[must_use]
+pub fn authenticated_callbacks<'a>() -> git2::RemoteCallbacks<'a> {
+    let mut callbacks = git2::RemoteCallbacks::new();
+    if let Ok(git_config) = git2::Config::open_default().or_else(|_| git2::Config::new()) {
+        let mut credential_handler = git2_credentials::CredentialHandler::new(git_config);
+        callbacks.credentials(move |url, username_from_url, allowed_types| {
+            credential_handler.try_next_credential(url, username_from_url, allowed_types)
+        });
+    }
+    callbacks
+}
+
+/// Fetch options using `callbacks` and the proxy from the git configuration
+#[must_use]
+pub fn fetch_options(callbacks: git2::RemoteCallbacks<'_>) -> git2::FetchOptions<'_> {
+    let mut proxy_options = git2::ProxyOptions::new();
+    proxy_options.auto();
+
+    let mut fetch_options = git2::FetchOptions::new();
+    fetch_options.remote_callbacks(callbacks);
+    fetch_options.proxy_options(proxy_options);
+    fetch_options
+}
+
 /// Git provider trait defining all git operations needed by guisu
 pub trait GitProvider {
     /// Clone a repository from URL to target path
@@ -74,6 +106,39 @@ pub trait GitProvider {
     ///
     /// Returns an error if branch name cannot be determined (e.g., not a repository, detached HEAD)
     fn current_branch(&self, repo_path: &Path) -> Result<String>;
+
+    /// Stage all changes under the given paths, deletions included
+    ///
+    /// Paths are relative to the working tree. Ignored files are not staged.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the index cannot be updated (e.g., not a repository, I/O error)
+    fn stage(&self, repo_path: &Path, paths: &[PathBuf]) -> Result<()>;
+
+    /// Paths whose staged content differs from `HEAD`
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the index cannot be compared (e.g., not a repository)
+    fn staged_changes(&self, repo_path: &Path) -> Result<Vec<PathBuf>>;
+
+    /// Commit the index on the current branch, returning the commit ID
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if committing fails (e.g., no user identity configured)
+    fn commit(&self, repo_path: &Path, message: &str) -> Result<String>;
+
+    /// Push the current branch to its upstream remote, or `origin`
+    ///
+    /// Credentials come from the git configuration (SSH agent, credential
+    /// helpers), as for clone and fetch.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if pushing fails (e.g., authentication failure, rejected update)
+    fn push(&self, repo_path: &Path) -> Result<()>;
 }
 
 /// Git repository status
@@ -131,10 +196,10 @@ impl GitProvider for Git2Provider {
         branch: Option<&str>,
         recurse_submodules: bool,
     ) -> Result<()> {
-        use git2::{FetchOptions, RemoteCallbacks, build::RepoBuilder};
+        use git2::build::RepoBuilder;
 
-        // Set up callbacks for progress reporting
-        let mut callbacks = RemoteCallbacks::new();
+        // Set up callbacks for authentication and progress reporting
+        let mut callbacks = authenticated_callbacks();
         if let Some(progress_fn) = &self.progress_callback {
             callbacks.transfer_progress(move |stats| {
                 let received = stats.received_objects();
@@ -147,8 +212,7 @@ impl GitProvider for Git2Provider {
         }
 
         // Configure fetch options
-        let mut fetch_options = FetchOptions::new();
-        fetch_options.remote_callbacks(callbacks);
+        let mut fetch_options = fetch_options(callbacks);
 
         if let Some(d) = depth {
             #[allow(clippy::cast_possible_truncation, clippy::cast_possible_wrap)]
@@ -179,12 +243,12 @@ impl GitProvider for Git2Provider {
     }
 
     fn fetch(&self, repo_path: &Path, remote: &str) -> Result<()> {
-        use git2::{AutotagOption, FetchOptions, RemoteCallbacks, Repository};
+        use git2::{AutotagOption, Repository};
 
         let repo = Repository::open(repo_path).map_err(git_err)?;
         let mut remote = repo.find_remote(remote).map_err(git_err)?;
 
-        let mut callbacks = RemoteCallbacks::new();
+        let mut callbacks = authenticated_callbacks();
         if let Some(progress_fn) = &self.progress_callback {
             callbacks.transfer_progress(move |stats| {
                 let received = stats.received_objects();
@@ -196,8 +260,7 @@ impl GitProvider for Git2Provider {
             });
         }
 
-        let mut fetch_options = FetchOptions::new();
-        fetch_options.remote_callbacks(callbacks);
+        let mut fetch_options = fetch_options(callbacks);
         fetch_options.download_tags(AutotagOption::Auto);
 
         remote
@@ -337,11 +400,124 @@ impl GitProvider for Git2Provider {
             .to_string();
         Ok(branch)
     }
+
+    fn stage(&self, repo_path: &Path, paths: &[PathBuf]) -> Result<()> {
+        use git2::{IndexAddOption, Repository};
+
+        let repo = Repository::open(repo_path).map_err(git_err)?;
+        let mut index = repo.index().map_err(git_err)?;
+        let pathspecs: Vec<String> = paths
+            .iter()
+            .map(|path| path.to_string_lossy().into_owned())
+            .collect();
+
+        // add_all picks up new and modified files, update_all removes deleted ones
+        index
+            .add_all(pathspecs.iter(), IndexAddOption::DEFAULT, None)
+            .map_err(git_err)?;
+        index.update_all(pathspecs.iter(), None).map_err(git_err)?;
+        index.write().map_err(git_err)
+    }
+
+    fn staged_changes(&self, repo_path: &Path) -> Result<Vec<PathBuf>> {
+        use git2::Repository;
+
+        let repo = Repository::open(repo_path).map_err(git_err)?;
+        // An unborn branch has no HEAD tree: everything staged is a change
+        let head_tree = repo.head().ok().and_then(|head| head.peel_to_tree().ok());
+        let diff = repo
+            .diff_tree_to_index(head_tree.as_ref(), None, None)
+            .map_err(git_err)?;
+
+        Ok(diff
+            .deltas()
+            .filter_map(|delta| {
+                delta
+                    .new_file()
+                    .path()
+                    .or_else(|| delta.old_file().path())
+                    .map(Path::to_path_buf)
+            })
+            .collect())
+    }
+
+    fn commit(&self, repo_path: &Path, message: &str) -> Result<String> {
+        use git2::Repository;
+
+        let repo = Repository::open(repo_path).map_err(git_err)?;
+        let tree_id = repo
+            .index()
+            .and_then(|mut index| index.write_tree())
+            .map_err(git_err)?;
+        let tree = repo.find_tree(tree_id).map_err(git_err)?;
+        let signature = repo.signature().map_err(|e| {
+            guisu_core::Error::Message(format!(
+                "Failed to commit: set user.name and user.email in your git configuration ({e})"
+            ))
+        })?;
+
+        let parent = repo.head().ok().and_then(|head| head.peel_to_commit().ok());
+        let parents: Vec<&git2::Commit> = parent.iter().collect();
+        let commit_id = repo
+            .commit(
+                Some("HEAD"),
+                &signature,
+                &signature,
+                message,
+                &tree,
+                &parents,
+            )
+            .map_err(git_err)?;
+
+        Ok(commit_id.to_string())
+    }
+
+    fn push(&self, repo_path: &Path) -> Result<()> {
+        use git2::{ProxyOptions, PushOptions, Repository};
+
+        let repo = Repository::open(repo_path).map_err(git_err)?;
+        let branch = self.current_branch(repo_path)?;
+        let branch_ref = format!("refs/heads/{branch}");
+
+        let remote_name = push_remote(&repo, &branch_ref);
+        let mut remote = repo.find_remote(&remote_name).map_err(git_err)?;
+
+        let mut callbacks = authenticated_callbacks();
+        // Rejected updates are only reported through this callback
+        callbacks.push_update_reference(|refname, status| match status {
+            Some(message) => Err(git2::Error::from_str(&format!(
+                "{refname} was rejected by {remote_name}: {message}"
+            ))),
+            None => Ok(()),
+        });
+
+        let mut proxy_options = ProxyOptions::new();
+        proxy_options.auto();
+
+        let mut push_options = PushOptions::new();
+        push_options.remote_callbacks(callbacks);
+        push_options.proxy_options(proxy_options);
+
+        remote
+            .push(
+                &[format!("{branch_ref}:{branch_ref}")],
+                Some(&mut push_options),
+            )
+            .map_err(git_err)
+    }
+}
+
+/// Remote the current branch is pushed to: its upstream remote, or `origin`
+fn push_remote(repo: &git2::Repository, branch_ref: &str) -> String {
+    repo.branch_upstream_remote(branch_ref)
+        .ok()
+        .and_then(|name| name.as_str().map(str::to_string))
+        .unwrap_or_else(|| "origin".to_string())
 }
 
 /// Helper function to recursively initialize submodules
 fn init_submodules_recursive(repo: &git2::Repository, repo_path: &Path) -> Result<()> {
-    use git2::{FetchOptions, RemoteCallbacks, Repository, SubmoduleUpdateOptions};
+    use git2::{Repository, SubmoduleUpdateOptions};
 
     let submodules = repo.submodules().map_err(git_err)?;
 
@@ -354,11 +530,7 @@ fn init_submodules_recursive(repo: &git2::Repository, repo_path: &Path) -> Resul
 
         // Update the submodule
         let mut update_options = SubmoduleUpdateOptions::new();
-        let mut fetch_options = FetchOptions::new();
-        let mut callbacks = RemoteCallbacks::new();
-        callbacks.transfer_progress(|_| true);
-        fetch_options.remote_callbacks(callbacks);
-        update_options.fetch(fetch_options);
+        update_options.fetch(fetch_options(authenticated_callbacks()));
 
         submodule
             .update(true, Some(&mut update_options))
@@ -387,10 +559,429 @@ fn count_new_commits(repo: &git2::Repository, new_commit: &git2::AnnotatedCommit
     Ok(revwalk.count())
 }
 
-/// Create git provider (uses git2)
+/// Git provider running the `git` binary for network operations
+///
+/// Clone, fetch, and push shell out to `git`, so its credential helpers, SSH
+/// configuration, and proxy settings apply exactly as on the command line.
+/// Its progress output and prompts go straight to the terminal. Local
+/// operations are delegated to [`Git2Provider`].
+pub struct SystemGitProvider {
+    program: PathBuf,
+    local: Git2Provider,
+}
+
+impl SystemGitProvider {
+    /// Create a provider running `git` from `PATH`
+    #[must_use]
+    pub fn new() -> Self {
+        Self::with_program("git")
+    }
+
+    /// Create a provider running the given git binary
+    #[must_use]
+    pub fn with_program(program: impl Into<PathBuf>) -> Self {
+        Self {
+            program: program.into(),
+            local: Git2Provider::new(),
+        }
+    }
+
+    /// Fetch `refspecs` from `remote`, or its default refspecs if empty
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if `git fetch` cannot be run or fails
+    pub fn fetch_refspecs(
+        &self,
+        repo_path: &Path,
+        remote: &str,
+        refspecs: &[String],
+    ) -> Result<()> {
+        let mut command = self.command();
+        command
+            .arg("-C")
+            .arg(repo_path)
+            .args(["fetch", remote])
+            .args(refspecs);
+        self.run(command, "fetch")
+    }
+
+    fn command(&self) -> Command {
+        Command::new(&self.program)
+    }
+
+    /// Run `command`, failing if `git` is missing or exits unsuccessfully
+    fn run(&self, mut command: Command, operation: &str) -> Result<()> {
+        let status = command.status().map_err(|e| {
+            if e.kind() == std::io::ErrorKind::NotFound {
+                guisu_core::Error::Message(format!(
+                    "{} not found: install git or unset useSystemGit in the [git] configuration",
+                    self.program.display()
+                ))
+            } else {
+                guisu_core::Error::Message(format!("Failed to run {}: {e}", self.program.display()))
+            }
+        })?;
+
+        if !status.success() {
+            return Err(guisu_core::Error::Message(format!(
+                "git {operation} failed ({status})"
+            )));
+        }
+        Ok(())
+    }
+}
+
+impl Default for SystemGitProvider {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl GitProvider for SystemGitProvider {
+    fn clone(
+        &self,
+        url: &str,
+        target: &Path,
+        depth: Option<usize>,
+        branch: Option<&str>,
+        recurse_submodules: bool,
+    ) -> Result<()> {
+        let mut command = self.command();
+        command.arg("clone");
+        if let Some(depth) = depth {
+            command.arg(format!("--depth={depth}"));
+        }
+        if let Some(branch) = branch {
+            command.args([OsStr::new("--branch"), OsStr::new(branch)]);
+        }
+        if recurse_submodules {
+            command.arg("--recurse-submodules");
+        }
+        command.args([OsStr::new("--"), OsStr::new(url), target.as_os_str()]);
+        self.run(command, "clone")
+    }
+
+    fn fetch(&self, repo_path: &Path, remote: &str) -> Result<()> {
+        self.fetch_refspecs(repo_path, remote, &["HEAD".to_string()])
+    }
+
+    fn fast_forward(&self, repo_path: &Path) -> Result<usize> {
+        self.local.fast_forward(repo_path)
+    }
+
+    fn rebase(&self, repo_path: &Path) -> Result<()> {
+        self.local.rebase(repo_path)
+    }
+
+    fn is_up_to_date(&self, repo_path: &Path) -> Result<bool> {
+        self.local.is_up_to_date(repo_path)
+    }
+
+    fn status(&self, repo_path: &Path) -> Result<GitStatus> {
+        self.local.status(repo_path)
+    }
+
+    fn current_branch(&self, repo_path: &Path) -> Result<String> {
+        self.local.current_branch(repo_path)
+    }
+
+    fn stage(&self, repo_path: &Path, paths: &[PathBuf]) -> Result<()> {
+        self.local.stage(repo_path, paths)
+    }
+
+    fn staged_changes(&self, repo_path: &Path) -> Result<Vec<PathBuf>> {
+        self.local.staged_changes(repo_path)
+    }
+
+    fn commit(&self, repo_path: &Path, message: &str) -> Result<String> {
+        self.local.commit(repo_path, message)
+    }
+
+    fn push(&self, repo_path: &Path) -> Result<()> {
+        let repo = git2::Repository::open(repo_path).map_err(git_err)?;
+        let branch = self.current_branch(repo_path)?;
+        let branch_ref = format!("refs/heads/{branch}");
+        let remote_name = push_remote(&repo, &branch_ref);
+
+        let mut command = self.command();
+        command.arg("-C").arg(repo_path).args([
+            "push",
+            &remote_name,
+            &format!("{branch_ref}:{branch_ref}"),
+        ]);
+        self.run(command, "push")
+    }
+}
+
+/// Whether `config` asks for the `git` binary instead of libgit2
+///
+/// Either `[git] useSystemGit = true` or `[general] useBuiltinGit = false`.
 #[must_use]
-pub fn create_provider(_use_builtin: &guisu_config::config::AutoBool) -> Box<dyn GitProvider> {
-    Box::new(Git2Provider::new())
+pub fn use_system_git(config: &guisu_config::Config) -> bool {
+    config.git.use_system_git
+        || matches!(
+            config.general.use_builtin_git,
+            guisu_config::config::AutoBool::False
+        )
+}
+
+/// Create the git provider selected by `config`
+#[must_use]
+pub fn create_provider(config: &guisu_config::Config) -> Box<dyn GitProvider> {
+    if use_system_git(config) {
+        Box::new(SystemGitProvider::new())
+    } else {
+        Box::new(Git2Provider::new())
+    }
+}
+
+/// Git file mode of executable blobs
+const MODE_EXECUTABLE: i32 = 0o100_755;
+/// Git file mode of symbolic links
+const MODE_SYMLINK: i32 = 0o120_000;
+
+/// Source directory as of a git revision, read from the object database
+///
+/// The working tree and index are never touched, so a commit, tag, or branch
+/// can be applied while the checkout has other changes. Git only records the
+/// executable bit, so files are exported with mode 0644 or 0755, as in a
+/// fresh clone. Submodules are skipped.
+///
+/// As a [`SourceReader`], the source state is scanned and read straight from
+/// the revision's tree.
+pub struct GitTreeSource {
+    // libgit2 repositories are not safe to share between threads
+    repo: Mutex<git2::Repository>,
+    commit: git2::Oid,
+    tree: git2::Oid,
+}
+
+impl std::fmt::Debug for GitTreeSource {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("GitTreeSource")
+            .field("commit", &self.commit)
+            .field("tree", &self.tree)
+            .finish_non_exhaustive()
+    }
+}
+
+impl GitTreeSource {
+    /// Resolve `revision` in the repository containing `source_dir`
+    ///
+    /// When `source_dir` is a subdirectory of the working tree, only that
+    /// subdirectory of the revision is used.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if `source_dir` is not in a git repository, the
+    /// revision does not name a commit, or the source directory does not
+    /// exist in it
+    pub fn open(source_dir: &Path, revision: &str) -> Result<Self> {
+        let repo = git2::Repository::discover(source_dir).map_err(git_err)?;
+        let subdir = Self::subdir(&repo, source_dir)?;
+        let (commit, tree) = {
+            let commit = repo
+                .revparse_single(revision)
+                .and_then(|object| object.peel_to_commit())
+                .map_err(|e| {
+                    guisu_core::Error::Message(format!("Unknown revision '{revision}': {e}"))
+                })?;
+            let mut tree = commit.tree().map_err(git_err)?;
+            if !subdir.as_os_str().is_empty() {
+                tree = tree
+                    .get_path(&subdir)
+                    .and_then(|entry| entry.to_object(&repo))
+                    .and_then(|object| object.peel_to_tree())
+                    .map_err(|_| {
+                        guisu_core::Error::Message(format!(
+                            "{} does not exist at revision '{revision}'",
+                            subdir.display()
+                        ))
+                    })?;
+            }
+            (commit.id(), tree.id())
+        };
+
+        Ok(Self {
+            repo: Mutex::new(repo),
+            commit,
+            tree,
+        })
+    }
+
+    /// Path of `source_dir` relative to the working tree, empty at its root
+    fn subdir(repo: &git2::Repository, source_dir: &Path) -> Result<PathBuf> {
+        let workdir = repo
+            .workdir()
+            .ok_or_else(|| guisu_core::Error::Message("Repository has no working tree".into()))?;
+        Ok(std::fs::canonicalize(source_dir)
+            .ok()
+            .zip(std::fs::canonicalize(workdir).ok())
+            .and_then(|(source, workdir)| source.strip_prefix(&workdir).ok().map(Path::to_path_buf))
+            .unwrap_or_default())
+    }
+
+    /// Full ID of the commit the revision resolved to
+    #[must_use]
+    pub fn commit_id(&self) -> String {
+        self.commit.to_string()
+    }
+
+    /// Write the files of the source directory at this revision under `dest`
+    ///
+    /// Returns the number of files and symlinks written.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if an object cannot be read or a file cannot be written
+    pub fn export(&self, dest: &Path) -> Result<usize> {
+        let repo = self.repo();
+        let tree = repo.find_tree(self.tree).map_err(git_err)?;
+        std::fs::create_dir_all(dest).map_err(|e| file_err(dest, e))?;
+        let mut written = 0;
+        let mut failure = None;
+
+        tree.walk(git2::TreeWalkMode::PreOrder, |parent, entry| {
+            let Some(name) = entry.name() else {
+                return git2::TreeWalkResult::Skip;
+            };
+            let path = dest.join(parent).join(name);
+            let result = match entry.kind() {
+                Some(git2::ObjectType::Tree) => {
+                    std::fs::create_dir_all(&path).map_err(|e| file_err(&path, e))
+                }
+                Some(git2::ObjectType::Blob) => repo
+                    .find_blob(entry.id())
+                    .map_err(git_err)
+                    .and_then(|blob| write_blob(&path, blob.content(), entry.filemode())),
+                // Submodules have no content in this repository
+                _ => {
+                    tracing::debug!(path = %path.display(), "Skipping submodule");
+                    return git2::TreeWalkResult::Skip;
+                }
+            };
+            match result {
+                Ok(()) => {
+                    if entry.kind() == Some(git2::ObjectType::Blob) {
+                        written += 1;
+                    }
+                    git2::TreeWalkResult::Ok
+                }
+                Err(e) => {
+                    failure = Some(e);
+                    git2::TreeWalkResult::Abort
+                }
+            }
+        })
+        .or_else(|e| {
+            // Aborting the walk reports an error of its own
+            if failure.is_some() {
+                Ok(())
+            } else {
+                Err(git_err(e))
+            }
+        })?;
+
+        match failure {
+            Some(e) => Err(e),
+            None => Ok(written),
+        }
+    }
+
+    /// Lock the repository, which holds no state a panic could corrupt
+    fn repo(&self) -> std::sync::MutexGuard<'_, git2::Repository> {
+        self.repo
+            .lock()
+            .unwrap_or_else(std::sync::PoisonError::into_inner)
+    }
+
+    /// Tree entry of a file at this revision
+    fn blob_entry(
+        repo: &git2::Repository,
+        tree: git2::Oid,
+        path: &Path,
+    ) -> Result<(git2::Oid, i32)> {
+        let entry = repo
+            .find_tree(tree)
+            .and_then(|tree| tree.get_path(path))
+            .map_err(|_| guisu_core::Error::FileRead {
+                path: path.to_path_buf(),
+                source: std::io::Error::from(std::io::ErrorKind::NotFound),
+            })?;
+        Ok((entry.id(), entry.filemode()))
+    }
+}
+
+impl SourceReader for GitTreeSource {
+    fn walk(&self, skip: &dyn Fn(&Path) -> bool) -> Result<Vec<SourceNode>> {
+        let repo = self.repo();
+        let tree = repo.find_tree(self.tree).map_err(git_err)?;
+        let mut nodes = Vec::new();
+
+        tree.walk(git2::TreeWalkMode::PreOrder, |parent, entry| {
+            let Some(name) = entry.name() else {
+                return git2::TreeWalkResult::Skip;
+            };
+            let path = Path::new(parent).join(name);
+            if skip(&path) {
+                return git2::TreeWalkResult::Skip;
+            }
+            let kind = match entry.kind() {
+                Some(git2::ObjectType::Tree) => SourceNodeKind::Directory,
+                Some(git2::ObjectType::Blob) if entry.filemode() != MODE_SYMLINK => {
+                    SourceNodeKind::File
+                }
+                _ => return git2::TreeWalkResult::Skip,
+            };
+            nodes.push(SourceNode { path, kind });
+            git2::TreeWalkResult::Ok
+        })
+        .map_err(git_err)?;
+
+        Ok(nodes)
+    }
+
+    fn mode(&self, path: &Path) -> Result<Option<u32>> {
+        let (_, filemode) = Self::blob_entry(&self.repo(), self.tree, path)?;
+        Ok(u32::try_from(filemode).ok())
+    }
+
+    fn read(&self, path: &Path) -> Result<Vec<u8>> {
+        let repo = self.repo();
+        let (id, _) = Self::blob_entry(&repo, self.tree, path)?;
+        let blob = repo.find_blob(id).map_err(git_err)?;
+        Ok(blob.content().to_vec())
+    }
+}
+
+/// Error for a file that could not be written
+fn file_err(path: &Path, source: std::io::Error) -> guisu_core::Error {
+    guisu_core::Error::FileWrite {
+        path: path.to_path_buf(),
+        source,
+    }
+}
+
+/// Write the content of a blob as a file or symlink, by its git file mode
+fn write_blob(path: &Path, content: &[u8], filemode: i32) -> Result<()> {
+    if filemode == MODE_SYMLINK {
+        #[cfg(unix)]
+        {
+            use std::os::unix::ffi::OsStrExt;
+            return std::os::unix::fs::symlink(OsStr::from_bytes(content), path)
+                .map_err(|e| file_err(path, e));
+        }
+    }
+
+    std::fs::write(path, content).map_err(|e| file_err(path, e))?;
+    #[cfg(unix)]
+    if filemode == MODE_EXECUTABLE {
+        use std::os::unix::fs::PermissionsExt;
+        std::fs::set_permissions(path, std::fs::Permissions::from_mode(0o755))
+            .map_err(|e| file_err(path, e))?;
+    }
+    Ok(())
 }
 
 /// Find git working tree root starting from the given path
@@ -411,3 +1002,239 @@ pub fn find_working_tree(start_path: &Path) -> Option<std::path::PathBuf> {
 
     None
 }
+
+#[cfg(test)]
+mod tests {
+    #![allow(clippy::unwrap_used, clippy::panic)]
+    use super::*;
+    use git2::Repository;
+    use std::fs;
+    use tempfile::TempDir;
+
+    fn init_repo(path: &Path) -> Repository {
+        let repo = Repository::init(path).unwrap();
+        let mut config = repo.config().unwrap();
+        config.set_str("user.name", "Guisu Test").unwrap();
+        config.set_str("user.email", "test@example.com").unwrap();
+        repo
+    }
+
+    #[test]
+    fn test_stage_commit_and_push() {
+        let temp = TempDir::new().unwrap();
+        let work = temp.path().join("work");
+        let remote = temp.path().join("remote.git");
+        let repo = init_repo(&work);
+        Repository::init_bare(&remote).unwrap();
+        repo.remote("origin", remote.to_str().unwrap()).unwrap();
+
+        fs::create_dir_all(work.join("home")).unwrap();
+        fs::write(work.join("home/.bashrc"), "export A=1\n").unwrap();
+        fs::write(work.join("notes.txt"), "not staged\n").unwrap();
+
+        let provider = Git2Provider::new();
+        provider.stage(&work, &[PathBuf::from("home")]).unwrap();
+        assert_eq!(
+            provider.staged_changes(&work).unwrap(),
+            vec![PathBuf::from("home/.bashrc")]
+        );
+
+        let first = provider.commit(&work, "Add .bashrc").unwrap();
+        assert_eq!(
+            provider.staged_changes(&work).unwrap(),
+            Vec::<PathBuf>::new()
+        );
+
+        // Deletions are staged too
+        fs::remove_file(work.join("home/.bashrc")).unwrap();
+        provider.stage(&work, &[PathBuf::from("home")]).unwrap();
+        assert_eq!(
+            provider.staged_changes(&work).unwrap(),
+            vec![PathBuf::from("home/.bashrc")]
+        );
+        let second = provider.commit(&work, "Remove .bashrc").unwrap();
+        let head = repo.head().unwrap().peel_to_commit().unwrap();
+        assert_eq!(head.id().to_string(), second);
+        assert_eq!(head.parent_id(0).unwrap().to_string(), first);
+
+        provider.push(&work).unwrap();
+        let branch = provider.current_branch(&work).unwrap();
+        let pushed = Repository::open_bare(&remote)
+            .unwrap()
+            .refname_to_id(&format!("refs/heads/{branch}"))
+            .unwrap();
+        assert_eq!(pushed.to_string(), second);
+    }
+
+    #[test]
+    fn test_system_git_clone_fetch_and_push() {
+        if which::which("git").is_err() {
+            return;
+        }
+        let temp = TempDir::new().unwrap();
+        let origin = temp.path().join("origin");
+        let remote = temp.path().join("remote.git");
+        let clone = temp.path().join("clone");
+
+        let repo = init_repo(&origin);
+        fs::write(origin.join("README.md"), "dotfiles\n").unwrap();
+        let git2 = Git2Provider::new();
+        git2.stage(&origin, &[PathBuf::from("README.md")]).unwrap();
+        git2.commit(&origin, "Initial commit").unwrap();
+        Repository::init_bare(&remote).unwrap();
+        repo.remote("origin", remote.to_str().unwrap()).unwrap();
+        git2.push(&origin).unwrap();
+
+        let provider = SystemGitProvider::new();
+        provider
+            .clone(remote.to_str().unwrap(), &clone, None, None, false)
+            .unwrap();
+        assert_eq!(
+            fs::read_to_string(clone.join("README.md")).unwrap(),
+            "dotfiles\n"
+        );
+
+        let mut config = Repository::open(&clone).unwrap().config().unwrap();
+        config.set_str("user.name", "Guisu Test").unwrap();
+        config.set_str("user.email", "test@example.com").unwrap();
+        fs::write(clone.join("notes.txt"), "notes\n").unwrap();
+        provider
+            .stage(&clone, &[PathBuf::from("notes.txt")])
+            .unwrap();
+        let commit = provider.commit(&clone, "Add notes").unwrap();
+        provider.push(&clone).unwrap();
+
+        let branch = provider.current_branch(&clone).unwrap();
+        let pushed = Repository::open_bare(&remote)
+            .unwrap()
+            .refname_to_id(&format!("refs/heads/{branch}"))
+            .unwrap();
+        assert_eq!(pushed.to_string(), commit);
+
+        provider.fetch(&origin, "origin").unwrap();
+        assert!(!provider.is_up_to_date(&origin).unwrap());
+        provider.fast_forward(&origin).unwrap();
+        assert!(origin.join("notes.txt").exists());
+    }
+
+    #[test]
+    fn test_system_git_missing_binary() {
+        let temp = TempDir::new().unwrap();
+        let provider = SystemGitProvider::with_program(temp.path().join("no-such-git"));
+        let err = provider
+            .clone(
+                "https://example.com/repo.git",
+                &temp.path().join("clone"),
+                None,
+                None,
+                false,
+            )
+            .unwrap_err();
+        assert!(err.to_string().contains("useSystemGit"));
+    }
+
+    #[test]
+    fn test_create_provider_selection() {
+        let mut config = guisu_config::Config::default();
+        assert!(!use_system_git(&config));
+        config.git.use_system_git = true;
+        assert!(use_system_git(&config));
+
+        let mut config = guisu_config::Config::default();
+        config.general.use_builtin_git = guisu_config::config::AutoBool::False;
+        assert!(use_system_git(&config));
+    }
+
+    #[test]
+    fn test_git_tree_source_export() {
+        let temp = TempDir::new().unwrap();
+        let work = temp.path().join("work");
+        init_repo(&work);
+        fs::create_dir_all(work.join("dotfiles/home/.config")).unwrap();
+        fs::write(work.join("dotfiles/home/.bashrc"), "export A=1\n").unwrap();
+        fs::write(work.join("dotfiles/home/.config/run.sh"), "#!/bin/sh\n").unwrap();
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            fs::set_permissions(
+                work.join("dotfiles/home/.config/run.sh"),
+                fs::Permissions::from_mode(0o755),
+            )
+            .unwrap();
+        }
+        fs::write(work.join("README.md"), "outside the source\n").unwrap();
+
+        let provider = Git2Provider::new();
+        provider.stage(&work, &[PathBuf::from(".")]).unwrap();
+        provider.commit(&work, "v1").unwrap();
+        let repo = Repository::open(&work).unwrap();
+        let head = repo.head().unwrap().peel_to_commit().unwrap();
+        repo.tag_lightweight("v1", head.as_object(), false).unwrap();
+
+        // Later changes in the working tree are not exported
+        fs::write(work.join("dotfiles/home/.bashrc"), "export A=2\n").unwrap();
+        fs::write(work.join("dotfiles/home/.profile"), "uncommitted\n").unwrap();
+
+        let source = GitTreeSource::open(&work.join("dotfiles"), "v1").unwrap();
+        assert_eq!(source.commit_id(), head.id().to_string());
+        let dest = temp.path().join("export");
+        assert_eq!(source.export(&dest).unwrap(), 2);
+        assert_eq!(
+            fs::read_to_string(dest.join("home/.bashrc")).unwrap(),
+            "export A=1\n"
+        );
+        assert!(!dest.join("home/.profile").exists());
+        assert!(!dest.join("README.md").exists());
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            let mode = fs::metadata(dest.join("home/.config/run.sh"))
+                .unwrap()
+                .permissions()
+                .mode();
+            assert_eq!(mode & 0o777, 0o755);
+        }
+
+        assert!(GitTreeSource::open(&work.join("dotfiles"), "v2").is_err());
+    }
+
+    #[test]
+    fn test_git_tree_source_reader() {
+        let temp = TempDir::new().unwrap();
+        let work = fs::canonicalize(temp.path()).unwrap();
+        init_repo(&work);
+        fs::create_dir_all(work.join("home/exact_.vim")).unwrap();
+        fs::write(work.join("home/.bashrc"), "export A=1\n").unwrap();
+        fs::write(work.join("home/exact_.vim/init.vim"), "set nu\n").unwrap();
+        let provider = Git2Provider::new();
+        provider.stage(&work, &[PathBuf::from(".")]).unwrap();
+        provider.commit(&work, "Initial").unwrap();
+        fs::write(work.join("home/.bashrc"), "export A=2\n").unwrap();
+        fs::write(work.join("home/.profile"), "uncommitted\n").unwrap();
+
+        let home = work.join("home");
+        let reader = std::sync::Arc::new(GitTreeSource::open(&home, "HEAD").unwrap());
+        let source = crate::state::SourceState::read_from(
+            guisu_core::path::AbsPath::new(home).unwrap(),
+            reader,
+            None,
+        )
+        .unwrap();
+
+        let mut targets: Vec<String> = source
+            .entries()
+            .map(|entry| entry.target_path().to_string())
+            .collect();
+        targets.sort();
+        assert_eq!(targets, [".bashrc", ".vim/init.vim"]);
+        let bashrc = source
+            .entries()
+            .find(|entry| entry.target_path().to_string() == ".bashrc")
+            .unwrap();
+        assert_eq!(
+            source.read_file(bashrc.source_path()).unwrap(),
+            b"export A=1\n"
+        );
+        assert_eq!(source.exact_dirs().count(), 1);
+    }
+}
@@ -3,6 +3,7 @@
 //! Defines the core types for hook configuration including Hook definitions,
 //! collections, stages, and execution modes.
 
+use globset::{Glob, GlobSet, GlobSetBuilder};
 use guisu_core::{Error, Result};
 use indexmap::IndexMap;
 use serde::{Deserialize, Serialize};
@@ -88,9 +89,57 @@ pub struct Hook {
     /// if it runs longer than the specified number of seconds.
     #[serde(default)]
     pub timeout: u64,
+
+    /// Number of times to retry a failed run (default: 0 = no retries)
+    ///
+    /// Useful for hooks that depend on the network, such as package
+    /// installs against a flaky mirror.
+    #[serde(default)]
+    pub retries: u32,
+
+    /// Delay in seconds before the first retry (default: 1)
+    ///
+    /// The delay doubles after each failed attempt.
+    #[serde(default = "default_retry_delay")]
+    pub retry_delay: u64,
+
+    /// Working directory to run the hook in (default: source directory)
+    ///
+    /// Rendered as a template, then `${VAR}` references are expanded.
+    /// Relative paths are resolved against the source directory.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub working_dir: Option<String>,
+
+    /// Shell to run `cmd` through (e.g. "bash" or "sh -e")
+    ///
+    /// When set, the command is executed as `<shell> -c <cmd>`, so pipes,
+    /// redirections and globs work. Without it, `cmd` is executed directly.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub shell: Option<String>,
+
+    /// User to run the hook as via `sudo -u` (Unix only)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub user: Option<String>,
+
+    /// Target path globs that scope a post hook (empty = always run)
+    ///
+    /// Patterns are matched against paths relative to the destination
+    /// (e.g. ".config/nvim/**"). During apply the hook only runs when at
+    /// least one changed entry matches.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub paths: Vec<String>,
 }
 
 impl Hook {
+    /// Delay before retry number `attempt` (1-based), doubling each time
+    #[must_use]
+    pub fn retry_backoff(&self, attempt: u32) -> std::time::Duration {
+        let factor = 1u64
+            .checked_shl(attempt.saturating_sub(1))
+            .unwrap_or(u64::MAX);
+        std::time::Duration::from_secs(self.retry_delay.saturating_mul(factor))
+    }
+
     /// Get the content of this hook for hashing (used in onchange mode)
     ///
     /// Returns the cmd or script content that should be hashed to detect changes
@@ -116,6 +165,7 @@ impl Hook {
     /// - Valid platform names
     /// - Valid environment variable names
     /// - Non-empty name
+    /// - Non-empty `working_dir` and `shell`, `shell` only with `cmd`, valid `user`
     ///
     /// # Errors
     ///
@@ -207,9 +257,79 @@ impl Hook {
             )));
         }
 
+        self.validate_execution_options()
+    }
+
+    /// Validate `working_dir`, `shell` and `user`
+    fn validate_execution_options(&self) -> Result<()> {
+        if let Some(working_dir) = &self.working_dir
+            && working_dir.trim().is_empty()
+        {
+            return Err(Error::HookConfig(format!(
+                "Hook '{}' has empty 'working_dir' field",
+                self.name
+            )));
+        }
+
+        if let Some(shell) = &self.shell {
+            if shell.trim().is_empty() {
+                return Err(Error::HookConfig(format!(
+                    "Hook '{}' has empty 'shell' field",
+                    self.name
+                )));
+            }
+            if self.cmd.is_none() {
+                return Err(Error::HookConfig(format!(
+                    "Hook '{}' sets 'shell' but has no 'cmd' (scripts use their shebang)",
+                    self.name
+                )));
+            }
+        }
+
+        if let Some(user) = &self.user {
+            if cfg!(not(unix)) {
+                return Err(Error::HookConfig(format!(
+                    "Hook '{}' sets 'user', which is only supported on Unix",
+                    self.name
+                )));
+            }
+            if user.is_empty() || user.starts_with('-') || user.chars().any(char::is_whitespace) {
+                return Err(Error::HookConfig(format!(
+                    "Hook '{}' has invalid user '{}'",
+                    self.name, user
+                )));
+            }
+        }
+
+        self.path_matcher()?;
+
         Ok(())
     }
 
+    /// Build a matcher for `paths`
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if any pattern is not a valid glob
+    pub fn path_matcher(&self) -> Result<GlobSet> {
+        let mut builder = GlobSetBuilder::new();
+        for pattern in &self.paths {
+            let glob = Glob::new(pattern).map_err(|e| {
+                Error::HookConfig(format!(
+                    "Hook '{}' has invalid path pattern '{}': {}",
+                    self.name, pattern, e
+                ))
+            })?;
+            builder.add(glob);
+        }
+        builder.build().map_err(|e| {
+            Error::HookConfig(format!(
+                "Hook '{}' has invalid path patterns: {}",
+                self.name, e
+            ))
+        })
+    }
+
     /// Check if this hook should run on the given platform
     #[must_use]
     pub fn should_run_on(&self, platform: &str) -> bool {
@@ -282,6 +402,11 @@ pub(crate) fn default_failfast() -> bool {
     true
 }
 
+/// Default delay before the first hook retry, in seconds
+pub(crate) fn default_retry_delay() -> u64 {
+    1
+}
+
 #[cfg(test)]
 mod tests {
     #![allow(clippy::unwrap_used, clippy::panic)]
@@ -312,6 +437,12 @@ mod tests {
             failfast: true,
             mode: HookMode::Always,
             timeout: 0,
+            working_dir: None,
+            shell: None,
+            user: None,
+            paths: Vec::new(),
+            retries: 0,
+            retry_delay: 1,
         });
 
         assert!(!collections.is_empty());
@@ -344,6 +475,12 @@ mod tests {
             failfast: true,
             mode: HookMode::Always,
             timeout: 0,
+            working_dir: None,
+            shell: None,
+            user: None,
+            paths: Vec::new(),
+            retries: 0,
+            retry_delay: 1,
         }
     }
 
@@ -360,6 +497,12 @@ mod tests {
             failfast: true,
             mode: HookMode::Always,
             timeout: 0,
+            working_dir: None,
+            shell: None,
+            user: None,
+            paths: Vec::new(),
+            retries: 0,
+            retry_delay: 1,
         };
 
         assert_eq!(hook.get_content(), "echo hello");
@@ -378,6 +521,12 @@ mod tests {
             failfast: true,
             mode: HookMode::Always,
             timeout: 0,
+            working_dir: None,
+            shell: None,
+            user: None,
+            paths: Vec::new(),
+            retries: 0,
+            retry_delay: 1,
         };
 
         assert_eq!(hook.get_content(), "script.sh");
@@ -396,6 +545,12 @@ mod tests {
             failfast: true,
             mode: HookMode::Always,
             timeout: 0,
+            working_dir: None,
+            shell: None,
+            user: None,
+            paths: Vec::new(),
+            retries: 0,
+            retry_delay: 1,
         };
 
         assert_eq!(hook.get_content(), "");
@@ -414,6 +569,12 @@ mod tests {
             failfast: true,
             mode: HookMode::Always,
             timeout: 0,
+            working_dir: None,
+            shell: None,
+            user: None,
+            paths: Vec::new(),
+            retries: 0,
+            retry_delay: 1,
         };
 
         let result = hook.validate();
@@ -439,6 +600,12 @@ mod tests {
             failfast: true,
             mode: HookMode::Always,
             timeout: 0,
+            working_dir: None,
+            shell: None,
+            user: None,
+            paths: Vec::new(),
+            retries: 0,
+            retry_delay: 1,
         };
 
         let result = hook.validate();
@@ -459,6 +626,12 @@ mod tests {
             failfast: true,
             mode: HookMode::Always,
             timeout: 0,
+            working_dir: None,
+            shell: None,
+            user: None,
+            paths: Vec::new(),
+            retries: 0,
+            retry_delay: 1,
         };
 
         let result = hook.validate();
@@ -479,6 +652,12 @@ mod tests {
             failfast: true,
             mode: HookMode::Always,
             timeout: 0,
+            working_dir: None,
+            shell: None,
+            user: None,
+            paths: Vec::new(),
+            retries: 0,
+            retry_delay: 1,
         };
 
         assert!(hook.validate().is_ok());
@@ -497,6 +676,12 @@ mod tests {
             failfast: true,
             mode: HookMode::Always,
             timeout: 0,
+            working_dir: None,
+            shell: None,
+            user: None,
+            paths: Vec::new(),
+            retries: 0,
+            retry_delay: 1,
         };
 
         assert!(hook.validate().is_ok());
@@ -515,6 +700,12 @@ mod tests {
             failfast: true,
             mode: HookMode::Always,
             timeout: 0,
+            working_dir: None,
+            shell: None,
+            user: None,
+            paths: Vec::new(),
+            retries: 0,
+            retry_delay: 1,
         };
 
         let result = hook.validate();
@@ -540,6 +731,12 @@ mod tests {
             failfast: true,
             mode: HookMode::Always,
             timeout: 0,
+            working_dir: None,
+            shell: None,
+            user: None,
+            paths: Vec::new(),
+            retries: 0,
+            retry_delay: 1,
         };
 
         let result = hook.validate();
@@ -568,6 +765,12 @@ mod tests {
             failfast: true,
             mode: HookMode::Always,
             timeout: 0,
+            working_dir: None,
+            shell: None,
+            user: None,
+            paths: Vec::new(),
+            retries: 0,
+            retry_delay: 1,
         };
 
         let result = hook.validate();
@@ -596,6 +799,12 @@ mod tests {
             failfast: true,
             mode: HookMode::Always,
             timeout: 0,
+            working_dir: None,
+            shell: None,
+            user: None,
+            paths: Vec::new(),
+            retries: 0,
+            retry_delay: 1,
         };
 
         let result = hook.validate();
@@ -624,6 +833,12 @@ mod tests {
             failfast: true,
             mode: HookMode::Always,
             timeout: 0,
+            working_dir: None,
+            shell: None,
+            user: None,
+            paths: Vec::new(),
+            retries: 0,
+            retry_delay: 1,
         };
 
         let result = hook.validate();
@@ -655,6 +870,12 @@ mod tests {
             failfast: true,
             mode: HookMode::Always,
             timeout: 0,
+            working_dir: None,
+            shell: None,
+            user: None,
+            paths: Vec::new(),
+            retries: 0,
+            retry_delay: 1,
         };
 
         assert!(hook.validate().is_ok());
@@ -751,6 +972,12 @@ mod tests {
             failfast: true,
             mode: HookMode::OnChange,
             timeout: 30,
+            working_dir: None,
+            shell: None,
+            user: None,
+            paths: Vec::new(),
+            retries: 0,
+            retry_delay: 1,
         };
 
         let toml = toml::to_string(&hook).unwrap();
@@ -822,6 +1049,12 @@ cmd = "echo post"
             failfast: true,
             mode: HookMode::Always,
             timeout: 0,
+            working_dir: None,
+            shell: None,
+            user: None,
+            paths: Vec::new(),
+            retries: 0,
+            retry_delay: 1,
         };
 
         // script_content should be skipped in serialization
@@ -852,6 +1085,12 @@ cmd = "echo post"
             failfast: false,
             mode: HookMode::OnChange,
             timeout: 120,
+            working_dir: None,
+            shell: None,
+            user: None,
+            paths: Vec::new(),
+            retries: 0,
+            retry_delay: 1,
         };
 
         assert!(hook.validate().is_ok());
@@ -893,4 +1132,106 @@ cmd = "echo post"
         assert_eq!(original.pre.len(), cloned.pre.len());
         assert_eq!(original.pre[0].name, cloned.pre[0].name);
     }
+
+    #[test]
+    fn test_hook_execution_options_from_toml() {
+        let hook: Hook = toml::from_str(
+            r#"
+name = "as-root"
+cmd = "echo $HOME"
+working_dir = "{{ env.HOME }}"
+shell = "bash"
+user = "root"
+"#,
+        )
+        .unwrap();
+
+        assert_eq!(hook.working_dir.as_deref(), Some("{{ env.HOME }}"));
+        assert_eq!(hook.shell.as_deref(), Some("bash"));
+        assert_eq!(hook.user.as_deref(), Some("root"));
+    }
+
+    #[test]
+    fn test_hook_validate_empty_working_dir() {
+        let mut hook = create_test_hook("test");
+        hook.working_dir = Some("  ".to_string());
+
+        let err = hook.validate().unwrap_err();
+        assert!(err.to_string().contains("empty 'working_dir'"));
+    }
+
+    #[test]
+    fn test_hook_validate_shell_requires_cmd() {
+        let mut hook = create_test_hook("test");
+        hook.cmd = None;
+        hook.script = Some("script.sh".to_string());
+        hook.shell = Some("bash".to_string());
+
+        let err = hook.validate().unwrap_err();
+        assert!(err.to_string().contains("'shell'"));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_hook_validate_user() {
+        let mut hook = create_test_hook("test");
+        hook.user = Some("root".to_string());
+        assert!(hook.validate().is_ok());
+
+        hook.user = Some("-s".to_string());
+        assert!(hook.validate().is_err());
+
+        hook.user = Some("bad user".to_string());
+        assert!(hook.validate().is_err());
+    }
+
+    #[test]
+    fn test_hook_retry_backoff() {
+        let hook: Hook = toml::from_str(
+            r#"
+            name = "install"
+            cmd = "brew bundle"
+            retries = 3
+            retry_delay = 2
+            "#,
+        )
+        .unwrap();
+        assert_eq!(hook.retries, 3);
+        assert_eq!(hook.retry_backoff(1).as_secs(), 2);
+        assert_eq!(hook.retry_backoff(2).as_secs(), 4);
+        assert_eq!(hook.retry_backoff(3).as_secs(), 8);
+
+        let hook: Hook = toml::from_str("name = \"x\"\ncmd = \"true\"").unwrap();
+        assert_eq!(hook.retries, 0);
+        assert_eq!(hook.retry_delay, 1);
+    }
+
+    #[test]
+    fn test_hook_paths_from_toml() {
+        let hook: Hook = toml::from_str(
+            r#"
+name = "nvim-sync"
+cmd = "nvim --headless +Lazy! sync +qa"
+paths = [".config/nvim/**"]
+"#,
+        )
+        .unwrap();
+
+        assert_eq!(hook.paths, vec![".config/nvim/**".to_string()]);
+        assert!(hook.validate().is_ok());
+        assert!(
+            hook.path_matcher()
+                .unwrap()
+                .is_match(".config/nvim/init.lua")
+        );
+    }
+
+    #[test]
+    fn test_hook_validate_invalid_path_pattern() {
+        let mut hook = create_test_hook("test");
+        hook.paths = vec!["[".to_string()];
+
+        let err = hook.validate().unwrap_err();
+        assert!(err.to_string().contains("invalid path pattern"));
+    }
 }
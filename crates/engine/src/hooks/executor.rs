@@ -13,6 +13,73 @@ use std::path::{Path, PathBuf};
 /// Result tuple from hook execution: (`cached_hash`, `rendered_content`, `execution_result`)
 type HookExecutionResult = (Option<[u8; 32]>, Option<String>, Result<()>);
 
+/// Callback invoked with each finished hook run
+type OutputHandler<'a> = Box<dyn Fn(&HookRun) + Send + Sync + 'a>;
+
+/// A finished hook execution with its captured output
+#[derive(Debug, Clone, PartialEq, Eq, bincode::Encode, bincode::Decode)]
+pub struct HookRun {
+    /// Name of the hook
+    pub name: String,
+    /// Stage the hook ran in ("pre" or "post")
+    pub stage: String,
+    /// Captured standard output
+    pub stdout: String,
+    /// Captured standard error
+    pub stderr: String,
+    /// Exit code (`None` if the hook did not start, timed out, or was killed by a signal)
+    pub exit_code: Option<i32>,
+    /// Error message if the hook failed
+    pub error: Option<String>,
+    /// Wall-clock duration in milliseconds
+    pub duration_ms: u64,
+}
+
+impl HookRun {
+    /// Check if the hook completed successfully
+    #[must_use]
+    pub fn succeeded(&self) -> bool {
+        self.error.is_none()
+    }
+}
+
+/// A hook as `run_stage` would handle it, without executing it
+#[derive(Debug, Clone)]
+pub struct PlannedHook<'h> {
+    /// The hook definition
+    pub hook: &'h Hook,
+    /// Why the hook would be skipped, or `None` if it would run
+    pub skip_reason: Option<String>,
+    /// Content compared for mode=onchange (rendered for template scripts)
+    pub onchange_content: Option<String>,
+}
+
+/// Output captured from a hook process
+///
+/// A non-zero exit is reported through `exit_code` rather than as an error,
+/// so the output of failed hooks is kept.
+#[derive(Debug, Default)]
+struct CapturedOutput {
+    stdout: String,
+    stderr: String,
+    exit_code: Option<i32>,
+}
+
+impl CapturedOutput {
+    /// Turn a non-zero or missing exit code of hook `name` into an error
+    fn check_status(&self, name: &str) -> Result<()> {
+        match self.exit_code {
+            Some(0) => Ok(()),
+            Some(code) => Err(Error::HookExecution(format!(
+                "Hook '{name}' exited with code {code}"
+            ))),
+            None => Err(Error::HookExecution(format!(
+                "Hook '{name}' was terminated by a signal"
+            ))),
+        }
+    }
+}
+
 /// Template rendering trait for hook scripts
 pub trait TemplateRenderer {
     /// Render a template string
@@ -67,6 +134,12 @@ where
     onchange_hashes: std::sync::Arc<std::sync::Mutex<std::collections::HashMap<String, [u8; 32]>>>,
     /// Rendered content for onchange hooks executed in this session (thread-safe)
     onchange_rendered: std::sync::Arc<std::sync::Mutex<std::collections::HashMap<String, String>>>,
+    /// Hooks executed in this session with their captured output (thread-safe)
+    runs: std::sync::Arc<std::sync::Mutex<Vec<HookRun>>>,
+    /// Called with each hook run as soon as it finishes
+    output_handler: Option<OutputHandler<'a>>,
+    /// Target paths changed by the current apply (None = no change set)
+    changed_files: Option<Vec<String>>,
 }
 
 impl<'a> HookRunner<'a, NoOpRenderer> {
@@ -114,7 +187,7 @@ impl<'a> HookRunner<'a, NoOpRenderer> {
     }
 }
 
-impl<R> HookRunner<'_, R>
+impl<'a, R> HookRunner<'a, R>
 where
     R: TemplateRenderer + Sync,
 {
@@ -160,6 +233,35 @@ where
             .clone()
     }
 
+    /// Get the hooks executed in this session with their captured output
+    ///
+    /// Runs are in completion order. Failed hooks are included, so this should
+    /// be read even when `run_stage` returns an error.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the mutex is poisoned (should never happen in normal operation)
+    pub fn get_runs(&self) -> Vec<HookRun> {
+        self.runs.lock().expect("Hook runs mutex poisoned").clone()
+    }
+
+    /// Check if a hook is scoped by `paths` and no changed file matches
+    ///
+    /// Without a change set (e.g. `guisu hooks run`) scoped hooks always run.
+    /// Invalid patterns are left for `Hook::validate` to report.
+    fn out_of_scope(&self, hook: &Hook) -> bool {
+        if hook.paths.is_empty() {
+            return false;
+        }
+        let Some(changed) = &self.changed_files else {
+            return false;
+        };
+        let Ok(matcher) = hook.path_matcher() else {
+            return false;
+        };
+        !changed.iter().any(|path| matcher.is_match(path))
+    }
+
     /// Check if a hook should be skipped based on its mode
     ///
     /// Returns (`should_skip`, reason, `cached_hash`, `rendered_content`) for logging and state update
@@ -308,6 +410,50 @@ where
         }
     }
 
+    /// Plan a stage without executing anything
+    ///
+    /// Returns the hooks for the current platform in execution order, with the
+    /// reason each one would be skipped. Hooks with the same order run in
+    /// parallel.
+    #[must_use]
+    pub fn plan_stage(&self, stage: HookStage) -> Vec<PlannedHook<'a>> {
+        let hooks = match stage {
+            HookStage::Pre => &self.collections.pre,
+            HookStage::Post => &self.collections.post,
+        };
+        let platform = CURRENT_PLATFORM.os;
+
+        let mut planned: Vec<PlannedHook<'a>> = hooks
+            .iter()
+            .filter(|hook| hook.should_run_on(platform))
+            .map(|hook| {
+                if self.out_of_scope(hook) {
+                    return PlannedHook {
+                        hook,
+                        skip_reason: Some("no changed path matches".to_string()),
+                        onchange_content: None,
+                    };
+                }
+                let (should_skip, reason, _cached_hash, onchange_content) =
+                    self.should_skip_hook(hook);
+                let skip_reason = if should_skip {
+                    Some(reason.to_string())
+                } else {
+                    hook.validate().err().map(|e| e.to_string())
+                };
+                PlannedHook {
+                    hook,
+                    skip_reason,
+                    onchange_content,
+                }
+            })
+            .collect();
+
+        // Stable sort keeps declaration order within an order group
+        planned.sort_by_key(|p| p.hook.order);
+        planned
+    }
+
     /// Run all hooks for a specific stage
     ///
     /// # Errors
@@ -344,6 +490,13 @@ where
                 continue;
             }
 
+            // Skip scoped hooks when none of their paths changed, without
+            // recording them as executed
+            if self.out_of_scope(hook) {
+                tracing::debug!("Skipping hook '{}' (no changed path matches)", hook.name);
+                continue;
+            }
+
             // Skip based on execution mode
             let (should_skip, reason, cached_hash, rendered_content) = self.should_skip_hook(hook);
             if should_skip {
@@ -396,10 +549,11 @@ where
                     let start = std::time::Instant::now();
                     tracing::debug!("Starting hook execution");
 
-                    // Execute hook
-                    let result = self.execute_hook(hook);
+                    // Execute hook, keeping the output of failed runs
+                    let (output, result) = self.execute_with_retries(hook);
 
                     let elapsed = start.elapsed();
+                    self.record_run(hook, stage, output, &result, elapsed);
                     match &result {
                         Ok(()) => {
                             tracing::debug!(
@@ -455,8 +609,71 @@ where
         Ok(())
     }
 
+    /// Record a finished hook run and pass it to the output handler
+    fn record_run(
+        &self,
+        hook: &Hook,
+        stage: HookStage,
+        output: CapturedOutput,
+        result: &Result<()>,
+        elapsed: std::time::Duration,
+    ) {
+        let run = HookRun {
+            name: hook.name.clone(),
+            stage: stage.name().to_string(),
+            stdout: output.stdout,
+            stderr: output.stderr,
+            exit_code: output.exit_code,
+            error: result.as_ref().err().map(ToString::to_string),
+            duration_ms: u64::try_from(elapsed.as_millis()).unwrap_or(u64::MAX),
+        };
+
+        if let Some(handler) = &self.output_handler {
+            handler(&run);
+        }
+
+        self.runs
+            .lock()
+            .expect("Hook runs mutex poisoned")
+            .push(run);
+    }
+
+    /// Execute a hook, retrying failed runs with exponential backoff
+    ///
+    /// Returns the output and result of the last attempt.
+    fn execute_with_retries(&self, hook: &Hook) -> (CapturedOutput, Result<()>) {
+        let mut attempt = 0;
+        loop {
+            let (output, result) = match self.execute_hook(hook) {
+                Ok(output) => {
+                    let result = output.check_status(&hook.name);
+                    (output, result)
+                }
+                Err(e) => (CapturedOutput::default(), Err(e)),
+            };
+
+            match &result {
+                Err(e) if attempt < hook.retries => {
+                    attempt += 1;
+                    let delay = hook.retry_backoff(attempt);
+                    tracing::warn!(
+                        error = %e,
+                        attempt,
+                        retries = hook.retries,
+                        delay_secs = delay.as_secs(),
+                        "Hook failed, retrying"
+                    );
+                    std::thread::sleep(delay);
+                }
+                _ => return (output, result),
+            }
+        }
+    }
+
     /// Execute a single hook
-    fn execute_hook(&self, hook: &Hook) -> Result<()> {
+    ///
+    /// Returns the captured output, including for hooks that exit non-zero.
+    fn execute_hook(&self, hook: &Hook) -> Result<CapturedOutput> {
         // If hook uses 'script' and is a template (.j2 extension), process it specially
         if let Some(script) = &hook.script
             && script.to_lowercase().ends_with(".j2")
@@ -464,9 +681,7 @@ where
             return self.execute_template_script(hook);
         }
 
-        // Determine working directory
-        // Working directory is always source_dir
-        let working_dir = self.source_dir.to_path_buf();
+        let working_dir = self.resolve_working_dir(hook)?;
 
         // Build environment variables (only clone if hook has custom env)
         let env = if hook.env.is_empty() {
@@ -485,8 +700,8 @@ where
         // Execute based on hook type
         match (&hook.cmd, &hook.script) {
             (Some(cmd), None) => {
-                // Direct command execution (no shell)
-                self.execute_command(cmd, &working_dir, &env, hook.timeout)
+                // Direct command execution (through `shell` only if configured)
+                self.execute_command(hook, cmd, &working_dir, &env)
                     .map_err(|e| {
                         Error::HookExecution(format!("Hook '{}' command failed: {}", hook.name, e))
                     })
@@ -498,7 +713,7 @@ where
                 } else {
                     self.source_dir.join(script_path)
                 };
-                Self::execute_script(&script_abs, &working_dir, &env, hook.timeout).map_err(|e| {
+                Self::execute_script(hook, &script_abs, &working_dir, &env).map_err(|e| {
                     Error::HookExecution(format!(
                         "Hook '{}' script '{}' failed: {}",
                         hook.name, script_path, e
@@ -523,25 +738,37 @@ where
     ///
     /// Parses the command string into program and arguments, then executes
     /// without invoking a shell. This prevents shell injection vulnerabilities.
+    /// If the hook sets `shell`, the command is passed to it as `<shell> -c <cmd>`.
     ///
     /// Supports quoted arguments: `git commit -m "Initial commit"`
-    #[tracing::instrument(skip(self, env), fields(cmd = %cmd, working_dir = %working_dir.display(), timeout))]
+    #[tracing::instrument(skip(self, hook, env), fields(cmd = %cmd, working_dir = %working_dir.display(), timeout = hook.timeout))]
     fn execute_command(
         &self,
+        hook: &Hook,
         cmd: &str,
         working_dir: &Path,
         env: &IndexMap<String, String>,
-        timeout: u64,
-    ) -> Result<()> {
-        use std::time::Duration;
+    ) -> Result<CapturedOutput> {
+        let timeout = hook.timeout;
 
         // Expand environment variables in command
         let expanded_cmd = self.expand_env_vars(cmd);
 
-        // Parse command using shell-words for proper quote handling
-        // Handles: git commit -m "Initial commit" → ["git", "commit", "-m", "Initial commit"]
-        let parts = shell_words::split(&expanded_cmd)
-            .map_err(|e| Error::HookExecution(format!("Failed to parse command '{cmd}': {e}")))?;
+        let parts = if let Some(shell) = &hook.shell {
+            // Shell may carry its own flags, e.g. "bash -e"
+            let mut parts = shell_words::split(shell).map_err(|e| {
+                Error::HookExecution(format!("Failed to parse shell '{shell}': {e}"))
+            })?;
+            parts.push("-c".to_string());
+            parts.push(expanded_cmd.into_owned());
+            parts
+        } else {
+            // Parse command using shell-words for proper quote handling
+            // Handles: git commit -m "Initial commit" → ["git", "commit", "-m", "Initial commit"]
+            shell_words::split(&expanded_cmd).map_err(|e| {
+                Error::HookExecution(format!("Failed to parse command '{cmd}': {e}"))
+            })?
+        };
 
         if parts.is_empty() {
             return Err(Error::HookExecution("Empty command".to_string()));
@@ -556,51 +783,23 @@ where
             tracing::debug!("Timeout: {} seconds", timeout);
         }
 
-        // Build command - inherits parent env by default
-        let mut cmd_builder = duct::cmd(program, args).dir(working_dir).stderr_to_stdout();
-
-        // Add custom environment variables (guisu-specific + hook-specific)
-        for (key, value) in env {
-            cmd_builder = cmd_builder.env(key, value);
-        }
-
-        let cmd_builder = cmd_builder;
+        let cmd_builder = Self::build_expression(hook, program, args, working_dir, env);
 
-        // Execute with or without timeout
-        if timeout > 0 {
-            let handle = cmd_builder.start().map_err(|e| {
-                Error::HookExecution(format!("Failed to start command '{program}': {e}"))
-            })?;
-
-            match handle.wait_timeout(Duration::from_secs(timeout)) {
-                Ok(Some(_output)) => Ok(()),
-                Ok(None) => Err(Error::HookExecution(format!(
-                    "Command '{program}' timed out after {timeout} seconds"
-                ))),
-                Err(e) => Err(Error::HookExecution(format!(
-                    "Command '{program}' failed: {e}"
-                ))),
-            }
-        } else {
-            cmd_builder
-                .run()
-                .map(|_| ())
-                .map_err(|e| Error::HookExecution(format!("Command '{program}' failed: {e}")))
-        }
+        Self::run_captured(&cmd_builder, timeout, &format!("command '{program}'"))
     }
 
     /// Execute a script using its shebang interpreter
     ///
     /// Reads the script's shebang line to determine the interpreter,
     /// then executes the script with that interpreter.
-    #[tracing::instrument(skip(env), fields(script_path = %script_path.display(), working_dir = %working_dir.display(), timeout))]
+    #[tracing::instrument(skip(hook, env), fields(script_path = %script_path.display(), working_dir = %working_dir.display(), timeout = hook.timeout))]
     fn execute_script(
+        hook: &Hook,
         script_path: &Path,
         working_dir: &Path,
         env: &IndexMap<String, String>,
-        timeout: u64,
-    ) -> Result<()> {
-        use std::time::Duration;
+    ) -> Result<CapturedOutput> {
+        let timeout = hook.timeout;
 
         if !script_path.exists() {
             return Err(Error::HookExecution(format!(
@@ -624,46 +823,129 @@ where
 
         tracing::debug!("Using interpreter: {} {:?}", interpreter, cmd_args);
 
+        let cmd_builder = Self::build_expression(hook, &interpreter, &cmd_args, working_dir, env);
+
+        Self::run_captured(
+            &cmd_builder,
+            timeout,
+            &format!("script '{}'", script_path.display()),
+        )
+    }
+
+    /// Run a process to completion with or without timeout, capturing its output
+    ///
+    /// `what` describes the process in error messages, e.g. "command 'git'".
+    /// A process that times out is killed and its output is discarded.
+    fn run_captured(
+        expression: &duct::Expression,
+        timeout: u64,
+        what: &str,
+    ) -> Result<CapturedOutput> {
+        use std::time::Duration;
+
+        let expression = expression.stdout_capture().stderr_capture().unchecked();
+
+        let output = if timeout > 0 {
+            let handle = expression
+                .start()
+                .map_err(|e| Error::HookExecution(format!("Failed to start {what}: {e}")))?;
+
+            match handle.wait_timeout(Duration::from_secs(timeout)) {
+                Ok(Some(output)) => output.clone(),
+                Ok(None) => {
+                    if let Err(e) = handle.kill() {
+                        tracing::warn!("Failed to kill timed out {}: {}", what, e);
+                    }
+                    return Err(Error::HookExecution(format!(
+                        "{what} timed out after {timeout} seconds"
+                    )));
+                }
+                Err(e) => return Err(Error::HookExecution(format!("{what} failed: {e}"))),
+            }
+        } else {
+            expression
+                .run()
+                .map_err(|e| Error::HookExecution(format!("{what} failed: {e}")))?
+        };
+
+        Ok(CapturedOutput {
+            stdout: String::from_utf8_lossy(&output.stdout).into_owned(),
+            stderr: String::from_utf8_lossy(&output.stderr).into_owned(),
+            exit_code: output.status.code(),
+        })
+    }
+
+    /// Build the process for a hook, switching to the hook's `user` when set
+    ///
+    /// The process inherits the parent environment plus `env`. `sudo` resets
+    /// the environment, so for a different user only `GUISU_*` variables and
+    /// the hook's own `env` entries are forwarded, via `env(1)`.
+    fn build_expression(
+        hook: &Hook,
+        program: &str,
+        args: &[String],
+        working_dir: &Path,
+        env: &IndexMap<String, String>,
+    ) -> duct::Expression {
+        let expression = if let Some(user) = &hook.user {
+            tracing::debug!("Running as user: {}", user);
+            let mut sudo_args = vec![
+                "-u".to_string(),
+                user.clone(),
+                "--".to_string(),
+                "env".to_string(),
+            ];
+            sudo_args.extend(
+                env.iter()
+                    .filter(|(key, _)| key.starts_with("GUISU_") || hook.env.contains_key(*key))
+                    .map(|(key, value)| format!("{key}={value}")),
+            );
+            sudo_args.push(program.to_string());
+            sudo_args.extend(args.iter().cloned());
+            duct::cmd("sudo", sudo_args)
+        } else {
+            duct::cmd(program, args)
+        };
+
         // Build command - inherits parent env by default
-        let mut cmd_builder = duct::cmd(&interpreter, &cmd_args)
-            .dir(working_dir)
-            .stderr_to_stdout();
+        let mut expression = expression.dir(working_dir);
 
         // Add custom environment variables (guisu-specific + hook-specific)
         for (key, value) in env {
-            cmd_builder = cmd_builder.env(key, value);
+            expression = expression.env(key, value);
         }
 
-        let cmd_builder = cmd_builder;
+        expression
+    }
 
-        // Execute with or without timeout
-        if timeout > 0 {
-            let handle = cmd_builder.start().map_err(|e| {
-                Error::HookExecution(format!(
-                    "Failed to start script '{}': {}",
-                    script_path.display(),
-                    e
-                ))
-            })?;
+    /// Resolve the hook's working directory (default: source directory)
+    fn resolve_working_dir(&self, hook: &Hook) -> Result<PathBuf> {
+        let Some(working_dir) = &hook.working_dir else {
+            return Ok(self.source_dir.to_path_buf());
+        };
 
-            match handle.wait_timeout(Duration::from_secs(timeout)) {
-                Ok(Some(_output)) => Ok(()),
-                Ok(None) => Err(Error::HookExecution(format!(
-                    "Script '{}' timed out after {} seconds",
-                    script_path.display(),
-                    timeout
-                ))),
-                Err(e) => Err(Error::HookExecution(format!(
-                    "Script '{}' failed: {}",
-                    script_path.display(),
-                    e
-                ))),
-            }
+        let rendered = self.template_renderer.render(working_dir).map_err(|e| {
+            Error::HookExecution(format!(
+                "Hook '{}' failed to render working_dir: {}",
+                hook.name, e
+            ))
+        })?;
+        let path = PathBuf::from(self.expand_env_vars(rendered.trim()).as_ref());
+        let path = if path.is_absolute() {
+            path
         } else {
-            cmd_builder.run().map(|_| ()).map_err(|e| {
-                Error::HookExecution(format!("Script '{}' failed: {}", script_path.display(), e))
-            })
+            self.source_dir.join(path)
+        };
+
+        if !path.is_dir() {
+            return Err(Error::HookExecution(format!(
+                "Hook '{}' working directory does not exist: {}",
+                hook.name,
+                path.display()
+            )));
         }
+
+        Ok(path)
     }
 
     /// Parse shebang line from a script file
@@ -781,7 +1063,7 @@ where
     }
 
     /// Execute a template script by rendering it first
-    fn execute_template_script(&self, hook: &Hook) -> Result<()> {
+    fn execute_template_script(&self, hook: &Hook) -> Result<CapturedOutput> {
         let script_path = hook
             .script
             .as_ref()
@@ -817,7 +1099,7 @@ where
     }
 
     /// Execute a processed script via temporary file
-    fn execute_processed_script(&self, content: &str, hook: &Hook) -> Result<()> {
+    fn execute_processed_script(&self, content: &str, hook: &Hook) -> Result<CapturedOutput> {
         use tempfile::NamedTempFile;
 
         // Create temporary file
@@ -840,8 +1122,7 @@ where
                 .map_err(|e| Error::HookExecution(format!("Failed to set permissions: {e}")))?;
         }
 
-        // Working directory is always source_dir
-        let working_dir = self.source_dir.to_path_buf();
+        let working_dir = self.resolve_working_dir(hook)?;
 
         // Build environment variables (only clone if hook has custom env)
         let env = if hook.env.is_empty() {
@@ -863,7 +1144,7 @@ where
 
         // Execute script using shebang (same as regular scripts)
         // temp_file is automatically deleted when dropped
-        Self::execute_script(temp_path, &working_dir, &env, hook.timeout)
+        Self::execute_script(hook, temp_path, &working_dir, &env)
     }
 
     /// Expand environment variables in a string (simple ${VAR} expansion)
@@ -956,6 +1237,8 @@ where
     template_renderer: R,
     persistent_once: std::collections::HashSet<String>,
     persistent_onchange: std::collections::HashMap<String, [u8; 32]>,
+    output_handler: Option<OutputHandler<'a>>,
+    changed_files: Option<Vec<String>>,
 }
 
 impl<'a> HookRunnerBuilder<'a, NoOpRenderer> {
@@ -985,6 +1268,8 @@ impl<'a> HookRunnerBuilder<'a, NoOpRenderer> {
             template_renderer: NoOpRenderer,
             persistent_once: std::collections::HashSet::new(),
             persistent_onchange: std::collections::HashMap::new(),
+            output_handler: None,
+            changed_files: None,
         }
     }
 
@@ -1012,6 +1297,8 @@ impl<'a> HookRunnerBuilder<'a, NoOpRenderer> {
             template_renderer: renderer,
             persistent_once: self.persistent_once,
             persistent_onchange: self.persistent_onchange,
+            output_handler: self.output_handler,
+            changed_files: self.changed_files,
         }
     }
 }
@@ -1091,6 +1378,48 @@ where
         self
     }
 
+    /// Set a handler called with each hook run as soon as it finishes
+    ///
+    /// Hook output is captured rather than written to the terminal, so
+    /// parallel hooks don't interleave. Use this to display each hook's
+    /// output as one block. The handler may be called from several threads.
+    ///
+    /// # Examples
+    ///
+    /// ```ignore
+    /// let runner = HookRunner::builder(&collections, source_dir)
+    ///     .output_handler(|run| print!("{}", run.stdout))
+    ///     .build();
+    /// ```
+    #[must_use]
+    pub fn output_handler<F>(mut self, handler: F) -> Self
+    where
+        F: Fn(&HookRun) + Send + Sync + 'a,
+    {
+        self.output_handler = Some(Box::new(handler));
+        self
+    }
+
+    /// Set the target paths changed by the current apply
+    ///
+    /// Hooks with `paths` only run when one of these matches, and the list is
+    /// exposed to every hook as newline-separated `GUISU_CHANGED_FILES`.
+    ///
+    /// # Examples
+    ///
+    /// ```ignore
+    /// let runner = HookRunner::builder(&collections, source_dir)
+    ///     .changed_files(vec![".config/nvim/init.lua".to_string()])
+    ///     .build();
+    /// ```
+    #[must_use]
+    pub fn changed_files(mut self, files: Vec<String>) -> Self {
+        self.env_vars
+            .insert("GUISU_CHANGED_FILES".to_string(), files.join("\n"));
+        self.changed_files = Some(files);
+        self
+    }
+
     /// Build the `HookRunner`
     ///
     /// Consumes the builder and creates a configured `HookRunner`.
@@ -1120,6 +1449,9 @@ where
             onchange_rendered: std::sync::Arc::new(std::sync::Mutex::new(
                 std::collections::HashMap::new(),
             )),
+            runs: std::sync::Arc::new(std::sync::Mutex::new(Vec::new())),
+            output_handler: self.output_handler,
+            changed_files: self.changed_files,
         }
     }
 }
@@ -1287,6 +1619,12 @@ mod tests {
             failfast: true,
             mode,
             timeout: 0,
+            working_dir: None,
+            shell: None,
+            user: None,
+            paths: Vec::new(),
+            retries: 0,
+            retry_delay: 1,
         }
     }
 
@@ -1919,4 +2257,227 @@ mod tests {
         // Should have env vars set
         assert!(runner.env_vars.get("GUISU_SOURCE").is_some());
     }
+
+    // ======================================================================
+    // Working Directory and Shell Tests
+    // ======================================================================
+
+    #[test]
+    fn test_resolve_working_dir_defaults_to_source() {
+        let temp = TempDir::new().unwrap();
+        let collections = HookCollections::default();
+        let runner = HookRunner::new(&collections, temp.path());
+
+        let hook = create_test_hook("test", HookMode::Always);
+        assert_eq!(runner.resolve_working_dir(&hook).unwrap(), temp.path());
+    }
+
+    #[test]
+    fn test_resolve_working_dir_renders_and_expands() {
+        let temp = TempDir::new().unwrap();
+        fs::create_dir_all(temp.path().join("sub/dir")).unwrap();
+        let collections = HookCollections::default();
+        let runner = HookRunnerBuilder::new(&collections, temp.path())
+            .env("SUBDIR", "dir")
+            .template_renderer(|input: &str| Ok(input.replace("{{ name }}", "sub")))
+            .build();
+
+        let mut hook = create_test_hook("test", HookMode::Always);
+        hook.working_dir = Some("{{ name }}/${SUBDIR}".to_string());
+
+        assert_eq!(
+            runner.resolve_working_dir(&hook).unwrap(),
+            temp.path().join("sub/dir")
+        );
+    }
+
+    #[test]
+    fn test_resolve_working_dir_missing_directory() {
+        let temp = TempDir::new().unwrap();
+        let collections = HookCollections::default();
+        let runner = HookRunner::new(&collections, temp.path());
+
+        let mut hook = create_test_hook("test", HookMode::Always);
+        hook.working_dir = Some("missing".to_string());
+
+        let err = runner.resolve_working_dir(&hook).unwrap_err();
+        assert!(err.to_string().contains("does not exist"));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_execute_hook_with_shell_and_working_dir() {
+        let temp = TempDir::new().unwrap();
+        let work_dir = temp.path().join("work");
+        fs::create_dir_all(&work_dir).unwrap();
+        let collections = HookCollections::default();
+        let runner = HookRunner::new(&collections, temp.path());
+
+        let mut hook = create_test_hook("test", HookMode::Always);
+        hook.cmd = Some("echo hello | tr a-z A-Z > out.txt".to_string());
+        hook.shell = Some("sh -e".to_string());
+        hook.working_dir = Some(work_dir.display().to_string());
+
+        runner.execute_hook(&hook).unwrap();
+
+        let output = fs::read_to_string(work_dir.join("out.txt")).unwrap();
+        assert_eq!(output.trim(), "HELLO");
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_run_stage_captures_output_of_failed_hook() {
+        let temp = TempDir::new().unwrap();
+        let mut hook = create_test_hook("noisy", HookMode::Always);
+        hook.cmd = Some("echo out; echo err >&2; exit 3".to_string());
+        hook.shell = Some("sh".to_string());
+        hook.failfast = false;
+        let collections = HookCollections {
+            pre: vec![hook],
+            post: vec![],
+        };
+        let handled = std::sync::Mutex::new(Vec::new());
+        let runner = HookRunner::builder(&collections, temp.path())
+            .output_handler(|run| handled.lock().unwrap().push(run.name.clone()))
+            .build();
+
+        runner.run_stage(HookStage::Pre).unwrap();
+
+        let runs = runner.get_runs();
+        assert_eq!(runs.len(), 1);
+        assert_eq!(runs[0].stage, "pre");
+        assert_eq!(runs[0].stdout, "out\n");
+        assert_eq!(runs[0].stderr, "err\n");
+        assert_eq!(runs[0].exit_code, Some(3));
+        assert!(!runs[0].succeeded());
+
+        drop(runner);
+        assert_eq!(handled.into_inner().unwrap(), ["noisy"]);
+    }
+
+    #[test]
+    fn test_plan_stage_reports_skip_reasons_in_order() {
+        let temp = TempDir::new().unwrap();
+        let mut late = create_test_hook("late", HookMode::Always);
+        late.order = 20;
+        let mut once = create_test_hook("once", HookMode::Once);
+        once.order = 10;
+        let mut elsewhere = create_test_hook("elsewhere", HookMode::Always);
+        elsewhere.platforms = vec!["unknown-os".to_string()];
+        let collections = HookCollections {
+            pre: vec![late, once, elsewhere],
+            post: vec![],
+        };
+        let runner = HookRunner::builder(&collections, temp.path())
+            .persistent_state(
+                std::collections::HashSet::from(["once".to_string()]),
+                std::collections::HashMap::new(),
+            )
+            .build();
+
+        let planned = runner.plan_stage(HookStage::Pre);
+
+        let names: Vec<&str> = planned.iter().map(|p| p.hook.name.as_str()).collect();
+        assert_eq!(names, ["once", "late"]);
+        assert!(planned[0].skip_reason.is_some());
+        assert!(planned[1].skip_reason.is_none());
+        assert_eq!(runner.get_runs(), Vec::<HookRun>::new());
+    }
+
+    #[test]
+    fn test_plan_stage_onchange_content() {
+        let temp = TempDir::new().unwrap();
+        let collections = HookCollections {
+            pre: vec![create_test_hook("watch", HookMode::OnChange)],
+            post: vec![],
+        };
+        let runner = HookRunner::new(&collections, temp.path());
+
+        let planned = runner.plan_stage(HookStage::Pre);
+
+        assert_eq!(planned[0].onchange_content.as_deref(), Some("echo test"));
+        assert!(planned[0].skip_reason.is_none());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_run_stage_scopes_hooks_to_changed_files() {
+        let temp = TempDir::new().unwrap();
+        let mut nvim = create_test_hook("nvim", HookMode::Always);
+        nvim.cmd = Some("echo \"$GUISU_CHANGED_FILES\"".to_string());
+        nvim.shell = Some("sh".to_string());
+        nvim.paths = vec![".config/nvim/**".to_string()];
+        let mut zsh = nvim.clone();
+        zsh.name = "zsh".to_string();
+        zsh.paths = vec![".zshrc".to_string()];
+        let collections = HookCollections {
+            pre: vec![],
+            post: vec![nvim, zsh],
+        };
+        let changed = vec![
+            ".config/nvim/init.lua".to_string(),
+            ".gitconfig".to_string(),
+        ];
+        let runner = HookRunner::builder(&collections, temp.path())
+            .changed_files(changed)
+            .build();
+
+        let planned = runner.plan_stage(HookStage::Post);
+        assert!(planned[0].skip_reason.is_none());
+        assert_eq!(
+            planned[1].skip_reason.as_deref(),
+            Some("no changed path matches")
+        );
+
+        runner.run_stage(HookStage::Post).unwrap();
+
+        let runs = runner.get_runs();
+        assert_eq!(runs.len(), 1);
+        assert_eq!(runs[0].name, "nvim");
+        assert_eq!(runs[0].stdout, ".config/nvim/init.lua\n.gitconfig\n");
+    }
+
+    #[test]
+    fn test_scoped_hooks_run_without_change_set() {
+        let temp = TempDir::new().unwrap();
+        let mut hook = create_test_hook("nvim", HookMode::Always);
+        hook.paths = vec![".config/nvim/**".to_string()];
+        let collections = HookCollections {
+            pre: vec![],
+            post: vec![hook],
+        };
+        let runner = HookRunner::new(&collections, temp.path());
+
+        let planned = runner.plan_stage(HookStage::Post);
+        assert!(planned[0].skip_reason.is_none());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_run_stage_retries_failed_hook() {
+        let temp = TempDir::new().unwrap();
+        let mut hook = create_test_hook("flaky", HookMode::Always);
+        hook.cmd = Some("test -e marker || { touch marker; exit 1; }".to_string());
+        hook.shell = Some("sh".to_string());
+        hook.retry_delay = 0;
+        let collections = HookCollections {
+            pre: vec![],
+            post: vec![hook.clone()],
+        };
+        let runner = HookRunner::new(&collections, temp.path());
+        assert!(runner.run_stage(HookStage::Post).is_err());
+
+        std::fs::remove_file(temp.path().join("marker")).unwrap();
+        hook.retries = 1;
+        let collections = HookCollections {
+            pre: vec![],
+            post: vec![hook],
+        };
+        let runner = HookRunner::new(&collections, temp.path());
+        runner.run_stage(HookStage::Post).unwrap();
+
+        let runs = runner.get_runs();
+        assert_eq!(runs.len(), 1);
+        assert_eq!(runs[0].exit_code, Some(0));
+    }
 }
@@ -50,6 +50,14 @@ impl HookLoader {
             collections.pre = self
                 .load_hooks_from_dir(&pre_dir)
                 .map_err(|e| Error::HookConfig(format!("Failed to load pre hooks: {e}")))?;
+
+            // Changes are only known once entries have been applied
+            if let Some(hook) = collections.pre.iter().find(|h| !h.paths.is_empty()) {
+                return Err(Error::HookConfig(format!(
+                    "Pre hook '{}' sets 'paths', which is only supported for post hooks",
+                    hook.name
+                )));
+            }
         }
 
         // Load post hooks
@@ -150,6 +158,12 @@ impl HookLoader {
                         failfast: true,
                         mode: HookMode::default(),
                         timeout: 0, // No timeout by default
+                        working_dir: None,
+                        shell: None,
+                        user: None,
+                        paths: Vec::new(),
+                        retries: 0,
+                        retry_delay: 1,
                     };
                     return Ok(vec![hook]);
                 }
@@ -385,6 +399,24 @@ cmd = "echo test"
         assert_eq!(result.pre[0].cmd, Some("echo test".to_string()));
     }
 
+    #[test]
+    fn test_load_rejects_paths_on_pre_hooks() {
+        let temp = TempDir::new().unwrap();
+        let hooks_dir = create_hooks_dir_structure(temp.path());
+        let pre_dir = hooks_dir.join("pre");
+        fs::create_dir_all(&pre_dir).unwrap();
+
+        let toml_content = r#"
+name = "scoped"
+cmd = "echo test"
+paths = [".zshrc"]
+"#;
+        fs::write(pre_dir.join("hook.toml"), toml_content).unwrap();
+
+        let err = HookLoader::new(temp.path()).load().unwrap_err();
+        assert!(err.to_string().contains("only supported for post hooks"));
+    }
+
     #[test]
     fn test_load_toml_hooks_in_order() {
         let temp = TempDir::new().unwrap();
@@ -15,15 +15,20 @@
 //! - `config`: Hook configuration structures (Hook, `HookCollections`, etc.)
 //! - `loader`: Hook discovery and loading from filesystem
 //! - `executor`: Hook execution engine with parallel support
+//! - `scripts`: Script entries from the source tree (`run_*` files) as hooks
 //! - `state`: Hook configuration state tracking (separate from execution state)
 
 pub mod config;
 pub mod executor;
 pub mod loader;
+pub mod scripts;
 pub mod state;
 
 // Re-export main types for convenience
 pub use config::{Hook, HookCollections, HookMode, HookStage};
-pub use executor::{HookRunner, HookRunnerBuilder, NoOpRenderer, TemplateRenderer};
+pub use executor::{
+    HookRun, HookRunner, HookRunnerBuilder, NoOpRenderer, PlannedHook, TemplateRenderer,
+};
 pub use loader::HookLoader;
+pub use scripts::script_hooks;
 pub use state::HookConfigState;
@@ -0,0 +1,151 @@
+//! Script entries from the source tree
+//!
+//! Files named `run_[once_|onchange_][before_|after_]<name>` in the source
+//! directory are scripts rather than dotfiles. They are turned into hooks so
+//! the [`HookRunner`](super::HookRunner) executes them and tracks their state
+//! alongside configured hooks:
+//!
+//! - `before_` scripts run in the pre stage, all others in the post stage
+//! - `once_` scripts run once, tracked by name
+//! - `onchange_` scripts run when their (rendered) content changes
+//!
+//! Scripts run one at a time, ordered by source path.
+
+use super::config::{Hook, HookCollections, HookMode};
+use crate::entry::SourceEntry;
+use crate::state::SourceState;
+use guisu_core::{Error, Result};
+use indexmap::IndexMap;
+use std::fs;
+
+/// Prefix of hook names for script entries, keeping their state apart from hooks
+pub const SCRIPT_HOOK_PREFIX: &str = "script:";
+
+/// Build hook collections from the script entries of a source state
+///
+/// # Errors
+///
+/// Returns an error if a script is encrypted or cannot be read
+pub fn script_hooks(source: &SourceState) -> Result<HookCollections> {
+    let mut collections = HookCollections::default();
+
+    for (index, entry) in source.scripts().enumerate() {
+        let SourceEntry::Script {
+            source_path,
+            target_path,
+            attributes,
+        } = entry
+        else {
+            continue;
+        };
+
+        if attributes.is_encrypted() {
+            return Err(Error::HookConfig(format!(
+                "Encrypted scripts are not supported: {source_path}"
+            )));
+        }
+
+        let script_path = source.source_file_path(source_path);
+        let content = fs::read_to_string(script_path.as_path())
+            .map_err(|e| Error::HookConfig(format!("Failed to read script {script_path}: {e}")))?;
+
+        let mode = if attributes.is_once() {
+            HookMode::Once
+        } else if attributes.is_onchange() {
+            HookMode::OnChange
+        } else {
+            HookMode::Always
+        };
+
+        let hook = Hook {
+            name: format!("{SCRIPT_HOOK_PREFIX}{target_path}"),
+            order: i32::try_from(index).unwrap_or(i32::MAX),
+            platforms: Vec::new(),
+            cmd: None,
+            script: Some(script_path.to_string()),
+            script_content: Some(content),
+            env: IndexMap::new(),
+            failfast: true,
+            mode,
+            timeout: 0,
+            working_dir: None,
+            shell: None,
+            user: None,
+            paths: Vec::new(),
+            retries: 0,
+            retry_delay: 1,
+        };
+
+        if attributes.is_before() {
+            collections.pre.push(hook);
+        } else {
+            collections.post.push(hook);
+        }
+    }
+
+    Ok(collections)
+}
+
+#[cfg(test)]
+mod tests {
+    #![allow(clippy::unwrap_used, clippy::panic)]
+    use super::*;
+    use guisu_core::path::AbsPath;
+    use tempfile::TempDir;
+
+    fn read_source(files: &[&str]) -> (TempDir, SourceState) {
+        let temp = TempDir::new().unwrap();
+        for file in files {
+            fs::write(temp.path().join(file), "#!/bin/sh\necho hi\n").unwrap();
+        }
+        let root = AbsPath::new(fs::canonicalize(temp.path()).unwrap()).unwrap();
+        let source = SourceState::read(root).unwrap();
+        (temp, source)
+    }
+
+    #[test]
+    fn test_scripts_are_not_entries() {
+        let (_temp, source) = read_source(&[".bashrc", "run_once_install.sh"]);
+
+        assert_eq!(source.len(), 1);
+        let scripts: Vec<_> = source.scripts().collect();
+        assert_eq!(scripts.len(), 1);
+        assert_eq!(scripts[0].target_path().to_string(), "install.sh");
+    }
+
+    #[test]
+    fn test_script_hooks_stages_and_modes() {
+        let (_temp, source) = read_source(&[
+            "run_after_b.sh",
+            "run_onchange_a.sh",
+            "run_once_before_setup.sh",
+        ]);
+
+        let collections = script_hooks(&source).unwrap();
+        assert_eq!(collections.pre.len(), 1);
+        assert_eq!(collections.pre[0].name, "script:setup.sh");
+        assert_eq!(collections.pre[0].mode, HookMode::Once);
+
+        let post: Vec<_> = collections
+            .post
+            .iter()
+            .map(|hook| (hook.name.as_str(), hook.mode))
+            .collect();
+        assert_eq!(
+            post,
+            vec![
+                ("script:b.sh", HookMode::Always),
+                ("script:a.sh", HookMode::OnChange)
+            ]
+        );
+        // Scripts run one at a time, in source path order
+        assert!(collections.post[0].order < collections.post[1].order);
+        assert!(collections.post[0].validate().is_ok());
+    }
+
+    #[test]
+    fn test_script_hooks_reject_encrypted() {
+        let (_temp, source) = read_source(&["run_secret.sh.age"]);
+        assert!(script_hooks(&source).is_err());
+    }
+}
@@ -6,20 +6,32 @@
 //!
 //! - **Attributes**: Parsing and encoding file attributes in filenames
 //! - **State Management**: Three-state architecture (source, target, destination)
+//! - **Source Readers**: Source state read from the filesystem, a git revision, or memory
 //! - **Entry Types**: Representations of files, directories, and symlinks
 //! - **Content Processing**: Trait-based processing with pluggable decryption and rendering
+//! - **Compression**: zstd compression of large binary source files
+//! - **Content Pool**: Identical rendered contents shared and keyed by hash
+//! - **Parallelism**: Bounded worker pools and per-directory write batches
 //! - **System Abstraction**: Filesystem operations abstracted for testing
 //! - **Hooks**: Hook system for custom commands and scripts
+//! - **Externals**: Files and archives downloaded from URLs into the target state
+//! - **Clock**: Injectable timestamps and run IDs for state records
 
 pub mod adapters;
 pub mod attr;
+pub mod clock;
+pub mod compress;
 pub mod content;
 pub mod database;
 pub mod entry;
+pub mod external;
 pub mod git;
 pub mod hash;
 pub mod hooks;
+pub mod parallel;
+pub mod pool;
 pub mod processor;
+pub mod source;
 pub mod state;
 pub mod system;
 pub mod validator;
@@ -32,4 +44,5 @@ pub use guisu_core::{Error, Result};
 
 // Re-export commonly used types
 pub use attr::FileAttributes;
+pub use clock::{RunStamp, StateClock};
 pub use entry::{SourceEntry, TargetEntry};
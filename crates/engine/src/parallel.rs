@@ -0,0 +1,117 @@
+//! Bounded worker pools for the apply pipeline
+//!
+//! Reading the source state, rendering the target state and writing the
+//! destination all run on rayon. By default they share rayon's global pool,
+//! with one worker per CPU. A [`WorkerPool`] bounds every stage run inside
+//! [`WorkerPool::install`] to a fixed number of threads, and
+//! [`batch_by_parent`] groups writes so each destination directory is filled
+//! by a single worker instead of several workers contending for it.
+
+use crate::{Error, Result};
+use indexmap::IndexMap;
+use std::num::NonZeroUsize;
+use std::path::Path;
+
+/// Thread pool that parallel stages run on
+#[derive(Debug)]
+pub struct WorkerPool {
+    /// Dedicated pool, `None` to use rayon's global pool
+    pool: Option<rayon::ThreadPool>,
+}
+
+impl WorkerPool {
+    /// Create a pool with `jobs` worker threads, or the global pool for `None`
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the worker threads cannot be spawned
+    pub fn new(jobs: Option<NonZeroUsize>) -> Result<Self> {
+        let pool = jobs
+            .map(|jobs| {
+                rayon::ThreadPoolBuilder::new()
+                    .num_threads(jobs.get())
+                    .thread_name(|i| format!("guisu-worker-{i}"))
+                    .build()
+                    .map_err(|e| Error::Message(format!("Failed to start {jobs} workers: {e}")))
+            })
+            .transpose()?;
+        Ok(Self { pool })
+    }
+
+    /// Run `f` with every rayon parallel iterator inside it bound to this pool
+    pub fn install<R: Send>(&self, f: impl FnOnce() -> R + Send) -> R {
+        match &self.pool {
+            Some(pool) => pool.install(f),
+            None => f(),
+        }
+    }
+
+    /// Number of worker threads
+    #[must_use]
+    pub fn threads(&self) -> usize {
+        self.pool.as_ref().map_or_else(
+            rayon::current_num_threads,
+            rayon::ThreadPool::current_num_threads,
+        )
+    }
+}
+
+/// Group items by the parent directory of their path
+///
+/// Batches are returned in the order their directory first appears, and items
+/// keep their relative order within a batch.
+pub fn batch_by_parent<T>(
+    items: impl IntoIterator<Item = T>,
+    path: impl Fn(&T) -> &Path,
+) -> Vec<Vec<T>> {
+    let mut batches: IndexMap<std::path::PathBuf, Vec<T>> = IndexMap::new();
+    for item in items {
+        let parent = path(&item).parent().unwrap_or(Path::new("")).to_path_buf();
+        batches.entry(parent).or_default().push(item);
+    }
+    batches.into_values().collect()
+}
+
+#[cfg(test)]
+mod tests {
+    #![allow(clippy::unwrap_used, clippy::panic)]
+    use super::*;
+    use rayon::prelude::*;
+
+    #[test]
+    fn test_worker_pool_bounds_threads() {
+        let pool = WorkerPool::new(NonZeroUsize::new(2)).unwrap();
+        assert_eq!(pool.threads(), 2);
+        assert_eq!(pool.install(rayon::current_num_threads), 2);
+
+        let sum: u32 = pool.install(|| (1..=100).into_par_iter().sum());
+        assert_eq!(sum, 5050);
+    }
+
+    #[test]
+    fn test_worker_pool_defaults_to_global_pool() {
+        let pool = WorkerPool::new(None).unwrap();
+        assert_eq!(pool.threads(), rayon::current_num_threads());
+    }
+
+    #[test]
+    fn test_batch_by_parent() {
+        let paths = [
+            ".config/nvim/init.lua",
+            ".bashrc",
+            ".config/nvim/lua.lua",
+            ".config/git/config",
+            ".zshrc",
+        ];
+        let batches = batch_by_parent(paths, |path| Path::new(path));
+
+        assert_eq!(
+            batches,
+            vec![
+                vec![".config/nvim/init.lua", ".config/nvim/lua.lua"],
+                vec![".bashrc", ".zshrc"],
+                vec![".config/git/config"],
+            ]
+        );
+    }
+}
@@ -0,0 +1,158 @@
+//! Content-addressed sharing of rendered file contents
+//!
+//! Repositories often render many entries to identical bytes (license headers,
+//! shared snippets, the same template with the same variables). Target state
+//! building interns every rendered output in a [`ContentPool`], so identical
+//! contents share one allocation, and later stages can key per-content work on
+//! the blake3 hash with a [`ContentMemo`] instead of repeating it per entry.
+
+use crate::hash::hash_content;
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex, PoisonError};
+
+/// Rendered file content shared between entries with identical bytes
+pub type SharedContent = Arc<[u8]>;
+
+/// Pool of rendered contents keyed by their blake3 hash
+#[derive(Debug, Default)]
+pub struct ContentPool {
+    contents: Mutex<HashMap<[u8; 32], SharedContent>>,
+}
+
+impl ContentPool {
+    /// Create an empty pool
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Hash `content` and return the pooled copy together with its hash
+    ///
+    /// The first caller with a given content stores it; later callers get a
+    /// handle to the same allocation.
+    pub fn intern(&self, content: Vec<u8>) -> (SharedContent, [u8; 32]) {
+        let hash = hash_content(&content);
+        let mut contents = self.contents.lock().unwrap_or_else(PoisonError::into_inner);
+        let shared = Arc::clone(
+            contents
+                .entry(hash)
+                .or_insert_with(|| SharedContent::from(content)),
+        );
+        (shared, hash)
+    }
+
+    /// Number of distinct contents in the pool
+    #[must_use]
+    pub fn len(&self) -> usize {
+        self.contents
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .len()
+    }
+
+    /// Whether the pool is empty
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+/// Results of work that depends only on file content, keyed by content hash
+///
+/// Errors are not remembered, so each entry that fails reports its own error.
+/// The computation runs outside the lock; concurrent callers with the same
+/// content may both compute it, and either result is kept.
+#[derive(Debug)]
+pub struct ContentMemo<T> {
+    results: Mutex<HashMap<[u8; 32], T>>,
+}
+
+impl<T> Default for ContentMemo<T> {
+    fn default() -> Self {
+        Self {
+            results: Mutex::new(HashMap::new()),
+        }
+    }
+}
+
+impl<T: Clone> ContentMemo<T> {
+    /// Create an empty memo
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Return the remembered result for `hash`, computing it with `f` on first use
+    ///
+    /// # Errors
+    ///
+    /// Returns the error from `f`; failed computations are not remembered
+    pub fn get_or_try_insert_with<E>(
+        &self,
+        hash: &[u8; 32],
+        f: impl FnOnce() -> Result<T, E>,
+    ) -> Result<T, E> {
+        if let Some(result) = self.lock().get(hash) {
+            return Ok(result.clone());
+        }
+
+        let result = f()?;
+        Ok(self.lock().entry(*hash).or_insert(result).clone())
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, HashMap<[u8; 32], T>> {
+        self.results.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    #![allow(clippy::unwrap_used, clippy::panic)]
+    use super::*;
+    use std::cell::Cell;
+
+    #[test]
+    fn test_intern_shares_identical_content() {
+        let pool = ContentPool::new();
+        let (first, first_hash) = pool.intern(b"MIT License\n".to_vec());
+        let (second, second_hash) = pool.intern(b"MIT License\n".to_vec());
+        let (other, other_hash) = pool.intern(b"Apache License\n".to_vec());
+
+        assert!(Arc::ptr_eq(&first, &second));
+        assert_eq!(first_hash, second_hash);
+        assert_eq!(first_hash, hash_content(b"MIT License\n"));
+        assert!(!Arc::ptr_eq(&first, &other));
+        assert_ne!(first_hash, other_hash);
+        assert_eq!(pool.len(), 2);
+    }
+
+    #[test]
+    fn test_memo_computes_once_per_hash() {
+        let memo = ContentMemo::new();
+        let calls = Cell::new(0);
+        let compute = || {
+            calls.set(calls.get() + 1);
+            Ok::<_, ()>(42)
+        };
+
+        assert_eq!(memo.get_or_try_insert_with(&[1; 32], compute), Ok(42));
+        assert_eq!(memo.get_or_try_insert_with(&[1; 32], compute), Ok(42));
+        assert_eq!(calls.get(), 1);
+
+        assert_eq!(memo.get_or_try_insert_with(&[2; 32], compute), Ok(42));
+        assert_eq!(calls.get(), 2);
+    }
+
+    #[test]
+    fn test_memo_does_not_remember_errors() {
+        let memo: ContentMemo<u32> = ContentMemo::new();
+        assert_eq!(
+            memo.get_or_try_insert_with(&[1; 32], || Err("boom")),
+            Err("boom")
+        );
+        assert_eq!(
+            memo.get_or_try_insert_with(&[1; 32], || Ok::<_, &str>(7)),
+            Ok(7)
+        );
+    }
+}
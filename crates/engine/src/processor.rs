@@ -3,16 +3,25 @@
 //! This module handles the processing pipeline for source file contents:
 //! 1. Read source file
 //! 2. Decrypt if encrypted (.age extension)
-//! 3. Render template if templated (.j2 extension)
-//! 4. Return processed content
+//! 3. Decompress if compressed (`compress_` prefix)
+//! 4. Render template if templated (.j2 extension)
+//! 5. Return processed content
 //!
 //! The order is important: for `.j2.age` files, we decrypt first, then render.
+//! Compression is applied before encryption, so it is undone after decryption.
+//! With a line ending policy, text content is converted last.
+//!
+//! Files at or above the stream threshold that are not templates can instead
+//! go through [`ContentProcessor::stream_file`], which runs the same pipeline
+//! a chunk at a time so large files are never held in memory.
 
 use crate::attr::FileAttributes;
 use crate::content::{Decryptor, TemplateRenderer};
+use guisu_config::Eol;
 use guisu_core::path::AbsPath;
 use guisu_core::{Error, Result};
-use std::fs;
+use std::fs::{self, File};
+use std::io::{self, BufReader, Read, Write};
 
 /// Content processor with pluggable decryption and rendering
 ///
@@ -28,6 +37,12 @@ where
 
     /// Renderer for processing templates
     renderer: R,
+
+    /// Line endings text content is converted to, if any
+    eol: Option<Eol>,
+
+    /// Size in bytes from which source files are streamed, if any
+    stream_threshold: Option<u64>,
 }
 
 impl<D, R> ContentProcessor<D, R>
@@ -57,6 +72,103 @@ where
         Self {
             decryptor,
             renderer,
+            eol: None,
+            stream_threshold: None,
+        }
+    }
+
+    /// Convert line endings of processed text content
+    ///
+    /// Content with NUL bytes is considered binary and left unchanged.
+    #[must_use]
+    pub fn with_eol(mut self, eol: Option<Eol>) -> Self {
+        self.eol = eol;
+        self
+    }
+
+    /// Stream source files of at least `threshold` bytes
+    ///
+    /// See [`ContentProcessor::should_stream`].
+    #[must_use]
+    pub fn with_stream_threshold(mut self, threshold: Option<u64>) -> Self {
+        self.stream_threshold = threshold;
+        self
+    }
+
+    /// Check if a source file of `size` bytes should be streamed
+    ///
+    /// Templates are rendered as a whole, so they are never streamed.
+    #[must_use]
+    pub fn should_stream(&self, attrs: &FileAttributes, size: u64) -> bool {
+        !attrs.is_template()
+            && self
+                .stream_threshold
+                .is_some_and(|threshold| size >= threshold)
+    }
+
+    /// Process a file into `output` a chunk at a time
+    ///
+    /// Decrypts and decompresses like [`ContentProcessor::process_file`]
+    /// without holding the content in memory. Templates are not rendered and
+    /// line endings are left unchanged.
+    ///
+    /// Returns the blake3 hash and size of the processed content.
+    ///
+    /// # Errors
+    ///
+    /// Returns error if:
+    /// - File cannot be read
+    /// - Decryption fails
+    /// - Decompression fails
+    /// - Writing to `output` fails
+    pub fn stream_file(
+        &self,
+        source_path: &AbsPath,
+        attrs: &FileAttributes,
+        output: &mut dyn Write,
+    ) -> Result<([u8; 32], u64)> {
+        let path = source_path.to_string();
+        let file = File::open(source_path.as_path()).map_err(|e| Error::FileRead {
+            path: source_path.as_path().to_path_buf(),
+            source: e,
+        })?;
+        let mut input = BufReader::new(file);
+        let mut hashed = HashingWriter::new(output);
+
+        if attrs.is_compressed() {
+            let decompression = |e| Error::Decompression {
+                path: path.clone(),
+                source: e,
+            };
+            let mut decoder =
+                zstd::stream::write::Decoder::new(&mut hashed).map_err(decompression)?;
+            self.copy_decrypted(&mut input, &mut decoder, *attrs, &path, decompression)?;
+            decoder.flush().map_err(decompression)?;
+        } else {
+            self.copy_decrypted(&mut input, &mut hashed, *attrs, &path, Error::Io)?;
+        }
+
+        Ok(hashed.finish())
+    }
+
+    /// Copy `input` to `output`, decrypting it if the file is encrypted
+    fn copy_decrypted(
+        &self,
+        input: &mut dyn Read,
+        output: &mut dyn Write,
+        attrs: FileAttributes,
+        path: &str,
+        copy_error: impl Fn(io::Error) -> Error,
+    ) -> Result<()> {
+        if attrs.is_encrypted() {
+            self.decryptor
+                .decrypt_stream(path, input, output)
+                .map_err(|e| Error::Decryption {
+                    path: path.to_string(),
+                    source: Box::new(e),
+                })
+        } else {
+            io::copy(input, output).map(|_| ()).map_err(copy_error)
         }
     }
 
@@ -77,6 +189,7 @@ where
     /// Returns error if:
     /// - File cannot be read
     /// - Decryption fails
+    /// - Decompression fails
     /// - Template rendering fails
     /// - Content is not valid UTF-8 (for templates)
     pub fn process_file(
@@ -93,13 +206,20 @@ where
         self.process_content(file_data, attrs, context, &source_path.to_string())
     }
 
+    /// Let the renderer prepare for rendering `templates`
+    ///
+    /// See [`TemplateRenderer::prefetch`].
+    pub fn prefetch(&self, templates: &[&str]) {
+        self.renderer.prefetch(templates);
+    }
+
     /// Process file content directly (without reading from disk)
     ///
     /// This is useful for testing or when content is already in memory.
     ///
     /// # Errors
     ///
-    /// Returns an error if processing fails (e.g., decryption failure, invalid UTF-8, template rendering error)
+    /// Returns an error if processing fails (e.g., decryption or decompression failure, invalid UTF-8, template rendering error)
     pub fn process_content(
         &self,
         mut data: Vec<u8>,
@@ -110,13 +230,20 @@ where
         if attrs.is_encrypted() {
             data = self
                 .decryptor
-                .decrypt(&data)
+                .decrypt_file(path_for_errors, &data)
                 .map_err(|e| Error::Decryption {
                     path: path_for_errors.to_string(),
                     source: Box::new(e),
                 })?;
         }
 
+        if attrs.is_compressed() {
+            data = crate::compress::decompress(&data).map_err(|e| Error::Decompression {
+                path: path_for_errors.to_string(),
+                source: e,
+            })?;
+        }
+
         if attrs.is_template() {
             let text = String::from_utf8(data).map_err(|e| Error::InvalidUtf8 {
                 path: path_for_errors.to_string(),
@@ -134,10 +261,72 @@ where
             data = rendered.into_bytes();
         }
 
+        if let Some(eol) = self.eol {
+            data = convert_line_endings(data, eol);
+        }
+
         Ok(data)
     }
 }
 
+/// Writer that hashes and counts what passes through it
+struct HashingWriter<'a> {
+    inner: &'a mut dyn Write,
+    hasher: blake3::Hasher,
+    written: u64,
+}
+
+impl<'a> HashingWriter<'a> {
+    fn new(inner: &'a mut dyn Write) -> Self {
+        Self {
+            inner,
+            hasher: blake3::Hasher::new(),
+            written: 0,
+        }
+    }
+
+    /// Hash and size of everything written
+    fn finish(self) -> ([u8; 32], u64) {
+        (*self.hasher.finalize().as_bytes(), self.written)
+    }
+}
+
+impl Write for HashingWriter<'_> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let written = self.inner.write(buf)?;
+        self.hasher.update(&buf[..written]);
+        self.written += written as u64;
+        Ok(written)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.inner.flush()
+    }
+}
+
+/// Convert all line endings of text content, leaving binary content unchanged
+fn convert_line_endings(data: Vec<u8>, eol: Eol) -> Vec<u8> {
+    if data.contains(&0) {
+        return data;
+    }
+    let crlf = eol.is_crlf();
+    if !crlf && !data.contains(&b'\r') {
+        return data;
+    }
+
+    let mut converted = Vec::with_capacity(data.len() + data.len() / 32);
+    for (i, &byte) in data.iter().enumerate() {
+        if byte == b'\r' && data.get(i + 1) == Some(&b'\n') {
+            continue;
+        }
+        if byte == b'\n' && crlf {
+            converted.push(b'\r');
+        }
+        converted.push(byte);
+    }
+    converted
+}
+
 // Type alias for no-op processor (useful for testing)
 use crate::content::{NoOpDecryptor, NoOpRenderer};
 
@@ -199,6 +388,18 @@ mod tests {
             }
         }
 
+        fn decrypt_stream(
+            &self,
+            _path: &str,
+            encrypted: &mut dyn Read,
+            output: &mut dyn Write,
+        ) -> std::result::Result<(), Self::Error> {
+            let mut content = Vec::new();
+            encrypted.read_to_end(&mut content)?;
+            output.write_all(&self.decrypt(&content)?)?;
+            Ok(())
+        }
+
         fn decrypt_inline(&self, _text: &str) -> std::result::Result<String, Self::Error> {
             if self.should_fail {
                 Err(Error::Message("Decryption failed".to_string()))
@@ -506,6 +707,60 @@ mod tests {
         assert_eq!(result, content);
     }
 
+    #[test]
+    fn test_process_compressed_file() {
+        let content = vec![0x00, 0x01, 0x02, 0x03, 0xFF, 0xFE];
+        let compressed = crate::compress::compress(&content).unwrap();
+        let processor = ContentProcessor::new(MockDecryptor::success(compressed), NoOpRenderer);
+        let attrs = FileAttributes::ENCRYPTED | FileAttributes::COMPRESSED;
+        let template_context = serde_json::json!({});
+
+        let result = processor
+            .process_content(
+                b"ciphertext".to_vec(),
+                &attrs,
+                &template_context,
+                "font.ttf",
+            )
+            .unwrap();
+        assert_eq!(result, content);
+
+        let error = NoOpProcessor::default()
+            .process_content(content, &FileAttributes::COMPRESSED, &template_context, "x")
+            .unwrap_err();
+        assert!(matches!(error, Error::Decompression { .. }));
+    }
+
+    #[test]
+    fn test_stream_file() {
+        let content: Vec<u8> = (0..300_000u32).map(|i| (i % 7) as u8).collect();
+        let compressed = crate::compress::compress(&content).unwrap();
+        let mut temp_file = NamedTempFile::new().unwrap();
+        temp_file.write_all(b"ciphertext").unwrap();
+        let abs_path = AbsPath::new(temp_file.path().to_path_buf()).unwrap();
+
+        let processor = ContentProcessor::new(MockDecryptor::success(compressed), NoOpRenderer)
+            .with_stream_threshold(Some(1024));
+        assert!(processor.should_stream(&FileAttributes::ENCRYPTED, 1024));
+        assert!(!processor.should_stream(&FileAttributes::ENCRYPTED, 1023));
+        assert!(!processor.should_stream(&FileAttributes::TEMPLATE, 1 << 30));
+        assert!(!NoOpProcessor::default().should_stream(&FileAttributes::new(), 1 << 30));
+
+        let mut output = Vec::new();
+        let attrs = FileAttributes::ENCRYPTED | FileAttributes::COMPRESSED;
+        let (hash, size) = processor
+            .stream_file(&abs_path, &attrs, &mut output)
+            .unwrap();
+        assert_eq!(output, content);
+        assert_eq!(size, content.len() as u64);
+        assert_eq!(hash, crate::hash::hash_content(&content));
+
+        let error = NoOpProcessor::default()
+            .stream_file(&abs_path, &FileAttributes::COMPRESSED, &mut io::sink())
+            .unwrap_err();
+        assert!(matches!(error, Error::Decompression { .. }));
+    }
+
     #[test]
     fn test_large_file_content() {
         let processor = NoOpProcessor::default();
@@ -563,4 +818,23 @@ mod tests {
 
         assert_eq!(result, rendered.into_bytes());
     }
+
+    #[test]
+    fn test_eol_conversion() {
+        let attrs = FileAttributes::new();
+        let context = serde_json::json!({});
+        let process = |eol, data: &[u8]| {
+            NoOpProcessor::default()
+                .with_eol(eol)
+                .process_content(data.to_vec(), &attrs, &context, "test")
+                .unwrap()
+        };
+
+        assert_eq!(process(Some(Eol::Crlf), b"a\nb\r\nc"), b"a\r\nb\r\nc");
+        assert_eq!(process(Some(Eol::Lf), b"a\r\nb\nc\r"), b"a\nb\nc\r");
+        assert_eq!(process(None, b"a\r\nb\n"), b"a\r\nb\n");
+
+        // Binary content is written as is
+        assert_eq!(process(Some(Eol::Crlf), b"\0a\nb"), b"\0a\nb");
+    }
 }
@@ -0,0 +1,294 @@
+//! Storage backends of the source state
+//!
+//! [`SourceState`](crate::state::SourceState) scans its entries and reads
+//! their contents through a [`SourceReader`], so the source can come from the
+//! filesystem ([`FsSourceReader`]), a git revision
+//! ([`GitTreeSource`](crate::git::GitTreeSource)), or an in-memory map
+//! ([`MemorySourceReader`], for tests).
+//!
+//! Paths are relative to the root of the source directory.
+
+use guisu_core::{Error, Result};
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use walkdir::WalkDir;
+
+/// Permission bits of files without an explicit mode
+const DEFAULT_FILE_MODE: u32 = 0o100_644;
+
+/// Kind of an entry found while scanning a source
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SourceNodeKind {
+    /// Regular file
+    File,
+    /// Directory
+    Directory,
+}
+
+/// Entry found while scanning a source
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SourceNode {
+    /// Path relative to the source root
+    pub path: PathBuf,
+    /// Whether the entry is a file or a directory
+    pub kind: SourceNodeKind,
+}
+
+/// Storage the source state is read from
+///
+/// Other kinds of entries, such as symlinks in the source directory, are not
+/// reported by [`SourceReader::walk`].
+pub trait SourceReader: Send + Sync + std::fmt::Debug {
+    /// List files and directories below the root, parents before children
+    ///
+    /// Entries for which `skip` returns true are left out, and so are the
+    /// contents of skipped directories.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the source cannot be listed
+    fn walk(&self, skip: &dyn Fn(&Path) -> bool) -> Result<Vec<SourceNode>>;
+
+    /// Unix mode of a file, `None` where the storage has no permissions
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the file does not exist
+    fn mode(&self, path: &Path) -> Result<Option<u32>>;
+
+    /// Read the content of a file
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the file does not exist or cannot be read
+    fn read(&self, path: &Path) -> Result<Vec<u8>>;
+
+    /// Path of a file on the local filesystem, `None` where the storage has
+    /// no files of its own
+    ///
+    /// Large files are only streamed from sources that have one.
+    fn local_path(&self, path: &Path) -> Option<PathBuf> {
+        let _ = path;
+        None
+    }
+}
+
+/// Source directory on the filesystem
+#[derive(Debug, Clone)]
+pub struct FsSourceReader {
+    root: PathBuf,
+}
+
+impl FsSourceReader {
+    /// Read the source directory at `root`
+    #[must_use]
+    pub fn new(root: impl Into<PathBuf>) -> Self {
+        Self { root: root.into() }
+    }
+}
+
+impl SourceReader for FsSourceReader {
+    fn walk(&self, skip: &dyn Fn(&Path) -> bool) -> Result<Vec<SourceNode>> {
+        let root = self.root.as_path();
+        Ok(WalkDir::new(root)
+            .follow_links(false)
+            .into_iter()
+            .filter_entry(|entry| {
+                entry
+                    .path()
+                    .strip_prefix(root)
+                    .is_ok_and(|rel| rel.as_os_str().is_empty() || !skip(rel))
+            })
+            .filter_map(std::result::Result::ok)
+            .filter_map(|entry| {
+                let kind = if entry.file_type().is_file() {
+                    SourceNodeKind::File
+                } else if entry.file_type().is_dir() {
+                    SourceNodeKind::Directory
+                } else {
+                    return None;
+                };
+                let path = entry.path().strip_prefix(root).ok()?;
+                // The root directory itself is not an entry
+                (!path.as_os_str().is_empty()).then(|| SourceNode {
+                    path: path.to_path_buf(),
+                    kind,
+                })
+            })
+            .collect())
+    }
+
+    fn mode(&self, path: &Path) -> Result<Option<u32>> {
+        let full_path = self.root.join(path);
+        let metadata = std::fs::metadata(&full_path).map_err(|e| Error::FileRead {
+            path: full_path,
+            source: e,
+        })?;
+
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            Ok(Some(metadata.permissions().mode()))
+        }
+
+        #[cfg(not(unix))]
+        {
+            let _ = metadata;
+            Ok(None)
+        }
+    }
+
+    fn read(&self, path: &Path) -> Result<Vec<u8>> {
+        let full_path = self.root.join(path);
+        std::fs::read(&full_path).map_err(|e| Error::FileRead {
+            path: full_path,
+            source: e,
+        })
+    }
+
+    fn local_path(&self, path: &Path) -> Option<PathBuf> {
+        Some(self.root.join(path))
+    }
+}
+
+/// Source held in memory, for tests
+///
+/// Directories are implied by the paths of the files.
+#[derive(Debug, Clone, Default)]
+pub struct MemorySourceReader {
+    files: BTreeMap<PathBuf, (Vec<u8>, u32)>,
+}
+
+impl MemorySourceReader {
+    /// Create an empty source
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add a file with mode 0644
+    #[must_use]
+    pub fn with_file(self, path: impl Into<PathBuf>, content: impl Into<Vec<u8>>) -> Self {
+        self.with_file_mode(path, content, DEFAULT_FILE_MODE)
+    }
+
+    /// Add a file with the given mode
+    #[must_use]
+    pub fn with_file_mode(
+        mut self,
+        path: impl Into<PathBuf>,
+        content: impl Into<Vec<u8>>,
+        mode: u32,
+    ) -> Self {
+        self.files.insert(path.into(), (content.into(), mode));
+        self
+    }
+
+    /// Look up a file, failing like a missing file on disk
+    fn file(&self, path: &Path) -> Result<&(Vec<u8>, u32)> {
+        self.files.get(path).ok_or_else(|| Error::FileRead {
+            path: path.to_path_buf(),
+            source: std::io::Error::from(std::io::ErrorKind::NotFound),
+        })
+    }
+}
+
+impl SourceReader for MemorySourceReader {
+    fn walk(&self, skip: &dyn Fn(&Path) -> bool) -> Result<Vec<SourceNode>> {
+        let mut nodes = Vec::new();
+        let mut seen_dirs = std::collections::HashSet::new();
+        let mut skipped_dirs = Vec::new();
+
+        for path in self.files.keys() {
+            if skipped_dirs.iter().any(|dir| path.starts_with(dir)) {
+                continue;
+            }
+            let mut visible = true;
+            for dir in path
+                .ancestors()
+                .skip(1)
+                .collect::<Vec<_>>()
+                .into_iter()
+                .rev()
+            {
+                if dir.as_os_str().is_empty() || seen_dirs.contains(dir) {
+                    continue;
+                }
+                if skip(dir) {
+                    skipped_dirs.push(dir.to_path_buf());
+                    visible = false;
+                    break;
+                }
+                seen_dirs.insert(dir.to_path_buf());
+                nodes.push(SourceNode {
+                    path: dir.to_path_buf(),
+                    kind: SourceNodeKind::Directory,
+                });
+            }
+            if visible && !skip(path) {
+                nodes.push(SourceNode {
+                    path: path.clone(),
+                    kind: SourceNodeKind::File,
+                });
+            }
+        }
+        Ok(nodes)
+    }
+
+    fn mode(&self, path: &Path) -> Result<Option<u32>> {
+        self.file(path).map(|(_, mode)| Some(*mode))
+    }
+
+    fn read(&self, path: &Path) -> Result<Vec<u8>> {
+        self.file(path).map(|(content, _)| content.clone())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    #![allow(clippy::unwrap_used, clippy::panic)]
+    use super::*;
+    use tempfile::TempDir;
+
+    fn paths(nodes: &[SourceNode]) -> Vec<(String, SourceNodeKind)> {
+        let mut paths: Vec<_> = nodes
+            .iter()
+            .map(|node| (node.path.to_string_lossy().into_owned(), node.kind))
+            .collect();
+        paths.sort_by(|a, b| a.0.cmp(&b.0));
+        paths
+    }
+
+    #[test]
+    fn test_fs_and_memory_readers_agree() {
+        let temp = TempDir::new().unwrap();
+        std::fs::create_dir_all(temp.path().join("home/.config/skip")).unwrap();
+        std::fs::write(temp.path().join("home/.bashrc"), "export A=1\n").unwrap();
+        std::fs::write(temp.path().join("home/.config/skip/x"), "x").unwrap();
+        let fs_reader = FsSourceReader::new(temp.path());
+        let memory = MemorySourceReader::new()
+            .with_file("home/.bashrc", "export A=1\n")
+            .with_file("home/.config/skip/x", "x");
+
+        let skip = |path: &Path| path.ends_with("skip");
+        let expected = vec![
+            ("home".to_string(), SourceNodeKind::Directory),
+            ("home/.bashrc".to_string(), SourceNodeKind::File),
+            ("home/.config".to_string(), SourceNodeKind::Directory),
+        ];
+        assert_eq!(paths(&fs_reader.walk(&skip).unwrap()), expected);
+        assert_eq!(paths(&memory.walk(&skip).unwrap()), expected);
+
+        for reader in [&fs_reader as &dyn SourceReader, &memory] {
+            assert_eq!(
+                reader.read(Path::new("home/.bashrc")).unwrap(),
+                b"export A=1\n"
+            );
+            assert!(reader.read(Path::new("home/.missing")).is_err());
+        }
+        assert_eq!(
+            memory.mode(Path::new("home/.bashrc")).unwrap(),
+            Some(DEFAULT_FILE_MODE)
+        );
+    }
+}
@@ -2,11 +2,16 @@
 //!
 //! Provides state tracking for source, target, destination, and persistent states.
 
-use crate::attr::FileAttributes;
+use crate::attr::{FileAttributes, Ownership};
+use crate::clock::RunStamp;
 use crate::entry::{DestEntry, SourceEntry, TargetEntry};
 use crate::hash;
+use crate::hooks::executor::HookRun;
+use crate::pool::ContentPool;
 use crate::processor::ContentProcessor;
+use crate::source::{FsSourceReader, SourceNodeKind, SourceReader};
 use crate::system::System;
+use guisu_config::{ApplyMode, SymlinkTargets, Targets};
 use guisu_core::path::{AbsPath, RelPath, SourceRelPath};
 use guisu_core::{Error, Result};
 use redb::{Database, ReadableDatabase, ReadableTable, TableDefinition};
@@ -76,6 +81,37 @@ pub struct HookState {
     /// Snapshot of hooks from last execution (for diff display)
     #[serde(default)]
     pub last_collections: Option<crate::hooks::config::HookCollections>,
+    /// ID of the run that last executed hooks
+    #[serde(default)]
+    pub last_run_id: Option<String>,
+}
+
+/// `HookState` layout before `last_run_id` was added
+///
+/// Decoded as a fallback so existing databases keep their once/onchange history.
+#[derive(bincode::Decode)]
+struct LegacyHookState {
+    #[bincode(with_serde)]
+    last_executed: Option<std::time::SystemTime>,
+    content_hash: Option<[u8; 32]>,
+    once_executed: std::collections::HashSet<String>,
+    onchange_hashes: std::collections::HashMap<String, [u8; 32]>,
+    onchange_rendered: std::collections::HashMap<String, String>,
+    last_collections: Option<crate::hooks::config::HookCollections>,
+}
+
+impl From<LegacyHookState> for HookState {
+    fn from(legacy: LegacyHookState) -> Self {
+        Self {
+            last_executed: legacy.last_executed,
+            content_hash: legacy.content_hash,
+            once_executed: legacy.once_executed,
+            onchange_hashes: legacy.onchange_hashes,
+            onchange_rendered: legacy.onchange_rendered,
+            last_collections: legacy.last_collections,
+            last_run_id: None,
+        }
+    }
 }
 
 impl HookState {
@@ -89,6 +125,7 @@ impl HookState {
             onchange_hashes: std::collections::HashMap::new(),
             onchange_rendered: std::collections::HashMap::new(),
             last_collections: None,
+            last_run_id: None,
         }
     }
 
@@ -128,16 +165,16 @@ impl HookState {
 
     /// Update the state from a hooks directory
     ///
-    /// This computes a hash of all files in the hooks directory and updates
-    /// the `last_executed` timestamp.
-    /// Optionally saves the current hook collections for diff display.
+    /// This computes a hash of all files in the hooks directory and records
+    /// the run's timestamp and ID.
     ///
     /// # Errors
     ///
     /// Returns an error if the directory hash cannot be computed (e.g., I/O error, permission denied)
-    pub fn update(&mut self, hooks_dir: &Path) -> Result<()> {
+    pub fn update(&mut self, hooks_dir: &Path, stamp: &RunStamp) -> Result<()> {
         self.content_hash = Some(Self::compute_directory_hash(hooks_dir)?);
-        self.last_executed = Some(std::time::SystemTime::now());
+        self.last_executed = Some(stamp.time());
+        self.last_run_id = Some(stamp.run_id.clone());
         Ok(())
     }
 
@@ -150,9 +187,9 @@ impl HookState {
         &mut self,
         hooks_dir: &Path,
         collections: crate::hooks::config::HookCollections,
+        stamp: &RunStamp,
     ) -> Result<()> {
-        self.content_hash = Some(Self::compute_directory_hash(hooks_dir)?);
-        self.last_executed = Some(std::time::SystemTime::now());
+        self.update(hooks_dir, stamp)?;
         self.last_collections = Some(collections);
         Ok(())
     }
@@ -254,11 +291,24 @@ impl HookState {
     }
 
     /// Deserialize from bytes using bincode
+    ///
+    /// Falls back to the layout without `last_run_id` for databases written by
+    /// older versions.
     #[must_use]
     pub fn from_bytes(bytes: &[u8]) -> Option<Self> {
-        bincode::decode_from_slice(bytes, bincode::config::standard())
-            .ok()
-            .map(|(state, _len)| state)
+        decode_exact::<Self>(bytes)
+            .or_else(|| decode_exact::<LegacyHookState>(bytes).map(Into::into))
+    }
+}
+
+/// Decode a value with bincode, rejecting input with trailing bytes
+///
+/// Trailing bytes mean the input was written with a different layout, which
+/// lets `from_bytes` implementations try a legacy layout instead.
+fn decode_exact<T: bincode::Decode<()>>(bytes: &[u8]) -> Option<T> {
+    match bincode::decode_from_slice(bytes, bincode::config::standard()) {
+        Ok((value, len)) if len == bytes.len() => Some(value),
+        _ => None,
     }
 }
 
@@ -289,8 +339,6 @@ impl<'a, T: PersistentState> HookStatePersistence<'a, T> {
     ///
     /// Returns an error if the state cannot be loaded from the database (e.g., database error)
     pub fn load(&self) -> Result<HookState> {
-        const HOOK_STATE_KEY: &[u8] = b"hooks";
-
         match self.db.get(HOOK_STATE_BUCKET, HOOK_STATE_KEY)? {
             Some(bytes) => {
                 // Try to deserialize, but if it fails (e.g., schema changed), return new state
@@ -310,8 +358,6 @@ impl<'a, T: PersistentState> HookStatePersistence<'a, T> {
     ///
     /// Returns an error if the state cannot be serialized or saved (e.g., serialization error, database error)
     pub fn save(&self, state: &HookState) -> Result<()> {
-        const HOOK_STATE_KEY: &[u8] = b"hooks";
-
         let bytes = state.to_bytes()?;
         self.db.set(HOOK_STATE_BUCKET, HOOK_STATE_KEY, &bytes)?;
         Ok(())
@@ -324,6 +370,9 @@ pub struct DestinationState {
     /// Root directory (typically home directory)
     root: AbsPath,
 
+    /// Roots of subtrees that live outside `root`
+    targets: Targets,
+
     /// Cached entries
     cache: HashMap<RelPath, DestEntry>,
 }
@@ -334,10 +383,18 @@ impl DestinationState {
     pub fn new(root: AbsPath) -> Self {
         Self {
             root,
+            targets: Targets::default(),
             cache: HashMap::new(),
         }
     }
 
+    /// Read subtrees of `.guisu/targets.toml` from their own roots
+    #[must_use]
+    pub fn with_targets(mut self, targets: Targets) -> Self {
+        self.targets = targets;
+        self
+    }
+
     /// Get the root directory
     #[must_use]
     pub fn root(&self) -> &AbsPath {
@@ -357,7 +414,7 @@ impl DestinationState {
     /// Panics if the cache entry cannot be retrieved after insertion (should never happen)
     pub fn read<S: System>(&mut self, path: &RelPath, system: &S) -> Result<&DestEntry> {
         if !self.cache.contains_key(path) {
-            let abs_path = self.root.join(path);
+            let abs_path = self.targets.resolve(&self.root, path);
             let entry = Self::read_entry(path, &abs_path, system)?;
             self.cache.insert(path.clone(), entry);
         }
@@ -530,6 +587,51 @@ pub const ENTRY_STATE_BUCKET: &str = "entryState";
 pub const HOOK_STATE_BUCKET: &str = "hookState";
 /// Database bucket name for config metadata (tracks rendered config and template hash)
 pub const CONFIG_METADATA_BUCKET: &str = "configMetadata";
+/// Database bucket name for identity hints (maps encrypted source files to the identity that decrypts them)
+pub const IDENTITY_HINT_BUCKET: &str = "identityHint";
+/// Database bucket name for conflict snapshots (destination content overwritten by apply)
+pub const CONFLICT_SNAPSHOT_BUCKET: &str = "conflictSnapshot";
+/// Database bucket name for drift events (destination changes made outside guisu)
+pub const DRIFT_EVENT_BUCKET: &str = "driftEvent";
+/// Database bucket name for external downloads (cached content of `.guisu/externals.toml` URLs)
+pub const EXTERNAL_CACHE_BUCKET: &str = "externalCache";
+/// Database bucket name for prompt answers (values given to `prompt*` functions in the config template)
+pub const PROMPT_ANSWER_BUCKET: &str = "promptAnswer";
+/// Database bucket name for hook logs (captured output and exit code of recent hook runs)
+pub const HOOK_LOG_BUCKET: &str = "hookLog";
+/// Bucket name for render records (input and output hashes of processed files)
+pub const RENDER_CACHE_BUCKET: &str = "renderCache";
+/// Bucket name for base content (last applied content of text files, the base of three-way merges)
+pub const BASE_CONTENT_BUCKET: &str = "baseContent";
+/// Bucket name for managed paths (every target written or checked by apply, with its kind)
+pub const MANAGED_PATH_BUCKET: &str = "managedPath";
+/// Bucket name for apply snapshots (destination files as they were before an apply)
+pub const APPLY_SNAPSHOT_BUCKET: &str = "applySnapshot";
+/// Bucket name for snapshot content (compressed file content of apply snapshots, keyed by hash)
+pub const SNAPSHOT_CONTENT_BUCKET: &str = "snapshotContent";
+/// Bucket name for database metadata (the schema version)
+pub const META_BUCKET: &str = "meta";
+
+/// Key of the hook state record in [`HOOK_STATE_BUCKET`]
+pub const HOOK_STATE_KEY: &[u8] = b"hooks";
+
+/// Every bucket of state records, [`META_BUCKET`] excluded
+pub const BUCKETS: &[&str] = &[
+    ENTRY_STATE_BUCKET,
+    HOOK_STATE_BUCKET,
+    CONFIG_METADATA_BUCKET,
+    IDENTITY_HINT_BUCKET,
+    CONFLICT_SNAPSHOT_BUCKET,
+    DRIFT_EVENT_BUCKET,
+    EXTERNAL_CACHE_BUCKET,
+    PROMPT_ANSWER_BUCKET,
+    HOOK_LOG_BUCKET,
+    RENDER_CACHE_BUCKET,
+    BASE_CONTENT_BUCKET,
+    MANAGED_PATH_BUCKET,
+    APPLY_SNAPSHOT_BUCKET,
+    SNAPSHOT_CONTENT_BUCKET,
+];
 
 /// Trait for persistent state storage
 pub trait PersistentState: Send + Sync {
@@ -634,13 +736,40 @@ impl RedbPersistentState {
         Ok(Self { db })
     }
 
+    /// Delete the given buckets in a single transaction
+    ///
+    /// Either every bucket is deleted or, if anything fails, none is.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the transaction fails (e.g., database error, write failure)
+    pub fn clear(&self, buckets: &[&str]) -> Result<()> {
+        let write_txn = self
+            .db
+            .begin_write()
+            .map_err(|e| crate::Error::State(format!("Failed to begin write transaction: {e}")))?;
+        for bucket in buckets {
+            write_txn
+                .delete_table(Self::table_def_with_storage(bucket))
+                .map_err(|e| crate::Error::State(format!("Failed to delete table: {e}")))?;
+        }
+        write_txn
+            .commit()
+            .map_err(|e| crate::Error::State(format!("Failed to commit transaction: {e}")))?;
+        Ok(())
+    }
+
     /// Create table definition for known bucket names
     ///
     /// # Panics
     ///
     /// Panics if called with an unknown bucket name. This is a programming error
     /// that should be caught during development. Only `ENTRY_STATE_BUCKET`,
-    /// `HOOK_STATE_BUCKET`, and `CONFIG_METADATA_BUCKET` are valid bucket names.
+    /// `HOOK_STATE_BUCKET`, `CONFIG_METADATA_BUCKET`, `IDENTITY_HINT_BUCKET`,
+    /// `CONFLICT_SNAPSHOT_BUCKET`, `DRIFT_EVENT_BUCKET`, `EXTERNAL_CACHE_BUCKET`,
+    /// `PROMPT_ANSWER_BUCKET`, `HOOK_LOG_BUCKET`, `RENDER_CACHE_BUCKET`,
+    /// `BASE_CONTENT_BUCKET`, `MANAGED_PATH_BUCKET`, `APPLY_SNAPSHOT_BUCKET`,
+    /// `SNAPSHOT_CONTENT_BUCKET`, and `META_BUCKET` are valid bucket names.
     #[inline]
     fn table_def_with_storage(
         bucket: &str,
@@ -649,9 +778,25 @@ impl RedbPersistentState {
             ENTRY_STATE_BUCKET => TableDefinition::new(ENTRY_STATE_BUCKET),
             HOOK_STATE_BUCKET => TableDefinition::new(HOOK_STATE_BUCKET),
             CONFIG_METADATA_BUCKET => TableDefinition::new(CONFIG_METADATA_BUCKET),
+            IDENTITY_HINT_BUCKET => TableDefinition::new(IDENTITY_HINT_BUCKET),
+            CONFLICT_SNAPSHOT_BUCKET => TableDefinition::new(CONFLICT_SNAPSHOT_BUCKET),
+            DRIFT_EVENT_BUCKET => TableDefinition::new(DRIFT_EVENT_BUCKET),
+            EXTERNAL_CACHE_BUCKET => TableDefinition::new(EXTERNAL_CACHE_BUCKET),
+            PROMPT_ANSWER_BUCKET => TableDefinition::new(PROMPT_ANSWER_BUCKET),
+            HOOK_LOG_BUCKET => TableDefinition::new(HOOK_LOG_BUCKET),
+            RENDER_CACHE_BUCKET => TableDefinition::new(RENDER_CACHE_BUCKET),
+            BASE_CONTENT_BUCKET => TableDefinition::new(BASE_CONTENT_BUCKET),
+            MANAGED_PATH_BUCKET => TableDefinition::new(MANAGED_PATH_BUCKET),
+            APPLY_SNAPSHOT_BUCKET => TableDefinition::new(APPLY_SNAPSHOT_BUCKET),
+            SNAPSHOT_CONTENT_BUCKET => TableDefinition::new(SNAPSHOT_CONTENT_BUCKET),
+            META_BUCKET => TableDefinition::new(META_BUCKET),
             _ => panic!(
-                "Unknown bucket name: '{bucket}'. Only ENTRY_STATE_BUCKET, \
-                 HOOK_STATE_BUCKET, and CONFIG_METADATA_BUCKET are valid. This is a programming error."
+                "Unknown bucket name: '{bucket}'. Only ENTRY_STATE_BUCKET, HOOK_STATE_BUCKET, \
+                 CONFIG_METADATA_BUCKET, IDENTITY_HINT_BUCKET, CONFLICT_SNAPSHOT_BUCKET, \
+                 DRIFT_EVENT_BUCKET, EXTERNAL_CACHE_BUCKET, PROMPT_ANSWER_BUCKET, \
+                 HOOK_LOG_BUCKET, RENDER_CACHE_BUCKET, BASE_CONTENT_BUCKET, MANAGED_PATH_BUCKET, \
+                 APPLY_SNAPSHOT_BUCKET, SNAPSHOT_CONTENT_BUCKET, and META_BUCKET are valid. \
+                 This is a programming error."
             ),
         }
     }
@@ -811,18 +956,41 @@ pub struct EntryState {
     pub content_hash: [u8; 32],
     /// File mode/permissions (Unix only)
     pub mode: Option<u32>,
+    /// Run that last applied this entry (`None` for entries written by older versions)
+    pub last_applied: Option<RunStamp>,
+}
+
+/// `EntryState` layout before `last_applied` was added
+#[derive(bincode::Decode)]
+struct LegacyEntryState {
+    content_hash: [u8; 32],
+    mode: Option<u32>,
 }
 
 impl EntryState {
     /// Create a new entry state from content and mode
     #[must_use]
     pub fn new(content: &[u8], mode: Option<u32>) -> Self {
+        Self::from_hash(hash_data(content), mode)
+    }
+
+    /// Create a new entry state from the hash of the content and mode
+    #[must_use]
+    pub fn from_hash(content_hash: [u8; 32], mode: Option<u32>) -> Self {
         Self {
-            content_hash: hash_data(content),
+            content_hash,
             mode,
+            last_applied: None,
         }
     }
 
+    /// Record the run that applied this entry
+    #[must_use]
+    pub fn with_stamp(mut self, stamp: &RunStamp) -> Self {
+        self.last_applied = Some(stamp.clone());
+        self
+    }
+
     /// Serialize to bytes using bincode
     ///
     /// # Errors
@@ -834,11 +1002,18 @@ impl EntryState {
     }
 
     /// Deserialize from bytes using bincode
+    ///
+    /// Falls back to the layout without `last_applied` for databases written by
+    /// older versions.
     #[must_use]
     pub fn from_bytes(bytes: &[u8]) -> Option<Self> {
-        bincode::decode_from_slice(bytes, bincode::config::standard())
-            .ok()
-            .map(|(state, _len)| state)
+        decode_exact::<Self>(bytes).or_else(|| {
+            decode_exact::<LegacyEntryState>(bytes).map(|legacy| Self {
+                content_hash: legacy.content_hash,
+                mode: legacy.mode,
+                last_applied: None,
+            })
+        })
     }
 }
 
@@ -927,6 +1102,346 @@ impl ConfigMetadata {
     }
 }
 
+/// Conflict snapshot - destination content discarded by an apply
+///
+/// Written just before apply overwrites a destination file that was modified
+/// locally, so the overwritten content can be recovered later.
+#[derive(Debug, Clone, PartialEq, Eq, bincode::Encode, bincode::Decode)]
+pub struct ConflictSnapshot {
+    /// Target path relative to the destination directory
+    pub path: String,
+    /// Destination content before it was overwritten
+    pub content: Vec<u8>,
+    /// Destination mode before it was overwritten (Unix only)
+    pub mode: Option<u32>,
+    /// Run that overwrote the destination
+    pub stamp: RunStamp,
+}
+
+impl ConflictSnapshot {
+    /// Length of a snapshot ID in hex characters
+    const ID_LEN: usize = 8;
+
+    /// Create a snapshot of destination content about to be overwritten
+    #[must_use]
+    pub fn new(
+        path: impl Into<String>,
+        content: Vec<u8>,
+        mode: Option<u32>,
+        stamp: &RunStamp,
+    ) -> Self {
+        Self {
+            path: path.into(),
+            content,
+            mode,
+            stamp: stamp.clone(),
+        }
+    }
+
+    /// Short ID used to refer to this snapshot
+    ///
+    /// Derived from the run ID and path, so it is stable and unique per
+    /// overwritten file per run.
+    #[must_use]
+    pub fn id(&self) -> String {
+        short_id(&self.stamp, &self.path, Self::ID_LEN)
+    }
+
+    /// Serialize to bytes using bincode
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if serialization fails (e.g., encoding error)
+    pub fn to_bytes(&self) -> Result<Vec<u8>> {
+        bincode::encode_to_vec(self, bincode::config::standard())
+            .map_err(|e| Error::State(format!("Failed to serialize ConflictSnapshot: {e}")))
+    }
+
+    /// Deserialize from bytes using bincode
+    #[must_use]
+    pub fn from_bytes(bytes: &[u8]) -> Option<Self> {
+        decode_exact(bytes)
+    }
+}
+
+/// Apply snapshot - destination files as they were before an apply
+///
+/// Taken before an apply writes anything when `[apply] snapshots` is set, so
+/// `guisu rollback` can put the destination back. File content is stored
+/// separately, compressed and keyed by its hash, so unchanged files shared
+/// by several snapshots are stored once.
+#[derive(Debug, Clone, PartialEq, Eq, bincode::Encode, bincode::Decode)]
+pub struct ApplySnapshot {
+    /// Files the apply was about to change
+    pub files: Vec<SnapshotFile>,
+    /// Run the snapshot was taken for
+    pub stamp: RunStamp,
+}
+
+/// A destination file recorded in an [`ApplySnapshot`]
+#[derive(Debug, Clone, PartialEq, Eq, bincode::Encode, bincode::Decode)]
+pub struct SnapshotFile {
+    /// Target path relative to the destination directory
+    pub path: String,
+    /// Hash of the saved content, `None` if the file did not exist
+    pub content_hash: Option<[u8; 32]>,
+    /// Permission bits of the file (Unix only)
+    pub mode: Option<u32>,
+}
+
+impl ApplySnapshot {
+    /// Length of a snapshot ID in hex characters
+    const ID_LEN: usize = 8;
+
+    /// Create an empty snapshot for the run `stamp`
+    #[must_use]
+    pub fn new(stamp: &RunStamp) -> Self {
+        Self {
+            files: Vec::new(),
+            stamp: stamp.clone(),
+        }
+    }
+
+    /// Short ID used to refer to this snapshot
+    ///
+    /// Derived from the run ID, so there is one snapshot per run.
+    #[must_use]
+    pub fn id(&self) -> String {
+        short_id(&self.stamp, "", Self::ID_LEN)
+    }
+
+    /// Serialize to bytes using bincode
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if serialization fails (e.g., encoding error)
+    pub fn to_bytes(&self) -> Result<Vec<u8>> {
+        bincode::encode_to_vec(self, bincode::config::standard())
+            .map_err(|e| Error::State(format!("Failed to serialize ApplySnapshot: {e}")))
+    }
+
+    /// Deserialize from bytes using bincode
+    #[must_use]
+    pub fn from_bytes(bytes: &[u8]) -> Option<Self> {
+        decode_exact(bytes)
+    }
+}
+
+/// Destination change made by something other than guisu
+///
+/// Recorded by `guisu drift watch` when a managed destination file changes
+/// without guisu having applied that content.
+#[derive(Debug, Clone, PartialEq, Eq, bincode::Encode, bincode::Decode)]
+pub struct DriftEvent {
+    /// Target path relative to the destination directory
+    pub path: String,
+    /// Unified diff from the previously seen content to the new content
+    pub diff: String,
+    /// Poll that noticed the change
+    pub stamp: RunStamp,
+}
+
+impl DriftEvent {
+    /// Length of an event ID in hex characters
+    const ID_LEN: usize = 8;
+
+    /// Create an event for a change noticed during the poll `stamp`
+    #[must_use]
+    pub fn new(path: impl Into<String>, diff: String, stamp: &RunStamp) -> Self {
+        Self {
+            path: path.into(),
+            diff,
+            stamp: stamp.clone(),
+        }
+    }
+
+    /// Short ID used to refer to this event, unique per path per poll
+    #[must_use]
+    pub fn id(&self) -> String {
+        short_id(&self.stamp, &self.path, Self::ID_LEN)
+    }
+
+    /// Serialize to bytes using bincode
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if serialization fails (e.g., encoding error)
+    pub fn to_bytes(&self) -> Result<Vec<u8>> {
+        bincode::encode_to_vec(self, bincode::config::standard())
+            .map_err(|e| Error::State(format!("Failed to serialize DriftEvent: {e}")))
+    }
+
+    /// Deserialize from bytes using bincode
+    #[must_use]
+    pub fn from_bytes(bytes: &[u8]) -> Option<Self> {
+        decode_exact(bytes)
+    }
+}
+
+/// Captured output of one hook run
+///
+/// Written after each hook run so `guisu hooks log` can show what a hook
+/// printed, including hooks that failed.
+#[derive(Debug, Clone, PartialEq, Eq, bincode::Encode, bincode::Decode)]
+pub struct HookRunLog {
+    /// The hook run with its output and exit code
+    pub run: HookRun,
+    /// Run that executed the hook
+    pub stamp: RunStamp,
+}
+
+impl HookRunLog {
+    /// Length of a log ID in hex characters
+    const ID_LEN: usize = 8;
+
+    /// Create a log entry for a hook that ran during `stamp`
+    #[must_use]
+    pub fn new(run: HookRun, stamp: &RunStamp) -> Self {
+        Self {
+            run,
+            stamp: stamp.clone(),
+        }
+    }
+
+    /// Short ID used to refer to this log, unique per hook and stage per run
+    #[must_use]
+    pub fn id(&self) -> String {
+        let key = format!("{}/{}", self.run.stage, self.run.name);
+        short_id(&self.stamp, &key, Self::ID_LEN)
+    }
+
+    /// Serialize to bytes using bincode
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if serialization fails (e.g., encoding error)
+    pub fn to_bytes(&self) -> Result<Vec<u8>> {
+        bincode::encode_to_vec(self, bincode::config::standard())
+            .map_err(|e| Error::State(format!("Failed to serialize HookRunLog: {e}")))
+    }
+
+    /// Deserialize from bytes using bincode
+    #[must_use]
+    pub fn from_bytes(bytes: &[u8]) -> Option<Self> {
+        decode_exact(bytes)
+    }
+}
+
+/// Result of processing a template or encrypted source file
+///
+/// Keyed by target path. Records that processing inputs hashing to
+/// `input_hash` produced content hashing to `content_hash`, and which files
+/// the template included on the way. Records written before includes and
+/// external lookups were tracked no longer decode, so those files are
+/// processed once more.
+#[derive(Debug, Clone, PartialEq, Eq, bincode::Encode, bincode::Decode)]
+pub struct RenderRecord {
+    /// blake3 hash of the source file and everything its processing depends on
+    pub input_hash: [u8; 32],
+    /// blake3 hash of the processed content
+    pub content_hash: [u8; 32],
+    /// Files read by `include()` and `includeTemplate()`, sorted by path
+    pub dependencies: Vec<RenderDependency>,
+    /// Whether processing looked up external values (password managers,
+    /// commands), so the content can change with the same inputs
+    pub volatile: bool,
+}
+
+/// File included while processing a template, with the hash of its content
+#[derive(Debug, Clone, PartialEq, Eq, bincode::Encode, bincode::Decode)]
+pub struct RenderDependency {
+    /// Absolute path of the included file
+    pub path: String,
+    /// blake3 hash of the content that was included
+    pub hash: [u8; 32],
+}
+
+impl RenderDependency {
+    /// Check if the file still has the content that was included
+    #[must_use]
+    pub fn is_current(&self) -> bool {
+        fs::read(&self.path).is_ok_and(|content| hash::hash_content(&content) == self.hash)
+    }
+}
+
+impl RenderRecord {
+    /// Serialize to bytes using bincode
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if serialization fails (e.g., encoding error)
+    pub fn to_bytes(&self) -> Result<Vec<u8>> {
+        bincode::encode_to_vec(self, bincode::config::standard())
+            .map_err(|e| Error::State(format!("Failed to serialize RenderRecord: {e}")))
+    }
+
+    /// Deserialize from bytes using bincode
+    #[must_use]
+    pub fn from_bytes(bytes: &[u8]) -> Option<Self> {
+        decode_exact(bytes)
+    }
+}
+
+/// Kind of a target recorded as managed by apply
+///
+/// Kept so that a target deleted from the source can be removed from the
+/// destination without knowing what the source entry was.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, bincode::Encode, bincode::Decode)]
+pub enum ManagedKind {
+    /// A regular file
+    File,
+    /// A directory
+    Directory,
+    /// A symbolic link
+    Symlink,
+}
+
+impl ManagedKind {
+    /// Kind of a target entry, or `None` for removal entries
+    #[must_use]
+    pub fn of(entry: &TargetEntry) -> Option<Self> {
+        match entry {
+            TargetEntry::File { .. } | TargetEntry::LargeFile { .. } => Some(Self::File),
+            TargetEntry::Directory { .. } => Some(Self::Directory),
+            TargetEntry::Symlink { .. } => Some(Self::Symlink),
+            TargetEntry::Remove { .. } => None,
+        }
+    }
+
+    /// Serialize to bytes using bincode
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if serialization fails (e.g., encoding error)
+    pub fn to_bytes(self) -> Result<Vec<u8>> {
+        bincode::encode_to_vec(self, bincode::config::standard())
+            .map_err(|e| Error::State(format!("Failed to serialize ManagedKind: {e}")))
+    }
+
+    /// Deserialize from bytes using bincode
+    #[must_use]
+    pub fn from_bytes(bytes: &[u8]) -> Option<Self> {
+        decode_exact(bytes)
+    }
+}
+
+/// Hex ID of `len` characters derived from a run and a path
+fn short_id(stamp: &RunStamp, path: &str, len: usize) -> String {
+    let mut key = Vec::with_capacity(stamp.run_id.len() + path.len() + 1);
+    key.extend_from_slice(stamp.run_id.as_bytes());
+    key.push(0);
+    key.extend_from_slice(path.as_bytes());
+
+    hash_data(&key)
+        .iter()
+        .take(len / 2)
+        .fold(String::with_capacity(len), |mut id, byte| {
+            use std::fmt::Write;
+            let _ = write!(id, "{byte:02x}");
+            id
+        })
+}
+
 /// Type aliases for mock state data structure
 /// Inner map: key-value pairs within a bucket
 type BucketData = HashMap<Vec<u8>, Vec<u8>>;
@@ -1040,8 +1555,35 @@ pub struct SourceState {
     /// Root directory of the source files
     root: AbsPath,
 
+    /// Storage the source files are read from
+    reader: Arc<dyn SourceReader>,
+
     /// Map of target paths to source entries
     entries: HashMap<RelPath, SourceEntry>,
+
+    /// Script entries, ordered by source path
+    scripts: Vec<SourceEntry>,
+
+    /// Owner and group of target paths with `owner_`/`group_` prefixes
+    ownership: HashMap<RelPath, Ownership>,
+
+    /// Target paths of directories with the `exact_` prefix
+    exact_dirs: HashSet<RelPath>,
+}
+
+/// Target path of a path in the source directory, without directory prefixes
+///
+/// Every directory component goes through
+/// [`FileAttributes::parse_directory_name`]; the last component is kept as is.
+fn target_dir_path(rel_path: &Path) -> Result<std::path::PathBuf> {
+    rel_path
+        .components()
+        .map(|component| {
+            let name = component.as_os_str().to_string_lossy();
+            let (_, target_name) = FileAttributes::parse_directory_name(&name)?;
+            Ok(target_name.to_string())
+        })
+        .collect()
 }
 
 impl SourceState {
@@ -1063,7 +1605,9 @@ impl SourceState {
 
     /// Read the source state from a directory with ignore matcher
     ///
-    /// This version allows filtering files using an `IgnoreMatcher`.
+    /// This version allows filtering files using an `IgnoreMatcher`. Source
+    /// (`src:`) patterns are checked against paths relative to `root` as they
+    /// appear on disk; target patterns against the target path each file maps to.
     ///
     /// # Arguments
     ///
@@ -1076,129 +1620,203 @@ impl SourceState {
     pub fn read_with_matcher(
         root: AbsPath,
         matcher: Option<&guisu_config::IgnoreMatcher>,
+    ) -> Result<Self> {
+        let reader = Arc::new(FsSourceReader::new(root.as_path()));
+        Self::read_from(root, reader, matcher)
+    }
+
+    /// Read the source state through a [`SourceReader`]
+    ///
+    /// `root` is where the source would be on disk. It is used for paths in
+    /// messages and by [`SourceState::source_file_path`]; contents are read
+    /// through `reader`.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the source cannot be listed or files cannot be processed (e.g., I/O error, invalid attributes, invalid path structure)
+    #[allow(clippy::too_many_lines)]
+    pub fn read_from(
+        root: AbsPath,
+        reader: Arc<dyn SourceReader>,
+        matcher: Option<&guisu_config::IgnoreMatcher>,
     ) -> Result<Self> {
         use rayon::prelude::*;
 
-        let root_path = root.as_path();
+        // First, list all paths (skipping subtrees excluded by conditions
+        // without descending into them)
+        let nodes = reader.walk(&|rel| matcher.is_some_and(|m| m.is_excluded(rel)))?;
 
-        // First, collect all file paths (WalkDir must be sequential)
-        let file_paths: Vec<std::path::PathBuf> = WalkDir::new(root_path)
-            .follow_links(false)
+        let mut exact_dir_paths = Vec::new();
+        let file_paths: Vec<std::path::PathBuf> = nodes
             .into_iter()
-            .filter_map(std::result::Result::ok)
-            .filter_map(|entry| {
-                let path = entry.path();
-
-                // Skip the root directory itself
-                if path == root_path {
+            .filter_map(|node| {
+                let rel_path = node.path;
+                let file_name = rel_path.file_name()?;
+
+                // Directories only matter for their exact_ prefix
+                if node.kind == SourceNodeKind::Directory {
+                    if file_name.to_string_lossy().starts_with("exact_")
+                        && matcher.is_none_or(|m| !m.is_source_ignored(&rel_path, Some(true)))
+                    {
+                        exact_dir_paths.push(rel_path);
+                    }
                     return None;
                 }
 
-                // Only process files, not directories
                 // Note: With rootEntry enforced (defaults to "home"), all dotfiles are in a
                 // subdirectory, so we don't need to skip .git, .guisu, etc.
-                if !entry.file_type().is_file() {
+
+                // Per-directory ignore files configure guisu; they are not dotfiles
+                if file_name == guisu_config::IGNORE_FILE_NAME {
                     return None;
                 }
 
-                // Apply ignore matcher if provided
-                if let Some(matcher) = matcher
-                    && let Ok(rel_path) = path.strip_prefix(root_path)
-                    && matcher.is_ignored(rel_path, None)
-                {
+                // Apply source ignore patterns if provided
+                if matcher.is_some_and(|m| m.is_source_ignored(&rel_path, Some(false))) {
                     return None;
                 }
 
-                Some(path.to_path_buf())
+                Some(rel_path)
             })
             .collect();
 
-        // Now process all files in parallel (metadata reading + attribute parsing)
-        // Pre-wrap root_path in Arc to avoid cloning in parallel error paths
-        let root_path_arc = Arc::new(root_path.to_path_buf());
-
+        // Now process all files in parallel (mode reading + attribute parsing)
         let entries: Result<Vec<_>> = file_paths
             .par_iter()
-            .map(|path| {
-                // Get relative path from root
-                let rel_path =
-                    path.strip_prefix(root_path)
-                        .map_err(|_| Error::InvalidPathPrefix {
-                            path: Arc::new(path.clone()),
-                            base: Arc::clone(&root_path_arc),
-                        })?;
-
-                let source_rel_path = SourceRelPath::new(rel_path.to_path_buf())?;
+            .map(|rel_path| {
+                let source_rel_path = SourceRelPath::new(rel_path.clone())?;
 
                 // Parse attributes from filename
-                let file_name = path
+                let file_name = rel_path
                     .file_name()
                     .ok_or_else(|| Error::InvalidConfig {
-                        message: format!("Invalid path: {}", path.display()),
+                        message: format!("Invalid path: {}", rel_path.display()),
                     })?
                     .to_string_lossy();
 
-                let metadata = std::fs::metadata(path).map_err(|e| Error::FileRead {
-                    path: root
-                        .join(&source_rel_path.to_rel_path())
-                        .as_path()
-                        .to_path_buf(),
-                    source: e,
+                let permissions = reader.mode(rel_path).map_err(|e| match e {
+                    Error::FileRead { source, .. } => Error::FileRead {
+                        path: root
+                            .join(&source_rel_path.to_rel_path())
+                            .as_path()
+                            .to_path_buf(),
+                        source,
+                    },
+                    other => other,
                 })?;
 
-                #[cfg(unix)]
-                let permissions = {
-                    use std::os::unix::fs::PermissionsExt;
-                    Some(metadata.permissions().mode())
-                };
-
-                #[cfg(not(unix))]
-                let permissions = None;
-
+                let (ownership, file_name) = Ownership::parse_prefix(&file_name)?;
                 let (attrs, target_name) =
-                    FileAttributes::parse_from_source(&file_name, permissions)?;
+                    FileAttributes::parse_from_source(file_name, permissions)?;
 
                 // Calculate target path
                 let target_rel = if let Some(parent) = rel_path.parent() {
-                    parent.join(&target_name)
+                    target_dir_path(parent)?.join(&target_name)
                 } else {
                     std::path::PathBuf::from(&target_name)
                 };
 
+                // Apply target ignore patterns if provided
+                if matcher.is_some_and(|m| m.is_ignored(&target_rel, Some(false))) {
+                    return Ok(None);
+                }
+
                 let target_path = RelPath::new(target_rel)?;
 
-                let source_entry = SourceEntry::File {
-                    source_path: source_rel_path,
-                    target_path: target_path.clone(),
-                    attributes: attrs,
+                let source_entry = if attrs.is_script() {
+                    SourceEntry::Script {
+                        source_path: source_rel_path,
+                        target_path: target_path.clone(),
+                        attributes: attrs,
+                    }
+                } else if attrs.is_symlink() {
+                    SourceEntry::Symlink {
+                        source_path: source_rel_path,
+                        target_path: target_path.clone(),
+                        attributes: attrs,
+                    }
+                } else {
+                    SourceEntry::File {
+                        source_path: source_rel_path,
+                        target_path: target_path.clone(),
+                        attributes: attrs,
+                    }
                 };
 
-                Ok((target_path, source_entry))
+                Ok(Some((target_path, source_entry, ownership)))
             })
             .collect();
 
         let mut entry_map = HashMap::new();
-        for (target_path, source_entry) in entries? {
+        let mut scripts = Vec::new();
+        let mut ownership_map = HashMap::new();
+        for (target_path, source_entry, ownership) in entries?.into_iter().flatten() {
+            if matches!(source_entry, SourceEntry::Script { .. }) {
+                scripts.push(source_entry);
+                continue;
+            }
+            if !ownership.is_empty() {
+                ownership_map.insert(target_path.clone(), ownership);
+            }
             entry_map.insert(target_path, source_entry);
         }
+        scripts.sort_by(|a, b| a.source_path().as_path().cmp(b.source_path().as_path()));
+
+        let mut exact_dirs = HashSet::new();
+        for rel_path in exact_dir_paths {
+            let target_rel = target_dir_path(&rel_path)?;
+            if matcher.is_some_and(|m| m.is_ignored(&target_rel, Some(true))) {
+                continue;
+            }
+            exact_dirs.insert(RelPath::new(target_rel)?);
+        }
 
         Ok(Self {
             root,
+            reader,
             entries: entry_map,
+            scripts,
+            ownership: ownership_map,
+            exact_dirs,
         })
     }
 
     /// Get all source entries
+    ///
+    /// Scripts are not included, see [`SourceState::scripts`].
     pub fn entries(&self) -> impl Iterator<Item = &SourceEntry> {
         self.entries.values()
     }
 
+    /// Keep only the source entries for which `keep` returns true
+    ///
+    /// Scripts are not affected.
+    pub fn retain(&mut self, mut keep: impl FnMut(&SourceEntry) -> bool) {
+        self.entries.retain(|_, entry| keep(entry));
+    }
+
+    /// Get all script entries, ordered by source path
+    pub fn scripts(&self) -> impl Iterator<Item = &SourceEntry> {
+        self.scripts.iter()
+    }
+
     /// Get a source entry by target path
     #[must_use]
     pub fn get(&self, target_path: &RelPath) -> Option<&SourceEntry> {
         self.entries.get(target_path)
     }
 
+    /// Get the owner and group a target path should have, if any
+    #[must_use]
+    pub fn ownership(&self, target_path: &RelPath) -> Option<&Ownership> {
+        self.ownership.get(target_path)
+    }
+
+    /// Target paths of directories whose unmanaged entries are removed on apply
+    pub fn exact_dirs(&self) -> impl Iterator<Item = &RelPath> {
+        self.exact_dirs.iter()
+    }
+
     /// Get the root directory
     #[must_use]
     pub fn root(&self) -> &AbsPath {
@@ -1223,6 +1841,342 @@ impl SourceState {
         // Convert SourceRelPath to RelPath first, then join
         self.root.join(&source_path.to_rel_path())
     }
+
+    /// Read the content of a source file
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the file cannot be read
+    pub fn read_file(&self, source_path: &SourceRelPath) -> Result<Vec<u8>> {
+        self.reader
+            .read(source_path.as_path())
+            .map_err(|e| match e {
+                Error::FileRead { source, .. } => Error::FileRead {
+                    path: self.source_file_path(source_path).as_path().to_path_buf(),
+                    source,
+                },
+                other => other,
+            })
+    }
+
+    /// Local path and size of a source file that should be streamed
+    ///
+    /// Returns `None` for files below the processor's stream threshold,
+    /// templates, and sources without local files (such as a git revision).
+    pub fn streamed_file<D, R>(
+        &self,
+        source_path: &SourceRelPath,
+        attributes: FileAttributes,
+        processor: &ContentProcessor<D, R>,
+    ) -> Option<AbsPath>
+    where
+        D: crate::content::Decryptor,
+        R: crate::content::TemplateRenderer,
+    {
+        let local = AbsPath::new(self.reader.local_path(source_path.as_path())?).ok()?;
+        let size = fs::metadata(local.as_path()).ok()?.len();
+        processor.should_stream(&attributes, size).then_some(local)
+    }
+
+    /// Where a `symlink_` entry points: its content, decrypted and rendered
+    ///
+    /// Surrounding whitespace, such as the trailing newline editors add, is
+    /// not part of the target.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the file cannot be read or processed, or if the
+    /// target is empty or not valid UTF-8
+    pub fn symlink_target<D, R>(
+        &self,
+        source_path: &SourceRelPath,
+        attributes: &FileAttributes,
+        processor: &ContentProcessor<D, R>,
+        context: &serde_json::Value,
+    ) -> Result<std::path::PathBuf>
+    where
+        D: crate::content::Decryptor,
+        R: crate::content::TemplateRenderer,
+    {
+        let path = self.source_file_path(source_path).to_string();
+        let bytes =
+            processor.process_content(self.read_file(source_path)?, attributes, context, &path)?;
+        let target = String::from_utf8(bytes).map_err(|source| Error::InvalidUtf8 {
+            path: path.clone(),
+            source,
+        })?;
+        let target = target.trim();
+        if target.is_empty() {
+            return Err(Error::InvalidConfig {
+                message: format!("Symlink {path} has an empty target"),
+            });
+        }
+        Ok(std::path::PathBuf::from(target))
+    }
+
+    /// Source file a destination should link to in the given apply mode
+    ///
+    /// Only plain files are linked: templates, encrypted or compressed files,
+    /// and files with inline age values render differently from the source,
+    /// so they are copied in either mode.
+    #[must_use]
+    pub fn link_target(&self, entry: &SourceEntry, mode: ApplyMode) -> Option<AbsPath> {
+        let SourceEntry::File {
+            source_path,
+            attributes,
+            ..
+        } = entry
+        else {
+            return None;
+        };
+        if mode != ApplyMode::Symlink
+            || attributes.is_template()
+            || attributes.is_encrypted()
+            || attributes.is_compressed()
+        {
+            return None;
+        }
+
+        let content = self.read_file(source_path).ok()?;
+        if content.windows(4).any(|window| window == b"age:") {
+            return None;
+        }
+        Some(self.source_file_path(source_path))
+    }
+}
+
+/// Render records of earlier applies, used to skip processing unchanged files
+///
+/// A template, encrypted, or compressed file is not processed again when its inputs hash
+/// to the recorded `input_hash` and the destination still holds the recorded
+/// output; the destination content is used as the target content instead.
+///
+/// The inputs of a file are its source content and attributes, the template
+/// context (without the environment), the values of environment variables
+/// named in the source, and a caller-provided fingerprint for anything else
+/// processing depends on, such as identities and included templates. Files
+/// read with `include()` or `includeTemplate()` are recorded while processing,
+/// and must still have the content that was included.
+#[derive(Debug)]
+pub struct RenderCache {
+    records: HashMap<String, RenderRecord>,
+    dest_dir: AbsPath,
+    targets: Targets,
+    fingerprint: Vec<u8>,
+    context: ContextHash,
+}
+
+impl RenderCache {
+    /// Create a cache from stored records for files under `dest_dir`
+    ///
+    /// `context` must be the template context files are processed with.
+    #[must_use]
+    pub fn new(
+        records: HashMap<String, RenderRecord>,
+        dest_dir: AbsPath,
+        context: &serde_json::Value,
+        fingerprint: Vec<u8>,
+    ) -> Self {
+        Self {
+            records,
+            dest_dir,
+            targets: Targets::default(),
+            fingerprint,
+            context: ContextHash::new(context),
+        }
+    }
+
+    /// Look for subtrees of `.guisu/targets.toml` under their own roots
+    #[must_use]
+    pub fn with_targets(mut self, targets: Targets) -> Self {
+        self.targets = targets;
+        self
+    }
+
+    /// Check if files with these attributes go through the render cache
+    ///
+    /// Plain files are cheaper to read than to look up.
+    #[must_use]
+    pub fn applies_to(attributes: FileAttributes) -> bool {
+        attributes.is_template() || attributes.is_encrypted() || attributes.is_compressed()
+    }
+
+    /// Process a source file unless the destination still holds its output
+    ///
+    /// Returns the content and, when the file was processed, its new render
+    /// record.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the source file cannot be read or processing fails
+    pub fn process_file<D, R>(
+        &self,
+        source: &SourceState,
+        source_path: &SourceRelPath,
+        target_path: &RelPath,
+        attributes: FileAttributes,
+        processor: &ContentProcessor<D, R>,
+        context: &serde_json::Value,
+    ) -> Result<(Vec<u8>, Option<RenderRecord>)>
+    where
+        D: crate::content::Decryptor,
+        R: crate::content::TemplateRenderer,
+    {
+        let abs_source_path = source.source_file_path(source_path);
+        let source_content = source.read_file(source_path)?;
+        let input_hash = self.input_hash(source_path, attributes, &source_content);
+
+        if let Some(reused) = self.reuse(target_path, &input_hash) {
+            tracing::debug!(path = %target_path, "Inputs unchanged, reusing destination content");
+            return Ok((reused, None));
+        }
+
+        let (processed, included) = guisu_template::track_dependencies(|| {
+            processor.process_content(
+                source_content,
+                &attributes,
+                context,
+                &abs_source_path.to_string(),
+            )
+        });
+        let processed = processed?;
+        let record = RenderRecord {
+            input_hash,
+            content_hash: hash::hash_content(&processed),
+            volatile: included.is_none(),
+            dependencies: included
+                .unwrap_or_default()
+                .into_iter()
+                .map(|(path, hash)| RenderDependency {
+                    path: path.to_string_lossy().into_owned(),
+                    hash,
+                })
+                .collect(),
+        };
+        Ok((processed, Some(record)))
+    }
+
+    /// Hash of the output of processing a source file, without processing it
+    ///
+    /// Returns `None` if the file's inputs changed since it was last processed,
+    /// or it never was. The destination is not read.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the source file cannot be read
+    pub fn cached_hash(
+        &self,
+        source: &SourceState,
+        source_path: &SourceRelPath,
+        target_path: &RelPath,
+        attributes: FileAttributes,
+    ) -> Result<Option<[u8; 32]>> {
+        let source_content = source.read_file(source_path)?;
+        let input_hash = self.input_hash(source_path, attributes, &source_content);
+        Ok(self
+            .current_record(target_path, &input_hash)
+            .map(|record| record.content_hash))
+    }
+
+    /// Hash the inputs of processing one source file
+    fn input_hash(
+        &self,
+        source_path: &SourceRelPath,
+        attributes: FileAttributes,
+        source_content: &[u8],
+    ) -> [u8; 32] {
+        let context = &self.context;
+        let mut hasher = blake3::Hasher::new();
+        hasher.update(&self.fingerprint);
+        hasher.update(&context.without_env);
+        hasher.update(source_path.to_string().as_bytes());
+        hasher.update(&attributes.bits().to_le_bytes());
+        hasher.update(source_content);
+
+        // Encrypted templates don't show which variables they use
+        let all_env = attributes.is_template() && attributes.is_encrypted();
+        for (key, value) in &context.env {
+            if all_env || contains_bytes(source_content, key.as_bytes()) {
+                hasher.update(key.as_bytes());
+                hasher.update(b"=");
+                hasher.update(value.as_bytes());
+                hasher.update(b"\0");
+            }
+        }
+
+        *hasher.finalize().as_bytes()
+    }
+
+    /// Destination content if it is still the output of processing `input_hash`
+    fn reuse(&self, target_path: &RelPath, input_hash: &[u8; 32]) -> Option<Vec<u8>> {
+        let record = self.current_record(target_path, input_hash)?;
+        let dest_path = self.targets.resolve(&self.dest_dir, target_path);
+        let content = fs::read(dest_path.as_path()).ok()?;
+        (hash::hash_content(&content) == record.content_hash).then_some(content)
+    }
+
+    /// Record of `target_path` if it was made from inputs hashing to `input_hash`
+    /// and the files it included are unchanged
+    fn current_record(
+        &self,
+        target_path: &RelPath,
+        input_hash: &[u8; 32],
+    ) -> Option<&RenderRecord> {
+        let record = self.records.get(&target_path.to_string())?;
+        if record.input_hash != *input_hash {
+            return None;
+        }
+        if record.volatile {
+            tracing::debug!(path = %target_path, "Looks up external values, processing again");
+            return None;
+        }
+        if let Some(changed) = record.dependencies.iter().find(|dep| !dep.is_current()) {
+            tracing::debug!(path = %target_path, included = %changed.path, "Included file changed");
+            return None;
+        }
+        Some(record)
+    }
+}
+
+/// Template context split for render cache hashing
+#[derive(Debug)]
+struct ContextHash {
+    /// Serialized context without `env`
+    without_env: Vec<u8>,
+    /// Environment variables, sorted by name
+    env: Vec<(String, String)>,
+}
+
+impl ContextHash {
+    fn new(context: &serde_json::Value) -> Self {
+        let mut context = context.clone();
+        let mut env: Vec<(String, String)> = context
+            .as_object_mut()
+            .and_then(|object| object.remove("env"))
+            .and_then(|env| match env {
+                serde_json::Value::Object(vars) => Some(
+                    vars.into_iter()
+                        .filter_map(|(key, value)| Some((key, value.as_str()?.to_string())))
+                        .collect(),
+                ),
+                _ => None,
+            })
+            .unwrap_or_default();
+        env.sort();
+
+        Self {
+            without_env: serde_json::to_vec(&context).unwrap_or_default(),
+            env,
+        }
+    }
+}
+
+/// Check if `haystack` contains `needle`
+fn contains_bytes(haystack: &[u8], needle: &[u8]) -> bool {
+    !needle.is_empty()
+        && haystack
+            .windows(needle.len())
+            .any(|window| window == needle)
 }
 
 /// State of target files (after processing templates and encryption)
@@ -1233,6 +2187,18 @@ impl SourceState {
 pub struct TargetState {
     /// Map of target paths to target entries
     entries: HashMap<RelPath, TargetEntry>,
+
+    /// Owner and group of target paths, applied when running as root
+    ownership: HashMap<RelPath, Ownership>,
+
+    /// Target paths of secret env files, whose values are never displayed
+    env_files: HashSet<RelPath>,
+
+    /// Target paths of files decrypted from encrypted sources
+    encrypted_files: HashSet<RelPath>,
+
+    /// Render records of files processed (not reused from the render cache)
+    render_records: HashMap<RelPath, RenderRecord>,
 }
 
 impl TargetState {
@@ -1241,6 +2207,10 @@ impl TargetState {
     pub fn new() -> Self {
         Self {
             entries: HashMap::new(),
+            ownership: HashMap::new(),
+            env_files: HashSet::new(),
+            encrypted_files: HashSet::new(),
+            render_records: HashMap::new(),
         }
     }
 
@@ -1279,38 +2249,233 @@ impl TargetState {
         processor: &ContentProcessor<D, R>,
         context: &serde_json::Value,
     ) -> Result<Self>
+    where
+        D: crate::content::Decryptor + Sync,
+        R: crate::content::TemplateRenderer + Sync,
+    {
+        Self::from_source_with_mode(source, processor, context, ApplyMode::Copy)
+    }
+
+    /// Create target state from source state, linking plain files in symlink mode
+    ///
+    /// In [`ApplyMode::Symlink`], files selected by [`SourceState::link_target`]
+    /// become symlinks to the source file instead of copies of its content.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if processing fails (e.g., file read error, decryption failure, template rendering error, invalid UTF-8)
+    pub fn from_source_with_mode<D, R>(
+        source: &SourceState,
+        processor: &ContentProcessor<D, R>,
+        context: &serde_json::Value,
+        mode: ApplyMode,
+    ) -> Result<Self>
+    where
+        D: crate::content::Decryptor + Sync,
+        R: crate::content::TemplateRenderer + Sync,
+    {
+        Self::build(source, processor, context, mode, None)
+    }
+
+    /// Create target state from source state, reusing unchanged processed files
+    ///
+    /// Template and encrypted files whose inputs match their render record in
+    /// `cache` take their content from the destination instead of being
+    /// processed. Records for files that were processed are available from
+    /// [`TargetState::render_records`] to be saved after applying.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if processing fails (e.g., file read error, decryption failure, template rendering error, invalid UTF-8)
+    pub fn from_source_incremental<D, R>(
+        source: &SourceState,
+        processor: &ContentProcessor<D, R>,
+        context: &serde_json::Value,
+        mode: ApplyMode,
+        cache: &RenderCache,
+    ) -> Result<Self>
+    where
+        D: crate::content::Decryptor + Sync,
+        R: crate::content::TemplateRenderer + Sync,
+    {
+        Self::build(source, processor, context, mode, Some(cache))
+    }
+
+    fn build<D, R>(
+        source: &SourceState,
+        processor: &ContentProcessor<D, R>,
+        context: &serde_json::Value,
+        mode: ApplyMode,
+        cache: Option<&RenderCache>,
+    ) -> Result<Self>
     where
         D: crate::content::Decryptor + Sync,
         R: crate::content::TemplateRenderer + Sync,
     {
         use rayon::prelude::*;
 
+        // Identical rendered contents share one allocation
+        let pool = ContentPool::new();
+
         // Parallel processing of source entries (template rendering + decryption are CPU-intensive)
-        let entries: Result<Vec<_>> = source
-            .entries()
-            .par_bridge()
-            .map(|source_entry| Self::process_entry(source, source_entry, processor, context))
+        // Collected first so rayon can split the work evenly instead of pulling entries one by one
+        let source_entries: Vec<&SourceEntry> = source.entries().collect();
+        Self::prefetch_templates(source, &source_entries, processor, mode, cache);
+        let entries: Result<Vec<_>> = source_entries
+            .par_iter()
+            .map(|source_entry| {
+                let _span =
+                    tracing::trace_span!("process_entry", path = %source_entry.target_path())
+                        .entered();
+                if let Some(link_target) = source.link_target(source_entry, mode) {
+                    return Ok((
+                        TargetEntry::Symlink {
+                            path: source_entry.target_path().clone(),
+                            target: link_target.as_path().to_path_buf(),
+                        },
+                        None,
+                    ));
+                }
+                if let SourceEntry::File {
+                    source_path,
+                    target_path,
+                    attributes,
+                } = source_entry
+                    && let Some(local) = source.streamed_file(source_path, *attributes, processor)
+                {
+                    // Only the hash is kept, the content is streamed again when written
+                    let (content_hash, size) =
+                        processor.stream_file(&local, attributes, &mut std::io::sink())?;
+                    let entry = TargetEntry::LargeFile {
+                        path: target_path.clone(),
+                        source: local.as_path().to_path_buf(),
+                        attributes: *attributes,
+                        content_hash,
+                        size,
+                        mode: attributes.mode(),
+                    };
+                    return Ok((entry, None));
+                }
+                if let Some(cache) = cache
+                    && let SourceEntry::File {
+                        source_path,
+                        target_path,
+                        attributes,
+                    } = source_entry
+                    && RenderCache::applies_to(*attributes)
+                {
+                    let (processed_content, record) = cache.process_file(
+                        source,
+                        source_path,
+                        target_path,
+                        *attributes,
+                        processor,
+                        context,
+                    )?;
+                    let (shared_content, content_hash) = pool.intern(processed_content);
+                    let entry = TargetEntry::File {
+                        path: target_path.clone(),
+                        content: shared_content,
+                        content_hash,
+                        mode: attributes.mode(),
+                    };
+                    return Ok((entry, record));
+                }
+                Self::process_entry(source, source_entry, processor, context, &pool)
+                    .map(|entry| (entry, None))
+            })
             .collect();
 
         let mut target_state = Self::new();
-        for entry in entries? {
+        for (entry, record) in entries? {
+            if let Some(ownership) = source.ownership(entry.path()) {
+                target_state.set_ownership(entry.path().clone(), ownership.clone());
+            }
+            if source.get(entry.path()).is_some_and(SourceEntry::is_env) {
+                target_state.set_env_file(entry.path().clone());
+            }
+            if source
+                .get(entry.path())
+                .is_some_and(SourceEntry::is_encrypted)
+            {
+                target_state.set_encrypted_file(entry.path().clone());
+            }
+            if let Some(record) = record {
+                target_state
+                    .render_records
+                    .insert(entry.path().clone(), record);
+            }
             target_state.add(entry);
         }
 
         Ok(target_state)
     }
 
+    /// Let the renderer prepare for the plain templates about to be rendered
+    ///
+    /// Templates the render cache will reuse are left out. Encrypted and
+    /// compressed templates are too, since they would be decoded twice.
+    fn prefetch_templates<D, R>(
+        source: &SourceState,
+        source_entries: &[&SourceEntry],
+        processor: &ContentProcessor<D, R>,
+        mode: ApplyMode,
+        cache: Option<&RenderCache>,
+    ) where
+        D: crate::content::Decryptor + Sync,
+        R: crate::content::TemplateRenderer + Sync,
+    {
+        use rayon::prelude::*;
+
+        let templates: Vec<String> = source_entries
+            .par_iter()
+            .filter_map(|source_entry| {
+                let SourceEntry::File {
+                    source_path,
+                    target_path,
+                    attributes,
+                } = source_entry
+                else {
+                    return None;
+                };
+                if !attributes.is_template()
+                    || attributes.is_encrypted()
+                    || attributes.is_compressed()
+                    || source.link_target(source_entry, mode).is_some()
+                {
+                    return None;
+                }
+                if let Some(cache) = cache
+                    && matches!(
+                        cache.cached_hash(source, source_path, target_path, *attributes),
+                        Ok(Some(_))
+                    )
+                {
+                    return None;
+                }
+                // Unreadable files are reported when processed
+                String::from_utf8(source.read_file(source_path).ok()?).ok()
+            })
+            .collect();
+
+        if !templates.is_empty() {
+            let templates: Vec<&str> = templates.iter().map(String::as_str).collect();
+            processor.prefetch(&templates);
+        }
+    }
+
     /// Process a single source entry into a target entry
     ///
     /// This applies the appropriate transformations based on the entry type:
     /// - Files: Read contents, decrypt if needed, render templates if needed
     /// - Directories: Create directory entry with permissions
-    /// - Symlinks: Create symlink entry (no content processing)
+    /// - Symlinks: Read the link target, decrypting and rendering it if needed
     fn process_entry<D, R>(
         source: &SourceState,
         source_entry: &SourceEntry,
         processor: &ContentProcessor<D, R>,
         context: &serde_json::Value,
+        pool: &ContentPool,
     ) -> Result<TargetEntry>
     where
         D: crate::content::Decryptor,
@@ -1326,17 +2491,21 @@ impl TargetState {
                 let abs_source_path = source.source_file_path(source_path);
 
                 // Process the file contents through the decrypt→render pipeline
-                // Note: process_file already provides detailed error context,
+                // Note: process_content already provides detailed error context,
                 // so we don't wrap it here to avoid redundant error messages
-                let processed_content =
-                    processor.process_file(&abs_source_path, attributes, context)?;
+                let processed_content = processor.process_content(
+                    source.read_file(source_path)?,
+                    attributes,
+                    context,
+                    &abs_source_path.to_string(),
+                )?;
 
                 let mode = attributes.mode();
-                let content_hash = crate::hash::hash_content(&processed_content);
+                let (shared_content, content_hash) = pool.intern(processed_content);
 
                 Ok(TargetEntry::File {
                     path: target_path.clone(),
-                    content: processed_content,
+                    content: shared_content,
                     content_hash,
                     mode,
                 })
@@ -1357,18 +2526,35 @@ impl TargetState {
             }
 
             SourceEntry::Symlink {
+                source_path,
                 target_path,
-                link_target,
-                ..
-            } => {
-                // Symlinks don't have content processing currently
-                // NOTE: Future enhancement - support templating in symlink targets
-                // Chezmoi supports this via .tmpl suffix on symlink files
-                // See CLAUDE.md: "Symlink Target Templating"
-                Ok(TargetEntry::Symlink {
-                    path: target_path.clone(),
-                    target: link_target.clone(),
-                })
+                attributes,
+            } => Ok(TargetEntry::Symlink {
+                path: target_path.clone(),
+                target: source.symlink_target(source_path, attributes, processor, context)?,
+            }),
+
+            SourceEntry::Script { source_path, .. } => Err(Error::InvalidConfig {
+                message: format!("Script {source_path} is run, not written to the destination"),
+            }),
+        }
+    }
+
+    /// Rewrite symlink targets as relative or absolute paths, as `policy` asks
+    ///
+    /// `dest_path` maps the path of an entry to where its link is created.
+    pub fn normalize_link_targets(
+        &mut self,
+        policy: SymlinkTargets,
+        dest_path: impl Fn(&RelPath) -> AbsPath,
+    ) {
+        if policy == SymlinkTargets::Keep {
+            return;
+        }
+        for entry in self.entries.values_mut() {
+            if let TargetEntry::Symlink { path, target } = entry {
+                *target =
+                    crate::system::normalize_link_target(dest_path(path).as_path(), target, policy);
             }
         }
     }
@@ -1379,12 +2565,108 @@ impl TargetState {
         self.entries.insert(path, entry);
     }
 
+    /// Add removals for unmanaged entries of the source's exact directories
+    ///
+    /// Every destination entry directly inside an `exact_` directory that is
+    /// neither a target of this state or of `source` nor a parent of one
+    /// becomes a [`TargetEntry::Remove`],
+    /// unless `is_ignored` (given the path and whether it is a directory)
+    /// keeps it. Subdirectories are not exact unless marked themselves.
+    /// `dest_path` gives the destination of a target path.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if an existing exact directory cannot be read
+    pub fn add_exact_removals(
+        &mut self,
+        source: &SourceState,
+        dest_path: impl Fn(&RelPath) -> AbsPath,
+        is_ignored: impl Fn(&RelPath, bool) -> bool,
+    ) -> Result<()> {
+        let managed: HashSet<&Path> = self
+            .entries
+            .keys()
+            .chain(source.entries().map(SourceEntry::target_path))
+            .map(RelPath::as_path)
+            .collect();
+        let managed_dirs: HashSet<&Path> = managed
+            .iter()
+            .flat_map(|path| path.ancestors().skip(1))
+            .collect();
+
+        let mut removals = Vec::new();
+        for dir in source.exact_dirs() {
+            let read_dir = match fs::read_dir(dest_path(dir).as_path()) {
+                Ok(read_dir) => read_dir,
+                Err(e) if e.kind() == std::io::ErrorKind::NotFound => continue,
+                Err(e) => return Err(e.into()),
+            };
+            for dest_entry in read_dir {
+                let dest_entry = dest_entry?;
+                let path = RelPath::new(dir.as_path().join(dest_entry.file_name()))?;
+                if managed.contains(path.as_path()) || managed_dirs.contains(path.as_path()) {
+                    continue;
+                }
+                let is_dir = dest_entry.file_type()?.is_dir();
+                if !is_ignored(&path, is_dir) {
+                    removals.push(path);
+                }
+            }
+        }
+
+        for path in removals {
+            self.add(TargetEntry::Remove { path });
+        }
+        Ok(())
+    }
+
     /// Get a target entry by path
     #[must_use]
     pub fn get(&self, path: &RelPath) -> Option<&TargetEntry> {
         self.entries.get(path)
     }
 
+    /// Set the owner and group of a target path
+    pub fn set_ownership(&mut self, path: RelPath, ownership: Ownership) {
+        self.ownership.insert(path, ownership);
+    }
+
+    /// Get the owner and group a target path should have, if any
+    #[must_use]
+    pub fn ownership(&self, path: &RelPath) -> Option<&Ownership> {
+        self.ownership.get(path)
+    }
+
+    /// Mark a target path as a secret env file
+    pub fn set_env_file(&mut self, path: RelPath) {
+        self.env_files.insert(path);
+    }
+
+    /// Check if a target path is a secret env file
+    #[must_use]
+    pub fn is_env_file(&self, path: &RelPath) -> bool {
+        self.env_files.contains(path)
+    }
+
+    /// Mark a target path as decrypted from an encrypted source
+    pub fn set_encrypted_file(&mut self, path: RelPath) {
+        self.encrypted_files.insert(path);
+    }
+
+    /// Check if a target path is decrypted from an encrypted source
+    #[must_use]
+    pub fn is_encrypted_file(&self, path: &RelPath) -> bool {
+        self.encrypted_files.contains(path)
+    }
+
+    /// Render records of the files processed while building this state
+    ///
+    /// Files reused from the render cache have no new record.
+    #[must_use]
+    pub fn render_records(&self) -> &HashMap<RelPath, RenderRecord> {
+        &self.render_records
+    }
+
     /// Iterate over all entries
     pub fn entries(&self) -> impl Iterator<Item = &TargetEntry> {
         self.entries.values()
@@ -1409,6 +2691,539 @@ impl Default for TargetState {
     }
 }
 
+#[cfg(test)]
+mod tests {
+    #![allow(clippy::unwrap_used, clippy::panic)]
+    use super::*;
+
+    #[test]
+    fn test_read_with_matcher_applies_source_and_target_patterns() {
+        let temp = tempfile::TempDir::new().unwrap();
+        let root = fs::canonicalize(temp.path()).unwrap();
+        fs::create_dir_all(root.join(".guisu")).unwrap();
+        fs::write(
+            root.join(".guisu/ignores.toml"),
+            r#"global = ["src:*.orig", "*.log"]"#,
+        )
+        .unwrap();
+
+        let home = root.join("home");
+        fs::create_dir_all(&home).unwrap();
+        fs::write(home.join(".bashrc.j2"), "x").unwrap();
+        fs::write(home.join(".bashrc.j2.orig"), "x").unwrap();
+        fs::write(home.join("debug.log.j2"), "x").unwrap();
+
+        let matcher = guisu_config::IgnoreMatcher::from_ignores_toml(&root).unwrap();
+        let state =
+            SourceState::read_with_matcher(AbsPath::new(home).unwrap(), Some(&matcher)).unwrap();
+
+        // `src:*.orig` drops the leftover by its source name and
+        // `*.log` drops the template by the target path it renders to
+        let targets: Vec<String> = state
+            .entries()
+            .map(|e| e.target_path().to_string())
+            .collect();
+        assert_eq!(targets, [".bashrc"]);
+    }
+
+    #[test]
+    fn test_read_with_matcher_uses_guisuignore_files() {
+        let temp = tempfile::TempDir::new().unwrap();
+        let root = fs::canonicalize(temp.path()).unwrap();
+        let home = root.join("home");
+        fs::create_dir_all(home.join(".config/nvim")).unwrap();
+        fs::create_dir_all(home.join(".config/fish")).unwrap();
+        fs::write(home.join(".config/.guisuignore"), "*\n!nvim/\n").unwrap();
+        fs::write(home.join(".config/nvim/init.lua"), "x").unwrap();
+        fs::write(home.join(".config/fish/config.fish"), "x").unwrap();
+
+        let matcher = guisu_config::IgnoreMatcher::load(&root, &home).unwrap();
+        let state =
+            SourceState::read_with_matcher(AbsPath::new(home).unwrap(), Some(&matcher)).unwrap();
+
+        // The ignore file itself is never managed
+        let targets: Vec<String> = state
+            .entries()
+            .map(|e| e.target_path().to_string())
+            .collect();
+        assert_eq!(targets, [".config/nvim/init.lua"]);
+    }
+
+    #[test]
+    fn test_read_with_matcher_skips_excluded_subtrees() {
+        let temp = tempfile::TempDir::new().unwrap();
+        let root = fs::canonicalize(temp.path()).unwrap();
+        let home = root.join("home");
+        fs::create_dir_all(home.join("work/nested")).unwrap();
+        fs::write(home.join("work/nested/a"), "x").unwrap();
+        fs::write(home.join("workbench"), "x").unwrap();
+
+        let matcher = guisu_config::IgnoreMatcher::load(&root, &home)
+            .unwrap()
+            .with_excluded_dirs(vec![std::path::PathBuf::from("work")]);
+        let state =
+            SourceState::read_with_matcher(AbsPath::new(home).unwrap(), Some(&matcher)).unwrap();
+
+        // Only whole path components match, so `workbench` stays
+        let targets: Vec<String> = state
+            .entries()
+            .map(|e| e.target_path().to_string())
+            .collect();
+        assert_eq!(targets, ["workbench"]);
+    }
+
+    #[test]
+    fn test_read_from_memory_source() {
+        let reader = crate::source::MemorySourceReader::new()
+            .with_file(".bashrc", "export A=1\n")
+            .with_file_mode("bin/run.sh", "#!/bin/sh\n", 0o100_755)
+            .with_file("exact_.vim/init.vim", "set nu\n");
+        let source = SourceState::read_from(
+            AbsPath::new("/nonexistent/home".into()).unwrap(),
+            Arc::new(reader),
+            None,
+        )
+        .unwrap();
+        assert_eq!(source.len(), 3);
+        assert_eq!(
+            source
+                .exact_dirs()
+                .map(ToString::to_string)
+                .collect::<Vec<_>>(),
+            [".vim"]
+        );
+
+        let processor =
+            ContentProcessor::new(crate::content::NoOpDecryptor, crate::content::NoOpRenderer);
+        let target = TargetState::from_source(&source, &processor, &serde_json::json!({})).unwrap();
+        let Some(TargetEntry::File { content, mode, .. }) =
+            target.get(&RelPath::new("bin/run.sh".into()).unwrap())
+        else {
+            panic!("bin/run.sh is not a file");
+        };
+        assert_eq!(&content[..], b"#!/bin/sh\n");
+        assert_eq!(*mode, Some(0o755));
+
+        // Sources without local files are never streamed
+        let processor = processor.with_stream_threshold(Some(0));
+        let target = TargetState::from_source(&source, &processor, &serde_json::json!({})).unwrap();
+        assert!(
+            !target
+                .entries()
+                .any(|entry| matches!(entry, TargetEntry::LargeFile { .. }))
+        );
+    }
+
+    #[test]
+    fn test_large_files_are_streamed() {
+        let temp = tempfile::TempDir::new().unwrap();
+        let home = temp.path().join("home");
+        fs::create_dir_all(&home).unwrap();
+        let large = vec![0x42; 4096];
+        fs::write(home.join("image.bin"), &large).unwrap();
+        fs::write(home.join("small"), "small").unwrap();
+        fs::write(home.join("large.j2"), "x".repeat(4096)).unwrap();
+
+        let source = SourceState::read(AbsPath::new(home.clone()).unwrap()).unwrap();
+        let processor =
+            ContentProcessor::new(crate::content::NoOpDecryptor, crate::content::NoOpRenderer)
+                .with_stream_threshold(Some(1024));
+        let target = TargetState::from_source(&source, &processor, &serde_json::json!({})).unwrap();
+
+        let Some(TargetEntry::LargeFile {
+            source: source_path,
+            content_hash,
+            size,
+            ..
+        }) = target.get(&RelPath::new("image.bin".into()).unwrap())
+        else {
+            panic!("image.bin is not streamed");
+        };
+        assert_eq!(source_path, &home.join("image.bin"));
+        assert_eq!(*content_hash, hash::hash_content(&large));
+        assert_eq!(*size, 4096);
+
+        // Small files and templates are held in memory
+        for path in ["small", "large"] {
+            assert!(matches!(
+                target.get(&RelPath::new(path.into()).unwrap()),
+                Some(TargetEntry::File { .. })
+            ));
+        }
+    }
+
+    #[test]
+    fn test_symlink_entries() {
+        let temp = tempfile::TempDir::new().unwrap();
+        let home = temp.path().join("home");
+        fs::create_dir_all(home.join(".config")).unwrap();
+        fs::write(home.join("symlink_.vimrc"), "/opt/vim/vimrc\n").unwrap();
+        fs::write(home.join(".config/symlink_nvim.j2"), "/home/NAME/nvim").unwrap();
+        fs::write(home.join("symlink_.empty"), "\n").unwrap();
+
+        let source = SourceState::read(AbsPath::new(home.clone()).unwrap()).unwrap();
+        assert!(matches!(
+            source.get(&RelPath::new(".vimrc".into()).unwrap()),
+            Some(SourceEntry::Symlink { .. })
+        ));
+
+        let processor = ContentProcessor::new(
+            crate::content::NoOpDecryptor,
+            CountingRenderer {
+                renders: Arc::default(),
+                prefetched: Arc::default(),
+            },
+        );
+        let context = serde_json::json!({ "name": "me" });
+        // An empty target is an error
+        assert!(TargetState::from_source(&source, &processor, &context).is_err());
+        fs::remove_file(home.join("symlink_.empty")).unwrap();
+
+        let source = SourceState::read(AbsPath::new(home).unwrap()).unwrap();
+        let mut target = TargetState::from_source(&source, &processor, &context).unwrap();
+        let link_target = |target: &TargetState, path: &str| match target
+            .get(&RelPath::new(path.into()).unwrap())
+        {
+            Some(TargetEntry::Symlink { target, .. }) => target.clone(),
+            other => panic!("{path} is not a symlink: {other:?}"),
+        };
+        assert_eq!(link_target(&target, ".vimrc"), Path::new("/opt/vim/vimrc"));
+        assert_eq!(
+            link_target(&target, ".config/nvim"),
+            Path::new("/home/me/nvim")
+        );
+
+        let dest = AbsPath::new("/home/me".into()).unwrap();
+        target.normalize_link_targets(SymlinkTargets::Relative, |path| dest.join(path));
+        assert_eq!(link_target(&target, ".config/nvim"), Path::new("../nvim"));
+        assert_eq!(
+            link_target(&target, ".vimrc"),
+            Path::new("../../opt/vim/vimrc")
+        );
+    }
+
+    /// Replaces `NAME` with the `name` context value and counts renders and
+    /// prefetched templates
+    struct CountingRenderer {
+        renders: Arc<std::sync::atomic::AtomicUsize>,
+        prefetched: Arc<std::sync::atomic::AtomicUsize>,
+    }
+
+    impl crate::content::TemplateRenderer for CountingRenderer {
+        type Error = std::io::Error;
+
+        fn render(
+            &self,
+            template: &str,
+            context: &serde_json::Value,
+        ) -> std::result::Result<String, Self::Error> {
+            self.renders
+                .fetch_add(1, std::sync::atomic::Ordering::SeqCst);
+            let name = context["name"].as_str().unwrap_or_default();
+            Ok(template.replace("NAME", name))
+        }
+
+        fn prefetch(&self, templates: &[&str]) {
+            self.prefetched
+                .fetch_add(templates.len(), std::sync::atomic::Ordering::SeqCst);
+        }
+    }
+
+    struct IncrementalFixture {
+        _temp: tempfile::TempDir,
+        dest: AbsPath,
+        source: SourceState,
+        processor: ContentProcessor<crate::content::NoOpDecryptor, CountingRenderer>,
+        renders: Arc<std::sync::atomic::AtomicUsize>,
+        prefetched: Arc<std::sync::atomic::AtomicUsize>,
+    }
+
+    impl IncrementalFixture {
+        fn new() -> Self {
+            let temp = tempfile::TempDir::new().unwrap();
+            let root = fs::canonicalize(temp.path()).unwrap();
+            fs::create_dir_all(root.join("home")).unwrap();
+            fs::create_dir_all(root.join("dest")).unwrap();
+            fs::write(root.join("home/.gitconfig.j2"), "user NAME $EDITOR").unwrap();
+            let renders = Arc::new(std::sync::atomic::AtomicUsize::new(0));
+            let prefetched = Arc::new(std::sync::atomic::AtomicUsize::new(0));
+
+            Self {
+                dest: AbsPath::new(root.join("dest")).unwrap(),
+                source: SourceState::read(AbsPath::new(root.join("home")).unwrap()).unwrap(),
+                processor: ContentProcessor::new(
+                    crate::content::NoOpDecryptor,
+                    CountingRenderer {
+                        renders: Arc::clone(&renders),
+                        prefetched: Arc::clone(&prefetched),
+                    },
+                ),
+                renders,
+                prefetched,
+                _temp: temp,
+            }
+        }
+
+        /// Build with records of the previous build, writing the result to the destination
+        fn build(
+            &self,
+            records: &HashMap<String, RenderRecord>,
+            context: &serde_json::Value,
+        ) -> TargetState {
+            let cache = RenderCache::new(records.clone(), self.dest.clone(), context, Vec::new());
+            let state = TargetState::from_source_incremental(
+                &self.source,
+                &self.processor,
+                context,
+                ApplyMode::Copy,
+                &cache,
+            )
+            .unwrap();
+            for entry in state.entries() {
+                if let TargetEntry::File { path, content, .. } = entry {
+                    fs::write(self.dest.join(path).as_path(), &**content).unwrap();
+                }
+            }
+            state
+        }
+
+        fn renders(&self) -> usize {
+            self.renders.load(std::sync::atomic::Ordering::SeqCst)
+        }
+
+        fn prefetched(&self) -> usize {
+            self.prefetched.load(std::sync::atomic::Ordering::SeqCst)
+        }
+    }
+
+    fn records_of(state: &TargetState) -> HashMap<String, RenderRecord> {
+        state
+            .render_records()
+            .iter()
+            .map(|(path, record)| (path.to_string(), record.clone()))
+            .collect()
+    }
+
+    #[test]
+    fn test_incremental_reuses_unchanged_output() {
+        let fixture = IncrementalFixture::new();
+        let context = serde_json::json!({"name": "alice", "env": {"EDITOR": "vim"}});
+
+        let first = fixture.build(&HashMap::new(), &context);
+        assert_eq!(fixture.renders(), 1);
+        let records = records_of(&first);
+        assert_eq!(records.len(), 1);
+
+        let second = fixture.build(&records, &context);
+        assert_eq!(fixture.renders(), 1);
+        assert!(second.render_records().is_empty());
+        let path = RelPath::new(".gitconfig".into()).unwrap();
+        let Some(TargetEntry::File { content, .. }) = second.get(&path) else {
+            panic!("expected file entry");
+        };
+        assert_eq!(&**content, b"user alice $EDITOR");
+    }
+
+    #[test]
+    fn test_incremental_prefetches_only_templates_to_render() {
+        let fixture = IncrementalFixture::new();
+        let context = serde_json::json!({"name": "alice"});
+
+        let records = records_of(&fixture.build(&HashMap::new(), &context));
+        assert_eq!(fixture.prefetched(), 1);
+
+        fixture.build(&records, &context);
+        assert_eq!(fixture.prefetched(), 1);
+    }
+
+    #[test]
+    fn test_cached_hash_skips_processing() {
+        let fixture = IncrementalFixture::new();
+        let context = serde_json::json!({"name": "alice"});
+        let records = records_of(&fixture.build(&HashMap::new(), &context));
+        let entry = fixture.source.entries().next().unwrap();
+        let SourceEntry::File {
+            source_path,
+            target_path,
+            attributes,
+        } = entry
+        else {
+            panic!("expected file entry");
+        };
+
+        let cached = |context: &serde_json::Value| {
+            RenderCache::new(records.clone(), fixture.dest.clone(), context, Vec::new())
+                .cached_hash(&fixture.source, source_path, target_path, *attributes)
+                .unwrap()
+        };
+        assert_eq!(cached(&context), Some(hash_data(b"user alice $EDITOR")));
+        assert_eq!(cached(&serde_json::json!({"name": "bob"})), None);
+        assert_eq!(fixture.renders(), 1);
+    }
+
+    #[test]
+    fn test_incremental_processes_changed_inputs() {
+        let fixture = IncrementalFixture::new();
+        let context = serde_json::json!({"name": "alice", "env": {"EDITOR": "vim", "PWD": "/a"}});
+        let records = records_of(&fixture.build(&HashMap::new(), &context));
+
+        // Environment variables the template doesn't name are not inputs
+        let moved = serde_json::json!({"name": "alice", "env": {"EDITOR": "vim", "PWD": "/b"}});
+        fixture.build(&records, &moved);
+        assert_eq!(fixture.renders(), 1);
+
+        let editor = serde_json::json!({"name": "alice", "env": {"EDITOR": "nvim", "PWD": "/a"}});
+        fixture.build(&records, &editor);
+        assert_eq!(fixture.renders(), 2);
+
+        let renamed = serde_json::json!({"name": "bob", "env": {"EDITOR": "vim", "PWD": "/a"}});
+        let records = records_of(&fixture.build(&records, &renamed));
+        assert_eq!(fixture.renders(), 3);
+
+        // A destination edited since the last build is processed again
+        fs::write(
+            fixture
+                .dest
+                .join(&RelPath::new(".gitconfig".into()).unwrap())
+                .as_path(),
+            "x",
+        )
+        .unwrap();
+        fixture.build(&records, &renamed);
+        assert_eq!(fixture.renders(), 4);
+    }
+
+    #[test]
+    fn test_incremental_tracks_included_files() {
+        let temp = tempfile::TempDir::new().unwrap();
+        let root = fs::canonicalize(temp.path()).unwrap();
+        fs::create_dir_all(root.join("home")).unwrap();
+        fs::create_dir_all(root.join("dest")).unwrap();
+        fs::write(
+            root.join("home/.bashrc.j2"),
+            r#"{{ include("common.sh") }}"#,
+        )
+        .unwrap();
+        fs::write(root.join("common.sh"), "alias ll='ls -l'\n").unwrap();
+
+        let source = SourceState::read(AbsPath::new(root.join("home")).unwrap()).unwrap();
+        let processor = ContentProcessor::new(
+            crate::content::NoOpDecryptor,
+            crate::adapters::template::TemplateRendererAdapter::new(
+                guisu_template::TemplateEngine::new(),
+            ),
+        );
+        let dest = AbsPath::new(root.join("dest")).unwrap();
+        let context = serde_json::json!({"guisu": {"srcDir": root.to_string_lossy()}});
+        let build = |records: &HashMap<String, RenderRecord>| {
+            let cache = RenderCache::new(records.clone(), dest.clone(), &context, Vec::new());
+            let state = TargetState::from_source_incremental(
+                &source,
+                &processor,
+                &context,
+                ApplyMode::Copy,
+                &cache,
+            )
+            .unwrap();
+            for entry in state.entries() {
+                if let TargetEntry::File { path, content, .. } = entry {
+                    fs::write(dest.join(path).as_path(), &**content).unwrap();
+                }
+            }
+            state
+        };
+
+        let records = records_of(&build(&HashMap::new()));
+        let dependencies = &records[".bashrc"].dependencies;
+        assert_eq!(dependencies.len(), 1);
+        assert!(dependencies[0].path.ends_with("common.sh"));
+        assert!(build(&records).render_records().is_empty());
+
+        // The template is unchanged, but what it includes is not
+        fs::write(root.join("common.sh"), "alias la='ls -a'\n").unwrap();
+        let rebuilt = build(&records);
+        assert_eq!(rebuilt.render_records().len(), 1);
+        assert_eq!(
+            fs::read_to_string(root.join("dest/.bashrc")).unwrap(),
+            "alias la='ls -a'\n"
+        );
+    }
+
+    #[cfg(unix)]
+    #[test]
+    #[serial_test::serial]
+    fn test_incremental_processes_vault_lookups_again() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let temp = tempfile::TempDir::new().unwrap();
+        let root = fs::canonicalize(temp.path()).unwrap();
+        fs::create_dir_all(root.join("home")).unwrap();
+        fs::create_dir_all(root.join("dest")).unwrap();
+        fs::write(
+            root.join("home/.netrc.j2"),
+            r#"password {{ pass("github") }}"#,
+        )
+        .unwrap();
+        // Stand-ins printing the password before and after it is rotated
+        let bin = root.join("bin");
+        fs::create_dir_all(&bin).unwrap();
+        for (command, password) in [("pass", "hunter2"), ("gopass", "correct-horse")] {
+            let path = bin.join(command);
+            fs::write(&path, format!("#!/bin/sh\necho {password}\n")).unwrap();
+            fs::set_permissions(&path, fs::Permissions::from_mode(0o755)).unwrap();
+        }
+        let path = format!(
+            "{}:{}",
+            bin.display(),
+            std::env::var("PATH").unwrap_or_default()
+        );
+
+        let source = SourceState::read(AbsPath::new(root.join("home")).unwrap()).unwrap();
+        let dest = AbsPath::new(root.join("dest")).unwrap();
+        let context = serde_json::json!({});
+        let build = |pass_command: &str, records: &HashMap<String, RenderRecord>| {
+            let processor = ContentProcessor::new(
+                crate::content::NoOpDecryptor,
+                crate::adapters::template::TemplateRendererAdapter::new(
+                    guisu_template::TemplateEngine::new().with_pass_command(pass_command),
+                ),
+            );
+            let cache = RenderCache::new(records.clone(), dest.clone(), &context, Vec::new());
+            let state = TargetState::from_source_incremental(
+                &source,
+                &processor,
+                &context,
+                ApplyMode::Copy,
+                &cache,
+            )
+            .unwrap();
+            for entry in state.entries() {
+                if let TargetEntry::File { path, content, .. } = entry {
+                    fs::write(dest.join(path).as_path(), &**content).unwrap();
+                }
+            }
+            state
+        };
+
+        temp_env::with_var("PATH", Some(path), || {
+            let records = records_of(&build("pass", &HashMap::new()));
+            assert!(records[".netrc"].volatile);
+            assert_eq!(
+                fs::read_to_string(root.join("dest/.netrc")).unwrap(),
+                "password hunter2"
+            );
+
+            // Same template and context, but the vault now has another password
+            let rebuilt = build("gopass", &records);
+            assert_eq!(rebuilt.render_records().len(), 1);
+            assert_eq!(
+                fs::read_to_string(root.join("dest/.netrc")).unwrap(),
+                "password correct-horse"
+            );
+        });
+    }
+}
+
 #[cfg(test)]
 #[allow(
     clippy::unwrap_used,
@@ -1443,10 +3258,38 @@ mod bincode_compat_verification {
         if result.is_some() {
             println!("Warning: Successfully read! This should not happen!");
             panic!("Vec<u8> and [u8; 32] should be incompatible!");
-        } else {
-            println!("Confirmed: Vec<u8> -> [u8; 32] is a breaking change");
-            println!("   Old database will not be readable!");
         }
+        println!("Confirmed: Vec<u8> -> [u8; 32] is a breaking change");
+        println!("   Old database will not be readable!");
+    }
+
+    #[test]
+    fn hook_state_reads_layout_without_run_id() {
+        #[derive(bincode::Encode)]
+        struct HookStateWithoutRunId {
+            #[bincode(with_serde)]
+            last_executed: Option<std::time::SystemTime>,
+            content_hash: Option<[u8; 32]>,
+            once_executed: HashSet<String>,
+            onchange_hashes: HashMap<String, [u8; 32]>,
+            onchange_rendered: HashMap<String, String>,
+            last_collections: Option<crate::hooks::config::HookCollections>,
+        }
+
+        let old = HookStateWithoutRunId {
+            last_executed: None,
+            content_hash: Some([7; 32]),
+            once_executed: HashSet::from(["setup".to_string()]),
+            onchange_hashes: HashMap::new(),
+            onchange_rendered: HashMap::new(),
+            last_collections: None,
+        };
+        let bytes = bincode::encode_to_vec(&old, bincode::config::standard()).unwrap();
+
+        let state = HookState::from_bytes(&bytes).unwrap();
+        assert!(state.has_executed_once("setup"));
+        assert_eq!(state.content_hash, Some([7; 32]));
+        assert!(state.last_run_id.is_none());
     }
 
     #[test]
@@ -1459,9 +3302,11 @@ mod bincode_compat_verification {
         let old_bytes =
             bincode::encode_to_vec(&old, bincode::config::standard()).expect("Failed to encode");
 
-        // [u8; 32] format (new)
+        // [u8; 32] format (new), without the later `last_applied` field
         let new = EntryState::new(&[0x12; 32], Some(0o644));
-        let new_bytes = new.to_bytes().expect("Failed to convert to bytes");
+        let new_bytes =
+            bincode::encode_to_vec((new.content_hash, new.mode), bincode::config::standard())
+                .expect("Failed to encode");
 
         println!("\n=== Serialization Size Comparison ===");
         println!("Vec<u8>:   {} bytes (with length prefix)", old_bytes.len());
@@ -1476,4 +3321,88 @@ mod bincode_compat_verification {
             "Serialization formats are completely different!"
         );
     }
+
+    #[test]
+    fn test_exact_dirs_remove_unmanaged_entries() {
+        let temp = tempfile::TempDir::new().unwrap();
+        let root = fs::canonicalize(temp.path()).unwrap();
+        let home = root.join("home");
+        fs::create_dir_all(home.join("exact_.vim/colors")).unwrap();
+        fs::create_dir_all(home.join("exact_.empty")).unwrap();
+        fs::write(home.join("exact_.vim/init.vim"), "x").unwrap();
+        fs::write(home.join("exact_.vim/colors/x.vim"), "x").unwrap();
+
+        let dest = root.join("dest");
+        fs::create_dir_all(dest.join(".vim/colors")).unwrap();
+        fs::create_dir_all(dest.join(".vim/plugged")).unwrap();
+        fs::create_dir_all(dest.join(".empty")).unwrap();
+        fs::write(dest.join(".vim/init.vim"), "x").unwrap();
+        fs::write(dest.join(".vim/old.vim"), "x").unwrap();
+        fs::write(dest.join(".vim/.init.vim.swp"), "x").unwrap();
+        fs::write(dest.join(".vim/colors/stale.vim"), "x").unwrap();
+        fs::write(dest.join(".empty/file"), "x").unwrap();
+
+        let source = SourceState::read(AbsPath::new(home).unwrap()).unwrap();
+        let mut exact: Vec<String> = source.exact_dirs().map(ToString::to_string).collect();
+        exact.sort();
+        assert_eq!(exact, [".empty", ".vim"]);
+
+        let processor =
+            ContentProcessor::new(crate::content::NoOpDecryptor, crate::content::NoOpRenderer);
+        let mut target =
+            TargetState::from_source(&source, &processor, &serde_json::json!({})).unwrap();
+        let dest = AbsPath::new(dest).unwrap();
+        target
+            .add_exact_removals(
+                &source,
+                |path| dest.join(path),
+                |path, _| path.as_path().extension().is_some_and(|ext| ext == "swp"),
+            )
+            .unwrap();
+
+        // Only direct children of exact directories are removed
+        let mut removed: Vec<String> = target
+            .entries()
+            .filter(|e| e.is_removal())
+            .map(|e| e.path().to_string())
+            .collect();
+        removed.sort();
+        assert_eq!(removed, [".empty/file", ".vim/old.vim", ".vim/plugged"]);
+        assert!(
+            target
+                .get(&RelPath::new(".vim/init.vim".into()).unwrap())
+                .is_some()
+        );
+    }
+
+    #[test]
+    fn test_conflict_snapshot_id() {
+        use crate::clock::StateClock;
+
+        let clock = StateClock::fixed(0);
+        let first = clock.begin_run();
+        let second = clock.begin_run();
+
+        let snapshot = ConflictSnapshot::new(".zshrc", b"old".to_vec(), None, &first);
+        let id = snapshot.id();
+        assert_eq!(id.len(), 8);
+        assert!(id.chars().all(|c| c.is_ascii_hexdigit()));
+
+        // Same run and path give the same ID regardless of content
+        assert_eq!(
+            ConflictSnapshot::new(".zshrc", b"other".to_vec(), None, &first).id(),
+            id
+        );
+        assert_ne!(
+            ConflictSnapshot::new(".zshrc", b"old".to_vec(), None, &second).id(),
+            id
+        );
+        assert_ne!(
+            ConflictSnapshot::new(".bashrc", b"old".to_vec(), None, &first).id(),
+            id
+        );
+
+        let bytes = snapshot.to_bytes().unwrap();
+        assert_eq!(ConflictSnapshot::from_bytes(&bytes), Some(snapshot));
+    }
 }
@@ -1,12 +1,416 @@
 //! System abstraction for filesystem operations
 //!
 //! This module provides a trait-based abstraction over filesystem operations,
-//! enabling testing and dry-run mode.
+//! enabling testing and dry-run mode, and the platform-specific parts of
+//! writing permissions and links.
 
+use guisu_config::SymlinkTargets;
 use guisu_core::path::AbsPath;
 use guisu_core::{Error, Result};
 use std::fs::{self, Metadata};
-use std::path::Path;
+use std::io;
+use std::path::{Component, Path, PathBuf};
+
+/// Windows error returned when creating a symlink needs developer mode or elevation
+#[cfg(windows)]
+const ERROR_PRIVILEGE_NOT_HELD: i32 = 1314;
+
+/// Apply permission bits to a path
+///
+/// On Unix the mode is set as is. Windows has no mode bits: a mode without
+/// group and other access, such as that of `private_` files, restricts the
+/// ACL to the current user, and a file mode without write access makes the
+/// file read-only.
+///
+/// # Errors
+///
+/// Returns an error if the permissions or the ACL cannot be changed
+pub fn set_permissions(path: &Path, mode: u32) -> io::Result<()> {
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        fs::set_permissions(path, fs::Permissions::from_mode(mode))
+    }
+
+    #[cfg(windows)]
+    {
+        if mode & 0o077 == 0 {
+            restrict_to_current_user(path)?;
+        }
+        let metadata = fs::metadata(path)?;
+        if metadata.is_file() && mode & 0o222 == 0 {
+            let mut permissions = metadata.permissions();
+            permissions.set_readonly(true);
+            fs::set_permissions(path, permissions)?;
+        }
+        Ok(())
+    }
+}
+
+/// Replace the ACL of a path with full control for the current user only
+#[cfg(windows)]
+fn restrict_to_current_user(path: &Path) -> io::Result<()> {
+    let user = std::env::var("USERNAME")
+        .map_err(|_| io::Error::other("USERNAME is not set, cannot restrict access"))?;
+    let output = std::process::Command::new("icacls")
+        .arg(path)
+        .arg("/inheritance:r")
+        .arg("/grant:r")
+        .arg(format!("{user}:(F)"))
+        .output()?;
+    if output.status.success() {
+        Ok(())
+    } else {
+        Err(io::Error::other(format!(
+            "icacls failed for {}: {}",
+            path.display(),
+            String::from_utf8_lossy(&output.stderr).trim()
+        )))
+    }
+}
+
+/// Create a symbolic link at `link` pointing to `target`
+///
+/// On Windows, a link to a directory falls back to a junction when creating
+/// symlinks is not permitted (developer mode off and not elevated). Links to
+/// files have no such fallback.
+///
+/// # Errors
+///
+/// Returns an error if the link cannot be created
+pub fn create_symlink(target: &Path, link: &Path) -> io::Result<()> {
+    #[cfg(unix)]
+    {
+        std::os::unix::fs::symlink(target, link)
+    }
+
+    #[cfg(windows)]
+    {
+        // Relative targets are resolved against the directory of the link
+        let resolved = link
+            .parent()
+            .map_or_else(|| target.to_path_buf(), |parent| parent.join(target));
+        if !resolved.is_dir() {
+            return std::os::windows::fs::symlink_file(target, link);
+        }
+        match std::os::windows::fs::symlink_dir(target, link) {
+            Err(e) if e.raw_os_error() == Some(ERROR_PRIVILEGE_NOT_HELD) => {
+                create_junction(&resolved, link)
+            }
+            result => result,
+        }
+    }
+}
+
+/// Create a directory junction, which needs no privileges but an absolute target
+#[cfg(windows)]
+fn create_junction(target: &Path, link: &Path) -> io::Result<()> {
+    let output = std::process::Command::new("cmd")
+        .args(["/C", "mklink", "/J"])
+        .arg(link)
+        .arg(target)
+        .output()?;
+    if output.status.success() {
+        Ok(())
+    } else {
+        Err(io::Error::other(format!(
+            "Failed to create junction {}: {}",
+            link.display(),
+            String::from_utf8_lossy(&output.stderr).trim()
+        )))
+    }
+}
+
+/// Read the target of a symlink or junction
+///
+/// Junction targets are returned without their `\\?\` or `\??\` prefix so
+/// that they compare equal to the target they were created with.
+///
+/// # Errors
+///
+/// Returns an error if the path is not a link or cannot be read
+pub fn read_symlink(path: &Path) -> io::Result<PathBuf> {
+    let target = fs::read_link(path)?;
+
+    #[cfg(windows)]
+    {
+        let text = target.to_string_lossy();
+        for prefix in [r"\\?\", r"\??\"] {
+            if let Some(stripped) = text.strip_prefix(prefix) {
+                return Ok(PathBuf::from(stripped));
+            }
+        }
+    }
+
+    Ok(target)
+}
+
+/// Remove a symlink or junction without touching its target
+///
+/// On Windows, links to directories are removed as directories.
+///
+/// # Errors
+///
+/// Returns an error if the link cannot be removed
+pub fn remove_symlink(path: &Path) -> io::Result<()> {
+    #[cfg(windows)]
+    if fs::metadata(path).is_ok_and(|metadata| metadata.is_dir()) {
+        return fs::remove_dir(path);
+    }
+
+    fs::remove_file(path)
+}
+
+/// Rewrite a symlink target as relative or absolute, as `policy` asks
+///
+/// `link` is where the symlink is created. Paths are resolved lexically,
+/// without following links, so `a/..` is taken to be the directory `a` is in.
+/// Targets on another Windows drive than the link stay absolute.
+#[must_use]
+pub fn normalize_link_target(link: &Path, target: &Path, policy: SymlinkTargets) -> PathBuf {
+    let Some(dir) = link.parent() else {
+        return target.to_path_buf();
+    };
+    match policy {
+        SymlinkTargets::Absolute if target.is_relative() => lexical_normalize(&dir.join(target)),
+        SymlinkTargets::Relative if target.is_absolute() => {
+            relative_to(&lexical_normalize(target), &lexical_normalize(dir))
+        }
+        _ => target.to_path_buf(),
+    }
+}
+
+/// Remove `.` components and resolve `..` against the preceding component
+fn lexical_normalize(path: &Path) -> PathBuf {
+    let mut parts: Vec<Component> = Vec::new();
+    for component in path.components() {
+        match component {
+            Component::CurDir => {}
+            Component::ParentDir => match parts.last() {
+                Some(Component::Normal(_)) => {
+                    parts.pop();
+                }
+                // The parent of the root is the root
+                Some(Component::RootDir | Component::Prefix(_)) => {}
+                _ => parts.push(component),
+            },
+            _ => parts.push(component),
+        }
+    }
+    parts.iter().collect()
+}
+
+/// Path of `target` relative to the directory `base`, both absolute and normalized
+fn relative_to(target: &Path, base: &Path) -> PathBuf {
+    let target_parts: Vec<Component> = target.components().collect();
+    let base_parts: Vec<Component> = base.components().collect();
+    // Paths under different roots, such as Windows drives, have no relative form
+    if target_parts.first() != base_parts.first() {
+        return target.to_path_buf();
+    }
+
+    let common = target_parts
+        .iter()
+        .zip(&base_parts)
+        .take_while(|(a, b)| a == b)
+        .count();
+    let mut relative: PathBuf =
+        std::iter::repeat_n(Component::ParentDir, base_parts.len() - common).collect();
+    relative.extend(&target_parts[common..]);
+    if relative.as_os_str().is_empty() {
+        relative.push(".");
+    }
+    relative
+}
+
+/// Extended attribute macOS sets on downloaded files, removed when preserving
+pub const QUARANTINE_XATTR: &str = "com.apple.quarantine";
+
+/// macOS flags that make a file unwritable (`uchg`, `uappnd`, `schg`, `sappnd`)
+#[cfg(target_os = "macos")]
+const LOCK_FLAGS: u32 = 0x0000_0002 | 0x0000_0004 | 0x0002_0000 | 0x0004_0000;
+
+/// Extended attributes and file flags of a file, kept across a rewrite
+///
+/// Captured before guisu replaces a destination and restored afterwards, so
+/// that files whose behavior depends on them keep working. The quarantine
+/// attribute is dropped instead of restored. Extended attributes are
+/// supported on Linux and macOS and file flags on macOS; elsewhere nothing is
+/// captured.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct PreservedAttrs {
+    /// Extended attribute names and values
+    xattrs: Vec<(String, Vec<u8>)>,
+    /// File flags (`st_flags`), zero where unsupported
+    flags: u32,
+}
+
+impl PreservedAttrs {
+    /// Capture the extended attributes and flags of an existing file
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the attributes cannot be read
+    pub fn capture(path: &Path) -> io::Result<Self> {
+        Ok(Self {
+            xattrs: xattrs::read(path)?,
+            flags: file_flags(path)?,
+        })
+    }
+
+    /// Extended attribute names and values
+    #[must_use]
+    pub fn xattrs(&self) -> &[(String, Vec<u8>)] {
+        &self.xattrs
+    }
+
+    /// Clear flags that keep the file from being rewritten, such as `uchg`
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the flags cannot be changed
+    pub fn unlock(&self, path: &Path) -> io::Result<()> {
+        #[cfg(target_os = "macos")]
+        if self.flags & LOCK_FLAGS != 0 {
+            return set_file_flags(path, self.flags & !LOCK_FLAGS);
+        }
+
+        let _ = path;
+        Ok(())
+    }
+
+    /// Restore the captured attributes and flags on the rewritten file
+    ///
+    /// Attributes the file still has are overwritten and the quarantine
+    /// attribute is removed.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if an attribute or the flags cannot be set
+    pub fn restore(&self, path: &Path) -> io::Result<()> {
+        for (name, value) in &self.xattrs {
+            if name != QUARANTINE_XATTR {
+                xattrs::write(path, name, value)?;
+            }
+        }
+        xattrs::remove(path, QUARANTINE_XATTR)?;
+
+        #[cfg(target_os = "macos")]
+        if file_flags(path)? != self.flags {
+            return set_file_flags(path, self.flags);
+        }
+
+        Ok(())
+    }
+}
+
+/// File flags of a path, zero where unsupported
+#[cfg_attr(not(target_os = "macos"), allow(clippy::unnecessary_wraps))]
+fn file_flags(path: &Path) -> io::Result<u32> {
+    #[cfg(target_os = "macos")]
+    {
+        use std::os::macos::fs::MetadataExt;
+        Ok(fs::metadata(path)?.st_flags())
+    }
+
+    #[cfg(not(target_os = "macos"))]
+    {
+        let _ = path;
+        Ok(0)
+    }
+}
+
+/// Set the file flags of a path with `chflags`, which takes them in octal
+#[cfg(target_os = "macos")]
+fn set_file_flags(path: &Path, flags: u32) -> io::Result<()> {
+    let output = std::process::Command::new("chflags")
+        .arg(format!("{flags:o}"))
+        .arg(path)
+        .output()?;
+    if output.status.success() {
+        Ok(())
+    } else {
+        Err(io::Error::other(format!(
+            "chflags failed for {}: {}",
+            path.display(),
+            String::from_utf8_lossy(&output.stderr).trim()
+        )))
+    }
+}
+
+/// Extended attribute backend for Linux and macOS
+#[cfg(any(target_os = "linux", target_os = "macos"))]
+mod xattrs {
+    use rustix::fs::XattrFlags;
+    use rustix::io::Errno;
+    use std::io;
+    use std::path::Path;
+
+    /// Whether an error means the filesystem has no extended attributes
+    fn unsupported(e: Errno) -> bool {
+        e == Errno::NOTSUP || e == Errno::OPNOTSUPP
+    }
+
+    pub(super) fn read(path: &Path) -> io::Result<Vec<(String, Vec<u8>)>> {
+        let len = match rustix::fs::listxattr(path, &mut [0_u8; 0][..]) {
+            Ok(len) => len,
+            Err(e) if unsupported(e) => return Ok(Vec::new()),
+            Err(e) => return Err(e.into()),
+        };
+        let mut names = vec![0; len];
+        let len = rustix::fs::listxattr(path, &mut names[..])?;
+        names.truncate(len);
+
+        let mut xattrs = Vec::new();
+        for name in names.split(|&b| b == 0).filter(|name| !name.is_empty()) {
+            let name = String::from_utf8_lossy(name).into_owned();
+            let len = rustix::fs::getxattr(path, name.as_str(), &mut [0_u8; 0][..])?;
+            let mut value = vec![0; len];
+            let len = rustix::fs::getxattr(path, name.as_str(), &mut value[..])?;
+            value.truncate(len);
+            xattrs.push((name, value));
+        }
+        Ok(xattrs)
+    }
+
+    pub(super) fn write(path: &Path, name: &str, value: &[u8]) -> io::Result<()> {
+        Ok(rustix::fs::setxattr(
+            path,
+            name,
+            value,
+            XattrFlags::empty(),
+        )?)
+    }
+
+    pub(super) fn remove(path: &Path, name: &str) -> io::Result<()> {
+        match rustix::fs::removexattr(path, name) {
+            Err(e) if e == Errno::NODATA || unsupported(e) => Ok(()),
+            result => Ok(result?),
+        }
+    }
+}
+
+/// Platforms without extended attributes
+#[cfg(not(any(target_os = "linux", target_os = "macos")))]
+mod xattrs {
+    use std::io;
+    use std::path::Path;
+
+    #[allow(clippy::unnecessary_wraps)]
+    pub(super) fn read(_path: &Path) -> io::Result<Vec<(String, Vec<u8>)>> {
+        Ok(Vec::new())
+    }
+
+    #[allow(clippy::unnecessary_wraps)]
+    pub(super) fn write(_path: &Path, _name: &str, _value: &[u8]) -> io::Result<()> {
+        Ok(())
+    }
+
+    #[allow(clippy::unnecessary_wraps)]
+    pub(super) fn remove(_path: &Path, _name: &str) -> io::Result<()> {
+        Ok(())
+    }
+}
 
 /// Abstraction over filesystem operations
 ///
@@ -108,11 +512,8 @@ impl System for RealSystem {
         })?;
 
         // Set permissions if specified
-        #[cfg(unix)]
         if let Some(mode) = mode {
-            use std::os::unix::fs::PermissionsExt;
-            let permissions = fs::Permissions::from_mode(mode);
-            fs::set_permissions(path.as_path(), permissions).map_err(|e| Error::FileWrite {
+            set_permissions(path.as_path(), mode).map_err(|e| Error::FileWrite {
                 path: path.as_path().to_path_buf(),
                 source: e,
             })?;
@@ -128,15 +529,10 @@ impl System for RealSystem {
         })?;
 
         // Set permissions if specified
-        #[cfg(unix)]
         if let Some(mode) = mode {
-            use std::os::unix::fs::PermissionsExt;
-            let permissions = fs::Permissions::from_mode(mode);
-            fs::set_permissions(path.as_path(), permissions).map_err(|e| {
-                Error::DirectoryCreate {
-                    path: path.as_path().to_path_buf(),
-                    source: e,
-                }
+            set_permissions(path.as_path(), mode).map_err(|e| Error::DirectoryCreate {
+                path: path.as_path().to_path_buf(),
+                source: e,
             })?;
         }
 
@@ -150,15 +546,10 @@ impl System for RealSystem {
         })?;
 
         // Set permissions if specified
-        #[cfg(unix)]
         if let Some(mode) = mode {
-            use std::os::unix::fs::PermissionsExt;
-            let permissions = fs::Permissions::from_mode(mode);
-            fs::set_permissions(path.as_path(), permissions).map_err(|e| {
-                Error::DirectoryCreate {
-                    path: path.as_path().to_path_buf(),
-                    source: e,
-                }
+            set_permissions(path.as_path(), mode).map_err(|e| Error::DirectoryCreate {
+                path: path.as_path().to_path_buf(),
+                source: e,
             })?;
         }
 
@@ -190,24 +581,11 @@ impl System for RealSystem {
     }
 
     fn symlink(&self, target: &Path, link: &AbsPath) -> Result<()> {
-        #[cfg(unix)]
-        {
-            std::os::unix::fs::symlink(target, link.as_path()).map_err(Error::Io)
-        }
-
-        #[cfg(windows)]
-        {
-            // On Windows, we need to check if target is a dir or file
-            if target.is_dir() {
-                std::os::windows::fs::symlink_dir(target, link.as_path()).map_err(|e| Error::Io(e))
-            } else {
-                std::os::windows::fs::symlink_file(target, link.as_path()).map_err(|e| Error::Io(e))
-            }
-        }
+        create_symlink(target, link.as_path()).map_err(Error::Io)
     }
 
     fn read_link(&self, path: &AbsPath) -> Result<std::path::PathBuf> {
-        fs::read_link(path.as_path()).map_err(Error::Io)
+        read_symlink(path.as_path()).map_err(Error::Io)
     }
 }
 
@@ -344,3 +722,43 @@ impl System for DryRunSystem {
         Ok(std::path::PathBuf::new())
     }
 }
+
+#[cfg(all(test, unix))]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_normalize_link_target() {
+        let link = Path::new("/home/me/.config/nvim");
+        let cases = [
+            (
+                "/home/me/dotfiles/nvim",
+                SymlinkTargets::Relative,
+                "../dotfiles/nvim",
+            ),
+            ("/home/me/.config/./x/../y", SymlinkTargets::Relative, "y"),
+            ("/home/me/.config", SymlinkTargets::Relative, "."),
+            ("/etc/hosts", SymlinkTargets::Relative, "../../../etc/hosts"),
+            (
+                "../dotfiles/nvim",
+                SymlinkTargets::Relative,
+                "../dotfiles/nvim",
+            ),
+            (
+                "../dotfiles/nvim",
+                SymlinkTargets::Absolute,
+                "/home/me/dotfiles/nvim",
+            ),
+            ("../../../../etc", SymlinkTargets::Absolute, "/etc"),
+            ("/etc/hosts", SymlinkTargets::Absolute, "/etc/hosts"),
+            ("../dotfiles/nvim", SymlinkTargets::Keep, "../dotfiles/nvim"),
+        ];
+        for (target, policy, expected) in cases {
+            assert_eq!(
+                normalize_link_target(link, Path::new(target), policy),
+                Path::new(expected),
+                "{target} with {policy:?}"
+            );
+        }
+    }
+}
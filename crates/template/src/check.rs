@@ -0,0 +1,343 @@
+//! Checking templates without rendering them
+//!
+//! [`TemplateEngine::check`] parses a template and reports syntax errors,
+//! variables missing from the context, filters the engine doesn't have, and
+//! calls to deprecated functions. Nothing is rendered, so no function with
+//! side effects (commands, password managers, prompts) runs.
+//!
+//! Variables, filters, and functions are found by scanning the template's
+//! tags, so line numbers point at the first use of a name.
+
+use crate::directive::Delimiters;
+use crate::{TemplateContext, TemplateEngine};
+use regex::Regex;
+use serde::Serialize;
+use std::collections::BTreeSet;
+use std::sync::LazyLock;
+
+/// Functions that still work but have a replacement, with that replacement
+pub const DEPRECATED_FUNCTIONS: &[(&str, &str)] = &[("home_dir", "homeDir")];
+
+/// Cached pattern of a filter applied with `|`
+static FILTER_USE: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"\|\s*([A-Za-z_][A-Za-z0-9_]*)").expect("Valid regex"));
+
+/// Cached pattern of a `{% filter name %}` block
+static FILTER_BLOCK: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r"^[-+]?\s*filter\s+([A-Za-z_][A-Za-z0-9_]*)").expect("Valid regex")
+});
+
+/// Cached pattern of a function call
+static FUNCTION_CALL: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"([A-Za-z_.][A-Za-z0-9_]*)\s*\(").expect("Valid regex"));
+
+/// Kind of a problem found in a template
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum IssueKind {
+    /// The template does not parse
+    Syntax,
+    /// A variable is neither in the context nor set by the template
+    UndefinedVariable,
+    /// A filter the engine doesn't have
+    UnknownFilter,
+    /// A function with a replacement
+    DeprecatedFunction,
+}
+
+impl std::fmt::Display for IssueKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            Self::Syntax => "syntax error",
+            Self::UndefinedVariable => "undefined variable",
+            Self::UnknownFilter => "unknown filter",
+            Self::DeprecatedFunction => "deprecated function",
+        })
+    }
+}
+
+/// Problem found in a template
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Serialize)]
+pub struct Issue {
+    /// Line of the problem, 1-based, if known
+    pub line: Option<usize>,
+    /// What kind of problem it is
+    pub kind: IssueKind,
+    /// Description of the problem
+    pub message: String,
+}
+
+impl TemplateEngine {
+    /// Check a template against `context` without rendering it
+    ///
+    /// Returns the problems found, ordered by line. A template with a syntax
+    /// error is not checked further.
+    #[must_use]
+    pub fn check(&self, name: &str, source: &str, context: &TemplateContext) -> Vec<Issue> {
+        let syntax_error = |line, message: String| {
+            vec![Issue {
+                line,
+                kind: IssueKind::Syntax,
+                message,
+            }]
+        };
+        let (source, delimiters) = match crate::directive::parse(source) {
+            Ok(parsed) => parsed,
+            Err(e) => return syntax_error(None, e.to_string()),
+        };
+        let delimiters = delimiters.unwrap_or_default();
+
+        // Parsing doesn't depend on the registered functions, so a plain
+        // environment borrowing the source will do
+        let mut parser = minijinja::Environment::new();
+        parser.set_trim_blocks(true);
+        parser.set_lstrip_blocks(true);
+        match delimiters.syntax() {
+            Ok(syntax) => parser.set_syntax(syntax),
+            Err(e) => return syntax_error(None, e.to_string()),
+        }
+        let source = source.as_ref();
+        let template = match parser.template_from_named_str(name, source) {
+            Ok(template) => template,
+            Err(e) => {
+                return syntax_error(e.line(), e.detail().unwrap_or("invalid syntax").to_string());
+            }
+        };
+
+        let tags = tags(source, &delimiters);
+        let mut issues = Vec::new();
+
+        let known: BTreeSet<String> = self
+            .env()
+            .globals()
+            .map(|(name, _)| name.to_string())
+            .chain(context_names(context))
+            .collect();
+        let mut undefined: Vec<String> = template
+            .undeclared_variables(false)
+            .into_iter()
+            .filter(|name| !known.contains(name))
+            .collect();
+        undefined.sort();
+        for name in undefined {
+            issues.push(Issue {
+                line: first_use(source, &tags, &name),
+                kind: IssueKind::UndefinedVariable,
+                message: format!("'{name}' is not defined"),
+            });
+        }
+
+        let mut seen_filters = BTreeSet::new();
+        for (offset, tag) in &tags {
+            let filters = FILTER_USE
+                .captures_iter(tag)
+                .chain(FILTER_BLOCK.captures_iter(tag))
+                .filter_map(|captures| captures.get(1));
+            for filter in filters {
+                let name = filter.as_str();
+                if seen_filters.insert(name.to_string()) && !is_filter(self.env(), name) {
+                    issues.push(Issue {
+                        line: Some(line_of(source, offset + filter.start())),
+                        kind: IssueKind::UnknownFilter,
+                        message: format!("'{name}' is not a filter"),
+                    });
+                }
+            }
+
+            for call in FUNCTION_CALL
+                .captures_iter(tag)
+                .filter_map(|captures| captures.get(1))
+            {
+                if let Some((old, new)) = DEPRECATED_FUNCTIONS
+                    .iter()
+                    .find(|(old, _)| *old == call.as_str())
+                {
+                    issues.push(Issue {
+                        line: Some(line_of(source, offset + call.start())),
+                        kind: IssueKind::DeprecatedFunction,
+                        message: format!("{old}() is deprecated, use {new}() instead"),
+                    });
+                }
+            }
+        }
+
+        issues.sort();
+        issues
+    }
+}
+
+/// Check if `env` has a filter called `name`
+pub(crate) fn is_filter(env: &minijinja::Environment<'_>, name: &str) -> bool {
+    env.compile_expression("name is filter")
+        .and_then(|expression| expression.eval(minijinja::context! { name }))
+        .is_ok_and(|value| value.is_true())
+}
+
+/// Top-level names the context provides
+fn context_names(context: &TemplateContext) -> Vec<String> {
+    match serde_json::to_value(context) {
+        Ok(serde_json::Value::Object(map)) => map.keys().cloned().collect(),
+        _ => Vec::new(),
+    }
+}
+
+/// Line of the byte at `offset`, 1-based
+fn line_of(source: &str, offset: usize) -> usize {
+    source[..offset].matches('\n').count() + 1
+}
+
+/// First line where `name` is used as a variable
+fn first_use(source: &str, tags: &[(usize, String)], name: &str) -> Option<usize> {
+    let pattern = Regex::new(&format!(r"(?:^|[^A-Za-z0-9_.]){}\b", regex::escape(name))).ok()?;
+    tags.iter().find_map(|(offset, tag)| {
+        // The match may include the character before the name
+        let found = pattern.find(tag)?;
+        Some(line_of(source, offset + found.end() - name.len()))
+    })
+}
+
+/// Code of the expression and statement tags, with the offset each starts at
+///
+/// Comments and raw blocks are left out, and string literals are blanked so
+/// their contents aren't taken for code. Offsets stay valid in the blanked
+/// text.
+fn tags(source: &str, delimiters: &Delimiters) -> Vec<(usize, String)> {
+    let kinds = [
+        (&delimiters.variable, false),
+        (&delimiters.block, true),
+        (&delimiters.comment, false),
+    ];
+    let mut tags = Vec::new();
+    let mut rest = 0;
+    // The earliest opening delimiter, the longest one if several start there
+    while let Some((start, (open, close), is_block)) = kinds
+        .iter()
+        .filter_map(|((open, close), is_block)| {
+            source[rest..]
+                .find(open.as_str())
+                .map(|i| (rest + i, (open, close), *is_block))
+        })
+        .min_by_key(|(start, (open, _), _)| (*start, std::cmp::Reverse(open.len())))
+    {
+        let body_start = start + open.len();
+        let Some(body_end) = source[body_start..]
+            .find(close.as_str())
+            .map(|i| body_start + i)
+        else {
+            break;
+        };
+        rest = body_end + close.len();
+        if open == &delimiters.comment.0 {
+            continue;
+        }
+
+        let body = blank_strings(&source[body_start..body_end]);
+        if is_block && body.trim_matches(['-', '+', ' ', '\t', '\n']) == "raw" {
+            // Skip to the end of the raw block
+            let Some(end) = source[rest..].find("endraw") else {
+                break;
+            };
+            rest += end;
+            continue;
+        }
+        tags.push((body_start, body));
+    }
+    tags
+}
+
+/// Replace the contents of string literals with spaces, keeping offsets
+fn blank_strings(code: &str) -> String {
+    let mut blanked = String::with_capacity(code.len());
+    let mut quote = None;
+    let mut escaped = false;
+    for c in code.chars() {
+        if let Some(q) = quote {
+            if escaped {
+                escaped = false;
+            } else if c == '\\' {
+                escaped = true;
+            } else if c == q {
+                quote = None;
+                blanked.push(c);
+                continue;
+            }
+            // Keep the byte length of multi-byte characters
+            blanked.extend(std::iter::repeat_n(' ', c.len_utf8()));
+        } else {
+            if c == '"' || c == '\'' {
+                quote = Some(c);
+            }
+            blanked.push(c);
+        }
+    }
+    blanked
+}
+
+#[cfg(test)]
+mod tests {
+    #![allow(clippy::unwrap_used, clippy::panic)]
+    use super::*;
+
+    fn check(source: &str) -> Vec<(Option<usize>, IssueKind)> {
+        let context = TemplateContext::new()
+            .with_variables([("email".to_string(), serde_json::json!("me@example.com"))].into());
+        TemplateEngine::new()
+            .check("test.j2", source, &context)
+            .into_iter()
+            .map(|issue| (issue.line, issue.kind))
+            .collect()
+    }
+
+    #[test]
+    fn test_check_clean_template() {
+        let source = "{% set name = \"x\" %}\n\
+                      {{ email | quote }} {{ name | upper }} {{ system.os }}\n\
+                      {% for path in glob(\"*.lua\") %}{{ include(path) | blake3sum }}{% endfor %}\n\
+                      {{ \"a | nofilter\" }} {# {{ missing }} #}\n\
+                      {% raw %}{{ missing | nofilter }}{% endraw %}\n";
+        assert_eq!(check(source), []);
+    }
+
+    #[test]
+    fn test_check_reports_issues_with_lines() {
+        let source = "# header\n\
+                      {{ email | nofilter }}\n\
+                      {% if editor %}{{ home_dir() }}{% endif %}\n\
+                      {%- filter shout %}x{% endfilter %}\n";
+        assert_eq!(
+            check(source),
+            [
+                (Some(2), IssueKind::UnknownFilter),
+                (Some(3), IssueKind::UndefinedVariable),
+                (Some(3), IssueKind::DeprecatedFunction),
+                (Some(4), IssueKind::UnknownFilter),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_check_custom_delimiters() {
+        let source = "# guisu:template:variable-delimiters=\"[[ ]]\"\n\
+                      image: {{ .Values.image }}\n\
+                      email: [[ email | nofilter ]] [[ home_dir() ]]\n";
+        assert_eq!(
+            check(source),
+            [
+                (Some(3), IssueKind::UnknownFilter),
+                (Some(3), IssueKind::DeprecatedFunction),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_check_syntax_error() {
+        let issues = TemplateEngine::new().check(
+            "test.j2",
+            "line 1\n{% if x %}\nunclosed\n",
+            &TemplateContext::new(),
+        );
+        assert_eq!(issues.len(), 1);
+        assert_eq!(issues[0].kind, IssueKind::Syntax);
+        assert!(issues[0].line.is_some());
+    }
+}
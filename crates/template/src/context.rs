@@ -46,6 +46,10 @@ pub struct GuisuInfo {
     #[serde(rename = "rootEntry")]
     pub root_entry: String,
 
+    /// Active profile from `.guisu/profiles.toml`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub profile: Option<String>,
+
     /// Configuration object (exposed to templates)
     #[serde(skip_serializing_if = "Option::is_none")]
     pub config: Option<ConfigInfo>,
@@ -73,6 +77,23 @@ pub struct SystemInfo {
     #[serde(rename = "distroVersion")]
     pub distro_version: String,
 
+    /// Kernel release (e.g., "6.8.0-45-generic", "23.6.0")
+    /// Empty string on Windows
+    pub kernel: String,
+
+    /// Whether running under Windows Subsystem for Linux
+    pub wsl: bool,
+
+    /// Container runtime (e.g., "docker", "podman", "lxc")
+    /// Empty string outside containers
+    pub container: String,
+
+    /// Number of CPUs available to this process
+    pub cpus: usize,
+
+    /// Whether the session was started over SSH
+    pub ssh: bool,
+
     /// Architecture (e.g., "`x86_64`", "aarch64")
     pub arch: String,
 
@@ -142,6 +163,7 @@ impl TemplateContext {
             working_tree,
             dst_dir,
             root_entry,
+            profile: None,
             config: None,
         });
         self
@@ -189,11 +211,23 @@ impl TemplateContext {
             working_tree,
             dst_dir,
             root_entry,
+            profile: None,
             config: Some(config),
         });
         self
     }
 
+    /// Set the active profile, exposed as `guisu.profile`
+    ///
+    /// Has no effect unless guisu information was set first.
+    #[must_use]
+    pub fn with_profile(mut self, profile: Option<String>) -> Self {
+        if let Some(guisu) = &mut self.guisu {
+            guisu.profile = profile;
+        }
+        self
+    }
+
     /// Add a custom variable
     pub fn add_variable(&mut self, key: String, value: serde_json::Value) {
         self.variables.insert(key, value);
@@ -271,6 +305,11 @@ impl SystemInfo {
             distro,
             distro_id,
             distro_version,
+            kernel: Self::detect_kernel(),
+            wsl: Self::detect_wsl(),
+            container: Self::detect_container(),
+            cpus: Self::detect_cpus(),
+            ssh: Self::detect_ssh(),
             arch: Self::detect_arch(),
             hostname: Self::detect_hostname(),
             username: Self::detect_username(),
@@ -310,6 +349,83 @@ impl SystemInfo {
         env::consts::ARCH.to_string()
     }
 
+    fn detect_kernel() -> String {
+        #[cfg(unix)]
+        {
+            rustix::system::uname()
+                .release()
+                .to_string_lossy()
+                .into_owned()
+        }
+
+        #[cfg(not(unix))]
+        {
+            String::new()
+        }
+    }
+
+    /// Detect WSL from its interop handler, falling back to the kernel release
+    fn detect_wsl() -> bool {
+        #[cfg(target_os = "linux")]
+        {
+            std::path::Path::new("/proc/sys/fs/binfmt_misc/WSLInterop").exists()
+                || Self::is_wsl_kernel(&Self::detect_kernel())
+        }
+
+        #[cfg(not(target_os = "linux"))]
+        {
+            false
+        }
+    }
+
+    /// WSL kernels carry "microsoft" in their release (e.g., "5.15.153.1-microsoft-standard-WSL2")
+    #[cfg(target_os = "linux")]
+    fn is_wsl_kernel(release: &str) -> bool {
+        release.to_ascii_lowercase().contains("microsoft")
+    }
+
+    /// Detect the container runtime from the marker files Docker and Podman
+    /// create, or from the `container` variable systemd-nspawn, LXC and Podman set
+    fn detect_container() -> String {
+        #[cfg(target_os = "linux")]
+        {
+            Self::container_from(
+                std::path::Path::new("/.dockerenv").exists(),
+                std::path::Path::new("/run/.containerenv").exists(),
+                env::var("container").ok(),
+            )
+        }
+
+        #[cfg(not(target_os = "linux"))]
+        {
+            String::new()
+        }
+    }
+
+    #[cfg(target_os = "linux")]
+    fn container_from(dockerenv: bool, containerenv: bool, variable: Option<String>) -> String {
+        if let Some(runtime) = variable.filter(|runtime| !runtime.is_empty()) {
+            runtime
+        } else if containerenv {
+            "podman".to_string()
+        } else if dockerenv {
+            "docker".to_string()
+        } else {
+            String::new()
+        }
+    }
+
+    fn detect_cpus() -> usize {
+        std::thread::available_parallelism().map_or(1, std::num::NonZeroUsize::get)
+    }
+
+    /// SSH sets these for remote sessions; `sudo` and `su` usually keep them
+    fn detect_ssh() -> bool {
+        ["SSH_CONNECTION", "SSH_CLIENT", "SSH_TTY"]
+            .iter()
+            .any(|name| env::var_os(name).is_some_and(|value| !value.is_empty()))
+    }
+
     fn detect_hostname() -> String {
         hostname::get()
             .ok()
@@ -517,6 +633,26 @@ mod tests {
         assert!(guisu.config.is_none());
     }
 
+    #[test]
+    fn test_with_profile() {
+        let ctx = TemplateContext::new()
+            .with_guisu_info(
+                "/source".to_string(),
+                "/working".to_string(),
+                "/dest".to_string(),
+                "home".to_string(),
+            )
+            .with_profile(Some("work".to_string()));
+
+        assert_eq!(ctx.guisu.unwrap().profile.as_deref(), Some("work"));
+        assert!(
+            TemplateContext::new()
+                .with_profile(Some("work".to_string()))
+                .guisu
+                .is_none()
+        );
+    }
+
     #[test]
     fn test_with_guisu_info_and_config() {
         let config_info = crate::info::ConfigInfo {
@@ -698,6 +834,52 @@ mod tests {
         assert_eq!(SystemInfo::unquote("  \"Debian\"  "), "Debian");
     }
 
+    #[test]
+    #[cfg(target_os = "linux")]
+    fn test_is_wsl_kernel() {
+        assert!(SystemInfo::is_wsl_kernel(
+            "5.15.153.1-microsoft-standard-WSL2"
+        ));
+        assert!(SystemInfo::is_wsl_kernel("4.4.0-19041-Microsoft"));
+        assert!(!SystemInfo::is_wsl_kernel("6.8.0-45-generic"));
+    }
+
+    #[test]
+    #[cfg(target_os = "linux")]
+    fn test_container_from() {
+        assert_eq!(SystemInfo::container_from(true, false, None), "docker");
+        assert_eq!(SystemInfo::container_from(true, true, None), "podman");
+        assert_eq!(
+            SystemInfo::container_from(false, false, Some("lxc".to_string())),
+            "lxc"
+        );
+        assert_eq!(
+            SystemInfo::container_from(false, false, Some(String::new())),
+            ""
+        );
+        assert_eq!(SystemInfo::container_from(false, false, None), "");
+    }
+
+    #[test]
+    fn test_detect_ssh() {
+        temp_env::with_vars(
+            [
+                ("SSH_CONNECTION", Some("10.0.0.1 50000 10.0.0.2 22")),
+                ("SSH_CLIENT", None),
+                ("SSH_TTY", None),
+            ],
+            || assert!(SystemInfo::detect_ssh()),
+        );
+        temp_env::with_vars(
+            [
+                ("SSH_CONNECTION", None::<&str>),
+                ("SSH_CLIENT", None),
+                ("SSH_TTY", None),
+            ],
+            || assert!(!SystemInfo::detect_ssh()),
+        );
+    }
+
     #[test]
     #[cfg(target_os = "linux")]
     fn test_detect_distro() {
@@ -757,6 +939,7 @@ mod tests {
             working_tree: "/work".to_string(),
             dst_dir: "/dest".to_string(),
             root_entry: "home".to_string(),
+            profile: None,
             config: None,
         };
 
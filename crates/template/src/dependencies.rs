@@ -0,0 +1,116 @@
+//! Files a template depends on
+//!
+//! `include()` and `includeTemplate()` read files outside the template
+//! itself, so a cached render is only valid while those files are unchanged.
+//! [`track_dependencies`] collects the files read during a render together
+//! with the blake3 hash of the content that was read.
+//!
+//! Password managers, commands, and directory listings can return something
+//! else on the next run without any file changing. Functions reading them
+//! mark the render as external, and such renders have no dependencies that
+//! could prove them current.
+//!
+//! Renders run on a single thread, so the files are collected per thread.
+
+use std::cell::RefCell;
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+
+/// Files read by include functions, with the hash of the content read
+pub type Dependencies = BTreeMap<PathBuf, [u8; 32]>;
+
+/// What the render being tracked read
+#[derive(Default)]
+struct Tracked {
+    files: Dependencies,
+    /// Whether a value came from outside the files, e.g. a password manager
+    external: bool,
+}
+
+thread_local! {
+    /// Dependencies of the render being tracked on this thread, if any
+    static TRACKED: RefCell<Option<Tracked>> = const { RefCell::new(None) };
+}
+
+/// Run `render`, returning its result and the files it included
+///
+/// The files are `None` if the render looked up external values, so its
+/// output can change while its inputs stay the same. Nested calls each see
+/// only what was read within them.
+pub fn track_dependencies<T>(render: impl FnOnce() -> T) -> (T, Option<Dependencies>) {
+    let outer = TRACKED.with(|tracked| tracked.replace(Some(Tracked::default())));
+    let result = render();
+    let inner = TRACKED
+        .with(|tracked| tracked.replace(outer))
+        .unwrap_or_default();
+
+    // What a nested render read is a dependency of this one too
+    TRACKED.with(|tracked| {
+        if let Some(outer) = tracked.borrow_mut().as_mut() {
+            outer
+                .files
+                .extend(inner.files.iter().map(|(path, hash)| (path.clone(), *hash)));
+            outer.external |= inner.external;
+        }
+    });
+    (result, (!inner.external).then_some(inner.files))
+}
+
+/// Record that the current render read `content` from `path`
+pub(crate) fn record(path: &Path, content: &[u8]) {
+    TRACKED.with(|tracked| {
+        if let Some(tracked) = tracked.borrow_mut().as_mut() {
+            tracked
+                .files
+                .insert(path.to_path_buf(), *blake3::hash(content).as_bytes());
+        }
+    });
+}
+
+/// Record that the current render looked up a value outside the files
+pub(crate) fn record_external() {
+    TRACKED.with(|tracked| {
+        if let Some(tracked) = tracked.borrow_mut().as_mut() {
+            tracked.external = true;
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    #![allow(clippy::unwrap_used, clippy::panic)]
+    use super::*;
+
+    #[test]
+    fn test_track_dependencies() {
+        // Nothing is recorded outside a tracked render
+        record(Path::new("/untracked"), b"x");
+
+        let ((), outer) = track_dependencies(|| {
+            record(Path::new("/a"), b"a");
+            let ((), inner) = track_dependencies(|| record(Path::new("/b"), b"b"));
+            assert_eq!(inner.unwrap().keys().collect::<Vec<_>>(), [Path::new("/b")]);
+        });
+
+        let outer = outer.unwrap();
+        assert_eq!(
+            outer.keys().collect::<Vec<_>>(),
+            [Path::new("/a"), Path::new("/b")]
+        );
+        assert_eq!(outer[Path::new("/a")], *blake3::hash(b"a").as_bytes());
+    }
+
+    #[test]
+    fn test_track_external_lookups() {
+        record_external();
+
+        let ((), plain) = track_dependencies(|| record(Path::new("/a"), b"a"));
+        assert!(plain.is_some());
+
+        let ((), outer) = track_dependencies(|| {
+            let ((), inner) = track_dependencies(record_external);
+            assert!(inner.is_none());
+        });
+        assert!(outer.is_none());
+    }
+}
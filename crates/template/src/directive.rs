@@ -0,0 +1,182 @@
+//! Per-file template options
+//!
+//! Files whose own syntax clashes with `{{ }}` (Go templates, Helm charts,
+//! other Jinja tools) can change the delimiters with a directive line:
+//!
+//! ```text
+//! # guisu:template:variable-delimiters="[[ ]]" block-delimiters="[% %]"
+//! ```
+//!
+//! `variable-delimiters`, `block-delimiters`, and `comment-delimiters` each
+//! take an opening and a closing delimiter; the ones not named keep their
+//! default. The directive line can be anywhere in the file and is left out of
+//! the output. It is replaced by an empty comment spanning its line break, so
+//! line numbers in errors stay the same.
+//!
+//! Parts that must not be processed at all go in a raw block written with the
+//! block delimiters in use, such as `[% raw %]{{ .Values.name }}[% endraw %]`.
+
+use crate::{Error, Result};
+use minijinja::syntax::SyntaxConfig;
+use regex::Regex;
+use std::borrow::Cow;
+use std::sync::LazyLock;
+
+/// Marker of a directive line
+const DIRECTIVE: &str = "guisu:template:";
+
+/// Cached pattern of a `key=value` option, the value optionally quoted
+static OPTION: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r#"([A-Za-z-]+)=(?:"([^"]*)"|(\S+))"#).expect("Valid regex"));
+
+/// Delimiters of a template
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Delimiters {
+    /// Opening and closing delimiters of statements, `{% %}` by default
+    pub block: (String, String),
+    /// Opening and closing delimiters of expressions, `{{ }}` by default
+    pub variable: (String, String),
+    /// Opening and closing delimiters of comments, `{# #}` by default
+    pub comment: (String, String),
+}
+
+impl Default for Delimiters {
+    fn default() -> Self {
+        let pair = |open: &str, close: &str| (open.to_string(), close.to_string());
+        Self {
+            block: pair("{%", "%}"),
+            variable: pair("{{", "}}"),
+            comment: pair("{#", "#}"),
+        }
+    }
+}
+
+impl Delimiters {
+    /// Syntax configuration using these delimiters
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the delimiters are empty or clash with each other
+    pub fn syntax(&self) -> Result<SyntaxConfig> {
+        SyntaxConfig::builder()
+            .block_delimiters(self.block.0.clone(), self.block.1.clone())
+            .variable_delimiters(self.variable.0.clone(), self.variable.1.clone())
+            .comment_delimiters(self.comment.0.clone(), self.comment.1.clone())
+            .build()
+            .map_err(|e| Error::Syntax(format!("Invalid template delimiters: {e}")))
+    }
+}
+
+/// Apply the directives of `template`
+///
+/// Returns the template with its directive lines replaced, and the
+/// delimiters it asks for, if it has directives.
+///
+/// # Errors
+///
+/// Returns an error if a directive has an unknown option or a value that
+/// isn't an opening and a closing delimiter
+pub fn parse(template: &str) -> Result<(Cow<'_, str>, Option<Delimiters>)> {
+    if !template.contains(DIRECTIVE) {
+        return Ok((Cow::Borrowed(template), None));
+    }
+
+    let mut delimiters = Delimiters::default();
+    for (index, line) in template.lines().enumerate() {
+        let Some(start) = line.find(DIRECTIVE) else {
+            continue;
+        };
+        for option in OPTION.captures_iter(&line[start + DIRECTIVE.len()..]) {
+            let key = &option[1];
+            let value = option
+                .get(2)
+                .or_else(|| option.get(3))
+                .map_or("", |m| m.as_str());
+            let target = match key {
+                "block-delimiters" => &mut delimiters.block,
+                "variable-delimiters" => &mut delimiters.variable,
+                "comment-delimiters" => &mut delimiters.comment,
+                _ => {
+                    return Err(Error::Syntax(format!(
+                        "Unknown template option '{key}' on line {}",
+                        index + 1
+                    )));
+                }
+            };
+            let mut parts = value.split_whitespace();
+            let (Some(open), Some(close), None) = (parts.next(), parts.next(), parts.next()) else {
+                return Err(Error::Syntax(format!(
+                    "{key} on line {} needs an opening and a closing delimiter, like \"[[ ]]\"",
+                    index + 1
+                )));
+            };
+            *target = (open.to_string(), close.to_string());
+        }
+    }
+
+    // An empty comment around the line break drops the line from the output
+    // without moving the lines after it
+    let (open, close) = &delimiters.comment;
+    let rewritten = template
+        .split_inclusive('\n')
+        .map(|line| {
+            if line.contains(DIRECTIVE) {
+                let line_break = &line[line.trim_end_matches(['\r', '\n']).len()..];
+                Cow::Owned(format!("{open}{line_break}{close}"))
+            } else {
+                Cow::Borrowed(line)
+            }
+        })
+        .collect();
+    Ok((Cow::Owned(rewritten), Some(delimiters)))
+}
+
+#[cfg(test)]
+mod tests {
+    #![allow(clippy::unwrap_used, clippy::panic)]
+    use super::*;
+    use crate::{TemplateContext, TemplateEngine};
+
+    #[test]
+    fn test_parse_directive() {
+        let (source, delimiters) = parse("a\n").unwrap();
+        assert!(matches!(source, Cow::Borrowed("a\n")));
+        assert!(delimiters.is_none());
+
+        let template = "# guisu:template:variable-delimiters=\"[[ ]]\" block-delimiters=\"<% %>\"\nname: [[ x ]]\n";
+        let (source, delimiters) = parse(template).unwrap();
+        let delimiters = delimiters.unwrap();
+        assert_eq!(delimiters.variable, ("[[".to_string(), "]]".to_string()));
+        assert_eq!(delimiters.block, ("<%".to_string(), "%>".to_string()));
+        assert_eq!(delimiters.comment, Delimiters::default().comment);
+        assert_eq!(source, "{#\n#}name: [[ x ]]\n");
+
+        assert!(parse("# guisu:template:left-delimiter=[[\n").is_err());
+        assert!(parse("# guisu:template:variable-delimiters=[[\n").is_err());
+    }
+
+    #[test]
+    fn test_render_with_delimiters() {
+        let engine = TemplateEngine::new();
+        let context = TemplateContext::new()
+            .with_variables([("name".to_string(), serde_json::json!("web"))].into());
+        let template = "# guisu:template:variable-delimiters=\"[[ ]]\" block-delimiters=\"[% %]\"\n\
+                        name: [[ name ]]\n\
+                        image: {{ .Values.image }}\n\
+                        [% raw %][[ kept ]][% endraw %] done\n";
+        assert_eq!(
+            engine.render_str(template, &context).unwrap(),
+            "name: web\nimage: {{ .Values.image }}\n[[ kept ]] done\n"
+        );
+
+        // Errors point at the line in the file, directive included
+        let error = engine
+            .render_named_str(
+                "values.yaml",
+                "# guisu:template:variable-delimiters=\"[[ ]]\"\n\n[[ 1 + ]]\n",
+                &context,
+            )
+            .unwrap_err();
+        assert!(error.to_string().contains("line 3"), "{error}");
+    }
+}
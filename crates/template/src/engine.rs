@@ -3,11 +3,12 @@
 //! The engine wraps minijinja and provides template rendering with custom functions.
 
 use crate::context::TemplateContext;
+use crate::directive;
 use crate::functions;
 use crate::{Error, Result};
 use guisu_crypto::Identity;
 use minijinja::Environment;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 use std::sync::Arc;
 
 /// Template engine for rendering templates
@@ -123,11 +124,22 @@ impl TemplateEngine {
         env.add_function("arch", functions::arch);
         env.add_function("hostname", functions::hostname);
         env.add_function("username", functions::username);
+        env.add_function("homeDir", functions::home_dir);
+        // Deprecated spelling, see `check::DEPRECATED_FUNCTIONS`
         env.add_function("home_dir", functions::home_dir);
+        env.add_function("xdgConfigHome", functions::xdg_config_home);
+        env.add_function("xdgDataHome", functions::xdg_data_home);
+        env.add_function("xdgCacheHome", functions::xdg_cache_home);
+        env.add_function("xdgStateHome", functions::xdg_state_home);
         env.add_function("joinPath", functions::join_path);
         env.add_function("lookPath", functions::look_path);
         env.add_function("include", functions::include);
         env.add_function("includeTemplate", functions::include_template);
+        env.add_function("glob", functions::glob);
+        env.add_function(
+            "sshPublicKeyFromPrivate",
+            functions::ssh_public_key_from_private,
+        );
 
         // Register Bitwarden functions with provider closure
         #[cfg(any(feature = "bw", feature = "rbw"))]
@@ -155,16 +167,16 @@ impl TemplateEngine {
         #[cfg(feature = "bws")]
         env.add_function("bitwardenSecrets", functions::bitwarden_secrets);
 
+        #[cfg(feature = "pass")]
+        env.add_function("pass", |args: &[minijinja::Value]| {
+            functions::pass(args, "pass")
+        });
+
+        #[cfg(feature = "keyring")]
+        env.add_function("keyring", functions::keyring);
+
         // Register filters
-        env.add_filter("quote", functions::quote);
-        env.add_filter("toJson", functions::to_json);
-        env.add_filter("fromJson", functions::from_json);
-        env.add_filter("toToml", functions::to_toml);
-        env.add_filter("fromToml", functions::from_toml);
-        env.add_filter("trim", functions::trim);
-        env.add_filter("trimStart", functions::trim_start);
-        env.add_filter("trimEnd", functions::trim_end);
-        env.add_filter("blake3sum", functions::blake3sum);
+        register_filters(&mut env);
 
         // Register string processing functions
         env.add_function("regexMatch", functions::regex_match);
@@ -232,7 +244,142 @@ impl TemplateEngine {
             });
         }
 
+        // No command may run and no key may be generated until allowed
         Self { env }
+            .with_allowed_commands(&[])
+            .with_ssh_keygen(functions::SshKeygen::Disabled)
+    }
+
+    /// Use `command` ("pass" or "gopass") for the `pass()` template function
+    #[must_use]
+    #[cfg_attr(not(feature = "pass"), allow(unused_mut))]
+    pub fn with_pass_command(mut self, command: &str) -> Self {
+        #[cfg(feature = "pass")]
+        {
+            let command = command.to_string();
+            self.env
+                .add_function("pass", move |args: &[minijinja::Value]| {
+                    functions::pass(args, &command)
+                });
+        }
+        #[cfg(not(feature = "pass"))]
+        let _ = command;
+        self
+    }
+
+    /// Retry failed vault calls up to `retries` times with exponential backoff
+    ///
+    /// Applies to all secret providers (`bitwarden*`, `pass`, `keyring`).
+    #[must_use]
+    pub fn with_vault_retries(self, retries: u32) -> Self {
+        functions::set_vault_retries(retries);
+        self
+    }
+
+    /// Only answer Bitwarden lookups from the cache when `offline`
+    ///
+    /// Lookups that are neither cached in memory nor in the disk cache (see
+    /// [`Self::with_vault_cache`]) fail with an error saying so instead of
+    /// reaching the network.
+    #[must_use]
+    pub fn with_vault_offline(self, offline: bool) -> Self {
+        functions::set_vault_offline(offline);
+        self
+    }
+
+    /// Keep Bitwarden responses in an encrypted cache across runs
+    ///
+    /// `None` disables the cache.
+    #[must_use]
+    pub fn with_vault_cache(self, cache: Option<guisu_vault::DiskCache>) -> Self {
+        functions::set_vault_disk_cache(cache);
+        self
+    }
+
+    /// Run up to `jobs` vault lookups at once in [`Self::prefetch_secrets`]
+    ///
+    /// 0 disables prefetching, so secrets are fetched one at a time while
+    /// rendering.
+    #[must_use]
+    pub fn with_vault_prefetch(self, jobs: usize) -> Self {
+        functions::set_vault_prefetch_jobs(jobs);
+        self
+    }
+
+    /// Register the functions defined in the Rhai scripts of `dir`
+    ///
+    /// See [`crate::scripting`] for how scripts are loaded. A missing
+    /// directory registers nothing.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if a script cannot be loaded, or if `dir` has scripts
+    /// and guisu was built without the `rhai` feature
+    #[cfg_attr(not(feature = "rhai"), allow(unused_mut))]
+    pub fn with_script_functions(mut self, dir: &Path) -> Result<Self> {
+        #[cfg(feature = "rhai")]
+        crate::scripting::register_functions(&mut self.env, dir)?;
+        #[cfg(not(feature = "rhai"))]
+        if std::fs::read_dir(dir).is_ok_and(|mut entries| {
+            entries.any(|entry| {
+                entry.is_ok_and(|entry| entry.path().extension().is_some_and(|ext| ext == "rhai"))
+            })
+        }) {
+            return Err(Error::Other(format!(
+                "{} has Rhai scripts, but guisu was built without the `rhai` feature",
+                dir.display()
+            )));
+        }
+        Ok(self)
+    }
+
+    /// Let the `output()` template function run the commands in `allowed`
+    ///
+    /// Each command line runs at most once per engine; later calls reuse its output.
+    #[must_use]
+    pub fn with_allowed_commands(mut self, allowed: &[String]) -> Self {
+        let outputs = functions::CommandOutputs::new(allowed);
+        self.env
+            .add_function("output", move |args: &[minijinja::Value]| {
+                functions::output(args, &outputs)
+            });
+        self
+    }
+
+    /// Set what `generateSshKeyIfMissing` may do
+    ///
+    /// Only [`SshKeygen::Write`](functions::SshKeygen::Write) writes keys;
+    /// engines rendering for anything but apply should use
+    /// [`SshKeygen::Preview`](functions::SshKeygen::Preview).
+    #[must_use]
+    pub fn with_ssh_keygen(mut self, mode: functions::SshKeygen) -> Self {
+        self.env.add_function(
+            "generateSshKeyIfMissing",
+            move |path: &str, key_type: Option<&str>| {
+                functions::generate_ssh_key_if_missing(path, key_type, mode)
+            },
+        );
+        self
+    }
+
+    /// Enable `promptString`, `promptBool`, and `promptInt`, answered from `answers`
+    ///
+    /// Only the config template asks questions, so the functions are not
+    /// registered by default.
+    #[must_use]
+    pub fn with_prompt_answers(mut self, answers: &functions::PromptAnswers) -> Self {
+        for kind in [
+            functions::PromptKind::String,
+            functions::PromptKind::Bool,
+            functions::PromptKind::Int,
+        ] {
+            let answers = answers.clone();
+            self.env
+                .add_function(kind.function_name(), move |args: &[minijinja::Value]| {
+                    functions::prompt(args, kind, &answers)
+                });
+        }
+        self
     }
 
     /// Render a template string with the given context
@@ -254,7 +401,7 @@ impl TemplateEngine {
     ///
     /// Returns error if template rendering fails
     pub fn render_str(&self, template: &str, context: &TemplateContext) -> Result<String> {
-        self.env.render_str(template, context).map_err(Error::from)
+        self.render_named_str("<string>", template, context)
     }
 
     /// Render a template string with a specific name for better error messages
@@ -263,6 +410,9 @@ impl TemplateEngine {
     /// meaningful name to associate with the template. Error messages will include
     /// this name instead of the generic `<string>`.
     ///
+    /// A `guisu:template:` line in the template changes its delimiters, see
+    /// [`crate::directive`].
+    ///
     /// # Examples
     ///
     /// ```
@@ -285,9 +435,36 @@ impl TemplateEngine {
         template: &str,
         context: &TemplateContext,
     ) -> Result<String> {
-        self.env
-            .render_named_str(name, template, context)
-            .map_err(Error::from)
+        let (template, delimiters) = directive::parse(template)?;
+        match delimiters {
+            None => self.env.render_named_str(name, &template, context),
+            Some(delimiters) => {
+                // The syntax applies to a whole environment, so the file gets its own copy
+                let mut env = self.env.clone();
+                env.set_syntax(delimiters.syntax()?);
+                env.render_named_str(name, &template, context)
+            }
+        }
+        .map_err(Error::from)
+    }
+
+    /// Evaluate a template expression as a condition
+    ///
+    /// The expression sees the full context plus the system facts (`os`,
+    /// `arch`, `hostname`, `username`, `distro`, ...) as plain names, so
+    /// `os == "darwin" and hostname == "work"` works as written. Context
+    /// variables take precedence over the system facts.
+    ///
+    /// # Errors
+    ///
+    /// Returns error if the expression is invalid or evaluation fails
+    pub fn eval_condition(&self, expression: &str, context: &TemplateContext) -> Result<bool> {
+        let expr = self.env.compile_expression(expression)?;
+        let value = expr.eval(minijinja::context! {
+            ..minijinja::Value::from_serialize(context),
+            ..minijinja::Value::from_serialize(&context.system)
+        })?;
+        Ok(value.is_true())
     }
 
     /// Render template content (bytes) with the given context
@@ -329,6 +506,21 @@ impl TemplateEngine {
     }
 }
 
+/// Register the data conversion and string filters
+fn register_filters(env: &mut Environment<'static>) {
+    env.add_filter("quote", functions::quote);
+    env.add_filter("toJson", functions::to_json);
+    env.add_filter("fromJson", functions::from_json);
+    env.add_filter("toToml", functions::to_toml);
+    env.add_filter("fromToml", functions::from_toml);
+    env.add_filter("toYaml", functions::to_yaml);
+    env.add_filter("fromYaml", functions::from_yaml);
+    env.add_filter("trim", functions::trim);
+    env.add_filter("trimStart", functions::trim_start);
+    env.add_filter("trimEnd", functions::trim_end);
+    env.add_filter("blake3sum", functions::blake3sum);
+}
+
 impl Default for TemplateEngine {
     fn default() -> Self {
         Self::new()
@@ -438,6 +630,75 @@ mod tests {
         assert!(result.is_ok());
     }
 
+    #[test]
+    #[cfg(unix)]
+    fn test_output_allowed_commands() {
+        let ctx = TemplateContext::new();
+        let template = r#"{{ output("echo", "hi") }}"#;
+
+        assert!(TemplateEngine::new().render_str(template, &ctx).is_err());
+
+        let engine = TemplateEngine::new().with_allowed_commands(&["echo".to_string()]);
+        assert_eq!(engine.render_str(template, &ctx).unwrap(), "hi");
+    }
+
+    #[test]
+    fn test_glob_source_files() {
+        let temp = TempDir::new().unwrap();
+        let source = temp.path().join("home");
+        let nvim = source.join("dot_config/nvim/lua");
+        std::fs::create_dir_all(nvim.join("plugins")).unwrap();
+        std::fs::write(nvim.join("options.lua"), "").unwrap();
+        std::fs::write(nvim.join("plugins/lsp.lua"), "").unwrap();
+        std::fs::write(nvim.join("plugins/old.lua"), "").unwrap();
+        std::fs::write(nvim.join("README.md"), "").unwrap();
+        std::fs::write(source.join(".guisuignore"), "src:**/old.lua\n").unwrap();
+
+        let ctx = TemplateContext::new().with_guisu_info(
+            source.to_string_lossy().into_owned(),
+            temp.path().to_string_lossy().into_owned(),
+            String::new(),
+            "home".to_string(),
+        );
+        let engine = TemplateEngine::new();
+
+        let result = engine
+            .render_str(r#"{{ glob("dot_config/**/*.lua") | join(",") }}"#, &ctx)
+            .unwrap();
+        assert_eq!(
+            result,
+            "dot_config/nvim/lua/options.lua,dot_config/nvim/lua/plugins/lsp.lua"
+        );
+
+        let result = engine
+            .render_str(
+                r#"{{ glob("dot_config/nvim/lua/*.lua") | join(",") }}"#,
+                &ctx,
+            )
+            .unwrap();
+        assert_eq!(result, "dot_config/nvim/lua/options.lua");
+
+        assert!(engine.render_str(r#"{{ glob("../*") }}"#, &ctx).is_err());
+        assert!(engine.render_str(r#"{{ glob("/etc/*") }}"#, &ctx).is_err());
+    }
+
+    #[test]
+    fn test_eval_condition() {
+        let engine = TemplateEngine::new();
+        let mut variables = indexmap::IndexMap::new();
+        variables.insert("work".to_string(), serde_json::json!(true));
+        let ctx = TemplateContext::new().with_variables(variables);
+        let os = ctx.system.os.clone();
+
+        assert!(
+            engine
+                .eval_condition(&format!("os == \"{os}\" and work"), &ctx)
+                .unwrap()
+        );
+        assert!(!engine.eval_condition("os == \"plan9\"", &ctx).unwrap());
+        assert!(engine.eval_condition("os ==", &ctx).is_err());
+    }
+
     #[test]
     fn test_render_str_basic() {
         let engine = TemplateEngine::new();
@@ -5,13 +5,21 @@
 //! This crate provides template rendering capabilities with custom functions
 //! for accessing system information, environment variables, and more.
 
+pub mod check;
 pub mod context;
+pub mod dependencies;
+pub mod directive;
 pub mod engine;
 pub mod functions;
 pub mod info;
+pub mod prefetch;
+#[cfg(feature = "rhai")]
+pub mod scripting;
 
 pub use context::TemplateContext;
+pub use dependencies::{Dependencies, track_dependencies};
 pub use engine::TemplateEngine;
+pub use functions::SshKeygen;
 pub use info::{AgeConfigInfo, BitwardenConfigInfo, ConfigInfo, UiConfigInfo};
 
 use thiserror::Error;
@@ -0,0 +1,224 @@
+//! Fetching the secrets of templates before rendering them
+//!
+//! Every `bitwarden*` call runs a vault CLI that can take seconds, and
+//! rendering calls them one at a time. [`TemplateEngine::prefetch_secrets`]
+//! scans templates for calls with literal arguments and runs those lookups
+//! concurrently, so that rendering finds the responses in the cache.
+//!
+//! Calls whose arguments are variables or expressions are not found; they
+//! are fetched while rendering, as before.
+//!
+//! Templates are scanned as text, not evaluated. Comments are skipped, but a
+//! call in a branch that does not render on this machine (such as
+//! `{% if os == "darwin" %}` on Linux) is still fetched. That costs a vault
+//! call whose result goes unused, never a wrong value; `prefetchJobs = 0`
+//! turns prefetching off for sources where that matters.
+
+use crate::TemplateEngine;
+use crate::{directive, functions};
+use regex::Regex;
+use std::borrow::Cow;
+use std::collections::BTreeSet;
+use std::sync::LazyLock;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+/// Cached pattern of a vault function call with one or two string literals
+static VAULT_CALL: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(
+        r#"\b(bitwarden|bitwardenFields|bitwardenAttachment|bitwardenSecrets)\s*\(\s*(?:"([^"\\]*)"|'([^'\\]*)')(?:\s*,\s*(?:"([^"\\]*)"|'([^'\\]*)'))?"#,
+    )
+    .expect("Valid regex")
+});
+
+/// A vault lookup made by a template
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum VaultLookup {
+    /// A Bitwarden item, from `bitwarden` or `bitwardenFields`
+    Item(String),
+    /// An attachment of a Bitwarden item
+    Attachment {
+        /// Name of the attachment
+        filename: String,
+        /// Name or ID of the item
+        item: String,
+    },
+    /// A Bitwarden Secrets Manager secret
+    Secret(String),
+}
+
+impl VaultLookup {
+    /// Template performing the lookup, with its arguments as `a` and `b`
+    fn template(&self) -> &'static str {
+        match self {
+            Self::Item(_) => "{% set _ = bitwarden(a) %}",
+            Self::Attachment { .. } => "{% set _ = bitwardenAttachment(a, b) %}",
+            Self::Secret(_) => "{% set _ = bitwardenSecrets(a) %}",
+        }
+    }
+
+    /// Arguments of the lookup
+    fn args(&self) -> (&str, &str) {
+        match self {
+            Self::Item(item) | Self::Secret(item) => (item, ""),
+            Self::Attachment { filename, item } => (filename, item),
+        }
+    }
+}
+
+/// Find the vault lookups of `source` whose arguments are string literals
+///
+/// Calls inside comments are ignored; calls anywhere else are found, whether
+/// or not they would render.
+#[must_use]
+pub fn vault_lookups(source: &str) -> BTreeSet<VaultLookup> {
+    VAULT_CALL
+        .captures_iter(&strip_comments(source))
+        .filter_map(|caps| {
+            let first = caps.get(2).or_else(|| caps.get(3))?.as_str().to_string();
+            let second = caps.get(4).or_else(|| caps.get(5)).map(|m| m.as_str());
+            match &caps[1] {
+                "bitwarden" | "bitwardenFields" => Some(VaultLookup::Item(first)),
+                "bitwardenAttachment" => Some(VaultLookup::Attachment {
+                    filename: first,
+                    item: second?.to_string(),
+                }),
+                "bitwardenSecrets" => Some(VaultLookup::Secret(first)),
+                _ => None,
+            }
+        })
+        .collect()
+}
+
+/// `source` without its comments, using the delimiters of its directive
+///
+/// A comment left open runs to the end of the template.
+fn strip_comments(source: &str) -> Cow<'_, str> {
+    let delimiters = directive::parse(source)
+        .ok()
+        .and_then(|(_, delimiters)| delimiters)
+        .unwrap_or_default();
+    let (open, close) = &delimiters.comment;
+    if !source.contains(open.as_str()) {
+        return Cow::Borrowed(source);
+    }
+
+    let mut stripped = String::with_capacity(source.len());
+    let mut rest = source;
+    while let Some(start) = rest.find(open.as_str()) {
+        stripped.push_str(&rest[..start]);
+        let comment = &rest[start + open.len()..];
+        rest = comment
+            .find(close.as_str())
+            .map_or("", |end| &comment[end + close.len()..]);
+    }
+    stripped.push_str(rest);
+    Cow::Owned(stripped)
+}
+
+impl TemplateEngine {
+    /// Fetch the vault secrets referenced by `templates` into the cache
+    ///
+    /// Up to the number of jobs set with [`Self::with_vault_prefetch`] lookups
+    /// run at once; the first runs alone so that a vault asking to be
+    /// unlocked asks only once. Failed lookups are ignored here and reported
+    /// when the template using them is rendered.
+    pub fn prefetch_secrets(&self, templates: &[&str]) {
+        let jobs = functions::vault_prefetch_jobs();
+        if jobs == 0 {
+            return;
+        }
+
+        let lookups: Vec<VaultLookup> = templates
+            .iter()
+            .flat_map(|template| vault_lookups(template))
+            .collect::<BTreeSet<_>>()
+            .into_iter()
+            .collect();
+        let Some((first, rest)) = lookups.split_first() else {
+            return;
+        };
+
+        self.run_lookup(first);
+        let next = AtomicUsize::new(0);
+        std::thread::scope(|scope| {
+            for _ in 0..jobs.min(rest.len()) {
+                scope.spawn(|| {
+                    while let Some(lookup) = rest.get(next.fetch_add(1, Ordering::Relaxed)) {
+                        self.run_lookup(lookup);
+                    }
+                });
+            }
+        });
+    }
+
+    /// Perform `lookup` through the registered function, filling its cache
+    fn run_lookup(&self, lookup: &VaultLookup) {
+        let (a, b) = lookup.args();
+        // Ignore errors - rendering reports them
+        let _ = self
+            .env()
+            .render_str(lookup.template(), minijinja::context! { a, b });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_vault_lookups_literal_arguments() {
+        let source = r#"
+            {{ bitwarden("GitHub").login.password }}
+            {{ bitwardenFields('GitHub', "token") }}
+            {{ bitwardenAttachment("id_rsa", "SSH keys") }}
+            {{ bitwardenSecrets( "abc-123" ).value }}
+        "#;
+
+        assert_eq!(
+            vault_lookups(source),
+            BTreeSet::from([
+                VaultLookup::Item("GitHub".to_string()),
+                VaultLookup::Attachment {
+                    filename: "id_rsa".to_string(),
+                    item: "SSH keys".to_string(),
+                },
+                VaultLookup::Secret("abc-123".to_string()),
+            ])
+        );
+    }
+
+    #[test]
+    fn test_vault_lookups_skip_dynamic_arguments() {
+        let source = r#"
+            {{ bitwarden(item) }}
+            {{ bitwardenAttachment(name, "SSH keys") }}
+            {{ bitwardenAttachment("id_rsa") }}
+            {{ mybitwarden("GitHub") }}
+        "#;
+
+        assert!(vault_lookups(source).is_empty());
+    }
+
+    #[test]
+    fn test_vault_lookups_skip_comments() {
+        let source = r#"
+            {# {{ bitwarden("Old") }} #}
+            {{ bitwarden("GitHub") }}
+            {#- bitwardenSecrets("unused")
+                spanning lines -#}
+        "#;
+        assert_eq!(
+            vault_lookups(source),
+            BTreeSet::from([VaultLookup::Item("GitHub".to_string())])
+        );
+
+        let source = r#"
+            # guisu:template:comment-delimiters="[# #]"
+            [# bitwarden("Old") #] {# {{ bitwarden("Kept") }} #}
+        "#;
+        assert_eq!(
+            vault_lookups(source),
+            BTreeSet::from([VaultLookup::Item("Kept".to_string())])
+        );
+    }
+}
@@ -0,0 +1,215 @@
+//! Template functions written in Rhai
+//!
+//! Every public function of a `.rhai` file in the functions directory
+//! (`.guisu/functions/`) is registered as both a template function and a
+//! filter under its own name, so `{{ slug(title) }}` and `{{ title | slug }}`
+//! both work. Functions marked `private` are helpers and are not registered.
+//!
+//! Arguments and results are converted through serde, so strings, numbers,
+//! booleans, arrays, and maps pass between templates and scripts.
+//!
+//! Scripts run in Rhai's sandbox: they cannot touch files, the network, or
+//! processes. Each call may run a limited number of operations, so a runaway
+//! loop fails the render instead of hanging it.
+
+use crate::{Error, Result};
+use minijinja::{Environment, ErrorKind, Value};
+use rhai::{AST, Dynamic, FnAccess, Scope};
+use std::collections::{BTreeMap, BTreeSet};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+/// Extension of script files
+const SCRIPT_EXTENSION: &str = "rhai";
+
+/// Operations a single call may run before it is stopped
+const MAX_OPERATIONS: u64 = 1_000_000;
+
+/// Function defined in a script, with the argument counts of its overloads
+struct ScriptFunction {
+    engine: Arc<rhai::Engine>,
+    ast: Arc<AST>,
+    name: String,
+    arities: BTreeSet<usize>,
+}
+
+impl ScriptFunction {
+    /// Call the function with template values
+    fn call(&self, args: &[Value]) -> std::result::Result<Value, minijinja::Error> {
+        if !self.arities.contains(&args.len()) {
+            let expected: Vec<String> = self.arities.iter().map(ToString::to_string).collect();
+            return Err(minijinja::Error::new(
+                ErrorKind::InvalidOperation,
+                format!(
+                    "{}() takes {} argument(s), got {}",
+                    self.name,
+                    expected.join(" or "),
+                    args.len()
+                ),
+            ));
+        }
+
+        let failed = |e: &dyn std::fmt::Display| {
+            minijinja::Error::new(ErrorKind::InvalidOperation, format!("{}(): {e}", self.name))
+        };
+        let args = args
+            .iter()
+            .map(rhai::serde::to_dynamic)
+            .collect::<std::result::Result<Vec<_>, _>>()
+            .map_err(|e| failed(&e))?;
+        let result: Dynamic = self
+            .engine
+            .call_fn(&mut Scope::new(), &self.ast, &self.name, args)
+            .map_err(|e| failed(&e))?;
+        Ok(Value::from_serialize(&result))
+    }
+}
+
+/// Register the public functions of the scripts in `dir` into `env`
+///
+/// Scripts are loaded in file name order. A missing directory registers
+/// nothing.
+///
+/// # Errors
+///
+/// Returns an error if a script cannot be read or compiled, if two scripts
+/// define the same function, or if a function has the name of a built-in
+/// function or filter
+pub(crate) fn register_functions(env: &mut Environment<'static>, dir: &Path) -> Result<()> {
+    let scripts = script_files(dir)?;
+    if scripts.is_empty() {
+        return Ok(());
+    }
+
+    let mut engine = rhai::Engine::new();
+    engine.set_max_operations(MAX_OPERATIONS);
+    let engine = Arc::new(engine);
+
+    let mut defined: BTreeMap<String, PathBuf> = BTreeMap::new();
+    for path in scripts {
+        let script = fs::read_to_string(&path)?;
+        let ast = engine
+            .compile(&script)
+            .map_err(|e| Error::Syntax(format!("{}: {e}", path.display())))?;
+
+        // Overloads share a name and differ in the number of arguments
+        let mut functions: BTreeMap<String, BTreeSet<usize>> = BTreeMap::new();
+        for function in ast.iter_functions() {
+            if function.access == FnAccess::Public {
+                functions
+                    .entry(function.name.to_string())
+                    .or_default()
+                    .insert(function.params.len());
+            }
+        }
+
+        let ast = Arc::new(ast);
+        for (name, arities) in functions {
+            if let Some(other) = defined.get(&name) {
+                return Err(Error::Other(format!(
+                    "{}: {name}() is already defined in {}",
+                    path.display(),
+                    other.display()
+                )));
+            }
+            if env.globals().any(|(global, _)| global == name)
+                || crate::check::is_filter(env, &name)
+            {
+                return Err(Error::Other(format!(
+                    "{}: {name}() has the name of a built-in function or filter",
+                    path.display()
+                )));
+            }
+            defined.insert(name.clone(), path.clone());
+
+            let function = Arc::new(ScriptFunction {
+                engine: Arc::clone(&engine),
+                ast: Arc::clone(&ast),
+                name: name.clone(),
+                arities,
+            });
+            let filter = Arc::clone(&function);
+            env.add_function(name.clone(), move |args: &[Value]| function.call(args));
+            env.add_filter(name, move |args: &[Value]| filter.call(args));
+        }
+    }
+    Ok(())
+}
+
+/// Script files directly in `dir`, sorted
+///
+/// # Errors
+///
+/// Returns an error if `dir` exists but cannot be listed
+fn script_files(dir: &Path) -> Result<Vec<PathBuf>> {
+    if !dir.is_dir() {
+        return Ok(Vec::new());
+    }
+    let mut scripts: Vec<PathBuf> = fs::read_dir(dir)?
+        .filter_map(std::result::Result::ok)
+        .map(|entry| entry.path())
+        .filter(|path| {
+            path.is_file()
+                && path
+                    .extension()
+                    .is_some_and(|ext| ext.eq_ignore_ascii_case(SCRIPT_EXTENSION))
+        })
+        .collect();
+    scripts.sort();
+    Ok(scripts)
+}
+
+#[cfg(test)]
+mod tests {
+    #![allow(clippy::unwrap_used, clippy::panic)]
+    use crate::{TemplateContext, TemplateEngine};
+    use tempfile::TempDir;
+
+    fn engine_with(scripts: &[(&str, &str)]) -> crate::Result<TemplateEngine> {
+        let temp = TempDir::new().unwrap();
+        for (name, script) in scripts {
+            std::fs::write(temp.path().join(name), script).unwrap();
+        }
+        TemplateEngine::new().with_script_functions(temp.path())
+    }
+
+    #[test]
+    fn test_script_functions_and_filters() {
+        let engine = engine_with(&[(
+            "text.rhai",
+            r#"
+            private fn dash(s) { let t = s.to_lower(); t.replace(" ", "-"); t }
+            fn slug(s) { dash(s) }
+            fn slug(s, sep) { let t = slug(s); t.replace("-", sep); t }
+            fn pair(a, b) { #{ first: a, second: [a, b] } }
+            fn spin() { loop {} }
+            "#,
+        )])
+        .unwrap();
+        let context = TemplateContext::new();
+        let render = |template: &str| engine.render_str(template, &context);
+
+        assert_eq!(
+            render(r#"{{ slug("Hello World") }}"#).unwrap(),
+            "hello-world"
+        );
+        assert_eq!(render(r#"{{ "A B" | slug("_") }}"#).unwrap(), "a_b");
+        assert_eq!(
+            render("{{ pair(1, 2).second | join(',') }}").unwrap(),
+            "1,2"
+        );
+        assert!(render(r#"{{ dash("x") }}"#).is_err());
+        assert!(render("{{ slug() }}").is_err());
+        assert!(render("{{ spin() }}").is_err());
+    }
+
+    #[test]
+    fn test_script_function_errors() {
+        assert!(engine_with(&[("bad.rhai", "fn broken( {")]).is_err());
+        assert!(engine_with(&[("upper.rhai", "fn upper(s) { s }")]).is_err());
+        assert!(engine_with(&[("a.rhai", "fn f() { 1 }"), ("b.rhai", "fn f() { 2 }")]).is_err());
+        // Other files in the directory are not scripts
+        assert!(engine_with(&[("notes.txt", "fn broken( {")]).is_ok());
+    }
+}
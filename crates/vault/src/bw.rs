@@ -9,6 +9,10 @@
 //! Template functions `bitwarden()` and `bitwardenFields()` use whichever
 //! implementation is configured.
 //!
+//! `BwCli` checks the vault once per run. A valid `BW_SESSION` is used as
+//! is; otherwise `bw unlock --raw` prompts for the master password and the
+//! resulting session key is kept in memory for the rest of the run.
+//!
 //! # Security Warning
 //!
 //! **Session Key Exposure Risk**: The Bitwarden CLI (`bw`) requires passing the
@@ -25,95 +29,125 @@
 //! at the application level without modifications to the `bw` tool itself.
 
 use crate::{Error, Result, SecretProvider};
+use secrecy::{ExposeSecret, SecretString};
 use serde_json::Value as JsonValue;
 use std::env;
 use std::process::{Command, Stdio};
-use std::sync::Mutex;
+use std::sync::{Mutex, PoisonError};
 use tracing::info;
 
+/// State of the vault, as reported by `bw status`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum VaultStatus {
+    /// No account is logged in
+    Unauthenticated,
+    /// Logged in, but the vault needs the master password
+    Locked,
+    /// Unlocked with the session that was checked
+    Unlocked,
+}
+
+impl VaultStatus {
+    /// Parse the JSON printed by `bw status`
+    ///
+    /// Anything unexpected counts as locked, so unlocking is attempted.
+    fn parse(stdout: &str) -> Self {
+        // {"serverUrl":"...","lastSync":"...","userEmail":"...","userId":"...","status":"locked"}
+        let status = serde_json::from_str::<JsonValue>(stdout).ok();
+        match status
+            .as_ref()
+            .and_then(|status| status.get("status"))
+            .and_then(JsonValue::as_str)
+        {
+            Some("unauthenticated") => Self::Unauthenticated,
+            Some("unlocked") => Self::Unlocked,
+            _ => Self::Locked,
+        }
+    }
+}
+
+/// What is known about the `bw` session in this run
+enum Session {
+    /// The vault status has not been checked yet
+    Unchecked,
+    /// The vault is unlocked; commands need this session key, if any
+    Unlocked(Option<SecretString>),
+}
+
 /// Official Bitwarden CLI provider (`bw`)
 ///
 /// Uses the official Node.js-based `bw` CLI with session-based authentication.
+/// The vault status is checked once per run: a `BW_SESSION` from the
+/// environment is used if it unlocks the vault, otherwise `bw unlock --raw`
+/// prompts for the master password. The session key is then reused for every
+/// command of the run.
 pub struct BwCli {
-    /// Cached session key (`BW_SESSION`)
-    session_key: Mutex<Option<String>>,
+    /// Session of this run, checked or unlocked on first use
+    session: Mutex<Session>,
 }
 
 impl BwCli {
     /// Create a new Bitwarden CLI provider
     #[must_use]
     pub fn new() -> Self {
-        // Try to get session from environment variable
-        let session_key = env::var("BW_SESSION").ok();
-
         Self {
-            session_key: Mutex::new(session_key),
+            session: Mutex::new(Session::Unchecked),
         }
     }
 
-    /// Get the current session key (from cache or environment)
-    fn get_session_key(&self) -> Option<String> {
-        // Check cache first
-        if let Ok(guard) = self.session_key.lock()
-            && let Some(ref session) = *guard
-        {
-            return Some(session.clone());
+    /// Session key to run commands with, unlocking the vault if needed
+    ///
+    /// The lock is held while unlocking, so concurrent lookups wait for a
+    /// single password prompt instead of each asking.
+    fn session(&self) -> Result<Option<SecretString>> {
+        let mut session = self.session.lock().unwrap_or_else(PoisonError::into_inner);
+        if let Session::Unlocked(key) = &*session {
+            return Ok(key.clone());
         }
 
-        // Check environment variable
-        if let Ok(session) = env::var("BW_SESSION") {
-            // Cache it
-            if let Ok(mut guard) = self.session_key.lock() {
-                *guard = Some(session.clone());
+        let from_env = env::var("BW_SESSION")
+            .ok()
+            .filter(|key| !key.is_empty())
+            .map(SecretString::from);
+        let key = match Self::vault_status(from_env.as_ref())? {
+            VaultStatus::Unlocked => from_env,
+            VaultStatus::Locked => Some(Self::try_unlock()?),
+            VaultStatus::Unauthenticated => {
+                return Err(Error::AuthenticationRequired(
+                    "Not logged in to Bitwarden. Run `bw login` first".to_string(),
+                ));
             }
-            return Some(session);
-        }
-
-        None
-    }
-
-    /// Cache session key
-    fn cache_session_key(&self, session: String) {
-        if let Ok(mut guard) = self.session_key.lock() {
-            *guard = Some(session);
-        }
+        };
+        *session = Session::Unlocked(key.clone());
+        Ok(key)
     }
 
-    /// Check vault status using `bw status`
-    fn check_vault_status() -> Result<bool> {
-        let output = Command::new("bw")
-            .arg("status")
+    /// Check vault status using `bw status` with `session`
+    fn vault_status(session: Option<&SecretString>) -> Result<VaultStatus> {
+        let mut cmd = Command::new("bw");
+        cmd.arg("status")
+            .env("NODE_OPTIONS", "--no-deprecation")
             .stdout(Stdio::piped())
-            .stderr(Stdio::piped())
-            .output()
-            .map_err(Error::Io)?;
-
-        if !output.status.success() {
-            return Ok(false); // Assume locked if status command fails
+            .stderr(Stdio::piped());
+        if let Some(session) = session {
+            cmd.env("BW_SESSION", session.expose_secret());
         }
+        let output = cmd.output().map_err(Error::Io)?;
 
-        let stdout = String::from_utf8_lossy(&output.stdout);
-
-        // Parse JSON status response
-        // {"serverUrl":"...","lastSync":"...","userEmail":"...","userId":"...","status":"locked"}
-        // or {"status":"unlocked"}
-        if let Ok(status) = serde_json::from_str::<JsonValue>(&stdout)
-            && let Some(status_str) = status.get("status").and_then(|s| s.as_str())
-        {
-            return Ok(status_str == "unlocked");
+        if !output.status.success() {
+            return Ok(VaultStatus::Locked); // Assume locked if status command fails
         }
-
-        // If we can't parse, assume locked for safety
-        Ok(false)
+        Ok(VaultStatus::parse(&String::from_utf8_lossy(&output.stdout)))
     }
 
     /// Try to unlock the vault interactively
-    fn try_unlock() -> Result<String> {
+    fn try_unlock() -> Result<SecretString> {
         info!("Bitwarden vault is locked. Unlocking...");
 
         let output = Command::new("bw")
             .arg("unlock")
             .arg("--raw")
+            .env("NODE_OPTIONS", "--no-deprecation")
             .stdin(Stdio::inherit())
             .stdout(Stdio::piped())
             .stderr(Stdio::inherit())
@@ -144,23 +178,12 @@ impl BwCli {
         }
 
         info!("✓ Vault unlocked successfully");
-        Ok(session_key)
+        Ok(SecretString::from(session_key))
     }
 
     /// Execute bw command with auto-unlock
     fn execute_with_unlock(&self, args: &[&str]) -> Result<JsonValue> {
-        // Check vault status first using `bw status`
-        let is_unlocked = Self::check_vault_status()?;
-
-        // If vault is locked, unlock it first
-        let session_key = if is_unlocked {
-            // Use cached session key if available
-            self.get_session_key()
-        } else {
-            let key = Self::try_unlock()?;
-            self.cache_session_key(key.clone());
-            Some(key)
-        };
+        let session_key = self.session()?;
 
         // Execute the actual command with session key
         let mut cmd = Command::new("bw");
@@ -171,7 +194,7 @@ impl BwCli {
             // to other users via process inspection (ps aux, /proc/<pid>/environ).
             // This is a limitation of the `bw` CLI design - consider using `rbw` instead.
             // See module documentation for details and mitigation strategies.
-            cmd.env("BW_SESSION", session);
+            cmd.env("BW_SESSION", session.expose_secret());
         }
 
         let output = cmd.output().map_err(Error::Io)?;
@@ -226,8 +249,7 @@ impl SecretProvider for BwCli {
         Command::new("bw")
             .arg("--version")
             .output()
-            .map(|o| o.status.success())
-            .unwrap_or(false)
+            .is_ok_and(|o| o.status.success())
     }
 
     fn help(&self) -> &'static str {
@@ -236,7 +258,7 @@ impl SecretProvider for BwCli {
          Requirements:\n\
          - Install: npm install -g @bitwarden/cli\n\
          - Login: bw login\n\
-         - The vault will be unlocked automatically when needed\n\
+         - The vault is unlocked once per run when needed (BW_SESSION is reused)\n\
          \n\
          Usage in templates:\n\
          {{ bitwarden(\"GitHub\") }}\n\
@@ -431,8 +453,7 @@ impl SecretProvider for RbwCli {
             .stdout(Stdio::null())
             .stderr(Stdio::null())
             .status()
-            .map(|s| s.success())
-            .unwrap_or(false)
+            .is_ok_and(|s| s.success())
     }
 
     fn help(&self) -> &'static str {
@@ -468,14 +489,9 @@ impl guisu_core::VaultProvider for BwCli {
     }
 
     fn unlock(&mut self) -> guisu_core::Result<()> {
-        if let Ok(true) = Self::check_vault_status() {
-            Ok(()) // Already unlocked
-        } else {
-            let session =
-                Self::try_unlock().map_err(|e| guisu_core::Error::Message(e.to_string()))?;
-            self.cache_session_key(session);
-            Ok(())
-        }
+        self.session()
+            .map(|_| ())
+            .map_err(|e| guisu_core::Error::Message(e.to_string()))
     }
 
     fn get_secret(&self, key: &str) -> guisu_core::Result<String> {
@@ -517,3 +533,25 @@ impl guisu_core::VaultProvider for RbwCli {
             .map_err(|e| guisu_core::Error::Message(e.to_string()))
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_vault_status_parse() {
+        assert_eq!(
+            VaultStatus::parse(r#"{"serverUrl":null,"status":"unlocked"}"#),
+            VaultStatus::Unlocked
+        );
+        assert_eq!(
+            VaultStatus::parse(r#"{"status":"locked"}"#),
+            VaultStatus::Locked
+        );
+        assert_eq!(
+            VaultStatus::parse(r#"{"status":"unauthenticated"}"#),
+            VaultStatus::Unauthenticated
+        );
+        assert_eq!(VaultStatus::parse("not json"), VaultStatus::Locked);
+    }
+}
@@ -9,6 +9,10 @@ use crate::{Error, Result, SecretProvider};
 use serde_json::Value as JsonValue;
 use std::process::Command;
 
+/// Keyring user under which the access token may be stored
+#[cfg(feature = "keyring")]
+pub const KEYRING_USER: &str = "bws-access-token";
+
 /// Bitwarden Secrets Manager CLI provider (`bws`)
 pub struct BwsCli;
 
@@ -19,19 +23,32 @@ impl BwsCli {
         Self
     }
 
-    fn check_access_token() -> Result<()> {
-        if std::env::var("BWS_ACCESS_TOKEN").is_err() {
-            return Err(Error::AuthenticationRequired(
-                "BWS_ACCESS_TOKEN environment variable not set.\n\
-                 Get your access token from Bitwarden Secrets Manager:\n\
-                 1. Go to your organization's Secrets Manager\n\
-                 2. Create a Machine Account\n\
-                 3. Generate an access token\n\
-                 4. Set it: export BWS_ACCESS_TOKEN='your-token'"
-                    .to_string(),
-            ));
+    /// Access token from `BWS_ACCESS_TOKEN`, else from the system keyring
+    ///
+    /// Returns `None` when the environment variable is set, since `bws` reads
+    /// it directly.
+    fn access_token() -> Result<Option<String>> {
+        if std::env::var("BWS_ACCESS_TOKEN").is_ok() {
+            return Ok(None);
+        }
+
+        #[cfg(feature = "keyring")]
+        if let Ok(Some(token)) =
+            crate::keyring::Keyring::new().get(crate::keyring::SERVICE, KEYRING_USER)
+        {
+            return Ok(Some(token));
         }
-        Ok(())
+
+        Err(Error::AuthenticationRequired(
+            "BWS_ACCESS_TOKEN environment variable not set.\n\
+             Get your access token from Bitwarden Secrets Manager:\n\
+             1. Go to your organization's Secrets Manager\n\
+             2. Create a Machine Account\n\
+             3. Generate an access token\n\
+             4. Set it: export BWS_ACCESS_TOKEN='your-token'\n\
+                or store it: secret-tool store --label=bws service guisu username bws-access-token"
+                .to_string(),
+        ))
     }
 }
 
@@ -53,17 +70,19 @@ impl SecretProvider for BwsCli {
             ));
         }
 
-        Self::check_access_token()?;
+        let token = Self::access_token()?;
 
         // Build command with --output json flag
         let mut cmd_args: Vec<&str> = args.to_vec();
         cmd_args.push("--output");
         cmd_args.push("json");
 
-        let output = Command::new("bws")
-            .args(&cmd_args)
-            .output()
-            .map_err(Error::Io)?;
+        let mut command = Command::new("bws");
+        command.args(&cmd_args);
+        if let Some(token) = token {
+            command.env("BWS_ACCESS_TOKEN", token);
+        }
+        let output = command.output().map_err(Error::Io)?;
 
         if !output.status.success() {
             let stderr = String::from_utf8_lossy(&output.stderr);
@@ -82,8 +101,7 @@ impl SecretProvider for BwsCli {
         Command::new("bws")
             .arg("--version")
             .output()
-            .map(|o| o.status.success())
-            .unwrap_or(false)
+            .is_ok_and(|o| o.status.success())
     }
 
     fn help(&self) -> &'static str {
@@ -91,7 +109,8 @@ impl SecretProvider for BwsCli {
          \n\
          Requirements:\n\
          - Install: cargo install bws\n\
-         - Set BWS_ACCESS_TOKEN environment variable\n\
+         - Set BWS_ACCESS_TOKEN environment variable, or store it in the\n\
+           system keyring (service \"guisu\", user \"bws-access-token\")\n\
          \n\
          Usage in templates:\n\
          {{ bitwardenSecrets(\"secret-uuid\") }}\n\
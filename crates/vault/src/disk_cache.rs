@@ -0,0 +1,307 @@
+//! Persistent cache of provider responses
+//!
+//! Some vault CLIs take seconds per call (`bw` unlocks and syncs its
+//! database), so responses can be kept across runs. Each response is stored
+//! in its own file, age-encrypted to the user's identities, and is reused
+//! until it is older than the cache's TTL. File names are hashes of the
+//! lookup keyed with a random salt, which is itself encrypted in the
+//! directory, so without the identities the file names cannot be matched to
+//! the secrets that were read.
+//!
+//! Files are written through a renamed temporary file, so a crash never
+//! leaves a truncated entry, and the directory is only accessible by its
+//! owner.
+
+use crate::{Error, Result};
+use guisu_crypto::{Identity, Recipient};
+use serde::{Deserialize, Serialize};
+use serde_json::Value as JsonValue;
+use std::fs;
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+/// File holding the encrypted salt of entry file names
+const SALT_FILE: &str = "salt";
+
+/// A cached response with the time it was fetched
+#[derive(Serialize, Deserialize)]
+struct CachedResponse {
+    /// Seconds since the Unix epoch
+    stored_at: u64,
+    value: JsonValue,
+}
+
+/// Directory of age-encrypted provider responses
+pub struct DiskCache {
+    dir: PathBuf,
+    identities: Arc<Vec<Identity>>,
+    recipients: Vec<Recipient>,
+    ttl: Duration,
+    /// Salt of entry file names, once read or created
+    salt: Mutex<Option<[u8; 32]>>,
+}
+
+impl DiskCache {
+    /// Create a cache in `dir` whose entries expire after `ttl`
+    ///
+    /// Entries are encrypted to and decrypted with `identities`.
+    #[must_use]
+    pub fn new(dir: impl Into<PathBuf>, identities: Arc<Vec<Identity>>, ttl: Duration) -> Self {
+        let recipients = guisu_crypto::identities_to_recipients(&identities);
+        Self {
+            dir: dir.into(),
+            identities,
+            recipients,
+            ttl,
+            salt: Mutex::new(None),
+        }
+    }
+
+    /// Get the cached response for `key`
+    ///
+    /// Expired entries are only returned with `include_expired`. Entries that
+    /// cannot be read or decrypted (e.g. after changing identities) are
+    /// treated as missing.
+    #[must_use]
+    pub fn get(&self, key: &str, include_expired: bool) -> Option<JsonValue> {
+        let salt = self.salt(false).ok().flatten()?;
+        let path = self.entry_path(key, &salt);
+        let encrypted = fs::read(&path).ok()?;
+        let decrypted = match guisu_crypto::decrypt(&encrypted, &self.identities) {
+            Ok(decrypted) => decrypted,
+            Err(e) => {
+                tracing::debug!(path = %path.display(), error = %e, "Ignoring unreadable vault cache entry");
+                return None;
+            }
+        };
+        let cached: CachedResponse = serde_json::from_slice(&decrypted).ok()?;
+
+        let age = now().saturating_sub(cached.stored_at);
+        if !include_expired && age >= self.ttl.as_secs() {
+            return None;
+        }
+        Some(cached.value)
+    }
+
+    /// Store the response for `key`
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the entry cannot be encrypted or written
+    pub fn insert(&self, key: &str, value: &JsonValue) -> Result<()> {
+        let cached = CachedResponse {
+            stored_at: now(),
+            value: value.clone(),
+        };
+        let encrypted = guisu_crypto::encrypt(&serde_json::to_vec(&cached)?, &self.recipients)
+            .map_err(|e| Error::Other(format!("Failed to encrypt vault cache entry: {e}")))?;
+
+        let salt = self
+            .salt(true)?
+            .ok_or_else(|| Error::Other("Failed to create vault cache salt".to_string()))?;
+        self.write(&self.entry_path(key, &salt), &encrypted)
+    }
+
+    /// Delete every entry of the cache in `dir`
+    ///
+    /// Returns the number of entries deleted.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the directory cannot be read or an entry cannot be
+    /// deleted
+    pub fn clear(dir: &Path) -> Result<usize> {
+        let entries = match fs::read_dir(dir) {
+            Ok(entries) => entries,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(0),
+            Err(e) => return Err(e.into()),
+        };
+
+        let mut removed = 0;
+        for entry in entries {
+            let path = entry?.path();
+            if path.extension().is_some_and(|ext| ext == "age") {
+                fs::remove_file(&path)?;
+                removed += 1;
+            }
+        }
+        Ok(removed)
+    }
+
+    fn entry_path(&self, key: &str, salt: &[u8; 32]) -> PathBuf {
+        self.dir.join(format!(
+            "{}.age",
+            blake3::keyed_hash(salt, key.as_bytes()).to_hex()
+        ))
+    }
+
+    /// Read the salt of entry file names, creating it if `create` is set
+    ///
+    /// A salt that cannot be decrypted is replaced when creating, as the
+    /// entries named with it cannot be decrypted either.
+    fn salt(&self, create: bool) -> Result<Option<[u8; 32]>> {
+        let mut cached = self
+            .salt
+            .lock()
+            .unwrap_or_else(std::sync::PoisonError::into_inner);
+        if cached.is_some() {
+            return Ok(*cached);
+        }
+
+        let path = self.dir.join(SALT_FILE);
+        let stored = fs::read(&path)
+            .ok()
+            .and_then(|encrypted| guisu_crypto::decrypt(&encrypted, &self.identities).ok())
+            .and_then(|salt| <[u8; 32]>::try_from(salt.as_slice()).ok());
+        if let Some(salt) = stored {
+            *cached = Some(salt);
+            return Ok(*cached);
+        }
+        if !create {
+            return Ok(None);
+        }
+
+        let mut salt = [0; 32];
+        getrandom::fill(&mut salt)
+            .map_err(|e| Error::Other(format!("Failed to generate vault cache salt: {e}")))?;
+        let encrypted = guisu_crypto::encrypt(&salt, &self.recipients)
+            .map_err(|e| Error::Other(format!("Failed to encrypt vault cache salt: {e}")))?;
+        self.write(&path, &encrypted)?;
+        *cached = Some(salt);
+        Ok(*cached)
+    }
+
+    /// Replace `path` in the cache directory with `content`
+    ///
+    /// The directory is created readable only by its owner, and the content
+    /// is written to a temporary file that is renamed over `path`.
+    fn write(&self, path: &Path, content: &[u8]) -> Result<()> {
+        create_private_dir(&self.dir)?;
+        let mut temp = tempfile::Builder::new()
+            .prefix(".guisu-")
+            .suffix(".tmp")
+            .tempfile_in(&self.dir)?;
+        temp.write_all(content)?;
+        temp.as_file().sync_all()?;
+        temp.persist(path).map_err(|e| e.error)?;
+        Ok(())
+    }
+}
+
+/// Create `dir` and its parents, with `dir` itself only accessible by its owner
+fn create_private_dir(dir: &Path) -> io::Result<()> {
+    if dir.is_dir() {
+        return Ok(());
+    }
+    if let Some(parent) = dir.parent() {
+        fs::create_dir_all(parent)?;
+    }
+
+    let mut builder = fs::DirBuilder::new();
+    #[cfg(unix)]
+    std::os::unix::fs::DirBuilderExt::mode(&mut builder, 0o700);
+    match builder.create(dir) {
+        Err(e) if e.kind() == io::ErrorKind::AlreadyExists && dir.is_dir() => Ok(()),
+        result => result,
+    }
+}
+
+/// Current time in seconds since the Unix epoch
+fn now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |elapsed| elapsed.as_secs())
+}
+
+#[cfg(test)]
+mod tests {
+    #![allow(clippy::unwrap_used)]
+    use super::*;
+    use tempfile::TempDir;
+
+    fn cache(dir: &Path, ttl: Duration) -> DiskCache {
+        DiskCache::new(dir, Arc::new(vec![Identity::generate()]), ttl)
+    }
+
+    #[test]
+    fn test_disk_cache_roundtrip_is_encrypted() {
+        let temp = TempDir::new().unwrap();
+        let cache = cache(temp.path(), Duration::from_secs(60));
+        let value = serde_json::json!({"password": "hunter2"});
+
+        assert!(cache.get("bw|get|item", false).is_none());
+        cache.insert("bw|get|item", &value).unwrap();
+        assert_eq!(cache.get("bw|get|item", false), Some(value));
+
+        for entry in fs::read_dir(temp.path()).unwrap() {
+            let content = fs::read(entry.unwrap().path()).unwrap();
+            assert!(!content.windows(7).any(|window| window == b"hunter2"));
+        }
+    }
+
+    #[test]
+    fn test_disk_cache_salts_file_names() {
+        let temp = TempDir::new().unwrap();
+        let dir = temp.path().join("vault-cache");
+        cache(&dir, Duration::from_secs(60))
+            .insert("bw|get|item", &JsonValue::Null)
+            .unwrap();
+
+        let mut names: Vec<_> = fs::read_dir(&dir)
+            .unwrap()
+            .map(|entry| entry.unwrap().file_name().into_string().unwrap())
+            .collect();
+        names.sort();
+        assert_eq!(names.len(), 2);
+        assert_eq!(names[1], SALT_FILE);
+        assert_ne!(
+            names[0],
+            format!("{}.age", blake3::hash(b"bw|get|item").to_hex())
+        );
+
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            let mode = fs::metadata(&dir).unwrap().permissions().mode();
+            assert_eq!(mode & 0o777, 0o700);
+        }
+    }
+
+    #[test]
+    fn test_disk_cache_expired_entries() {
+        let temp = TempDir::new().unwrap();
+        let cache = cache(temp.path(), Duration::ZERO);
+        cache.insert("key", &JsonValue::Bool(true)).unwrap();
+
+        assert!(cache.get("key", false).is_none());
+        assert_eq!(cache.get("key", true), Some(JsonValue::Bool(true)));
+    }
+
+    #[test]
+    fn test_disk_cache_other_identity_misses() {
+        let temp = TempDir::new().unwrap();
+        cache(temp.path(), Duration::from_secs(60))
+            .insert("key", &JsonValue::Bool(true))
+            .unwrap();
+
+        assert!(
+            cache(temp.path(), Duration::from_secs(60))
+                .get("key", false)
+                .is_none()
+        );
+    }
+
+    #[test]
+    fn test_disk_cache_clear() {
+        let temp = TempDir::new().unwrap();
+        let cache = cache(temp.path(), Duration::from_secs(60));
+        cache.insert("a", &JsonValue::Null).unwrap();
+        cache.insert("b", &JsonValue::Null).unwrap();
+
+        assert_eq!(DiskCache::clear(temp.path()).unwrap(), 2);
+        assert!(cache.get("a", true).is_none());
+        assert_eq!(DiskCache::clear(&temp.path().join("missing")).unwrap(), 0);
+    }
+}
@@ -0,0 +1,354 @@
+//! System keyring integration
+//!
+//! Stores and retrieves short-lived secrets (identity passphrases, access
+//! tokens) in the OS keychain through the platform's command line tool:
+//! `security` for the macOS Keychain and `secret-tool` for the Secret Service
+//! (`GNOME Keyring`, `KWallet`) elsewhere. Entries are addressed by service and
+//! user, like the `keyring` crate.
+//!
+//! Windows Credential Manager is not supported: every operation fails with
+//! [`Error::ProviderNotAvailable`] there.
+//!
+//! Template function: `keyring()`
+
+use crate::{Error, Result, SecretProvider};
+use serde_json::Value as JsonValue;
+use std::io::Write;
+use std::process::{Command, Output, Stdio};
+
+/// Service under which guisu stores its own secrets
+pub const SERVICE: &str = "guisu";
+
+/// Exit code of `security` when no matching item exists
+#[cfg(target_os = "macos")]
+const SECURITY_ITEM_NOT_FOUND: i32 = 44;
+
+/// OS keychain accessed through its command line tool
+pub struct Keyring {
+    command: &'static str,
+}
+
+impl Keyring {
+    /// Create a keyring for the current platform
+    #[must_use]
+    pub fn new() -> Self {
+        let command = if cfg!(target_os = "macos") {
+            "security"
+        } else {
+            "secret-tool"
+        };
+        Self { command }
+    }
+
+    /// Look up the secret stored for `service` and `user`
+    ///
+    /// Returns `None` if no entry exists.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the keyring tool is missing or fails
+    pub fn get(&self, service: &str, user: &str) -> Result<Option<String>> {
+        let output = self.run(&Self::lookup_args(self.command, service, user), None)?;
+
+        if !output.status.success() {
+            if self.is_not_found(&output) {
+                return Ok(None);
+            }
+            return Err(self.failure(&output));
+        }
+
+        let secret = String::from_utf8(output.stdout).map_err(|e| {
+            Error::ParseError(format!("{} output is not valid UTF-8: {e}", self.command))
+        })?;
+        let secret = secret.strip_suffix('\n').unwrap_or(&secret);
+        // secret-tool prints nothing and exits 0 for some missing entries
+        Ok((!secret.is_empty()).then(|| secret.to_string()))
+    }
+
+    /// Store `secret` for `service` and `user`, replacing any existing entry
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the keyring tool is missing or fails
+    pub fn set(&self, service: &str, user: &str, secret: &str) -> Result<()> {
+        let output = if self.command == "security" {
+            // `security` only takes the password as an argument, and arguments
+            // are visible to other processes, so the command is read from stdin
+            if secret.contains('\n') {
+                return Err(Error::InvalidArguments(
+                    "Secrets stored in the macOS Keychain cannot contain newlines".to_string(),
+                ));
+            }
+            let mut args = Self::store_args(self.command, service, user);
+            args.push(secret.to_string());
+            let output = self.run(
+                &["-i".to_string()],
+                Some(Self::security_line(&args).as_bytes()),
+            )?;
+            // Interactive mode exits 0 even when its command fails
+            if !output.stderr.is_empty() {
+                return Err(self.failure(&output));
+            }
+            output
+        } else {
+            self.run(
+                &Self::store_args(self.command, service, user),
+                Some(secret.as_bytes()),
+            )?
+        };
+
+        if !output.status.success() {
+            return Err(self.failure(&output));
+        }
+        Ok(())
+    }
+
+    /// Remove the entry for `service` and `user`, if any
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the keyring tool is missing or fails
+    pub fn delete(&self, service: &str, user: &str) -> Result<()> {
+        let output = self.run(&Self::delete_args(self.command, service, user), None)?;
+
+        if !output.status.success() && !self.is_not_found(&output) {
+            return Err(self.failure(&output));
+        }
+        Ok(())
+    }
+
+    /// Arguments that print the secret for `service` and `user`
+    fn lookup_args(command: &str, service: &str, user: &str) -> Vec<String> {
+        let args: &[&str] = if command == "security" {
+            &["find-generic-password", "-s", service, "-a", user, "-w"]
+        } else {
+            &["lookup", "service", service, "username", user]
+        };
+        args.iter().map(ToString::to_string).collect()
+    }
+
+    /// Arguments that store a secret for `service` and `user`
+    ///
+    /// `secret-tool` reads the secret from stdin; `security` expects it appended.
+    fn store_args(command: &str, service: &str, user: &str) -> Vec<String> {
+        if command == "security" {
+            [
+                "add-generic-password",
+                "-U",
+                "-s",
+                service,
+                "-a",
+                user,
+                "-w",
+            ]
+            .iter()
+            .map(ToString::to_string)
+            .collect()
+        } else {
+            vec![
+                "store".to_string(),
+                format!("--label={service} ({user})"),
+                "service".to_string(),
+                service.to_string(),
+                "username".to_string(),
+                user.to_string(),
+            ]
+        }
+    }
+
+    /// Command line for `security -i`, with every argument double-quoted
+    fn security_line(args: &[String]) -> String {
+        let quoted: Vec<String> = args
+            .iter()
+            .map(|arg| format!("\"{}\"", arg.replace('\\', "\\\\").replace('"', "\\\"")))
+            .collect();
+        format!("{}\n", quoted.join(" "))
+    }
+
+    /// Arguments that remove the entry for `service` and `user`
+    fn delete_args(command: &str, service: &str, user: &str) -> Vec<String> {
+        let args: &[&str] = if command == "security" {
+            &["delete-generic-password", "-s", service, "-a", user]
+        } else {
+            &["clear", "service", service, "username", user]
+        };
+        args.iter().map(ToString::to_string).collect()
+    }
+
+    fn run(&self, args: &[String], stdin: Option<&[u8]>) -> Result<Output> {
+        if cfg!(windows) {
+            return Err(Error::ProviderNotAvailable(
+                "The system keyring is not supported on Windows (only the macOS Keychain and \
+                 the Secret Service are)"
+                    .to_string(),
+            ));
+        }
+
+        let mut child = Command::new(self.command)
+            .args(args)
+            .stdin(if stdin.is_some() {
+                Stdio::piped()
+            } else {
+                Stdio::null()
+            })
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .spawn()
+            .map_err(|e| {
+                if e.kind() == std::io::ErrorKind::NotFound {
+                    Error::ProviderNotAvailable(format!("{} is not installed", self.command))
+                } else {
+                    Error::Io(e)
+                }
+            })?;
+
+        if let Some(input) = stdin
+            && let Some(mut pipe) = child.stdin.take()
+        {
+            pipe.write_all(input)?;
+        }
+        Ok(child.wait_with_output()?)
+    }
+
+    /// Whether a failed lookup or delete means the entry does not exist
+    fn is_not_found(&self, output: &Output) -> bool {
+        #[cfg(target_os = "macos")]
+        if output.status.code() == Some(SECURITY_ITEM_NOT_FOUND) {
+            return true;
+        }
+        // secret-tool exits 1 without output when nothing matches
+        self.command == "secret-tool" && output.stdout.is_empty() && output.stderr.is_empty()
+    }
+
+    fn failure(&self, output: &Output) -> Error {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        Error::ExecutionFailed(format!("{} error: {}", self.command, stderr.trim()))
+    }
+}
+
+impl Default for Keyring {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl SecretProvider for Keyring {
+    fn name(&self) -> &'static str {
+        "keyring"
+    }
+
+    fn execute(&self, args: &[&str]) -> Result<JsonValue> {
+        let [service, user] = args else {
+            return Err(Error::InvalidArguments(
+                "Exactly two arguments required: service and user".to_string(),
+            ));
+        };
+
+        self.get(service, user)?
+            .map(JsonValue::from)
+            .ok_or_else(|| Error::SecretNotFound(format!("{service}/{user}")))
+    }
+
+    fn is_available(&self) -> bool {
+        if cfg!(windows) {
+            return false;
+        }
+        // Neither tool has a version flag; both print usage and exit non-zero
+        Command::new(self.command)
+            .arg("--help")
+            .stdout(Stdio::null())
+            .stderr(Stdio::null())
+            .status()
+            .is_ok()
+    }
+
+    fn help(&self) -> &'static str {
+        "System keyring (macOS Keychain or Secret Service)\n\
+         \n\
+         Requirements:\n\
+         - macOS: the built-in `security` tool\n\
+         - Linux/BSD: `secret-tool` (libsecret) and a running Secret Service\n\
+         - Windows: not supported\n\
+         \n\
+         Store a secret:\n\
+         secret-tool store --label=github service github username me\n\
+         \n\
+         Usage in templates:\n\
+         {{ keyring(\"github\", \"me\") }}"
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    #![allow(clippy::unwrap_used, clippy::panic)]
+    use super::*;
+
+    #[test]
+    fn test_secret_tool_args() {
+        assert_eq!(
+            Keyring::lookup_args("secret-tool", "github", "me"),
+            ["lookup", "service", "github", "username", "me"]
+        );
+        assert_eq!(
+            Keyring::store_args("secret-tool", "github", "me"),
+            [
+                "store",
+                "--label=github (me)",
+                "service",
+                "github",
+                "username",
+                "me"
+            ]
+        );
+        assert_eq!(
+            Keyring::delete_args("secret-tool", "github", "me"),
+            ["clear", "service", "github", "username", "me"]
+        );
+    }
+
+    #[test]
+    fn test_security_args() {
+        assert_eq!(
+            Keyring::lookup_args("security", "github", "me"),
+            ["find-generic-password", "-s", "github", "-a", "me", "-w"]
+        );
+        assert_eq!(
+            Keyring::store_args("security", "github", "me"),
+            [
+                "add-generic-password",
+                "-U",
+                "-s",
+                "github",
+                "-a",
+                "me",
+                "-w"
+            ]
+        );
+        assert_eq!(
+            Keyring::delete_args("security", "github", "me"),
+            ["delete-generic-password", "-s", "github", "-a", "me"]
+        );
+    }
+
+    #[test]
+    fn test_security_line_quotes_arguments() {
+        let args = ["add-generic-password", "-w", r#"pa ss"w\rd"#].map(String::from);
+        assert_eq!(
+            Keyring::security_line(&args),
+            "\"add-generic-password\" \"-w\" \"pa ss\\\"w\\\\rd\"\n"
+        );
+    }
+
+    #[test]
+    fn test_execute_requires_service_and_user() {
+        let keyring = Keyring::new();
+        assert!(matches!(
+            keyring.execute(&["github"]),
+            Err(Error::InvalidArguments(_))
+        ));
+        assert!(matches!(
+            keyring.execute(&["a", "b", "c"]),
+            Err(Error::InvalidArguments(_))
+        ));
+    }
+}
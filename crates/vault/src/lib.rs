@@ -1,10 +1,12 @@
 //! Vault providers for password managers
 //!
 //! This crate provides a unified interface for accessing secrets from various
-//! password manager vaults like `Bitwarden`, `1Password`, `LastPass`, etc.
+//! password manager vaults like `Bitwarden`, `pass`, `1Password`, `LastPass`, etc.
 
 use indexmap::IndexMap;
 use serde_json::Value as JsonValue;
+use std::sync::Arc;
+use std::time::Duration;
 use thiserror::Error;
 
 /// Result type for vault operations
@@ -41,6 +43,10 @@ pub enum Error {
     #[error("User cancelled operation")]
     Cancelled,
 
+    /// The secret is not cached and the provider must not be called offline
+    #[error("Offline mode: {0} is not cached")]
+    Offline(String),
+
     /// IO error occurred
     #[error("IO error: {0}")]
     Io(#[from] std::io::Error),
@@ -54,6 +60,21 @@ pub enum Error {
     Other(String),
 }
 
+impl Error {
+    /// Whether the error may go away when the call is repeated
+    ///
+    /// Command and IO failures (e.g. a network hiccup reaching the vault) are
+    /// transient. Missing secrets, authentication and cancellation are not.
+    #[must_use]
+    pub fn is_transient(&self) -> bool {
+        matches!(self, Self::ExecutionFailed(_) | Self::Io(_))
+    }
+}
+
+// Persistent, encrypted cache of provider responses
+pub mod disk_cache;
+pub use disk_cache::DiskCache;
+
 // Bitwarden Vault (personal/team passwords)
 // Provides BwCli and RbwCli
 #[cfg(feature = "bw")]
@@ -64,6 +85,16 @@ pub mod bw;
 #[cfg(feature = "bws")]
 pub mod bws;
 
+// password-store (pass and gopass)
+// Provides PassCli
+#[cfg(feature = "pass")]
+pub mod pass;
+
+// System keyring (macOS Keychain, Secret Service)
+// Provides Keyring
+#[cfg(feature = "keyring")]
+pub mod keyring;
+
 // Future providers
 // #[cfg(feature = "onepassword")]
 // pub mod onepassword;
@@ -93,10 +124,85 @@ pub trait SecretProvider: Send + Sync {
     fn help(&self) -> &'static str;
 }
 
+/// Retry policy for transient provider failures
+///
+/// Failed calls are repeated up to `retries` times, waiting `delay` before
+/// the first retry and doubling the wait after each further failure.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RetryPolicy {
+    /// Maximum number of retries (0 = fail on the first error)
+    pub retries: u32,
+    /// Delay before the first retry
+    pub delay: Duration,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self::new(0)
+    }
+}
+
+impl RetryPolicy {
+    /// Create a policy with the given number of retries and a one second delay
+    #[must_use]
+    pub fn new(retries: u32) -> Self {
+        Self {
+            retries,
+            delay: Duration::from_secs(1),
+        }
+    }
+
+    /// Set the delay before the first retry
+    #[must_use]
+    pub fn with_delay(mut self, delay: Duration) -> Self {
+        self.delay = delay;
+        self
+    }
+
+    /// Delay before retry number `attempt` (1-based)
+    #[must_use]
+    pub fn backoff(&self, attempt: u32) -> Duration {
+        let factor = 1u32
+            .checked_shl(attempt.saturating_sub(1))
+            .unwrap_or(u32::MAX);
+        self.delay.saturating_mul(factor)
+    }
+
+    /// Run `op`, retrying transient failures with exponential backoff
+    ///
+    /// # Errors
+    ///
+    /// Returns the last error if `op` keeps failing, or the first
+    /// non-transient error
+    pub fn run<T>(&self, mut op: impl FnMut() -> Result<T>) -> Result<T> {
+        let mut attempt = 0;
+        loop {
+            match op() {
+                Err(e) if e.is_transient() && attempt < self.retries => {
+                    attempt += 1;
+                    let delay = self.backoff(attempt);
+                    tracing::warn!(
+                        error = %e,
+                        attempt,
+                        retries = self.retries,
+                        delay_ms = delay.as_millis(),
+                        "Vault call failed, retrying"
+                    );
+                    std::thread::sleep(delay);
+                }
+                result => return result,
+            }
+        }
+    }
+}
+
 /// Secret manager that caches results
 pub struct CachedSecretProvider<P: SecretProvider> {
     provider: P,
     cache: IndexMap<String, JsonValue>,
+    retry: RetryPolicy,
+    offline: bool,
+    disk: Option<Arc<DiskCache>>,
 }
 
 impl<P: SecretProvider> CachedSecretProvider<P> {
@@ -106,14 +212,41 @@ impl<P: SecretProvider> CachedSecretProvider<P> {
         Self {
             provider,
             cache: IndexMap::new(),
+            retry: RetryPolicy::default(),
+            offline: false,
+            disk: None,
         }
     }
 
+    /// Keep responses in `disk` across runs
+    ///
+    /// Offline, expired entries are still used.
+    #[must_use]
+    pub fn with_disk_cache(mut self, disk: Option<Arc<DiskCache>>) -> Self {
+        self.disk = disk;
+        self
+    }
+
+    /// Only answer from the cache, failing with [`Error::Offline`] otherwise
+    #[must_use]
+    pub fn with_offline(mut self, offline: bool) -> Self {
+        self.offline = offline;
+        self
+    }
+
+    /// Retry transient provider failures according to `policy`
+    #[must_use]
+    pub fn with_retry(mut self, policy: RetryPolicy) -> Self {
+        self.retry = policy;
+        self
+    }
+
     /// Execute command with caching
     ///
     /// # Errors
     ///
-    /// Returns error if command execution fails or JSON parsing fails
+    /// Returns error if command execution fails or JSON parsing fails, or
+    /// offline if the result is not cached
     pub fn execute_cached(&mut self, args: &[&str]) -> Result<JsonValue> {
         let cache_key = args.join("|");
 
@@ -121,7 +254,28 @@ impl<P: SecretProvider> CachedSecretProvider<P> {
             return Ok(cached.clone());
         }
 
-        let result = self.provider.execute(args)?;
+        let disk_key = format!("{}|{cache_key}", self.provider.name());
+        if let Some(disk) = &self.disk
+            && let Some(cached) = disk.get(&disk_key, self.offline)
+        {
+            self.cache.insert(cache_key, cached.clone());
+            return Ok(cached);
+        }
+
+        if self.offline {
+            return Err(Error::Offline(format!(
+                "'{} {}'",
+                self.provider.name(),
+                args.join(" ")
+            )));
+        }
+
+        let result = self.retry.run(|| self.provider.execute(args))?;
+        if let Some(disk) = &self.disk
+            && let Err(e) = disk.insert(&disk_key, &result)
+        {
+            tracing::warn!(error = %e, "Failed to write vault cache");
+        }
         self.cache.insert(cache_key, result.clone());
 
         Ok(result)
@@ -404,4 +558,130 @@ mod tests {
         provider.execute(&["arg2"]).unwrap();
         assert_eq!(provider.get_call_count(), 2);
     }
+
+    // Tests for RetryPolicy
+
+    struct FlakyProvider {
+        failures: usize,
+        error: fn() -> Error,
+        call_count: AtomicUsize,
+    }
+
+    impl SecretProvider for FlakyProvider {
+        fn name(&self) -> &'static str {
+            "flaky"
+        }
+
+        fn execute(&self, _args: &[&str]) -> Result<JsonValue> {
+            if self.call_count.fetch_add(1, Ordering::SeqCst) < self.failures {
+                return Err((self.error)());
+            }
+            Ok(JsonValue::Bool(true))
+        }
+
+        fn is_available(&self) -> bool {
+            true
+        }
+
+        fn help(&self) -> &'static str {
+            ""
+        }
+    }
+
+    #[test]
+    fn test_retry_policy_backoff_doubles() {
+        let policy = RetryPolicy::new(3).with_delay(Duration::from_millis(100));
+        assert_eq!(policy.backoff(1), Duration::from_millis(100));
+        assert_eq!(policy.backoff(2), Duration::from_millis(200));
+        assert_eq!(policy.backoff(3), Duration::from_millis(400));
+    }
+
+    #[test]
+    fn test_cached_provider_retries_transient_errors() {
+        let provider = FlakyProvider {
+            failures: 2,
+            error: || Error::ExecutionFailed("timeout".to_string()),
+            call_count: AtomicUsize::new(0),
+        };
+        let policy = RetryPolicy::new(2).with_delay(Duration::ZERO);
+        let mut cached = CachedSecretProvider::new(provider).with_retry(policy);
+
+        assert_eq!(
+            cached.execute_cached(&["get"]).unwrap(),
+            JsonValue::Bool(true)
+        );
+        assert_eq!(cached.provider.call_count.load(Ordering::SeqCst), 3);
+    }
+
+    #[test]
+    fn test_cached_provider_gives_up_after_retries() {
+        let provider = FlakyProvider {
+            failures: 5,
+            error: || Error::ExecutionFailed("timeout".to_string()),
+            call_count: AtomicUsize::new(0),
+        };
+        let policy = RetryPolicy::new(1).with_delay(Duration::ZERO);
+        let mut cached = CachedSecretProvider::new(provider).with_retry(policy);
+
+        assert!(cached.execute_cached(&["get"]).is_err());
+        assert_eq!(cached.provider.call_count.load(Ordering::SeqCst), 2);
+    }
+
+    #[test]
+    fn test_cached_provider_does_not_retry_permanent_errors() {
+        let provider = FlakyProvider {
+            failures: 1,
+            error: || Error::SecretNotFound("item".to_string()),
+            call_count: AtomicUsize::new(0),
+        };
+        let policy = RetryPolicy::new(3).with_delay(Duration::ZERO);
+        let mut cached = CachedSecretProvider::new(provider).with_retry(policy);
+
+        assert!(matches!(
+            cached.execute_cached(&["get"]),
+            Err(Error::SecretNotFound(_))
+        ));
+        assert_eq!(cached.provider.call_count.load(Ordering::SeqCst), 1);
+    }
+
+    #[test]
+    fn test_cached_provider_offline_only_uses_cache() {
+        let response = serde_json::json!({"key": "value"});
+        let provider = MockProvider::new("test", response.clone());
+        let mut cached = CachedSecretProvider::new(provider);
+        cached.execute_cached(&["get", "item"]).unwrap();
+
+        let mut cached = cached.with_offline(true);
+        assert_eq!(cached.execute_cached(&["get", "item"]).unwrap(), response);
+        assert!(matches!(
+            cached.execute_cached(&["get", "other"]),
+            Err(Error::Offline(_))
+        ));
+        assert_eq!(cached.provider.get_call_count(), 1);
+    }
+
+    #[test]
+    fn test_cached_provider_disk_cache_survives_runs() {
+        let temp = tempfile::TempDir::new().unwrap();
+        let identities = Arc::new(vec![guisu_crypto::Identity::generate()]);
+        let disk = || {
+            Some(Arc::new(DiskCache::new(
+                temp.path(),
+                Arc::clone(&identities),
+                Duration::from_secs(60),
+            )))
+        };
+        let response = serde_json::json!({"key": "value"});
+
+        let mut first = CachedSecretProvider::new(MockProvider::new("test", response.clone()))
+            .with_disk_cache(disk());
+        first.execute_cached(&["get", "item"]).unwrap();
+        assert_eq!(first.provider.get_call_count(), 1);
+
+        let mut second = CachedSecretProvider::new(MockProvider::new("test", JsonValue::Null))
+            .with_disk_cache(disk())
+            .with_offline(true);
+        assert_eq!(second.execute_cached(&["get", "item"]).unwrap(), response);
+        assert_eq!(second.provider.get_call_count(), 0);
+    }
 }
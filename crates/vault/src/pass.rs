@@ -0,0 +1,184 @@
+//! password-store integration
+//!
+//! Provides access to secrets kept in the standard Unix password manager
+//! (`pass`) or the compatible `gopass`. Both store one secret per file, with
+//! the password on the first line and optional `key: value` lines below it.
+//!
+//! Template function: `pass()`
+
+use crate::{Error, Result, SecretProvider};
+use serde_json::{Map, Value as JsonValue};
+use std::process::{Command, Stdio};
+
+/// password-store CLI provider (`pass` or `gopass`)
+pub struct PassCli {
+    command: &'static str,
+}
+
+impl PassCli {
+    /// Create a provider that runs `pass`
+    #[must_use]
+    pub fn new() -> Self {
+        Self { command: "pass" }
+    }
+
+    /// Create a provider that runs `gopass`
+    #[must_use]
+    pub fn gopass() -> Self {
+        Self { command: "gopass" }
+    }
+
+    /// Parse the decrypted content of a password-store entry
+    ///
+    /// Returns an object with:
+    /// - `password`: the first line
+    /// - `fields`: `key: value` lines after the first, by key
+    /// - `notes`: every line after the first, unparsed
+    fn parse_entry(content: &str) -> JsonValue {
+        let mut lines = content.lines();
+        let password = lines.next().unwrap_or_default();
+
+        let rest: Vec<&str> = lines.collect();
+        let fields: Map<String, JsonValue> = rest
+            .iter()
+            .filter_map(|line| line.split_once(':'))
+            .filter(|(key, _)| !key.is_empty() && !key.contains(char::is_whitespace))
+            .map(|(key, value)| (key.to_string(), JsonValue::from(value.trim())))
+            .collect();
+
+        serde_json::json!({
+            "password": password,
+            "fields": fields,
+            "notes": rest.join("\n"),
+        })
+    }
+}
+
+impl Default for PassCli {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl SecretProvider for PassCli {
+    fn name(&self) -> &'static str {
+        self.command
+    }
+
+    fn execute(&self, args: &[&str]) -> Result<JsonValue> {
+        let [path] = args else {
+            return Err(Error::InvalidArguments(
+                "Exactly one argument required: the secret path".to_string(),
+            ));
+        };
+
+        let output = Command::new(self.command)
+            .args(["show", path])
+            .stdin(Stdio::inherit()) // Allow gpg to prompt for the passphrase
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .output()
+            .map_err(|e| {
+                if e.kind() == std::io::ErrorKind::NotFound {
+                    Error::ProviderNotAvailable(format!("{} is not installed", self.command))
+                } else {
+                    Error::Io(e)
+                }
+            })?;
+
+        if !output.status.success() {
+            let stderr = String::from_utf8_lossy(&output.stderr);
+            if stderr.contains("not in the password store") || stderr.contains("not found") {
+                return Err(Error::SecretNotFound(format!("{path}: {}", stderr.trim())));
+            }
+            return Err(Error::ExecutionFailed(format!(
+                "{} error: {}",
+                self.command,
+                stderr.trim()
+            )));
+        }
+
+        let stdout = String::from_utf8(output.stdout).map_err(|e| {
+            Error::ParseError(format!("{} output is not valid UTF-8: {e}", self.command))
+        })?;
+        Ok(Self::parse_entry(&stdout))
+    }
+
+    fn is_available(&self) -> bool {
+        Command::new(self.command)
+            .arg("--version")
+            .output()
+            .is_ok_and(|o| o.status.success())
+    }
+
+    fn help(&self) -> &'static str {
+        "password-store CLI (pass or gopass)\n\
+         \n\
+         Requirements:\n\
+         - Install pass (https://www.passwordstore.org) or gopass\n\
+         - Initialize the store: pass init <gpg-id>\n\
+         \n\
+         Configuration:\n\
+         [pass]\n\
+         command = \"gopass\"  # default: \"pass\"\n\
+         \n\
+         Usage in templates:\n\
+         {{ pass(\"email/work\") }}"
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    #![allow(clippy::unwrap_used, clippy::panic)]
+    use super::*;
+
+    #[test]
+    fn test_parse_entry_password_only() {
+        let entry = PassCli::parse_entry("hunter2\n");
+        assert_eq!(entry["password"], "hunter2");
+        assert_eq!(entry["fields"], serde_json::json!({}));
+        assert_eq!(entry["notes"], "");
+    }
+
+    #[test]
+    fn test_parse_entry_with_fields() {
+        let entry = PassCli::parse_entry(
+            "hunter2\nlogin: me@example.com\nurl: https://example.com/login\nSee the wiki: later\n",
+        );
+        assert_eq!(entry["password"], "hunter2");
+        assert_eq!(entry["fields"]["login"], "me@example.com");
+        assert_eq!(entry["fields"]["url"], "https://example.com/login");
+        // Lines with spaces before the colon are notes, not fields
+        assert!(entry["fields"].get("See the wiki").is_none());
+        assert!(
+            entry["notes"]
+                .as_str()
+                .unwrap()
+                .ends_with("See the wiki: later")
+        );
+    }
+
+    #[test]
+    fn test_parse_entry_empty() {
+        assert_eq!(PassCli::parse_entry("")["password"], "");
+    }
+
+    #[test]
+    fn test_name_follows_command() {
+        assert_eq!(PassCli::new().name(), "pass");
+        assert_eq!(PassCli::gopass().name(), "gopass");
+    }
+
+    #[test]
+    fn test_execute_requires_one_path() {
+        let provider = PassCli::new();
+        assert!(matches!(
+            provider.execute(&[]),
+            Err(Error::InvalidArguments(_))
+        ));
+        assert!(matches!(
+            provider.execute(&["a", "b"]),
+            Err(Error::InvalidArguments(_))
+        ));
+    }
+}